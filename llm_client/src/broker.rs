@@ -4,7 +4,11 @@
 
 use std::{
     collections::HashMap,
-    sync::{Arc, Mutex},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
 };
 
 use either::Either;
@@ -12,13 +16,18 @@ use futures::{stream, FutureExt, StreamExt};
 use sqlx::SqlitePool;
 
 use crate::{
+    capture::{LLMCaptureMessage, LLMCaptureRecord, LLMCaptureSink},
+    metrics::{LLMMetricsRecorder, ModelLatencyStats},
     clients::{
         anthropic::AnthropicClient,
+        bedrock::BedrockClient,
         codestory::CodeStoryClient,
         fireworks::FireworksAIClient,
+        gemini::GeminiClient,
         gemini_pro::GeminiProClient,
         google_ai::GoogleAIStdioClient,
         groq::GroqClient,
+        llama_cpp::LlamaCppClient,
         lmstudio::LMStudioClient,
         ollama::OllamaClient,
         open_router::OpenRouterClient,
@@ -27,7 +36,9 @@ use crate::{
         togetherai::TogetherAIClient,
         types::{
             LLMClient, LLMClientCompletionRequest, LLMClientCompletionResponse,
-            LLMClientCompletionStringRequest, LLMClientError, LLMType,
+            LLMClientCompletionStringRequest, LLMClientError, LLMClientMessage,
+            LLMClientResponseFormat, LLMEmbeddingClient, LLMEmbeddingRequest,
+            LLMEmbeddingResponse, LLMType,
         },
     },
     provider::{CodeStoryLLMTypes, LLMProvider, LLMProviderAPIKeys},
@@ -38,10 +49,197 @@ use logging::parea::{PareaClient, PareaLogCompletion, PareaLogMessage};
 
 pub type SqlDb = Arc<SqlitePool>;
 
+/// fraction of the backoff delay we randomize on every retry, keeps a herd
+/// of retrying agents from hammering the provider in lockstep
+const RETRY_JITTER_FRACTION: f64 = 0.2;
+
+/// Retry policy for transient failures coming back from the providers,
+/// rate limits and flaky streams get retried with exponential backoff
+/// instead of bubbling up and killing a long agentic run
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct LLMBrokerRetryPolicy {
+    /// total number of attempts including the first one
+    max_attempts: usize,
+    /// backoff before the first retry, doubles on every attempt after that
+    initial_backoff_ms: u64,
+    /// ceiling for the backoff delay
+    max_backoff_ms: u64,
+}
+
+impl Default for LLMBrokerRetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff_ms: 500,
+            max_backoff_ms: 10_000,
+        }
+    }
+}
+
+impl LLMBrokerRetryPolicy {
+    pub fn new(max_attempts: usize, initial_backoff_ms: u64, max_backoff_ms: u64) -> Self {
+        Self {
+            // we always make at least one attempt
+            max_attempts: max_attempts.max(1),
+            initial_backoff_ms,
+            max_backoff_ms,
+        }
+    }
+
+    fn max_attempts(&self) -> usize {
+        self.max_attempts
+    }
+
+    /// Classifies which errors are worth retrying, anything which smells
+    /// like a rate limit or a transient transport failure qualifies
+    fn is_retryable(error: &LLMClientError) -> bool {
+        matches!(
+            error,
+            LLMClientError::RateLimitExceeded
+                | LLMClientError::FailedToGetResponse
+                | LLMClientError::EventStreamError(_)
+                | LLMClientError::ReqwestError(_)
+                | LLMClientError::ReqwestMiddlewareError(_)
+        )
+    }
+
+    /// The delay before the retry for a given attempt (0 indexed), grows
+    /// exponentially with a jitter on top and is capped at the maximum
+    fn backoff_delay(&self, attempt: usize) -> std::time::Duration {
+        let exponential_backoff_ms = self
+            .initial_backoff_ms
+            .saturating_mul(1_u64.checked_shl(attempt as u32).unwrap_or(u64::MAX))
+            .min(self.max_backoff_ms);
+        let jitter_range = exponential_backoff_ms as f64 * RETRY_JITTER_FRACTION;
+        let jittered_ms =
+            exponential_backoff_ms as f64 - jitter_range + 2.0 * jitter_range * jitter_factor();
+        std::time::Duration::from_millis(jittered_ms as u64)
+    }
+}
+
+/// Cheap jitter in [0, 1) without pulling in a rng, the subsecond nanos are
+/// spread out enough across processes for backoff purposes
+fn jitter_factor() -> f64 {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|duration| duration.subsec_nanos())
+        .unwrap_or_default();
+    (nanos % 1000) as f64 / 1000.0
+}
+
+/// default cap on concurrent in-flight requests per provider, parallel
+/// probing fans out with buffer_unordered and can trip rate limits without
+/// a bound here
+const DEFAULT_PROVIDER_CONCURRENCY: usize = 10;
+
+/// Point-in-time view of the request limiter for one provider, surfaced on
+/// the debug endpoints
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ProviderConcurrencyStats {
+    pub provider: String,
+    pub limit: usize,
+    pub in_flight: usize,
+    pub waiting: usize,
+    pub total_served: u64,
+}
+
+struct ProviderLimiterEntry {
+    semaphore: Arc<tokio::sync::Semaphore>,
+    limit: usize,
+    waiting: AtomicUsize,
+    total_served: AtomicU64,
+}
+
+/// Per-provider semaphore over in-flight requests with queue metrics, the
+/// permits bound how hard we hammer any single provider no matter how wide
+/// the callers fan out
+struct ProviderRequestLimiter {
+    default_limit: usize,
+    /// configured per-provider overrides over the default limit
+    overrides: HashMap<LLMProvider, usize>,
+    entries: Mutex<HashMap<LLMProvider, Arc<ProviderLimiterEntry>>>,
+}
+
+impl ProviderRequestLimiter {
+    fn new(default_limit: usize) -> Self {
+        Self {
+            default_limit,
+            overrides: HashMap::new(),
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn entry(&self, provider: &LLMProvider) -> Arc<ProviderLimiterEntry> {
+        let mut entries = self.entries.lock().expect("lock to not be poisoned");
+        entries
+            .entry(provider.clone())
+            .or_insert_with(|| {
+                let limit = self
+                    .overrides
+                    .get(provider)
+                    .copied()
+                    .unwrap_or(self.default_limit)
+                    .max(1);
+                Arc::new(ProviderLimiterEntry {
+                    semaphore: Arc::new(tokio::sync::Semaphore::new(limit)),
+                    limit,
+                    waiting: AtomicUsize::new(0),
+                    total_served: AtomicU64::new(0),
+                })
+            })
+            .clone()
+    }
+
+    async fn acquire(&self, provider: &LLMProvider) -> tokio::sync::OwnedSemaphorePermit {
+        let entry = self.entry(provider);
+        entry.waiting.fetch_add(1, Ordering::SeqCst);
+        let permit = entry
+            .semaphore
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("the semaphore is never closed");
+        entry.waiting.fetch_sub(1, Ordering::SeqCst);
+        entry.total_served.fetch_add(1, Ordering::SeqCst);
+        permit
+    }
+
+    fn stats(&self) -> Vec<ProviderConcurrencyStats> {
+        let entries = self.entries.lock().expect("lock to not be poisoned");
+        let mut stats = entries
+            .iter()
+            .map(|(provider, entry)| ProviderConcurrencyStats {
+                provider: provider.to_string(),
+                limit: entry.limit,
+                in_flight: entry
+                    .limit
+                    .saturating_sub(entry.semaphore.available_permits()),
+                waiting: entry.waiting.load(Ordering::SeqCst),
+                total_served: entry.total_served.load(Ordering::SeqCst),
+            })
+            .collect::<Vec<_>>();
+        stats.sort_by(|left, right| left.provider.cmp(&right.provider));
+        stats
+    }
+}
+
 pub struct LLMBroker {
     pub providers: HashMap<LLMProvider, Box<dyn LLMClient + Send + Sync>>,
+    /// providers which can also generate embeddings, keyed the same way so
+    /// callers pick them with the LLMProvider they already have
+    embedding_providers: HashMap<LLMProvider, Box<dyn LLMEmbeddingClient + Send + Sync>>,
     posthog_client: Arc<PosthogClient>,
     parea_client: Arc<PareaClient>,
+    /// when set we write scrubbed request/response pairs to per-session
+    /// files in this directory for offline debugging
+    capture_sink: Option<Arc<LLMCaptureSink>>,
+    retry_policy: LLMBrokerRetryPolicy,
+    request_limiter: ProviderRequestLimiter,
+    metrics: LLMMetricsRecorder,
+    /// when set every request gets this sampling seed and zero temperature
+    /// so providers which support seeding reproduce the same output,
+    /// toggled at runtime for reproducible benchmark and bug report runs
+    deterministic_seed: std::sync::RwLock<Option<u64>>,
 }
 
 pub type LLMBrokerResponse = Result<LLMClientCompletionResponse, LLMClientError>;
@@ -54,14 +252,23 @@ impl LLMBroker {
         let parea_client = Arc::new(PareaClient::new());
         let broker = Self {
             providers: HashMap::new(),
+            embedding_providers: HashMap::new(),
             posthog_client,
             parea_client,
+            capture_sink: None,
+            retry_policy: LLMBrokerRetryPolicy::default(),
+            request_limiter: ProviderRequestLimiter::new(DEFAULT_PROVIDER_CONCURRENCY),
+            metrics: LLMMetricsRecorder::new(),
+            deterministic_seed: std::sync::RwLock::new(None),
         };
         Ok(broker
+            .add_embedding_provider(LLMProvider::OpenAI, Box::new(OpenAIClient::new()))
+            .add_embedding_provider(LLMProvider::Ollama, Box::new(OllamaClient::new()))
             .add_provider(LLMProvider::OpenAI, Box::new(OpenAIClient::new()))
             .add_provider(LLMProvider::Ollama, Box::new(OllamaClient::new()))
             .add_provider(LLMProvider::TogetherAI, Box::new(TogetherAIClient::new()))
             .add_provider(LLMProvider::LMStudio, Box::new(LMStudioClient::new()))
+            .add_provider(LLMProvider::LlamaCpp, Box::new(LlamaCppClient::new()))
             .add_provider(
                 LLMProvider::OpenAICompatible,
                 Box::new(OpenAICompatibleClient::new()),
@@ -74,13 +281,31 @@ impl LLMBroker {
             )
             .add_provider(LLMProvider::FireworksAI, Box::new(FireworksAIClient::new()))
             .add_provider(LLMProvider::Anthropic, Box::new(AnthropicClient::new()))
+            .add_provider(LLMProvider::Gemini, Box::new(GeminiClient::new()))
             .add_provider(LLMProvider::GeminiPro, Box::new(GeminiProClient::new()))
             .add_provider(LLMProvider::OpenRouter, Box::new(OpenRouterClient::new()))
             .add_provider(
                 LLMProvider::GoogleAIStudio,
                 Box::new(GoogleAIStdioClient::new()),
             )
-            .add_provider(LLMProvider::Groq, Box::new(GroqClient::new())))
+            .add_provider(LLMProvider::Groq, Box::new(GroqClient::new()))
+            .add_provider(LLMProvider::Bedrock, Box::new(BedrockClient::new())))
+    }
+
+    /// Pins the sampling seed for every request from here on, None turns
+    /// the deterministic mode back off
+    pub fn set_deterministic_seed(&self, seed: Option<u64>) {
+        *self
+            .deterministic_seed
+            .write()
+            .expect("deterministic_seed lock to not be poisoned") = seed;
+    }
+
+    pub fn deterministic_seed(&self) -> Option<u64> {
+        *self
+            .deterministic_seed
+            .read()
+            .expect("deterministic_seed lock to not be poisoned")
     }
 
     pub fn add_provider(
@@ -92,6 +317,85 @@ impl LLMBroker {
         self
     }
 
+    /// Caps the number of requests we keep in flight against this provider,
+    /// anything above the cap waits for a permit
+    pub fn with_provider_concurrency(mut self, provider: LLMProvider, limit: usize) -> Self {
+        self.request_limiter.overrides.insert(provider, limit.max(1));
+        self
+    }
+
+    /// Queue metrics of the per-provider request limiter for the debug
+    /// endpoints
+    pub fn provider_concurrency_stats(&self) -> Vec<ProviderConcurrencyStats> {
+        self.request_limiter.stats()
+    }
+
+    /// Time-to-first-token and throughput aggregates per model and provider
+    pub fn latency_stats(&self) -> Vec<ModelLatencyStats> {
+        self.metrics.stats()
+    }
+
+    pub fn add_embedding_provider(
+        mut self,
+        provider: LLMProvider,
+        client: Box<dyn LLMEmbeddingClient + Send + Sync>,
+    ) -> Self {
+        self.embedding_providers.insert(provider, client);
+        self
+    }
+
+    /// Generates embeddings through the provider backing the api key, errors
+    /// out when the provider has no embedding support registered
+    pub async fn generate_embeddings(
+        &self,
+        api_key: LLMProviderAPIKeys,
+        provider: LLMProvider,
+        request: LLMEmbeddingRequest,
+    ) -> Result<LLMEmbeddingResponse, LLMClientError> {
+        let embedding_client = self
+            .embedding_providers
+            .get(&provider)
+            .ok_or(LLMClientError::UnSupportedModel)?;
+        embedding_client.generate_embeddings(api_key, request).await
+    }
+
+    /// Turns on request/response capture, every pair which goes through the
+    /// broker gets scrubbed and appended to a per-session file in the directory
+    pub fn with_capture_directory(mut self, directory: PathBuf) -> Self {
+        self.capture_sink = Some(Arc::new(LLMCaptureSink::new(directory)));
+        self
+    }
+
+    /// Overrides the default retry policy, the host application threads its
+    /// configuration through here
+    pub fn with_retry_policy(mut self, retry_policy: LLMBrokerRetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Re-registers the ollama provider pointing at a non-default server,
+    /// optionally with a keep_alive so the model stays loaded between requests
+    pub fn with_ollama_endpoint(self, base_url: String, keep_alive: Option<String>) -> Self {
+        let mut ollama_client = OllamaClient::new().with_base_url(base_url.to_owned());
+        let mut ollama_embedding_client = OllamaClient::new().with_base_url(base_url);
+        if let Some(keep_alive) = keep_alive {
+            ollama_client = ollama_client.with_keep_alive(keep_alive.to_owned());
+            ollama_embedding_client = ollama_embedding_client.with_keep_alive(keep_alive);
+        }
+        self.add_provider(LLMProvider::Ollama, Box::new(ollama_client))
+            .add_embedding_provider(LLMProvider::Ollama, Box::new(ollama_embedding_client))
+    }
+
+    /// The session id we file capture records under, the root_id in the
+    /// metadata maps to the session on the editor side
+    fn capture_session_id(metadata: &HashMap<String, String>) -> String {
+        metadata
+            .get("root_id")
+            .or_else(|| metadata.get("session_id"))
+            .map(|session_id| session_id.to_owned())
+            .unwrap_or("no_session".to_owned())
+    }
+
     pub async fn stream_answer(
         &self,
         api_key: LLMProviderAPIKeys,
@@ -119,16 +423,19 @@ impl LLMBroker {
             LLMProviderAPIKeys::OpenAIAzureConfig(_) => LLMProvider::OpenAI,
             LLMProviderAPIKeys::TogetherAI(_) => LLMProvider::TogetherAI,
             LLMProviderAPIKeys::LMStudio(_) => LLMProvider::LMStudio,
+            LLMProviderAPIKeys::LlamaCpp(_) => LLMProvider::LlamaCpp,
             LLMProviderAPIKeys::CodeStory(_) => {
                 LLMProvider::CodeStory(CodeStoryLLMTypes { llm_type: None })
             }
             LLMProviderAPIKeys::OpenAICompatible(_) => LLMProvider::OpenAICompatible,
             LLMProviderAPIKeys::Anthropic(_) => LLMProvider::Anthropic,
             LLMProviderAPIKeys::FireworksAI(_) => LLMProvider::FireworksAI,
+            LLMProviderAPIKeys::Gemini(_) => LLMProvider::Gemini,
             LLMProviderAPIKeys::GeminiPro(_) => LLMProvider::GeminiPro,
             LLMProviderAPIKeys::GoogleAIStudio(_) => LLMProvider::GoogleAIStudio,
             LLMProviderAPIKeys::OpenRouter(_) => LLMProvider::OpenRouter,
             LLMProviderAPIKeys::GroqProvider(_) => LLMProvider::Groq,
+            LLMProviderAPIKeys::Bedrock(_) => LLMProvider::Bedrock,
         }
     }
 
@@ -141,6 +448,14 @@ impl LLMBroker {
         sender: tokio::sync::mpsc::UnboundedSender<LLMClientCompletionResponse>,
     ) -> LLMBrokerResponse {
         let request_id = uuid::Uuid::new_v4();
+        // deterministic runs pin the seed and zero the temperature,
+        // requests which already carry a seed keep it
+        let request = match self.deterministic_seed() {
+            Some(seed) if request.seed().is_none() => {
+                request.set_seed(seed).set_temperature(0.0)
+            }
+            _ => request,
+        };
         let api_key = api_key
             .key(&provider)
             .ok_or(LLMClientError::UnSupportedModel)?;
@@ -150,22 +465,87 @@ impl LLMBroker {
             LLMProviderAPIKeys::OpenAIAzureConfig(_) => LLMProvider::OpenAI,
             LLMProviderAPIKeys::TogetherAI(_) => LLMProvider::TogetherAI,
             LLMProviderAPIKeys::LMStudio(_) => LLMProvider::LMStudio,
+            LLMProviderAPIKeys::LlamaCpp(_) => LLMProvider::LlamaCpp,
             LLMProviderAPIKeys::CodeStory(_) => {
                 LLMProvider::CodeStory(CodeStoryLLMTypes { llm_type: None })
             }
             LLMProviderAPIKeys::OpenAICompatible(_) => LLMProvider::OpenAICompatible,
             LLMProviderAPIKeys::Anthropic(_) => LLMProvider::Anthropic,
             LLMProviderAPIKeys::FireworksAI(_) => LLMProvider::FireworksAI,
+            LLMProviderAPIKeys::Gemini(_) => LLMProvider::Gemini,
             LLMProviderAPIKeys::GeminiPro(_) => LLMProvider::GeminiPro,
             LLMProviderAPIKeys::GoogleAIStudio(_) => LLMProvider::GoogleAIStudio,
             LLMProviderAPIKeys::OpenRouter(_) => LLMProvider::OpenRouter,
             LLMProviderAPIKeys::GroqProvider(_) => LLMProvider::Groq,
+            LLMProviderAPIKeys::Bedrock(_) => LLMProvider::Bedrock,
         };
         let provider = self.providers.get(&provider_type);
         if let Some(provider) = provider {
-            let result = provider
-                .stream_completion(api_key, request.clone(), sender)
-                .await;
+            // hold a concurrency permit for this provider for as long as
+            // the request (including retries) is in flight
+            let _permit = self.request_limiter.acquire(&provider_type).await;
+            // proxy the deltas so we can stamp the arrival of the first
+            // token for the latency metrics
+            let request_start = std::time::Instant::now();
+            let first_token_at = Arc::new(Mutex::new(None::<std::time::Instant>));
+            let (proxy_sender, mut proxy_receiver) =
+                tokio::sync::mpsc::unbounded_channel::<LLMClientCompletionResponse>();
+            let cloned_first_token_at = first_token_at.clone();
+            let downstream_sender = sender.clone();
+            let forwarder = tokio::spawn(async move {
+                while let Some(response) = proxy_receiver.recv().await {
+                    if let Ok(mut first_token) = cloned_first_token_at.lock() {
+                        first_token.get_or_insert_with(std::time::Instant::now);
+                    }
+                    let _ = downstream_sender.send(response);
+                }
+            });
+            let mut attempt = 0;
+            let result = loop {
+                let result = provider
+                    .stream_completion(api_key.clone(), request.clone(), proxy_sender.clone())
+                    .await;
+                match result.as_ref() {
+                    Err(error)
+                        if attempt + 1 < self.retry_policy.max_attempts()
+                            && LLMBrokerRetryPolicy::is_retryable(error) =>
+                    {
+                        let delay = self.retry_policy.backoff_delay(attempt);
+                        println!(
+                            "llm_broker::stream_completion::retryable_error({:?})::attempt({})::retrying_in({:?})",
+                            error, attempt, delay
+                        );
+                        attempt = attempt + 1;
+                        tokio::time::sleep(delay).await;
+                    }
+                    _ => break result,
+                }
+            };
+            drop(proxy_sender);
+            let _ = forwarder.await;
+            if let Ok(response) = result.as_ref() {
+                let total_duration = request_start.elapsed();
+                let time_to_first_token = first_token_at
+                    .lock()
+                    .ok()
+                    .and_then(|first_token| {
+                        first_token.map(|instant| instant.duration_since(request_start))
+                    });
+                // fall back to a rough 4-chars-per-token estimate when the
+                // provider does not report usage
+                let output_tokens = response
+                    .usage_statistics()
+                    .output_tokens()
+                    .map(|tokens| tokens as u64)
+                    .unwrap_or((response.answer_up_until_now().len() / 4) as u64);
+                self.metrics.record_request(
+                    &request.model().to_string(),
+                    &provider_type.to_string(),
+                    time_to_first_token,
+                    Some(output_tokens),
+                    total_duration,
+                );
+            }
             if let Ok(result) = result.as_ref() {
                 let parea_log_completion = PareaLogCompletion::new(
                     request
@@ -200,8 +580,38 @@ impl LLMBroker {
                 // Log to posthog as well
                 let _ = self
                     .posthog_client
-                    .capture_reqeust_and_response(&request, result.answer_up_until_now(), metadata)
+                    .capture_reqeust_and_response(
+                        &request,
+                        result.answer_up_until_now(),
+                        metadata.clone(),
+                    )
                     .await;
+                if let Some(capture_sink) = self.capture_sink.as_ref() {
+                    capture_sink
+                        .capture(
+                            &Self::capture_session_id(&metadata),
+                            LLMCaptureRecord::chat(
+                                metadata
+                                    .get("event_type")
+                                    .map(|s| s.to_owned())
+                                    .unwrap_or("no_event_type".to_owned()),
+                                request.model().to_string(),
+                                provider_type.to_string(),
+                                request
+                                    .messages()
+                                    .into_iter()
+                                    .map(|message| {
+                                        LLMCaptureMessage::new(
+                                            message.role().to_string(),
+                                            message.content().to_owned(),
+                                        )
+                                    })
+                                    .collect::<Vec<_>>(),
+                                result.answer_up_until_now().to_owned(),
+                            ),
+                        )
+                        .await;
+                }
             }
             result
         } else {
@@ -209,6 +619,108 @@ impl LLMBroker {
         }
     }
 
+    /// Same as [`Self::stream_completion`] but races the request against a
+    /// cancellation token. When the token fires mid-stream the in-flight http
+    /// stream is dropped and whatever answer accumulated until then comes
+    /// back, so callers can surface partial results instead of waiting the
+    /// request out
+    pub async fn stream_completion_with_cancellation(
+        &self,
+        api_key: LLMProviderAPIKeys,
+        request: LLMClientCompletionRequest,
+        provider: LLMProvider,
+        metadata: HashMap<String, String>,
+        sender: tokio::sync::mpsc::UnboundedSender<LLMClientCompletionResponse>,
+        cancellation_token: tokio_util::sync::CancellationToken,
+    ) -> LLMBrokerResponse {
+        let model = request.model().to_string();
+        // forward deltas to the caller while remembering the answer so far,
+        // the partial answer is what we hand back on cancellation
+        let (proxy_sender, mut proxy_receiver) =
+            tokio::sync::mpsc::unbounded_channel::<LLMClientCompletionResponse>();
+        let answer_up_until_now = Arc::new(Mutex::new(String::new()));
+        let cloned_answer_up_until_now = answer_up_until_now.clone();
+        let forwarder = tokio::spawn(async move {
+            while let Some(response) = proxy_receiver.recv().await {
+                if let Ok(mut answer) = cloned_answer_up_until_now.lock() {
+                    *answer = response.answer_up_until_now().to_owned();
+                }
+                let _ = sender.send(response);
+            }
+        });
+        tokio::select! {
+            result = self.stream_completion(api_key, request, provider, metadata, proxy_sender) => {
+                // the proxy sender is gone at this point so the forwarder
+                // drains the last deltas and finishes on its own
+                let _ = forwarder.await;
+                result
+            }
+            _ = cancellation_token.cancelled() => {
+                // dropping the stream_completion future here drops the
+                // underlying http stream, the provider sees the connection
+                // go away
+                let answer = answer_up_until_now
+                    .lock()
+                    .map(|answer| answer.to_owned())
+                    .unwrap_or_default();
+                Ok(LLMClientCompletionResponse::new(answer, None, model))
+            }
+        }
+    }
+
+    /// Structured-output version of stream_completion. The schema gets
+    /// attached to the request so providers which can enforce it do, the
+    /// final answer is validated against the schema and we retry once with
+    /// the validation error appended before giving up
+    pub async fn stream_completion_with_schema(
+        &self,
+        api_key: LLMProviderAPIKeys,
+        request: LLMClientCompletionRequest,
+        provider: LLMProvider,
+        response_format: LLMClientResponseFormat,
+        metadata: HashMap<String, String>,
+        sender: tokio::sync::mpsc::UnboundedSender<LLMClientCompletionResponse>,
+    ) -> LLMBrokerResponse {
+        let request = request.set_response_format(response_format.clone());
+        let response = self
+            .stream_completion(
+                api_key.clone(),
+                request.clone(),
+                provider.clone(),
+                metadata.clone(),
+                sender.clone(),
+            )
+            .await?;
+        let validation_error = match response_format.validate(response.answer_up_until_now()) {
+            Ok(_) => return Ok(response),
+            Err(validation_error) => validation_error,
+        };
+        // one retry with the validation error appended so the model can
+        // correct itself
+        let mut messages = request.messages().to_vec();
+        messages.push(LLMClientMessage::assistant(
+            response.answer_up_until_now().to_owned(),
+        ));
+        messages.push(LLMClientMessage::user(format!(
+            "Your response failed schema validation: {}. Respond again with json matching the schema.",
+            validation_error
+        )));
+        let retry_request = LLMClientCompletionRequest::new(
+            request.model().clone(),
+            messages,
+            request.temperature(),
+            request.frequency_penalty(),
+        )
+        .set_response_format(response_format.clone());
+        let retry_response = self
+            .stream_completion(api_key, retry_request, provider, metadata, sender)
+            .await?;
+        match response_format.validate(retry_response.answer_up_until_now()) {
+            Ok(_) => Ok(retry_response),
+            Err(validation_error) => Err(LLMClientError::SchemaValidationFailed(validation_error)),
+        }
+    }
+
     // TODO(skcd): Debug this part of the code later on, cause we have
     // some bugs around here about the new line we are sending over
     pub async fn stream_string_completion_owned(
@@ -387,31 +899,114 @@ impl LLMBroker {
         &'a self,
         api_key: LLMProviderAPIKeys,
         request: LLMClientCompletionStringRequest,
-        _metadata: HashMap<String, String>,
+        metadata: HashMap<String, String>,
         sender: tokio::sync::mpsc::UnboundedSender<LLMClientCompletionResponse>,
     ) -> LLMBrokerResponse {
+        // deterministic runs pin the seed here the same way the chat
+        // completion path does
+        let request = match self.deterministic_seed() {
+            Some(seed) if request.seed().is_none() => {
+                request.set_seed(seed).set_temperature(0.0)
+            }
+            _ => request,
+        };
         let provider_type = match &api_key {
             LLMProviderAPIKeys::Ollama(_) => LLMProvider::Ollama,
             LLMProviderAPIKeys::OpenAI(_) => LLMProvider::OpenAI,
             LLMProviderAPIKeys::OpenAIAzureConfig(_) => LLMProvider::OpenAI,
             LLMProviderAPIKeys::TogetherAI(_) => LLMProvider::TogetherAI,
             LLMProviderAPIKeys::LMStudio(_) => LLMProvider::LMStudio,
+            LLMProviderAPIKeys::LlamaCpp(_) => LLMProvider::LlamaCpp,
             LLMProviderAPIKeys::CodeStory(_) => {
                 LLMProvider::CodeStory(CodeStoryLLMTypes { llm_type: None })
             }
             LLMProviderAPIKeys::OpenAICompatible(_) => LLMProvider::OpenAICompatible,
             LLMProviderAPIKeys::Anthropic(_) => LLMProvider::Anthropic,
             LLMProviderAPIKeys::FireworksAI(_) => LLMProvider::FireworksAI,
+            LLMProviderAPIKeys::Gemini(_) => LLMProvider::Gemini,
             LLMProviderAPIKeys::GeminiPro(_) => LLMProvider::GeminiPro,
             LLMProviderAPIKeys::GoogleAIStudio(_) => LLMProvider::GoogleAIStudio,
             LLMProviderAPIKeys::OpenRouter(_) => LLMProvider::OpenRouter,
             LLMProviderAPIKeys::GroqProvider(_) => LLMProvider::Groq,
+            LLMProviderAPIKeys::Bedrock(_) => LLMProvider::Bedrock,
         };
         let provider = self.providers.get(&provider_type);
         if let Some(provider) = provider {
-            let result = provider
-                .stream_prompt_completion(api_key, request.clone(), sender)
-                .await;
+            // same per-provider concurrency permit as the chat completion
+            // path
+            let _permit = self.request_limiter.acquire(&provider_type).await;
+            // proxy the deltas so we can stamp the arrival of the first
+            // token for the latency metrics
+            let request_start = std::time::Instant::now();
+            let first_token_at = Arc::new(Mutex::new(None::<std::time::Instant>));
+            let (proxy_sender, mut proxy_receiver) =
+                tokio::sync::mpsc::unbounded_channel::<LLMClientCompletionResponse>();
+            let cloned_first_token_at = first_token_at.clone();
+            let downstream_sender = sender.clone();
+            let forwarder = tokio::spawn(async move {
+                while let Some(response) = proxy_receiver.recv().await {
+                    if let Ok(mut first_token) = cloned_first_token_at.lock() {
+                        first_token.get_or_insert_with(std::time::Instant::now);
+                    }
+                    let _ = downstream_sender.send(response);
+                }
+            });
+            let mut attempt = 0;
+            let result = loop {
+                let result = provider
+                    .stream_prompt_completion(api_key.clone(), request.clone(), proxy_sender.clone())
+                    .await;
+                match result.as_ref() {
+                    Err(error)
+                        if attempt + 1 < self.retry_policy.max_attempts()
+                            && LLMBrokerRetryPolicy::is_retryable(error) =>
+                    {
+                        let delay = self.retry_policy.backoff_delay(attempt);
+                        println!(
+                            "llm_broker::stream_string_completion::retryable_error({:?})::attempt({})::retrying_in({:?})",
+                            error, attempt, delay
+                        );
+                        attempt = attempt + 1;
+                        tokio::time::sleep(delay).await;
+                    }
+                    _ => break result,
+                }
+            };
+            drop(proxy_sender);
+            let _ = forwarder.await;
+            if let Ok(answer) = result.as_ref() {
+                let total_duration = request_start.elapsed();
+                let time_to_first_token = first_token_at.lock().ok().and_then(|first_token| {
+                    first_token.map(|instant| instant.duration_since(request_start))
+                });
+                // prompt completions return a bare string, so estimate the
+                // output tokens at a rough 4 chars per token
+                self.metrics.record_request(
+                    &request.model().to_string(),
+                    &provider_type.to_string(),
+                    time_to_first_token,
+                    Some((answer.len() / 4) as u64),
+                    total_duration,
+                );
+            }
+            if let (Ok(result), Some(capture_sink)) = (result.as_ref(), self.capture_sink.as_ref())
+            {
+                capture_sink
+                    .capture(
+                        &Self::capture_session_id(&metadata),
+                        LLMCaptureRecord::prompt(
+                            metadata
+                                .get("event_type")
+                                .map(|s| s.to_owned())
+                                .unwrap_or("no_event_type".to_owned()),
+                            request.model().to_string(),
+                            provider_type.to_string(),
+                            request.prompt().to_owned(),
+                            result.to_owned(),
+                        ),
+                    )
+                    .await;
+            }
             result.map(|result| {
                 LLMClientCompletionResponse::new(result, None, "not_present".to_owned())
             })
@@ -456,3 +1051,48 @@ fn get_indent_diff(s: &str, whitespace: &str) -> String {
         whitespace_string
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::ProviderRequestLimiter;
+    use crate::provider::LLMProvider;
+
+    #[tokio::test]
+    async fn test_limiter_bounds_in_flight_requests() {
+        let limiter = ProviderRequestLimiter::new(2);
+        let first = limiter.acquire(&LLMProvider::OpenAI).await;
+        let _second = limiter.acquire(&LLMProvider::OpenAI).await;
+        assert_eq!(limiter.stats()[0].in_flight, 2);
+        // a third acquire has to wait for a permit
+        let blocked = tokio::time::timeout(
+            std::time::Duration::from_millis(50),
+            limiter.acquire(&LLMProvider::OpenAI),
+        )
+        .await;
+        assert!(blocked.is_err());
+        drop(first);
+        let _third = tokio::time::timeout(
+            std::time::Duration::from_millis(50),
+            limiter.acquire(&LLMProvider::OpenAI),
+        )
+        .await
+        .expect("dropping a permit frees up a slot");
+    }
+
+    #[tokio::test]
+    async fn test_limiter_tracks_stats_per_provider() {
+        let limiter = ProviderRequestLimiter::new(4);
+        let _openai = limiter.acquire(&LLMProvider::OpenAI).await;
+        let _anthropic = limiter.acquire(&LLMProvider::Anthropic).await;
+        drop(limiter.acquire(&LLMProvider::Anthropic).await);
+        let stats = limiter.stats();
+        assert_eq!(stats.len(), 2);
+        // sorted by provider name so the output is stable
+        assert_eq!(stats[0].provider, "Anthropic");
+        assert_eq!(stats[0].in_flight, 1);
+        assert_eq!(stats[0].total_served, 2);
+        assert_eq!(stats[1].provider, "OpenAI");
+        assert_eq!(stats[1].in_flight, 1);
+        assert_eq!(stats[1].waiting, 0);
+    }
+}
@@ -0,0 +1,134 @@
+//! Static registry of what each model can do: how big its context window is,
+//! whether it can call tools or look at images and what it costs to run.
+//! Callers use this to pick a truncation strategy upfront and to fall over to
+//! a bigger model instead of failing mid-run when a request does not fit.
+
+use crate::clients::types::LLMType;
+
+/// The capabilities we track per model, the costs are in dollars per million
+/// tokens since per-token numbers are unreadably small
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ModelCapabilities {
+    context_window_tokens: usize,
+    supports_tools: bool,
+    supports_vision: bool,
+    input_cost_per_million_tokens: f32,
+    output_cost_per_million_tokens: f32,
+}
+
+impl ModelCapabilities {
+    /// Looks up the capabilities for a model, None for custom or local models
+    /// we know nothing about so callers can keep their current behaviour
+    pub fn for_model(llm: &LLMType) -> Option<Self> {
+        let capabilities = match llm {
+            LLMType::Gpt4 => Self::new(8_192, true, false, 30.0, 60.0),
+            LLMType::Gpt4_32k => Self::new(32_768, true, false, 60.0, 120.0),
+            LLMType::GPT3_5_16k => Self::new(16_385, true, false, 0.5, 1.5),
+            LLMType::Gpt4Turbo => Self::new(128_000, true, true, 10.0, 30.0),
+            LLMType::Gpt4O => Self::new(128_000, true, true, 2.5, 10.0),
+            LLMType::Gpt4OMini => Self::new(128_000, true, true, 0.15, 0.6),
+            LLMType::O1Preview => Self::new(128_000, false, false, 15.0, 60.0),
+            LLMType::O1Mini => Self::new(128_000, false, false, 1.1, 4.4),
+            LLMType::O1 => Self::new(200_000, true, true, 15.0, 60.0),
+            LLMType::O3MiniHigh => Self::new(200_000, true, false, 1.1, 4.4),
+            LLMType::ClaudeOpus => Self::new(200_000, true, true, 15.0, 75.0),
+            LLMType::ClaudeSonnet => Self::new(200_000, true, true, 3.0, 15.0),
+            LLMType::ClaudeHaiku => Self::new(200_000, true, true, 0.8, 4.0),
+            LLMType::GeminiPro => Self::new(2_000_000, true, true, 1.25, 5.0),
+            LLMType::GeminiProFlash => Self::new(1_000_000, true, true, 0.075, 0.3),
+            LLMType::Gemini2_0FlashExperimental => Self::new(1_000_000, true, true, 0.1, 0.4),
+            LLMType::Gemini2_0FlashThinkingExperimental => {
+                Self::new(1_000_000, false, true, 0.1, 0.4)
+            }
+            LLMType::DeepSeekCoderV3 => Self::new(64_000, true, false, 0.27, 1.1),
+            LLMType::DeepSeekR1 => Self::new(64_000, false, false, 0.55, 2.19),
+            LLMType::Llama3_1_8bInstruct => Self::new(128_000, false, false, 0.05, 0.08),
+            LLMType::Llama3_1_70bInstruct => Self::new(128_000, false, false, 0.35, 0.4),
+            _ => return None,
+        };
+        Some(capabilities)
+    }
+
+    fn new(
+        context_window_tokens: usize,
+        supports_tools: bool,
+        supports_vision: bool,
+        input_cost_per_million_tokens: f32,
+        output_cost_per_million_tokens: f32,
+    ) -> Self {
+        Self {
+            context_window_tokens,
+            supports_tools,
+            supports_vision,
+            input_cost_per_million_tokens,
+            output_cost_per_million_tokens,
+        }
+    }
+
+    pub fn context_window_tokens(&self) -> usize {
+        self.context_window_tokens
+    }
+
+    pub fn supports_tools(&self) -> bool {
+        self.supports_tools
+    }
+
+    pub fn supports_vision(&self) -> bool {
+        self.supports_vision
+    }
+
+    pub fn input_cost_per_million_tokens(&self) -> f32 {
+        self.input_cost_per_million_tokens
+    }
+
+    pub fn output_cost_per_million_tokens(&self) -> f32 {
+        self.output_cost_per_million_tokens
+    }
+
+    /// Whether a request of this estimated size fits in the context window,
+    /// we keep a small slack for the completion so a request which technically
+    /// fits does not leave the model with no room to answer
+    pub fn fits_in_context(&self, estimated_prompt_tokens: usize) -> bool {
+        const COMPLETION_SLACK_TOKENS: usize = 4_096;
+        estimated_prompt_tokens + COMPLETION_SLACK_TOKENS <= self.context_window_tokens
+    }
+}
+
+/// Rough token estimate for prompt sizing, 4 characters per token is the
+/// usual rule of thumb and close enough for a fits-or-not decision
+pub fn estimate_prompt_tokens(content: &str) -> usize {
+    content.len() / 4
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{estimate_prompt_tokens, ModelCapabilities};
+    use crate::clients::types::LLMType;
+
+    #[test]
+    fn test_known_models_have_capabilities() {
+        let capabilities =
+            ModelCapabilities::for_model(&LLMType::ClaudeSonnet).expect("claude to be known");
+        assert_eq!(capabilities.context_window_tokens(), 200_000);
+        assert!(capabilities.supports_tools());
+        assert!(capabilities.supports_vision());
+    }
+
+    #[test]
+    fn test_custom_models_are_unknown() {
+        assert!(ModelCapabilities::for_model(&LLMType::Custom("local-llama".to_owned())).is_none());
+    }
+
+    #[test]
+    fn test_fits_in_context_keeps_completion_slack() {
+        let capabilities = ModelCapabilities::for_model(&LLMType::Gpt4).expect("gpt4 to be known");
+        assert!(capabilities.fits_in_context(1_000));
+        // technically inside the window but leaves no room for the answer
+        assert!(!capabilities.fits_in_context(8_000));
+    }
+
+    #[test]
+    fn test_token_estimate_is_a_quarter_of_the_characters() {
+        assert_eq!(estimate_prompt_tokens(&"a".repeat(4_000)), 1_000);
+    }
+}
@@ -0,0 +1,237 @@
+//! Optional capture of every request/response pair which goes through the
+//! broker, this powers the "show what was sent to the model" flow in the
+//! editor and makes offline prompt debugging possible
+//!
+//! Captured records are scrubbed of anything which looks like an api key or
+//! secret before they touch the disk, one jsonl file is written per session
+//! so the editor can index them cheaply
+
+use std::path::PathBuf;
+
+use tokio::io::AsyncWriteExt;
+
+/// Token prefixes which are a strong signal that we are looking at a secret,
+/// anything starting with one of these gets redacted before capture
+const SECRET_TOKEN_PREFIXES: &[&str] = &[
+    "sk-",
+    "ghp_",
+    "github_pat_",
+    "glpat-",
+    "xoxb-",
+    "xoxp-",
+    "AKIA",
+];
+
+const REDACTED: &str = "[REDACTED]";
+
+/// A single message which was part of the request we sent to the model
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct LLMCaptureMessage {
+    role: String,
+    content: String,
+}
+
+impl LLMCaptureMessage {
+    pub fn new(role: String, content: String) -> Self {
+        Self { role, content }
+    }
+}
+
+/// One request/response pair as it was sent to the model, already scrubbed
+/// of secrets
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct LLMCaptureRecord {
+    /// rfc3339 timestamp for when we got the full response back
+    timestamp: String,
+    /// the event type from the request metadata, e.g. tool_use
+    event_type: String,
+    model: String,
+    provider: String,
+    /// populated for chat style requests
+    messages: Vec<LLMCaptureMessage>,
+    /// populated for raw prompt completion requests
+    #[serde(skip_serializing_if = "Option::is_none")]
+    prompt: Option<String>,
+    response: String,
+}
+
+impl LLMCaptureRecord {
+    pub fn chat(
+        event_type: String,
+        model: String,
+        provider: String,
+        messages: Vec<LLMCaptureMessage>,
+        response: String,
+    ) -> Self {
+        Self {
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            event_type,
+            model,
+            provider,
+            messages: messages
+                .into_iter()
+                .map(|message| LLMCaptureMessage {
+                    role: message.role,
+                    content: scrub_secrets(&message.content),
+                })
+                .collect(),
+            prompt: None,
+            response: scrub_secrets(&response),
+        }
+    }
+
+    pub fn prompt(
+        event_type: String,
+        model: String,
+        provider: String,
+        prompt: String,
+        response: String,
+    ) -> Self {
+        Self {
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            event_type,
+            model,
+            provider,
+            messages: vec![],
+            prompt: Some(scrub_secrets(&prompt)),
+            response: scrub_secrets(&response),
+        }
+    }
+}
+
+/// Appends capture records to per-session jsonl files under the directory
+/// it was created with, writes are best effort and never block the request
+/// path on failure
+pub struct LLMCaptureSink {
+    directory: PathBuf,
+}
+
+impl LLMCaptureSink {
+    pub fn new(directory: PathBuf) -> Self {
+        Self { directory }
+    }
+
+    pub fn directory(&self) -> &PathBuf {
+        &self.directory
+    }
+
+    /// Appends a single record to the session file, creating the capture
+    /// directory on first use
+    pub async fn capture(&self, session_id: &str, record: LLMCaptureRecord) {
+        let Ok(serialized) = serde_json::to_string(&record) else {
+            return;
+        };
+        if tokio::fs::create_dir_all(&self.directory).await.is_err() {
+            return;
+        }
+        let file_path = self
+            .directory
+            .join(format!("{}.jsonl", sanitize_session_id(session_id)));
+        let file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(file_path)
+            .await;
+        if let Ok(mut file) = file {
+            let _ = file.write_all(serialized.as_bytes()).await;
+            let _ = file.write_all(b"\n").await;
+        }
+    }
+}
+
+/// Session ids come from the editor so we make sure they can not escape the
+/// capture directory when used as a file name
+fn sanitize_session_id(session_id: &str) -> String {
+    session_id
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Redacts anything which looks like an api key or secret from the content,
+/// we check for well known token prefixes along with key=value style
+/// assignments where the key name suggests a secret
+pub fn scrub_secrets(content: &str) -> String {
+    content
+        .split('\n')
+        .map(scrub_line)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn scrub_line(line: &str) -> String {
+    line.split(' ')
+        .map(|token| {
+            if let Some((key, value)) = token.split_once('=') {
+                let key_lowercase = key.to_lowercase();
+                if !value.is_empty()
+                    && (key_lowercase.contains("key")
+                        || key_lowercase.contains("token")
+                        || key_lowercase.contains("secret")
+                        || key_lowercase.contains("password"))
+                {
+                    return format!("{}={}", key, REDACTED);
+                }
+            }
+            if is_secret_token(token) {
+                REDACTED.to_owned()
+            } else {
+                token.to_owned()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn is_secret_token(token: &str) -> bool {
+    // the length check keeps short natural language tokens like sk-ii from
+    // getting redacted, real keys are all much longer
+    token.len() >= 20
+        && SECRET_TOKEN_PREFIXES
+            .iter()
+            .any(|prefix| token.starts_with(prefix))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{sanitize_session_id, scrub_secrets};
+
+    #[test]
+    fn test_scrubbing_known_token_prefixes() {
+        let content = "use the key sk-proj-abcdefghijklmnopqrstuvwxyz to call the api";
+        assert_eq!(
+            scrub_secrets(content),
+            "use the key [REDACTED] to call the api"
+        );
+    }
+
+    #[test]
+    fn test_scrubbing_key_value_assignments() {
+        let content = "export OPENAI_API_KEY=some-very-secret-value\nlet port=8080";
+        assert_eq!(
+            scrub_secrets(content),
+            "export OPENAI_API_KEY=[REDACTED]\nlet port=8080"
+        );
+    }
+
+    #[test]
+    fn test_short_tokens_are_not_scrubbed() {
+        let content = "the sk- prefix on its own is fine";
+        assert_eq!(scrub_secrets(content), content);
+    }
+
+    #[test]
+    fn test_session_id_sanitization() {
+        assert_eq!(
+            sanitize_session_id("../../etc/passwd"),
+            "______etc_passwd"
+        );
+        assert_eq!(sanitize_session_id("session-123_abc"), "session-123_abc");
+    }
+}
@@ -13,10 +13,11 @@ use crate::{
     provider::{LLMProvider, LLMProviderAPIKeys},
 };
 
+use super::stream::{AnswerStreamTranscoder, StreamControl};
 use super::types::{
     LLMClient, LLMClientCompletionRequest, LLMClientCompletionResponse,
-    LLMClientCompletionStringRequest, LLMClientError, LLMClientMessageImage, LLMClientToolReturn,
-    LLMClientToolUse, LLMType,
+    LLMClientCompletionStringRequest, LLMClientError, LLMClientMessageImage, LLMClientToolChoice,
+    LLMClientToolReturn, LLMClientToolUse, LLMType,
 };
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -216,6 +217,8 @@ struct AnthropicRequest {
     #[serde(skip_serializing_if = "Vec::is_empty")]
     /// This is going to be such a fucking nightmare later on...
     tools: Vec<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_choice: Option<serde_json::Value>,
     temperature: f32,
     stream: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -235,7 +238,7 @@ impl AnthropicRequest {
         };
         let messages = completion_request.messages();
         // grab the tools over here ONLY from the system message
-        let tools = messages
+        let mut tools = messages
             .iter()
             .find(|message| message.is_system_message())
             .map(|message| {
@@ -246,6 +249,37 @@ impl AnthropicRequest {
                     .collect::<Vec<_>>()
             })
             .unwrap_or_default();
+        // the request level tools are the native function-calling mode, they
+        // already carry the shape anthropic wants
+        tools.extend(completion_request.tools().iter().map(|tool| {
+            serde_json::json!({
+                "name": tool.name(),
+                "description": tool.description(),
+                "input_schema": tool.input_schema(),
+            })
+        }));
+        let mut tool_choice = completion_request
+            .tool_choice()
+            .map(|tool_choice| match tool_choice {
+                LLMClientToolChoice::Auto => serde_json::json!({"type": "auto"}),
+                LLMClientToolChoice::Required => serde_json::json!({"type": "any"}),
+                LLMClientToolChoice::Tool(name) => {
+                    serde_json::json!({"type": "tool", "name": name})
+                }
+            });
+        // structured output rides on a forced tool call, the json the model
+        // feeds the tool is the response we hand back
+        if let Some(response_format) = completion_request.response_format() {
+            if tools.is_empty() {
+                tools.push(serde_json::json!({
+                    "name": response_format.name(),
+                    "description": "Respond by calling this tool with arguments matching the schema",
+                    "input_schema": response_format.schema(),
+                }));
+                tool_choice =
+                    Some(serde_json::json!({"type": "tool", "name": response_format.name()}));
+            }
+        }
         // First we try to find the system message
         let system_message = messages
             .iter()
@@ -317,6 +351,7 @@ impl AnthropicRequest {
             messages,
             temperature,
             tools,
+            tool_choice,
             stream: true,
             max_tokens,
             model: model_str,
@@ -338,6 +373,7 @@ impl AnthropicRequest {
             messages,
             temperature,
             tools: vec![],
+            tool_choice: None,
             stream: true,
             max_tokens,
             model: model_str,
@@ -454,6 +490,11 @@ impl AnthropicClient {
             return Err(LLMClientError::UnauthorizedAccess);
         }
 
+        if response_stream.status() == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            error!("Rate limited by Anthropic API");
+            return Err(LLMClientError::RateLimitExceeded);
+        }
+
         let mut event_source = response_stream.bytes_stream().eventsource();
 
         // let event_next = event_source.next().await;
@@ -682,6 +723,15 @@ impl LLMClient for AnthropicClient {
         info!("anthropic::stream_completion");
         let endpoint = self.chat_endpoint();
         let model_str = self.get_model_string(request.model())?;
+        let structured_output = request.response_format().is_some();
+        let stop_patterns = request
+            .stop_words()
+            .map(|stop_words| stop_words.to_vec())
+            .unwrap_or_default();
+        let stop_after_patterns = request
+            .stop_after_patterns()
+            .map(|patterns| patterns.to_vec())
+            .unwrap_or_default();
         let message_tokens = request
             .messages()
             .iter()
@@ -727,13 +777,20 @@ impl LLMClient for AnthropicClient {
             return Err(LLMClientError::UnauthorizedAccess);
         }
 
+        if response_stream.status() == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            error!("Rate limited by Anthropic API");
+            return Err(LLMClientError::RateLimitExceeded);
+        }
+
         let mut event_source = response_stream.bytes_stream().eventsource();
 
         let mut input_tokens = 0;
         let mut output_tokens = 0;
         let mut input_cached_tokens = 0;
 
-        let mut buffered_string = "".to_owned();
+        let mut transcoder = AnswerStreamTranscoder::new(model_str.to_owned())
+            .with_stop_patterns(stop_patterns)
+            .with_stop_after_patterns(stop_after_patterns);
         while let Some(Ok(event)) = event_source.next().await {
             // TODO: debugging this
             let event = serde_json::from_str::<AnthropicEvent>(&event.data);
@@ -744,28 +801,15 @@ impl LLMClient for AnthropicClient {
                             println!("anthropic::tool_use::{}", &name);
                         }
                         ContentBlockStart::TextDelta { text } => {
-                            buffered_string = buffered_string + &text;
-                            if let Err(e) = sender.send(
-                                LLMClientCompletionResponse::new(
-                                    buffered_string.to_owned(),
-                                    Some(text),
-                                    model_str.to_owned(),
-                                )
-                                .set_usage_statistics(
-                                    LLMClientUsageStatistics::new()
-                                        .set_input_tokens(input_tokens)
-                                        .set_output_tokens(output_tokens)
-                                ),
-                            ) {
-                                error!("Failed to send completion response: {}", e);
-                                return Err(LLMClientError::SendError(e));
+                            if let StreamControl::Stop = transcoder.transcode_delta(&text, &sender)
+                            {
+                                break;
                             }
                         }
                     }
                 }
                 Ok(AnthropicEvent::ContentBlockDelta { delta, .. }) => match delta {
                     ContentBlockDeltaType::TextDelta { text } => {
-                        buffered_string = buffered_string + &text;
                         let time_now = std::time::SystemTime::now()
                             .duration_since(std::time::UNIX_EPOCH)
                             .unwrap()
@@ -774,28 +818,25 @@ impl LLMClient for AnthropicClient {
                         debug!(
                             event_name = "anthropic.buffered_string",
                             message_tokens_count = message_tokens_count,
-                            generated_tokens_count = &buffered_string.len(),
+                            generated_tokens_count = transcoder.answer_up_until_now().len(),
                             time_taken = time_diff,
                         );
-                        if let Err(e) = sender.send(
-                            LLMClientCompletionResponse::new(
-                                buffered_string.to_owned(),
-                                Some(text),
-                                model_str.to_owned(),
-                            )
-                            .set_usage_statistics(
-                                LLMClientUsageStatistics::new()
-                                    .set_input_tokens(input_tokens)
-                                    .set_output_tokens(output_tokens)
-                                    .set_cached_input_tokens(input_cached_tokens),
-                            ),
-                        ) {
-                            error!("Failed to send completion response: {}", e);
-                            return Err(LLMClientError::SendError(e));
+                        if let StreamControl::Stop = transcoder.transcode_delta(&text, &sender) {
+                            break;
                         }
                     }
                     ContentBlockDeltaType::InputJsonDelta { partial_json } => {
-                        debug!("input_json_delta::{}", &partial_json);
+                        if structured_output {
+                            // the forced tool call carries the structured
+                            // response, stream its input json as the answer
+                            if let StreamControl::Stop =
+                                transcoder.transcode_delta(&partial_json, &sender)
+                            {
+                                break;
+                            }
+                        } else {
+                            debug!("input_json_delta::{}", &partial_json);
+                        }
                     }
                 },
                 Ok(AnthropicEvent::MessageStart { message }) => {
@@ -804,12 +845,24 @@ impl LLMClient for AnthropicClient {
                     output_tokens = output_tokens + message.usage.output_tokens.unwrap_or_default();
                     input_cached_tokens = input_cached_tokens
                         + message.usage.cache_read_input_tokens.unwrap_or_default();
+                    transcoder.update_usage_statistics(
+                        LLMClientUsageStatistics::new()
+                            .set_input_tokens(input_tokens)
+                            .set_output_tokens(output_tokens)
+                            .set_cached_input_tokens(input_cached_tokens),
+                    );
                 }
                 Ok(AnthropicEvent::MessageDelta { _delta: _, usage }) => {
                     input_tokens = input_tokens + usage.input_tokens.unwrap_or_default();
                     output_tokens = output_tokens + usage.output_tokens.unwrap_or_default();
                     input_cached_tokens =
                         input_cached_tokens + usage.cache_read_input_tokens.unwrap_or_default();
+                    transcoder.update_usage_statistics(
+                        LLMClientUsageStatistics::new()
+                            .set_input_tokens(input_tokens)
+                            .set_output_tokens(output_tokens)
+                            .set_cached_input_tokens(input_cached_tokens),
+                    );
                 }
                 Err(e) => {
                     println!("{:?}", e);
@@ -821,15 +874,7 @@ impl LLMClient for AnthropicClient {
             }
         }
 
-        Ok(
-            LLMClientCompletionResponse::new(buffered_string, None, model_str)
-                .set_usage_statistics(
-                    LLMClientUsageStatistics::new()
-                        .set_input_tokens(input_tokens)
-                        .set_output_tokens(output_tokens)
-                        .set_cached_input_tokens(input_cached_tokens),
-                ),
-        )
+        Ok(transcoder.finish(&sender))
     }
 
     async fn stream_prompt_completion(
@@ -866,6 +911,11 @@ impl LLMClient for AnthropicClient {
             return Err(LLMClientError::UnauthorizedAccess);
         }
 
+        if response.status() == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            error!("Rate limited by Anthropic API");
+            return Err(LLMClientError::RateLimitExceeded);
+        }
+
         let mut response_stream = response.bytes_stream().eventsource();
 
         let mut buffered_string = "".to_owned();
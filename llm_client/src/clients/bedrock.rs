@@ -0,0 +1,436 @@
+//! Client for the AWS Bedrock runtime API. Bedrock does not take an api key,
+//! requests are signed with SigV4 using the access key pair (plus an optional
+//! session token for assumed roles), so we implement the signing here instead
+//! of pulling in the whole aws sdk. The streaming endpoint replies with the
+//! aws event stream framing, each frame carrying a base64 json chunk which we
+//! map back to [`LLMClientCompletionResponse`].
+
+use async_trait::async_trait;
+use base64::Engine;
+use futures::StreamExt;
+use hmac::{Hmac, Mac};
+use logging::new_client;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::sync::mpsc::UnboundedSender;
+use tracing::error;
+
+use crate::provider::{BedrockConfig, LLMProvider, LLMProviderAPIKeys};
+
+use super::types::{
+    LLMClient, LLMClientCompletionRequest, LLMClientCompletionResponse,
+    LLMClientCompletionStringRequest, LLMClientError, LLMClientMessage, LLMType,
+};
+
+type HmacSha256 = Hmac<Sha256>;
+
+pub struct BedrockClient {
+    client: reqwest_middleware::ClientWithMiddleware,
+}
+
+impl BedrockClient {
+    pub fn new() -> Self {
+        Self {
+            client: new_client(),
+        }
+    }
+
+    fn model(&self, model: &LLMType) -> Option<String> {
+        match model {
+            LLMType::ClaudeOpus => Some("anthropic.claude-3-opus-20240229-v1:0".to_owned()),
+            LLMType::ClaudeSonnet => Some("anthropic.claude-3-5-sonnet-20241022-v2:0".to_owned()),
+            LLMType::ClaudeHaiku => Some("anthropic.claude-3-haiku-20240307-v1:0".to_owned()),
+            LLMType::Llama3_8bInstruct => Some("meta.llama3-8b-instruct-v1:0".to_owned()),
+            LLMType::Llama3_1_8bInstruct => Some("meta.llama3-1-8b-instruct-v1:0".to_owned()),
+            LLMType::Llama3_1_70bInstruct => Some("meta.llama3-1-70b-instruct-v1:0".to_owned()),
+            LLMType::Custom(model_id) => Some(model_id.to_owned()),
+            _ => None,
+        }
+    }
+
+    fn get_config(&self, api_key: &LLMProviderAPIKeys) -> Option<BedrockConfig> {
+        match api_key {
+            LLMProviderAPIKeys::Bedrock(config) => Some(config.clone()),
+            _ => None,
+        }
+    }
+
+    /// Claude models on bedrock speak the anthropic messages format with the
+    /// system prompt in a dedicated field.
+    fn claude_request_body(&self, request: &LLMClientCompletionRequest) -> serde_json::Value {
+        let system = request
+            .messages()
+            .iter()
+            .filter(|message| message.role().is_system())
+            .map(|message| message.content().to_owned())
+            .collect::<Vec<_>>()
+            .join("\n");
+        let messages = request
+            .messages()
+            .iter()
+            .filter(|message| !message.role().is_system())
+            .map(|message| {
+                serde_json::json!({
+                    "role": if message.role().is_assistant() { "assistant" } else { "user" },
+                    "content": message.content(),
+                })
+            })
+            .collect::<Vec<_>>();
+        serde_json::json!({
+            "anthropic_version": "bedrock-2023-05-31",
+            "max_tokens": request.get_max_tokens().unwrap_or(4096),
+            "system": system,
+            "messages": messages,
+            "temperature": request.temperature(),
+        })
+    }
+
+    /// Llama models take a single flattened prompt.
+    fn llama_request_body(&self, request: &LLMClientCompletionRequest) -> serde_json::Value {
+        let prompt = self.llama_prompt(request.messages());
+        serde_json::json!({
+            "prompt": prompt,
+            "max_gen_len": request.get_max_tokens().unwrap_or(2048),
+            "temperature": request.temperature(),
+        })
+    }
+
+    fn llama_prompt(&self, messages: &[LLMClientMessage]) -> String {
+        let mut prompt = "<|begin_of_text|>".to_owned();
+        for message in messages.iter() {
+            let role = if message.role().is_system() {
+                "system"
+            } else if message.role().is_assistant() {
+                "assistant"
+            } else {
+                "user"
+            };
+            prompt.push_str(&format!(
+                "<|start_header_id|>{}<|end_header_id|>\n{}<|eot_id|>",
+                role,
+                message.content()
+            ));
+        }
+        prompt.push_str("<|start_header_id|>assistant<|end_header_id|>\n");
+        prompt
+    }
+
+    /// Pulls the answer delta out of a decoded chunk, the shape depends on
+    /// the model family.
+    fn delta_from_chunk(&self, model_id: &str, chunk: &serde_json::Value) -> Option<String> {
+        if model_id.starts_with("meta.") {
+            return chunk
+                .get("generation")
+                .and_then(|generation| generation.as_str())
+                .map(|generation| generation.to_owned());
+        }
+        // anthropic chunks stream content block deltas
+        if chunk.get("type").and_then(|t| t.as_str()) == Some("content_block_delta") {
+            return chunk
+                .get("delta")
+                .and_then(|delta| delta.get("text"))
+                .and_then(|text| text.as_str())
+                .map(|text| text.to_owned());
+        }
+        None
+    }
+}
+
+/// uri encodes a path segment the way sigv4 wants it (everything except the
+/// unreserved characters)
+fn uri_encode(segment: &str) -> String {
+    segment
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+fn hmac_sha256(key: &[u8], data: &str) -> Vec<u8> {
+    let mut mac = HmacSha256::new_from_slice(key).expect("hmac accepts any key length");
+    mac.update(data.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    hex::encode(Sha256::digest(data))
+}
+
+/// A signed request ready to go out: the headers which have to be set on the
+/// http request for the signature to verify.
+struct SignedHeaders {
+    amz_date: String,
+    authorization: String,
+    security_token: Option<String>,
+}
+
+/// Signs a POST to the bedrock runtime with SigV4. `canonical_path` must
+/// already be uri encoded.
+fn sign_request(
+    config: &BedrockConfig,
+    host: &str,
+    canonical_path: &str,
+    body: &[u8],
+    now: chrono::DateTime<chrono::Utc>,
+) -> SignedHeaders {
+    let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+    let date_stamp = now.format("%Y%m%d").to_string();
+    let payload_hash = sha256_hex(body);
+
+    let mut canonical_headers = format!("host:{}\nx-amz-date:{}\n", host, amz_date);
+    let mut signed_headers = "host;x-amz-date".to_owned();
+    if let Some(session_token) = config.session_token.as_ref() {
+        canonical_headers.push_str(&format!("x-amz-security-token:{}\n", session_token));
+        signed_headers.push_str(";x-amz-security-token");
+    }
+
+    let canonical_request = format!(
+        "POST\n{}\n\n{}\n{}\n{}",
+        canonical_path, canonical_headers, signed_headers, payload_hash
+    );
+    let credential_scope = format!("{}/{}/bedrock/aws4_request", date_stamp, config.region);
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        amz_date,
+        credential_scope,
+        sha256_hex(canonical_request.as_bytes())
+    );
+
+    let secret = format!("AWS4{}", config.secret_access_key);
+    let date_key = hmac_sha256(secret.as_bytes(), &date_stamp);
+    let region_key = hmac_sha256(&date_key, &config.region);
+    let service_key = hmac_sha256(&region_key, "bedrock");
+    let signing_key = hmac_sha256(&service_key, "aws4_request");
+    let signature = hex::encode(hmac_sha256(&signing_key, &string_to_sign));
+
+    let authorization = format!(
+        "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+        config.access_key_id, credential_scope, signed_headers, signature
+    );
+    SignedHeaders {
+        amz_date,
+        authorization,
+        security_token: config.session_token.clone(),
+    }
+}
+
+/// The json payload of a single event stream frame.
+#[derive(Debug, Serialize, Deserialize)]
+struct BedrockChunkPayload {
+    bytes: String,
+}
+
+/// Parses as many complete aws event stream frames as the buffer holds and
+/// returns their payloads along with the number of bytes consumed. Frames are
+/// length prefixed: 4 bytes total length, 4 bytes headers length, 4 bytes
+/// prelude crc, headers, payload, 4 bytes message crc. We skip crc
+/// validation, tls already covers integrity for us.
+fn parse_event_stream_frames(
+    buffer: &[u8],
+) -> Result<(Vec<Vec<u8>>, usize), LLMClientError> {
+    let mut payloads = vec![];
+    let mut consumed = 0;
+    while buffer.len() >= consumed + 12 {
+        let frame = &buffer[consumed..];
+        let total_length = u32::from_be_bytes([frame[0], frame[1], frame[2], frame[3]]) as usize;
+        let headers_length = u32::from_be_bytes([frame[4], frame[5], frame[6], frame[7]]) as usize;
+        if total_length < headers_length + 16 {
+            // a prelude which cannot even hold its own headers is corruption,
+            // waiting for more bytes here would stall the stream forever
+            return Err(LLMClientError::FailedToGetResponse);
+        }
+        if frame.len() < total_length {
+            break;
+        }
+        let payload_start = 12 + headers_length;
+        let payload_end = total_length - 4;
+        payloads.push(frame[payload_start..payload_end].to_vec());
+        consumed += total_length;
+    }
+    Ok((payloads, consumed))
+}
+
+#[async_trait]
+impl LLMClient for BedrockClient {
+    fn client(&self) -> &LLMProvider {
+        &LLMProvider::Bedrock
+    }
+
+    async fn stream_completion(
+        &self,
+        provider_api_key: LLMProviderAPIKeys,
+        request: LLMClientCompletionRequest,
+        sender: UnboundedSender<LLMClientCompletionResponse>,
+    ) -> Result<LLMClientCompletionResponse, LLMClientError> {
+        let model_id = self
+            .model(request.model())
+            .ok_or(LLMClientError::UnSupportedModel)?;
+        let config = self
+            .get_config(&provider_api_key)
+            .ok_or(LLMClientError::WrongAPIKeyType)?;
+
+        let body = if model_id.starts_with("meta.") {
+            self.llama_request_body(&request)
+        } else {
+            self.claude_request_body(&request)
+        };
+        let body_bytes = serde_json::to_vec(&body).map_err(|e| LLMClientError::SerdeError(e))?;
+
+        let host = format!("bedrock-runtime.{}.amazonaws.com", config.region);
+        let canonical_path = format!(
+            "/model/{}/invoke-with-response-stream",
+            uri_encode(&model_id)
+        );
+        let signed_headers = sign_request(
+            &config,
+            &host,
+            &canonical_path,
+            &body_bytes,
+            chrono::Utc::now(),
+        );
+
+        let mut request_builder = self
+            .client
+            .post(format!("https://{}{}", host, canonical_path))
+            .header("Content-Type", "application/json")
+            .header("x-amz-date", signed_headers.amz_date)
+            .header("Authorization", signed_headers.authorization);
+        if let Some(security_token) = signed_headers.security_token {
+            request_builder = request_builder.header("x-amz-security-token", security_token);
+        }
+        let response = request_builder.body(body_bytes).send().await?;
+
+        if response.status() == reqwest::StatusCode::FORBIDDEN {
+            error!("Bedrock rejected the request signature");
+            return Err(LLMClientError::UnauthorizedAccess);
+        }
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_body = response.text().await?;
+            error!(
+                "HTTP Error: {} - Response body: {}",
+                status.as_u16(),
+                error_body
+            );
+            return Err(LLMClientError::FailedToGetResponse);
+        }
+
+        let mut buffered_string = "".to_owned();
+        let mut frame_buffer: Vec<u8> = vec![];
+        let mut bytes_stream = response.bytes_stream();
+        while let Some(bytes) = bytes_stream.next().await {
+            let bytes = bytes.map_err(|_e| LLMClientError::FailedToGetResponse)?;
+            frame_buffer.extend_from_slice(&bytes);
+            let (payloads, consumed) = parse_event_stream_frames(&frame_buffer)?;
+            frame_buffer.drain(..consumed);
+            for payload in payloads {
+                let Ok(chunk_payload) = serde_json::from_slice::<BedrockChunkPayload>(&payload)
+                else {
+                    continue;
+                };
+                let Ok(decoded) =
+                    base64::engine::general_purpose::STANDARD.decode(chunk_payload.bytes)
+                else {
+                    continue;
+                };
+                let Ok(chunk) = serde_json::from_slice::<serde_json::Value>(&decoded) else {
+                    continue;
+                };
+                if let Some(delta) = self.delta_from_chunk(&model_id, &chunk) {
+                    buffered_string = buffered_string + &delta;
+                    if let Err(e) = sender.send(LLMClientCompletionResponse::new(
+                        buffered_string.clone(),
+                        Some(delta),
+                        model_id.to_owned(),
+                    )) {
+                        error!("Failed to send completion response: {}", e);
+                        return Err(LLMClientError::SendError(e));
+                    }
+                }
+            }
+        }
+        Ok(LLMClientCompletionResponse::new(
+            buffered_string,
+            None,
+            model_id,
+        ))
+    }
+
+    async fn completion(
+        &self,
+        api_key: LLMProviderAPIKeys,
+        request: LLMClientCompletionRequest,
+    ) -> Result<String, LLMClientError> {
+        let (sender, _receiver) = tokio::sync::mpsc::unbounded_channel();
+        self.stream_completion(api_key, request, sender)
+            .await
+            .map(|answer| answer.answer_up_until_now().to_owned())
+    }
+
+    async fn stream_prompt_completion(
+        &self,
+        _api_key: LLMProviderAPIKeys,
+        _request: LLMClientCompletionStringRequest,
+        _sender: UnboundedSender<LLMClientCompletionResponse>,
+    ) -> Result<String, LLMClientError> {
+        Err(LLMClientError::FailedToGetResponse)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_event_stream_frames, uri_encode};
+
+    fn frame_with_payload(payload: &[u8]) -> Vec<u8> {
+        let headers: &[u8] = &[];
+        let total_length = (12 + headers.len() + payload.len() + 4) as u32;
+        let mut frame = vec![];
+        frame.extend_from_slice(&total_length.to_be_bytes());
+        frame.extend_from_slice(&(headers.len() as u32).to_be_bytes());
+        frame.extend_from_slice(&[0, 0, 0, 0]); // prelude crc, not validated
+        frame.extend_from_slice(headers);
+        frame.extend_from_slice(payload);
+        frame.extend_from_slice(&[0, 0, 0, 0]); // message crc, not validated
+        frame
+    }
+
+    #[test]
+    fn test_uri_encoding_keeps_unreserved_characters() {
+        assert_eq!(
+            uri_encode("anthropic.claude-3-haiku-20240307-v1:0"),
+            "anthropic.claude-3-haiku-20240307-v1%3A0"
+        );
+    }
+
+    #[test]
+    fn test_parsing_complete_and_partial_frames() {
+        let mut buffer = frame_with_payload(b"{\"bytes\":\"aGk=\"}");
+        buffer.extend_from_slice(&frame_with_payload(b"{\"bytes\":\"eW8=\"}"));
+        // a partial frame at the end must stay in the buffer
+        buffer.extend_from_slice(&[0, 0, 0, 42, 0, 0]);
+        let (payloads, consumed) =
+            parse_event_stream_frames(&buffer).expect("frames to parse");
+        assert_eq!(payloads.len(), 2);
+        assert_eq!(payloads[0], b"{\"bytes\":\"aGk=\"}");
+        assert_eq!(consumed, buffer.len() - 6);
+    }
+
+    #[test]
+    fn test_empty_buffer_parses_nothing() {
+        let (payloads, consumed) = parse_event_stream_frames(&[]).expect("frames to parse");
+        assert!(payloads.is_empty());
+        assert_eq!(consumed, 0);
+    }
+
+    #[test]
+    fn test_invalid_prelude_errors_instead_of_stalling() {
+        // total length smaller than the headers length + framing overhead can
+        // never become a valid frame no matter how many bytes arrive
+        let buffer: &[u8] = &[0, 0, 0, 8, 0, 0, 0, 64, 0, 0, 0, 0, 1, 2, 3, 4];
+        assert!(parse_event_stream_frames(buffer).is_err());
+    }
+}
@@ -0,0 +1,258 @@
+//! Client for the Google Gemini API (generativelanguage.googleapis.com)
+//! using a plain Gemini API key. This is the provider to pick for the public
+//! Gemini models, the older GeminiPro client stays around for deployments
+//! which talk to a custom api base.
+
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use eventsource_stream::Eventsource;
+use futures::StreamExt;
+use logging::new_client;
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc::UnboundedSender;
+use tracing::error;
+
+use crate::provider::{LLMProvider, LLMProviderAPIKeys};
+
+use super::types::{
+    LLMClient, LLMClientCompletionRequest, LLMClientCompletionResponse,
+    LLMClientCompletionStringRequest, LLMClientError, LLMClientMessage, LLMClientRole, LLMType,
+};
+
+pub struct GeminiClient {
+    client: reqwest_middleware::ClientWithMiddleware,
+}
+
+impl GeminiClient {
+    pub fn new() -> Self {
+        Self {
+            client: new_client(),
+        }
+    }
+
+    fn streaming_endpoint(&self, model: &str, api_key: &str) -> String {
+        format!("https://generativelanguage.googleapis.com/v1beta/models/{model}:streamGenerateContent?alt=sse&key={api_key}")
+    }
+
+    fn model(&self, model: &LLMType) -> Option<String> {
+        match model {
+            LLMType::GeminiPro => Some("gemini-1.5-pro".to_owned()),
+            LLMType::GeminiProFlash => Some("gemini-1.5-flash".to_owned()),
+            LLMType::Gemini2_0FlashExperimental => Some("gemini-2.0-flash-exp".to_owned()),
+            LLMType::Gemini2_0FlashThinkingExperimental => {
+                Some("gemini-2.0-flash-thinking-exp-1219".to_owned())
+            }
+            LLMType::Custom(llm_name) => Some(llm_name.to_owned()),
+            _ => None,
+        }
+    }
+
+    /// The gemini api carries the system prompt in a dedicated
+    /// systemInstruction field instead of a message with a system role, so we
+    /// pull it out of the message list here.
+    fn get_system_instruction(&self, messages: &[LLMClientMessage]) -> Option<GeminiContent> {
+        let system_messages = messages
+            .iter()
+            .filter(|message| message.role().is_system())
+            .map(|message| {
+                HashMap::from([("text".to_owned(), message.content().to_owned())])
+            })
+            .collect::<Vec<_>>();
+        if system_messages.is_empty() {
+            None
+        } else {
+            Some(GeminiContent {
+                role: None,
+                parts: system_messages,
+            })
+        }
+    }
+
+    fn get_role(&self, role: &LLMClientRole) -> Option<String> {
+        match role {
+            LLMClientRole::User => Some("user".to_owned()),
+            LLMClientRole::Assistant => Some("model".to_owned()),
+            // system messages go over systemInstruction and the function role
+            // has no mapping on gemini
+            _ => None,
+        }
+    }
+
+    fn get_contents(&self, messages: &[LLMClientMessage]) -> Vec<GeminiContent> {
+        messages
+            .iter()
+            .filter(|message| !message.role().is_system())
+            .filter_map(|message| {
+                self.get_role(message.role()).map(|role| GeminiContent {
+                    role: Some(role),
+                    parts: vec![HashMap::from([(
+                        "text".to_owned(),
+                        message.content().to_owned(),
+                    )])],
+                })
+            })
+            .collect()
+    }
+
+    fn get_api_key(&self, api_key: &LLMProviderAPIKeys) -> Option<String> {
+        match api_key {
+            LLMProviderAPIKeys::Gemini(api_key) => Some(api_key.api_key.to_owned()),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct GeminiContent {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    role: Option<String>,
+    // the only parts we will be providing is "text": "content"
+    parts: Vec<HashMap<String, String>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiGenerationConfig {
+    temperature: f32,
+    max_output_tokens: u32,
+    candidate_count: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiRequestBody {
+    contents: Vec<GeminiContent>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system_instruction: Option<GeminiContent>,
+    generation_config: GeminiGenerationConfig,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct GeminiResponse {
+    candidates: Vec<GeminiCandidate>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct GeminiCandidate {
+    content: GeminiContent,
+}
+
+#[async_trait]
+impl LLMClient for GeminiClient {
+    fn client(&self) -> &LLMProvider {
+        &LLMProvider::Gemini
+    }
+
+    async fn stream_completion(
+        &self,
+        provider_api_key: LLMProviderAPIKeys,
+        request: LLMClientCompletionRequest,
+        sender: UnboundedSender<LLMClientCompletionResponse>,
+    ) -> Result<LLMClientCompletionResponse, LLMClientError> {
+        let model = self.model(request.model());
+        if model.is_none() {
+            return Err(LLMClientError::UnSupportedModel);
+        }
+        let model = model.unwrap();
+        let api_key = self.get_api_key(&provider_api_key);
+        if api_key.is_none() {
+            return Err(LLMClientError::WrongAPIKeyType);
+        }
+        let api_key = api_key.expect("to be present");
+        let request_body = GeminiRequestBody {
+            contents: self.get_contents(request.messages()),
+            system_instruction: self.get_system_instruction(request.messages()),
+            generation_config: GeminiGenerationConfig {
+                temperature: request.temperature(),
+                max_output_tokens: 8192,
+                candidate_count: 1,
+            },
+        };
+
+        let response = self
+            .client
+            .post(self.streaming_endpoint(&model, &api_key))
+            .header("Content-Type", "application/json")
+            .json(&request_body)
+            .send()
+            .await?;
+
+        if response.status() == reqwest::StatusCode::UNAUTHORIZED {
+            error!("Unauthorized access to the Gemini API");
+            return Err(LLMClientError::UnauthorizedAccess);
+        }
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_body = response.text().await?;
+            error!(
+                "HTTP Error: {} {} - Response body: {}",
+                status.as_u16(),
+                status.as_str(),
+                error_body
+            );
+            return Err(LLMClientError::FailedToGetResponse);
+        }
+
+        let mut buffered_string = "".to_owned();
+        let mut response_stream = response.bytes_stream().eventsource();
+        while let Some(event) = response_stream.next().await {
+            match event {
+                Ok(event) => {
+                    match serde_json::from_slice::<GeminiResponse>(event.data.as_bytes()) {
+                        Ok(parsed_event) => {
+                            if let Some(text_part) = parsed_event
+                                .candidates
+                                .first()
+                                .and_then(|candidate| candidate.content.parts.first())
+                                .and_then(|part| part.get("text"))
+                            {
+                                buffered_string = buffered_string + text_part;
+                                if let Err(e) = sender.send(LLMClientCompletionResponse::new(
+                                    buffered_string.clone(),
+                                    Some(text_part.to_owned()),
+                                    model.to_owned(),
+                                )) {
+                                    error!("Failed to send completion response: {}", e);
+                                    return Err(LLMClientError::SendError(e));
+                                }
+                            }
+                        }
+                        Err(e) => {
+                            error!("Failed to parse Gemini response: {:?}", e);
+                        }
+                    }
+                }
+                Err(e) => {
+                    error!("Stream error encountered: {:?}", e);
+                }
+            }
+        }
+        Ok(LLMClientCompletionResponse::new(
+            buffered_string,
+            None,
+            model,
+        ))
+    }
+
+    async fn completion(
+        &self,
+        api_key: LLMProviderAPIKeys,
+        request: LLMClientCompletionRequest,
+    ) -> Result<String, LLMClientError> {
+        let (sender, _receiver) = tokio::sync::mpsc::unbounded_channel();
+        self.stream_completion(api_key, request, sender)
+            .await
+            .map(|answer| answer.answer_up_until_now().to_owned())
+    }
+
+    async fn stream_prompt_completion(
+        &self,
+        _api_key: LLMProviderAPIKeys,
+        _request: LLMClientCompletionStringRequest,
+        _sender: UnboundedSender<LLMClientCompletionResponse>,
+    ) -> Result<String, LLMClientError> {
+        Err(LLMClientError::GeminiProDoesNotSupportPromptCompletion)
+    }
+}
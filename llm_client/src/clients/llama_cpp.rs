@@ -0,0 +1,253 @@
+//! Client for a local llama.cpp server (or a llamafile, same API). String
+//! completions go to the native `/completion` endpoint so fill-in-middle
+//! prompts rendered by llm_prompts pass through verbatim, chat goes through
+//! the OpenAI-compatible `/v1/chat/completions` endpoint so the chat template
+//! baked into the gguf applies. Inline completion runs fully offline this way
+
+use async_trait::async_trait;
+use eventsource_stream::Eventsource;
+use futures::StreamExt;
+use logging::new_client;
+use tokio::sync::mpsc::UnboundedSender;
+use tracing::{debug, error};
+
+use crate::provider::{LLMProvider, LLMProviderAPIKeys};
+
+use super::types::{
+    LLMClient, LLMClientCompletionRequest, LLMClientCompletionResponse,
+    LLMClientCompletionStringRequest, LLMClientError, LLMClientRole,
+};
+
+pub struct LlamaCppClient {
+    client: reqwest_middleware::ClientWithMiddleware,
+}
+
+#[derive(serde::Serialize, Debug)]
+struct LlamaCppCompletionRequest {
+    prompt: String,
+    temperature: f32,
+    stream: bool,
+    // -1 lets the server generate until the model stops on its own
+    n_predict: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    frequency_penalty: Option<f32>,
+}
+
+impl LlamaCppCompletionRequest {
+    fn from_string_request(request: LLMClientCompletionStringRequest) -> Self {
+        Self {
+            prompt: request.prompt().to_owned(),
+            temperature: request.temperature(),
+            stream: true,
+            n_predict: -1,
+            frequency_penalty: request.frequency_penalty(),
+        }
+    }
+}
+
+#[derive(serde::Deserialize, Debug)]
+struct LlamaCppCompletionResponse {
+    content: String,
+    #[serde(default)]
+    model: Option<String>,
+}
+
+#[derive(serde::Serialize, Debug)]
+struct LlamaCppChatMessage {
+    role: String,
+    content: String,
+}
+
+#[derive(serde::Serialize, Debug)]
+struct LlamaCppChatRequest {
+    messages: Vec<LlamaCppChatMessage>,
+    temperature: f32,
+    stream: bool,
+}
+
+impl LlamaCppChatRequest {
+    fn from_chat_request(request: LLMClientCompletionRequest) -> Self {
+        Self {
+            messages: request
+                .messages()
+                .into_iter()
+                .map(|message| LlamaCppChatMessage {
+                    role: match message.role() {
+                        LLMClientRole::System => "system".to_owned(),
+                        LLMClientRole::User => "user".to_owned(),
+                        LLMClientRole::Function => "function".to_owned(),
+                        LLMClientRole::Assistant => "assistant".to_owned(),
+                    },
+                    content: message.content().to_owned(),
+                })
+                .collect(),
+            temperature: request.temperature(),
+            stream: true,
+        }
+    }
+}
+
+#[derive(serde::Deserialize, Debug)]
+struct LlamaCppChatResponse {
+    choices: Vec<LlamaCppChatChoice>,
+    #[serde(default)]
+    model: Option<String>,
+}
+
+#[derive(serde::Deserialize, Debug)]
+struct LlamaCppChatChoice {
+    delta: LlamaCppChatDelta,
+}
+
+#[derive(serde::Deserialize, Debug)]
+struct LlamaCppChatDelta {
+    #[serde(default)]
+    content: Option<String>,
+}
+
+impl LlamaCppClient {
+    pub fn new() -> Self {
+        Self {
+            client: new_client(),
+        }
+    }
+
+    fn completion_endpoint(&self, base_url: &str) -> String {
+        format!("{}/completion", base_url)
+    }
+
+    fn chat_endpoint(&self, base_url: &str) -> String {
+        format!("{}/v1/chat/completions", base_url)
+    }
+
+    fn generate_base_url(&self, api_key: LLMProviderAPIKeys) -> Result<String, LLMClientError> {
+        match api_key {
+            LLMProviderAPIKeys::LlamaCpp(config) => {
+                Ok(config.api_base().trim_end_matches('/').to_owned())
+            }
+            _ => Err(LLMClientError::WrongAPIKeyType),
+        }
+    }
+}
+
+#[async_trait]
+impl LLMClient for LlamaCppClient {
+    fn client(&self) -> &LLMProvider {
+        &LLMProvider::LlamaCpp
+    }
+
+    async fn completion(
+        &self,
+        api_key: LLMProviderAPIKeys,
+        request: LLMClientCompletionRequest,
+    ) -> Result<String, LLMClientError> {
+        let (sender, _receiver) = tokio::sync::mpsc::unbounded_channel();
+        self.stream_completion(api_key, request, sender)
+            .await
+            .map(|answer| answer.answer_up_until_now().to_owned())
+    }
+
+    async fn stream_completion(
+        &self,
+        api_key: LLMProviderAPIKeys,
+        request: LLMClientCompletionRequest,
+        sender: UnboundedSender<LLMClientCompletionResponse>,
+    ) -> Result<LLMClientCompletionResponse, LLMClientError> {
+        let base_url = self.generate_base_url(api_key)?;
+        let endpoint = self.chat_endpoint(&base_url);
+
+        let request = LlamaCppChatRequest::from_chat_request(request);
+        let mut response_stream = self
+            .client
+            .post(endpoint)
+            .json(&request)
+            .send()
+            .await?
+            .bytes_stream()
+            .eventsource();
+
+        let mut buffered_stream = "".to_owned();
+        let mut model = "llama.cpp".to_owned();
+        while let Some(event) = response_stream.next().await {
+            match event {
+                Ok(event) => {
+                    if &event.data == "[DONE]" {
+                        continue;
+                    }
+                    let value = serde_json::from_str::<LlamaCppChatResponse>(&event.data)?;
+                    debug!("llama.cpp chat event: {}", &event.data);
+                    if let Some(response_model) = value.model {
+                        model = response_model;
+                    }
+                    let delta = value
+                        .choices
+                        .first()
+                        .and_then(|choice| choice.delta.content.to_owned())
+                        .unwrap_or_default();
+                    if delta.is_empty() {
+                        continue;
+                    }
+                    buffered_stream = buffered_stream + &delta;
+                    if let Err(e) = sender.send(LLMClientCompletionResponse::new(
+                        buffered_stream.to_owned(),
+                        Some(delta),
+                        model.to_owned(),
+                    )) {
+                        error!("Failed to send completion response: {}", e);
+                        return Err(LLMClientError::SendError(e));
+                    }
+                }
+                Err(e) => {
+                    error!("Stream error encountered: {:?}", e);
+                }
+            }
+        }
+        Ok(LLMClientCompletionResponse::new(buffered_stream, None, model))
+    }
+
+    async fn stream_prompt_completion(
+        &self,
+        api_key: LLMProviderAPIKeys,
+        request: LLMClientCompletionStringRequest,
+        sender: UnboundedSender<LLMClientCompletionResponse>,
+    ) -> Result<String, LLMClientError> {
+        let base_url = self.generate_base_url(api_key)?;
+        let endpoint = self.completion_endpoint(&base_url);
+
+        let request = LlamaCppCompletionRequest::from_string_request(request);
+        let mut response_stream = self
+            .client
+            .post(endpoint)
+            .json(&request)
+            .send()
+            .await?
+            .bytes_stream()
+            .eventsource();
+
+        let mut buffered_stream = "".to_owned();
+        while let Some(event) = response_stream.next().await {
+            match event {
+                Ok(event) => {
+                    let value = serde_json::from_str::<LlamaCppCompletionResponse>(&event.data)?;
+                    debug!("llama.cpp completion event: {}", &event.data);
+                    if value.content.is_empty() {
+                        continue;
+                    }
+                    buffered_stream = buffered_stream + &value.content;
+                    if let Err(e) = sender.send(LLMClientCompletionResponse::new(
+                        buffered_stream.to_owned(),
+                        Some(value.content),
+                        value.model.unwrap_or("llama.cpp".to_owned()),
+                    )) {
+                        error!("Failed to send completion response: {}", e);
+                        return Err(LLMClientError::SendError(e));
+                    }
+                }
+                Err(e) => {
+                    error!("Stream error encountered: {:?}", e);
+                }
+            }
+        }
+        Ok(buffered_stream)
+    }
+}
@@ -5,13 +5,17 @@ pub mod anthropic;
 pub mod anyscaleai;
 pub mod codestory;
 pub mod fireworks;
+pub mod bedrock;
+pub mod gemini;
 pub mod gemini_pro;
 pub mod google_ai;
 pub mod groq;
+pub mod llama_cpp;
 pub mod lmstudio;
 pub mod ollama;
 pub mod open_router;
 pub mod openai;
 pub mod openai_compatible;
+pub mod stream;
 pub mod togetherai;
 pub mod types;
@@ -7,22 +7,54 @@ use tracing::{debug, error};
 
 use crate::provider::LLMProviderAPIKeys;
 
+use super::stream::AnswerStreamTranscoder;
+use super::stream::StreamControl;
 use super::types::LLMClient;
 use super::types::LLMClientCompletionRequest;
 use super::types::LLMClientCompletionResponse;
 use super::types::LLMClientCompletionStringRequest;
 use super::types::LLMClientError;
+use super::types::LLMClientUsageStatistics;
+use super::types::LLMEmbeddingClient;
+use super::types::LLMEmbeddingRequest;
+use super::types::LLMEmbeddingResponse;
 use super::types::LLMType;
 
 pub struct OllamaClient {
     pub client: reqwest_middleware::ClientWithMiddleware,
     pub base_url: String,
+    /// How long ollama keeps the model loaded after the request, passed
+    /// through verbatim (values like "5m" or "0" to unload immediately)
+    keep_alive: Option<String>,
 }
 
 #[derive(serde::Deserialize, Debug, Clone)]
 struct OllamaResponse {
     model: String,
     response: String,
+    // ollama only sets these on the final chunk (when done is true)
+    #[serde(default)]
+    prompt_eval_count: Option<u32>,
+    #[serde(default)]
+    eval_count: Option<u32>,
+}
+
+impl OllamaResponse {
+    fn usage_statistics(&self) -> Option<LLMClientUsageStatistics> {
+        match (self.prompt_eval_count, self.eval_count) {
+            (None, None) => None,
+            _ => {
+                let mut usage_statistics = LLMClientUsageStatistics::new();
+                if let Some(prompt_eval_count) = self.prompt_eval_count {
+                    usage_statistics = usage_statistics.set_input_tokens(prompt_eval_count);
+                }
+                if let Some(eval_count) = self.eval_count {
+                    usage_statistics = usage_statistics.set_output_tokens(eval_count);
+                }
+                Some(usage_statistics)
+            }
+        }
+    }
 }
 
 impl LLMType {
@@ -45,6 +77,8 @@ struct OllamaClientOptions {
     temperature: f32,
     #[serde(skip_serializing_if = "Option::is_none")]
     num_predict: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<u64>,
 }
 
 #[derive(Debug, serde::Serialize)]
@@ -56,32 +90,139 @@ struct OllamaClientRequest {
     options: OllamaClientOptions,
     #[serde(skip_serializing_if = "Option::is_none")]
     frequency_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    keep_alive: Option<String>,
 }
 
-impl OllamaClientRequest {
-    pub fn from_request(request: LLMClientCompletionRequest) -> Result<Self, LLMClientError> {
+#[derive(serde::Serialize, Debug, Clone)]
+struct OllamaChatMessage {
+    role: String,
+    content: String,
+}
+
+/// Request for the /api/chat endpoint which keeps the role structure of the
+/// conversation intact instead of flattening everything into a raw prompt
+#[derive(Debug, serde::Serialize)]
+struct OllamaChatRequest {
+    model: String,
+    messages: Vec<OllamaChatMessage>,
+    stream: bool,
+    options: OllamaClientOptions,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    keep_alive: Option<String>,
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+struct OllamaChatResponseMessage {
+    content: String,
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+struct OllamaChatResponse {
+    #[serde(default)]
+    model: String,
+    #[serde(default)]
+    message: Option<OllamaChatResponseMessage>,
+    #[serde(default)]
+    done: bool,
+    // set on the final chunk, "length" means the answer got truncated
+    #[serde(default)]
+    done_reason: Option<String>,
+    #[serde(default)]
+    prompt_eval_count: Option<u32>,
+    #[serde(default)]
+    eval_count: Option<u32>,
+    // ollama reports failures (model missing, prompt too large, ...) inline
+    #[serde(default)]
+    error: Option<String>,
+}
+
+impl OllamaChatResponse {
+    fn usage_statistics(&self) -> Option<LLMClientUsageStatistics> {
+        match (self.prompt_eval_count, self.eval_count) {
+            (None, None) => None,
+            _ => {
+                let mut usage_statistics = LLMClientUsageStatistics::new();
+                if let Some(prompt_eval_count) = self.prompt_eval_count {
+                    usage_statistics = usage_statistics.set_input_tokens(prompt_eval_count);
+                }
+                if let Some(eval_count) = self.eval_count {
+                    usage_statistics = usage_statistics.set_output_tokens(eval_count);
+                }
+                Some(usage_statistics)
+            }
+        }
+    }
+}
+
+impl OllamaChatRequest {
+    pub fn from_request(
+        request: LLMClientCompletionRequest,
+        keep_alive: Option<String>,
+    ) -> Result<Self, LLMClientError> {
         let model = request.model().to_ollama_model()?;
-        debug!("Creating Ollama request with model: {}", model);
-        Ok(Self {
-            prompt: request
-                .messages()
-                .into_iter()
-                .map(|message| message.content().to_owned())
+        debug!("Creating Ollama chat request with model: {}", model);
+        let max_tokens = request.get_max_tokens();
+        let mut messages = request
+            .messages()
+            .into_iter()
+            .map(|message| OllamaChatMessage {
+                // ollama knows system, user, assistant and tool
+                role: if message.role().is_function() {
+                    "tool".to_owned()
+                } else {
+                    message.role().to_string()
+                },
+                content: message.content().to_owned(),
+            })
+            .collect::<Vec<_>>();
+        if !request.model().supports_system_message() {
+            // reasoning models like deepseek-r1 reject system messages, so
+            // their content rides along at the top of the first user message
+            let system_content = messages
+                .iter()
+                .filter(|message| message.role == "system")
+                .map(|message| message.content.to_owned())
                 .collect::<Vec<_>>()
-                .join("\n"),
-            model: request.model().to_ollama_model()?,
+                .join("\n");
+            if !system_content.is_empty() {
+                messages.retain(|message| message.role != "system");
+                match messages
+                    .iter_mut()
+                    .find(|message| message.role == "user")
+                {
+                    Some(first_user_message) => {
+                        first_user_message.content =
+                            format!("{}\n\n{}", system_content, first_user_message.content);
+                    }
+                    None => messages.insert(
+                        0,
+                        OllamaChatMessage {
+                            role: "user".to_owned(),
+                            content: system_content,
+                        },
+                    ),
+                }
+            }
+        }
+        Ok(Self {
+            messages,
+            model,
             options: OllamaClientOptions {
                 temperature: request.temperature(),
-                num_predict: Some(1000),
+                num_predict: max_tokens,
+                seed: request.seed(),
             },
             stream: true,
-            raw: true,
-            frequency_penalty: request.frequency_penalty(),
+            keep_alive,
         })
     }
+}
 
+impl OllamaClientRequest {
     pub fn from_string_request(
         request: LLMClientCompletionStringRequest,
+        keep_alive: Option<String>,
     ) -> Result<Self, LLMClientError> {
         Ok(Self {
             prompt: request.prompt().to_owned(),
@@ -89,27 +230,119 @@ impl OllamaClientRequest {
             options: OllamaClientOptions {
                 temperature: request.temperature(),
                 num_predict: request.get_max_tokens(),
+                seed: request.seed(),
             },
             stream: true,
             raw: true,
             frequency_penalty: None,
+            keep_alive,
         })
     }
 }
 
 impl OllamaClient {
     pub fn new() -> Self {
-        // ollama always runs on the following url:
+        // ollama runs on the following url by default:
         // http://localhost:11434/
         Self {
             client: new_client(),
             base_url: "http://localhost:11434".to_owned(),
+            keep_alive: None,
         }
     }
 
+    /// Points the client at a non-default ollama server
+    pub fn with_base_url(mut self, base_url: String) -> Self {
+        self.base_url = base_url.trim_end_matches('/').to_owned();
+        self
+    }
+
+    pub fn with_keep_alive(mut self, keep_alive: String) -> Self {
+        self.keep_alive = Some(keep_alive);
+        self
+    }
+
     pub fn generation_endpoint(&self) -> String {
         format!("{}/api/generate", self.base_url)
     }
+
+    pub fn chat_endpoint(&self) -> String {
+        format!("{}/api/chat", self.base_url)
+    }
+
+    pub fn embeddings_endpoint(&self) -> String {
+        format!("{}/api/embeddings", self.base_url)
+    }
+
+    /// Maps an error ollama reported back to us onto our error type, the
+    /// prompt blowing past the context window gets its own variant so callers
+    /// can react instead of us silently truncating
+    fn error_from_message(error: String) -> LLMClientError {
+        let lowered = error.to_lowercase();
+        if lowered.contains("context") {
+            LLMClientError::ContextWindowExceeded(error)
+        } else {
+            error!("Ollama API error: {}", error);
+            LLMClientError::FailedToGetResponse
+        }
+    }
+}
+
+#[derive(Debug, serde::Serialize)]
+struct OllamaEmbeddingRequest {
+    model: String,
+    prompt: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    keep_alive: Option<String>,
+}
+
+#[derive(serde::Deserialize, Debug)]
+struct OllamaEmbeddingResponse {
+    #[serde(default)]
+    embedding: Vec<f32>,
+    #[serde(default)]
+    error: Option<String>,
+}
+
+#[async_trait]
+impl LLMEmbeddingClient for OllamaClient {
+    fn client(&self) -> &crate::provider::LLMProvider {
+        &crate::provider::LLMProvider::Ollama
+    }
+
+    async fn generate_embeddings(
+        &self,
+        _api_key: LLMProviderAPIKeys,
+        request: LLMEmbeddingRequest,
+    ) -> Result<LLMEmbeddingResponse, LLMClientError> {
+        // /api/embeddings takes a single prompt, so we fan the documents out
+        // one request at a time
+        let mut embeddings = Vec::with_capacity(request.documents().len());
+        for document in request.documents() {
+            let embedding_request = OllamaEmbeddingRequest {
+                model: request.model().to_owned(),
+                prompt: document.to_owned(),
+                keep_alive: self.keep_alive.clone(),
+            };
+            let response = self
+                .client
+                .post(self.embeddings_endpoint())
+                .json(&embedding_request)
+                .send()
+                .await?
+                .json::<OllamaEmbeddingResponse>()
+                .await
+                .map_err(|e| {
+                    error!("Failed to parse Ollama embedding response: {:?}", e);
+                    LLMClientError::FailedToGetResponse
+                })?;
+            if let Some(error) = response.error {
+                return Err(Self::error_from_message(error));
+            }
+            embeddings.push(response.embedding);
+        }
+        Ok(LLMEmbeddingResponse::new(embeddings))
+    }
 }
 
 #[async_trait]
@@ -124,10 +357,19 @@ impl LLMClient for OllamaClient {
         request: LLMClientCompletionRequest,
         sender: tokio::sync::mpsc::UnboundedSender<LLMClientCompletionResponse>,
     ) -> Result<LLMClientCompletionResponse, LLMClientError> {
-        let ollama_request = OllamaClientRequest::from_request(request)?;
+        let stop_patterns = request
+            .stop_words()
+            .map(|stop_words| stop_words.to_vec())
+            .unwrap_or_default();
+        let stop_after_patterns = request
+            .stop_after_patterns()
+            .map(|patterns| patterns.to_vec())
+            .unwrap_or_default();
+        let extract_thinking = request.model().is_reasoning_model();
+        let ollama_request = OllamaChatRequest::from_request(request, self.keep_alive.clone())?;
         let mut response = self
             .client
-            .post(self.generation_endpoint())
+            .post(self.chat_endpoint())
             .json(&ollama_request)
             .send()
             .await
@@ -136,30 +378,45 @@ impl LLMClient for OllamaClient {
                 e
             })?;
 
-        let mut buffered_string = "".to_owned();
+        let mut transcoder = AnswerStreamTranscoder::new(ollama_request.model.to_owned())
+            .with_stop_patterns(stop_patterns)
+            .with_stop_after_patterns(stop_after_patterns)
+            .with_thinking_extraction(extract_thinking);
         while let Some(chunk) = response.chunk().await? {
-            let value = match serde_json::from_slice::<OllamaResponse>(chunk.to_vec().as_slice()) {
+            let value = match serde_json::from_slice::<OllamaChatResponse>(
+                chunk.to_vec().as_slice(),
+            ) {
                 Ok(v) => v,
                 Err(e) => {
                     error!("Failed to parse Ollama response: {:?}", e);
                     return Err(LLMClientError::SerdeError(e));
                 }
             };
-            buffered_string.push_str(&value.response);
-            if let Err(e) = sender.send(LLMClientCompletionResponse::new(
-                buffered_string.to_owned(),
-                Some(value.response),
-                value.model,
-            )) {
-                error!("Failed to send completion response: {}", e);
-                return Err(LLMClientError::SendError(e));
+            if let Some(error) = value.error {
+                return Err(Self::error_from_message(error));
+            }
+            if let Some(chunk_usage) = value.usage_statistics() {
+                transcoder.update_usage_statistics(chunk_usage);
+            }
+            let delta = value
+                .message
+                .map(|message| message.content)
+                .unwrap_or_default();
+            if let StreamControl::Stop = transcoder.transcode_delta(&delta, &sender) {
+                break;
+            }
+            if value.done {
+                // surface a truncated answer instead of passing it off as a
+                // complete one
+                if value.done_reason.as_deref() == Some("length") {
+                    return Err(LLMClientError::ContextWindowExceeded(
+                        "Ollama stopped generating because the token limit was hit".to_owned(),
+                    ));
+                }
+                break;
             }
         }
-        Ok(LLMClientCompletionResponse::new(
-            buffered_string,
-            None,
-            ollama_request.model,
-        ))
+        Ok(transcoder.finish(&sender))
     }
 
     async fn completion(
@@ -179,7 +436,13 @@ impl LLMClient for OllamaClient {
         sender: UnboundedSender<LLMClientCompletionResponse>,
     ) -> Result<String, LLMClientError> {
         let prompt = request.prompt().to_owned();
-        let ollama_request = OllamaClientRequest::from_string_request(request)?;
+        let stop_patterns = request
+            .stop_words()
+            .map(|stop_words| stop_words.to_vec())
+            .unwrap_or_default();
+        let extract_thinking = request.model().is_reasoning_model();
+        let ollama_request =
+            OllamaClientRequest::from_string_request(request, self.keep_alive.clone())?;
         debug!("Sending prompt completion request: {}", prompt);
 
         let mut response = self
@@ -195,7 +458,9 @@ impl LLMClient for OllamaClient {
             return Err(LLMClientError::UnauthorizedAccess);
         }
 
-        let mut buffered_string = "".to_owned();
+        let mut transcoder = AnswerStreamTranscoder::new(ollama_request.model.to_owned())
+            .with_stop_patterns(stop_patterns)
+            .with_thinking_extraction(extract_thinking);
         while let Some(chunk) = response.chunk().await? {
             let value = match serde_json::from_slice::<OllamaResponse>(chunk.to_vec().as_slice()) {
                 Ok(v) => v,
@@ -204,16 +469,16 @@ impl LLMClient for OllamaClient {
                     return Err(LLMClientError::SerdeError(e));
                 }
             };
-            buffered_string.push_str(&value.response);
-            if let Err(e) = sender.send(LLMClientCompletionResponse::new(
-                buffered_string.to_owned(),
-                Some(value.response),
-                value.model,
-            )) {
-                error!("Failed to send completion response: {}", e);
-                return Err(LLMClientError::SendError(e));
+            if let Some(chunk_usage) = value.usage_statistics() {
+                transcoder.update_usage_statistics(chunk_usage);
+            }
+            if let StreamControl::Stop = transcoder.transcode_delta(&value.response, &sender) {
+                break;
             }
         }
-        Ok(buffered_string)
+        Ok(transcoder
+            .finish(&sender)
+            .answer_up_until_now()
+            .to_owned())
     }
 }
\ No newline at end of file
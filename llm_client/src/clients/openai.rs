@@ -3,24 +3,51 @@
 use async_openai::{
     config::{AzureConfig, OpenAIConfig},
     types::{
-        ChatCompletionRequestAssistantMessageArgs, ChatCompletionRequestDeveloperMessageArgs,
-        ChatCompletionRequestMessage, ChatCompletionRequestSystemMessageArgs,
-        ChatCompletionRequestUserMessageArgs, CreateChatCompletionRequestArgs, FunctionCall,
-        ReasoningEffort, ResponseFormat,
+        ChatCompletionNamedToolChoice, ChatCompletionRequestAssistantMessageArgs,
+        ChatCompletionRequestDeveloperMessageArgs, ChatCompletionRequestMessage,
+        ChatCompletionRequestMessageContentPartImageArgs,
+        ChatCompletionRequestMessageContentPartTextArgs, ChatCompletionRequestSystemMessageArgs,
+        ChatCompletionRequestUserMessageArgs, ChatCompletionRequestUserMessageContent,
+        ChatCompletionRequestUserMessageContentPart, ChatCompletionStreamOptions,
+        ChatCompletionTool, ChatCompletionToolChoiceOption, ChatCompletionToolType,
+        CompletionUsage, CreateChatCompletionRequestArgs, CreateEmbeddingRequestArgs, FunctionCall,
+        FunctionName, FunctionObject, ImageUrlArgs, ReasoningEffort, ResponseFormat,
+        ResponseFormatJsonSchema,
     },
     Client,
 };
 use async_trait::async_trait;
 use futures::StreamExt;
+use std::collections::{BTreeMap, HashMap};
 use tracing::{debug, error};
 
 use crate::provider::LLMProviderAPIKeys;
 
+use super::stream::{AnswerStreamTranscoder, StreamControl};
 use super::types::{
     LLMClient, LLMClientCompletionRequest, LLMClientCompletionResponse, LLMClientError,
-    LLMClientMessage, LLMClientRole, LLMType,
+    LLMClientMessage, LLMClientReasoningEffort, LLMClientRole, LLMClientToolChoice,
+    LLMClientUsageStatistics,
+    LLMEmbeddingClient, LLMEmbeddingRequest, LLMEmbeddingResponse, LLMType,
 };
 
+/// Maps the usage block openai reports to our own accounting format, the
+/// cached tokens live inside the prompt token details
+fn usage_statistics_from_openai(usage: &CompletionUsage) -> LLMClientUsageStatistics {
+    let mut usage_statistics = LLMClientUsageStatistics::new()
+        .set_input_tokens(usage.prompt_tokens)
+        .set_output_tokens(usage.completion_tokens);
+    if let Some(cached_tokens) = usage
+        .prompt_tokens_details
+        .as_ref()
+        .map(|details| details.cached_tokens)
+        .flatten()
+    {
+        usage_statistics = usage_statistics.set_cached_input_tokens(cached_tokens);
+    }
+    usage_statistics
+}
+
 enum OpenAIClientType {
     AzureClient(Client<AzureConfig>),
     OpenAIClient(Client<OpenAIConfig>),
@@ -59,11 +86,15 @@ impl OpenAIClient {
             .map(|message| {
                 let role = message.role();
                 match role {
-                    LLMClientRole::User => ChatCompletionRequestUserMessageArgs::default()
-                        .content(message.content().to_owned())
-                        .build()
-                        .map(|message| ChatCompletionRequestMessage::User(message))
-                        .map_err(|e| LLMClientError::OpenAPIError(e)),
+                    LLMClientRole::User => Self::user_message_content(message).and_then(
+                        |content| {
+                            ChatCompletionRequestUserMessageArgs::default()
+                                .content(content)
+                                .build()
+                                .map(|message| ChatCompletionRequestMessage::User(message))
+                                .map_err(|e| LLMClientError::OpenAPIError(e))
+                        },
+                    ),
                     // system messages for reasoning models are developer messages
                     LLMClientRole::System => ChatCompletionRequestDeveloperMessageArgs::default()
                         .content(message.content().to_owned())
@@ -105,6 +136,44 @@ impl OpenAIClient {
             .collect::<Result<Vec<ChatCompletionRequestMessage>, LLMClientError>>()
     }
 
+    /// Builds the content for a user message, plain text when there are no
+    /// images attached and an array of content parts when there are so that
+    /// pasted screenshots make it to the model
+    fn user_message_content(
+        message: &LLMClientMessage,
+    ) -> Result<ChatCompletionRequestUserMessageContent, LLMClientError> {
+        if message.images().is_empty() {
+            return Ok(message.content().to_owned().into());
+        }
+        let mut content_parts = vec![ChatCompletionRequestMessageContentPartTextArgs::default()
+            .text(message.content().to_owned())
+            .build()
+            .map(|part| ChatCompletionRequestUserMessageContentPart::Text(part))
+            .map_err(|e| LLMClientError::OpenAPIError(e))?];
+        for image in message.images() {
+            // url images pass through as-is, base64 images go over as a
+            // data url which is how the chat completions api expects them
+            let image_url = if image.r#type() == "url" {
+                image.data().to_owned()
+            } else {
+                format!("data:{};base64,{}", image.media(), image.data())
+            };
+            content_parts.push(
+                ChatCompletionRequestMessageContentPartImageArgs::default()
+                    .image_url(
+                        ImageUrlArgs::default()
+                            .url(image_url)
+                            .build()
+                            .map_err(|e| LLMClientError::OpenAPIError(e))?,
+                    )
+                    .build()
+                    .map(|part| ChatCompletionRequestUserMessageContentPart::ImageUrl(part))
+                    .map_err(|e| LLMClientError::OpenAPIError(e))?,
+            );
+        }
+        Ok(ChatCompletionRequestUserMessageContent::Array(content_parts))
+    }
+
     pub fn messages(
         &self,
         messages: &[LLMClientMessage],
@@ -114,11 +183,15 @@ impl OpenAIClient {
             .map(|message| {
                 let role = message.role();
                 match role {
-                    LLMClientRole::User => ChatCompletionRequestUserMessageArgs::default()
-                        .content(message.content().to_owned())
-                        .build()
-                        .map(|message| ChatCompletionRequestMessage::User(message))
-                        .map_err(|e| LLMClientError::OpenAPIError(e)),
+                    LLMClientRole::User => Self::user_message_content(message).and_then(
+                        |content| {
+                            ChatCompletionRequestUserMessageArgs::default()
+                                .content(content)
+                                .build()
+                                .map(|message| ChatCompletionRequestMessage::User(message))
+                                .map_err(|e| LLMClientError::OpenAPIError(e))
+                        },
+                    ),
                     LLMClientRole::System => ChatCompletionRequestSystemMessageArgs::default()
                         .content(message.content().to_owned())
                         .build()
@@ -187,16 +260,134 @@ impl OpenAIClient {
                 Ok(OpenAIClientType::OpenAIClient(Client::with_config(config)))
             }
             LLMProviderAPIKeys::OpenAIAzureConfig(azure_config) => {
+                // resolve the deployment for this model first, enterprises
+                // with several deployments map different model families to
+                // different deployment ids
+                let deployment_id = azure_config.deployment_id_for(llm_model);
+                let api_version = azure_config.api_version_for(llm_model);
                 let config = AzureConfig::new()
                     .with_api_base(azure_config.api_base)
                     .with_api_key(azure_config.api_key)
-                    .with_deployment_id(azure_config.deployment_id)
-                    .with_api_version(azure_config.api_version);
+                    .with_deployment_id(deployment_id)
+                    .with_api_version(api_version);
                 Ok(OpenAIClientType::AzureClient(Client::with_config(config)))
             }
             _ => Err(LLMClientError::WrongAPIKeyType),
         }
     }
+
+    /// Native function-calling version of stream_completion: sends the tool
+    /// schemas from the request and collects the tool calls the model makes.
+    /// Mirrors the shape of the anthropic client so the agent loop can treat
+    /// both the same way, the second part of the tuple is
+    /// (tool_name, (tool_use_id, serialized_json_arguments))
+    pub async fn stream_completion_with_tool(
+        &self,
+        api_key: LLMProviderAPIKeys,
+        request: LLMClientCompletionRequest,
+        _metadata: HashMap<String, String>,
+        sender: tokio::sync::mpsc::UnboundedSender<LLMClientCompletionResponse>,
+    ) -> Result<(String, Vec<(String, (String, String))>), LLMClientError> {
+        let llm_model = request.model();
+        let model = self
+            .model(llm_model)
+            .ok_or(LLMClientError::UnSupportedModel)?;
+        let messages = self.messages(request.messages())?;
+        let tools = request
+            .tools()
+            .iter()
+            .map(|tool| ChatCompletionTool {
+                r#type: ChatCompletionToolType::Function,
+                function: FunctionObject {
+                    name: tool.name().to_owned(),
+                    description: Some(tool.description().to_owned()),
+                    parameters: Some(tool.input_schema().clone()),
+                    strict: None,
+                },
+            })
+            .collect::<Vec<_>>();
+        let mut request_builder_args = CreateChatCompletionRequestArgs::default();
+        let mut request_builder = request_builder_args
+            .model(model.to_owned())
+            .messages(messages)
+            .temperature(request.temperature())
+            .stream(true);
+        if let Some(seed) = request.seed() {
+            request_builder = request_builder.seed(seed as i64);
+        }
+        if !tools.is_empty() {
+            request_builder = request_builder.tools(tools);
+        }
+        if let Some(tool_choice) = request.tool_choice() {
+            request_builder = request_builder.tool_choice(match tool_choice {
+                LLMClientToolChoice::Auto => ChatCompletionToolChoiceOption::Auto,
+                LLMClientToolChoice::Required => ChatCompletionToolChoiceOption::Required,
+                LLMClientToolChoice::Tool(name) => {
+                    ChatCompletionToolChoiceOption::Named(ChatCompletionNamedToolChoice {
+                        r#type: ChatCompletionToolType::Function,
+                        function: FunctionName {
+                            name: name.to_owned(),
+                        },
+                    })
+                }
+            });
+        }
+        let request = request_builder.build()?;
+        let mut stream = match self.generate_openai_client(api_key, llm_model)? {
+            OpenAIClientType::AzureClient(client) => client.chat().create_stream(request).await?,
+            OpenAIClientType::OpenAIClient(client) => client.chat().create_stream(request).await?,
+        };
+
+        let mut buffer = String::new();
+        // keyed by the stream index of the tool call so parallel tool calls
+        // accumulate their arguments separately
+        let mut tool_calls: BTreeMap<u32, (String, String, String)> = BTreeMap::new();
+        while let Some(response) = stream.next().await {
+            match response {
+                Ok(response) => {
+                    let Some(choice) = response.choices.get(0) else {
+                        continue;
+                    };
+                    if let Some(text) = choice.delta.content.as_ref() {
+                        buffer.push_str(text);
+                        if let Err(e) = sender.send(LLMClientCompletionResponse::new(
+                            buffer.to_owned(),
+                            Some(text.to_owned()),
+                            model.to_owned(),
+                        )) {
+                            error!("Failed to send completion response: {}", e);
+                            return Err(LLMClientError::SendError(e));
+                        }
+                    }
+                    for tool_call in choice.delta.tool_calls.as_deref().unwrap_or(&[]) {
+                        let entry = tool_calls.entry(tool_call.index).or_insert_with(|| {
+                            (String::new(), String::new(), String::new())
+                        });
+                        if let Some(id) = tool_call.id.as_ref() {
+                            entry.0 = id.to_owned();
+                        }
+                        if let Some(function) = tool_call.function.as_ref() {
+                            if let Some(name) = function.name.as_ref() {
+                                entry.1 = name.to_owned();
+                            }
+                            if let Some(arguments) = function.arguments.as_ref() {
+                                entry.2.push_str(arguments);
+                            }
+                        }
+                    }
+                }
+                Err(err) => {
+                    error!("OpenAI stream error: {:?}", err);
+                    break;
+                }
+            }
+        }
+        let tool_calls = tool_calls
+            .into_values()
+            .map(|(tool_use_id, tool_name, arguments)| (tool_name, (tool_use_id, arguments)))
+            .collect::<Vec<_>>();
+        Ok((buffer, tool_calls))
+    }
 }
 
 #[async_trait]
@@ -234,25 +425,65 @@ impl LLMClient for OpenAIClient {
         // o1 and o3-mini do not support streaming on the api
         if llm_model != &LLMType::O1 {
             request_builder = request_builder.stream(true);
+            // ask for the usage block on the final stream chunk so we can
+            // account for the tokens we burn
+            request_builder = request_builder.stream_options(ChatCompletionStreamOptions {
+                include_usage: true,
+            });
+        }
+        match request.response_format() {
+            Some(response_format) => {
+                // structured output, the api guarantees the reply is json
+                // matching the schema
+                request_builder.response_format(ResponseFormat::JsonSchema {
+                    json_schema: ResponseFormatJsonSchema {
+                        description: None,
+                        name: response_format.name().to_owned(),
+                        schema: Some(response_format.schema().clone()),
+                        strict: Some(true),
+                    },
+                });
+            }
+            None => {
+                // set response format to text
+                request_builder.response_format(ResponseFormat::Text);
+            }
         }
-        // set response format to text
-        request_builder.response_format(ResponseFormat::Text);
 
         // we cannot set temperature for o1 and o3-mini-high
         if llm_model != &LLMType::O1 && llm_model != &LLMType::O3MiniHigh {
             request_builder = request_builder.temperature(request.temperature());
         }
 
-        // if its o1 or o3-mini we should set reasoning_effort to high
+        // if its o1 or o3-mini we follow the requested reasoning effort and
+        // default to high
         if llm_model == &LLMType::O1 || llm_model == &LLMType::O3MiniHigh {
-            request_builder = request_builder.reasoning_effort(ReasoningEffort::High);
+            request_builder = request_builder.reasoning_effort(match request.reasoning_effort() {
+                Some(LLMClientReasoningEffort::Low) => ReasoningEffort::Low,
+                Some(LLMClientReasoningEffort::Medium) => ReasoningEffort::Medium,
+                Some(LLMClientReasoningEffort::High) | None => ReasoningEffort::High,
+            });
         }
 
+        if let Some(seed) = request.seed() {
+            request_builder = request_builder.seed(seed as i64);
+        }
         if let Some(frequency_penalty) = request.frequency_penalty() {
             request_builder = request_builder.frequency_penalty(frequency_penalty);
         }
+        let stop_patterns = request
+            .stop_words()
+            .map(|stop_words| stop_words.to_vec())
+            .unwrap_or_default();
+        let stop_after_patterns = request
+            .stop_after_patterns()
+            .map(|patterns| patterns.to_vec())
+            .unwrap_or_default();
         let request = request_builder.build()?;
-        let mut buffer = String::new();
+        let mut transcoder = AnswerStreamTranscoder::new(model.to_owned())
+            .with_stop_patterns(stop_patterns)
+            .with_stop_after_patterns(stop_after_patterns)
+            .with_thinking_extraction(llm_model.is_reasoning_model());
         let client = self.generate_openai_client(api_key, llm_model)?;
 
         // TODO(skcd): Bad code :| we are repeating too many things but this
@@ -269,25 +500,20 @@ impl LLMClient for OpenAIClient {
                 while let Some(response) = stream.next().await {
                     match response {
                         Ok(response) => {
+                            if let Some(usage) = response.usage.as_ref() {
+                                transcoder
+                                    .update_usage_statistics(usage_statistics_from_openai(usage));
+                            }
                             let delta = response
                                 .choices
                                 .get(0)
                                 .map(|choice| choice.delta.content.to_owned())
                                 .flatten()
                                 .unwrap_or("".to_owned());
-                            let _value = response
-                                .choices
-                                .get(0)
-                                .map(|choice| choice.delta.content.as_ref())
-                                .flatten();
-                            buffer.push_str(&delta);
-                            if let Err(e) = sender.send(LLMClientCompletionResponse::new(
-                                buffer.to_owned(),
-                                Some(delta),
-                                model.to_owned(),
-                            )) {
-                                error!("Failed to send completion response: {}", e);
-                                return Err(LLMClientError::SendError(e));
+                            if let StreamControl::Stop =
+                                transcoder.transcode_delta(&delta, &sender)
+                            {
+                                break;
                             }
                         }
                         Err(err) => {
@@ -300,6 +526,9 @@ impl LLMClient for OpenAIClient {
             OpenAIClientType::OpenAIClient(client) => {
                 if llm_model == &LLMType::O1 {
                     let completion = client.chat().create(request).await?;
+                    if let Some(usage) = completion.usage.as_ref() {
+                        transcoder.update_usage_statistics(usage_statistics_from_openai(usage));
+                    }
                     let response = completion
                         .choices
                         .get(0)
@@ -309,15 +538,7 @@ impl LLMClient for OpenAIClient {
                         .content
                         .as_ref()
                         .ok_or(LLMClientError::FailedToGetResponse)?;
-                    if let Err(e) = sender.send(LLMClientCompletionResponse::new(
-                        content.to_owned(),
-                        Some(content.to_owned()),
-                        model.to_owned(),
-                    )) {
-                        error!("Failed to send completion response: {}", e);
-                        return Err(LLMClientError::SendError(e));
-                    }
-                    buffer = content.to_owned();
+                    transcoder.transcode_delta(content, &sender);
                 } else {
                     if llm_model == &LLMType::O3MiniHigh {
                         debug!("o3-mini-high");
@@ -327,20 +548,22 @@ impl LLMClient for OpenAIClient {
                         debug!("OpenAI stream response: {:?}", &response);
                         match response {
                             Ok(response) => {
-                                let response = response
-                                    .choices
-                                    .get(0)
-                                    .ok_or(LLMClientError::FailedToGetResponse)?;
+                                if let Some(usage) = response.usage.as_ref() {
+                                    transcoder.update_usage_statistics(
+                                        usage_statistics_from_openai(usage),
+                                    );
+                                }
+                                // the usage only chunk at the end of the
+                                // stream comes with no choices
+                                let Some(response) = response.choices.get(0) else {
+                                    continue;
+                                };
                                 let text = response.delta.content.to_owned();
                                 if let Some(text) = text {
-                                    buffer.push_str(&text);
-                                    if let Err(e) = sender.send(LLMClientCompletionResponse::new(
-                                        buffer.to_owned(),
-                                        Some(text),
-                                        model.to_owned(),
-                                    )) {
-                                        error!("Failed to send completion response: {}", e);
-                                        return Err(LLMClientError::SendError(e));
+                                    if let StreamControl::Stop =
+                                        transcoder.transcode_delta(&text, &sender)
+                                    {
+                                        break;
                                     }
                                 }
                             }
@@ -354,11 +577,7 @@ impl LLMClient for OpenAIClient {
             }
         }
 
-        Ok(LLMClientCompletionResponse::new(
-            buffer,
-            None,
-            model.to_owned(),
-        ))
+        Ok(transcoder.finish(&sender))
     }
 
     async fn completion(
@@ -380,3 +599,40 @@ impl LLMClient for OpenAIClient {
         Err(LLMClientError::OpenAIDoesNotSupportCompletion)
     }
 }
+
+#[async_trait]
+impl LLMEmbeddingClient for OpenAIClient {
+    fn client(&self) -> &crate::provider::LLMProvider {
+        &crate::provider::LLMProvider::OpenAI
+    }
+
+    async fn generate_embeddings(
+        &self,
+        api_key: LLMProviderAPIKeys,
+        request: LLMEmbeddingRequest,
+    ) -> Result<LLMEmbeddingResponse, LLMClientError> {
+        let embedding_request = CreateEmbeddingRequestArgs::default()
+            .model(request.model())
+            .input(request.documents().to_vec())
+            .build()?;
+        // azure keys route through the azure client, plain openai and the
+        // compatible proxies go through the stock one
+        let response = match self.generate_openai_client(api_key, &LLMType::Gpt4)? {
+            OpenAIClientType::AzureClient(client) => {
+                client.embeddings().create(embedding_request).await?
+            }
+            OpenAIClientType::OpenAIClient(client) => {
+                client.embeddings().create(embedding_request).await?
+            }
+        };
+        let mut embeddings = response.data;
+        // the api does not guarantee ordering, the index field does
+        embeddings.sort_by_key(|embedding| embedding.index);
+        Ok(LLMEmbeddingResponse::new(
+            embeddings
+                .into_iter()
+                .map(|embedding| embedding.embedding)
+                .collect::<Vec<_>>(),
+        ))
+    }
+}
@@ -0,0 +1,414 @@
+//! Provider-agnostic transcoding of streamed completion deltas
+//!
+//! Every client used to keep its own ad-hoc answer buffer next to its wire
+//! format, which meant behaviors like stopping on a pattern or noticing the
+//! receiver going away existed in some clients and not in others. The
+//! transcoder owns the accumulated answer and the delivery to the caller,
+//! the clients just feed it the deltas their wire format produces (OpenAI
+//! SSE chunks, Anthropic events, Ollama json lines)
+
+use tokio::sync::mpsc::UnboundedSender;
+
+use super::types::{LLMClientCompletionResponse, LLMClientUsageStatistics};
+
+/// an xml tag name never gets longer than this, anything bigger is a stray
+/// < in prose or code and flushes through
+const MAX_PARTIAL_TAG_LENGTH: usize = 64;
+
+/// What the client driving the wire format should do after handing a delta
+/// to the transcoder
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamControl {
+    /// keep pulling deltas from the provider
+    Continue,
+    /// stop the request, either a stop pattern matched or the receiver went
+    /// away and nobody is listening anymore
+    Stop,
+}
+
+/// Accumulates the answer for one streamed completion and delivers the
+/// deltas to the caller, independent of which provider produced them
+pub struct AnswerStreamTranscoder {
+    model: String,
+    answer: String,
+    /// tail of the stream which looks like an unfinished xml tag, held back
+    /// until the closing > arrives so downstream parsers never see a tag
+    /// split across deltas
+    held_back: String,
+    /// when the answer hits one of these the stream stops and the answer
+    /// gets truncated right before the pattern
+    stop_patterns: Vec<String>,
+    /// like stop_patterns but the pattern stays in the answer, for closing
+    /// tags which the parsers downstream still need to see
+    stop_after_patterns: Vec<String>,
+    /// chain of thought of a reasoning model, routed here instead of the
+    /// answer when thinking extraction is on
+    thinking: String,
+    /// true while the stream is inside a <think> block
+    in_thinking: bool,
+    extract_thinking: bool,
+    usage_statistics: LLMClientUsageStatistics,
+    cancelled: bool,
+}
+
+impl AnswerStreamTranscoder {
+    pub fn new(model: String) -> Self {
+        Self {
+            model,
+            answer: "".to_owned(),
+            held_back: "".to_owned(),
+            stop_patterns: vec![],
+            stop_after_patterns: vec![],
+            thinking: "".to_owned(),
+            in_thinking: false,
+            extract_thinking: false,
+            usage_statistics: LLMClientUsageStatistics::new(),
+            cancelled: false,
+        }
+    }
+
+    pub fn with_stop_patterns(mut self, stop_patterns: Vec<String>) -> Self {
+        self.stop_patterns = stop_patterns;
+        self
+    }
+
+    /// stop the stream once one of these patterns has been emitted in full,
+    /// generating past the closing tag of a structured reply is wasted cost
+    pub fn with_stop_after_patterns(mut self, stop_after_patterns: Vec<String>) -> Self {
+        self.stop_after_patterns = stop_after_patterns;
+        self
+    }
+
+    /// reasoning models emit their chain of thought in <think> blocks, with
+    /// extraction on those blocks end up on the thinking field of the
+    /// responses instead of the answer
+    pub fn with_thinking_extraction(mut self, extract_thinking: bool) -> Self {
+        self.extract_thinking = extract_thinking;
+        self
+    }
+
+    pub fn answer_up_until_now(&self) -> &str {
+        &self.answer
+    }
+
+    /// true when the receiver went away mid-stream, the answer holds
+    /// whatever had been generated up to that point
+    pub fn was_cancelled(&self) -> bool {
+        self.cancelled
+    }
+
+    /// latest usage numbers from the provider, these ride along on every
+    /// delta and on the final response
+    pub fn update_usage_statistics(&mut self, usage_statistics: LLMClientUsageStatistics) {
+        self.usage_statistics
+            .set_usage_statistics(usage_statistics);
+    }
+
+    /// Folds one wire-format delta into the answer and forwards it to the
+    /// caller, the client should stop pulling from the provider when this
+    /// returns Stop
+    pub fn transcode_delta(
+        &mut self,
+        delta: &str,
+        sender: &UnboundedSender<LLMClientCompletionResponse>,
+    ) -> StreamControl {
+        let combined = format!("{}{}", self.held_back, delta);
+        let (emit, held_tag) = split_partial_tag(&combined);
+        // a stop pattern straddling two deltas must never leak its prefix
+        // to the caller, so that tail waits with the partial tags
+        let (emit, held_pattern) = split_partial_stop_pattern(emit, &self.stop_patterns);
+        let emit = emit.to_owned();
+        self.held_back = format!("{}{}", held_pattern, held_tag);
+        if emit.is_empty() {
+            return StreamControl::Continue;
+        }
+        self.emit(emit, sender)
+    }
+
+    /// Flushes anything held back and returns the final response, the same
+    /// shape every client hands back from stream_completion
+    pub fn finish(
+        mut self,
+        sender: &UnboundedSender<LLMClientCompletionResponse>,
+    ) -> LLMClientCompletionResponse {
+        if !self.held_back.is_empty() && !self.cancelled {
+            // the tag never completed, it was a stray < after all
+            let held_back = std::mem::take(&mut self.held_back);
+            self.emit(held_back, sender);
+        }
+        let mut response = LLMClientCompletionResponse::new(self.answer, None, self.model)
+            .set_usage_statistics(self.usage_statistics);
+        if !self.thinking.is_empty() {
+            response = response.set_thinking(self.thinking);
+        }
+        response
+    }
+
+    fn emit(
+        &mut self,
+        delta: String,
+        sender: &UnboundedSender<LLMClientCompletionResponse>,
+    ) -> StreamControl {
+        let thinking_length_before = self.thinking.len();
+        let mut delta = if self.extract_thinking {
+            self.route_thinking(&delta)
+        } else {
+            delta
+        };
+        let answer_length_before = self.answer.len();
+        self.answer.push_str(&delta);
+        let mut control = StreamControl::Continue;
+        let pattern_start = self
+            .stop_patterns
+            .iter()
+            .filter_map(|pattern| self.answer.find(pattern.as_str()))
+            .min();
+        if let Some(pattern_start) = pattern_start {
+            self.answer.truncate(pattern_start);
+            delta.truncate(pattern_start.saturating_sub(answer_length_before));
+            control = StreamControl::Stop;
+        }
+        let pattern_end = self
+            .stop_after_patterns
+            .iter()
+            .filter_map(|pattern| {
+                self.answer
+                    .find(pattern.as_str())
+                    .map(|pattern_start| pattern_start + pattern.len())
+            })
+            .min();
+        if let Some(pattern_end) = pattern_end {
+            self.answer.truncate(pattern_end);
+            delta.truncate(pattern_end.saturating_sub(answer_length_before));
+            control = StreamControl::Stop;
+        }
+        if delta.is_empty() && self.thinking.len() == thinking_length_before {
+            return control;
+        }
+        let mut response = LLMClientCompletionResponse::new(
+            self.answer.to_owned(),
+            (!delta.is_empty()).then_some(delta),
+            self.model.to_owned(),
+        )
+        .set_usage_statistics(self.usage_statistics.clone());
+        if !self.thinking.is_empty() {
+            response = response.set_thinking(self.thinking.to_owned());
+        }
+        if sender.send(response).is_err() {
+            // the receiver hung up, stop the request instead of generating
+            // tokens nobody is going to read
+            self.cancelled = true;
+            return StreamControl::Stop;
+        }
+        control
+    }
+
+    /// Walks one emitted chunk and routes the <think> blocks into the
+    /// thinking buffer, returns what remains of the chunk for the answer.
+    /// The partial tag holdback guarantees the tags arrive in one piece
+    fn route_thinking(&mut self, text: &str) -> String {
+        let mut answer_delta = "".to_owned();
+        let mut remaining = text;
+        loop {
+            if self.in_thinking {
+                match remaining.find("</think>") {
+                    Some(tag_start) => {
+                        self.thinking.push_str(&remaining[..tag_start]);
+                        remaining = &remaining[tag_start + "</think>".len()..];
+                        self.in_thinking = false;
+                    }
+                    None => {
+                        self.thinking.push_str(remaining);
+                        break;
+                    }
+                }
+            } else {
+                match remaining.find("<think>") {
+                    Some(tag_start) => {
+                        answer_delta.push_str(&remaining[..tag_start]);
+                        remaining = &remaining[tag_start + "<think>".len()..];
+                        self.in_thinking = true;
+                    }
+                    None => {
+                        answer_delta.push_str(remaining);
+                        break;
+                    }
+                }
+            }
+        }
+        answer_delta
+    }
+}
+
+/// Splits off the longest suffix which is a proper prefix of one of the
+/// stop patterns, it gets held back until the next delta decides whether
+/// the pattern completes
+fn split_partial_stop_pattern<'a>(
+    emit: &'a str,
+    stop_patterns: &[String],
+) -> (&'a str, &'a str) {
+    let longest_prefix = stop_patterns
+        .iter()
+        .flat_map(|pattern| {
+            (1..pattern.len())
+                .filter(|prefix_length| pattern.is_char_boundary(*prefix_length))
+                .filter_map(|prefix_length| {
+                    let prefix = &pattern[..prefix_length];
+                    emit.ends_with(prefix).then_some(prefix_length)
+                })
+        })
+        .max()
+        .unwrap_or(0);
+    emit.split_at(emit.len() - longest_prefix)
+}
+
+/// Splits off a trailing unfinished xml tag so it can be held back until
+/// the rest of it arrives
+fn split_partial_tag(combined: &str) -> (&str, &str) {
+    let Some(tag_start) = combined.rfind('<') else {
+        return (combined, "");
+    };
+    let tail = &combined[tag_start..];
+    if tail.contains('>') || tail.len() > MAX_PARTIAL_TAG_LENGTH {
+        return (combined, "");
+    }
+    // a tag opens with a letter or / right after the <, anything else is a
+    // stray < in prose or code
+    if tail
+        .chars()
+        .skip(1)
+        .enumerate()
+        .all(|(index, character)| match index {
+            0 => character.is_ascii_alphabetic() || character == '/',
+            _ => {
+                character.is_ascii_alphanumeric()
+                    || character == '_'
+                    || character == '-'
+                    || character == '/'
+            }
+        })
+    {
+        combined.split_at(tag_start)
+    } else {
+        (combined, "")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{split_partial_tag, AnswerStreamTranscoder, StreamControl};
+
+    fn channel() -> (
+        tokio::sync::mpsc::UnboundedSender<super::LLMClientCompletionResponse>,
+        tokio::sync::mpsc::UnboundedReceiver<super::LLMClientCompletionResponse>,
+    ) {
+        tokio::sync::mpsc::unbounded_channel()
+    }
+
+    #[test]
+    fn test_stop_pattern_truncates_the_answer() {
+        let (sender, mut receiver) = channel();
+        let mut transcoder = AnswerStreamTranscoder::new("test-model".to_owned())
+            .with_stop_patterns(vec!["STOP".to_owned()]);
+        assert_eq!(
+            transcoder.transcode_delta("keep this ", &sender),
+            StreamControl::Continue
+        );
+        // the pattern straddles two deltas and still matches
+        assert_eq!(transcoder.transcode_delta("ST", &sender), StreamControl::Continue);
+        assert_eq!(
+            transcoder.transcode_delta("OP and drop this", &sender),
+            StreamControl::Stop
+        );
+        assert_eq!(transcoder.finish(&sender).answer_up_until_now(), "keep this ");
+        let mut last_answer = "".to_owned();
+        while let Ok(response) = receiver.try_recv() {
+            last_answer = response.answer_up_until_now().to_owned();
+        }
+        assert_eq!(last_answer, "keep this ");
+    }
+
+    #[test]
+    fn test_partial_xml_tag_is_held_back() {
+        let (sender, mut receiver) = channel();
+        let mut transcoder = AnswerStreamTranscoder::new("test-model".to_owned());
+        transcoder.transcode_delta("before <th", &sender);
+        // the split tag never reaches the caller
+        assert_eq!(
+            receiver.try_recv().expect("delta to arrive").delta(),
+            Some("before ")
+        );
+        transcoder.transcode_delta("inking>after", &sender);
+        assert_eq!(
+            receiver.try_recv().expect("delta to arrive").delta(),
+            Some("<thinking>after")
+        );
+        assert_eq!(
+            transcoder.finish(&sender).answer_up_until_now(),
+            "before <thinking>after"
+        );
+    }
+
+    #[test]
+    fn test_closed_receiver_cancels_the_stream() {
+        let (sender, receiver) = channel();
+        drop(receiver);
+        let mut transcoder = AnswerStreamTranscoder::new("test-model".to_owned());
+        assert_eq!(
+            transcoder.transcode_delta("some answer", &sender),
+            StreamControl::Stop
+        );
+        assert!(transcoder.was_cancelled());
+        // the answer generated before the cancellation survives
+        assert_eq!(transcoder.finish(&sender).answer_up_until_now(), "some answer");
+    }
+
+    #[test]
+    fn test_stop_after_pattern_keeps_the_closing_tag() {
+        let (sender, _receiver) = channel();
+        let mut transcoder = AnswerStreamTranscoder::new("test-model".to_owned())
+            .with_stop_after_patterns(vec!["</response>".to_owned()]);
+        assert_eq!(
+            transcoder.transcode_delta("<response>done</resp", &sender),
+            StreamControl::Continue
+        );
+        // the closing tag completes on the next delta and the stream stops
+        // right after it
+        assert_eq!(
+            transcoder.transcode_delta("onse> trailing prose", &sender),
+            StreamControl::Stop
+        );
+        assert_eq!(
+            transcoder.finish(&sender).answer_up_until_now(),
+            "<response>done</response>"
+        );
+    }
+
+    #[test]
+    fn test_think_blocks_route_into_the_thinking_field() {
+        let (sender, mut receiver) = channel();
+        let mut transcoder =
+            AnswerStreamTranscoder::new("test-model".to_owned()).with_thinking_extraction(true);
+        transcoder.transcode_delta("<think>ponder", &sender);
+        // even the tag split across deltas stays out of the answer
+        transcoder.transcode_delta(" more</th", &sender);
+        transcoder.transcode_delta("ink>the answer", &sender);
+        let response = transcoder.finish(&sender);
+        assert_eq!(response.answer_up_until_now(), "the answer");
+        assert_eq!(response.thinking(), Some("ponder more"));
+        // the streamed responses carry the thinking as it grows
+        let first = receiver.try_recv().expect("delta to arrive");
+        assert_eq!(first.thinking(), Some("ponder"));
+        assert_eq!(first.delta(), None);
+    }
+
+    #[test]
+    fn test_split_partial_tag_ignores_stray_angle_brackets() {
+        // comparisons and closed tags flush straight through
+        assert_eq!(split_partial_tag("a < b"), ("a < b", ""));
+        assert_eq!(split_partial_tag("<done>"), ("<done>", ""));
+        // open tags and a bare trailing < get held back
+        assert_eq!(split_partial_tag("text <reply"), ("text ", "<reply"));
+        assert_eq!(split_partial_tag("text <"), ("text ", "<"));
+    }
+}
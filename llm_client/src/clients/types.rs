@@ -177,6 +177,25 @@ impl LLMType {
         matches!(self, LLMType::O1Preview | LLMType::O1Mini)
     }
 
+    /// reasoning models think before they answer, some of them surface the
+    /// chain of thought as <think> blocks on the stream
+    pub fn is_reasoning_model(&self) -> bool {
+        matches!(
+            self,
+            LLMType::O1Preview
+                | LLMType::O1Mini
+                | LLMType::O1
+                | LLMType::O3MiniHigh
+                | LLMType::DeepSeekR1
+        )
+    }
+
+    /// false for the reasoning models which reject system messages, their
+    /// instructions have to ride along in the first user message instead
+    pub fn supports_system_message(&self) -> bool {
+        !matches!(self, LLMType::DeepSeekR1)
+    }
+
     pub fn is_custom(&self) -> bool {
         matches!(self, LLMType::Custom(_))
     }
@@ -664,6 +683,126 @@ pub struct LLMClientCompletionRequest {
     frequency_penalty: Option<f32>,
     stop_words: Option<Vec<String>>,
     max_tokens: Option<usize>,
+    tools: Vec<LLMClientTool>,
+    tool_choice: Option<LLMClientToolChoice>,
+    response_format: Option<LLMClientResponseFormat>,
+    reasoning_effort: Option<LLMClientReasoningEffort>,
+    stop_after_patterns: Option<Vec<String>>,
+    /// fixed sampling seed for reproducible runs, only honoured by the
+    /// providers which support seeding
+    seed: Option<u64>,
+}
+
+/// A tool the model can call natively, the input schema is the json-schema
+/// describing the arguments the tool accepts. The clients translate this
+/// into whatever shape their provider wants
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct LLMClientTool {
+    name: String,
+    description: String,
+    input_schema: serde_json::Value,
+}
+
+impl LLMClientTool {
+    pub fn new(name: String, description: String, input_schema: serde_json::Value) -> Self {
+        Self {
+            name,
+            description,
+            input_schema,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    pub fn input_schema(&self) -> &serde_json::Value {
+        &self.input_schema
+    }
+}
+
+/// How the model should pick between replying with text and calling the
+/// tools it was given
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum LLMClientToolChoice {
+    /// the model decides between replying and calling a tool
+    Auto,
+    /// the model has to call some tool
+    Required,
+    /// the model has to call this exact tool
+    Tool(String),
+}
+
+/// How much thinking a reasoning model should do before answering, maps to
+/// reasoning_effort on the providers which support it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum LLMClientReasoningEffort {
+    Low,
+    Medium,
+    High,
+}
+
+/// Structured output enforcement for a request. With a schema attached the
+/// clients which support it force the model to emit json matching it
+/// (OpenAI json_schema mode, anthropic rides on a forced tool call),
+/// providers without support just ignore it and we rely on validation
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct LLMClientResponseFormat {
+    /// name for the schema, the providers surface this in their dashboards
+    name: String,
+    /// json-schema the response body has to validate against
+    schema: serde_json::Value,
+}
+
+impl LLMClientResponseFormat {
+    pub fn new(name: String, schema: serde_json::Value) -> Self {
+        Self { name, schema }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn schema(&self) -> &serde_json::Value {
+        &self.schema
+    }
+
+    /// Checks a raw model response against the schema. This is not a full
+    /// json-schema implementation, it covers what our schemas actually use:
+    /// the top-level type and the required properties being present
+    pub fn validate(&self, raw_response: &str) -> Result<serde_json::Value, String> {
+        let value: serde_json::Value = serde_json::from_str(raw_response.trim())
+            .map_err(|e| format!("the response is not valid json: {}", e))?;
+        if let Some(expected_type) = self.schema.get("type").and_then(|value| value.as_str()) {
+            let type_matches = match expected_type {
+                "object" => value.is_object(),
+                "array" => value.is_array(),
+                "string" => value.is_string(),
+                "number" => value.is_number(),
+                "integer" => value.is_i64() || value.is_u64(),
+                "boolean" => value.is_boolean(),
+                _ => true,
+            };
+            if !type_matches {
+                return Err(format!(
+                    "expected the response to be of type {}",
+                    expected_type
+                ));
+            }
+        }
+        if let Some(required) = self.schema.get("required").and_then(|value| value.as_array()) {
+            for key in required.iter().filter_map(|key| key.as_str()) {
+                if value.get(key).is_none() {
+                    return Err(format!("missing required property: {}", key));
+                }
+            }
+        }
+        Ok(value)
+    }
 }
 
 #[derive(Clone)]
@@ -674,6 +813,9 @@ pub struct LLMClientCompletionStringRequest {
     frequency_penalty: Option<f32>,
     stop_words: Option<Vec<String>>,
     max_tokens: Option<usize>,
+    /// fixed sampling seed for reproducible runs, only honoured by the
+    /// providers which support seeding
+    seed: Option<u64>,
 }
 
 impl LLMClientCompletionStringRequest {
@@ -690,14 +832,35 @@ impl LLMClientCompletionStringRequest {
             frequency_penalty,
             stop_words: None,
             max_tokens: None,
+            seed: None,
         }
     }
 
+    pub fn set_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    pub fn set_temperature(mut self, temperature: f32) -> Self {
+        self.temperature = temperature;
+        self
+    }
+
     pub fn set_stop_words(mut self, stop_words: Vec<String>) -> Self {
         self.stop_words = Some(stop_words);
         self
     }
 
+    pub fn set_generation_profile(mut self, profile: GenerationProfile) -> Self {
+        self.temperature = profile.temperature();
+        self.frequency_penalty = profile.frequency_penalty();
+        self
+    }
+
     pub fn model(&self) -> &LLMType {
         &self.model
     }
@@ -728,6 +891,34 @@ impl LLMClientCompletionStringRequest {
     }
 }
 
+/// Named generation profiles so callers state their intent instead of
+/// hard-coding temperatures at every call site. The profile picks the
+/// sampling parameters consistently across the brokers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GenerationProfile {
+    /// code edits and anything else which ends up applied to files
+    Deterministic,
+    /// brainstorming, ideation and exploratory questions
+    Creative,
+    /// quick filtering and classification passes where latency matters
+    Fast,
+}
+
+impl GenerationProfile {
+    pub fn temperature(&self) -> f32 {
+        match self {
+            GenerationProfile::Deterministic => 0.0,
+            GenerationProfile::Creative => 0.7,
+            GenerationProfile::Fast => 0.2,
+        }
+    }
+
+    pub fn frequency_penalty(&self) -> Option<f32> {
+        None
+    }
+}
+
 impl LLMClientCompletionRequest {
     pub fn new(
         model: LLMType,
@@ -742,6 +933,12 @@ impl LLMClientCompletionRequest {
             frequency_penalty,
             stop_words: None,
             max_tokens: None,
+            tools: vec![],
+            tool_choice: None,
+            response_format: None,
+            reasoning_effort: None,
+            stop_after_patterns: None,
+            seed: None,
         }
     }
 
@@ -797,11 +994,41 @@ impl LLMClientCompletionRequest {
         Self::new(model, messages, 0.0, None)
     }
 
+    /// Builds a request with the sampling parameters taken from a generation
+    /// profile instead of a hard-coded temperature
+    pub fn from_messages_with_profile(
+        profile: GenerationProfile,
+        messages: Vec<LLMClientMessage>,
+        model: LLMType,
+    ) -> Self {
+        Self::new(
+            model,
+            messages,
+            profile.temperature(),
+            profile.frequency_penalty(),
+        )
+    }
+
     pub fn set_temperature(mut self, temperature: f32) -> Self {
         self.temperature = temperature;
         self
     }
 
+    pub fn set_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    pub fn set_generation_profile(mut self, profile: GenerationProfile) -> Self {
+        self.temperature = profile.temperature();
+        self.frequency_penalty = profile.frequency_penalty();
+        self
+    }
+
     pub fn messages(&self) -> &[LLMClientMessage] {
         self.messages.as_slice()
     }
@@ -830,6 +1057,61 @@ impl LLMClientCompletionRequest {
     pub fn get_max_tokens(&self) -> Option<usize> {
         self.max_tokens
     }
+
+    /// Turns on native function-calling for this request, the clients which
+    /// support it send these schemas instead of relying on the xml-ish
+    /// tool prompting
+    pub fn set_tools(mut self, tools: Vec<LLMClientTool>) -> Self {
+        self.tools = tools;
+        self
+    }
+
+    pub fn set_tool_choice(mut self, tool_choice: LLMClientToolChoice) -> Self {
+        self.tool_choice = Some(tool_choice);
+        self
+    }
+
+    pub fn tools(&self) -> &[LLMClientTool] {
+        self.tools.as_slice()
+    }
+
+    pub fn tool_choice(&self) -> Option<&LLMClientToolChoice> {
+        self.tool_choice.as_ref()
+    }
+
+    /// Asks for structured output, the response has to be json matching the
+    /// attached schema on providers which can enforce it
+    pub fn set_response_format(mut self, response_format: LLMClientResponseFormat) -> Self {
+        self.response_format = Some(response_format);
+        self
+    }
+
+    pub fn response_format(&self) -> Option<&LLMClientResponseFormat> {
+        self.response_format.as_ref()
+    }
+
+    /// hint for how long a reasoning model should think, providers which do
+    /// not support it ignore the hint
+    pub fn set_reasoning_effort(mut self, reasoning_effort: LLMClientReasoningEffort) -> Self {
+        self.reasoning_effort = Some(reasoning_effort);
+        self
+    }
+
+    pub fn reasoning_effort(&self) -> Option<LLMClientReasoningEffort> {
+        self.reasoning_effort
+    }
+
+    /// abort the stream once one of these patterns has been generated in
+    /// full, unlike stop words the pattern stays part of the answer so the
+    /// parsers which need the closing tag still see it
+    pub fn set_stop_after_patterns(mut self, stop_after_patterns: Vec<String>) -> Self {
+        self.stop_after_patterns = Some(stop_after_patterns);
+        self
+    }
+
+    pub fn stop_after_patterns(&self) -> Option<&[String]> {
+        self.stop_after_patterns.as_deref()
+    }
 }
 
 #[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
@@ -900,6 +1182,9 @@ pub struct LLMClientCompletionResponse {
     delta: Option<String>,
     model: String,
     usage_statistics: LLMClientUsageStatistics,
+    /// chain of thought of a reasoning model, extracted from the stream so
+    /// the UI can show it distinctly from the answer
+    thinking: Option<String>,
 }
 
 impl LLMClientCompletionResponse {
@@ -909,6 +1194,7 @@ impl LLMClientCompletionResponse {
             delta,
             model,
             usage_statistics: LLMClientUsageStatistics::new(),
+            thinking: None,
         }
     }
 
@@ -929,6 +1215,15 @@ impl LLMClientCompletionResponse {
         self.delta.as_deref()
     }
 
+    pub fn set_thinking(mut self, thinking: String) -> Self {
+        self.thinking = Some(thinking);
+        self
+    }
+
+    pub fn thinking(&self) -> Option<&str> {
+        self.thinking.as_deref()
+    }
+
     pub fn model(&self) -> &str {
         &self.model
     }
@@ -986,6 +1281,15 @@ pub enum LLMClientError {
 
     #[error("Unauthorized access to API")]
     UnauthorizedAccess,
+
+    #[error("Rate limit exceeded")]
+    RateLimitExceeded,
+
+    #[error("Context window exceeded: {0}")]
+    ContextWindowExceeded(String),
+
+    #[error("Response failed schema validation: {0}")]
+    SchemaValidationFailed(String),
 }
 
 #[async_trait]
@@ -1013,8 +1317,58 @@ pub trait LLMClient {
     ) -> Result<String, LLMClientError>;
 }
 
+/// Embedding models do not map cleanly on LLMType so we keep the model name
+/// as a free-form string here (text-embedding-3-small, nomic-embed-text, ...)
+#[derive(Clone, Debug)]
+pub struct LLMEmbeddingRequest {
+    model: String,
+    documents: Vec<String>,
+}
+
+impl LLMEmbeddingRequest {
+    pub fn new(model: String, documents: Vec<String>) -> Self {
+        Self { model, documents }
+    }
+
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    pub fn documents(&self) -> &[String] {
+        &self.documents
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct LLMEmbeddingResponse {
+    embeddings: Vec<Vec<f32>>,
+}
+
+impl LLMEmbeddingResponse {
+    pub fn new(embeddings: Vec<Vec<f32>>) -> Self {
+        Self { embeddings }
+    }
+
+    /// One embedding per input document, in the order they were sent
+    pub fn embeddings(&self) -> &[Vec<f32>] {
+        &self.embeddings
+    }
+}
+
+#[async_trait]
+pub trait LLMEmbeddingClient {
+    fn client(&self) -> &LLMProvider;
+
+    async fn generate_embeddings(
+        &self,
+        api_key: LLMProviderAPIKeys,
+        request: LLMEmbeddingRequest,
+    ) -> Result<LLMEmbeddingResponse, LLMClientError>;
+}
+
 #[cfg(test)]
 mod tests {
+    use super::LLMClientResponseFormat;
     use super::LLMType;
 
     #[test]
@@ -1023,4 +1377,31 @@ mod tests {
         let str_llm_type = serde_json::to_string(&llm_type).expect("to work");
         assert_eq!(str_llm_type, "");
     }
+
+    #[test]
+    fn test_response_format_validation() {
+        let response_format = LLMClientResponseFormat::new(
+            "plan_step".to_owned(),
+            serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "title": {"type": "string"},
+                    "files_to_edit": {"type": "array"},
+                },
+                "required": ["title", "files_to_edit"],
+            }),
+        );
+        assert!(response_format
+            .validate(r#"{"title": "add tests", "files_to_edit": ["src/lib.rs"]}"#)
+            .is_ok());
+        // missing a required property
+        let error = response_format
+            .validate(r#"{"title": "add tests"}"#)
+            .expect_err("validation to fail");
+        assert!(error.contains("files_to_edit"));
+        // not json at all
+        assert!(response_format.validate("not json").is_err());
+        // wrong top-level type
+        assert!(response_format.validate(r#"["a", "b"]"#).is_err());
+    }
 }
\ No newline at end of file
@@ -1,7 +1,10 @@
 pub mod broker;
+pub mod capabilities;
+pub mod capture;
 pub mod clients;
 pub mod config;
 pub mod format;
+pub mod metrics;
 pub mod provider;
 mod reporting;
 pub mod tokenizer;
@@ -0,0 +1,162 @@
+//! Timing metrics over LLM requests. The broker records time-to-first-token
+//! and throughput for every streamed completion and aggregates them per
+//! model and provider, so users can compare how the models they configured
+//! actually perform on their machine and network
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Aggregated latency numbers for one model on one provider
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ModelLatencyStats {
+    pub model: String,
+    pub provider: String,
+    /// completed requests which went into these averages
+    pub requests: u64,
+    /// running average of the time until the first token arrived, missing
+    /// when no request produced any tokens yet
+    pub average_time_to_first_token_ms: Option<f64>,
+    /// running average of output tokens per second over the whole request
+    pub average_tokens_per_second: Option<f64>,
+}
+
+#[derive(Default)]
+struct ModelLatencyAggregate {
+    requests: u64,
+    first_token_samples: u64,
+    first_token_total_ms: f64,
+    throughput_samples: u64,
+    throughput_total: f64,
+}
+
+/// Records per-request timings and aggregates them keyed by model and
+/// provider, everything lives in memory and resets with the process
+#[derive(Default)]
+pub struct LLMMetricsRecorder {
+    aggregates: Mutex<HashMap<(String, String), ModelLatencyAggregate>>,
+}
+
+impl LLMMetricsRecorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Folds one finished request into the aggregates. Output tokens come
+    /// from the provider usage block when available, callers fall back to
+    /// an estimate from the answer length otherwise
+    pub fn record_request(
+        &self,
+        model: &str,
+        provider: &str,
+        time_to_first_token: Option<Duration>,
+        output_tokens: Option<u64>,
+        total_duration: Duration,
+    ) {
+        let Ok(mut aggregates) = self.aggregates.lock() else {
+            return;
+        };
+        let aggregate = aggregates
+            .entry((model.to_owned(), provider.to_owned()))
+            .or_default();
+        aggregate.requests = aggregate.requests + 1;
+        if let Some(time_to_first_token) = time_to_first_token {
+            aggregate.first_token_samples = aggregate.first_token_samples + 1;
+            aggregate.first_token_total_ms =
+                aggregate.first_token_total_ms + time_to_first_token.as_secs_f64() * 1000.0;
+        }
+        if let Some(output_tokens) = output_tokens {
+            let seconds = total_duration.as_secs_f64();
+            if seconds > 0.0 {
+                aggregate.throughput_samples = aggregate.throughput_samples + 1;
+                aggregate.throughput_total =
+                    aggregate.throughput_total + output_tokens as f64 / seconds;
+            }
+        }
+    }
+
+    /// Snapshot of the aggregates, sorted by model and provider so the
+    /// output is stable
+    pub fn stats(&self) -> Vec<ModelLatencyStats> {
+        let Ok(aggregates) = self.aggregates.lock() else {
+            return vec![];
+        };
+        let mut stats = aggregates
+            .iter()
+            .map(|((model, provider), aggregate)| ModelLatencyStats {
+                model: model.to_owned(),
+                provider: provider.to_owned(),
+                requests: aggregate.requests,
+                average_time_to_first_token_ms: (aggregate.first_token_samples > 0).then(|| {
+                    aggregate.first_token_total_ms / aggregate.first_token_samples as f64
+                }),
+                average_tokens_per_second: (aggregate.throughput_samples > 0)
+                    .then(|| aggregate.throughput_total / aggregate.throughput_samples as f64),
+            })
+            .collect::<Vec<_>>();
+        stats.sort_by(|left, right| {
+            (left.model.as_str(), left.provider.as_str())
+                .cmp(&(right.model.as_str(), right.provider.as_str()))
+        });
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LLMMetricsRecorder;
+    use std::time::Duration;
+
+    #[test]
+    fn test_averages_accumulate_per_model() {
+        let recorder = LLMMetricsRecorder::new();
+        recorder.record_request(
+            "gpt-4o",
+            "OpenAI",
+            Some(Duration::from_millis(200)),
+            Some(100),
+            Duration::from_secs(2),
+        );
+        recorder.record_request(
+            "gpt-4o",
+            "OpenAI",
+            Some(Duration::from_millis(400)),
+            Some(300),
+            Duration::from_secs(2),
+        );
+        let stats = recorder.stats();
+        assert_eq!(stats.len(), 1);
+        assert_eq!(stats[0].requests, 2);
+        assert_eq!(stats[0].average_time_to_first_token_ms, Some(300.0));
+        // 50 tokens/sec and 150 tokens/sec average to 100
+        assert_eq!(stats[0].average_tokens_per_second, Some(100.0));
+    }
+
+    #[test]
+    fn test_missing_samples_stay_none() {
+        let recorder = LLMMetricsRecorder::new();
+        recorder.record_request("gpt-4o", "OpenAI", None, None, Duration::from_secs(1));
+        let stats = recorder.stats();
+        assert_eq!(stats[0].requests, 1);
+        assert_eq!(stats[0].average_time_to_first_token_ms, None);
+        assert_eq!(stats[0].average_tokens_per_second, None);
+    }
+
+    #[test]
+    fn test_stats_sorted_by_model_and_provider() {
+        let recorder = LLMMetricsRecorder::new();
+        let duration = Duration::from_secs(1);
+        recorder.record_request("sonnet", "Anthropic", None, None, duration);
+        recorder.record_request("gpt-4o", "OpenAI", None, None, duration);
+        recorder.record_request("gpt-4o", "Azure", None, None, duration);
+        let stats = recorder.stats();
+        let keys = stats
+            .iter()
+            .map(|entry| (entry.model.as_str(), entry.provider.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            keys,
+            vec![("gpt-4o", "Azure"), ("gpt-4o", "OpenAI"), ("sonnet", "Anthropic")]
+        );
+    }
+}
@@ -36,10 +36,13 @@ pub enum LLMProvider {
     OpenAICompatible,
     Anthropic,
     FireworksAI,
+    Gemini,
     GeminiPro,
     GoogleAIStudio,
     OpenRouter,
     Groq,
+    Bedrock,
+    LlamaCpp,
 }
 
 impl std::fmt::Display for LLMProvider {
@@ -54,10 +57,13 @@ impl std::fmt::Display for LLMProvider {
             LLMProvider::OpenAICompatible => write!(f, "OpenAICompatible"),
             LLMProvider::Anthropic => write!(f, "Anthropic"),
             LLMProvider::FireworksAI => write!(f, "FireworksAI"),
+            LLMProvider::Gemini => write!(f, "Gemini"),
             LLMProvider::GeminiPro => write!(f, "GeminiPro"),
             LLMProvider::GoogleAIStudio => write!(f, "GoogleAIStudio"),
             LLMProvider::OpenRouter => write!(f, "OpenRouter"),
             LLMProvider::Groq => write!(f, "Groq"),
+            LLMProvider::Bedrock => write!(f, "Bedrock"),
+            LLMProvider::LlamaCpp => write!(f, "LlamaCpp"),
         }
     }
 }
@@ -83,10 +89,13 @@ pub enum LLMProviderAPIKeys {
     CodeStory(CodestoryAccessToken),
     Anthropic(AnthropicAPIKey),
     FireworksAI(FireworksAPIKey),
+    Gemini(GeminiAPIKey),
     GeminiPro(GeminiProAPIKey),
     GoogleAIStudio(GoogleAIStudioKey),
     OpenRouter(OpenRouterAPIKey),
     GroqProvider(GroqProviderAPIKey),
+    Bedrock(BedrockConfig),
+    LlamaCpp(LlamaCppConfig),
 }
 
 impl LLMProviderAPIKeys {
@@ -115,10 +124,13 @@ impl LLMProviderAPIKeys {
             LLMProviderAPIKeys::OpenAICompatible(_) => LLMProvider::OpenAICompatible,
             LLMProviderAPIKeys::Anthropic(_) => LLMProvider::Anthropic,
             LLMProviderAPIKeys::FireworksAI(_) => LLMProvider::FireworksAI,
+            LLMProviderAPIKeys::Gemini(_) => LLMProvider::Gemini,
             LLMProviderAPIKeys::GeminiPro(_) => LLMProvider::GeminiPro,
             LLMProviderAPIKeys::GoogleAIStudio(_) => LLMProvider::GoogleAIStudio,
             LLMProviderAPIKeys::OpenRouter(_) => LLMProvider::OpenRouter,
             LLMProviderAPIKeys::GroqProvider(_) => LLMProvider::Groq,
+            LLMProviderAPIKeys::Bedrock(_) => LLMProvider::Bedrock,
+            LLMProviderAPIKeys::LlamaCpp(_) => LLMProvider::LlamaCpp,
         }
     }
 
@@ -161,10 +173,16 @@ impl LLMProviderAPIKeys {
             // NOTE: We should change this to using the codestory configuration
             // and make calls appropriately, for now this is fine
             LLMProvider::Azure(deployment_id) => {
-                if deployment_id.deployment_id == "" {
-                    return None;
-                }
                 if let LLMProviderAPIKeys::OpenAIAzureConfig(key) = self {
+                    if deployment_id.deployment_id == "" {
+                        // no deployment on the provider, we can still route
+                        // if the config carries a per-model mapping which the
+                        // client resolves at request time
+                        if key.deployments.is_empty() {
+                            return None;
+                        }
+                        return Some(LLMProviderAPIKeys::OpenAIAzureConfig(key.clone()));
+                    }
                     let mut azure_config = key.clone();
                     azure_config.deployment_id = deployment_id.deployment_id.to_owned();
                     Some(LLMProviderAPIKeys::OpenAIAzureConfig(azure_config))
@@ -203,6 +221,13 @@ impl LLMProviderAPIKeys {
                     None
                 }
             }
+            LLMProvider::Gemini => {
+                if let LLMProviderAPIKeys::Gemini(api_key) = self {
+                    Some(LLMProviderAPIKeys::Gemini(api_key.clone()))
+                } else {
+                    None
+                }
+            }
             LLMProvider::GeminiPro => {
                 if let LLMProviderAPIKeys::GeminiPro(api_key) = self {
                     Some(LLMProviderAPIKeys::GeminiPro(api_key.clone()))
@@ -231,6 +256,20 @@ impl LLMProviderAPIKeys {
                     None
                 }
             }
+            LLMProvider::Bedrock => {
+                if let LLMProviderAPIKeys::Bedrock(bedrock_config) = self {
+                    Some(LLMProviderAPIKeys::Bedrock(bedrock_config.clone()))
+                } else {
+                    None
+                }
+            }
+            LLMProvider::LlamaCpp => {
+                if let LLMProviderAPIKeys::LlamaCpp(llama_cpp_config) = self {
+                    Some(LLMProviderAPIKeys::LlamaCpp(llama_cpp_config.clone()))
+                } else {
+                    None
+                }
+            }
         }
     }
 }
@@ -291,6 +330,17 @@ impl GoogleAIStudioKey {
     }
 }
 
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
+pub struct GeminiAPIKey {
+    pub api_key: String,
+}
+
+impl GeminiAPIKey {
+    pub fn new(api_key: String) -> Self {
+        Self { api_key }
+    }
+}
+
 #[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
 pub struct GeminiProAPIKey {
     pub api_key: String,
@@ -303,6 +353,34 @@ impl GeminiProAPIKey {
     }
 }
 
+/// Credentials for the AWS Bedrock runtime, requests get signed with SigV4
+/// instead of sending a bearer key.
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
+pub struct BedrockConfig {
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    /// set when the credentials come from an assumed role
+    #[serde(default)]
+    pub session_token: Option<String>,
+    pub region: String,
+}
+
+impl BedrockConfig {
+    pub fn new(
+        access_key_id: String,
+        secret_access_key: String,
+        session_token: Option<String>,
+        region: String,
+    ) -> Self {
+        Self {
+            access_key_id,
+            secret_access_key,
+            session_token,
+            region,
+        }
+    }
+}
+
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct FireworksAPIKey {
     pub api_key: String,
@@ -347,17 +425,86 @@ impl OpenAICompatibleConfig {
     pub fn new(api_key: String, api_base: String) -> Self {
         Self { api_base, api_key }
     }
+
+    /// Groq's OpenAI-compatible endpoint
+    pub fn groq(api_key: String) -> Self {
+        Self::new(api_key, "https://api.groq.com/openai/v1".to_owned())
+    }
+
+    /// Together.ai's OpenAI-compatible endpoint
+    pub fn together(api_key: String) -> Self {
+        Self::new(api_key, "https://api.together.xyz/v1".to_owned())
+    }
+
+    /// Fireworks' OpenAI-compatible endpoint
+    pub fn fireworks(api_key: String) -> Self {
+        Self::new(api_key, "https://api.fireworks.ai/inference/v1".to_owned())
+    }
+
+    /// A self-hosted vLLM server, these generally run without an api key
+    pub fn vllm(api_base: String) -> Self {
+        Self::new("".to_owned(), api_base)
+    }
 }
 
 #[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
 pub struct OllamaProvider {}
 
+/// A single Azure OpenAI deployment, the api version falls back to the one
+/// on the config when its not set here
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
+pub struct AzureDeployment {
+    pub deployment_id: String,
+    #[serde(default)]
+    pub api_version: Option<String>,
+}
+
 #[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
 pub struct AzureConfig {
     pub deployment_id: String,
     pub api_base: String,
     pub api_key: String,
     pub api_version: String,
+    /// per-model deployment overrides, enterprises with multiple deployments
+    /// route different model families through different deployment ids
+    #[serde(default, skip_serializing_if = "std::collections::HashMap::is_empty")]
+    pub deployments: std::collections::HashMap<LLMType, AzureDeployment>,
+}
+
+impl AzureConfig {
+    /// The deployment id serving this model, the per-model mapping wins over
+    /// the top level deployment id
+    pub fn deployment_id_for(&self, llm_type: &LLMType) -> String {
+        self.deployments
+            .get(llm_type)
+            .map(|deployment| deployment.deployment_id.to_owned())
+            .unwrap_or_else(|| self.deployment_id.to_owned())
+    }
+
+    /// The api version to use for this model, the per-model mapping wins
+    /// over the top level api version
+    pub fn api_version_for(&self, llm_type: &LLMType) -> String {
+        self.deployments
+            .get(llm_type)
+            .and_then(|deployment| deployment.api_version.to_owned())
+            .unwrap_or_else(|| self.api_version.to_owned())
+    }
+}
+
+/// A local llama.cpp server or llamafile, these run without an api key
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
+pub struct LlamaCppConfig {
+    pub api_base: String,
+}
+
+impl LlamaCppConfig {
+    pub fn new(api_base: String) -> Self {
+        Self { api_base }
+    }
+
+    pub fn api_base(&self) -> &str {
+        &self.api_base
+    }
 }
 
 #[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
@@ -395,6 +542,44 @@ mod tests {
         assert_eq!(string_provider, "\"Ollama\"");
     }
 
+    #[test]
+    fn test_azure_per_model_deployment_resolution() {
+        use super::{AzureConfig, AzureDeployment};
+        use crate::clients::types::LLMType;
+        let config = AzureConfig {
+            deployment_id: "default-deployment".to_owned(),
+            api_base: "https://example.openai.azure.com".to_owned(),
+            api_key: "key".to_owned(),
+            api_version: "2023-08-01-preview".to_owned(),
+            deployments: vec![(
+                LLMType::Gpt4O,
+                AzureDeployment {
+                    deployment_id: "gpt4o-access".to_owned(),
+                    api_version: Some("2024-06-01".to_owned()),
+                },
+            )]
+            .into_iter()
+            .collect(),
+        };
+        // the mapped model gets its own deployment and api version
+        assert_eq!(config.deployment_id_for(&LLMType::Gpt4O), "gpt4o-access");
+        assert_eq!(config.api_version_for(&LLMType::Gpt4O), "2024-06-01");
+        // unmapped models fall back to the top level configuration
+        assert_eq!(
+            config.deployment_id_for(&LLMType::Gpt4),
+            "default-deployment"
+        );
+        assert_eq!(config.api_version_for(&LLMType::Gpt4), "2023-08-01-preview");
+    }
+
+    #[test]
+    fn test_azure_config_without_mapping_still_parses() {
+        use super::AzureConfig;
+        let data = r#"{"deployment_id":"gpt4-access","api_base":"https://example.openai.azure.com","api_key":"key","api_version":"2023-08-01-preview"}"#;
+        let config = serde_json::from_str::<AzureConfig>(data).expect("to work");
+        assert!(config.deployments.is_empty());
+    }
+
     #[test]
     fn test_reading_provider_keys() {
         let provider_keys = LLMProviderAPIKeys::OpenAI(super::OpenAIProvider {
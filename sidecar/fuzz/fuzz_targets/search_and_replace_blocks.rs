@@ -0,0 +1,33 @@
+//! Streams arbitrary bytes through the search/replace block accumulator,
+//! the parser sits directly on LLM output so malformed blocks must never
+//! panic the server
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use sidecar::agentic::tool::code_edit::search_and_replace::SearchAndReplaceAccumulator;
+
+fuzz_target!(|data: &str| {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .expect("runtime to build");
+    runtime.block_on(async {
+        let (sender, receiver) = tokio::sync::mpsc::unbounded_channel();
+        // dropping the receiver up front so the accumulator never waits on
+        // the edit lock handshake
+        drop(receiver);
+        let mut accumulator = SearchAndReplaceAccumulator::new(
+            "fn add(a: i32, b: i32) -> i32 {\n    a + b\n}".to_owned(),
+            0,
+            sender,
+        );
+        // feed the input in small deltas to exercise the streaming paths
+        for chunk in data.as_bytes().chunks(7) {
+            if let Ok(chunk) = std::str::from_utf8(chunk) {
+                accumulator.add_delta(chunk.to_owned()).await;
+            }
+        }
+        accumulator.add_delta(data.to_owned()).await;
+        accumulator.end_streaming().await;
+    });
+});
@@ -0,0 +1,10 @@
+//! Fuzzes the reasoning/plan section parser over raw model output
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use sidecar::agentic::tool::session::tool_use_agent::ToolUseAgentReasoningParams;
+
+fuzz_target!(|data: &str| {
+    let _ = ToolUseAgentReasoningParams::from_response(data);
+});
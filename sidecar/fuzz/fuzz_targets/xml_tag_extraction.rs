@@ -0,0 +1,21 @@
+//! Fuzzes the streaming XML tag extraction which chops tool-call XML out of
+//! partial LLM responses
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use sidecar::agentic::tool::code_edit::xml_processor::XmlProcessor;
+
+fuzz_target!(|data: &str| {
+    let mut processor = XmlProcessor::new();
+    // append in chunks so the tag boundaries can straddle deltas
+    for chunk in data.as_bytes().chunks(5) {
+        if let Ok(chunk) = std::str::from_utf8(chunk) {
+            processor.append(chunk);
+        }
+        let _ = processor.extract_all_tag_contents("thinking");
+    }
+    let _ = processor.extract_tag_content("thinking");
+    let _ = processor.extract_tag_content("step");
+    let _ = XmlProcessor::wrap_xml("response", data);
+});
@@ -0,0 +1,202 @@
+//! A/B experiment facility for prompt and model variations. A tool can
+//! register an experiment with weighted variants and each session gets a
+//! deterministic assignment, outcome metrics (edit acceptance, correction
+//! iterations, reward scores) are logged to disk as jsonl so prompt changes
+//! can be evaluated quantitatively after the fact.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::path::PathBuf;
+
+/// A variant of an experiment along with its assignment weight
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ExperimentVariant {
+    name: String,
+    weight: u32,
+}
+
+impl ExperimentVariant {
+    pub fn new(name: String, weight: u32) -> Self {
+        Self { name, weight }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+/// An experiment over prompt templates or model choices for a single tool
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Experiment {
+    name: String,
+    variants: Vec<ExperimentVariant>,
+}
+
+impl Experiment {
+    pub fn new(name: String, variants: Vec<ExperimentVariant>) -> Self {
+        Self { name, variants }
+    }
+
+    fn total_weight(&self) -> u32 {
+        self.variants.iter().map(|variant| variant.weight).sum()
+    }
+}
+
+/// The outcome metrics we track for an experiment, these map onto the
+/// signals we already collect during a session
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExperimentMetric {
+    /// whether the user accepted the edits from an exchange
+    EditAccepted(bool),
+    /// how many correction iterations were needed before the edit stuck
+    CorrectionIterations(usize),
+    /// the reward score the evaluation gave to the trajectory
+    RewardScore(i32),
+}
+
+/// A single outcome observation which gets appended to the experiment log
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ExperimentOutcome {
+    experiment: String,
+    variant: String,
+    session_id: String,
+    exchange_id: String,
+    metric: ExperimentMetric,
+    logged_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl ExperimentOutcome {
+    pub fn new(
+        experiment: String,
+        variant: String,
+        session_id: String,
+        exchange_id: String,
+        metric: ExperimentMetric,
+    ) -> Self {
+        Self {
+            experiment,
+            variant,
+            session_id,
+            exchange_id,
+            metric,
+            logged_at: chrono::Utc::now(),
+        }
+    }
+}
+
+/// Registry holding the active experiments and the log file the outcomes
+/// get appended to
+pub struct ExperimentRegistry {
+    experiments: tokio::sync::Mutex<HashMap<String, Experiment>>,
+    log_file_path: PathBuf,
+}
+
+impl ExperimentRegistry {
+    pub fn new(log_file_path: PathBuf) -> Self {
+        Self {
+            experiments: tokio::sync::Mutex::new(HashMap::new()),
+            log_file_path,
+        }
+    }
+
+    pub async fn register(&self, experiment: Experiment) {
+        let mut experiments = self.experiments.lock().await;
+        experiments.insert(experiment.name.to_owned(), experiment);
+    }
+
+    /// Grabs the variant assigned to a session for an experiment. The
+    /// assignment is deterministic: the same session always lands on the
+    /// same variant so behaviour stays stable across exchanges
+    pub async fn variant_for_session(
+        &self,
+        experiment_name: &str,
+        session_id: &str,
+    ) -> Option<String> {
+        let experiments = self.experiments.lock().await;
+        let experiment = experiments.get(experiment_name)?;
+        assign_variant(experiment, session_id).map(|variant| variant.to_owned())
+    }
+
+    /// Appends an outcome observation to the experiment log
+    pub async fn log_outcome(&self, outcome: ExperimentOutcome) {
+        let serialised = match serde_json::to_string(&outcome) {
+            Ok(serialised) => serialised,
+            Err(_) => return,
+        };
+        use tokio::io::AsyncWriteExt;
+        let file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.log_file_path)
+            .await;
+        if let Ok(mut file) = file {
+            let _ = file.write_all(serialised.as_bytes()).await;
+            let _ = file.write_all(b"\n").await;
+        }
+    }
+}
+
+/// Weighted deterministic assignment: hash the session id together with the
+/// experiment name and walk the cumulative weights
+fn assign_variant<'a>(experiment: &'a Experiment, session_id: &str) -> Option<&'a str> {
+    let total_weight = experiment.total_weight();
+    if total_weight == 0 {
+        return None;
+    }
+    let mut hasher = DefaultHasher::new();
+    experiment.name.hash(&mut hasher);
+    session_id.hash(&mut hasher);
+    let mut bucket = (hasher.finish() % total_weight as u64) as u32;
+    for variant in experiment.variants.iter() {
+        if bucket < variant.weight {
+            return Some(variant.name());
+        }
+        bucket = bucket - variant.weight;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{assign_variant, Experiment, ExperimentVariant};
+
+    fn experiment() -> Experiment {
+        Experiment::new(
+            "search_and_replace_prompt".to_owned(),
+            vec![
+                ExperimentVariant::new("control".to_owned(), 50),
+                ExperimentVariant::new("terse_prompt".to_owned(), 50),
+            ],
+        )
+    }
+
+    #[test]
+    fn test_assignment_is_deterministic() {
+        let experiment = experiment();
+        let first = assign_variant(&experiment, "session-1");
+        let second = assign_variant(&experiment, "session-1");
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn test_assignment_covers_all_variants() {
+        let experiment = experiment();
+        let mut seen = std::collections::HashSet::new();
+        for index in 0..100 {
+            let session_id = format!("session-{}", index);
+            seen.insert(assign_variant(&experiment, &session_id).expect("variant to be assigned"));
+        }
+        assert_eq!(seen.len(), 2);
+    }
+
+    #[test]
+    fn test_zero_weight_experiment_assigns_nothing() {
+        let experiment = Experiment::new(
+            "disabled".to_owned(),
+            vec![ExperimentVariant::new("control".to_owned(), 0)],
+        );
+        assert!(assign_variant(&experiment, "session-1").is_none());
+    }
+}
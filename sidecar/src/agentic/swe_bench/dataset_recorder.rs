@@ -0,0 +1,246 @@
+//! Opt-in recorder which captures anonymized task/edit/outcome tuples from
+//! real sessions into a local jsonl dataset. The shape mirrors the
+//! SWE-bench instances our harness consumes so users can build private eval
+//! sets for their own codebase. File paths get hashed and common secret
+//! patterns are scrubbed before anything is written out, the dataset never
+//! leaves the local disk.
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::path::PathBuf;
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+/// An anonymized instance in the recorded dataset, field names line up with
+/// the SWE-bench format the harness reads
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct RecordedEvalInstance {
+    repo: String,
+    instance_id: String,
+    problem_statement: String,
+    patch: String,
+    /// whether the user accepted the edits, instances where they did not are
+    /// still useful as hard cases
+    accepted: bool,
+    created_at: String,
+}
+
+pub struct DatasetRecorder {
+    enabled: bool,
+    dataset_path: PathBuf,
+}
+
+impl DatasetRecorder {
+    pub fn new(enabled: bool, dataset_path: PathBuf) -> Self {
+        Self {
+            enabled,
+            dataset_path,
+        }
+    }
+
+    /// Records the task/edit/outcome tuple for an exchange by reading the
+    /// session file on disk. We go through the stored json instead of the
+    /// live session so recording stays completely decoupled from the session
+    /// flow
+    pub async fn record_from_session_file(
+        &self,
+        storage_path: String,
+        exchange_id: String,
+        accepted: bool,
+    ) {
+        if !self.enabled {
+            return;
+        }
+        let contents = match tokio::fs::read_to_string(&storage_path).await {
+            Ok(contents) => contents,
+            Err(_) => return,
+        };
+        let session: serde_json::Value = match serde_json::from_str(&contents) {
+            Ok(session) => session,
+            Err(_) => return,
+        };
+        let instance = match extract_instance(&session, &exchange_id, accepted) {
+            Some(instance) => instance,
+            None => return,
+        };
+        let serialised = match serde_json::to_string(&instance) {
+            Ok(serialised) => serialised,
+            Err(_) => return,
+        };
+        use tokio::io::AsyncWriteExt;
+        let file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.dataset_path)
+            .await;
+        if let Ok(mut file) = file {
+            let _ = file.write_all(serialised.as_bytes()).await;
+            let _ = file.write_all(b"\n").await;
+        }
+    }
+}
+
+/// Pulls the human query and the agent's edit diff for an exchange out of
+/// the stored session json
+fn extract_instance(
+    session: &serde_json::Value,
+    exchange_id: &str,
+    accepted: bool,
+) -> Option<RecordedEvalInstance> {
+    let exchanges = session.get("exchanges")?.as_array()?;
+    let mut problem_statement = None;
+    let mut patch = None;
+    for exchange in exchanges {
+        let current_exchange_id = exchange.get("exchange_id").and_then(|id| id.as_str());
+        let exchange_type = exchange.get("exchange_type")?;
+        if current_exchange_id == Some(exchange_id) {
+            if let Some(human_chat) = exchange_type.get("HumanChat") {
+                problem_statement = human_chat
+                    .get("query")
+                    .and_then(|query| query.as_str())
+                    .map(|query| query.to_owned());
+            }
+        }
+        // the agent reply points back at the human exchange it replies to
+        let is_reply_to_exchange = exchange_type
+            .get("AgentChat")
+            .and_then(|agent_chat| agent_chat.get("parent_exchange_id"))
+            .and_then(|parent| parent.as_str())
+            == Some(exchange_id);
+        if is_reply_to_exchange {
+            patch = exchange_type
+                .get("AgentChat")
+                .and_then(|agent_chat| agent_chat.get("reply"))
+                .and_then(|reply| reply.get("Edit"))
+                .and_then(|edit| edit.get("edits_made_diff"))
+                .and_then(|diff| diff.as_str())
+                .map(|diff| diff.to_owned());
+        }
+    }
+    let problem_statement = problem_statement?;
+    let patch = patch.unwrap_or_default();
+    let repo = session
+        .get("repo_ref")
+        .map(|repo_ref| hash_identifier(&repo_ref.to_string()))
+        .unwrap_or_default();
+    Some(RecordedEvalInstance {
+        repo,
+        instance_id: uuid::Uuid::new_v4().to_string(),
+        problem_statement: scrub_secrets(&problem_statement),
+        patch: hash_diff_paths(&scrub_secrets(&patch)),
+        accepted,
+        created_at: chrono::Utc::now().to_rfc3339(),
+    })
+}
+
+/// Hashes an identifier (repo name, path) into a stable opaque token
+fn hash_identifier(identifier: &str) -> String {
+    let mut hasher = DefaultHasher::new();
+    identifier.hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
+
+/// Hashes a file path keeping the extension so the language signal stays
+/// intact in the dataset
+pub fn hash_path(path: &str) -> String {
+    let extension = std::path::Path::new(path)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(|extension| format!(".{}", extension))
+        .unwrap_or_default();
+    format!("file_{}{}", hash_identifier(path), extension)
+}
+
+static SECRET_PATTERNS: Lazy<Vec<Regex>> = Lazy::new(|| {
+    vec![
+        // openai style keys
+        Regex::new(r"sk-[A-Za-z0-9\-_]{20,}").expect("static regex to compile"),
+        // aws access key ids
+        Regex::new(r"AKIA[0-9A-Z]{16}").expect("static regex to compile"),
+        // github tokens
+        Regex::new(r"gh[pousr]_[A-Za-z0-9]{30,}").expect("static regex to compile"),
+        // bearer tokens in headers
+        Regex::new(r"Bearer\s+[A-Za-z0-9._\-]{16,}").expect("static regex to compile"),
+    ]
+});
+
+static SECRET_ASSIGNMENT: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?i)(api[_-]?key|secret|password|token)(["']?\s*[:=]\s*["']?)[^\s"',;]+"#)
+        .expect("static regex to compile")
+});
+
+/// Scrubs common secret patterns out of the content before it lands in the
+/// dataset
+pub fn scrub_secrets(input: &str) -> String {
+    let mut scrubbed = input.to_owned();
+    for pattern in SECRET_PATTERNS.iter() {
+        scrubbed = pattern.replace_all(&scrubbed, "[REDACTED]").to_string();
+    }
+    SECRET_ASSIGNMENT
+        .replace_all(&scrubbed, "$1$2[REDACTED]")
+        .to_string()
+}
+
+/// Rewrites the file paths in a unified diff with their hashed versions so
+/// directory layout does not leak into the dataset
+pub fn hash_diff_paths(diff: &str) -> String {
+    diff.lines()
+        .map(|line| {
+            if let Some(path) = line.strip_prefix("--- a/") {
+                format!("--- a/{}", hash_path(path))
+            } else if let Some(path) = line.strip_prefix("+++ b/") {
+                format!("+++ b/{}", hash_path(path))
+            } else if line.starts_with("diff --git ") {
+                let mut parts = line.split_whitespace();
+                let _ = parts.next();
+                let _ = parts.next();
+                let old_path = parts.next().and_then(|part| part.strip_prefix("a/"));
+                let new_path = parts.next().and_then(|part| part.strip_prefix("b/"));
+                match (old_path, new_path) {
+                    (Some(old_path), Some(new_path)) => format!(
+                        "diff --git a/{} b/{}",
+                        hash_path(old_path),
+                        hash_path(new_path)
+                    ),
+                    _ => line.to_owned(),
+                }
+            } else {
+                line.to_owned()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{hash_diff_paths, hash_path, scrub_secrets};
+
+    #[test]
+    fn test_hash_path_keeps_extension() {
+        let hashed = hash_path("src/webserver/agentic.rs");
+        assert!(hashed.starts_with("file_"));
+        assert!(hashed.ends_with(".rs"));
+        assert!(!hashed.contains("webserver"));
+        // stable across calls
+        assert_eq!(hashed, hash_path("src/webserver/agentic.rs"));
+    }
+
+    #[test]
+    fn test_scrubs_api_keys_and_assignments() {
+        let input = "export OPENAI_API_KEY=sk-abcdefghijklmnopqrstuvwx and api_key = \"hunter2value\"";
+        let scrubbed = scrub_secrets(input);
+        assert!(!scrubbed.contains("sk-abcdefghijklmnopqrstuvwx"));
+        assert!(!scrubbed.contains("hunter2value"));
+    }
+
+    #[test]
+    fn test_hashes_paths_in_diff_headers() {
+        let diff = "diff --git a/src/main.rs b/src/main.rs\n--- a/src/main.rs\n+++ b/src/main.rs\n@@ -1 +1 @@\n-old\n+new";
+        let hashed = hash_diff_paths(diff);
+        assert!(!hashed.contains("src/main.rs"));
+        assert!(hashed.contains("-old"));
+        assert!(hashed.contains("+new"));
+    }
+}
@@ -1,3 +1,4 @@
 //! Contains helper functions for swe_bench evaluation
 
+pub mod dataset_recorder;
 pub mod search_cache;
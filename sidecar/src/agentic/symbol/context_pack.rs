@@ -0,0 +1,268 @@
+//! Context packs bundle the neighborhood of a symbol (definitions, references,
+//! type hierarchy and siblings) into a single prompt ready block. The callers
+//! describe what they want with a [`ContextPackSpec`] (how deep to follow
+//! definitions, which kinds of context to include and the token budget) and
+//! the gathering itself lives on the toolbox so code edits, probing and chat
+//! all construct their context the same way instead of hand rolling it
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use tokio::sync::Mutex;
+
+use crate::agentic::tool::session::session::approximate_token_count;
+
+/// The kinds of context we know how to gather around a symbol
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize)]
+pub enum ContextPackKind {
+    /// The definitions the symbol points at (following `depth` hops)
+    Definitions,
+    /// Where the symbol is referenced from
+    References,
+    /// The type hierarchy: trait impls, subclasses, interface implementations
+    Types,
+    /// The other symbols which live in the same file
+    Siblings,
+}
+
+impl ContextPackKind {
+    fn heading(&self) -> &'static str {
+        match self {
+            ContextPackKind::Definitions => "Definitions",
+            ContextPackKind::References => "References",
+            ContextPackKind::Types => "Type hierarchy",
+            ContextPackKind::Siblings => "Siblings in the same file",
+        }
+    }
+}
+
+/// Declarative description of the context pack we want to build, the default
+/// gathers everything a single hop deep with a budget which keeps the pack
+/// comfortably inside the prompt
+#[derive(Debug, Clone)]
+pub struct ContextPackSpec {
+    depth: usize,
+    kinds: Vec<ContextPackKind>,
+    token_budget: usize,
+}
+
+impl ContextPackSpec {
+    pub fn new() -> Self {
+        Self {
+            depth: 1,
+            kinds: vec![
+                ContextPackKind::Definitions,
+                ContextPackKind::References,
+                ContextPackKind::Types,
+                ContextPackKind::Siblings,
+            ],
+            token_budget: 8_000,
+        }
+    }
+
+    pub fn with_depth(mut self, depth: usize) -> Self {
+        self.depth = depth;
+        self
+    }
+
+    pub fn with_kinds(mut self, kinds: Vec<ContextPackKind>) -> Self {
+        self.kinds = kinds;
+        self
+    }
+
+    pub fn with_token_budget(mut self, token_budget: usize) -> Self {
+        self.token_budget = token_budget;
+        self
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    pub fn kinds(&self) -> &[ContextPackKind] {
+        &self.kinds
+    }
+
+    pub fn token_budget(&self) -> usize {
+        self.token_budget
+    }
+
+    /// The cache key folds in everything which changes the shape of the pack
+    pub(crate) fn cache_key(&self, fs_file_path: &str, symbol_name: &str) -> String {
+        format!(
+            "{}::{}::depth({})::kinds({:?})::budget({})",
+            fs_file_path, symbol_name, self.depth, self.kinds, self.token_budget
+        )
+    }
+}
+
+/// A single gathered section, the content is already rendered for the prompt
+#[derive(Debug, Clone)]
+pub struct ContextPackSection {
+    kind: ContextPackKind,
+    content: String,
+}
+
+impl ContextPackSection {
+    pub fn new(kind: ContextPackKind, content: String) -> Self {
+        Self { kind, content }
+    }
+
+    pub fn kind(&self) -> ContextPackKind {
+        self.kind
+    }
+
+    pub fn content(&self) -> &str {
+        &self.content
+    }
+}
+
+/// The assembled pack for a symbol, sections which did not fit into the token
+/// budget are dropped whole and counted so the caller can tell the context is
+/// incomplete
+#[derive(Debug, Clone)]
+pub struct ContextPack {
+    symbol_name: String,
+    sections: Vec<ContextPackSection>,
+    dropped_sections: usize,
+}
+
+impl ContextPack {
+    pub fn symbol_name(&self) -> &str {
+        &self.symbol_name
+    }
+
+    pub fn sections(&self) -> &[ContextPackSection] {
+        &self.sections
+    }
+
+    pub fn dropped_sections(&self) -> usize {
+        self.dropped_sections
+    }
+
+    pub fn to_prompt_string(&self) -> String {
+        let symbol_name = &self.symbol_name;
+        let mut parts = vec![format!("Context for {symbol_name}:")];
+        for section in self.sections.iter() {
+            parts.push(format!(
+                "## {}\n{}",
+                section.kind.heading(),
+                section.content
+            ));
+        }
+        if self.dropped_sections != 0 {
+            parts.push(format!(
+                "({} sections omitted to stay inside the token budget)",
+                self.dropped_sections
+            ));
+        }
+        parts.join("\n\n")
+    }
+}
+
+/// Packs the gathered sections into the budget, sections are considered in
+/// the order the spec asked for them and a section which does not fit in the
+/// remaining budget is dropped whole (partial sections confuse the LLM more
+/// than missing ones)
+pub(crate) fn assemble_within_budget(
+    symbol_name: &str,
+    sections: Vec<ContextPackSection>,
+    token_budget: usize,
+) -> ContextPack {
+    let mut kept = vec![];
+    let mut dropped_sections = 0;
+    let mut remaining_budget = token_budget;
+    for section in sections.into_iter() {
+        let section_tokens = approximate_token_count(section.content());
+        if section_tokens <= remaining_budget {
+            remaining_budget = remaining_budget - section_tokens;
+            kept.push(section);
+        } else {
+            dropped_sections = dropped_sections + 1;
+        }
+    }
+    ContextPack {
+        symbol_name: symbol_name.to_owned(),
+        sections: kept,
+        dropped_sections,
+    }
+}
+
+/// Caches assembled packs keyed by the spec and symbol they were built for,
+/// the cache lives for as long as the toolbox does so repeated probing or
+/// chat turns over the same symbol do not redo the LSP round trips
+pub struct ContextPackCache {
+    packs: Mutex<HashMap<String, Arc<ContextPack>>>,
+}
+
+impl ContextPackCache {
+    pub fn new() -> Self {
+        Self {
+            packs: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub async fn get(&self, cache_key: &str) -> Option<Arc<ContextPack>> {
+        self.packs.lock().await.get(cache_key).cloned()
+    }
+
+    pub async fn insert(&self, cache_key: String, pack: Arc<ContextPack>) {
+        self.packs.lock().await.insert(cache_key, pack);
+    }
+
+    /// Drops the cached packs which were built from a file, called when we
+    /// know the file has been edited from underneath us
+    pub async fn invalidate_file(&self, fs_file_path: &str) {
+        self.packs
+            .lock()
+            .await
+            .retain(|cache_key, _| !cache_key.starts_with(&format!("{}::", fs_file_path)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{assemble_within_budget, ContextPackKind, ContextPackSection, ContextPackSpec};
+
+    #[test]
+    fn test_sections_past_the_budget_are_dropped_whole() {
+        let sections = vec![
+            ContextPackSection::new(ContextPackKind::Definitions, "fn small() {}".to_owned()),
+            ContextPackSection::new(
+                ContextPackKind::References,
+                "a very long section ".repeat(100),
+            ),
+            ContextPackSection::new(ContextPackKind::Siblings, "fn sibling() {}".to_owned()),
+        ];
+        let pack = assemble_within_budget("small", sections, 50);
+        // the huge references section gets dropped but the sibling section
+        // after it still fits
+        assert_eq!(pack.sections().len(), 2);
+        assert_eq!(pack.dropped_sections(), 1);
+        assert_eq!(pack.sections()[0].kind(), ContextPackKind::Definitions);
+        assert_eq!(pack.sections()[1].kind(), ContextPackKind::Siblings);
+        assert!(pack.to_prompt_string().contains("1 sections omitted"));
+    }
+
+    #[test]
+    fn test_everything_fits_inside_a_large_budget() {
+        let sections = vec![
+            ContextPackSection::new(ContextPackKind::Definitions, "fn small() {}".to_owned()),
+            ContextPackSection::new(ContextPackKind::Types, "impl Display for Small".to_owned()),
+        ];
+        let pack = assemble_within_budget("small", sections, 10_000);
+        assert_eq!(pack.sections().len(), 2);
+        assert_eq!(pack.dropped_sections(), 0);
+        assert!(!pack.to_prompt_string().contains("omitted"));
+    }
+
+    #[test]
+    fn test_cache_key_changes_with_the_spec() {
+        let spec = ContextPackSpec::new();
+        let deeper_spec = ContextPackSpec::new().with_depth(2);
+        assert_ne!(
+            spec.cache_key("/tmp/file.rs", "Symbol"),
+            deeper_spec.cache_key("/tmp/file.rs", "Symbol")
+        );
+    }
+}
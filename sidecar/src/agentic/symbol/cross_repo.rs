@@ -0,0 +1,113 @@
+//! Read-only resolution for symbols which live outside the active workspace
+//!
+//! Go-to-definition can land in another registered repository or inside vendored
+//! dependency sources (cargo registry checkouts, node_modules etc). The editor
+//! does not always have these files open, so instead of erroring out we read
+//! them straight from disk and generate outlines locally, allowing probes to
+//! answer questions about third-party code. We never edit these files, this
+//! path is strictly read-only.
+
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::agentic::tool::lsp::open_file::OpenFileResponse;
+use crate::chunking::editor_parsing::EditorParsing;
+use crate::chunking::types::OutlineNode;
+
+use super::errors::SymbolError;
+
+/// Path fragments which indicate that a file belongs to vendored or linked
+/// dependency sources and not to the active workspace
+const EXTERNAL_DEPENDENCY_MARKERS: &[&str] = &[
+    ".cargo/registry/src",
+    ".cargo\\registry\\src",
+    ".rustup/toolchains",
+    ".rustup\\toolchains",
+    "node_modules",
+    "site-packages",
+    "go/pkg/mod",
+];
+
+/// Checks if the file path points inside vendored dependency sources, these
+/// files are resolved read-only since the editor might not have them open
+/// and we should never be editing them
+pub fn is_external_dependency_path(fs_file_path: &str) -> bool {
+    EXTERNAL_DEPENDENCY_MARKERS
+        .iter()
+        .any(|marker| fs_file_path.contains(marker))
+}
+
+/// Resolves files which are outside the active workspace by reading them
+/// directly from disk, used as a fallback when the editor is not able to
+/// open the file for us
+pub struct CrossRepoResolver {
+    editor_parsing: Arc<EditorParsing>,
+}
+
+impl CrossRepoResolver {
+    pub fn new(editor_parsing: Arc<EditorParsing>) -> Self {
+        Self { editor_parsing }
+    }
+
+    /// Reads the file from disk and presents it in the same shape as an
+    /// editor backed file-open, so the rest of the pipeline does not have
+    /// to care where the content came from
+    pub async fn open_file_read_only(
+        &self,
+        fs_file_path: &str,
+    ) -> Result<OpenFileResponse, SymbolError> {
+        if !Path::new(fs_file_path).is_file() {
+            return Err(SymbolError::ExpectedFileToExist);
+        }
+        let file_contents = tokio::fs::read_to_string(fs_file_path)
+            .await
+            .map_err(|e| SymbolError::IOError(e))?;
+        let language = self
+            .editor_parsing
+            .for_file_path(fs_file_path)
+            .map(|language_config| language_config.language_str.to_owned())
+            .unwrap_or_default();
+        Ok(OpenFileResponse::new(
+            fs_file_path.to_owned(),
+            file_contents,
+            true,
+            language,
+            None,
+            None,
+        ))
+    }
+
+    /// Generates the outline nodes for an external file by parsing it locally
+    /// with tree-sitter, this allows probes to look at the structure of
+    /// third-party code without the editor being involved
+    pub async fn outline_nodes_read_only(
+        &self,
+        fs_file_path: &str,
+    ) -> Result<Vec<OutlineNode>, SymbolError> {
+        let file_contents = self.open_file_read_only(fs_file_path).await?;
+        let language_config = self
+            .editor_parsing
+            .for_file_path(fs_file_path)
+            .ok_or(SymbolError::FileTypeNotSupported(fs_file_path.to_owned()))?;
+        Ok(language_config
+            .generate_outline_fresh(file_contents.contents_ref().as_bytes(), fs_file_path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::is_external_dependency_path;
+
+    #[test]
+    fn test_external_dependency_path_detection() {
+        assert!(is_external_dependency_path(
+            "/home/user/.cargo/registry/src/index.crates.io-6f17d22bba15001f/serde-1.0.0/src/lib.rs"
+        ));
+        assert!(is_external_dependency_path(
+            "/project/node_modules/react/index.js"
+        ));
+        assert!(!is_external_dependency_path(
+            "/project/src/main.rs"
+        ));
+    }
+}
@@ -93,7 +93,9 @@ pub fn split_file_content_into_parts(
         .join("\n");
 
     let below: Option<String>;
-    if end_line >= lines.len() as i64 {
+    // the selection swallowing the last line leaves nothing below, mirroring
+    // how above is None when the selection starts at the first line
+    if end_line + 1 >= lines.len() as i64 {
         below = None;
     } else {
         let below_lines = lines
@@ -0,0 +1,158 @@
+//! Tracks the health of the editor's language server from the results we
+//! see on the lsp backed tools
+//!
+//! When the language server is cold or has crashed, goto requests come back
+//! empty and the agent would otherwise treat that as ground truth and make
+//! decisions on bad data. We watch for streaks of empty results along with
+//! latency spikes, allow a single retry after a warmup delay and surface a
+//! degraded mode notice on the ui when the heuristics keep firing
+
+use std::time::Duration;
+
+use tokio::sync::Mutex;
+
+/// consecutive empty responses before we consider the language server degraded,
+/// a single empty response is perfectly normal (dead symbol, wrong position)
+const EMPTY_RESULT_STREAK_THRESHOLD: usize = 5;
+/// responses slower than this count as a latency spike
+const SLOW_RESPONSE_THRESHOLD: Duration = Duration::from_secs(5);
+/// consecutive latency spikes before we consider the language server degraded
+const SLOW_RESPONSE_STREAK_THRESHOLD: usize = 3;
+/// how long we wait before retrying a request when the language server looks
+/// like its still warming up
+const WARMUP_RETRY_DELAY: Duration = Duration::from_millis(1500);
+
+#[derive(Debug, Default)]
+struct LSPHealthState {
+    empty_result_streak: usize,
+    slow_response_streak: usize,
+    /// we only want to surface the degraded notice once per episode instead
+    /// of spamming the ui on every lsp request
+    degraded_notice_sent: bool,
+}
+
+/// Shared across all the lsp backed tool invocations on the toolbox, the
+/// observations from every request feed the degradation heuristics
+pub struct LSPHealthTracker {
+    state: Mutex<LSPHealthState>,
+}
+
+impl LSPHealthTracker {
+    pub fn new() -> Self {
+        Self {
+            state: Mutex::new(LSPHealthState::default()),
+        }
+    }
+
+    /// Records a single observation from an lsp backed tool, a non-empty
+    /// response which came back quickly counts as a recovery and resets
+    /// the streaks
+    pub async fn record(&self, response_was_empty: bool, latency: Duration) {
+        let mut state = self.state.lock().await;
+        if response_was_empty {
+            state.empty_result_streak = state.empty_result_streak + 1;
+        } else {
+            state.empty_result_streak = 0;
+        }
+        if latency >= SLOW_RESPONSE_THRESHOLD {
+            state.slow_response_streak = state.slow_response_streak + 1;
+        } else {
+            state.slow_response_streak = 0;
+        }
+        if state.empty_result_streak == 0 && state.slow_response_streak == 0 {
+            state.degraded_notice_sent = false;
+        }
+    }
+
+    /// Whether the heuristics say the language server is degraded right now
+    pub async fn is_degraded(&self) -> bool {
+        let state = self.state.lock().await;
+        state.empty_result_streak >= EMPTY_RESULT_STREAK_THRESHOLD
+            || state.slow_response_streak >= SLOW_RESPONSE_STREAK_THRESHOLD
+    }
+
+    /// How long we should wait before retrying a request which came back
+    /// empty, gives a cold language server a chance to finish warming up
+    pub fn warmup_retry_delay(&self) -> Duration {
+        WARMUP_RETRY_DELAY
+    }
+
+    /// Returns the degraded mode notice if we are degraded and have not
+    /// surfaced it yet in this episode, the caller forwards it as a ui event
+    pub async fn degraded_notice(&self) -> Option<String> {
+        let mut state = self.state.lock().await;
+        if state.degraded_notice_sent {
+            return None;
+        }
+        let reason = if state.empty_result_streak >= EMPTY_RESULT_STREAK_THRESHOLD {
+            Some(format!(
+                "The language server returned empty results for the last {} requests, it might be cold or crashed. Goto and diagnostic results may be incomplete until it recovers.",
+                state.empty_result_streak
+            ))
+        } else if state.slow_response_streak >= SLOW_RESPONSE_STREAK_THRESHOLD {
+            Some(format!(
+                "The language server has been slow for the last {} requests, results may be incomplete while it catches up.",
+                state.slow_response_streak
+            ))
+        } else {
+            None
+        };
+        if reason.is_some() {
+            state.degraded_notice_sent = true;
+        }
+        reason
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{LSPHealthTracker, EMPTY_RESULT_STREAK_THRESHOLD};
+
+    const FAST_RESPONSE: Duration = Duration::from_millis(50);
+
+    #[tokio::test]
+    async fn test_empty_result_streak_marks_degraded() {
+        let tracker = LSPHealthTracker::new();
+        for _ in 0..EMPTY_RESULT_STREAK_THRESHOLD {
+            tracker.record(true, FAST_RESPONSE).await;
+        }
+        assert!(tracker.is_degraded().await);
+    }
+
+    #[tokio::test]
+    async fn test_non_empty_response_resets_streak() {
+        let tracker = LSPHealthTracker::new();
+        for _ in 0..EMPTY_RESULT_STREAK_THRESHOLD {
+            tracker.record(true, FAST_RESPONSE).await;
+        }
+        tracker.record(false, FAST_RESPONSE).await;
+        assert!(!tracker.is_degraded().await);
+    }
+
+    #[tokio::test]
+    async fn test_degraded_notice_only_fires_once_per_episode() {
+        let tracker = LSPHealthTracker::new();
+        for _ in 0..EMPTY_RESULT_STREAK_THRESHOLD {
+            tracker.record(true, FAST_RESPONSE).await;
+        }
+        assert!(tracker.degraded_notice().await.is_some());
+        assert!(tracker.degraded_notice().await.is_none());
+        // a recovery followed by another degradation surfaces the notice again
+        tracker.record(false, FAST_RESPONSE).await;
+        for _ in 0..EMPTY_RESULT_STREAK_THRESHOLD {
+            tracker.record(true, FAST_RESPONSE).await;
+        }
+        assert!(tracker.degraded_notice().await.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_latency_spikes_mark_degraded() {
+        let tracker = LSPHealthTracker::new();
+        for _ in 0..3 {
+            tracker.record(false, Duration::from_secs(10)).await;
+        }
+        assert!(tracker.is_degraded().await);
+    }
+}
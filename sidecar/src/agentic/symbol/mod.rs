@@ -3,13 +3,17 @@
 //! or the general question which is being asked to the symbol
 
 pub mod anchored;
+pub mod context_pack;
+pub mod cross_repo;
 pub mod errors;
 pub mod events;
 pub mod helpers;
 pub mod identifier;
 pub mod locker;
+pub mod lsp_health;
 pub mod manager;
 pub mod scratch_pad;
+pub mod signature_change;
 pub mod tool_box;
 pub mod tool_properties;
 pub mod toolbox;
@@ -0,0 +1,219 @@
+//! Impact preview and decisions for signature changes
+//!
+//! When an edit changes a function signature the followup machinery fans
+//! edit requests out to every caller. That can be exactly right or wildly
+//! invasive depending on how many call sites there are, so the editor can
+//! ask for the blast radius first (call site counts per file with sample
+//! snippets) and record what the user chose: update every caller, keep the
+//! callers untouched and add an adapter with the old signature, or cancel
+//! the followups entirely. The followup pass consumes the recorded decision
+//! and defaults to updating callers when nobody was asked
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use tokio::sync::Mutex;
+
+/// how many example call sites we keep per file, enough to recognise the
+/// usage pattern without shipping every caller over the wire
+const SAMPLE_SNIPPETS_PER_FILE: usize = 3;
+
+/// What the user chose to do about a signature change
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SignatureChangeAction {
+    /// the default, followups rewrite every call site
+    UpdateAllCallers,
+    /// leave the callers alone, add an overload or adapter with the old
+    /// signature which delegates to the new implementation
+    AddAdapter,
+    /// no followups at all, the user deals with the callers themselves
+    Cancel,
+}
+
+/// The call sites in a single file which the signature change touches
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct SignatureChangeFileImpact {
+    fs_file_path: String,
+    call_site_count: usize,
+    /// up to [`SAMPLE_SNIPPETS_PER_FILE`] lines showing how the file calls
+    /// the symbol today
+    sample_snippets: Vec<String>,
+}
+
+impl SignatureChangeFileImpact {
+    pub fn fs_file_path(&self) -> &str {
+        &self.fs_file_path
+    }
+
+    pub fn call_site_count(&self) -> usize {
+        self.call_site_count
+    }
+
+    pub fn sample_snippets(&self) -> &[String] {
+        &self.sample_snippets
+    }
+}
+
+/// The full preview the editor shows before a signature change is applied
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct SignatureChangeImpact {
+    symbol_name: String,
+    fs_file_path: String,
+    /// the symbols the outline diff saw changing their signature line
+    changed_signatures: Vec<String>,
+    per_file: Vec<SignatureChangeFileImpact>,
+}
+
+impl SignatureChangeImpact {
+    pub fn new(
+        symbol_name: String,
+        fs_file_path: String,
+        changed_signatures: Vec<String>,
+        per_file: Vec<SignatureChangeFileImpact>,
+    ) -> Self {
+        Self {
+            symbol_name,
+            fs_file_path,
+            changed_signatures,
+            per_file,
+        }
+    }
+
+    pub fn symbol_name(&self) -> &str {
+        &self.symbol_name
+    }
+
+    pub fn per_file(&self) -> &[SignatureChangeFileImpact] {
+        &self.per_file
+    }
+
+    pub fn total_call_sites(&self) -> usize {
+        self.per_file
+            .iter()
+            .map(|file_impact| file_impact.call_site_count)
+            .sum()
+    }
+}
+
+/// Groups the raw call sites (file path and the line the call sits on) into
+/// per-file counts with sample snippets, files with the most call sites
+/// first so the preview leads with the biggest blast radius
+pub fn summarize_call_sites(call_sites: Vec<(String, String)>) -> Vec<SignatureChangeFileImpact> {
+    let mut per_file: HashMap<String, (usize, Vec<String>)> = HashMap::new();
+    for (fs_file_path, snippet) in call_sites.into_iter() {
+        let entry = per_file.entry(fs_file_path).or_insert((0, vec![]));
+        entry.0 += 1;
+        let snippet = snippet.trim();
+        if !snippet.is_empty() && entry.1.len() < SAMPLE_SNIPPETS_PER_FILE {
+            entry.1.push(snippet.to_owned());
+        }
+    }
+    let mut file_impacts = per_file
+        .into_iter()
+        .map(
+            |(fs_file_path, (call_site_count, sample_snippets))| SignatureChangeFileImpact {
+                fs_file_path,
+                call_site_count,
+                sample_snippets,
+            },
+        )
+        .collect::<Vec<_>>();
+    file_impacts.sort_by(|left, right| {
+        right
+            .call_site_count
+            .cmp(&left.call_site_count)
+            .then_with(|| left.fs_file_path.cmp(&right.fs_file_path))
+    });
+    file_impacts
+}
+
+/// The recorded decisions, keyed by file and symbol. A decision is consumed
+/// when the followup pass reads it so a choice made for one edit never
+/// leaks into a later edit of the same symbol
+pub struct SignatureChangeDecisions {
+    decisions: Arc<Mutex<HashMap<String, SignatureChangeAction>>>,
+}
+
+impl SignatureChangeDecisions {
+    pub fn new() -> Self {
+        Self {
+            decisions: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    fn decision_key(fs_file_path: &str, symbol_name: &str) -> String {
+        format!("{}::{}", fs_file_path, symbol_name)
+    }
+
+    pub async fn record(
+        &self,
+        fs_file_path: &str,
+        symbol_name: &str,
+        action: SignatureChangeAction,
+    ) {
+        self.decisions
+            .lock()
+            .await
+            .insert(Self::decision_key(fs_file_path, symbol_name), action);
+    }
+
+    pub async fn take(
+        &self,
+        fs_file_path: &str,
+        symbol_name: &str,
+    ) -> Option<SignatureChangeAction> {
+        self.decisions
+            .lock()
+            .await
+            .remove(&Self::decision_key(fs_file_path, symbol_name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{summarize_call_sites, SignatureChangeAction, SignatureChangeDecisions};
+
+    #[test]
+    fn test_summarize_groups_and_orders_by_call_site_count() {
+        let call_sites = vec![
+            ("b.rs".to_owned(), "  use_it(1);".to_owned()),
+            ("a.rs".to_owned(), "use_it(2);".to_owned()),
+            ("b.rs".to_owned(), "use_it(3);".to_owned()),
+        ];
+        let summary = summarize_call_sites(call_sites);
+        assert_eq!(summary.len(), 2);
+        assert_eq!(summary[0].fs_file_path(), "b.rs");
+        assert_eq!(summary[0].call_site_count(), 2);
+        // snippets come back trimmed
+        assert_eq!(summary[0].sample_snippets(), &["use_it(1);", "use_it(3);"]);
+        assert_eq!(summary[1].fs_file_path(), "a.rs");
+        assert_eq!(summary[1].call_site_count(), 1);
+    }
+
+    #[test]
+    fn test_sample_snippets_are_capped_per_file() {
+        let call_sites = (0..10)
+            .map(|index| ("a.rs".to_owned(), format!("use_it({});", index)))
+            .collect::<Vec<_>>();
+        let summary = summarize_call_sites(call_sites);
+        assert_eq!(summary[0].call_site_count(), 10);
+        assert_eq!(summary[0].sample_snippets().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_decisions_are_consumed_on_read() {
+        let decisions = SignatureChangeDecisions::new();
+        decisions
+            .record("a.rs", "use_it", SignatureChangeAction::Cancel)
+            .await;
+        // a decision for one symbol does not answer for another
+        assert_eq!(decisions.take("a.rs", "other").await, None);
+        assert_eq!(
+            decisions.take("a.rs", "use_it").await,
+            Some(SignatureChangeAction::Cancel)
+        );
+        // consumed, the next edit of the same symbol starts fresh
+        assert_eq!(decisions.take("a.rs", "use_it").await, None);
+    }
+}
@@ -4,7 +4,7 @@ use std::sync::Arc;
 use std::time::Instant;
 
 use futures::{stream, StreamExt};
-use llm_client::clients::types::LLMType;
+use llm_client::clients::types::{LLMClientCompletionRequest, LLMClientMessage, LLMType};
 use llm_client::provider::{
     AnthropicAPIKey, FireworksAPIKey, GoogleAIStudioKey, LLMProvider, LLMProviderAPIKeys,
 };
@@ -17,7 +17,11 @@ use crate::agentic::tool::code_edit::filter_edit::{
     FilterEditOperationRequest, FilterEditOperationResponse,
 };
 use crate::agentic::tool::code_edit::search_and_replace::SearchAndReplaceEditingRequest;
+use crate::agentic::tool::code_edit::auto_fix;
+use crate::agentic::tool::code_edit::compiler_suggestions::ApplyCompilerSuggestionsRequest;
+use crate::agentic::tool::code_edit::module_path;
 use crate::agentic::tool::code_edit::test_correction::TestOutputCorrectionRequest;
+use crate::agentic::tool::format::formatter::FormatCodeRequest;
 use crate::agentic::tool::code_edit::types::CodeEdit;
 use crate::agentic::tool::code_symbol::correctness::{
     CodeCorrectnessAction, CodeCorrectnessRequest,
@@ -45,6 +49,7 @@ use crate::agentic::tool::code_symbol::models::anthropic::{
     ProbeNextSymbol,
 };
 use crate::agentic::tool::code_symbol::new_location::CodeSymbolNewLocationRequest;
+use crate::agentic::tool::code_symbol::outline_diff::OutlineDiffRequest;
 use crate::agentic::tool::code_symbol::new_sub_symbol::{
     NewSubSymbolRequiredRequest, NewSubSymbolRequiredResponse,
 };
@@ -87,6 +92,9 @@ use crate::agentic::tool::lsp::gotodefintion::{
 use crate::agentic::tool::lsp::gotoimplementations::{
     GoToImplementationRequest, GoToImplementationResponse,
 };
+use crate::agentic::tool::lsp::call_hierarchy::{
+    CallHierarchyDirection, CallHierarchyRequest, CallHierarchyResponse,
+};
 use crate::agentic::tool::lsp::gotoreferences::{
     AnchoredReference, GoToReferencesRequest, GoToReferencesResponse, ReferenceLocation,
 };
@@ -99,8 +107,10 @@ use crate::agentic::tool::lsp::quick_fix::{
     GetQuickFixRequest, GetQuickFixResponse, LSPQuickFixInvocationRequest,
     LSPQuickFixInvocationResponse,
 };
+use crate::agentic::tool::lsp::rename::{LSPRenameRequest, LSPRenameResponse};
 use crate::agentic::tool::lsp::subprocess_spawned_output::SubProcessSpawnedPendingOutputRequest;
 use crate::agentic::tool::lsp::undo_changes::UndoChangesMadeDuringExchangeRequest;
+use crate::agentic::tool::lsp::workspace_symbol::{WorkspaceSymbolRequest, WorkspaceSymbolResponse};
 use crate::agentic::tool::plan::add_steps::PlanAddRequest;
 use crate::agentic::tool::plan::generator::{StepGeneratorRequest, StepSenderEvent};
 use crate::agentic::tool::plan::plan_step::PlanStep;
@@ -110,10 +120,14 @@ use crate::agentic::tool::ref_filter::ref_filter::ReferenceFilterRequest;
 use crate::agentic::tool::session::chat::SessionChatMessage;
 use crate::agentic::tool::session::exchange::SessionExchangeNewRequest;
 use crate::agentic::tool::swe_bench::test_tool::{SWEBenchTestRepsonse, SWEBenchTestRequest};
+use crate::agentic::tool::terminal::long_running::{
+    checkin_prompt, tail_for_checkin, CheckinDecision, LongRunningCommandStatus,
+    CHECKIN_OUTPUT_BUDGET,
+};
 use crate::agentic::tool::terminal::terminal::{TerminalInput, TerminalOutput};
 use crate::chunking::editor_parsing::EditorParsing;
 use crate::chunking::text_document::{Position, Range};
-use crate::chunking::types::{OutlineNode, OutlineNodeContent};
+use crate::chunking::types::{declaration_visibility, OutlineNode, OutlineNodeContent};
 use crate::repomap::tag::TagIndex;
 use crate::repomap::types::RepoMap;
 use crate::user_context::types::{UserContext, VariableInformation};
@@ -123,6 +137,14 @@ use crate::{
 };
 
 use super::anchored::AnchoredSymbol;
+use super::lsp_health::LSPHealthTracker;
+use crate::agentic::tool::helpers::circuit_breaker::CircuitBreaker;
+
+use super::context_pack::{
+    assemble_within_budget, ContextPack, ContextPackCache, ContextPackKind, ContextPackSection,
+    ContextPackSpec,
+};
+use super::cross_repo::CrossRepoResolver;
 use super::errors::SymbolError;
 use super::events::context_event::ContextGatheringEvent;
 use super::events::edit::{SymbolToEdit, SymbolToEditRequest};
@@ -132,16 +154,135 @@ use super::events::message_event::{SymbolEventMessage, SymbolEventMessagePropert
 use super::events::probe::{SubSymbolToProbe, SymbolToProbeRequest};
 use super::helpers::{find_needle_position, generate_hyperlink_from_snippet, SymbolFollowupBFS};
 use super::identifier::{LLMProperties, MechaCodeSymbolThinking};
+use super::signature_change::{
+    summarize_call_sites, SignatureChangeAction, SignatureChangeDecisions, SignatureChangeImpact,
+};
 use super::tool_properties::ToolProperties;
 use super::toolbox::helpers::{SymbolChangeSet, SymbolChanges};
 use super::types::SymbolEventRequest;
 use super::ui_event::UIEventWithID;
 
+/// How many definitions we chase from a single position when building the
+/// definitions section of a context pack
+const MAX_DEFINITIONS_PER_HOP: usize = 5;
+/// How many reference locations a context pack lists before truncating
+const MAX_REFERENCES_IN_PACK: usize = 15;
+
+/// A single entry in a type hierarchy: one place where the type is declared
+/// or implemented along with its short outline.
+#[derive(Debug, Clone)]
+pub struct TypeHierarchyEntry {
+    fs_file_path: String,
+    start_line: usize,
+    end_line: usize,
+    outline: String,
+}
+
+impl TypeHierarchyEntry {
+    fn from_outline_node(outline_node: &OutlineNode) -> Self {
+        Self {
+            fs_file_path: outline_node.fs_file_path().to_owned(),
+            start_line: outline_node.range().start_line(),
+            end_line: outline_node.range().end_line(),
+            outline: outline_node.get_outline_short(),
+        }
+    }
+
+    pub fn fs_file_path(&self) -> &str {
+        &self.fs_file_path
+    }
+
+    /// file path along with the line range, matches the format we use in the
+    /// outline prompts
+    fn location(&self) -> String {
+        format!("{}-{}:{}", self.fs_file_path, self.start_line, self.end_line)
+    }
+}
+
+/// The aggregated hierarchy of a class like symbol: its declaration along
+/// with the trait impls / subclasses / interface implementations we found
+/// across the workspace.
+#[derive(Debug, Clone)]
+pub struct TypeHierarchy {
+    symbol_name: String,
+    declaration: TypeHierarchyEntry,
+    implementations: Vec<TypeHierarchyEntry>,
+}
+
+impl TypeHierarchy {
+    pub fn symbol_name(&self) -> &str {
+        &self.symbol_name
+    }
+
+    pub fn implementations(&self) -> &[TypeHierarchyEntry] {
+        &self.implementations
+    }
+
+    /// Compact view of the hierarchy for prompts: one line per location with
+    /// the short outlines underneath.
+    pub fn to_compact_view(&self) -> String {
+        let symbol_name = &self.symbol_name;
+        let mut lines = vec![format!(
+            "Type hierarchy for {symbol_name}:\n- declared at {}",
+            self.declaration.location()
+        )];
+        for implementation in self.implementations.iter() {
+            lines.push(format!(
+                "- implementation at {}\n{}",
+                implementation.location(),
+                implementation.outline
+            ));
+        }
+        lines.join("\n")
+    }
+
+    /// The `<outline_list>` representation the symbol prompts consume.
+    fn to_outline_list(&self) -> String {
+        let symbol_name = &self.symbol_name;
+        let outlines = self
+            .implementations
+            .iter()
+            .chain(std::iter::once(&self.declaration))
+            .map(|entry| {
+                let fs_file_path = entry.location();
+                let outline = &entry.outline;
+                format!(
+                    r#"<outline>
+<symbol_name>
+{symbol_name}
+</symbol_name>
+<file_path>
+{fs_file_path}
+</file_path>
+<content>
+{outline}
+</content>
+</outline>"#
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        format!(
+            r#"<outline_list>
+{outlines}
+</outline_line>"#
+        )
+    }
+}
+
 #[derive(Clone)]
 pub struct ToolBox {
     tools: Arc<ToolBroker>,
     symbol_broker: Arc<SymbolTrackerInline>,
     editor_parsing: Arc<EditorParsing>,
+    context_packs: Arc<ContextPackCache>,
+    lsp_health: Arc<LSPHealthTracker>,
+    /// decisions the user made on signature-change previews, consumed by
+    /// the followup pass before it fans edits out to callers
+    signature_change_decisions: Arc<SignatureChangeDecisions>,
+    /// breaker around the editor's references endpoint, when open we serve
+    /// the outline fallback instead of hammering a broken language server
+    references_breaker: Arc<CircuitBreaker>,
 }
 
 impl ToolBox {
@@ -154,6 +295,10 @@ impl ToolBox {
             tools,
             symbol_broker,
             editor_parsing,
+            context_packs: Arc::new(ContextPackCache::new()),
+            lsp_health: Arc::new(LSPHealthTracker::new()),
+            signature_change_decisions: Arc::new(SignatureChangeDecisions::new()),
+            references_breaker: Arc::new(CircuitBreaker::for_tool("go_to_references")),
         }
     }
 
@@ -162,7 +307,7 @@ impl ToolBox {
     }
 
     pub fn mcp_tools(&self) -> Box<[ToolType]> {
-        self.tools.mcp_tools.clone()
+        self.tools.mcp_tools()
     }
 
     /// sends the user query to the scratch-pad agent
@@ -957,18 +1102,21 @@ impl ToolBox {
             .into_iter()
             .chain(symbol_filter.into_iter())
             .collect::<HashSet<String>>();
-        let outline_nodes = stream::iter(
-            imported_files
-                .into_iter()
-                .chain(extra_files_to_include)
-                .collect::<HashSet<String>>()
-                .into_iter()
-                .map(|imported_file| (imported_file, message_properties.clone())),
-        )
-        .map(|(imported_file, message_properties)| async move {
-            let file_open_response = self
-                .file_open(imported_file.to_owned(), message_properties)
-                .await;
+        let files_to_open = imported_files
+            .into_iter()
+            .chain(extra_files_to_include)
+            .collect::<HashSet<String>>()
+            .into_iter()
+            .collect::<Vec<_>>();
+        // the broker fans the opens out concurrently and bounds how many
+        // hit the editor at once
+        let file_open_responses = self
+            .file_open_many(files_to_open.to_vec(), message_properties.clone())
+            .await;
+        let mut outline_nodes = vec![];
+        for (imported_file, file_open_response) in
+            files_to_open.into_iter().zip(file_open_responses)
+        {
             if let Ok(file_open_response) = file_open_response {
                 let _ = self
                     .force_add_document(
@@ -978,18 +1126,16 @@ impl ToolBox {
                     )
                     .await;
             }
-            self.get_outline_nodes_grouped(&imported_file).await
-        })
-        .buffer_unordered(5)
-        .collect::<Vec<_>>()
-        .await
-        .into_iter()
-        .filter_map(|s| s)
-        .flatten()
+            if let Some(nodes) = self.get_outline_nodes_grouped(&imported_file).await {
+                outline_nodes.extend(nodes);
+            }
+        }
         // filter to only include the ouline nodes which we know of, not including
         // the whole world
-        .filter(|outline_node| final_outline_nodes_filter.contains(outline_node.name()))
-        .collect::<Vec<_>>();
+        let outline_nodes = outline_nodes
+            .into_iter()
+            .filter(|outline_node| final_outline_nodes_filter.contains(outline_node.name()))
+            .collect::<Vec<_>>();
         Ok(outline_nodes)
     }
 
@@ -1837,138 +1983,320 @@ We also believe this symbol needs to be probed because of:
 </outline_list>"
                 ))
             } else {
-                let start = Instant::now();
-                // we need to check for implementations as well and then return it
-                let identifier_position = outline_node.identifier_range();
-                // now we go to the implementations using this identifier node (this can take some time)
-                let identifier_node_positions = self
-                    .go_to_implementations_exact(
-                        fs_file_path,
-                        &identifier_position.start_position(),
-                        message_properties.clone(),
-                    )
-                    .await?
-                    .remove_implementations_vec();
-
-                println!(
-                    "go_to_implementations_exact::elapsed({:?})",
-                    start.elapsed()
-                );
-                // Now that we have the identifier positions we want to grab the
-                // remaining implementations as well
-                let file_paths = identifier_node_positions
-                    .into_iter()
-                    .map(|implementation| implementation.fs_file_path().to_owned())
-                    .collect::<HashSet<String>>();
-                // send a request to open all these files
+                // class like symbols get their full hierarchy (trait impls,
+                // subclasses, interface implementations) aggregated
+                let hierarchy = self
+                    .hierarchy_for_outline_node(&outline_node, message_properties)
+                    .await?;
+                Ok(hierarchy.to_outline_list())
+            }
+        } else {
+            // we did not find anything here so skip this part
+            Err(SymbolError::OutlineNodeNotFound(symbol_name.to_owned()))
+        }
+    }
 
-                let _ = stream::iter(
-                    file_paths
-                        .clone()
-                        .into_iter()
-                        .map(|fs_file_path| (fs_file_path, message_properties.clone())),
-                )
-                .map(|(fs_file_path, message_properties)| async move {
-                    self.file_open(fs_file_path, message_properties).await
-                })
-                .buffer_unordered(100)
-                .collect::<Vec<_>>()
-                .await;
+    /// Aggregates the type hierarchy of a class like symbol across the
+    /// workspace: its declaration along with every trait impl / subclass /
+    /// interface implementation the editor knows about.
+    pub async fn type_hierarchy_for_symbol(
+        &self,
+        fs_file_path: &str,
+        symbol_name: &str,
+        message_properties: SymbolEventMessageProperties,
+    ) -> Result<TypeHierarchy, SymbolError> {
+        let outline_node = self
+            .get_ouline_nodes_grouped_fresh(fs_file_path, message_properties.clone())
+            .await
+            .ok_or(SymbolError::WrongToolOutput)?
+            .into_iter()
+            .find(|outline_node| outline_node.name() == symbol_name)
+            .ok_or(SymbolError::OutlineNodeNotFound(symbol_name.to_owned()))?;
+        self.hierarchy_for_outline_node(&outline_node, message_properties)
+            .await
+    }
 
-                // this shit takes forever!
-                let start = Instant::now();
-                // Now all files are opened so we have also parsed them in the symbol broker
-                // so we can grab the appropriate outlines properly over here
-                let file_path_to_outline_nodes = stream::iter(file_paths)
-                    .map(|fs_file_path| async move {
-                        let start = Instant::now();
-                        let symbols = self.symbol_broker.get_symbols_outline(&fs_file_path).await;
-                        println!("get_symbol_outline::elapsed({:?}", start.elapsed());
-                        (fs_file_path, symbols)
-                    })
-                    .buffer_unordered(100)
-                    .collect::<Vec<_>>()
+    /// Builds (or returns the cached) context pack for a symbol as described
+    /// by the spec, this is the shared entry point for code editing, probing
+    /// and chat so they all gather their surrounding context the same way
+    pub async fn gather_context_pack(
+        &self,
+        fs_file_path: &str,
+        symbol_name: &str,
+        spec: ContextPackSpec,
+        message_properties: SymbolEventMessageProperties,
+    ) -> Result<Arc<ContextPack>, SymbolError> {
+        let cache_key = spec.cache_key(fs_file_path, symbol_name);
+        if let Some(context_pack) = self.context_packs.get(&cache_key).await {
+            return Ok(context_pack);
+        }
+        let outline_nodes = self
+            .get_ouline_nodes_grouped_fresh(fs_file_path, message_properties.clone())
+            .await
+            .ok_or(SymbolError::WrongToolOutput)?;
+        let outline_node = outline_nodes
+            .iter()
+            .find(|outline_node| outline_node.name() == symbol_name)
+            .ok_or(SymbolError::OutlineNodeNotFound(symbol_name.to_owned()))?;
+        let mut sections = vec![];
+        for kind in spec.kinds().to_vec() {
+            let content = match kind {
+                ContextPackKind::Definitions => {
+                    self.definitions_context(outline_node, spec.depth(), message_properties.clone())
+                        .await
+                }
+                ContextPackKind::References => {
+                    self.references_context(outline_node, message_properties.clone())
+                        .await
+                }
+                ContextPackKind::Types => self
+                    .hierarchy_for_outline_node(outline_node, message_properties.clone())
                     .await
-                    .into_iter()
-                    .filter_map(
-                        |(fs_file_path, outline_nodes_maybe)| match outline_nodes_maybe {
-                            Some(outline_nodes) => Some((fs_file_path, outline_nodes)),
-                            None => None,
-                        },
-                    )
-                    .filter_map(|(fs_file_path, outline_nodes)| {
-                        match outline_nodes
-                            .into_iter()
-                            .find(|outline_node| outline_node.name() == symbol_name)
-                        {
-                            Some(outline_node) => Some((fs_file_path, outline_node)),
-                            None => None,
-                        }
-                    })
-                    .collect::<HashMap<String, OutlineNode>>();
-
-                println!("file_path_to_outline_nodes::elapsed({:?})", start.elapsed());
+                    .map(|hierarchy| hierarchy.to_compact_view())
+                    .ok(),
+                ContextPackKind::Siblings => Self::siblings_context(symbol_name, &outline_nodes),
+            };
+            if let Some(content) = content {
+                sections.push(ContextPackSection::new(kind, content));
+            }
+        }
+        let context_pack = Arc::new(assemble_within_budget(
+            symbol_name,
+            sections,
+            spec.token_budget(),
+        ));
+        self.context_packs
+            .insert(cache_key, context_pack.clone())
+            .await;
+        Ok(context_pack)
+    }
 
-                // we need to get the outline for the symbol over here
-                let mut outlines = vec![];
-                for (_, outline_node) in file_path_to_outline_nodes.into_iter() {
-                    // Fuck it we ball, let's return the full outline here we need to truncate it later on
-                    let fs_file_path = format!(
+    /// Collects the outlines of the definitions the symbol resolves to,
+    /// walking `depth` hops outwards along the definition chain
+    async fn definitions_context(
+        &self,
+        outline_node: &OutlineNode,
+        depth: usize,
+        message_properties: SymbolEventMessageProperties,
+    ) -> Option<String> {
+        let mut visited: HashSet<String> = HashSet::new();
+        visited.insert(format!(
+            "{}-{}:{}",
+            outline_node.fs_file_path(),
+            outline_node.range().start_line(),
+            outline_node.range().end_line()
+        ));
+        let mut frontier = vec![(
+            outline_node.fs_file_path().to_owned(),
+            outline_node.identifier_range().start_position(),
+        )];
+        let mut outlines = vec![];
+        for _ in 0..depth {
+            let mut next_frontier = vec![];
+            for (fs_file_path, position) in frontier.into_iter() {
+                let definitions = match self
+                    .go_to_definition(&fs_file_path, position, message_properties.clone())
+                    .await
+                {
+                    Ok(response) => response.definitions(),
+                    Err(_) => continue,
+                };
+                for definition in definitions.into_iter().take(MAX_DEFINITIONS_PER_HOP) {
+                    let definition_file = definition.file_path().to_owned();
+                    let _ = self
+                        .file_open(definition_file.to_owned(), message_properties.clone())
+                        .await;
+                    let Some(definition_nodes) =
+                        self.get_outline_nodes_grouped(&definition_file).await
+                    else {
+                        continue;
+                    };
+                    let Some(containing_node) = definition_nodes
+                        .into_iter()
+                        .find(|node| node.range().contains(definition.range()))
+                    else {
+                        continue;
+                    };
+                    let location = format!(
                         "{}-{}:{}",
-                        outline_node.fs_file_path(),
-                        outline_node.range().start_line(),
-                        outline_node.range().end_line()
+                        containing_node.fs_file_path(),
+                        containing_node.range().start_line(),
+                        containing_node.range().end_line()
                     );
-                    let outline = outline_node.get_outline_short();
+                    if !visited.insert(location.to_owned()) {
+                        continue;
+                    }
                     outlines.push(format!(
-                        r#"<outline>
-<symbol_name>
-{symbol_name}
-</symbol_name>
-<file_path>
-{fs_file_path}
-</file_path>
-<content>
-{outline}
-</content>
-</outline>"#
-                    ))
+                        "{}\n{}",
+                        location,
+                        containing_node.get_outline_short()
+                    ));
+                    next_frontier.push((
+                        containing_node.fs_file_path().to_owned(),
+                        containing_node.identifier_range().start_position(),
+                    ));
                 }
-
-                // now add the identifier node which we are originally looking at the implementations for
-                let fs_file_path = format!(
-                    "{}-{}:{}",
-                    outline_node.fs_file_path(),
-                    outline_node.range().start_line(),
-                    outline_node.range().end_line()
-                );
-                let outline = outline_node.get_outline_short();
-                outlines.push(format!(
-                    r#"<outline>
-<symbol_name>
-{symbol_name}
-</symbol_name>
-<file_path>
-{fs_file_path}
-</file_path>
-<content>
-{outline}
-</content>
-</outline>"#
-                ));
-                let joined_outlines = outlines.join("\n");
-                Ok(format!(
-                    r#"<outline_list>
-{joined_outlines}
-</outline_line>"#
-                ))
             }
+            frontier = next_frontier;
+            if frontier.is_empty() {
+                break;
+            }
+        }
+        if outlines.is_empty() {
+            None
         } else {
-            // we did not find anything here so skip this part
-            Err(SymbolError::OutlineNodeNotFound(symbol_name.to_owned()))
+            Some(outlines.join("\n\n"))
         }
     }
 
+    /// Lists where the symbol is referenced from, capped so a heavily used
+    /// symbol does not eat the whole budget
+    async fn references_context(
+        &self,
+        outline_node: &OutlineNode,
+        message_properties: SymbolEventMessageProperties,
+    ) -> Option<String> {
+        let references = self
+            .go_to_references(
+                outline_node.fs_file_path().to_owned(),
+                outline_node.identifier_range().start_position(),
+                message_properties,
+            )
+            .await
+            .ok()?
+            .locations();
+        if references.is_empty() {
+            return None;
+        }
+        let total_references = references.len();
+        let mut lines = references
+            .into_iter()
+            .take(MAX_REFERENCES_IN_PACK)
+            .map(|reference| {
+                format!(
+                    "- {}:{}",
+                    reference.fs_file_path(),
+                    reference.range().start_line()
+                )
+            })
+            .collect::<Vec<_>>();
+        if total_references > MAX_REFERENCES_IN_PACK {
+            lines.push(format!(
+                "... and {} more",
+                total_references - MAX_REFERENCES_IN_PACK
+            ));
+        }
+        Some(lines.join("\n"))
+    }
+
+    /// The other symbols which share the file with the one we are packing
+    fn siblings_context(symbol_name: &str, outline_nodes: &[OutlineNode]) -> Option<String> {
+        let siblings = outline_nodes
+            .iter()
+            .filter(|node| node.name() != symbol_name)
+            .map(|node| node.get_outline_short())
+            .collect::<Vec<_>>();
+        if siblings.is_empty() {
+            None
+        } else {
+            Some(siblings.join("\n"))
+        }
+    }
+
+    /// Gathers the implementations of an already resolved outline node into
+    /// a [`TypeHierarchy`], this is the workhorse behind both the hierarchy
+    /// tool and the outline gathering for class like symbols.
+    async fn hierarchy_for_outline_node(
+        &self,
+        outline_node: &OutlineNode,
+        message_properties: SymbolEventMessageProperties,
+    ) -> Result<TypeHierarchy, SymbolError> {
+        let symbol_name = outline_node.name().to_owned();
+        let fs_file_path = outline_node.fs_file_path();
+        let start = Instant::now();
+        // we need to check for implementations as well and then return it
+        let identifier_position = outline_node.identifier_range();
+        // now we go to the implementations using this identifier node (this can take some time)
+        let identifier_node_positions = self
+            .go_to_implementations_exact(
+                fs_file_path,
+                &identifier_position.start_position(),
+                message_properties.clone(),
+            )
+            .await?
+            .remove_implementations_vec();
+
+        println!(
+            "go_to_implementations_exact::elapsed({:?})",
+            start.elapsed()
+        );
+        // Now that we have the identifier positions we want to grab the
+        // remaining implementations as well
+        let file_paths = identifier_node_positions
+            .into_iter()
+            .map(|implementation| implementation.fs_file_path().to_owned())
+            .collect::<HashSet<String>>();
+        // send a request to open all these files
+
+        let _ = stream::iter(
+            file_paths
+                .clone()
+                .into_iter()
+                .map(|fs_file_path| (fs_file_path, message_properties.clone())),
+        )
+        .map(|(fs_file_path, message_properties)| async move {
+            self.file_open(fs_file_path, message_properties).await
+        })
+        .buffer_unordered(100)
+        .collect::<Vec<_>>()
+        .await;
+
+        // this shit takes forever!
+        let start = Instant::now();
+        let symbol_name_ref = symbol_name.as_str();
+        // Now all files are opened so we have also parsed them in the symbol broker
+        // so we can grab the appropriate outlines properly over here
+        let file_path_to_outline_nodes = stream::iter(file_paths)
+            .map(|fs_file_path| async move {
+                let start = Instant::now();
+                let symbols = self.symbol_broker.get_symbols_outline(&fs_file_path).await;
+                println!("get_symbol_outline::elapsed({:?}", start.elapsed());
+                (fs_file_path, symbols)
+            })
+            .buffer_unordered(100)
+            .collect::<Vec<_>>()
+            .await
+            .into_iter()
+            .filter_map(
+                |(fs_file_path, outline_nodes_maybe)| match outline_nodes_maybe {
+                    Some(outline_nodes) => Some((fs_file_path, outline_nodes)),
+                    None => None,
+                },
+            )
+            .filter_map(|(fs_file_path, outline_nodes)| {
+                match outline_nodes
+                    .into_iter()
+                    .find(|outline_node| outline_node.name() == symbol_name_ref)
+                {
+                    Some(outline_node) => Some((fs_file_path, outline_node)),
+                    None => None,
+                }
+            })
+            .collect::<HashMap<String, OutlineNode>>();
+
+        println!("file_path_to_outline_nodes::elapsed({:?})", start.elapsed());
+
+        let implementations = file_path_to_outline_nodes
+            .values()
+            .map(|outline_node| TypeHierarchyEntry::from_outline_node(outline_node))
+            .collect::<Vec<_>>();
+        Ok(TypeHierarchy {
+            symbol_name,
+            declaration: TypeHierarchyEntry::from_outline_node(outline_node),
+            implementations,
+        })
+    }
+
     pub async fn find_sub_symbol_to_probe_with_name(
         &self,
         parent_symbol_name: &str,
@@ -2205,6 +2533,204 @@ We also believe this symbol needs to be probed because of:
     /// We want to check for followups on the functions which implies that we can
     /// simply look at the places where these functions are being used and then just
     /// do go-to-reference on it
+    /// Builds the dependency-changed prompt the followups send to every
+    /// affected outline node. When the outline diff finds structural changes
+    /// we send its summary with the updated signature instead of dumping
+    /// both full implementations, which keeps the prompt small and the
+    /// followup precise; body-only edits keep the full before and after
+    /// since the behavior change is all that is left to describe
+    async fn dependency_changed_prompt(
+        &self,
+        symbol_name: &str,
+        fs_file_path: &str,
+        original_code: &str,
+        edited_code: &str,
+    ) -> String {
+        let outline_diff = self
+            .tools
+            .invoke(ToolInput::OutlineDiff(OutlineDiffRequest::new(
+                fs_file_path.to_owned(),
+                original_code.to_owned(),
+                edited_code.to_owned(),
+            )))
+            .await
+            .ok()
+            .and_then(|output| output.get_outline_diff_output());
+        let change_description = match outline_diff {
+            Some(outline_diff) if !outline_diff.is_empty() => {
+                let structural_summary = outline_diff.to_summary();
+                let updated_signature = edited_code.lines().next().unwrap_or("").trim();
+                format!(
+                    r#"<structural_changes>
+{structural_summary}
+</structural_changes>
+<updated_signature>
+{updated_signature}
+</updated_signature>"#
+                )
+            }
+            _ => format!(
+                r#"<original_implementation>
+{original_code}
+</original_implementation>
+<updated_implementation>
+{edited_code}
+</updated_implementation>"#
+            ),
+        };
+        format!(
+            r#"A dependency of this code has changed. You are given the list of changes below:
+<dependency>
+<name>
+{symbol_name}
+</name>
+<fs_file_path>
+{fs_file_path}
+</fs_file_path>
+{change_description}
+</dependency>
+Please update this code to accommodate these changes. Consider:
+1. Method signature changes (parameters, return types)
+2. Behavioural changes in the dependency
+3. Potential side effects or new exceptions
+4. Deprecated features that should no longer be used
+5. If no changes are required, do not make any changes to the code! I do not want to review code if no changes are required."#
+        )
+    }
+
+    /// Records what the user chose on a signature-change preview, the next
+    /// followup pass over the same symbol consumes it
+    pub async fn record_signature_change_decision(
+        &self,
+        fs_file_path: &str,
+        symbol_name: &str,
+        action: SignatureChangeAction,
+    ) {
+        println!(
+            "tool_box::signature_change::decision::({})::({})::({:?})",
+            fs_file_path, symbol_name, action
+        );
+        self.signature_change_decisions
+            .record(fs_file_path, symbol_name, action)
+            .await;
+    }
+
+    /// Computes the blast radius of an edit before it is applied: when the
+    /// outline diff sees a signature change we gather the call sites of the
+    /// symbol and group them per file with sample snippets. Body-only edits
+    /// come back as None since the callers are unaffected
+    pub async fn signature_change_impact(
+        &self,
+        fs_file_path: &str,
+        symbol_name: &str,
+        original_code: &str,
+        edited_code: &str,
+        message_properties: SymbolEventMessageProperties,
+    ) -> Result<Option<SignatureChangeImpact>, SymbolError> {
+        let outline_diff = self
+            .tools
+            .invoke(ToolInput::OutlineDiff(OutlineDiffRequest::new(
+                fs_file_path.to_owned(),
+                original_code.to_owned(),
+                edited_code.to_owned(),
+            )))
+            .await
+            .ok()
+            .and_then(|output| output.get_outline_diff_output());
+        let changed_signatures = match outline_diff {
+            Some(outline_diff) if !outline_diff.signature_changed().is_empty() => {
+                outline_diff.signature_changed().to_vec()
+            }
+            _ => return Ok(None),
+        };
+        // anchor the reference search on the symbol as it sits in the file
+        // right now, before the edit lands
+        let outline_node = self
+            .get_ouline_nodes_grouped_fresh(fs_file_path, message_properties.clone())
+            .await
+            .unwrap_or_default()
+            .into_iter()
+            .find(|outline_node| outline_node.name() == symbol_name)
+            .ok_or(SymbolError::SymbolNotFound)?;
+        let reference_locations = self
+            .go_to_references(
+                fs_file_path.to_owned(),
+                outline_node.identifier_range().start_position(),
+                message_properties.clone(),
+            )
+            .await?
+            .locations();
+        // pull the line each call sits on so the preview can show how the
+        // file uses the symbol today
+        let mut call_sites = vec![];
+        for reference_location in reference_locations.into_iter() {
+            let snippet = self
+                .file_open(
+                    reference_location.fs_file_path().to_owned(),
+                    message_properties.clone(),
+                )
+                .await
+                .ok()
+                .and_then(|response| {
+                    response
+                        .contents()
+                        .lines()
+                        .nth(reference_location.range().start_line())
+                        .map(|line| line.to_owned())
+                })
+                .unwrap_or_default();
+            call_sites.push((reference_location.fs_file_path().to_owned(), snippet));
+        }
+        Ok(Some(SignatureChangeImpact::new(
+            symbol_name.to_owned(),
+            fs_file_path.to_owned(),
+            changed_signatures,
+            summarize_call_sites(call_sites),
+        )))
+    }
+
+    /// The user chose to keep the callers untouched, so instead of fanning
+    /// edits out we ask for a backwards-compatible overload or adapter with
+    /// the old signature on the symbol's own outline node
+    async fn add_adapter_for_signature_change(
+        &self,
+        symbol_edited: &SymbolToEdit,
+        symbol_followup_bfs: &SymbolFollowupBFS,
+        hub_sender: UnboundedSender<SymbolEventMessage>,
+        message_properties: SymbolEventMessageProperties,
+        tool_properties: ToolProperties,
+    ) -> Result<(), SymbolError> {
+        let outline_node = self
+            .get_ouline_nodes_grouped_fresh(
+                symbol_edited.fs_file_path(),
+                message_properties.clone(),
+            )
+            .await
+            .unwrap_or_default()
+            .into_iter()
+            .find(|outline_node| outline_node.name() == symbol_edited.symbol_name())
+            .ok_or(SymbolError::SymbolNotFound)?;
+        let original_code = symbol_followup_bfs.original_code();
+        let edited_code = symbol_followup_bfs.edited_code();
+        let instruction = format!(
+            r#"The signature of this symbol has just changed and the callers must keep working without being edited. Add a backwards-compatible overload or thin adapter which keeps the old signature and delegates to the new implementation. Do not change the new implementation itself and do not touch any caller.
+<original_implementation>
+{original_code}
+</original_implementation>
+<updated_implementation>
+{edited_code}
+</updated_implementation>"#
+        );
+        self.send_edit_instruction_to_outline_node(
+            outline_node,
+            instruction,
+            hub_sender,
+            message_properties,
+            tool_properties,
+        )
+        .await
+    }
+
     async fn check_for_followups_on_functions(
         &self,
         outline_node: OutlineNodeContent,
@@ -2231,6 +2757,98 @@ We also believe this symbol needs to be probed because of:
         if references.is_ok() {
             reference_locations.extend(references.expect("is_ok to hold").locations());
         }
+        // the call hierarchy surfaces callers the reference search can miss
+        // (macro expansions, trait dispatch), so we merge its call sites in
+        // and let the agent reason about transitive callers as well
+        if let Ok(incoming_calls) = self
+            .call_hierarchy(
+                symbol_edited.fs_file_path(),
+                outline_node.identifier_range().start_position(),
+                CallHierarchyDirection::Incoming,
+                message_properties.clone(),
+            )
+            .await
+        {
+            for item in incoming_calls.items().into_iter() {
+                for from_range in item.from_ranges().to_vec().into_iter() {
+                    let location =
+                        ReferenceLocation::new(item.fs_file_path().to_owned(), from_range);
+                    if !reference_locations.iter().any(|reference_location| {
+                        reference_location.fs_file_path() == location.fs_file_path()
+                            && reference_location.range() == location.range()
+                    }) {
+                        reference_locations.push(location);
+                    }
+                }
+            }
+        }
+
+        // a private symbol cannot have consumers outside its own file, so we
+        // drop the cross-file locations instead of spawning followups which
+        // can only be false positives
+        let visibility = outline_node.visibility();
+        if visibility.is_private() {
+            reference_locations.retain(|reference_location| {
+                reference_location.fs_file_path() == symbol_edited.fs_file_path()
+            });
+            println!(
+                "tool_box::check_for_followups::private_symbol::({})::same_file_references({})",
+                outline_node.name(),
+                reference_locations.len()
+            );
+        }
+        // an edit which widens or narrows visibility is usually accidental,
+        // flag it so it shows up in the logs next to the followups it causes
+        let edited_visibility = declaration_visibility(
+            outline_node.language(),
+            outline_node.name(),
+            symbol_followup_bfs.edited_code(),
+        );
+        if declaration_visibility(
+            outline_node.language(),
+            outline_node.name(),
+            symbol_followup_bfs.original_code(),
+        ) != edited_visibility
+        {
+            println!(
+                "tool_box::check_for_followups::visibility_changed::({})::now({:?})",
+                outline_node.name(),
+                edited_visibility
+            );
+        }
+
+        // the editor can preview the blast radius of a signature change and
+        // record what the user chose, a recorded decision wins over the
+        // default of rewriting every caller
+        match self
+            .signature_change_decisions
+            .take(symbol_edited.fs_file_path(), outline_node.name())
+            .await
+        {
+            Some(SignatureChangeAction::Cancel) => {
+                println!(
+                    "tool_box::check_for_followups::signature_change::cancelled::({})",
+                    outline_node.name()
+                );
+                return Ok(vec![]);
+            }
+            Some(SignatureChangeAction::AddAdapter) => {
+                println!(
+                    "tool_box::check_for_followups::signature_change::add_adapter::({})",
+                    outline_node.name()
+                );
+                self.add_adapter_for_signature_change(
+                    symbol_edited,
+                    symbol_followup_bfs,
+                    hub_sender.clone(),
+                    message_properties.clone(),
+                    tool_properties.clone(),
+                )
+                .await?;
+                return Ok(vec![]);
+            }
+            Some(SignatureChangeAction::UpdateAllCallers) | None => {}
+        }
 
         // Now that we have the reference locations we want to execute changes to the outline nodes containing the reference
         let outline_nodes_to_edit = stream::iter(
@@ -2263,31 +2881,19 @@ We also believe this symbol needs to be probed because of:
         let function_file_path = symbol_followup_bfs.symbol_edited().fs_file_path();
         let original_code = symbol_followup_bfs.original_code();
         let edited_code = symbol_followup_bfs.edited_code();
+        let prompt = self
+            .dependency_changed_prompt(
+                function_name,
+                function_file_path,
+                original_code,
+                edited_code,
+            )
+            .await;
         for outline_node_to_edit in outline_nodes_to_edit.to_vec().into_iter() {
             let _ = self
                 .send_edit_instruction_to_outline_node(
                     outline_node_to_edit,
-                    format!(r#"A dependency of this code has changed. You are given the list of changes below:
-<dependency>
-<name>
-{function_name}
-</name>
-<fs_file_path>
-{function_file_path}
-</fs_file_path>
-<original_implementation>
-{original_code}
-</original_implementation>
-<updated_implementation>
-{edited_code}
-</updated_implementation>
-</dependency>
-Please update this code to accommodate these changes. Consider:
-1. Method signature changes (parameters, return types)
-2. Behavioural changes in the dependency
-3. Potential side effects or new exceptions
-4. Deprecated features that should no longer be used
-5. If no changes are required, do not make any changes to the code! I do not want to review code if no changes are required."#),
+                    prompt.to_owned(),
                     hub_sender.clone(),
                     message_properties.clone(),
                     tool_properties.clone(),
@@ -2440,43 +3046,28 @@ Please update this code to accommodate these changes. Consider:
             let outline_node_range = outline_node.range();
             reference_locations.iter().any(|(reference_location, _)| {
                 outline_node_range.contains_check_line_column(reference_location.range())
-            })
-        })
-        .filter(|outline_node| outline_node.name() == class_symbol_name)
-        .filter(|outline_node| {
-            outline_node.children().into_iter().any(|child_node| {
-                let child_range = child_node.range();
-                reference_locations.iter().any(|(reference_location, _)| {
-                    child_range.contains_check_line_column(reference_location.range())
-                })
-            })
-        })
-        .collect::<Vec<_>>();
-
-        // now we can execute the edits on each of these files
-        let prompt = format!(
-            r#"A dependency of this code has changed. You are given the list of changes below:
-<dependency>
-<name>
-{class_symbol_name}
-</name>
-<fs_file_path>
-{class_fs_file_path}
-</fs_file_path>
-<original_implementation>
-{original_code}
-</original_implementation>
-<updated_implementation>
-{edited_code}
-</updated_implementation>
-</dependency>
-Please update this code to accommodate these changes. Consider:
-1. Method signature changes (parameters, return types)
-2. Behavioural changes in the dependency
-3. Potential side effects or new exceptions
-4. Deprecated features that should no longer be used
-5. If no changes are required, do not make any changes to the code! I do not want to review code if no changes are required."#
-        );
+            })
+        })
+        .filter(|outline_node| outline_node.name() == class_symbol_name)
+        .filter(|outline_node| {
+            outline_node.children().into_iter().any(|child_node| {
+                let child_range = child_node.range();
+                reference_locations.iter().any(|(reference_location, _)| {
+                    child_range.contains_check_line_column(reference_location.range())
+                })
+            })
+        })
+        .collect::<Vec<_>>();
+
+        // now we can execute the edits on each of these files
+        let prompt = self
+            .dependency_changed_prompt(
+                class_symbol_name,
+                class_fs_file_path,
+                original_code,
+                edited_code,
+            )
+            .await;
 
         println!(
             "tool_box::check_for_followups_class_definitions::symbol_name({})::outline_nodes({})",
@@ -4801,14 +5392,156 @@ Make the necessary changes if required making sure that nothing breaks"#
             &fs_file_path, &position
         );
 
-        let reference_locations = self
-            .tools
+        // when the references endpoint keeps failing we stop hammering it and
+        // serve the outline fallback until the breaker lets a probe through
+        if !self.references_breaker.should_allow().await {
+            println!("tool_box::go_to_references::circuit_open::outline_fallback");
+            return Ok(self
+                .references_from_outline_fallback(&fs_file_path, &position)
+                .await);
+        }
+
+        let start_instant = std::time::Instant::now();
+        let mut reference_locations = match self.tools.invoke(input).await {
+            Ok(output) => output
+                .get_references()
+                .ok_or(SymbolError::WrongToolOutput)?,
+            Err(e) => {
+                self.references_breaker.record_failure().await;
+                return Err(SymbolError::ToolError(e));
+            }
+        };
+        if self.references_breaker.record_success().await {
+            let _ = message_properties
+                .ui_sender()
+                .send(UIEventWithID::tool_recovered(
+                    message_properties.root_request_id().to_owned(),
+                    message_properties.request_id_str().to_owned(),
+                    self.references_breaker.name().to_owned(),
+                ));
+        }
+        self.lsp_health
+            .record(
+                reference_locations.is_empty(),
+                start_instant.elapsed(),
+            )
+            .await;
+        // a reference request which comes back empty on a degraded language
+        // server is most likely bad data, retry once after a warmup delay
+        if reference_locations.is_empty()
+            && self.lsp_health.is_degraded().await
+        {
+            tokio::time::sleep(self.lsp_health.warmup_retry_delay()).await;
+            let retry_input = ToolInput::GoToReference(GoToReferencesRequest::new(
+                fs_file_path.to_owned(),
+                position.clone(),
+                message_properties.editor_url().to_owned(),
+            ));
+            let retry_instant = std::time::Instant::now();
+            reference_locations = self
+                .tools
+                .invoke(retry_input)
+                .await
+                .map_err(|e| SymbolError::ToolError(e))?
+                .get_references()
+                .ok_or(SymbolError::WrongToolOutput)?;
+            self.lsp_health
+                .record(
+                    reference_locations.is_empty(),
+                    retry_instant.elapsed(),
+                )
+                .await;
+        }
+        if let Some(notice) = self.lsp_health.degraded_notice().await {
+            let _ = message_properties
+                .ui_sender()
+                .send(UIEventWithID::lsp_degraded(
+                    message_properties.root_request_id().to_owned(),
+                    message_properties.request_id_str().to_owned(),
+                    notice,
+                ));
+        }
+        Ok(reference_locations.filter_out_same_position_location(&fs_file_path, &position))
+    }
+
+    /// Asks the editor for the incoming or outgoing calls of a symbol, the
+    /// followups use the incoming direction to walk transitive callers
+    /// instead of only the direct references
+    pub async fn call_hierarchy(
+        &self,
+        fs_file_path: &str,
+        position: Position,
+        direction: CallHierarchyDirection,
+        message_properties: SymbolEventMessageProperties,
+    ) -> Result<CallHierarchyResponse, SymbolError> {
+        println!(
+            "tool_box::call_hierarchy::fs_file_path({})::direction({:?})",
+            fs_file_path, direction
+        );
+        let input = ToolInput::CallHierarchy(CallHierarchyRequest::new(
+            fs_file_path.to_owned(),
+            position,
+            direction,
+            message_properties.editor_url().to_owned(),
+        ));
+        self.tools
             .invoke(input)
             .await
             .map_err(|e| SymbolError::ToolError(e))?
-            .get_references()
-            .ok_or(SymbolError::WrongToolOutput)?;
-        Ok(reference_locations.filter_out_same_position_location(&fs_file_path, &position))
+            .downcast::<CallHierarchyResponse>()
+            .ok_or(SymbolError::WrongToolOutput)
+    }
+
+    /// Renames a symbol across the workspace through the language server, the
+    /// followup system prefers this over LLM driven per-reference edits when
+    /// the change is a pure rename
+    pub async fn rename_symbol(
+        &self,
+        fs_file_path: &str,
+        position: Position,
+        new_name: &str,
+        message_properties: SymbolEventMessageProperties,
+    ) -> Result<LSPRenameResponse, SymbolError> {
+        println!(
+            "tool_box::rename_symbol::fs_file_path({})::new_name({})",
+            fs_file_path, new_name
+        );
+        let input = ToolInput::Rename(LSPRenameRequest::new(
+            fs_file_path.to_owned(),
+            position,
+            new_name.to_owned(),
+            message_properties.editor_url().to_owned(),
+        ));
+        self.tools
+            .invoke(input)
+            .await
+            .map_err(|e| SymbolError::ToolError(e))?
+            .downcast::<LSPRenameResponse>()
+            .ok_or(SymbolError::WrongToolOutput)
+    }
+
+    /// Degraded alternative for references while the breaker is open, we
+    /// point at the identifiers of the outline nodes enclosing the position
+    /// from the in-memory tracker instead of doing an editor round trip
+    async fn references_from_outline_fallback(
+        &self,
+        fs_file_path: &str,
+        position: &Position,
+    ) -> GoToReferencesResponse {
+        let locations = self
+            .get_outline_nodes_grouped(fs_file_path)
+            .await
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|outline_node| outline_node.range().contains_position(position))
+            .map(|outline_node| {
+                ReferenceLocation::new(
+                    outline_node.fs_file_path().to_owned(),
+                    outline_node.identifier_range().clone(),
+                )
+            })
+            .collect::<Vec<_>>();
+        GoToReferencesResponse::new(locations)
     }
 
     async fn _swe_bench_test_tool(
@@ -5095,6 +5828,56 @@ instruction:
         //     }
         // }
 
+        // run the language formatter over the edited file first, the edit
+        // models produce correct but badly formatted output often enough
+        let format_request =
+            ToolInput::FormatCode(FormatCodeRequest::new(fs_file_path.to_owned(), None));
+        match self.tools.invoke(format_request).await {
+            Ok(output) => {
+                if let Some(response) = output.get_format_code_response() {
+                    println!(
+                        "tool_box::check_code_correctness::format_code::formatted({})",
+                        response.formatted()
+                    );
+                }
+            }
+            Err(e) => {
+                println!(
+                    "tool_box::check_code_correctness::format_code::error({:?})",
+                    e
+                );
+            }
+        }
+
+        // for rust the compiler ships the fix for a lot of diagnostics,
+        // apply the machine-applicable ones before asking any LLM
+        let is_rust_file = self
+            .editor_parsing
+            .for_file_path(fs_file_path)
+            .map(|language_config| language_config.is_rust())
+            .unwrap_or(false);
+        if is_rust_file {
+            let suggestions_request = ToolInput::ApplyCompilerSuggestions(
+                ApplyCompilerSuggestionsRequest::new(fs_file_path.to_owned()),
+            );
+            match self.tools.invoke(suggestions_request).await {
+                Ok(output) => {
+                    if let Some(response) = output.get_apply_compiler_suggestions_response() {
+                        println!(
+                            "tool_box::check_code_correctness::apply_compiler_suggestions::applied({})",
+                            response.applied_suggestions()
+                        );
+                    }
+                }
+                Err(e) => {
+                    println!(
+                        "tool_box::check_code_correctness::apply_compiler_suggestions::error({:?})",
+                        e
+                    );
+                }
+            }
+        }
+
         // Now we check for LSP diagnostics
         let lsp_diagnostics_output = self
             .get_lsp_diagnostics(fs_file_path, &edited_range, message_properties.to_owned())
@@ -5166,6 +5949,43 @@ instruction:
                 symbol_identifier,
                 hub_sender,
             )| async move {
+                // deterministic rules cover the mechanical diagnostics, the
+                // LLM only gets the ones no rule understands
+                if let Some(auto_fix) = auto_fix::try_auto_fix(
+                    diagnostic_with_snippet.message(),
+                    diagnostic_with_snippet.snippet(),
+                ) {
+                    println!(
+                        "tool_box::check_code_correctness::auto_fix::rule({})",
+                        auto_fix.rule()
+                    );
+                    // the snippet covers whole lines, so the replacement
+                    // range does too
+                    let snippet_end_column = diagnostic_with_snippet
+                        .snippet()
+                        .lines()
+                        .last()
+                        .map(|line| line.chars().count())
+                        .unwrap_or(0);
+                    let snippet_range = Range::new(
+                        Position::new(diagnostic_with_snippet.range().start_line(), 0, 0),
+                        Position::new(
+                            diagnostic_with_snippet.range().end_line(),
+                            snippet_end_column,
+                            0,
+                        ),
+                    );
+                    let _ = self
+                        .apply_edits_to_editor(
+                            fs_file_path,
+                            &snippet_range,
+                            auto_fix.fixed_snippet(),
+                            true,
+                            message_properties.to_owned(),
+                        )
+                        .await?;
+                    return Ok(());
+                }
                 // get quick actions for diagnostics range
                 let quick_fix_actions = self
                     .get_quick_fix_actions(
@@ -5616,6 +6436,17 @@ FILEPATH: {fs_file_path}
                 .collect::<Vec<_>>()
                 .join("\n")
         });
+        // hand the model the canonical import path of the symbol so the
+        // imports it generates are right the first time instead of getting
+        // patched up by the correction loop
+        let extra_context = match symbol_to_edit.as_ref().and_then(|symbol_name| {
+            module_path::canonical_import_path(fs_file_path, symbol_name, &language)
+        }) {
+            Some(import_path) => format!(
+                "{extra_context}\nThe canonical import path for the symbol being edited is `{import_path}`, use this exact path when referring to it in imports."
+            ),
+            None => extra_context,
+        };
         let session_id = message_properties.root_request_id().to_owned();
         let exchange_id = message_properties.request_id_str().to_owned();
         let llm_properties = message_properties.llm_properties().clone();
@@ -5941,6 +6772,115 @@ FILEPATH: {fs_file_path}
             .ok_or(SymbolError::WrongToolOutput)
     }
 
+    /// Runs a long running command (builds, test suites) without blocking the
+    /// turn on it. The command is spawned without waiting for exit and we poll
+    /// the pending output on a cadence, returning as soon as a check-in decides
+    /// the agent should act: either the output shows an early failure or the
+    /// wait budget has run out and the command should be killed
+    pub async fn use_terminal_command_with_checkins(
+        &self,
+        command: &str,
+        checkin_interval_seconds: u64,
+        max_wait_seconds: u64,
+        message_properties: SymbolEventMessageProperties,
+    ) -> Result<LongRunningCommandStatus, SymbolError> {
+        let _ = self
+            .use_terminal_command(command, false, message_properties.clone())
+            .await?;
+        let started = std::time::Instant::now();
+        let mut partial_output = String::new();
+        loop {
+            tokio::time::sleep(std::time::Duration::from_secs(checkin_interval_seconds)).await;
+            if message_properties.cancellation_token().is_cancelled() {
+                return Err(SymbolError::CancelledResponseStream);
+            }
+            if let Some(new_output) = self
+                .grab_pending_subprocess_output(message_properties.clone())
+                .await?
+            {
+                partial_output.push_str(&new_output);
+            }
+            let elapsed_seconds = started.elapsed().as_secs();
+            // the wait budget is a hard stop, no point asking the model
+            // once we are past it
+            let decision = if elapsed_seconds >= max_wait_seconds {
+                CheckinDecision::Kill
+            } else {
+                self.checkin_decision_from_llm(
+                    command,
+                    &partial_output,
+                    elapsed_seconds,
+                    max_wait_seconds,
+                    message_properties.clone(),
+                )
+                .await
+                .unwrap_or_else(|| {
+                    CheckinDecision::from_partial_output(
+                        &partial_output,
+                        elapsed_seconds,
+                        max_wait_seconds,
+                    )
+                })
+            };
+            if !matches!(decision, CheckinDecision::KeepWaiting) {
+                return Ok(LongRunningCommandStatus::new(
+                    command.to_owned(),
+                    tail_for_checkin(&partial_output, CHECKIN_OUTPUT_BUDGET),
+                    elapsed_seconds,
+                    decision,
+                ));
+            }
+        }
+    }
+
+    /// Asks the model what to do with a long running command given its
+    /// partial output, None when the call fails or the response has no
+    /// parseable decision so the caller can fall back to the marker
+    /// heuristic
+    async fn checkin_decision_from_llm(
+        &self,
+        command: &str,
+        partial_output: &str,
+        elapsed_seconds: u64,
+        max_wait_seconds: u64,
+        message_properties: SymbolEventMessageProperties,
+    ) -> Option<CheckinDecision> {
+        let llm_properties = message_properties.llm_properties().clone();
+        let request = LLMClientCompletionRequest::new(
+            llm_properties.llm().clone(),
+            vec![LLMClientMessage::user(checkin_prompt(
+                command,
+                partial_output,
+                elapsed_seconds,
+                max_wait_seconds,
+            ))],
+            0.2,
+            None,
+        );
+        let (sender, _receiver) = tokio::sync::mpsc::unbounded_channel();
+        let response = self
+            .tools
+            .llm_broker()
+            .stream_completion(
+                llm_properties.api_key().clone(),
+                request,
+                llm_properties.provider().clone(),
+                vec![
+                    (
+                        "root_id".to_owned(),
+                        message_properties.root_request_id().to_owned(),
+                    ),
+                    ("event_type".to_owned(), "terminal_checkin".to_owned()),
+                ]
+                .into_iter()
+                .collect(),
+                sender,
+            )
+            .await
+            .ok()?;
+        CheckinDecision::from_llm_response(response.answer_up_until_now())
+    }
+
     /// Grabs full workspace diagnostics
     pub async fn grab_workspace_diagnostics(
         &self,
@@ -6181,13 +7121,22 @@ FILEPATH: {fs_file_path}
         apply_directly: bool,
         message_properties: SymbolEventMessageProperties,
     ) -> Result<EditorApplyResponse, SymbolError> {
-        let input = ToolInput::EditorApplyChange(EditorApplyRequest::new(
-            fs_file_path.to_owned(),
-            updated_code.to_owned(),
-            range.clone(),
-            message_properties.editor_url().to_owned(),
-            apply_directly,
-        ));
+        // attach the version of the document we generated the edit against so
+        // the editor can detect the apply racing with newer user edits
+        let expected_document_version = self.symbol_broker.get_document_version(fs_file_path).await;
+        // the file is about to change so the context packs built from it are
+        // stale
+        self.context_packs.invalidate_file(fs_file_path).await;
+        let input = ToolInput::EditorApplyChange(
+            EditorApplyRequest::new(
+                fs_file_path.to_owned(),
+                updated_code.to_owned(),
+                range.clone(),
+                message_properties.editor_url().to_owned(),
+                apply_directly,
+            )
+            .with_expected_document_version(expected_document_version),
+        );
         self.tools
             .invoke(input)
             .await
@@ -6581,14 +7530,75 @@ FILEPATH: {fs_file_path}
             .send(UIEventWithID::open_file_event(
                 message_properties.root_request_id().to_owned(),
                 message_properties.request_id_str().to_owned(),
-                fs_file_path,
+                fs_file_path.to_owned(),
             ));
-        self.tools
+        let file_open_response = self
+            .tools
             .invoke(request)
             .await
-            .map_err(|e| SymbolError::ToolError(e))?
-            .get_file_open_response()
-            .ok_or(SymbolError::WrongToolOutput)
+            .map_err(|e| SymbolError::ToolError(e))
+            .map(|response| {
+                response
+                    .get_file_open_response()
+                    .ok_or(SymbolError::WrongToolOutput)
+            })
+            .and_then(|response| response);
+        match file_open_response {
+            Ok(response) => Ok(response),
+            Err(e) => {
+                // the editor can fail to open files which belong to another
+                // repository or to vendored dependency sources, for those we
+                // fallback to reading the file from disk read-only so probes
+                // can still answer questions about third-party code
+                let cross_repo_resolver = CrossRepoResolver::new(self.editor_parsing.clone());
+                match cross_repo_resolver.open_file_read_only(&fs_file_path).await {
+                    Ok(response) => Ok(response),
+                    Err(_) => Err(e),
+                }
+            }
+        }
+    }
+
+    /// Opens a batch of files through a single invoke_many call on the
+    /// broker, responses come back in the same order as the paths with the
+    /// broker bounding how many opens hit the editor at once
+    pub async fn file_open_many(
+        &self,
+        fs_file_paths: Vec<String>,
+        message_properties: SymbolEventMessageProperties,
+    ) -> Vec<Result<OpenFileResponse, SymbolError>> {
+        let requests = fs_file_paths
+            .iter()
+            .map(|fs_file_path| {
+                let _ = message_properties
+                    .ui_sender()
+                    .send(UIEventWithID::open_file_event(
+                        message_properties.root_request_id().to_owned(),
+                        message_properties.request_id_str().to_owned(),
+                        fs_file_path.to_owned(),
+                    ));
+                ToolInput::OpenFile(OpenFileRequest::new(
+                    fs_file_path.to_owned(),
+                    message_properties.editor_url().to_owned(),
+                    None,
+                    None,
+                ))
+            })
+            .collect::<Vec<_>>();
+        self.tools
+            .invoke_many(requests)
+            .await
+            .into_iter()
+            .map(|response| {
+                response
+                    .map_err(|e| SymbolError::ToolError(e))
+                    .and_then(|response| {
+                        response
+                            .get_file_open_response()
+                            .ok_or(SymbolError::WrongToolOutput)
+                    })
+            })
+            .collect()
     }
 
     async fn find_in_file(
@@ -6634,14 +7644,50 @@ FILEPATH: {fs_file_path}
         let request = ToolInput::GoToDefinition(GoToDefinitionRequest::new(
             fs_file_path.to_owned(),
             message_properties.editor_url().to_owned(),
-            position,
+            position.clone(),
         ));
-        self.tools
+        let start_instant = std::time::Instant::now();
+        let mut response = self
+            .tools
             .invoke(request)
             .await
             .map_err(|e| SymbolError::ToolError(e))?
             .get_go_to_definition()
-            .ok_or(SymbolError::WrongToolOutput)
+            .ok_or(SymbolError::WrongToolOutput)?;
+        self.lsp_health
+            .record(response.is_empty(), start_instant.elapsed())
+            .await;
+        // empty results on a degraded language server usually mean its still
+        // warming up, give it a moment and retry the request once
+        if response.is_empty() && self.lsp_health.is_degraded().await {
+            tokio::time::sleep(self.lsp_health.warmup_retry_delay()).await;
+            let retry_request = ToolInput::GoToDefinition(GoToDefinitionRequest::new(
+                fs_file_path.to_owned(),
+                message_properties.editor_url().to_owned(),
+                position,
+            ));
+            let retry_instant = std::time::Instant::now();
+            response = self
+                .tools
+                .invoke(retry_request)
+                .await
+                .map_err(|e| SymbolError::ToolError(e))?
+                .get_go_to_definition()
+                .ok_or(SymbolError::WrongToolOutput)?;
+            self.lsp_health
+                .record(response.is_empty(), retry_instant.elapsed())
+                .await;
+        }
+        if let Some(notice) = self.lsp_health.degraded_notice().await {
+            let _ = message_properties
+                .ui_sender()
+                .send(UIEventWithID::lsp_degraded(
+                    message_properties.root_request_id().to_owned(),
+                    message_properties.request_id_str().to_owned(),
+                    notice,
+                ));
+        }
+        Ok(response)
     }
 
     pub async fn edits_required_full_symbol(
@@ -6768,7 +7814,11 @@ FILEPATH: {fs_file_path}
                         .await?;
                     Ok(snippet_node)
                 } else {
-                    Err(SymbolError::SnippetNotFound)
+                    // the symbol is not even mentioned in the file we were
+                    // pointed at, ask the language server to search the whole
+                    // workspace for it before giving up
+                    self.find_snippet_via_workspace_symbols(symbol_name, message_properties)
+                        .await
                 }
             } else {
                 // if we have multiple outline nodes, then we need to select
@@ -6789,6 +7839,55 @@ FILEPATH: {fs_file_path}
         }
     }
 
+    /// Last resort lookup for a symbol: a workspace-wide symbol search
+    /// through the language server, used when the outline lookup and the
+    /// find-in-file both came up empty. The results are typed so an exact
+    /// name match is enough to anchor on
+    async fn find_snippet_via_workspace_symbols(
+        &self,
+        symbol_name: &str,
+        message_properties: SymbolEventMessageProperties,
+    ) -> Result<Snippet, SymbolError> {
+        println!(
+            "tool_box::find_snippet_via_workspace_symbols::symbol_name({})",
+            symbol_name
+        );
+        let response = self
+            .tools
+            .invoke(ToolInput::WorkspaceSymbol(WorkspaceSymbolRequest::new(
+                symbol_name.to_owned(),
+                message_properties.editor_url().to_owned(),
+            )))
+            .await
+            .map_err(|e| SymbolError::ToolError(e))?
+            .downcast::<WorkspaceSymbolResponse>()
+            .ok_or(SymbolError::WrongToolOutput)?;
+        let matching_symbol = response
+            .symbols()
+            .iter()
+            .find(|symbol| symbol.name() == symbol_name)
+            .ok_or(SymbolError::SnippetNotFound)?;
+        let outline_nodes = self
+            .get_ouline_nodes_grouped_fresh(
+                matching_symbol.fs_file_path(),
+                message_properties.clone(),
+            )
+            .await
+            .ok_or(SymbolError::OutlineNodeNotFound(symbol_name.to_owned()))?;
+        let mut outline_nodes = self.grab_symbols_from_outline(outline_nodes, symbol_name);
+        if outline_nodes.is_empty() {
+            return Err(SymbolError::SnippetNotFound);
+        }
+        let outline_node = outline_nodes.remove(0);
+        Ok(Snippet::new(
+            outline_node.name().to_owned(),
+            outline_node.range().clone(),
+            outline_node.fs_file_path().to_owned(),
+            outline_node.content().to_owned(),
+            outline_node,
+        ))
+    }
+
     /// Finds the changed symbols which are present in the file using simple git-diff
     ///
     /// Coming soon:
@@ -8584,7 +9683,7 @@ FILEPATH: {fs_file_path}
             .into_iter()
             .filter(|outline_node| {
                 let fs_file_path = outline_node.fs_file_path();
-                if fs_file_path.contains("node_modules")
+                if super::cross_repo::is_external_dependency_path(fs_file_path)
                     || fs_file_path.contains(".rustup")
                     || fs_file_path.contains(".cargo")
                 {
@@ -4,6 +4,8 @@
 
 use std::collections::HashMap;
 
+use llm_client::clients::types::LLMClientUsageStatistics;
+
 use crate::{
     agentic::tool::{
         code_symbol::models::anthropic::StepListItem, input::ToolInputPartial, r#type::ToolType,
@@ -137,6 +139,17 @@ impl UIEventWithID {
         }
     }
 
+    /// The final probe reply if this event carries one, lets callers tee
+    /// the event stream and persist the answer on the side
+    pub fn probe_finished_reply(&self) -> Option<&str> {
+        match &self.event {
+            UIEvent::RequestEvent(RequestEvents::ProbeFinished(finished)) => {
+                Some(finished.reply.as_str())
+            }
+            _ => None,
+        }
+    }
+
     pub fn range_selection_for_edit(
         request_id: String,
         symbol_identifier: SymbolIdentifier,
@@ -710,10 +723,44 @@ impl UIEventWithID {
             exchange_id: session_id,
             event: UIEvent::Error(ErrorEvent {
                 message: error_message,
+                category: None,
+                code: None,
+                remediation: None,
+            }),
+        }
+    }
+
+    /// Error event carrying the classified taxonomy entry so the editor
+    /// can show the remediation instead of a bare message
+    pub fn structured_error(
+        session_id: String,
+        structured_error: crate::errors::StructuredError,
+    ) -> Self {
+        Self {
+            request_id: session_id.to_owned(),
+            exchange_id: session_id,
+            event: UIEvent::Error(ErrorEvent {
+                message: structured_error.message().to_owned(),
+                category: Some(structured_error.category()),
+                code: Some(structured_error.code()),
+                remediation: structured_error.remediation(),
             }),
         }
     }
 
+    /// An MCP server went down or came back, raised by the health monitor
+    /// outside any exchange so both ids carry the session
+    pub fn mcp_server_status(
+        session_id: String,
+        event: crate::agentic::tool::mcp::health::McpServerStatusEvent,
+    ) -> Self {
+        Self {
+            request_id: session_id.to_owned(),
+            exchange_id: session_id,
+            event: UIEvent::FrameworkEvent(FrameworkEvent::McpServerStatus(event)),
+        }
+    }
+
     pub fn tool_found(session_id: String, exchange_id: String, tool_type: ToolType) -> Self {
         Self {
             request_id: session_id.to_owned(),
@@ -757,11 +804,82 @@ impl UIEventWithID {
             )),
         }
     }
+
+    /// The session crossed the cost budget it was given, the editor should
+    /// ask the user whether to continue before anything else runs
+    pub fn cost_budget_exceeded(
+        session_id: String,
+        exchange_id: String,
+        consumed_tokens: u64,
+        estimated_cost_dollars: f64,
+        message: String,
+    ) -> Self {
+        Self {
+            request_id: session_id.to_owned(),
+            exchange_id,
+            event: UIEvent::FrameworkEvent(FrameworkEvent::CostBudgetExceeded(
+                CostBudgetExceededEvent {
+                    consumed_tokens,
+                    estimated_cost_dollars,
+                    message,
+                },
+            )),
+        }
+    }
+
+    /// Tells the editor that a tool which had its circuit breaker open is
+    /// healthy again and the primary path is back in use
+    pub fn tool_recovered(session_id: String, exchange_id: String, tool: String) -> Self {
+        Self {
+            request_id: session_id.to_owned(),
+            exchange_id,
+            event: UIEvent::FrameworkEvent(FrameworkEvent::ToolRecovered(ToolRecoveredEvent {
+                tool,
+            })),
+        }
+    }
+
+    /// Warns the editor that the language server looks degraded and lsp
+    /// backed results may be incomplete until it recovers
+    pub fn lsp_degraded(session_id: String, exchange_id: String, message: String) -> Self {
+        Self {
+            request_id: session_id.to_owned(),
+            exchange_id,
+            event: UIEvent::FrameworkEvent(FrameworkEvent::LSPDegraded(LSPDegradedEvent {
+                message,
+            })),
+        }
+    }
+
+    /// Tells the editor how many tokens the LLM call backing the current
+    /// step consumed
+    pub fn token_usage(
+        session_id: String,
+        exchange_id: String,
+        usage_statistics: LLMClientUsageStatistics,
+    ) -> Self {
+        Self {
+            request_id: session_id.to_owned(),
+            exchange_id,
+            event: UIEvent::FrameworkEvent(FrameworkEvent::TokenUsage(TokenUsageEvent {
+                usage_statistics,
+            })),
+        }
+    }
 }
 
 #[derive(Debug, serde::Serialize)]
 pub struct ErrorEvent {
     message: String,
+    /// stable category from the error taxonomy when the failure was
+    /// classified, editors branch on this instead of the message
+    #[serde(skip_serializing_if = "Option::is_none")]
+    category: Option<crate::errors::ErrorCategory>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    code: Option<&'static str>,
+    /// what the user can do about it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    remediation: Option<&'static str>,
 }
 
 #[derive(Debug, serde::Serialize)]
@@ -1311,6 +1429,34 @@ pub enum FrameworkEvent {
     ToolTypeFound(ToolTypeFoundEvent),
     ToolParameterFound(ToolParameterFoundEvent),
     ToolOutput(ToolOutputEvent),
+    TokenUsage(TokenUsageEvent),
+    CostBudgetExceeded(CostBudgetExceededEvent),
+    LSPDegraded(LSPDegradedEvent),
+    ToolRecovered(ToolRecoveredEvent),
+    /// an MCP server stopped answering health checks or came back
+    McpServerStatus(crate::agentic::tool::mcp::health::McpServerStatusEvent),
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct ToolRecoveredEvent {
+    tool: String,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct TokenUsageEvent {
+    usage_statistics: LLMClientUsageStatistics,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct LSPDegradedEvent {
+    message: String,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct CostBudgetExceededEvent {
+    consumed_tokens: u64,
+    estimated_cost_dollars: f64,
+    message: String,
 }
 
 #[derive(Debug, serde::Serialize)]
@@ -0,0 +1,238 @@
+//! Append-only audit log of tool invocations with offline replay
+//!
+//! Every invocation going through the broker appends one jsonl record with
+//! the tool, timing and a snapshot of the input and output. The read-only
+//! lsp tools serialize their full request into the record, which is enough
+//! for the replay endpoint to re-run a recorded trajectory against a live
+//! broker and compare what the tools answer today. Everything else (inputs
+//! carrying channels or editor state) only gets its debug representation,
+//! those records replay as skipped
+
+use std::path::PathBuf;
+
+use super::broker::ToolBroker;
+use super::determinism::RecordedNondeterministicInput;
+use super::input::ToolInput;
+use super::sensitive_files;
+use super::r#type::{Tool, ToolType};
+
+/// One tool invocation as it went through the broker
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ToolAuditRecord {
+    tool_type: ToolType,
+    recorded_at: chrono::DateTime<chrono::Utc>,
+    elapsed_ms: u128,
+    success: bool,
+    /// the serialized request for the replayable tools, None for inputs
+    /// which do not serialize cleanly
+    #[serde(default)]
+    replay_input: Option<serde_json::Value>,
+    /// debug representation of the input, always present so the log stays
+    /// readable even for the non-replayable tools
+    input_debug: String,
+    /// debug representation of the output on success
+    #[serde(default)]
+    output_debug: Option<String>,
+    /// the error on failure
+    #[serde(default)]
+    error: Option<String>,
+}
+
+impl ToolAuditRecord {
+    pub fn new(
+        input: &ToolInput,
+        elapsed: std::time::Duration,
+        output_debug: Option<String>,
+        error: Option<String>,
+    ) -> Self {
+        // edits to configuration and secret files carry the secret values
+        // in the request, those stay out of the trajectory file entirely
+        let masks_sensitive_file = sensitive_files::edited_file_path(input)
+            .map(|fs_file_path| sensitive_files::is_sensitive_file(fs_file_path))
+            .unwrap_or(false);
+        let (replay_input, input_debug, output_debug) = if masks_sensitive_file {
+            let fs_file_path = sensitive_files::edited_file_path(input)
+                .expect("masks_sensitive_file implies the path is present");
+            (
+                None,
+                format!("<sensitive file edit masked: {}>", fs_file_path),
+                output_debug.map(|_| "<sensitive file edit output masked>".to_owned()),
+            )
+        } else {
+            (input.replay_json(), format!("{:?}", input), output_debug)
+        };
+        Self {
+            tool_type: input.tool_type(),
+            recorded_at: chrono::Utc::now(),
+            elapsed_ms: elapsed.as_millis(),
+            success: error.is_none(),
+            replay_input,
+            input_debug,
+            output_debug,
+            error,
+        }
+    }
+
+    pub fn tool_type(&self) -> &ToolType {
+        &self.tool_type
+    }
+
+    /// Replaces the wall-clock timestamp, deterministic runs stamp their
+    /// records from the seeded clock instead
+    pub fn with_recorded_at(mut self, recorded_at: chrono::DateTime<chrono::Utc>) -> Self {
+        self.recorded_at = recorded_at;
+        self
+    }
+
+    /// Reconstructs the input for replay, only works for the records of
+    /// the replayable tools
+    pub fn replay_input(&self) -> Option<ToolInput> {
+        let replay_input = self.replay_input.clone()?;
+        ToolInput::from_replay_json(&self.tool_type, replay_input)
+    }
+}
+
+/// Append-only jsonl log of the audit records, one trajectory per file
+pub struct ToolAuditLog {
+    file_path: PathBuf,
+}
+
+impl ToolAuditLog {
+    pub fn new(file_path: PathBuf) -> Self {
+        Self { file_path }
+    }
+
+    pub async fn append(&self, record: ToolAuditRecord) {
+        let serialised = match serde_json::to_string(&record) {
+            Ok(serialised) => serialised,
+            Err(_) => return,
+        };
+        self.append_line(serialised).await;
+    }
+
+    /// Appends the seeded identifiers and timestamps a deterministic run
+    /// handed out. They share the trajectory file with the tool records
+    /// but sit on their own lines, `load` skips them during replay
+    pub async fn append_nondeterministic_inputs(
+        &self,
+        inputs: &[RecordedNondeterministicInput],
+    ) {
+        let serialised = match serde_json::to_string(
+            &serde_json::json!({ "nondeterministic_inputs": inputs }),
+        ) {
+            Ok(serialised) => serialised,
+            Err(_) => return,
+        };
+        self.append_line(serialised).await;
+    }
+
+    async fn append_line(&self, serialised: String) {
+        use tokio::io::AsyncWriteExt;
+        let file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.file_path)
+            .await;
+        if let Ok(mut file) = file {
+            let _ = file.write_all(serialised.as_bytes()).await;
+            let _ = file.write_all(b"\n").await;
+        }
+    }
+
+    /// Loads a trajectory file, malformed lines get skipped so a partially
+    /// written record never blocks replaying the rest
+    pub async fn load(trajectory_path: &std::path::Path) -> Vec<ToolAuditRecord> {
+        let contents = match tokio::fs::read_to_string(trajectory_path).await {
+            Ok(contents) => contents,
+            Err(_) => return vec![],
+        };
+        contents
+            .lines()
+            .filter_map(|line| serde_json::from_str::<ToolAuditRecord>(line).ok())
+            .collect()
+    }
+}
+
+/// What happened to a single record during replay
+#[derive(Debug, Clone, serde::Serialize)]
+pub enum ReplayOutcome {
+    /// the tool ran again and answered
+    Replayed { success: bool },
+    /// the record has no replayable input
+    Skipped,
+}
+
+/// One replayed record along with how the original invocation went, lining
+/// the two up is the whole point of replay
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ReplayedRecord {
+    tool_type: String,
+    original_success: bool,
+    outcome: ReplayOutcome,
+}
+
+/// Re-runs a recorded trajectory against the broker in order. Only the
+/// records carrying a serialized request get invoked again, the rest are
+/// reported as skipped
+pub async fn replay_trajectory(
+    tool_broker: &ToolBroker,
+    records: Vec<ToolAuditRecord>,
+) -> Vec<ReplayedRecord> {
+    let mut replayed = vec![];
+    for record in records.into_iter() {
+        let outcome = match record.replay_input() {
+            Some(input) => {
+                let result = tool_broker.invoke(input).await;
+                ReplayOutcome::Replayed {
+                    success: result.is_ok(),
+                }
+            }
+            None => ReplayOutcome::Skipped,
+        };
+        replayed.push(ReplayedRecord {
+            tool_type: record.tool_type().to_string(),
+            original_success: record.success,
+            outcome,
+        });
+    }
+    replayed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ToolAuditRecord;
+    use crate::agentic::tool::input::ToolInput;
+    use crate::agentic::tool::lsp::open_file::OpenFileRequest;
+    use crate::agentic::tool::r#type::ToolType;
+
+    #[test]
+    fn test_lsp_inputs_round_trip_through_the_record() {
+        let input = ToolInput::OpenFile(OpenFileRequest::new(
+            "/tmp/main.rs".to_owned(),
+            "http://localhost:6897".to_owned(),
+            None,
+            None,
+        ));
+        let record =
+            ToolAuditRecord::new(&input, std::time::Duration::from_millis(12), None, None);
+        let replayed = record.replay_input().expect("lsp inputs to be replayable");
+        assert!(matches!(replayed, ToolInput::OpenFile(_)));
+        assert_eq!(record.tool_type(), &ToolType::OpenFile);
+    }
+
+    #[test]
+    fn test_records_survive_serialization() {
+        let input = ToolInput::OpenFile(OpenFileRequest::new(
+            "/tmp/main.rs".to_owned(),
+            "http://localhost:6897".to_owned(),
+            None,
+            None,
+        ));
+        let record =
+            ToolAuditRecord::new(&input, std::time::Duration::from_millis(12), None, None);
+        let serialised = serde_json::to_string(&record).expect("record to serialize");
+        let loaded =
+            serde_json::from_str::<ToolAuditRecord>(&serialised).expect("record to deserialize");
+        assert!(loaded.replay_input().is_some());
+    }
+}
@@ -1,17 +1,23 @@
 use crate::{
-    agentic::symbol::identifier::LLMProperties, chunking::languages::TSLanguageParsing,
+    agentic::symbol::identifier::LLMProperties,
+    chunking::{editor_parsing::EditorParsing, languages::TSLanguageParsing},
     inline_completion::symbols_tracker::SymbolTrackerInline,
 };
 use async_trait::async_trait;
 use llm_client::broker::LLMBroker;
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex},
+};
 use tracing::error;
 
 use super::{
+    audit::{ToolAuditLog, ToolAuditRecord},
     code_edit::{
-        filter_edit::FilterEditOperationBroker, find::FindCodeSectionsToEdit,
-        models::broker::CodeEditBroker, search_and_replace::SearchAndReplaceEditing,
-        test_correction::TestCorrection, types::CodeEditingTool,
+        compiler_suggestions::CompilerSuggestionsClient, filter_edit::FilterEditOperationBroker,
+        find::FindCodeSectionsToEdit, models::broker::CodeEditBroker,
+        search_and_replace::SearchAndReplaceEditing, test_correction::TestCorrection,
+        types::CodeEditingTool,
     },
     code_symbol::{
         apply_outline_edit_to_range::ApplyOutlineEditsToRange, correctness::CodeCorrectnessBroker,
@@ -19,6 +25,7 @@ use super::{
         find_symbols_to_edit_in_context::FindSymbolsToEditInContext,
         followup::ClassSymbolFollowupBroker, important::CodeSymbolImportantBroker,
         initial_request_follow::CodeSymbolFollowInitialRequestBroker,
+        outline_diff::OutlineDiffTool,
         new_location::CodeSymbolNewLocation, new_sub_symbol::NewSubSymbolRequired,
         planning_before_code_edit::PlanningBeforeCodeEdit, probe::ProbeEnoughOrDeeper,
         probe_question_for_symbol::ProbeQuestionForSymbol,
@@ -26,16 +33,20 @@ use super::{
         reranking_symbols_for_editing_context::ReRankingSnippetsForCodeEditingContext,
         scratch_pad::ScratchPadAgentBroker, should_edit::ShouldEditCodeSymbol,
     },
+    determinism::DeterminismMode,
     devtools::screenshot::RequestScreenshot,
     editor::apply::EditorApply,
     errors::ToolError,
     feedback::feedback::FeedbackClientGenerator,
     file::{file_finder::ImportantFilesFinderBroker, semantic_search::SemanticSearch},
     filtering::broker::CodeToEditFormatterBroker,
-    git::{diff_client::GitDiffClient, edited_files::EditedFiles},
+    format::formatter::CodeFormatterClient,
+    git::{bisect::GitBisectClient, diff_client::GitDiffClient, edited_files::EditedFiles},
     grep::file::FindInFile,
+    helpers::cancellation_future::run_with_cancellation,
     input::{ToolInput, ToolInputPartial},
     lsp::{
+        call_hierarchy::CallHierarchyClient,
         create_file::LSPCreateFile,
         diagnostics::LSPDiagnostics,
         file_diagnostics::FileDiagnostics,
@@ -51,16 +62,30 @@ use super::{
         list_files::ListFilesClient,
         open_file::LSPOpenFile,
         quick_fix::{LSPQuickFixClient, LSPQuickFixInvocationClient},
+        rename::LSPRename,
         search_file::SearchFileContentClient,
         subprocess_spawned_output::SubProcessSpawnedPendingOutputClient,
         undo_changes::UndoChangesMadeDuringExchange,
+        workspace_symbol::WorkspaceSymbolClient,
+    },
+    mcp::{
+        health::{ping_server, McpHealthTracker, McpServerStatus, McpServerStatusEvent},
+        init::{reload_registry, respawn_server, McpError, McpRegistry, McpReloadSummary},
+        integration_tool::McpTool,
+        prompts::{
+            flatten_prompt_messages, get_server_prompt, list_server_prompts, McpPromptDescriptor,
+            McpServerPrompts,
+        },
+        resources::{flatten_resource_contents, McpResourceDescriptor, McpServerResources},
     },
-    mcp::init::discover_mcp_tools,
+    middleware::{ToolMiddleware, ToolMiddlewareDecision},
+    model_overrides::ToolModelOverrides,
     output::ToolOutput,
     plan::{
         add_steps::PlanAddStepClient, generator::StepGeneratorClient, reasoning::ReasoningClient,
         updater::PlanUpdaterClient,
     },
+    policy::ToolPolicy,
     r#type::{Tool, ToolRewardScale, ToolType},
     ref_filter::ref_filter::ReferenceFilterBroker,
     repo_map::generator::RepoMapGeneratorClient,
@@ -75,11 +100,37 @@ use super::{
     swe_bench::test_tool::SWEBenchTestTool,
     terminal::terminal::TerminalTool,
     test_runner::runner::TestRunner,
+    tree_sitter::{import_graph::ImportGraphClient, query::TreeSitterQueryClient},
+    workspace::transport::WorkspaceTransport,
 };
 
+/// how many invocations of the same tool run at once through invoke_many
+/// when no explicit limit is configured for it
+const DEFAULT_TOOL_CONCURRENCY: usize = 4;
+
 pub struct ToolBrokerConfiguration {
     editor_agent: Option<LLMProperties>,
     apply_edits_directly: bool,
+    /// set in remote-workspace mode, the file and terminal tools run against
+    /// the remote machine over this transport instead of the local one
+    remote_workspace: Option<Arc<dyn WorkspaceTransport + Send + Sync>>,
+    /// invocations of these tools get cut off after the configured duration
+    /// instead of stalling the whole symbol agent on a hung endpoint
+    tool_timeouts: HashMap<ToolType, std::time::Duration>,
+    /// results of these tools get cached on the broker keyed by the input,
+    /// repeated calls within a run come back without hitting the editor
+    cached_tools: HashSet<ToolType>,
+    /// which tools are allowed to run, usually loaded from the policy file
+    /// next to the index
+    tool_policy: ToolPolicy,
+    /// per-tool concurrency limits for invoke_many, tools without an entry
+    /// run at most DEFAULT_TOOL_CONCURRENCY invocations at once
+    tool_concurrency_limits: HashMap<ToolType, usize>,
+    /// per-tool model overrides, usually loaded from the overrides file
+    /// next to the index
+    model_overrides: ToolModelOverrides,
+    /// when set every invocation appends an audit record here
+    audit_log: Option<Arc<ToolAuditLog>>,
 }
 
 impl ToolBrokerConfiguration {
@@ -87,8 +138,70 @@ impl ToolBrokerConfiguration {
         Self {
             editor_agent,
             apply_edits_directly,
+            remote_workspace: None,
+            tool_timeouts: HashMap::new(),
+            cached_tools: HashSet::new(),
+            tool_policy: ToolPolicy::default(),
+            tool_concurrency_limits: HashMap::new(),
+            model_overrides: ToolModelOverrides::default(),
+            audit_log: None,
         }
     }
+
+    /// Replaces the default allow-everything policy, usually with one
+    /// loaded from the policy file next to the index
+    pub fn with_tool_policy(mut self, tool_policy: ToolPolicy) -> Self {
+        self.tool_policy = tool_policy;
+        self
+    }
+
+    pub fn with_remote_workspace(
+        mut self,
+        remote_workspace: Arc<dyn WorkspaceTransport + Send + Sync>,
+    ) -> Self {
+        self.remote_workspace = Some(remote_workspace);
+        self
+    }
+
+    /// Bounds how long a single invocation of this tool is allowed to run,
+    /// tools without an entry here keep running until they finish
+    pub fn with_tool_timeout(
+        mut self,
+        tool_type: ToolType,
+        timeout: std::time::Duration,
+    ) -> Self {
+        self.tool_timeouts.insert(tool_type, timeout);
+        self
+    }
+
+    /// Opts this tool into result caching, repeated invocations with the
+    /// same input within a run are answered from the broker cache until an
+    /// edit lands and invalidates it
+    pub fn with_cached_tool(mut self, tool_type: ToolType) -> Self {
+        self.cached_tools.insert(tool_type);
+        self
+    }
+
+    /// Bounds how many invocations of this tool run at once through
+    /// invoke_many, the default limit applies to tools without an entry
+    pub fn with_tool_concurrency_limit(mut self, tool_type: ToolType, limit: usize) -> Self {
+        self.tool_concurrency_limits.insert(tool_type, limit);
+        self
+    }
+
+    /// Replaces the default empty override map, usually with one loaded
+    /// from the overrides file next to the index
+    pub fn with_model_overrides(mut self, model_overrides: ToolModelOverrides) -> Self {
+        self.model_overrides = model_overrides;
+        self
+    }
+
+    /// Switches on the audit log, every invocation from then on appends a
+    /// record to the trajectory file behind it
+    pub fn with_audit_log(mut self, audit_log: Arc<ToolAuditLog>) -> Self {
+        self.audit_log = Some(audit_log);
+        self
+    }
 }
 
 // TODO(skcd): We want to use a different serializer and deserializer for this
@@ -96,7 +209,41 @@ impl ToolBrokerConfiguration {
 // sure that we do not store everything about the tool but a representation of it
 pub struct ToolBroker {
     tools: HashMap<ToolType, Box<dyn Tool + Send + Sync>>,
-    pub mcp_tools: Box<[ToolType]>,
+    /// the dynamic tools coming from MCP servers, behind a lock so a config
+    /// reload can swap servers in and out without restarting sidecar
+    mcp_registry: std::sync::RwLock<McpRegistry>,
+    /// kept around for the few places which need to make a quick llm call
+    /// outside of the tool map (terminal check-ins for example)
+    llm_client: Arc<LLMBroker>,
+    /// interceptors which wrap every invocation going through the broker,
+    /// registered at construction with with_middleware
+    middlewares: Vec<Box<dyn ToolMiddleware + Send + Sync>>,
+    /// per-tool invocation deadlines coming from the broker configuration
+    tool_timeouts: HashMap<ToolType, std::time::Duration>,
+    /// tools whose results get cached keyed on a hash of the input
+    cached_tools: HashSet<ToolType>,
+    /// cached results for the opted-in tools, dropped wholesale whenever an
+    /// edit goes through since we cannot tell which entries it stales
+    tool_result_cache: Mutex<HashMap<(ToolType, u64), ToolOutput>>,
+    /// which tools are allowed to run, replaceable at runtime through the
+    /// webserver so a policy change does not need a restart
+    tool_policy: std::sync::RwLock<ToolPolicy>,
+    /// per-tool concurrency limits applied by invoke_many
+    tool_concurrency_limits: HashMap<ToolType, usize>,
+    /// per-tool model overrides, replaceable at runtime through the
+    /// webserver just like the policy
+    model_overrides: std::sync::RwLock<ToolModelOverrides>,
+    /// when set every invocation appends an audit record here
+    audit_log: Option<Arc<ToolAuditLog>>,
+    /// runtime switch for reproducible runs: serialized fan-out, pinned
+    /// sampling seed and seeded identifiers recorded into the trace
+    determinism: Arc<DeterminismMode>,
+    /// last observed health per MCP server along with the respawn backoff
+    /// bookkeeping
+    mcp_health: Arc<McpHealthTracker>,
+    /// status transitions fan out here, the webserver streams them to the
+    /// editor
+    mcp_status_sender: tokio::sync::broadcast::Sender<McpServerStatusEvent>,
 }
 
 impl ToolBroker {
@@ -129,7 +276,7 @@ impl ToolBroker {
             ToolType::FindCodeSnippets,
             Box::new(FindCodeSectionsToEdit::new(
                 symbol_tracking,
-                language_broker,
+                language_broker.clone(),
                 code_edit_broker.clone(),
                 llm_client.clone(),
             )),
@@ -164,7 +311,10 @@ impl ToolBroker {
             Box::new(LSPGoToDefinition::new()),
         );
         tools.insert(ToolType::GoToReferences, Box::new(LSPGoToReferences::new()));
-        tools.insert(ToolType::OpenFile, Box::new(LSPOpenFile::new()));
+        tools.insert(
+            ToolType::OpenFile,
+            Box::new(LSPOpenFile::new(tool_broker_config.remote_workspace.clone())),
+        );
         tools.insert(ToolType::GrepInFile, Box::new(FindInFile::new()));
         tools.insert(
             ToolType::GoToImplementations,
@@ -390,10 +540,17 @@ impl ToolBroker {
                 llm_client.clone(),
                 fail_over_llm.clone(),
                 tool_broker_config.apply_edits_directly,
-                Arc::new(Box::new(LSPOpenFile::new())),
+                Arc::new(Box::new(LSPOpenFile::new(
+                    tool_broker_config.remote_workspace.clone(),
+                ))),
             )),
         );
         tools.insert(ToolType::GitDiff, Box::new(GitDiffClient::new()));
+        tools.insert(ToolType::GitBisect, Box::new(GitBisectClient::new()));
+        tools.insert(
+            ToolType::OutlineDiff,
+            Box::new(OutlineDiffTool::new(language_broker.clone())),
+        );
         tools.insert(
             ToolType::OutlineNodesUsingEditor,
             Box::new(OutlineNodesUsingEditorClient::new()),
@@ -452,12 +609,42 @@ impl ToolBroker {
             ToolType::ContextDriveHotStreakReply,
             Box::new(SessionHotStreakClient::new(llm_client.clone())),
         );
-        tools.insert(ToolType::TerminalCommand, Box::new(TerminalTool::new()));
+        tools.insert(
+            ToolType::TerminalCommand,
+            Box::new(TerminalTool::new(tool_broker_config.remote_workspace.clone())),
+        );
         tools.insert(
             ToolType::SearchFileContentWithRegex,
             Box::new(SearchFileContentClient::new()),
         );
-        tools.insert(ToolType::ListFiles, Box::new(ListFilesClient::new()));
+        tools.insert(
+            ToolType::TreeSitterQuery,
+            Box::new(TreeSitterQueryClient::new(language_broker.clone())),
+        );
+        tools.insert(
+            ToolType::FormatCode,
+            Box::new(CodeFormatterClient::new(Arc::new(EditorParsing::default()))),
+        );
+        tools.insert(
+            ToolType::ApplyCompilerSuggestions,
+            Box::new(CompilerSuggestionsClient::new()),
+        );
+        tools.insert(
+            ToolType::ImportGraph,
+            Box::new(ImportGraphClient::new(language_broker.clone())),
+        );
+        tools.insert(ToolType::Rename, Box::new(LSPRename::new()));
+        tools.insert(ToolType::CallHierarchy, Box::new(CallHierarchyClient::new()));
+        tools.insert(
+            ToolType::WorkspaceSymbol,
+            Box::new(WorkspaceSymbolClient::new()),
+        );
+        tools.insert(
+            ToolType::ListFiles,
+            Box::new(ListFilesClient::new(
+                tool_broker_config.remote_workspace.clone(),
+            )),
+        );
         tools.insert(
             ToolType::AskFollowupQuestions,
             Box::new(AskFollowupQuestions::new()),
@@ -485,7 +672,7 @@ impl ToolBroker {
         );
         tools.insert(
             ToolType::SemanticSearch,
-            Box::new(SemanticSearch::new(llm_client)),
+            Box::new(SemanticSearch::new(llm_client.clone())),
         );
         tools.insert(ToolType::FindFiles, Box::new(FindFilesClient::new()));
         tools.insert(
@@ -493,67 +680,560 @@ impl ToolBroker {
             Box::new(RequestScreenshot::new()),
         );
 
-        let mut mcp_tools = Vec::new();
-
-        for tool in discover_mcp_tools().await.unwrap_or_else(|e| {
+        // the initial discovery is a reload against an empty registry, the
+        // same path the runtime reload endpoint takes later
+        let mcp_registry = std::sync::RwLock::new(McpRegistry::new());
+        if let Err(e) = reload_registry(&mcp_registry).await {
             error!("Failed to discover MCP tools: {}", e);
-            Vec::new()
-        }) {
-            let tool_type = ToolType::McpTool(tool.full_name.clone());
-            tools.insert(tool_type.clone(), Box::new(tool));
-            mcp_tools.push(tool_type);
         }
 
         // we also want to add the re-ranking tool here, so we invoke it freely
         Self {
             tools,
-            mcp_tools: mcp_tools.into_boxed_slice(),
+            mcp_registry,
+            llm_client,
+            middlewares: vec![],
+            tool_timeouts: tool_broker_config.tool_timeouts,
+            cached_tools: tool_broker_config.cached_tools,
+            tool_result_cache: Mutex::new(HashMap::new()),
+            tool_policy: std::sync::RwLock::new(tool_broker_config.tool_policy),
+            tool_concurrency_limits: tool_broker_config.tool_concurrency_limits,
+            model_overrides: std::sync::RwLock::new(tool_broker_config.model_overrides),
+            audit_log: tool_broker_config.audit_log,
+            determinism: Arc::new(DeterminismMode::new()),
+            mcp_health: Arc::new(McpHealthTracker::new()),
+            mcp_status_sender: tokio::sync::broadcast::channel(64).0,
+        }
+    }
+
+    /// Registers an interceptor which wraps every tool invocation, they run
+    /// in registration order before the tool and in reverse order after it
+    pub fn with_middleware(mut self, middleware: Box<dyn ToolMiddleware + Send + Sync>) -> Self {
+        self.middlewares.push(middleware);
+        self
+    }
+
+    /// The registered tools which are allowed to run under the current
+    /// policy, this is what the webserver reports as the active tool set
+    pub fn active_tools(&self) -> Vec<ToolType> {
+        let tool_policy = self
+            .tool_policy
+            .read()
+            .expect("tool_policy lock to not be poisoned");
+        let mut tools = self
+            .tools
+            .keys()
+            .cloned()
+            .chain(
+                self.mcp_registry
+                    .read()
+                    .expect("mcp_registry lock to not be poisoned")
+                    .tool_types(),
+            )
+            .filter(|tool_type| tool_policy.is_allowed(tool_type))
+            .collect::<Vec<_>>();
+        tools.sort_by_key(|tool_type| tool_type.to_string());
+        tools
+    }
+
+    pub fn tool_policy(&self) -> ToolPolicy {
+        self.tool_policy
+            .read()
+            .expect("tool_policy lock to not be poisoned")
+            .clone()
+    }
+
+    pub fn model_overrides(&self) -> ToolModelOverrides {
+        self.model_overrides
+            .read()
+            .expect("model_overrides lock to not be poisoned")
+            .clone()
+    }
+
+    /// Swaps in a new override map, every invocation from here on runs
+    /// against it
+    pub fn set_model_overrides(&self, model_overrides: ToolModelOverrides) {
+        *self
+            .model_overrides
+            .write()
+            .expect("model_overrides lock to not be poisoned") = model_overrides;
+    }
+
+    fn model_override_for(&self, tool_type: &ToolType) -> Option<LLMProperties> {
+        self.model_overrides
+            .read()
+            .expect("model_overrides lock to not be poisoned")
+            .override_for(tool_type)
+            .cloned()
+    }
+
+    /// The tool types contributed by the currently running MCP servers
+    pub fn mcp_tools(&self) -> Box<[ToolType]> {
+        self.mcp_registry
+            .read()
+            .expect("mcp_registry lock to not be poisoned")
+            .tool_types()
+            .into_boxed_slice()
+    }
+
+    /// Clones the Arc out of the registry so the guard never lives across
+    /// the await of the tool invocation
+    fn mcp_tool(&self, tool_type: &ToolType) -> Option<Arc<McpTool>> {
+        self.mcp_registry
+            .read()
+            .expect("mcp_registry lock to not be poisoned")
+            .tool(tool_type)
+    }
+
+    /// Lists the resources every running MCP server exposes, a server
+    /// which fails the listing gets skipped with a log so one broken
+    /// server never hides the others
+    pub async fn list_mcp_resources(&self) -> Vec<McpServerResources> {
+        let clients = self
+            .mcp_registry
+            .read()
+            .expect("mcp_registry lock to not be poisoned")
+            .clients();
+        let mut servers = vec![];
+        for (server_name, client) in clients.into_iter() {
+            match client.list_resources().await {
+                Ok(listing) => servers.push(McpServerResources {
+                    resources: listing
+                        .resources
+                        .iter()
+                        .map(|resource| {
+                            McpResourceDescriptor::from_resource(&server_name, resource)
+                        })
+                        .collect(),
+                    server_name,
+                }),
+                Err(e) => println!(
+                    "tool_broker::list_mcp_resources::server({})::error({})",
+                    server_name, e
+                ),
+            }
+        }
+        servers
+    }
+
+    /// Reads one resource from a server and flattens the contents into
+    /// plain text ready to attach as context
+    pub async fn read_mcp_resource(
+        &self,
+        server_name: &str,
+        uri: &str,
+    ) -> Result<String, McpError> {
+        let client = self
+            .mcp_registry
+            .read()
+            .expect("mcp_registry lock to not be poisoned")
+            .client_for(server_name)
+            .ok_or_else(|| McpError::UnknownServer(server_name.to_owned()))?;
+        let read_result = client
+            .read_resource(uri)
+            .await
+            .map_err(|e| McpError::ResourceError {
+                server: server_name.to_owned(),
+                source: e,
+            })?;
+        Ok(flatten_resource_contents(&read_result.contents))
+    }
+
+    /// Lists the prompt templates every running MCP server exposes so the
+    /// editor can render them as slash commands, servers which fail the
+    /// listing are skipped
+    pub async fn list_mcp_prompts(&self) -> Vec<McpServerPrompts> {
+        let clients = self
+            .mcp_registry
+            .read()
+            .expect("mcp_registry lock to not be poisoned")
+            .clients();
+        let mut servers = vec![];
+        for (server_name, client) in clients.into_iter() {
+            match list_server_prompts(&client).await {
+                Ok(listing) => servers.push(McpServerPrompts {
+                    prompts: listing
+                        .prompts
+                        .iter()
+                        .map(|prompt| McpPromptDescriptor::from_prompt(&server_name, prompt))
+                        .collect(),
+                    server_name,
+                }),
+                Err(e) => println!(
+                    "tool_broker::list_mcp_prompts::server({})::error({})",
+                    server_name, e
+                ),
+            }
+        }
+        servers
+    }
+
+    /// Expands a prompt template on a server with the given arguments and
+    /// flattens the result into plain text ready to ride in as a chat query
+    pub async fn expand_mcp_prompt(
+        &self,
+        server_name: &str,
+        prompt_name: &str,
+        arguments: &std::collections::HashMap<String, String>,
+    ) -> Result<String, McpError> {
+        let client = self
+            .mcp_registry
+            .read()
+            .expect("mcp_registry lock to not be poisoned")
+            .client_for(server_name)
+            .ok_or_else(|| McpError::UnknownServer(server_name.to_owned()))?;
+        let prompt_result = get_server_prompt(&client, prompt_name, arguments)
+            .await
+            .map_err(|e| McpError::PromptError {
+                server: server_name.to_owned(),
+                source: e,
+            })?;
+        Ok(flatten_prompt_messages(&prompt_result.messages))
+    }
+
+    /// The last observed health per MCP server
+    pub fn mcp_server_statuses(&self) -> HashMap<String, McpServerStatus> {
+        self.mcp_health.statuses()
+    }
+
+    /// Subscribes to the status transitions the health monitor raises
+    pub fn subscribe_mcp_status(
+        &self,
+    ) -> tokio::sync::broadcast::Receiver<McpServerStatusEvent> {
+        self.mcp_status_sender.subscribe()
+    }
+
+    /// Pings every running MCP server once and respawns crashed ones whose
+    /// backoff window has passed, returning the status transitions
+    pub async fn check_mcp_servers_once(&self) -> Vec<McpServerStatusEvent> {
+        let clients = self
+            .mcp_registry
+            .read()
+            .expect("mcp_registry lock to not be poisoned")
+            .clients();
+        let server_names = clients
+            .iter()
+            .map(|(server_name, _)| server_name.clone())
+            .collect::<Vec<_>>();
+        self.mcp_health.retain_servers(&server_names);
+        let mut events = vec![];
+        for (server_name, client) in clients.into_iter() {
+            if ping_server(&client).await {
+                if let Some(event) = self.mcp_health.observe(&server_name, true) {
+                    events.push(event);
+                }
+                continue;
+            }
+            if let Some(event) = self.mcp_health.observe(&server_name, false) {
+                println!("tool_broker::mcp_health::down({})", &server_name);
+                events.push(event);
+            }
+            if self.mcp_health.respawn_due(&server_name) {
+                if respawn_server(&self.mcp_registry, &server_name).await {
+                    println!("tool_broker::mcp_health::respawned({})", &server_name);
+                    if let Some(event) = self.mcp_health.observe(&server_name, true) {
+                        events.push(event);
+                    }
+                } else {
+                    self.mcp_health.respawn_failed(&server_name);
+                }
+            }
+        }
+        for event in events.iter() {
+            let _ = self.mcp_status_sender.send(event.clone());
+        }
+        events
+    }
+
+    /// Spawns the background loop which health checks the MCP servers,
+    /// called once at startup
+    pub fn start_mcp_health_monitor(self: &Arc<Self>) {
+        let broker = self.clone();
+        tokio::spawn(async move {
+            let mut interval =
+                tokio::time::interval(std::time::Duration::from_secs(30));
+            // the first tick fires immediately, skip it so startup does not
+            // race the initial server spawns
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                let _ = broker.check_mcp_servers_once().await;
+            }
+        });
+    }
+
+    /// The runtime determinism switch, the seeded identifiers and
+    /// timestamps for a reproducible run come from here
+    pub fn determinism(&self) -> Arc<DeterminismMode> {
+        self.determinism.clone()
+    }
+
+    /// Turns the reproducible mode on: the LLM broker pins this sampling
+    /// seed, fan-out invocations serialize into input order and the
+    /// identifiers we hand out become seeded and recorded
+    pub fn enable_deterministic_mode(&self, seed: u64) {
+        self.determinism.enable(seed);
+        self.llm_client.set_deterministic_seed(Some(seed));
+        println!("tool_broker::deterministic_mode::enabled::seed({})", seed);
+    }
+
+    pub fn disable_deterministic_mode(&self) {
+        self.determinism.disable();
+        self.llm_client.set_deterministic_seed(None);
+        println!("tool_broker::deterministic_mode::disabled");
+    }
+
+    /// Re-reads the MCP config and reconciles the running servers against
+    /// it: new ones get spawned, removed ones torn down, changed ones
+    /// restarted. Exposed through the webserver so config edits do not need
+    /// a sidecar restart
+    pub async fn reload_mcp_servers(&self) -> Result<McpReloadSummary, McpError> {
+        let summary = reload_registry(&self.mcp_registry).await?;
+        println!(
+            "tool_broker::reload_mcp_servers::added({})::removed({})::restarted({})",
+            summary.added.len(),
+            summary.removed.len(),
+            summary.restarted.len()
+        );
+        Ok(summary)
+    }
+
+    /// Runs a batch of invocations concurrently and returns the results in
+    /// input order. Invocations of the same tool share a concurrency limit
+    /// so a fan-out over many files does not flood the editor endpoint
+    pub async fn invoke_many(
+        &self,
+        inputs: Vec<ToolInput>,
+    ) -> Vec<Result<ToolOutput, ToolError>> {
+        // a deterministic run gives up the concurrency and executes the
+        // batch one by one in input order so two runs invoke identically
+        if self.determinism.is_enabled() {
+            let mut results = Vec::with_capacity(inputs.len());
+            for input in inputs.into_iter() {
+                results.push(self.invoke(input).await);
+            }
+            return results;
         }
+        let mut semaphores: HashMap<ToolType, Arc<tokio::sync::Semaphore>> = HashMap::new();
+        let invocations = inputs
+            .into_iter()
+            .map(|input| {
+                let tool_type = input.tool_type();
+                let semaphore = semaphores
+                    .entry(tool_type.clone())
+                    .or_insert_with(|| {
+                        Arc::new(tokio::sync::Semaphore::new(
+                            self.tool_concurrency_limit(&tool_type),
+                        ))
+                    })
+                    .clone();
+                async move {
+                    let _permit = semaphore
+                        .acquire()
+                        .await
+                        .expect("semaphore to not be closed");
+                    self.invoke(input).await
+                }
+            })
+            .collect::<Vec<_>>();
+        futures::future::join_all(invocations).await
+    }
+
+    fn tool_concurrency_limit(&self, tool_type: &ToolType) -> usize {
+        self.tool_concurrency_limits
+            .get(tool_type)
+            .copied()
+            .unwrap_or(DEFAULT_TOOL_CONCURRENCY)
+    }
+
+    /// Swaps in a new policy, every invocation from here on runs against it
+    pub fn set_tool_policy(&self, tool_policy: ToolPolicy) {
+        *self
+            .tool_policy
+            .write()
+            .expect("tool_policy lock to not be poisoned") = tool_policy;
+    }
+
+    pub fn llm_broker(&self) -> Arc<LLMBroker> {
+        self.llm_client.clone()
     }
 
     /// Sets a reminder for the tool, including the name and the format of it
     pub fn get_tool_reminder(&self, tool_type: &ToolType) -> Option<String> {
-        if let Some(tool) = self.tools.get(tool_type) {
-            let tool_format = tool.tool_input_format();
-            let tool_name = tool_type.to_string();
-            Some(format!(
-                r#"### {tool_name}
-{tool_format}"#
-            ))
+        let tool_format = if let Some(tool) = self.tools.get(tool_type) {
+            tool.tool_input_format()
         } else {
-            None
-        }
+            self.mcp_tool(tool_type)?.tool_input_format()
+        };
+        let tool_name = tool_type.to_string();
+        Some(format!(
+            r#"### {tool_name}
+{tool_format}"#
+        ))
     }
 
     pub fn get_tool_description(&self, tool_type: &ToolType) -> Option<String> {
-        if let Some(tool) = self.tools.get(tool_type) {
-            let tool_description = tool.tool_description();
-            let tool_format = tool.tool_input_format();
-            Some(format!(
-                r#"{tool_description}
-{tool_format}"#
-            ))
+        let (tool_description, tool_format) = if let Some(tool) = self.tools.get(tool_type) {
+            (tool.tool_description(), tool.tool_input_format())
         } else {
-            None
-        }
+            let mcp_tool = self.mcp_tool(tool_type)?;
+            (mcp_tool.tool_description(), mcp_tool.tool_input_format())
+        };
+        Some(format!(
+            r#"{tool_description}
+{tool_format}"#
+        ))
     }
 
     pub fn get_tool_json(&self, tool_type: &ToolType) -> Option<serde_json::Value> {
         ToolInputPartial::to_json(tool_type.clone())
     }
+
+    /// Invokes the tool but races it against the cancellation token, when the
+    /// user hits cancel mid-flight the tool future is dropped (taking any
+    /// in-flight llm or http streams with it) and we bubble up a cancellation
+    /// error instead of letting the tool run to completion
+    pub async fn invoke_with_cancellation(
+        &self,
+        input: ToolInput,
+        cancellation_token: tokio_util::sync::CancellationToken,
+    ) -> Result<ToolOutput, ToolError> {
+        match run_with_cancellation(cancellation_token, self.invoke(input)).await {
+            Some(result) => result,
+            None => Err(ToolError::UserCancellation),
+        }
+    }
 }
 
 #[async_trait]
 impl Tool for ToolBroker {
     async fn invoke(&self, input: ToolInput) -> Result<ToolOutput, ToolError> {
+        let mut input = input;
+        for middleware in self.middlewares.iter() {
+            match middleware.before_invoke(input).await {
+                ToolMiddlewareDecision::Continue(updated_input) => input = updated_input,
+                ToolMiddlewareDecision::ShortCircuit(result) => return result,
+            }
+        }
         let tool_type = input.tool_type();
-        if let Some(tool) = self.tools.get(&tool_type) {
-            let result = tool.invoke(input).await;
-            result
+        if !self
+            .tool_policy
+            .read()
+            .expect("tool_policy lock to not be poisoned")
+            .is_allowed(&tool_type)
+        {
+            println!(
+                "tool_broker::invoke::tool({})::disabled_by_policy",
+                &tool_type
+            );
+            return Err(ToolError::ToolDisabled(tool_type));
+        }
+        // a per-tool model override rewrites the request before anything
+        // else looks at it, so the cache key below also reflects the model
+        // the tool really ran with
+        let input = match self.model_override_for(&tool_type) {
+            Some(llm_properties) => {
+                println!(
+                    "tool_broker::invoke::tool({})::model_override({:?})",
+                    &tool_type,
+                    llm_properties.llm()
+                );
+                input.with_llm_properties_override(llm_properties)
+            }
+            None => input,
+        };
+        // the cache key hashes the full debug representation of the input,
+        // any difference in the request leads to a different entry
+        let cache_key = if self.cached_tools.contains(&tool_type) {
+            use std::hash::{Hash, Hasher};
+            let mut hasher = std::collections::hash_map::DefaultHasher::new();
+            format!("{:?}", &input).hash(&mut hasher);
+            Some((tool_type.clone(), hasher.finish()))
         } else {
-            let result = Err(ToolError::MissingTool);
-            result
+            None
+        };
+        if let Some(cache_key) = cache_key.as_ref() {
+            let cached_output = self
+                .tool_result_cache
+                .lock()
+                .expect("tool_result_cache lock to not be poisoned")
+                .get(cache_key)
+                .and_then(|output| output.cheap_clone());
+            if let Some(cached_output) = cached_output {
+                println!("tool_broker::invoke::tool({})::cache_hit", &tool_type);
+                return Ok(cached_output);
+            }
+        }
+        let invocation_start = std::time::Instant::now();
+        // the input moves into the tool below, keep a copy around for the
+        // audit record only when the log is switched on
+        let audit_input = self.audit_log.as_ref().map(|_| input.clone());
+        let mut result = if let Some(tool) = self.tools.get(&tool_type) {
+            match self.tool_timeouts.get(&tool_type) {
+                Some(timeout) => {
+                    match tokio::time::timeout(*timeout, tool.invoke(input)).await {
+                        Ok(result) => result,
+                        Err(_) => {
+                            println!(
+                                "tool_broker::invoke::tool({})::timed_out_after({}ms)",
+                                &tool_type,
+                                timeout.as_millis()
+                            );
+                            Err(ToolError::Timeout(tool_type.clone()))
+                        }
+                    }
+                }
+                None => tool.invoke(input).await,
+            }
+        } else if let Some(mcp_tool) = self.mcp_tool(&tool_type) {
+            mcp_tool.invoke(input).await
+        } else {
+            Err(ToolError::MissingTool)
+        };
+        if let (Some(audit_log), Some(audit_input)) = (self.audit_log.as_ref(), audit_input) {
+            let record = ToolAuditRecord::new(
+                &audit_input,
+                invocation_start.elapsed(),
+                result.as_ref().ok().map(|output| format!("{:?}", output)),
+                result.as_ref().err().map(|e| format!("{:?}", e)),
+            );
+            // a deterministic run stamps the record from the seeded clock
+            // so two runs produce byte-identical traces
+            let record = if self.determinism.is_enabled() {
+                record.with_recorded_at(self.determinism.timestamp())
+            } else {
+                record
+            };
+            audit_log.append(record).await;
+            // a deterministic run files the identifiers and timestamps it
+            // handed out into the same trace so a replay can line them up
+            let recorded = self.determinism.drain_recorded();
+            if !recorded.is_empty() {
+                audit_log.append_nondeterministic_inputs(&recorded).await;
+            }
+        }
+        if let (Some(cache_key), Ok(output)) = (cache_key, &result) {
+            if let Some(cached_output) = output.cheap_clone() {
+                self.tool_result_cache
+                    .lock()
+                    .expect("tool_result_cache lock to not be poisoned")
+                    .insert(cache_key, cached_output);
+            }
+        }
+        // an edit going through stales any file derived results, we do not
+        // track which entries depend on the edited file so everything goes
+        if matches!(tool_type, ToolType::EditorApplyEdits) && result.is_ok() {
+            self.tool_result_cache
+                .lock()
+                .expect("tool_result_cache lock to not be poisoned")
+                .clear();
+        }
+        for middleware in self.middlewares.iter().rev() {
+            result = middleware
+                .after_invoke(&tool_type, invocation_start.elapsed(), result)
+                .await;
         }
+        result
     }
 
     fn tool_description(&self) -> String {
@@ -0,0 +1,256 @@
+//! Deterministic fixes for diagnostics we have seen a thousand times
+//!
+//! A chunk of the correction loop is spent asking an LLM to fix diagnostics
+//! which have exactly one mechanical answer: a &str where a String was
+//! expected, a missing semicolon, a typo the compiler already spelled out
+//! with a "did you mean" hint. Each rule here maps such a diagnostic to a
+//! text transform of the offending snippet, the LLM only gets the
+//! diagnostics no rule understands
+
+/// A deterministic fix produced by one of the rules
+#[derive(Debug, Clone)]
+pub struct AutoFixEdit {
+    /// name of the rule which produced the fix, for the logs
+    rule: &'static str,
+    /// the snippet with the fix applied, replaces the diagnostic's lines
+    fixed_snippet: String,
+}
+
+impl AutoFixEdit {
+    pub fn rule(&self) -> &'static str {
+        self.rule
+    }
+
+    pub fn fixed_snippet(&self) -> &str {
+        &self.fixed_snippet
+    }
+}
+
+/// Runs the rules in order and returns the first fix which applies, None
+/// hands the diagnostic over to the LLM correction path
+pub fn try_auto_fix(diagnostic_message: &str, snippet: &str) -> Option<AutoFixEdit> {
+    if let Some(fixed_snippet) = rename_to_compiler_suggestion(diagnostic_message, snippet) {
+        return Some(AutoFixEdit {
+            rule: "rename_to_compiler_suggestion",
+            fixed_snippet,
+        });
+    }
+    if let Some(fixed_snippet) = string_from_str_conversion(diagnostic_message, snippet) {
+        return Some(AutoFixEdit {
+            rule: "string_from_str_conversion",
+            fixed_snippet,
+        });
+    }
+    if let Some(fixed_snippet) = str_from_string_conversion(diagnostic_message, snippet) {
+        return Some(AutoFixEdit {
+            rule: "str_from_string_conversion",
+            fixed_snippet,
+        });
+    }
+    if let Some(fixed_snippet) = missing_semicolon(diagnostic_message, snippet) {
+        return Some(AutoFixEdit {
+            rule: "missing_semicolon",
+            fixed_snippet,
+        });
+    }
+    None
+}
+
+/// the quoted identifiers in a diagnostic message, compilers disagree on
+/// the quote style so we accept backticks and single quotes
+fn quoted_identifiers(message: &str) -> Vec<String> {
+    let mut identifiers = vec![];
+    let mut current: Option<String> = None;
+    for character in message.chars() {
+        match (&mut current, character) {
+            (None, '`' | '\'') => current = Some(String::new()),
+            (Some(identifier), '`' | '\'') => {
+                if !identifier.is_empty()
+                    && identifier
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '_')
+                {
+                    identifiers.push(identifier.to_owned());
+                }
+                current = None;
+            }
+            (Some(identifier), c) => identifier.push(c),
+            (None, _) => {}
+        }
+    }
+    identifiers
+}
+
+/// plain levenshtein distance, the messages quote several identifiers and
+/// the one closest to the suggestion is the one being corrected
+fn edit_distance(left: &str, right: &str) -> usize {
+    let left = left.chars().collect::<Vec<_>>();
+    let right = right.chars().collect::<Vec<_>>();
+    let mut previous_row = (0..=right.len()).collect::<Vec<_>>();
+    for (left_index, left_char) in left.iter().enumerate() {
+        let mut current_row = vec![left_index + 1];
+        for (right_index, right_char) in right.iter().enumerate() {
+            let substitution = previous_row[right_index] + usize::from(left_char != right_char);
+            current_row.push(
+                substitution
+                    .min(previous_row[right_index + 1] + 1)
+                    .min(current_row[right_index] + 1),
+            );
+        }
+        previous_row = current_row;
+    }
+    previous_row[right.len()]
+}
+
+/// replaces whole-word occurrences of an identifier, so fixing `mid` does
+/// not chew through `middle`
+fn replace_identifier(snippet: &str, from: &str, to: &str) -> String {
+    let is_word_char = |c: char| c.is_ascii_alphanumeric() || c == '_';
+    let mut output = String::new();
+    let mut position = 0;
+    while let Some(offset) = snippet[position..].find(from) {
+        let index = position + offset;
+        let before_ok = snippet[..index]
+            .chars()
+            .next_back()
+            .map(|c| !is_word_char(c))
+            .unwrap_or(true);
+        let after_ok = snippet[index + from.len()..]
+            .chars()
+            .next()
+            .map(|c| !is_word_char(c))
+            .unwrap_or(true);
+        output.push_str(&snippet[position..index]);
+        if before_ok && after_ok {
+            output.push_str(to);
+        } else {
+            output.push_str(from);
+        }
+        position = index + from.len();
+    }
+    output.push_str(&snippet[position..]);
+    output
+}
+
+/// Covers the "did you mean" family: rustc E0425/E0599 hints, typescript
+/// TS2551/TS2339 suggestions and python AttributeError hints all quote the
+/// correct spelling, we swap the misspelled identifier for it
+fn rename_to_compiler_suggestion(diagnostic_message: &str, snippet: &str) -> Option<String> {
+    let message_lowercase = diagnostic_message.to_lowercase();
+    let did_you_mean_index = message_lowercase.find("did you mean")?;
+    let suggestion = quoted_identifiers(&diagnostic_message[did_you_mean_index..])
+        .into_iter()
+        .next()?;
+    // the misspelled identifier is quoted earlier in the message, pick the
+    // candidate which actually occurs in the snippet and sits closest to
+    // the suggestion (messages also quote types and trait names)
+    let misspelled = quoted_identifiers(&diagnostic_message[..did_you_mean_index])
+        .into_iter()
+        .filter(|candidate| candidate != &suggestion)
+        .filter(|candidate| snippet.contains(candidate.as_str()))
+        .min_by_key(|candidate| edit_distance(candidate, &suggestion))?;
+    let fixed_snippet = replace_identifier(snippet, &misspelled, &suggestion);
+    (fixed_snippet != snippet).then_some(fixed_snippet)
+}
+
+/// appends a method call to the end of a single-line snippet, slotting it
+/// in before any trailing `;` or `,`
+fn append_method_call(snippet: &str, method_call: &str) -> Option<String> {
+    if snippet.lines().count() != 1 {
+        return None;
+    }
+    let trimmed = snippet.trim_end();
+    let (expression, punctuation) = match trimmed.strip_suffix([';', ',']) {
+        Some(expression) => (expression, &trimmed[expression.len()..]),
+        None => (trimmed, ""),
+    };
+    if expression.is_empty() {
+        return None;
+    }
+    Some(format!("{}{}{}", expression, method_call, punctuation))
+}
+
+/// rustc E0308 expected `String`, found `&str`: a `.to_string()` at the end
+/// of the expression is the answer rustc itself suggests
+fn string_from_str_conversion(diagnostic_message: &str, snippet: &str) -> Option<String> {
+    if !diagnostic_message.contains("expected `String`, found `&str`") {
+        return None;
+    }
+    append_method_call(snippet, ".to_string()")
+}
+
+/// rustc E0308 expected `&str`, found `String`: borrow it back down
+fn str_from_string_conversion(diagnostic_message: &str, snippet: &str) -> Option<String> {
+    if !diagnostic_message.contains("expected `&str`, found `String`") {
+        return None;
+    }
+    append_method_call(snippet, ".as_str()")
+}
+
+/// rustc's "expected `;`" on a line which does not end with one
+fn missing_semicolon(diagnostic_message: &str, snippet: &str) -> Option<String> {
+    if !diagnostic_message.contains("expected `;`") {
+        return None;
+    }
+    if snippet.lines().count() != 1 || snippet.trim_end().ends_with(';') {
+        return None;
+    }
+    Some(format!("{};", snippet.trim_end()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::try_auto_fix;
+
+    #[test]
+    fn test_string_from_str_appends_to_string_before_the_semicolon() {
+        let fix = try_auto_fix(
+            "mismatched types: expected `String`, found `&str`",
+            r#"    let name = "sidecar";"#,
+        )
+        .expect("rule to match");
+        assert_eq!(fix.rule(), "string_from_str_conversion");
+        assert_eq!(fix.fixed_snippet(), r#"    let name = "sidecar".to_string();"#);
+    }
+
+    #[test]
+    fn test_did_you_mean_renames_only_the_misspelled_identifier() {
+        let fix = try_auto_fix(
+            "Property 'lenght' does not exist on type 'Buffer'. Did you mean 'length'?",
+            "const size = buffer.lenght;",
+        )
+        .expect("rule to match");
+        assert_eq!(fix.rule(), "rename_to_compiler_suggestion");
+        assert_eq!(fix.fixed_snippet(), "const size = buffer.length;");
+    }
+
+    #[test]
+    fn test_did_you_mean_skips_quoted_names_missing_from_the_snippet() {
+        // python quotes the type as well, only the attribute occurs in the
+        // snippet so it is the one which gets renamed
+        let fix = try_auto_fix(
+            "'list' object has no attribute 'push'. Did you mean: 'append'?",
+            "items.push(value)",
+        )
+        .expect("rule to match");
+        assert_eq!(fix.fixed_snippet(), "items.append(value)");
+    }
+
+    #[test]
+    fn test_missing_semicolon_is_added_once() {
+        let fix = try_auto_fix("expected `;`, found `}`", "    let total = compute()")
+            .expect("rule to match");
+        assert_eq!(fix.rule(), "missing_semicolon");
+        assert_eq!(fix.fixed_snippet(), "    let total = compute();");
+        assert!(try_auto_fix("expected `;`, found `}`", "    let total = compute();").is_none());
+    }
+
+    #[test]
+    fn test_unknown_diagnostics_fall_through_to_the_llm() {
+        assert!(try_auto_fix(
+            "cannot borrow `state` as mutable more than once at a time",
+            "let second = &mut state;",
+        )
+        .is_none());
+    }
+}
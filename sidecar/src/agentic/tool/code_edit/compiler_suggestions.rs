@@ -0,0 +1,285 @@
+//! Applies rustc's machine-applicable suggestions without an LLM
+//!
+//! rustc and clippy ship the fix for a lot of their diagnostics inside the
+//! JSON output: missing imports, missing borrows, dead code attributes, all
+//! tagged MachineApplicable. Applying those directly makes the easy part of
+//! the correction loop instant and free, the LLM only gets the diagnostics
+//! the compiler could not fix itself
+
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::agentic::tool::errors::ToolError;
+use crate::agentic::tool::input::ToolInput;
+use crate::agentic::tool::output::ToolOutput;
+use crate::agentic::tool::r#type::{Tool, ToolRewardScale};
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ApplyCompilerSuggestionsRequest {
+    /// the edited file, only suggestions landing in it get applied
+    fs_file_path: String,
+}
+
+impl ApplyCompilerSuggestionsRequest {
+    pub fn new(fs_file_path: String) -> Self {
+        Self { fs_file_path }
+    }
+
+    pub fn fs_file_path(&self) -> &str {
+        &self.fs_file_path
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ApplyCompilerSuggestionsResponse {
+    /// how many suggestions made it into the file
+    applied_suggestions: usize,
+}
+
+impl ApplyCompilerSuggestionsResponse {
+    pub fn applied_suggestions(&self) -> usize {
+        self.applied_suggestions
+    }
+}
+
+/// One machine-applicable replacement from the compiler JSON
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompilerSuggestion {
+    pub fs_file_path: String,
+    pub byte_start: usize,
+    pub byte_end: usize,
+    pub replacement: String,
+}
+
+/// Pulls the machine-applicable replacements out of compiler JSON output,
+/// one JSON object per line as `cargo check --message-format=json` emits it.
+/// Both cargo's compiler-message wrapper and raw rustc diagnostics parse
+pub fn parse_machine_applicable_suggestions(json_output: &str) -> Vec<CompilerSuggestion> {
+    let mut suggestions = vec![];
+    for line in json_output.lines() {
+        let Ok(value) = serde_json::from_str::<serde_json::Value>(line) else {
+            continue;
+        };
+        // cargo wraps the rustc diagnostic in a compiler-message envelope
+        let diagnostic = match value.get("message") {
+            Some(message) if value.get("reason").is_some() => message,
+            _ => &value,
+        };
+        collect_suggestions_from_diagnostic(diagnostic, &mut suggestions);
+    }
+    suggestions
+}
+
+fn collect_suggestions_from_diagnostic(
+    diagnostic: &serde_json::Value,
+    suggestions: &mut Vec<CompilerSuggestion>,
+) {
+    if let Some(spans) = diagnostic.get("spans").and_then(|spans| spans.as_array()) {
+        for span in spans {
+            let machine_applicable = span
+                .get("suggestion_applicability")
+                .and_then(|applicability| applicability.as_str())
+                .map(|applicability| applicability == "MachineApplicable")
+                .unwrap_or(false);
+            if !machine_applicable {
+                continue;
+            }
+            let (Some(replacement), Some(fs_file_path), Some(byte_start), Some(byte_end)) = (
+                span.get("suggested_replacement")
+                    .and_then(|replacement| replacement.as_str()),
+                span.get("file_name")
+                    .and_then(|file_name| file_name.as_str()),
+                span.get("byte_start").and_then(|byte| byte.as_u64()),
+                span.get("byte_end").and_then(|byte| byte.as_u64()),
+            ) else {
+                continue;
+            };
+            suggestions.push(CompilerSuggestion {
+                fs_file_path: fs_file_path.to_owned(),
+                byte_start: byte_start as usize,
+                byte_end: byte_end as usize,
+                replacement: replacement.to_owned(),
+            });
+        }
+    }
+    // the suggestions usually live on the help children of the error
+    if let Some(children) = diagnostic
+        .get("children")
+        .and_then(|children| children.as_array())
+    {
+        for child in children {
+            collect_suggestions_from_diagnostic(child, suggestions);
+        }
+    }
+}
+
+/// Applies the suggestions back-to-front so earlier byte offsets stay
+/// valid, overlapping suggestions keep only the first one
+pub fn apply_suggestions_to_content(
+    content: &str,
+    suggestions: &[CompilerSuggestion],
+) -> (String, usize) {
+    let mut ordered = suggestions.to_vec();
+    ordered.sort_by(|a, b| b.byte_start.cmp(&a.byte_start));
+    ordered.dedup_by_key(|suggestion| suggestion.byte_start);
+    let mut updated = content.to_owned();
+    let mut applied = 0;
+    let mut last_applied_start = usize::MAX;
+    for suggestion in ordered.into_iter() {
+        if suggestion.byte_end > updated.len()
+            || suggestion.byte_end > last_applied_start
+            || !updated.is_char_boundary(suggestion.byte_start)
+            || !updated.is_char_boundary(suggestion.byte_end)
+        {
+            continue;
+        }
+        updated.replace_range(
+            suggestion.byte_start..suggestion.byte_end,
+            &suggestion.replacement,
+        );
+        last_applied_start = suggestion.byte_start;
+        applied += 1;
+    }
+    (updated, applied)
+}
+
+/// the closest ancestor directory holding a Cargo.toml, where cargo check
+/// has to run from
+fn find_cargo_root(fs_file_path: &str) -> Option<PathBuf> {
+    let mut current = Path::new(fs_file_path).parent();
+    while let Some(directory) = current {
+        if directory.join("Cargo.toml").is_file() {
+            return Some(directory.to_owned());
+        }
+        current = directory.parent();
+    }
+    None
+}
+
+pub struct CompilerSuggestionsClient {}
+
+impl CompilerSuggestionsClient {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+#[async_trait]
+impl Tool for CompilerSuggestionsClient {
+    async fn invoke(&self, input: ToolInput) -> Result<ToolOutput, ToolError> {
+        let request = input.is_apply_compiler_suggestions()?;
+        let fs_file_path = request.fs_file_path().to_owned();
+        let Some(cargo_root) = find_cargo_root(&fs_file_path) else {
+            return Ok(ToolOutput::apply_compiler_suggestions(
+                ApplyCompilerSuggestionsResponse {
+                    applied_suggestions: 0,
+                },
+            ));
+        };
+        // a failing exit status is expected here, errors are the whole point
+        let output = tokio::process::Command::new("cargo")
+            .args(["check", "--message-format=json", "--quiet"])
+            .current_dir(&cargo_root)
+            .output()
+            .await
+            .map_err(|e| ToolError::InvalidInput(format!("failed to run cargo check: {}", e)))?;
+        let json_output = String::from_utf8_lossy(&output.stdout).to_string();
+        // the spans name files relative to the cargo root, group per file and
+        // keep only the ones resolving to the edited file
+        let mut suggestions_by_file: HashMap<String, Vec<CompilerSuggestion>> = HashMap::new();
+        for suggestion in parse_machine_applicable_suggestions(&json_output) {
+            let absolute_path = cargo_root
+                .join(&suggestion.fs_file_path)
+                .to_string_lossy()
+                .to_string();
+            if absolute_path != fs_file_path {
+                continue;
+            }
+            suggestions_by_file
+                .entry(absolute_path)
+                .or_default()
+                .push(suggestion);
+        }
+        let mut applied_suggestions = 0;
+        for (absolute_path, suggestions) in suggestions_by_file.into_iter() {
+            let Ok(content) = tokio::fs::read_to_string(&absolute_path).await else {
+                continue;
+            };
+            let (updated, applied) = apply_suggestions_to_content(&content, &suggestions);
+            if applied > 0 {
+                let _ = tokio::fs::write(&absolute_path, updated).await;
+                applied_suggestions += applied;
+            }
+        }
+        println!(
+            "apply_compiler_suggestions::invoke::file({})::applied({})",
+            &fs_file_path, applied_suggestions
+        );
+        Ok(ToolOutput::apply_compiler_suggestions(
+            ApplyCompilerSuggestionsResponse {
+                applied_suggestions,
+            },
+        ))
+    }
+
+    fn tool_description(&self) -> String {
+        "Applies the machine-applicable fixes rustc and clippy suggest for a file".to_owned()
+    }
+
+    fn tool_input_format(&self) -> String {
+        format!(
+            r#"Parameters:
+- fs_file_path: (required) The ABSOLUTE path of the rust file to apply compiler suggestions to.
+
+Usage:
+<apply_compiler_suggestions>
+<fs_file_path>
+File path here
+</fs_file_path>
+</apply_compiler_suggestions>
+"#
+        )
+    }
+
+    fn get_evaluation_criteria(&self, _trajectory_length: usize) -> Vec<String> {
+        vec![]
+    }
+
+    fn get_reward_scale(&self, _trajectory_length: usize) -> Vec<ToolRewardScale> {
+        vec![]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{apply_suggestions_to_content, parse_machine_applicable_suggestions};
+
+    #[test]
+    fn test_parsing_picks_only_machine_applicable_spans() {
+        let json_output = r#"{"reason":"compiler-message","message":{"message":"unused variable: `value`","spans":[{"file_name":"src/lib.rs","byte_start":10,"byte_end":15,"suggestion_applicability":null,"suggested_replacement":null}],"children":[{"message":"if this is intentional, prefix it with an underscore","spans":[{"file_name":"src/lib.rs","byte_start":10,"byte_end":15,"suggested_replacement":"_value","suggestion_applicability":"MachineApplicable"}],"children":[]},{"message":"consider something else","spans":[{"file_name":"src/lib.rs","byte_start":10,"byte_end":15,"suggested_replacement":"whatever","suggestion_applicability":"MaybeIncorrect"}],"children":[]}]}}
+{"reason":"build-finished","success":false}"#;
+        let suggestions = parse_machine_applicable_suggestions(json_output);
+        assert_eq!(suggestions.len(), 1);
+        assert_eq!(suggestions[0].replacement, "_value");
+        assert_eq!(suggestions[0].byte_start, 10);
+    }
+
+    #[test]
+    fn test_applying_back_to_front_keeps_offsets_valid() {
+        let json_output = r#"{"message":"unused variables","spans":[],"children":[{"spans":[{"file_name":"src/lib.rs","byte_start":3,"byte_end":4,"suggested_replacement":"_a","suggestion_applicability":"MachineApplicable"},{"file_name":"src/lib.rs","byte_start":13,"byte_end":14,"suggested_replacement":"_b","suggestion_applicability":"MachineApplicable"}],"children":[]}]}"#;
+        let suggestions = parse_machine_applicable_suggestions(json_output);
+        let content = "fn a() {}\nfn b() {}\n";
+        let (updated, applied) = apply_suggestions_to_content(content, &suggestions);
+        assert_eq!(applied, 2);
+        assert_eq!(updated, "fn _a() {}\nfn _b() {}\n");
+    }
+
+    #[test]
+    fn test_overlapping_suggestions_apply_once() {
+        let json_output = r#"{"message":"overlap","spans":[],"children":[{"spans":[{"file_name":"src/lib.rs","byte_start":0,"byte_end":4,"suggested_replacement":"first","suggestion_applicability":"MachineApplicable"},{"file_name":"src/lib.rs","byte_start":2,"byte_end":6,"suggested_replacement":"second","suggestion_applicability":"MachineApplicable"}],"children":[]}]}"#;
+        let suggestions = parse_machine_applicable_suggestions(json_output);
+        let (_, applied) = apply_suggestions_to_content("abcdefgh", &suggestions);
+        assert_eq!(applied, 1);
+    }
+}
@@ -0,0 +1,156 @@
+//! Telemetry over how much of the provided context an edit actually used
+//!
+//! The edit prompts carry context snippets which the user or the agent
+//! decided to attach. Scoring the string overlap between each snippet and
+//! the generated code tells us which snippets got referenced, so the
+//! default context policies can be tuned with data instead of guesses
+
+use std::collections::HashSet;
+
+/// lines shorter than this are braces and lone keywords which show up
+/// everywhere, they say nothing about whether a snippet got referenced
+const MIN_SIGNIFICANT_LINE_LENGTH: usize = 10;
+
+/// Overlap between one context snippet and the generated code
+#[derive(Debug, Clone)]
+pub struct ContextSnippetRelevance {
+    /// position of the snippet in the provided context
+    pub snippet_index: usize,
+    /// fraction of the snippet's significant lines which show up in the
+    /// generated code
+    pub score: f32,
+}
+
+impl ContextSnippetRelevance {
+    pub fn is_referenced(&self) -> bool {
+        self.score > 0.0
+    }
+}
+
+/// Relevance scores for every snippet of the context which went into one
+/// edit request
+#[derive(Debug, Clone)]
+pub struct ContextRelevanceReport {
+    pub snippets: Vec<ContextSnippetRelevance>,
+    /// average of the per-snippet scores
+    pub aggregate_score: f32,
+}
+
+impl ContextRelevanceReport {
+    pub fn referenced_snippets(&self) -> usize {
+        self.snippets
+            .iter()
+            .filter(|snippet| snippet.is_referenced())
+            .count()
+    }
+}
+
+/// trimmed lines which are long enough to act as evidence that a snippet
+/// got referenced
+fn significant_lines(text: &str) -> HashSet<&str> {
+    text.lines()
+        .map(|line| line.trim())
+        .filter(|line| line.len() >= MIN_SIGNIFICANT_LINE_LENGTH)
+        .collect()
+}
+
+/// Splits the provided context on the FILEPATH: markers our prompt formats
+/// put in front of each snippet, the whole context counts as one snippet
+/// when no markers are present
+pub fn split_context_snippets(context: &str) -> Vec<String> {
+    let mut snippets: Vec<Vec<&str>> = vec![];
+    for line in context.lines() {
+        if line.trim_start().starts_with("FILEPATH:") || snippets.is_empty() {
+            snippets.push(vec![]);
+        }
+        snippets
+            .last_mut()
+            .expect("pushed above to work")
+            .push(line);
+    }
+    snippets
+        .into_iter()
+        .map(|snippet_lines| snippet_lines.join("\n"))
+        .collect()
+}
+
+/// Scores how much of each context snippet the generated code references,
+/// by the overlap of their significant lines
+pub fn score_context_relevance(context: &str, generated_code: &str) -> ContextRelevanceReport {
+    let generated_lines = significant_lines(generated_code);
+    let snippets = split_context_snippets(context)
+        .into_iter()
+        .enumerate()
+        .map(|(snippet_index, snippet)| {
+            let snippet_lines = significant_lines(&snippet);
+            let score = if snippet_lines.is_empty() {
+                0.0
+            } else {
+                snippet_lines
+                    .iter()
+                    .filter(|line| generated_lines.contains(*line))
+                    .count() as f32
+                    / snippet_lines.len() as f32
+            };
+            ContextSnippetRelevance {
+                snippet_index,
+                score,
+            }
+        })
+        .collect::<Vec<_>>();
+    let aggregate_score = if snippets.is_empty() {
+        0.0
+    } else {
+        snippets
+            .iter()
+            .map(|snippet| snippet.score)
+            .sum::<f32>()
+            / snippets.len() as f32
+    };
+    ContextRelevanceReport {
+        snippets,
+        aggregate_score,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{score_context_relevance, split_context_snippets};
+
+    #[test]
+    fn test_context_splits_on_filepath_markers() {
+        let context = r#"FILEPATH: src/lib.rs
+fn first_function() {}
+FILEPATH: src/main.rs
+fn second_function() {}"#;
+        let snippets = split_context_snippets(context);
+        assert_eq!(snippets.len(), 2);
+        assert!(snippets[0].contains("first_function"));
+        assert!(snippets[1].contains("second_function"));
+        // no markers means the whole context is one snippet
+        assert_eq!(split_context_snippets("just some code").len(), 1);
+    }
+
+    #[test]
+    fn test_referenced_snippet_scores_higher() {
+        let context = r#"FILEPATH: src/used.rs
+let total = accumulate_totals(&entries);
+FILEPATH: src/unused.rs
+fn completely_unrelated_helper() {}"#;
+        let generated = r#"fn updated() {
+    let total = accumulate_totals(&entries);
+}"#;
+        let report = score_context_relevance(context, generated);
+        assert_eq!(report.referenced_snippets(), 1);
+        assert!(report.snippets[0].is_referenced());
+        assert!(!report.snippets[1].is_referenced());
+    }
+
+    #[test]
+    fn test_short_lines_do_not_count_as_evidence() {
+        // braces and short keywords exist in any generated code
+        let report = score_context_relevance("}\nelse {\n)", "}\nelse {\n)");
+        assert_eq!(report.aggregate_score, 0.0);
+        assert_eq!(report.referenced_snippets(), 0);
+    }
+}
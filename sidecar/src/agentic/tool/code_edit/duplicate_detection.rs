@@ -0,0 +1,205 @@
+//! Near-duplicate detection between a generated edit and existing code
+//!
+//! Models happily re-implement logic which already exists in the attached
+//! context instead of calling it. We simhash token shingles of the
+//! generated code and of every context snippet, a small hamming distance
+//! between the fingerprints means the edit duplicates existing logic and
+//! should probably reuse the existing symbol instead
+
+use std::hash::{Hash, Hasher};
+
+use super::context_relevance::split_context_snippets;
+
+/// how many consecutive tokens go into one shingle
+const SHINGLE_SIZE: usize = 3;
+
+/// fingerprints closer than this many differing bits count as duplicated
+/// logic, 64-bit simhashes of unrelated code sit around 32 bits apart
+const MAX_DUPLICATE_HAMMING_DISTANCE: u32 = 6;
+
+/// snippets with fewer tokens than this produce fingerprints dominated by
+/// noise, we skip them instead of reporting false duplicates
+const MIN_TOKENS_FOR_FINGERPRINT: usize = 24;
+
+/// A context snippet whose fingerprint sits close to the generated code
+#[derive(Debug, Clone)]
+pub struct DuplicateCandidate {
+    /// position of the snippet in the provided context
+    pub snippet_index: usize,
+    /// file path from the snippet's FILEPATH: marker, when present
+    pub fs_file_path: Option<String>,
+    /// differing bits between the two fingerprints, lower is more similar
+    pub hamming_distance: u32,
+}
+
+/// splits code into the alphanumeric tokens which carry its logic,
+/// punctuation and whitespace wash out across formatting styles
+fn tokens(content: &str) -> Vec<&str> {
+    content
+        .split(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+        .filter(|token| !token.is_empty())
+        .collect()
+}
+
+/// Simhash over token shingles: every shingle votes on each of the 64 bits
+/// and the majority wins, so mostly-shared shingles give mostly-shared bits
+pub fn simhash_fingerprint(content: &str) -> Option<u64> {
+    let tokens = tokens(content);
+    if tokens.len() < MIN_TOKENS_FOR_FINGERPRINT {
+        return None;
+    }
+    let mut bit_votes = [0i32; 64];
+    for shingle in tokens.windows(SHINGLE_SIZE) {
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        shingle.hash(&mut hasher);
+        let shingle_hash = hasher.finish();
+        for (bit, vote) in bit_votes.iter_mut().enumerate() {
+            if shingle_hash & (1u64 << bit) != 0 {
+                *vote += 1;
+            } else {
+                *vote -= 1;
+            }
+        }
+    }
+    let mut fingerprint = 0u64;
+    for (bit, vote) in bit_votes.iter().enumerate() {
+        if *vote > 0 {
+            fingerprint |= 1u64 << bit;
+        }
+    }
+    Some(fingerprint)
+}
+
+/// pulls the path out of the FILEPATH: marker line when the snippet starts
+/// with one
+fn snippet_file_path(snippet: &str) -> Option<String> {
+    snippet
+        .lines()
+        .next()
+        .and_then(|line| line.trim().strip_prefix("FILEPATH:"))
+        .map(|path| path.trim().to_owned())
+}
+
+/// Flags context snippets whose logic the generated code duplicates,
+/// closest matches first
+pub fn detect_duplicated_logic(generated_code: &str, context: &str) -> Vec<DuplicateCandidate> {
+    let Some(generated_fingerprint) = simhash_fingerprint(generated_code) else {
+        return vec![];
+    };
+    let mut candidates = split_context_snippets(context)
+        .into_iter()
+        .enumerate()
+        .filter_map(|(snippet_index, snippet)| {
+            let snippet_fingerprint = simhash_fingerprint(&snippet)?;
+            let hamming_distance = (generated_fingerprint ^ snippet_fingerprint).count_ones();
+            if hamming_distance > MAX_DUPLICATE_HAMMING_DISTANCE {
+                return None;
+            }
+            Some(DuplicateCandidate {
+                snippet_index,
+                fs_file_path: snippet_file_path(&snippet),
+                hamming_distance,
+            })
+        })
+        .collect::<Vec<_>>();
+    candidates.sort_by_key(|candidate| candidate.hamming_distance);
+    candidates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{detect_duplicated_logic, simhash_fingerprint};
+
+    #[test]
+    fn test_identical_code_has_zero_distance() {
+        let code = r#"fn binary_search(items: &[i32], needle: i32) -> Option<usize> {
+    let mut low = 0;
+    let mut high = items.len();
+    while low < high {
+        let mid = low + (high - low) / 2;
+        if items[mid] == needle {
+            return Some(mid);
+        } else if items[mid] < needle {
+            low = mid + 1;
+        } else {
+            high = mid;
+        }
+    }
+    None
+}"#;
+        let first = simhash_fingerprint(code).expect("enough tokens");
+        let second = simhash_fingerprint(code).expect("enough tokens");
+        assert_eq!((first ^ second).count_ones(), 0);
+    }
+
+    #[test]
+    fn test_tiny_snippets_produce_no_fingerprint() {
+        assert!(simhash_fingerprint("let x = 1;").is_none());
+    }
+
+    #[test]
+    fn test_duplicated_logic_is_flagged_with_its_file_path() {
+        let existing = r#"FILEPATH: src/search.rs
+fn binary_search(items: &[i32], needle: i32) -> Option<usize> {
+    let mut low = 0;
+    let mut high = items.len();
+    while low < high {
+        let mid = low + (high - low) / 2;
+        if items[mid] == needle {
+            return Some(mid);
+        } else if items[mid] < needle {
+            low = mid + 1;
+        } else {
+            high = mid;
+        }
+    }
+    None
+}"#;
+        // the generated edit re-implements the same search under a new
+        // name, the shingle overlap still dominates
+        let generated = r#"fn search_sorted(items: &[i32], needle: i32) -> Option<usize> {
+    let mut low = 0;
+    let mut high = items.len();
+    while low < high {
+        let mid = low + (high - low) / 2;
+        if items[mid] == needle {
+            return Some(mid);
+        } else if items[mid] < needle {
+            low = mid + 1;
+        } else {
+            high = mid;
+        }
+    }
+    None
+}"#;
+        let candidates = detect_duplicated_logic(generated, existing);
+        assert_eq!(candidates.len(), 1);
+        assert_eq!(candidates[0].fs_file_path.as_deref(), Some("src/search.rs"));
+    }
+
+    #[test]
+    fn test_unrelated_code_is_not_flagged() {
+        let existing = r#"FILEPATH: src/parser.rs
+fn parse_header(input: &str) -> Option<(String, String)> {
+    let mut parts = input.splitn(2, ':');
+    let name = parts.next()?.trim().to_owned();
+    let value = parts.next()?.trim().to_owned();
+    if name.is_empty() {
+        return None;
+    }
+    Some((name, value))
+}"#;
+        let generated = r#"fn render_table(rows: &[Vec<String>]) -> String {
+    let mut output = String::new();
+    for row in rows.iter() {
+        for cell in row.iter() {
+            output.push_str(cell);
+            output.push('\t');
+        }
+        output.push('\n');
+    }
+    output
+}"#;
+        assert!(detect_duplicated_logic(generated, existing).is_empty());
+    }
+}
@@ -0,0 +1,207 @@
+//! Heuristic fast path for trivial anchored edits
+//!
+//! One-line asks like "rename foo to bar" or "delete this" spend ten plus
+//! seconds going through the full planning pipeline today. The detector
+//! maps those instructions onto deterministic transforms (an lsp rename, a
+//! direct apply of an empty replacement) which run in well under a second,
+//! anything the heuristics cannot place falls through to the full flow
+//! untouched
+
+use crate::chunking::text_document::{Position, Range};
+
+/// What a trivial instruction maps onto before we know where in the file
+/// it lands
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FastPathEdit {
+    /// "rename foo to bar", both sides have to be identifiers so natural
+    /// language asks fall through to the full pipeline
+    Rename {
+        old_name: String,
+        new_name: String,
+    },
+    /// "delete this" and friends, the selection goes away entirely
+    DeleteSelection,
+}
+
+/// A fast path edit resolved against the file, ready to execute
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResolvedFastPathEdit {
+    /// rename through the lsp at the position the old name sits on
+    Rename {
+        position: Position,
+        old_name: String,
+        new_name: String,
+    },
+    DeleteSelection,
+}
+
+/// instructions longer than this carry intent the heuristics cannot see
+const FAST_PATH_INSTRUCTION_LIMIT: usize = 80;
+
+/// Maps a trivial instruction onto a fast path edit, None means the full
+/// pipeline should handle it
+pub fn detect_fast_path(instruction: &str) -> Option<FastPathEdit> {
+    let trimmed = instruction.trim().trim_end_matches(['.', '!']);
+    if trimmed.len() > FAST_PATH_INSTRUCTION_LIMIT {
+        return None;
+    }
+    if let Some(rest) = strip_prefix_ignore_ascii_case(trimmed, "rename ") {
+        let mut parts = rest.splitn(2, " to ");
+        let old_name = parts.next()?.trim().trim_matches('`');
+        let new_name = parts.next()?.trim().trim_matches('`');
+        if is_identifier(old_name) && is_identifier(new_name) {
+            return Some(FastPathEdit::Rename {
+                old_name: old_name.to_owned(),
+                new_name: new_name.to_owned(),
+            });
+        }
+        return None;
+    }
+    let mut words = trimmed.split_whitespace();
+    let first_word = words.next()?.to_lowercase();
+    if first_word == "delete" || first_word == "remove" {
+        // every remaining word has to be filler pointing at the selection,
+        // "delete the retry logic" names something and falls through
+        let filler = [
+            "this", "the", "selected", "selection", "block", "code", "line", "lines", "snippet",
+        ];
+        if words.all(|word| filler.contains(&word.to_lowercase().as_str())) {
+            return Some(FastPathEdit::DeleteSelection);
+        }
+    }
+    None
+}
+
+/// Resolves the detected edit against the file, a rename needs the old
+/// name to really sit inside the selection
+pub fn resolve_fast_path(
+    fast_path_edit: FastPathEdit,
+    file_contents: &str,
+    selection_range: &Range,
+) -> Option<ResolvedFastPathEdit> {
+    match fast_path_edit {
+        FastPathEdit::DeleteSelection => Some(ResolvedFastPathEdit::DeleteSelection),
+        FastPathEdit::Rename { old_name, new_name } => {
+            let position =
+                position_of_identifier(file_contents, &old_name, selection_range)?;
+            Some(ResolvedFastPathEdit::Rename {
+                position,
+                old_name,
+                new_name,
+            })
+        }
+    }
+}
+
+/// First occurrence of the identifier on the selected lines, with word
+/// boundaries so renaming `id` does not anchor inside `identifier`
+fn position_of_identifier(
+    file_contents: &str,
+    identifier: &str,
+    selection_range: &Range,
+) -> Option<Position> {
+    for (line_index, line) in file_contents.lines().enumerate() {
+        if line_index < selection_range.start_line() || line_index > selection_range.end_line() {
+            continue;
+        }
+        let mut search_start = 0;
+        while let Some(offset) = line[search_start..].find(identifier) {
+            let column = search_start + offset;
+            let before_ok = column == 0
+                || !line[..column]
+                    .chars()
+                    .next_back()
+                    .map(|c| c.is_alphanumeric() || c == '_')
+                    .unwrap_or(false);
+            let after = line[column + identifier.len()..].chars().next();
+            let after_ok = !after.map(|c| c.is_alphanumeric() || c == '_').unwrap_or(false);
+            if before_ok && after_ok {
+                return Some(Position::new(line_index, column, 0));
+            }
+            search_start = column + identifier.len();
+        }
+    }
+    None
+}
+
+fn strip_prefix_ignore_ascii_case<'a>(value: &'a str, prefix: &str) -> Option<&'a str> {
+    if value.len() >= prefix.len() && value[..prefix.len()].eq_ignore_ascii_case(prefix) {
+        Some(&value[prefix.len()..])
+    } else {
+        None
+    }
+}
+
+fn is_identifier(value: &str) -> bool {
+    !value.is_empty()
+        && value
+            .chars()
+            .next()
+            .map(|c| c.is_ascii_alphabetic() || c == '_')
+            .unwrap_or(false)
+        && value.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{detect_fast_path, resolve_fast_path, FastPathEdit, ResolvedFastPathEdit};
+    use crate::chunking::text_document::{Position, Range};
+
+    #[test]
+    fn test_rename_with_identifiers_is_detected() {
+        assert_eq!(
+            detect_fast_path("rename old_count to new_count"),
+            Some(FastPathEdit::Rename {
+                old_name: "old_count".to_owned(),
+                new_name: "new_count".to_owned(),
+            })
+        );
+        // natural language stays on the full pipeline
+        assert_eq!(
+            detect_fast_path("rename this to something clearer"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_delete_selection_needs_filler_words_only() {
+        assert_eq!(
+            detect_fast_path("delete this block"),
+            Some(FastPathEdit::DeleteSelection)
+        );
+        assert_eq!(
+            detect_fast_path("remove the selected lines"),
+            Some(FastPathEdit::DeleteSelection)
+        );
+        // naming what to delete means the heuristic cannot be sure
+        assert_eq!(detect_fast_path("delete the retry logic"), None);
+    }
+
+    #[test]
+    fn test_long_instructions_fall_through() {
+        let instruction = "rename old_count to new_count and while you are at it restructure the loop so it no longer allocates";
+        assert_eq!(detect_fast_path(instruction), None);
+    }
+
+    #[test]
+    fn test_rename_resolves_with_word_boundaries() {
+        let file_contents = "let identifier = 1;\nlet id = identifier + 2;\n";
+        let selection_range = Range::new(Position::new(0, 0, 0), Position::new(1, 0, 0));
+        let resolved = resolve_fast_path(
+            FastPathEdit::Rename {
+                old_name: "id".to_owned(),
+                new_name: "index".to_owned(),
+            },
+            file_contents,
+            &selection_range,
+        );
+        assert_eq!(
+            resolved,
+            Some(ResolvedFastPathEdit::Rename {
+                position: Position::new(1, 4, 0),
+                old_name: "id".to_owned(),
+                new_name: "index".to_owned(),
+            })
+        );
+    }
+}
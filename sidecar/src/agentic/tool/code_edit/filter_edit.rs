@@ -53,6 +53,10 @@ impl FilterEditOperationRequest {
             root_id,
         }
     }
+
+    pub fn set_llm_properties(&mut self, llm_properties: LLMProperties) {
+        self.llm_properties = llm_properties;
+    }
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -0,0 +1,181 @@
+//! Detects the indentation style of a file and re-indents generated code to
+//! match it. Models frequently answer with 4 spaces no matter what the file
+//! uses, which is review noise in most languages and breakage in python, so
+//! we normalize the replacement blocks before they are applied
+
+/// The indentation style of a file, spaces carry the width of one level
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndentationStyle {
+    Tabs,
+    Spaces(usize),
+}
+
+impl IndentationStyle {
+    /// Detects the indentation style from file content, None when nothing in
+    /// the content is indented so there is nothing to go by
+    pub fn detect(content: &str) -> Option<Self> {
+        let mut tab_indented_lines = 0;
+        let mut space_indented_lines = 0;
+        let mut space_width_gcd = 0;
+        for line in content.lines() {
+            if line.starts_with('\t') {
+                tab_indented_lines += 1;
+            } else if line.starts_with(' ') {
+                space_indented_lines += 1;
+                let leading_spaces = line.chars().take_while(|c| *c == ' ').count();
+                space_width_gcd = gcd(space_width_gcd, leading_spaces);
+            }
+        }
+        if tab_indented_lines == 0 && space_indented_lines == 0 {
+            return None;
+        }
+        if tab_indented_lines >= space_indented_lines {
+            Some(IndentationStyle::Tabs)
+        } else {
+            // the gcd of the leading space counts is the indent width, a file
+            // indented at 4 and 8 spaces gets 4, at 2 and 6 gets 2
+            Some(IndentationStyle::Spaces(space_width_gcd.max(1)))
+        }
+    }
+
+    /// Measures the leading whitespace of a line in units of this style,
+    /// returning the level and any leftover spaces (alignment padding)
+    fn measure(&self, line: &str) -> (usize, usize) {
+        match self {
+            IndentationStyle::Tabs => {
+                let tabs = line.chars().take_while(|c| *c == '\t').count();
+                let leftover = line[tabs..].chars().take_while(|c| *c == ' ').count();
+                (tabs, leftover)
+            }
+            IndentationStyle::Spaces(width) => {
+                let spaces = line.chars().take_while(|c| *c == ' ').count();
+                (spaces / width, spaces % width)
+            }
+        }
+    }
+
+    /// Renders the given indentation level in this style
+    fn render(&self, level: usize) -> String {
+        match self {
+            IndentationStyle::Tabs => "\t".repeat(level),
+            IndentationStyle::Spaces(width) => " ".repeat(level * width),
+        }
+    }
+}
+
+/// Re-indents a single line from one style to another, keeping alignment
+/// padding after the indentation as it is. Lines whose leading whitespace is
+/// not in the from style are left untouched rather than guessed at
+pub fn reindent_line(line: &str, from: IndentationStyle, to: IndentationStyle) -> String {
+    if from == to || line.trim().is_empty() {
+        return line.to_owned();
+    }
+    let (level, leftover) = from.measure(line);
+    let content = line.trim_start_matches(|c| c == ' ' || c == '\t');
+    // the leading whitespace mixes tabs and spaces in a way the from style
+    // cannot account for, do not touch it
+    let expected_prefix_len = line.len() - content.len();
+    let measured_prefix = match from {
+        IndentationStyle::Tabs => level + leftover,
+        IndentationStyle::Spaces(width) => level * width + leftover,
+    };
+    if measured_prefix != expected_prefix_len {
+        return line.to_owned();
+    }
+    format!("{}{}{}", to.render(level), " ".repeat(leftover), content)
+}
+
+/// Applies the file's indentation style to a single generated line. Models
+/// overwhelmingly indent with 4 spaces no matter what the file uses, so that
+/// is what we assume when converting spaces to tabs; lines which already use
+/// the file's style pass through untouched
+pub fn enforce_indentation(line: &str, style: IndentationStyle) -> String {
+    match style {
+        IndentationStyle::Tabs if line.starts_with(' ') => {
+            reindent_line(line, IndentationStyle::Spaces(4), IndentationStyle::Tabs)
+        }
+        IndentationStyle::Spaces(width) if line.starts_with('\t') => {
+            reindent_line(line, IndentationStyle::Tabs, IndentationStyle::Spaces(width))
+        }
+        _ => line.to_owned(),
+    }
+}
+
+fn gcd(a: usize, b: usize) -> usize {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{reindent_line, IndentationStyle};
+
+    #[test]
+    fn test_detects_tabs_and_space_widths() {
+        assert_eq!(
+            IndentationStyle::detect("fn main() {\n\tcall();\n}"),
+            Some(IndentationStyle::Tabs)
+        );
+        assert_eq!(
+            IndentationStyle::detect("def f():\n  if x:\n      y()"),
+            Some(IndentationStyle::Spaces(2))
+        );
+        assert_eq!(IndentationStyle::detect("no indentation here"), None);
+    }
+
+    #[test]
+    fn test_reindents_spaces_to_tabs() {
+        assert_eq!(
+            reindent_line(
+                "        call();",
+                IndentationStyle::Spaces(4),
+                IndentationStyle::Tabs
+            ),
+            "\t\tcall();"
+        );
+    }
+
+    #[test]
+    fn test_reindents_between_space_widths_keeping_alignment() {
+        assert_eq!(
+            reindent_line(
+                "     aligned",
+                IndentationStyle::Spaces(4),
+                IndentationStyle::Spaces(2)
+            ),
+            "   aligned"
+        );
+    }
+
+    #[test]
+    fn test_enforce_converts_model_spaces_in_a_tab_file() {
+        use super::enforce_indentation;
+        assert_eq!(
+            enforce_indentation("        call();", IndentationStyle::Tabs),
+            "\t\tcall();"
+        );
+        assert_eq!(
+            enforce_indentation("\tcall();", IndentationStyle::Spaces(2)),
+            "  call();"
+        );
+        assert_eq!(
+            enforce_indentation("\tcall();", IndentationStyle::Tabs),
+            "\tcall();"
+        );
+    }
+
+    #[test]
+    fn test_leaves_mixed_indentation_alone() {
+        assert_eq!(
+            reindent_line(
+                " \tmixed",
+                IndentationStyle::Spaces(4),
+                IndentationStyle::Tabs
+            ),
+            " \tmixed"
+        );
+    }
+}
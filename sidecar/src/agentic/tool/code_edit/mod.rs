@@ -1,8 +1,15 @@
 pub(crate) mod code_editor;
+pub mod auto_fix;
+pub mod compiler_suggestions;
+pub mod context_relevance;
+pub mod duplicate_detection;
+pub mod fast_path;
 pub(crate) mod filter_edit;
 pub(crate) mod find;
+pub mod indentation;
 pub mod models;
-pub(crate) mod search_and_replace;
+pub mod module_path;
+pub mod search_and_replace;
 pub(crate) mod test_correction;
 pub mod types;
 pub mod xml_processor;
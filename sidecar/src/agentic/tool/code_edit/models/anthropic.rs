@@ -757,6 +757,12 @@ impl CodeEditPromptFormatters for AnthropicCodeEditFromatter {
         // add the system message
         messages.push(LLMClientMessage::system(system_message));
         messages.extend(few_shot_examples);
+        // the system message and the few shot examples never change between
+        // edit requests, marking the end of that prefix as a cache point lets
+        // anthropic reuse it instead of reprocessing it on every edit
+        if let Some(last_message) = messages.last_mut() {
+            last_message.set_cache_point();
+        }
         messages.extend(user_messages);
 
         // we use 0.2 temperature so the model can imagine ✨
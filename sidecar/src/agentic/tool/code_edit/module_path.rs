@@ -0,0 +1,167 @@
+//! Resolves the canonical import path of a symbol per language, using the
+//! file layout and the nearest manifest. The code-edit prompts carry the
+//! resolved path so generated imports are correct on the first try instead
+//! of bouncing through the correction loop
+
+use std::path::{Path, PathBuf};
+
+/// The canonical import path of a symbol defined in the given file, None
+/// when the language is not one we can resolve or the layout gives us no
+/// anchor (no Cargo.toml, no package, ...)
+pub fn canonical_import_path(
+    fs_file_path: &str,
+    symbol_name: &str,
+    language: &str,
+) -> Option<String> {
+    match language.to_lowercase().as_str() {
+        "rust" | "rs" => {
+            let cargo_root = nearest_manifest_directory(fs_file_path, "Cargo.toml")?;
+            let relative = Path::new(fs_file_path).strip_prefix(&cargo_root).ok()?;
+            rust_module_path(relative, symbol_name)
+        }
+        "python" | "py" => python_module_path(fs_file_path, symbol_name),
+        "typescript" | "tsx" | "javascript" | "jsx" => {
+            let package_root = nearest_manifest_directory(fs_file_path, "package.json")?;
+            let relative = Path::new(fs_file_path).strip_prefix(&package_root).ok()?;
+            javascript_module_specifier(relative)
+        }
+        _ => None,
+    }
+}
+
+/// walks up from the file to the closest directory holding the manifest
+fn nearest_manifest_directory(fs_file_path: &str, manifest_name: &str) -> Option<PathBuf> {
+    let mut current = Path::new(fs_file_path).parent();
+    while let Some(directory) = current {
+        if directory.join(manifest_name).is_file() {
+            return Some(directory.to_path_buf());
+        }
+        current = directory.parent();
+    }
+    None
+}
+
+/// The `crate::module::Item` path for a file relative to its Cargo.toml.
+/// `src/lib.rs` and `src/main.rs` sit at the crate root, `mod.rs` names the
+/// directory it lives in, every other file names itself
+pub(crate) fn rust_module_path(relative_path: &Path, symbol_name: &str) -> Option<String> {
+    let mut components = relative_path
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy().to_string())
+        .collect::<Vec<_>>();
+    // only files under src/ have a module path we can derive
+    if components.first().map(|c| c.as_str()) != Some("src") {
+        return None;
+    }
+    components.remove(0);
+    let file_name = components.pop()?;
+    match file_name.as_str() {
+        "lib.rs" | "main.rs" | "mod.rs" => {}
+        _ => {
+            if let Some(stem) = file_name.strip_suffix(".rs") {
+                components.push(stem.to_owned());
+            } else {
+                return None;
+            }
+        }
+    }
+    let mut segments = vec!["crate".to_owned()];
+    segments.extend(components);
+    segments.push(symbol_name.to_owned());
+    Some(segments.join("::"))
+}
+
+/// The `package.module.Class` path for a python file, the package being the
+/// chain of directories carrying an `__init__.py`
+pub(crate) fn python_module_path(fs_file_path: &str, symbol_name: &str) -> Option<String> {
+    let path = Path::new(fs_file_path);
+    let file_stem = path.file_stem()?.to_string_lossy().to_string();
+    let mut packages = vec![];
+    let mut current = path.parent();
+    while let Some(directory) = current {
+        if directory.join("__init__.py").is_file() {
+            packages.push(directory.file_name()?.to_string_lossy().to_string());
+            current = directory.parent();
+        } else {
+            break;
+        }
+    }
+    if packages.is_empty() && file_stem == "__init__" {
+        return None;
+    }
+    packages.reverse();
+    if file_stem != "__init__" {
+        packages.push(file_stem);
+    }
+    packages.push(symbol_name.to_owned());
+    Some(packages.join("."))
+}
+
+/// The module specifier for a typescript or javascript file relative to its
+/// package.json, extension stripped and `/index` folded into the directory
+pub(crate) fn javascript_module_specifier(relative_path: &Path) -> Option<String> {
+    let specifier = relative_path.to_string_lossy().to_string();
+    let specifier = ["ts", "tsx", "js", "jsx"]
+        .iter()
+        .find_map(|extension| specifier.strip_suffix(&format!(".{}", extension)))
+        .map(|stripped| stripped.to_owned())?;
+    let specifier = specifier
+        .strip_suffix("/index")
+        .map(|stripped| stripped.to_owned())
+        .unwrap_or(specifier);
+    Some(specifier)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{javascript_module_specifier, python_module_path, rust_module_path};
+    use std::path::Path;
+
+    #[test]
+    fn test_rust_module_paths_follow_the_file_layout() {
+        assert_eq!(
+            rust_module_path(Path::new("src/agentic/tool/broker.rs"), "ToolBroker"),
+            Some("crate::agentic::tool::broker::ToolBroker".to_owned())
+        );
+        assert_eq!(
+            rust_module_path(Path::new("src/agentic/tool/mod.rs"), "ToolBroker"),
+            Some("crate::agentic::tool::ToolBroker".to_owned())
+        );
+        assert_eq!(
+            rust_module_path(Path::new("src/lib.rs"), "Application"),
+            Some("crate::Application".to_owned())
+        );
+        assert_eq!(
+            rust_module_path(Path::new("benches/parsing.rs"), "bench"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_python_module_path_uses_the_init_chain() {
+        let tmp_dir = tempfile::tempdir().expect("tempdir to work");
+        let package = tmp_dir.path().join("mypkg").join("sub");
+        std::fs::create_dir_all(&package).expect("create_dir_all to work");
+        std::fs::write(tmp_dir.path().join("mypkg").join("__init__.py"), "").expect("write");
+        std::fs::write(package.join("__init__.py"), "").expect("write");
+        let module = package.join("worker.py");
+        std::fs::write(&module, "class Worker: pass").expect("write");
+        assert_eq!(
+            python_module_path(&module.to_string_lossy(), "Worker"),
+            Some("mypkg.sub.worker.Worker".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_javascript_specifier_strips_extension_and_index() {
+        assert_eq!(
+            javascript_module_specifier(Path::new("src/utils/parser.ts")),
+            Some("src/utils/parser".to_owned())
+        );
+        assert_eq!(
+            javascript_module_specifier(Path::new("src/utils/index.ts")),
+            Some("src/utils".to_owned())
+        );
+        assert_eq!(javascript_module_specifier(Path::new("src/README.md")), None);
+    }
+}
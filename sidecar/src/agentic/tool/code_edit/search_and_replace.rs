@@ -10,7 +10,7 @@ use tokio::sync::{mpsc::UnboundedSender, Semaphore};
 
 use llm_client::{
     broker::LLMBroker,
-    clients::types::{LLMClientCompletionRequest, LLMClientMessage},
+    clients::types::{GenerationProfile, LLMClientCompletionRequest, LLMClientMessage},
 };
 
 use crate::{
@@ -20,6 +20,9 @@ use crate::{
             ui_event::{EditedCodeStreamingRequest, UIEventWithID},
         },
         tool::{
+            code_edit::context_relevance,
+            code_edit::duplicate_detection,
+            code_edit::indentation::{enforce_indentation, IndentationStyle},
             errors::ToolError,
             helpers::{
                 cancellation_future::run_with_cancellation, diff_recent_changes::DiffRecentChanges,
@@ -165,6 +168,16 @@ impl SearchAndReplaceEditingRequest {
             should_stream,
         }
     }
+
+    pub fn fs_file_path(&self) -> &str {
+        &self.fs_file_path
+    }
+
+    /// Swaps the model the edit runs with, used by the broker when a
+    /// per-tool model override is configured
+    pub fn set_llm_properties(&mut self, llm_properties: LLMProperties) {
+        self.llm_properties = llm_properties;
+    }
 }
 
 pub struct StreamedEditingForEditor {
@@ -678,18 +691,20 @@ impl Tool for SearchAndReplaceEditing {
                 }
             })
             .collect::<Vec<_>>();
+        let cache_contents = context.cache_contents.clone();
         let user_messages = self.user_messages(context);
         let example_messages = self.example_messages();
-        let mut request = LLMClientCompletionRequest::new(
-            llm_properties.llm().to_owned(),
+        // edits want the deterministic profile so repeated runs produce the
+        // same diff
+        let mut request = LLMClientCompletionRequest::from_messages_with_profile(
+            GenerationProfile::Deterministic,
             vec![system_message]
                 .into_iter()
                 .chain(previous_messages)
                 .chain(example_messages)
                 .chain(user_messages)
                 .collect(),
-            0.2,
-            None,
+            llm_properties.llm().to_owned(),
         );
         if is_warmup {
             request = request.set_max_tokens(1);
@@ -939,6 +954,32 @@ impl Tool for SearchAndReplaceEditing {
                     "search_and_replace_accumulator::apply_directly({})",
                     &self.apply_directly
                 );
+                if let Some(cache_contents) = cache_contents.as_ref() {
+                    // which of the attached context snippets the edit really
+                    // used, for tuning the default context policies
+                    let relevance_report = context_relevance::score_context_relevance(
+                        cache_contents,
+                        response.answer_up_until_now(),
+                    );
+                    println!(
+                        "search_and_replace_editing::context_relevance::aggregate({:.2})::referenced({}/{})",
+                        relevance_report.aggregate_score,
+                        relevance_report.referenced_snippets(),
+                        relevance_report.snippets.len(),
+                    );
+                    // flag when the edit re-implements logic which already
+                    // exists in the attached context
+                    for candidate in duplicate_detection::detect_duplicated_logic(
+                        response.answer_up_until_now(),
+                        cache_contents,
+                    ) {
+                        println!(
+                            "search_and_replace_editing::duplicate_detection::near_duplicate({})::hamming({})",
+                            candidate.fs_file_path.as_deref().unwrap_or("unknown"),
+                            candidate.hamming_distance,
+                        );
+                    }
+                }
                 if self.apply_directly {
                     // update the file directly over here
                     if let Some(parent) = Path::new(&fs_file_path).parent() {
@@ -947,19 +988,13 @@ impl Tool for SearchAndReplaceEditing {
                     let mut file = tokio::fs::File::create(fs_file_path)
                         .await
                         .map_err(|e| ToolError::IOError(e))?;
-                    file.write_all(
-                        search_and_replace_accumulator
-                            .code_lines
-                            .to_vec()
-                            .join("\n")
-                            .as_bytes(),
-                    )
-                    .await
-                    .map_err(|e| ToolError::IOError(e))?;
+                    file.write_all(search_and_replace_accumulator.updated_file_content().as_bytes())
+                        .await
+                        .map_err(|e| ToolError::IOError(e))?;
                 }
                 Ok(ToolOutput::search_and_replace_editing(
                     SearchAndReplaceEditingResponse::new(
-                        search_and_replace_accumulator.code_lines.join("\n"),
+                        search_and_replace_accumulator.updated_file_content(),
                         response.answer_up_until_now().to_owned(),
                     ),
                 ))
@@ -1013,6 +1048,14 @@ pub struct SearchAndReplaceAccumulator {
     search_block_status: SearchBlockStatus,
     updated_block: Option<String>,
     sender: UnboundedSender<EditDelta>,
+    /// line-ending style of the file we are editing, we normalize to \n
+    /// internally and restore this when joining the lines back
+    line_ending: String,
+    /// whether the file ended with a newline when we started editing
+    ends_with_newline: bool,
+    /// indentation style of the file we are editing, replacement lines get
+    /// re-indented to this when the model answered in a different style
+    file_indentation: Option<IndentationStyle>,
 }
 
 impl SearchAndReplaceAccumulator {
@@ -1038,9 +1081,22 @@ impl SearchAndReplaceAccumulator {
             search_block_status: SearchBlockStatus::NoBlock,
             updated_block: None,
             sender,
+            line_ending: detect_line_ending(&code_to_edit).to_owned(),
+            ends_with_newline: code_to_edit.ends_with('\n'),
+            file_indentation: IndentationStyle::detect(&code_to_edit),
         }
     }
 
+    /// Joins the edited lines back together preserving the line-ending style
+    /// and the final-newline convention the file came with
+    pub fn updated_file_content(&self) -> String {
+        let mut content = self.code_lines.join(&self.line_ending);
+        if self.ends_with_newline && !content.is_empty() {
+            content.push_str(&self.line_ending);
+        }
+        content
+    }
+
     pub async fn end_streaming(&mut self) {
         let _ = self.sender.send(EditDelta::EndPollingStream);
     }
@@ -1088,11 +1144,15 @@ impl SearchAndReplaceAccumulator {
                         // Locating relevant snippet...
                         // ```language
                         let answer_lines_len = answer_lines.len();
-                        let answer_lines_last = answer_lines[answer_lines_len - 1].to_owned();
-                        let answer_lines_second_last =
-                            answer_lines[answer_lines_len - 2].to_owned();
-                        answer_lines[answer_lines_len - 1] = answer_lines_second_last;
-                        answer_lines[answer_lines_len - 2] = answer_lines_last;
+                        // the search block can show up as the very first line of
+                        // the answer, in which case there is nothing to swap with
+                        if answer_lines_len >= 2 {
+                            let answer_lines_last = answer_lines[answer_lines_len - 1].to_owned();
+                            let answer_lines_second_last =
+                                answer_lines[answer_lines_len - 2].to_owned();
+                            answer_lines[answer_lines_len - 1] = answer_lines_second_last;
+                            answer_lines[answer_lines_len - 2] = answer_lines_last;
+                        }
                         self.answer_to_show = answer_lines.join("\n");
                     } else {
                         if answer_line_at_index != "```" {
@@ -1113,9 +1173,14 @@ impl SearchAndReplaceAccumulator {
                     // so we should check for that too
                     if answer_line_at_index == divider {
                         let (sender, receiver) = tokio::sync::oneshot::channel();
-                        let _result = self.sender.send(EditDelta::EditLockAcquire(sender));
+                        // bind to _ so a failed send drops the oneshot sender right away,
+                        // a SendError held across the await below would deadlock us
+                        let _ = self.sender.send(EditDelta::EditLockAcquire(sender));
                         let file_contents = receiver.await.ok().flatten();
                         if let Some(file_contents) = file_contents {
+                            self.line_ending = detect_line_ending(&file_contents).to_owned();
+                            self.ends_with_newline = file_contents.ends_with('\n');
+                            self.file_indentation = IndentationStyle::detect(&file_contents);
                             self.code_lines = file_contents
                                 .lines()
                                 .into_iter()
@@ -1152,7 +1217,7 @@ impl SearchAndReplaceAccumulator {
                                 // then the ```{language}
                                 // accumulated lines for the search block
                                 // and the last ``` which we leave for rendering purposes
-                                answer_lines.truncate(answer_lines_len - (accumulated_length + 3));
+                                answer_lines.truncate(answer_lines_len.saturating_sub(accumulated_length + 3));
                                 answer_lines.push("Generating code....".to_owned());
                                 self.answer_to_show = answer_lines.join("\n");
                             }
@@ -1175,7 +1240,7 @@ impl SearchAndReplaceAccumulator {
                                     .map(|answer_line| answer_line.to_owned())
                                     .collect::<Vec<_>>();
                                 let answer_lines_len = answer_lines.len();
-                                answer_lines.truncate(answer_lines_len - (accumulated_length + 3));
+                                answer_lines.truncate(answer_lines_len.saturating_sub(accumulated_length + 3));
                                 answer_lines
                                     .push("Failed to find relevant code snippet...".to_owned());
                                 self.answer_to_show = answer_lines.join("\n");
@@ -1198,9 +1263,14 @@ impl SearchAndReplaceAccumulator {
                 SearchBlockStatus::BlockAccumulate(accumulated) => {
                     if answer_line_at_index == divider {
                         let (sender, receiver) = tokio::sync::oneshot::channel();
-                        let _result = self.sender.send(EditDelta::EditLockAcquire(sender));
+                        // bind to _ so a failed send drops the oneshot sender right away,
+                        // a SendError held across the await below would deadlock us
+                        let _ = self.sender.send(EditDelta::EditLockAcquire(sender));
                         let file_contents = receiver.await.ok().flatten();
                         if let Some(file_contents) = file_contents {
+                            self.line_ending = detect_line_ending(&file_contents).to_owned();
+                            self.ends_with_newline = file_contents.ends_with('\n');
+                            self.file_indentation = IndentationStyle::detect(&file_contents);
                             self.code_lines = file_contents
                                 .lines()
                                 .into_iter()
@@ -1239,7 +1309,7 @@ impl SearchAndReplaceAccumulator {
                                 // then the ```{language}
                                 // accumulated lines for the search block
                                 // and the last ``` which we leave for rendering purposes
-                                answer_lines.truncate(answer_lines_len - (accumulated_length + 3));
+                                answer_lines.truncate(answer_lines_len.saturating_sub(accumulated_length + 3));
                                 answer_lines.push("Generating code....".to_owned());
                                 self.answer_to_show = answer_lines.join("\n");
                             }
@@ -1262,7 +1332,7 @@ impl SearchAndReplaceAccumulator {
                                     .map(|answer_line| answer_line.to_owned())
                                     .collect::<Vec<_>>();
                                 let answer_lines_len = answer_lines.len();
-                                answer_lines.truncate(answer_lines_len - (accumulated_length + 3));
+                                answer_lines.truncate(answer_lines_len.saturating_sub(accumulated_length + 3));
                                 answer_lines
                                     .push("Failed to find relevant code snippet...".to_owned());
                                 self.answer_to_show = answer_lines.join("\n");
@@ -1330,27 +1400,39 @@ impl SearchAndReplaceAccumulator {
             }
             return;
         }
+        // the block range always comes back offset by start_line, clamp the
+        // indices anyways so a bad range can never panic us mid-stream
         if let Some(updated_answer) = self.updated_block.clone() {
-            let updated_range_start_line = block_range.start_line() - self.start_line;
-            let updated_range_end_line = block_range.end_line() - self.start_line;
+            let updated_range_start_line = block_range
+                .start_line()
+                .saturating_sub(self.start_line)
+                .min(self.code_lines.len());
+            let updated_range_end_line = block_range.end_line().saturating_sub(self.start_line);
             let mut updated_code_lines = self.code_lines[..updated_range_start_line].join("\n");
             if updated_range_start_line != 0 {
                 updated_code_lines.push('\n');
             }
             updated_code_lines.push_str(&updated_answer);
             updated_code_lines.push('\n');
-            updated_code_lines
-                .push_str(&self.code_lines[(updated_range_end_line + 1)..].join("\n"));
+            updated_code_lines.push_str(
+                &self.code_lines[(updated_range_end_line + 1).min(self.code_lines.len())..]
+                    .join("\n"),
+            );
             self.code_lines = updated_code_lines
                 .lines()
                 .map(|line| line.to_owned())
                 .collect();
         } else {
-            let updated_range_start_line = block_range.start_line() - self.start_line;
-            let updated_range_end_line = block_range.end_line() - self.start_line;
+            let updated_range_start_line = block_range
+                .start_line()
+                .saturating_sub(self.start_line)
+                .min(self.code_lines.len());
+            let updated_range_end_line = block_range.end_line().saturating_sub(self.start_line);
             let mut updated_code_lines = self.code_lines[..updated_range_start_line].join("\n");
-            updated_code_lines
-                .push_str(&self.code_lines[(updated_range_end_line + 1)..].join("\n"));
+            updated_code_lines.push_str(
+                &self.code_lines[(updated_range_end_line + 1).min(self.code_lines.len())..]
+                    .join("\n"),
+            );
             self.code_lines = updated_code_lines
                 .lines()
                 .map(|line| line.to_owned())
@@ -1360,6 +1442,16 @@ impl SearchAndReplaceAccumulator {
     }
 
     fn update_block(&mut self, answer_line_at_index: &str, block_range: &Range) {
+        // re-indent the replacement line to the file's style before it goes
+        // into both the code lines and the delta stream so they stay in sync
+        let reindented_line;
+        let answer_line_at_index = match self.file_indentation {
+            Some(style) => {
+                reindented_line = enforce_indentation(answer_line_at_index, style);
+                reindented_line.as_str()
+            }
+            None => answer_line_at_index,
+        };
         if self.updated_block.is_none() {
             self.updated_block = Some(answer_line_at_index.to_owned());
             let _ = self.sender.send(EditDelta::EditDelta((
@@ -1378,6 +1470,15 @@ impl SearchAndReplaceAccumulator {
     }
 }
 
+/// Detects the line-ending style of the content, windows files keep \r\n
+fn detect_line_ending(content: &str) -> &'static str {
+    if content.contains("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    }
+}
+
 /// Helps to get the last line number which has a \n
 fn get_last_newline_line_number(s: &str) -> Option<usize> {
     s.rfind('\n')
@@ -1407,7 +1508,13 @@ fn get_range_for_search_block(
         return Some(Range::new(Position::new(0, 0, 0), Position::new(0, 0, 0)));
     }
 
-    let search_block_lines = search_block.lines().into_iter().collect::<Vec<_>>();
+    // strip stray carriage returns so a model echoing windows line endings
+    // still matches the normalized file lines
+    let search_block_lines = search_block
+        .lines()
+        .into_iter()
+        .map(|line| line.trim_end_matches('\r'))
+        .collect::<Vec<_>>();
     let search_block_len = search_block_lines.len();
     if code_to_look_at_lines.len() < search_block_len {
         // return early over here if we do not want to edit this
@@ -2024,4 +2131,55 @@ fn add_numbers(a: i32, b: i32) -> i32 {
 }"#
         );
     }
+
+    #[tokio::test]
+    async fn test_crlf_and_final_newline_are_preserved() {
+        let code = "fn main() {\r\n    println!(\"hi\");\r\n}\r\n";
+        let edits = "src/main.rs\n```rust\n<<<<<<< SEARCH\n    println!(\"hi\");\n=======\n    println!(\"hello\");\n>>>>>>> REPLACE\n```\n";
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+        // answer the edit-lock handshake so the accumulator does not wait on
+        // a peer which is not there in the test
+        tokio::spawn(async move {
+            while let Some(edit_delta) = receiver.recv().await {
+                if let super::EditDelta::EditLockAcquire(lock_sender) = edit_delta {
+                    let _ = lock_sender.send(None);
+                }
+            }
+        });
+        let mut search_and_replace_accumulator =
+            SearchAndReplaceAccumulator::new(code.to_owned(), 0, sender);
+        search_and_replace_accumulator
+            .add_delta(edits.to_owned())
+            .await;
+        assert_eq!(
+            search_and_replace_accumulator.updated_file_content(),
+            "fn main() {\r\n    println!(\"hello\");\r\n}\r\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_replacement_lines_are_reindented_to_the_file_style() {
+        // the file indents with tabs but the model answers with 4 spaces
+        let code = "fn main() {\n\tprintln!(\"hi\");\n}\n";
+        let edits = "src/main.rs\n```rust\n<<<<<<< SEARCH\n\tprintln!(\"hi\");\n=======\n    println!(\"hello\");\n>>>>>>> REPLACE\n```\n";
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+        // answer the edit-lock handshake so the accumulator does not wait on
+        // a peer which is not there in the test
+        tokio::spawn(async move {
+            while let Some(edit_delta) = receiver.recv().await {
+                if let super::EditDelta::EditLockAcquire(lock_sender) = edit_delta {
+                    let _ = lock_sender.send(None);
+                }
+            }
+        });
+        let mut search_and_replace_accumulator =
+            SearchAndReplaceAccumulator::new(code.to_owned(), 0, sender);
+        search_and_replace_accumulator
+            .add_delta(edits.to_owned())
+            .await;
+        assert_eq!(
+            search_and_replace_accumulator.updated_file_content(),
+            "fn main() {\n\tprintln!(\"hello\");\n}\n"
+        );
+    }
 }
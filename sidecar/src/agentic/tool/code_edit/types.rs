@@ -6,7 +6,11 @@
 use std::sync::Arc;
 
 use async_trait::async_trait;
-use llm_client::{broker::LLMBroker, clients::types::LLMType};
+use llm_client::{
+    broker::LLMBroker,
+    capabilities::{estimate_prompt_tokens, ModelCapabilities},
+    clients::types::LLMType,
+};
 use tokio::sync::mpsc::UnboundedSender;
 
 use crate::{
@@ -25,6 +29,8 @@ use crate::{
     chunking::text_document::Range,
 };
 
+use super::context_relevance;
+use super::duplicate_detection;
 use super::models::broker::CodeEditBroker;
 
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
@@ -312,6 +318,12 @@ impl Tool for CodeEditingTool {
         let session_id = code_edit_context.session_id.to_owned();
         let fs_file_path = code_edit_context.fs_file_path.to_owned();
         let mut llm_message = self.broker.format_prompt(&code_edit_context)?;
+        // generating past the closing tag of the edit block is wasted cost,
+        // abort the provider stream as soon as it shows up
+        llm_message = llm_message.set_stop_after_patterns(vec![
+            "</code_edited>".to_owned(),
+            "</code_to_add>".to_owned(),
+        ]);
         if let Some(llm_properties) = self.get_llm_properties() {
             llm_message = llm_message.set_llm(llm_properties.llm().clone());
         }
@@ -341,6 +353,30 @@ impl Tool for CodeEditingTool {
                     code_edit_context.llm_properties.provider().clone(),
                 )
             };
+        // consult the capability registry before sending anything: when the
+        // prompt is already past the model's context window the request can
+        // only fail mid-run, so fall over to the bigger failover model upfront
+        let estimated_prompt_tokens = llm_message
+            .messages()
+            .iter()
+            .map(|message| estimate_prompt_tokens(message.content()))
+            .sum::<usize>();
+        let (request_llm, request_api_key, request_provider) =
+            match ModelCapabilities::for_model(&request_llm) {
+                Some(capabilities) if !capabilities.fits_in_context(estimated_prompt_tokens) => {
+                    println!(
+                        "code_editing_tool::context_window_exceeded::model({:?})::falling_over_to({:?})",
+                        &request_llm,
+                        self.fail_over_llm.llm(),
+                    );
+                    (
+                        self.fail_over_llm.llm().clone(),
+                        self.fail_over_llm.api_key().clone(),
+                        self.fail_over_llm.provider().clone(),
+                    )
+                }
+                _ => (request_llm, request_api_key, request_provider),
+            };
         llm_message = llm_message.set_llm(request_llm.clone());
         let mut retries = 0;
         // if we are not streaming we get more tries over here
@@ -454,6 +490,32 @@ impl Tool for CodeEditingTool {
             }
             match stream_result {
                 Some(Ok(response)) => {
+                    if let Some(user_provided_context) = code_edit_context.user_provided_context() {
+                        // which of the attached context snippets the edit
+                        // really used, for tuning the default context policies
+                        let relevance_report = context_relevance::score_context_relevance(
+                            &user_provided_context,
+                            response.answer_up_until_now(),
+                        );
+                        println!(
+                            "code_editing_tool::context_relevance::aggregate({:.2})::referenced({}/{})",
+                            relevance_report.aggregate_score,
+                            relevance_report.referenced_snippets(),
+                            relevance_report.snippets.len(),
+                        );
+                        // flag when the edit re-implements logic which
+                        // already exists in the attached context
+                        for candidate in duplicate_detection::detect_duplicated_logic(
+                            response.answer_up_until_now(),
+                            &user_provided_context,
+                        ) {
+                            println!(
+                                "code_editing_tool::duplicate_detection::near_duplicate({})::hamming({})",
+                                candidate.fs_file_path.as_deref().unwrap_or("unknown"),
+                                candidate.hamming_distance,
+                            );
+                        }
+                    }
                     let edited_code = Self::edit_code(
                         response.answer_up_until_now(),
                         code_edit_context.is_new_sub_symbol().is_some(),
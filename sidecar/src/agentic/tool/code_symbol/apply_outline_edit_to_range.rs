@@ -72,6 +72,10 @@ impl ApplyOutlineEditsToRangeRequest {
             exchange_id,
         }
     }
+
+    pub fn set_llm_properties(&mut self, llm_properties: LLMProperties) {
+        self.llm_properties = llm_properties;
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -9,6 +9,7 @@ pub mod initial_request_follow;
 pub mod models;
 pub mod new_location;
 pub mod new_sub_symbol;
+pub mod outline_diff;
 pub mod planning_before_code_edit;
 pub mod probe;
 pub mod probe_question_for_symbol;
@@ -2394,7 +2394,7 @@ Your reply should always be contained in <reply> tags, and the summarized result
         )
     }
 
-    fn system_message_for_probe_next_symbol(&self) -> String {
+    pub fn system_message_for_probe_next_symbol(&self) -> String {
         r#"You are an expert software engineer who is an expert at deciding if we have enough information to answer the user query or we need to look deeper in the codebase while working in an editor.
 - You are given this history of the various clicks we have done up until now in the editor to get to the current location in the codebase in <history> tag.
 - You are also given some extra symbols which we have accumulated up until now in the <extra_data> section.
@@ -3888,7 +3888,7 @@ You must only select symbols and lines to reply from the <code_in_selection> sec
         )
     }
 
-    fn system_message_for_class_symbol(&self) -> String {
+    pub fn system_message_for_class_symbol(&self) -> String {
         r#"You are an expert software engineer tasked with coming up with the next set of steps which need to be done because some class or enum definition has changed. More specifically some part of the class or enum defintiions have changed and we have to understand which identifier symbols to follow.
 - You will be provided with the original code for the symbol we are looking at and the content of the symbol after we have made the edits. You have to only select the identifiers which we should follow (usually by doing go-to-references) since they have changed in a big way.
 - The original code for the symbol will be provided in <original_code> section and the edited code in <edited_code> section.
@@ -4118,7 +4118,7 @@ def subtract(a, b, c, d):
 "#
         )
     }
-    fn system_message_for_correctness_check(&self) -> String {
+    pub fn system_message_for_correctness_check(&self) -> String {
         format!(
             r#"You are an expert software engineer who is tasked with taking actions for fixing errors in the code which is being written in the editor.
 - You will be given a list of quick fixes suggested by your code editor.
@@ -4909,7 +4909,7 @@ Other LLM's are implementing FillInMiddleFormatter trait, grok will also require
     async fn user_message_for_codebase_wide_search(
         &self,
         code_symbol_search_context_wide: CodeSymbolImportantWideSearch,
-    ) -> Result<String, CodeSymbolError> {
+    ) -> Result<Vec<LLMClientMessage>, CodeSymbolError> {
         let user_query = code_symbol_search_context_wide.user_query().to_owned();
         let file_extension_filter = code_symbol_search_context_wide.file_extension_filters();
         let recent_edits = code_symbol_search_context_wide.recent_edits().to_owned();
@@ -4920,8 +4920,7 @@ Other LLM's are implementing FillInMiddleFormatter trait, grok will also require
             .await
             .map_err(|e| CodeSymbolError::UserContextError(e))?;
         let mut user_message = format!(
-            r#"{context_string}
-<recent_edits>
+            r#"<recent_edits>
 {recent_edits}
 </recent_edits>
 <lsp_diagnostics>
@@ -4934,7 +4933,10 @@ Other LLM's are implementing FillInMiddleFormatter trait, grok will also require
 
         // if this is a big message, the easiest proxy is to look at the number of lines
         // and make sure that we send a reminder to it
-        if user_message.lines().collect::<Vec<_>>().len() > 2000 {
+        if context_string.lines().collect::<Vec<_>>().len()
+            + user_message.lines().collect::<Vec<_>>().len()
+            > 2000
+        {
             user_message = user_message
                 + "\n"
                 + r#"As a reminder, your output should strictly follow this format:
@@ -4958,7 +4960,16 @@ Other LLM's are implementing FillInMiddleFormatter trait, grok will also require
 </step_by_step>
 </reply>"#;
         }
-        Ok(user_message)
+        // the user context carries the file contents and the repo map which
+        // stay stable across the exchanges on a session, marking them as a
+        // cache point lets anthropic reuse that prefix while the recent edits
+        // and the query keep changing
+        let mut messages = vec![];
+        if !context_string.is_empty() {
+            messages.push(LLMClientMessage::user(context_string).cache_point());
+        }
+        messages.push(LLMClientMessage::user(user_message));
+        Ok(messages)
     }
 
     fn system_message_for_repo_map_search(
@@ -5277,7 +5288,9 @@ impl CodeSymbolImportant for AnthropicCodeSymbolImportant {
         &self,
         code_symbols: CodeSymbolImportantRequest,
     ) -> Result<CodeSymbolImportantResponse, CodeSymbolError> {
-        let system_message = LLMClientMessage::system(self.system_message(&code_symbols));
+        // the system message is the same for every request on a model so we
+        // mark it as a cache point
+        let system_message = LLMClientMessage::system(self.system_message(&code_symbols)).cache_point();
         let user_message = LLMClientMessage::user(self.user_message(&code_symbols));
         let messages = LLMClientCompletionRequest::new(
             code_symbols.model().clone(),
@@ -5358,17 +5371,14 @@ impl CodeSymbolImportant for AnthropicCodeSymbolImportant {
         let exchange_id = code_symbols.exchange_id();
         let ui_sender = code_symbols.message_properties().ui_sender();
         let cancellation_token = code_symbols.message_properties().cancellation_token();
-        let system_message = LLMClientMessage::system(self.system_message_context_wide());
-        let user_message = LLMClientMessage::user(
+        let system_message =
+            LLMClientMessage::system(self.system_message_context_wide()).cache_point();
+        let mut final_messages = vec![system_message];
+        final_messages.extend(
             self.user_message_for_codebase_wide_search(code_symbols)
                 .await?,
         );
-        let messages = LLMClientCompletionRequest::new(
-            model.clone(),
-            vec![system_message, user_message],
-            0.0,
-            None,
-        );
+        let messages = LLMClientCompletionRequest::new(model.clone(), final_messages, 0.0, None);
 
         let mut retries = 0;
         loop {
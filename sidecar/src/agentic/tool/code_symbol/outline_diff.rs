@@ -0,0 +1,311 @@
+//! Structural diff between two versions of a file computed over outline
+//! nodes. Followup prompts and session summaries want "what changed at the
+//! symbol level" (added/removed/signature changed) instead of the full old and
+//! new file contents, which keeps the prompt small for large files.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use crate::agentic::tool::r#type::ToolRewardScale;
+use crate::agentic::tool::{errors::ToolError, input::ToolInput, output::ToolOutput, r#type::Tool};
+use crate::chunking::languages::TSLanguageParsing;
+
+pub struct OutlineDiffTool {
+    language_parsing: Arc<TSLanguageParsing>,
+}
+
+impl OutlineDiffTool {
+    pub fn new(language_parsing: Arc<TSLanguageParsing>) -> Self {
+        Self { language_parsing }
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct OutlineDiffRequest {
+    fs_file_path: String,
+    old_content: String,
+    new_content: String,
+}
+
+impl OutlineDiffRequest {
+    pub fn new(fs_file_path: String, old_content: String, new_content: String) -> Self {
+        Self {
+            fs_file_path,
+            old_content,
+            new_content,
+        }
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct OutlineDiffResponse {
+    /// symbols present in the new version but not the old
+    added: Vec<String>,
+    /// symbols present in the old version but not the new
+    removed: Vec<String>,
+    /// symbols present in both versions whose signature line changed
+    signature_changed: Vec<String>,
+    /// (old name, new name) pairs where a symbol disappeared and another
+    /// appeared with the same body, almost certainly a rename
+    #[serde(default)]
+    renamed: Vec<(String, String)>,
+    /// symbols present in both versions with the same signature
+    unchanged_count: usize,
+}
+
+impl OutlineDiffResponse {
+    pub fn added(&self) -> &[String] {
+        &self.added
+    }
+
+    pub fn removed(&self) -> &[String] {
+        &self.removed
+    }
+
+    pub fn signature_changed(&self) -> &[String] {
+        &self.signature_changed
+    }
+
+    pub fn renamed(&self) -> &[(String, String)] {
+        &self.renamed
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.signature_changed.is_empty()
+            && self.renamed.is_empty()
+    }
+
+    /// Renders the diff for inclusion in followup prompts and session
+    /// summaries.
+    pub fn to_summary(&self) -> String {
+        if self.is_empty() {
+            return "No structural changes (no symbols added, removed or re-signatured)."
+                .to_owned();
+        }
+        let mut lines = vec!["Structural changes:".to_owned()];
+        for symbol in self.added.iter() {
+            lines.push(format!("- added: {}", symbol));
+        }
+        for symbol in self.removed.iter() {
+            lines.push(format!("- removed: {}", symbol));
+        }
+        for symbol in self.signature_changed.iter() {
+            lines.push(format!("- signature changed: {}", symbol));
+        }
+        for (old_name, new_name) in self.renamed.iter() {
+            lines.push(format!("- renamed: {} -> {}", old_name, new_name));
+        }
+        lines.push(format!("({} symbols unchanged)", self.unchanged_count));
+        lines.join("\n")
+    }
+}
+
+/// The body of a symbol is everything after its signature line, two symbols
+/// sharing a non-trivial body across a remove/add pair are treated as a
+/// rename.
+fn body_of(content: &str) -> String {
+    content
+        .lines()
+        .skip(1)
+        .map(|line| line.trim())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// The signature we compare is the first line of the symbol, that is where
+/// function arguments, generics and return types live for the languages we
+/// parse.
+fn signature_of(content: &str) -> String {
+    content.lines().next().unwrap_or("").trim().to_owned()
+}
+
+/// Computes the structural diff between the (name, content) pairs of the old
+/// and new outline. Order in the output follows the new version for added and
+/// changed symbols and the old version for removed ones.
+pub(crate) fn compute_outline_diff(
+    old_symbols: &[(String, String)],
+    new_symbols: &[(String, String)],
+) -> OutlineDiffResponse {
+    let old_by_name = old_symbols
+        .iter()
+        .map(|(name, content)| (name.as_str(), signature_of(content)))
+        .collect::<HashMap<_, _>>();
+    let new_names = new_symbols
+        .iter()
+        .map(|(name, _)| name.as_str())
+        .collect::<Vec<_>>();
+
+    let mut added = vec![];
+    let mut signature_changed = vec![];
+    let mut unchanged_count = 0;
+    for (name, content) in new_symbols.iter() {
+        match old_by_name.get(name.as_str()) {
+            None => added.push(name.to_owned()),
+            Some(old_signature) => {
+                if old_signature != &signature_of(content) {
+                    signature_changed.push(name.to_owned());
+                } else {
+                    unchanged_count += 1;
+                }
+            }
+        }
+    }
+    let mut removed = old_symbols
+        .iter()
+        .filter(|(name, _)| !new_names.contains(&name.as_str()))
+        .map(|(name, _)| name.to_owned())
+        .collect::<Vec<_>>();
+
+    // pair up removed and added symbols with identical non-empty bodies,
+    // those are renames rather than independent changes
+    let old_bodies = old_symbols
+        .iter()
+        .map(|(name, content)| (name.as_str(), body_of(content)))
+        .collect::<HashMap<_, _>>();
+    let new_bodies = new_symbols
+        .iter()
+        .map(|(name, content)| (name.as_str(), body_of(content)))
+        .collect::<HashMap<_, _>>();
+    let mut renamed = vec![];
+    for removed_name in removed.to_vec().into_iter() {
+        let removed_body = old_bodies.get(removed_name.as_str());
+        let rename_target = added.iter().find(|added_name| {
+            removed_body
+                .map(|body| !body.is_empty() && new_bodies.get(added_name.as_str()) == Some(body))
+                .unwrap_or(false)
+        });
+        if let Some(rename_target) = rename_target.cloned() {
+            added.retain(|name| name != &rename_target);
+            removed.retain(|name| name != &removed_name);
+            renamed.push((removed_name, rename_target));
+        }
+    }
+    OutlineDiffResponse {
+        added,
+        removed,
+        signature_changed,
+        renamed,
+        unchanged_count,
+    }
+}
+
+impl OutlineDiffTool {
+    fn outline_symbols(&self, fs_file_path: &str, content: &str) -> Vec<(String, String)> {
+        let Some(language_config) = self.language_parsing.for_file_path(fs_file_path) else {
+            return vec![];
+        };
+        language_config
+            .generate_outline_fresh(content.as_bytes(), fs_file_path)
+            .into_iter()
+            .map(|outline_node| {
+                (
+                    outline_node.name().to_owned(),
+                    outline_node.content().content().to_owned(),
+                )
+            })
+            .collect()
+    }
+}
+
+#[async_trait]
+impl Tool for OutlineDiffTool {
+    async fn invoke(&self, input: ToolInput) -> Result<ToolOutput, ToolError> {
+        let request = input.should_outline_diff()?;
+        let old_symbols = self.outline_symbols(&request.fs_file_path, &request.old_content);
+        let new_symbols = self.outline_symbols(&request.fs_file_path, &request.new_content);
+        let response = compute_outline_diff(&old_symbols, &new_symbols);
+        Ok(ToolOutput::outline_diff_response(response))
+    }
+
+    fn tool_description(&self) -> String {
+        r#"### outline_diff
+Computes a structural diff between two versions of a file: which symbols were added, removed or had their signature changed.
+Use this to summarize what an edit did instead of pasting the full old and new file contents."#
+            .to_owned()
+    }
+
+    fn tool_input_format(&self) -> String {
+        r#"Parameters:
+- fs_file_path: (required) The path of the file, used to pick the language grammar.
+- old_content: (required) The old version of the file.
+- new_content: (required) The new version of the file.
+
+Usage:
+<outline_diff>
+<fs_file_path>
+/path/to/file.rs
+</fs_file_path>
+<old_content>
+fn foo() {}
+</old_content>
+<new_content>
+fn foo(value: usize) {}
+</new_content>
+</outline_diff>"#
+            .to_owned()
+    }
+
+    fn get_evaluation_criteria(&self, _trajectory_length: usize) -> Vec<String> {
+        vec![]
+    }
+
+    fn get_reward_scale(&self, _trajectory_length: usize) -> Vec<ToolRewardScale> {
+        vec![]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::compute_outline_diff;
+
+    fn symbol(name: &str, content: &str) -> (String, String) {
+        (name.to_owned(), content.to_owned())
+    }
+
+    #[test]
+    fn test_added_removed_and_changed_symbols() {
+        let old_symbols = vec![
+            symbol("foo", "fn foo() {\n    1\n}"),
+            symbol("bar", "fn bar() {}"),
+            symbol("baz", "fn baz(a: usize) {}"),
+        ];
+        let new_symbols = vec![
+            symbol("foo", "fn foo() {\n    2\n}"),
+            symbol("baz", "fn baz(a: usize, b: usize) {}"),
+            symbol("qux", "fn qux() {}"),
+        ];
+        let diff = compute_outline_diff(&old_symbols, &new_symbols);
+        assert_eq!(diff.added(), &["qux".to_owned()]);
+        assert_eq!(diff.removed(), &["bar".to_owned()]);
+        assert_eq!(diff.signature_changed(), &["baz".to_owned()]);
+        // foo only changed its body, not its signature
+        let summary = diff.to_summary();
+        assert!(summary.contains("(1 symbols unchanged)"));
+    }
+
+    #[test]
+    fn test_renames_pair_up_matching_bodies() {
+        let old_symbols = vec![symbol("process", "fn process() {\n    run();\n    done();\n}")];
+        let new_symbols = vec![symbol("execute", "fn execute() {\n    run();\n    done();\n}")];
+        let diff = compute_outline_diff(&old_symbols, &new_symbols);
+        assert!(diff.added().is_empty());
+        assert!(diff.removed().is_empty());
+        assert_eq!(
+            diff.renamed(),
+            &[("process".to_owned(), "execute".to_owned())]
+        );
+        assert!(diff.to_summary().contains("- renamed: process -> execute"));
+    }
+
+    #[test]
+    fn test_identical_outlines_produce_empty_diff() {
+        let symbols = vec![symbol("foo", "fn foo() {}")];
+        let diff = compute_outline_diff(&symbols, &symbols);
+        assert!(diff.is_empty());
+        assert!(diff.to_summary().contains("No structural changes"));
+    }
+}
@@ -75,6 +75,10 @@ impl PlanningBeforeCodeEditRequest {
             _cancellation_token: cancellation_token,
         }
     }
+
+    pub fn set_llm_properties(&mut self, llm_properties: LLMProperties) {
+        self.llm_properties = llm_properties;
+    }
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
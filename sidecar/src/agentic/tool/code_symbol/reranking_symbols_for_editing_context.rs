@@ -83,6 +83,10 @@ impl ReRankingSnippetsForCodeEditingRequest {
             root_request_id,
         }
     }
+
+    pub fn set_llm_properties(&mut self, llm_properties: LLMProperties) {
+        self.llm_properties = llm_properties;
+    }
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -41,6 +41,10 @@ impl ShouldEditCodeSymbolRequest {
             root_request_id,
         }
     }
+
+    pub fn set_llm_properties(&mut self, llm_properties: LLMProperties) {
+        self.llm_properties = llm_properties;
+    }
 }
 
 #[derive(Debug, Clone, serde::Deserialize)]
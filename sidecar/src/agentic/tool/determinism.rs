@@ -0,0 +1,218 @@
+//! Deterministic mode for reproducible agent runs
+//!
+//! Benchmarks and bug reports want the same run twice: same sampling,
+//! same tool order, same identifiers. When the mode is on the LLM broker
+//! pins the sampling seed, the tool broker serializes fan-out invocations
+//! into input order, and the identifiers and timestamps we would normally
+//! pull from the environment come from a seeded generator instead. Every
+//! value handed out gets recorded so the trajectory trace carries the
+//! nondeterministic inputs alongside the tool records and a replay can
+//! reproduce the run bit-for-bit
+
+use std::sync::Mutex;
+
+/// The kind of nondeterministic input the seeded generator replaced
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NondeterministicInputKind {
+    Uuid,
+    Timestamp,
+}
+
+/// One value the seeded generator handed out, recorded in the order the
+/// run consumed them
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct RecordedNondeterministicInput {
+    pub kind: NondeterministicInputKind,
+    pub value: String,
+    /// position in the consumption order, replays consume in the same one
+    pub ordinal: u64,
+}
+
+/// The state behind an enabled deterministic mode
+struct DeterministicState {
+    seed: u64,
+    counter: u64,
+    recorded: Vec<RecordedNondeterministicInput>,
+}
+
+/// Runtime switch for the deterministic mode, disabled state falls back
+/// to the real clock and random uuids
+pub struct DeterminismMode {
+    state: Mutex<Option<DeterministicState>>,
+}
+
+/// Timestamps in deterministic runs count up from this fixed instant
+const DETERMINISTIC_EPOCH_SECS: i64 = 1_700_000_000;
+
+impl DeterminismMode {
+    pub fn new() -> Self {
+        Self {
+            state: Mutex::new(None),
+        }
+    }
+
+    /// Turns the mode on with a fresh seed, resetting the counter and the
+    /// recorded inputs from any earlier run
+    pub fn enable(&self, seed: u64) {
+        *self.state.lock().expect("determinism lock to not be poisoned") =
+            Some(DeterministicState {
+                seed,
+                counter: 0,
+                recorded: vec![],
+            });
+    }
+
+    pub fn disable(&self) {
+        *self.state.lock().expect("determinism lock to not be poisoned") = None;
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.state
+            .lock()
+            .expect("determinism lock to not be poisoned")
+            .is_some()
+    }
+
+    pub fn seed(&self) -> Option<u64> {
+        self.state
+            .lock()
+            .expect("determinism lock to not be poisoned")
+            .as_ref()
+            .map(|state| state.seed)
+    }
+
+    /// A request id: seeded and recorded when the mode is on, a random v4
+    /// uuid otherwise
+    pub fn request_id(&self) -> uuid::Uuid {
+        let mut guard = self
+            .state
+            .lock()
+            .expect("determinism lock to not be poisoned");
+        match guard.as_mut() {
+            Some(state) => {
+                let ordinal = state.counter;
+                state.counter += 1;
+                let uuid = seeded_uuid(state.seed, ordinal);
+                state.recorded.push(RecordedNondeterministicInput {
+                    kind: NondeterministicInputKind::Uuid,
+                    value: uuid.to_string(),
+                    ordinal,
+                });
+                uuid
+            }
+            None => uuid::Uuid::new_v4(),
+        }
+    }
+
+    /// The current time: a fixed epoch advanced by the consumption counter
+    /// when the mode is on, the real clock otherwise
+    pub fn timestamp(&self) -> chrono::DateTime<chrono::Utc> {
+        let mut guard = self
+            .state
+            .lock()
+            .expect("determinism lock to not be poisoned");
+        match guard.as_mut() {
+            Some(state) => {
+                let ordinal = state.counter;
+                state.counter += 1;
+                let timestamp = chrono::DateTime::from_timestamp(
+                    DETERMINISTIC_EPOCH_SECS + ordinal as i64,
+                    0,
+                )
+                .expect("deterministic epoch to be a valid timestamp");
+                state.recorded.push(RecordedNondeterministicInput {
+                    kind: NondeterministicInputKind::Timestamp,
+                    value: timestamp.to_rfc3339(),
+                    ordinal,
+                });
+                timestamp
+            }
+            None => chrono::Utc::now(),
+        }
+    }
+
+    /// Takes the inputs recorded since the last drain, the broker appends
+    /// them to the trajectory trace
+    pub fn drain_recorded(&self) -> Vec<RecordedNondeterministicInput> {
+        self.state
+            .lock()
+            .expect("determinism lock to not be poisoned")
+            .as_mut()
+            .map(|state| std::mem::take(&mut state.recorded))
+            .unwrap_or_default()
+    }
+}
+
+/// Deterministic uuid from the seed and the consumption ordinal, the
+/// version and variant bits are set so the result still parses as a v4
+/// uuid everywhere one is expected
+fn seeded_uuid(seed: u64, ordinal: u64) -> uuid::Uuid {
+    let high = splitmix64(seed ^ ordinal.wrapping_mul(0x9E37_79B9_7F4A_7C15));
+    let low = splitmix64(high);
+    let mut bytes = [0u8; 16];
+    bytes[..8].copy_from_slice(&high.to_be_bytes());
+    bytes[8..].copy_from_slice(&low.to_be_bytes());
+    bytes[6] = (bytes[6] & 0x0F) | 0x40;
+    bytes[8] = (bytes[8] & 0x3F) | 0x80;
+    uuid::Uuid::from_bytes(bytes)
+}
+
+/// The splitmix64 mixing function, enough statistical quality for seeded
+/// identifiers without pulling in an rng crate
+fn splitmix64(state: u64) -> u64 {
+    let mut z = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DeterminismMode, NondeterministicInputKind};
+
+    #[test]
+    fn test_same_seed_reproduces_the_same_sequence() {
+        let first = DeterminismMode::new();
+        first.enable(42);
+        let second = DeterminismMode::new();
+        second.enable(42);
+        for _ in 0..3 {
+            assert_eq!(first.request_id(), second.request_id());
+            assert_eq!(first.timestamp(), second.timestamp());
+        }
+    }
+
+    #[test]
+    fn test_different_seeds_diverge() {
+        let first = DeterminismMode::new();
+        first.enable(1);
+        let second = DeterminismMode::new();
+        second.enable(2);
+        assert_ne!(first.request_id(), second.request_id());
+    }
+
+    #[test]
+    fn test_handed_out_values_are_recorded_in_order() {
+        let mode = DeterminismMode::new();
+        mode.enable(7);
+        let uuid = mode.request_id();
+        let timestamp = mode.timestamp();
+        let recorded = mode.drain_recorded();
+        assert_eq!(recorded.len(), 2);
+        assert_eq!(recorded[0].kind, NondeterministicInputKind::Uuid);
+        assert_eq!(recorded[0].value, uuid.to_string());
+        assert_eq!(recorded[1].kind, NondeterministicInputKind::Timestamp);
+        assert_eq!(recorded[1].value, timestamp.to_rfc3339());
+        assert!(mode.drain_recorded().is_empty());
+    }
+
+    #[test]
+    fn test_disabled_mode_records_nothing() {
+        let mode = DeterminismMode::new();
+        let _ = mode.request_id();
+        let _ = mode.timestamp();
+        assert!(!mode.is_enabled());
+        assert!(mode.drain_recorded().is_empty());
+    }
+}
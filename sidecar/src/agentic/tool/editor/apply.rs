@@ -8,7 +8,7 @@ use crate::{
         output::ToolOutput,
         r#type::{Tool, ToolRewardScale},
     },
-    chunking::text_document::Range,
+    chunking::text_document::{PositionEncoding, Range},
 };
 
 pub struct EditorApply {
@@ -33,6 +33,10 @@ pub struct EditorApplyRequest {
     editor_url: String,
     // we want to apply the edits directly to the file and not stream it
     direct_apply: bool,
+    /// version of the document the edit was computed against, the editor can
+    /// reject the apply when the document has moved on since
+    #[serde(default)]
+    expected_document_version: Option<u64>,
 }
 
 impl EditorApplyRequest {
@@ -49,9 +53,22 @@ impl EditorApplyRequest {
             selected_range,
             editor_url,
             direct_apply,
+            expected_document_version: None,
         }
     }
 
+    pub fn fs_file_path(&self) -> &str {
+        &self.fs_file_path
+    }
+
+    pub fn with_expected_document_version(
+        mut self,
+        expected_document_version: Option<u64>,
+    ) -> Self {
+        self.expected_document_version = expected_document_version;
+        self
+    }
+
     fn to_editor_request(self, apply_edits: bool) -> EditorApplyRequestDirect {
         EditorApplyRequestDirect {
             fs_file_path: self.fs_file_path,
@@ -59,6 +76,7 @@ impl EditorApplyRequest {
             selected_range: self.selected_range,
             editor_url: self.editor_url,
             apply_directly: apply_edits || self.direct_apply,
+            expected_document_version: self.expected_document_version,
         }
     }
 }
@@ -70,6 +88,8 @@ pub struct EditorApplyRequestDirect {
     selected_range: Range,
     editor_url: String,
     apply_directly: bool,
+    #[serde(default)]
+    expected_document_version: Option<u64>,
 }
 
 #[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
@@ -79,11 +99,19 @@ pub struct EditorApplyResponse {
 }
 
 impl EditorApply {
-    async fn apply_edits(&self, request: EditorApplyRequest) -> Result<ToolOutput, ToolError> {
+    async fn apply_edits(&self, mut request: EditorApplyRequest) -> Result<ToolOutput, ToolError> {
         println!(
             "framework_event::edit_event::direct_apply::range({:?})::({:?})",
             &request.fs_file_path, &request.selected_range,
         );
+        // the editor talks utf-16 columns while we track code points, so we
+        // re-encode the selection before handing it over, skipping the
+        // conversion if the file is not readable
+        if let Ok(file_content) = tokio::fs::read_to_string(&request.fs_file_path).await {
+            request.selected_range = request
+                .selected_range
+                .encoded_for_file(&file_content, PositionEncoding::Utf16CodeUnit);
+        }
         let editor_endpoint = request.editor_url.to_owned() + "/apply_edits";
         let response = self
             .client
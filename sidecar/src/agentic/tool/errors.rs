@@ -97,4 +97,13 @@ pub enum ToolError {
 
     #[error("Invocation error: {0}")]
     InvocationError(String),
+
+    #[error("Tool timed out: {0}")]
+    Timeout(ToolType),
+
+    #[error("Tool disabled by policy: {0}")]
+    ToolDisabled(ToolType),
+
+    #[error("Editing the sensitive file {0} needs explicit confirmation first")]
+    SensitiveFileEditNotConfirmed(String),
 }
@@ -0,0 +1,222 @@
+//! Formats a file with the formatter of its language
+//!
+//! The edit models produce correct but badly formatted code often enough
+//! that we run the real formatter over the edited file before checking
+//! diagnostics. The language comes from `EditorParsing` and maps to the
+//! stock formatter of the ecosystem: rustfmt, prettier or black. A missing
+//! formatter binary is not an error, the file just stays as the model
+//! wrote it
+
+use async_trait::async_trait;
+use std::sync::Arc;
+
+use crate::{
+    agentic::tool::{
+        errors::ToolError,
+        input::ToolInput,
+        output::ToolOutput,
+        r#type::{Tool, ToolRewardScale},
+    },
+    chunking::{editor_parsing::EditorParsing, languages::TSLanguageConfig, text_document::Range},
+};
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct FormatCodeRequest {
+    fs_file_path: String,
+    /// the edited range, formatters which support partial formatting keep
+    /// the changes inside it, the others format the whole file
+    range: Option<Range>,
+}
+
+impl FormatCodeRequest {
+    pub fn new(fs_file_path: String, range: Option<Range>) -> Self {
+        Self {
+            fs_file_path,
+            range,
+        }
+    }
+
+    pub fn fs_file_path(&self) -> &str {
+        &self.fs_file_path
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct FormatCodeResponse {
+    /// true when a formatter ran and exited cleanly
+    formatted: bool,
+    /// what went wrong when it did not: unknown language, missing binary
+    /// or the formatter rejecting the file
+    message: Option<String>,
+}
+
+impl FormatCodeResponse {
+    pub fn formatted(&self) -> bool {
+        self.formatted
+    }
+
+    pub fn message(&self) -> Option<&str> {
+        self.message.as_deref()
+    }
+}
+
+pub struct CodeFormatterClient {
+    editor_parsing: Arc<EditorParsing>,
+}
+
+impl CodeFormatterClient {
+    pub fn new(editor_parsing: Arc<EditorParsing>) -> Self {
+        Self { editor_parsing }
+    }
+}
+
+/// The formatter command for this language, None when we do not know of
+/// one. Only black supports constraining the edit to a line range, the
+/// others always format the whole file
+fn formatter_invocation(
+    language_config: &TSLanguageConfig,
+    fs_file_path: &str,
+    range: Option<&Range>,
+) -> Option<(&'static str, Vec<String>)> {
+    if language_config.is_rust() {
+        return Some(("rustfmt", vec![fs_file_path.to_owned()]));
+    }
+    if language_config.is_python() {
+        let mut arguments = vec!["--quiet".to_owned()];
+        if let Some(range) = range {
+            // black line ranges are 1-based and inclusive
+            arguments.push("--line-ranges".to_owned());
+            arguments.push(format!(
+                "{}-{}",
+                range.start_line() + 1,
+                range.end_line() + 1
+            ));
+        }
+        arguments.push(fs_file_path.to_owned());
+        return Some(("black", arguments));
+    }
+    if language_config.is_js_like() {
+        return Some((
+            "prettier",
+            vec!["--write".to_owned(), fs_file_path.to_owned()],
+        ));
+    }
+    None
+}
+
+#[async_trait]
+impl Tool for CodeFormatterClient {
+    async fn invoke(&self, input: ToolInput) -> Result<ToolOutput, ToolError> {
+        let request = input.is_format_code()?;
+        let fs_file_path = request.fs_file_path().to_owned();
+        let Some(language_config) = self.editor_parsing.for_file_path(&fs_file_path) else {
+            return Ok(ToolOutput::format_code(FormatCodeResponse {
+                formatted: false,
+                message: Some("no language detected for file".to_owned()),
+            }));
+        };
+        let Some((command, arguments)) =
+            formatter_invocation(language_config, &fs_file_path, request.range.as_ref())
+        else {
+            return Ok(ToolOutput::format_code(FormatCodeResponse {
+                formatted: false,
+                message: Some(format!(
+                    "no formatter configured for {}",
+                    language_config.language_str
+                )),
+            }));
+        };
+        println!(
+            "format_code::invoke::command({})::file({})",
+            command, &fs_file_path
+        );
+        let output = match tokio::process::Command::new(command)
+            .args(&arguments)
+            .output()
+            .await
+        {
+            Ok(output) => output,
+            Err(e) => {
+                // the formatter not being installed should not break the
+                // correction loop
+                return Ok(ToolOutput::format_code(FormatCodeResponse {
+                    formatted: false,
+                    message: Some(format!("failed to run {}: {}", command, e)),
+                }));
+            }
+        };
+        if output.status.success() {
+            Ok(ToolOutput::format_code(FormatCodeResponse {
+                formatted: true,
+                message: None,
+            }))
+        } else {
+            Ok(ToolOutput::format_code(FormatCodeResponse {
+                formatted: false,
+                message: Some(String::from_utf8_lossy(&output.stderr).to_string()),
+            }))
+        }
+    }
+
+    fn tool_description(&self) -> String {
+        "Formats a file with the formatter of its language (rustfmt, prettier or black)".to_owned()
+    }
+
+    fn tool_input_format(&self) -> String {
+        format!(
+            r#"Parameters:
+- fs_file_path: (required) The ABSOLUTE path of the file to format.
+
+Usage:
+<format_code>
+<fs_file_path>
+File path here
+</fs_file_path>
+</format_code>
+"#
+        )
+    }
+
+    fn get_evaluation_criteria(&self, _trajectory_length: usize) -> Vec<String> {
+        vec![]
+    }
+
+    fn get_reward_scale(&self, _trajectory_length: usize) -> Vec<ToolRewardScale> {
+        vec![]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::formatter_invocation;
+    use crate::chunking::{
+        editor_parsing::EditorParsing,
+        text_document::{Position, Range},
+    };
+
+    #[test]
+    fn test_rust_files_format_with_rustfmt() {
+        let editor_parsing = EditorParsing::default();
+        let language_config = editor_parsing
+            .for_file_path("/tmp/lib.rs")
+            .expect("rust config");
+        let (command, arguments) =
+            formatter_invocation(language_config, "/tmp/lib.rs", None).expect("formatter");
+        assert_eq!(command, "rustfmt");
+        assert_eq!(arguments, vec!["/tmp/lib.rs"]);
+    }
+
+    #[test]
+    fn test_python_range_maps_to_one_based_line_ranges() {
+        let editor_parsing = EditorParsing::default();
+        let language_config = editor_parsing
+            .for_file_path("/tmp/main.py")
+            .expect("python config");
+        let range = Range::new(Position::new(4, 0, 0), Position::new(9, 0, 0));
+        let (command, arguments) =
+            formatter_invocation(language_config, "/tmp/main.py", Some(&range))
+                .expect("formatter");
+        assert_eq!(command, "black");
+        assert!(arguments.contains(&"5-10".to_owned()));
+    }
+}
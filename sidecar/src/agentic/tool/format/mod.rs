@@ -0,0 +1,3 @@
+//! Runs the language-appropriate code formatter over edited files
+
+pub mod formatter;
@@ -0,0 +1,221 @@
+//! Drives `git bisect` with a test command the agent provides, used for the
+//! "when did this break" debugging flow. The culprit commit along with its
+//! diff gets summarized back into the session so the agent can reason about
+//! the regression.
+
+use tokio::process::Command;
+
+use crate::agentic::tool::r#type::ToolRewardScale;
+use crate::agentic::tool::{errors::ToolError, input::ToolInput, output::ToolOutput, r#type::Tool};
+use async_trait::async_trait;
+
+/// we clamp the culprit diff so a giant commit does not blow up the prompt
+const CULPRIT_DIFF_BUDGET: usize = 20_000;
+
+pub struct GitBisectClient {}
+
+impl GitBisectClient {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct GitBisectRequest {
+    root_directory: String,
+    /// a commit known to be good, the starting point of the bisect
+    good_commit: String,
+    /// a commit known to be bad, defaults to HEAD when empty
+    bad_commit: String,
+    /// the command which exits non-zero on a bad commit
+    test_command: String,
+}
+
+impl GitBisectRequest {
+    pub fn new(
+        root_directory: String,
+        good_commit: String,
+        bad_commit: String,
+        test_command: String,
+    ) -> Self {
+        Self {
+            root_directory,
+            good_commit,
+            bad_commit,
+            test_command,
+        }
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct GitBisectResponse {
+    /// the first bad commit when the bisect converged
+    culprit_commit: Option<String>,
+    /// `git show` style summary of the culprit (author, date, message) along
+    /// with its diff
+    culprit_summary: String,
+    /// raw output of the bisect run for debugging
+    bisect_log: String,
+}
+
+impl GitBisectResponse {
+    pub fn culprit_commit(&self) -> Option<&str> {
+        self.culprit_commit.as_deref()
+    }
+
+    pub fn culprit_summary(&self) -> &str {
+        &self.culprit_summary
+    }
+
+    pub fn bisect_log(&self) -> &str {
+        &self.bisect_log
+    }
+
+    /// Formats the outcome the way it gets shown to the agent in the session
+    pub fn to_session_summary(&self) -> String {
+        match &self.culprit_commit {
+            Some(culprit_commit) => format!(
+                "git bisect found the first bad commit: {}\n{}",
+                culprit_commit, self.culprit_summary
+            ),
+            None => format!(
+                "git bisect did not converge on a culprit commit. Bisect output:\n{}",
+                self.bisect_log
+            ),
+        }
+    }
+}
+
+/// Parses the first bad commit hash out of the `git bisect run` output
+fn parse_culprit_commit(bisect_output: &str) -> Option<String> {
+    bisect_output.lines().find_map(|line| {
+        line.strip_suffix(" is the first bad commit")
+            .map(|commit| commit.trim().to_owned())
+    })
+}
+
+async fn run_git_command(root_directory: &str, args: &[&str]) -> Result<String, ToolError> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(root_directory)
+        .output()
+        .await
+        .map_err(|e| ToolError::IOError(e))?;
+    let mut combined = String::from_utf8_lossy(&output.stdout).to_string();
+    combined.push_str(&String::from_utf8_lossy(&output.stderr));
+    Ok(combined)
+}
+
+async fn run_bisect(request: GitBisectRequest) -> Result<GitBisectResponse, ToolError> {
+    let root_directory = request.root_directory.as_str();
+    let bad_commit = if request.bad_commit.is_empty() {
+        "HEAD"
+    } else {
+        request.bad_commit.as_str()
+    };
+    let mut bisect_log = run_git_command(
+        root_directory,
+        &["bisect", "start", bad_commit, request.good_commit.as_str()],
+    )
+    .await?;
+    let run_output = run_git_command(
+        root_directory,
+        &["bisect", "run", "sh", "-c", request.test_command.as_str()],
+    )
+    .await?;
+    bisect_log.push_str(&run_output);
+    let culprit_commit = parse_culprit_commit(&bisect_log);
+    // always reset, even when the bisect did not converge, so we do not
+    // leave the checkout in a detached state
+    let _ = run_git_command(root_directory, &["bisect", "reset"]).await;
+    let culprit_summary = match culprit_commit.as_ref() {
+        Some(culprit_commit) => {
+            let mut summary =
+                run_git_command(root_directory, &["show", "--stat", "--patch", culprit_commit])
+                    .await?;
+            if summary.len() > CULPRIT_DIFF_BUDGET {
+                summary.truncate(CULPRIT_DIFF_BUDGET);
+                summary.push_str("\n... (diff truncated)");
+            }
+            summary
+        }
+        None => "".to_owned(),
+    };
+    Ok(GitBisectResponse {
+        culprit_commit,
+        culprit_summary,
+        bisect_log,
+    })
+}
+
+#[async_trait]
+impl Tool for GitBisectClient {
+    async fn invoke(&self, input: ToolInput) -> Result<ToolOutput, ToolError> {
+        let request = input.should_git_bisect()?;
+        let response = run_bisect(request).await?;
+        Ok(ToolOutput::git_bisect_response(response))
+    }
+
+    fn tool_description(&self) -> String {
+        r#"### git_bisect
+Finds the commit which introduced a regression by driving `git bisect` with a test command.
+Provide a known good commit, a known bad commit (defaults to HEAD) and a command which exits non-zero when the bug is present.
+The culprit commit along with its diff gets summarized back so you can reason about the regression."#
+            .to_owned()
+    }
+
+    fn tool_input_format(&self) -> String {
+        r#"Parameters:
+- root_directory: (required) The root directory of the git repository.
+- good_commit: (required) A commit hash or ref known to be good.
+- bad_commit: (optional) A commit hash or ref known to be bad, defaults to HEAD.
+- test_command: (required) A shell command which exits with a non-zero status on a bad commit.
+
+Usage:
+<git_bisect>
+<root_directory>
+/path/to/repo
+</root_directory>
+<good_commit>
+v1.2.0
+</good_commit>
+<bad_commit>
+HEAD
+</bad_commit>
+<test_command>
+cargo test -p mycrate regression_test
+</test_command>
+</git_bisect>"#
+            .to_owned()
+    }
+
+    fn get_evaluation_criteria(&self, _trajectory_length: usize) -> Vec<String> {
+        vec![]
+    }
+
+    fn get_reward_scale(&self, _trajectory_length: usize) -> Vec<ToolRewardScale> {
+        vec![]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_culprit_commit;
+
+    #[test]
+    fn test_parses_first_bad_commit() {
+        let output = r#"Bisecting: 0 revisions left to test after this (roughly 0 steps)
+running sh -c cargo test
+abc123def456abc123def456abc123def456abcd is the first bad commit
+commit abc123def456abc123def456abc123def456abcd"#;
+        assert_eq!(
+            parse_culprit_commit(output).as_deref(),
+            Some("abc123def456abc123def456abc123def456abcd")
+        );
+    }
+
+    #[test]
+    fn test_no_culprit_in_output() {
+        assert!(parse_culprit_commit("bisect run failed: exit code 130").is_none());
+    }
+}
@@ -1,3 +1,4 @@
 //! Contains the helper functions for git related operations on the repo
+pub(crate) mod bisect;
 pub(crate) mod diff_client;
 pub(crate) mod edited_files;
@@ -0,0 +1,179 @@
+//! Circuit breakers around flaky tool categories
+//!
+//! When a tool keeps failing in a row we stop hammering it for a cooldown
+//! period and let callers route to a degraded alternative instead (outline
+//! fallback instead of references, in-memory state instead of an editor
+//! round trip). After the cooldown a single probe request is allowed
+//! through, a success closes the breaker again and the caller surfaces a
+//! recovery event for the ui
+
+use std::time::{Duration, Instant};
+
+use tokio::sync::Mutex;
+
+/// consecutive failures before the breaker opens
+const FAILURE_THRESHOLD: usize = 3;
+/// how long an open breaker blocks the primary before we probe it again
+const COOLDOWN: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BreakerState {
+    /// the primary is healthy, requests flow through
+    Closed,
+    /// the primary keeps failing, requests get routed to the alternative
+    Open,
+    /// cooldown expired, a single probe request is in flight
+    HalfOpen,
+}
+
+#[derive(Debug)]
+struct CircuitBreakerInner {
+    state: BreakerState,
+    consecutive_failures: usize,
+    opened_at: Option<Instant>,
+}
+
+/// One breaker guards one flaky tool category, shared behind an arc by all
+/// the call sites which hit that category
+pub struct CircuitBreaker {
+    /// name of the tool category we are guarding, shows up in the logs and
+    /// the recovery events
+    name: String,
+    failure_threshold: usize,
+    cooldown: Duration,
+    inner: Mutex<CircuitBreakerInner>,
+}
+
+impl CircuitBreaker {
+    pub fn new(name: String, failure_threshold: usize, cooldown: Duration) -> Self {
+        Self {
+            name,
+            failure_threshold,
+            cooldown,
+            inner: Mutex::new(CircuitBreakerInner {
+                state: BreakerState::Closed,
+                consecutive_failures: 0,
+                opened_at: None,
+            }),
+        }
+    }
+
+    /// Breaker with the default thresholds for a tool category
+    pub fn for_tool(name: &str) -> Self {
+        Self::new(name.to_owned(), FAILURE_THRESHOLD, COOLDOWN)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Whether the primary should be tried right now, an open breaker flips
+    /// to half-open once the cooldown has passed which lets a single probe
+    /// request through
+    pub async fn should_allow(&self) -> bool {
+        let mut inner = self.inner.lock().await;
+        match inner.state {
+            BreakerState::Closed => true,
+            BreakerState::Open => {
+                let cooled_down = inner
+                    .opened_at
+                    .map(|opened_at| opened_at.elapsed() >= self.cooldown)
+                    .unwrap_or(true);
+                if cooled_down {
+                    inner.state = BreakerState::HalfOpen;
+                    true
+                } else {
+                    false
+                }
+            }
+            // a probe is already in flight, keep everyone else on the
+            // alternative until it reports back
+            BreakerState::HalfOpen => false,
+        }
+    }
+
+    /// Records a successful call on the primary, returns true when this
+    /// success healed an open breaker so the caller can emit a recovery
+    /// event
+    pub async fn record_success(&self) -> bool {
+        let mut inner = self.inner.lock().await;
+        let recovered = inner.state != BreakerState::Closed;
+        inner.state = BreakerState::Closed;
+        inner.consecutive_failures = 0;
+        inner.opened_at = None;
+        recovered
+    }
+
+    /// Records a failed call on the primary, enough of these in a row opens
+    /// the breaker and a failed probe re-opens it
+    pub async fn record_failure(&self) {
+        let mut inner = self.inner.lock().await;
+        match inner.state {
+            BreakerState::Closed => {
+                inner.consecutive_failures = inner.consecutive_failures + 1;
+                if inner.consecutive_failures >= self.failure_threshold {
+                    inner.state = BreakerState::Open;
+                    inner.opened_at = Some(Instant::now());
+                }
+            }
+            BreakerState::HalfOpen => {
+                inner.state = BreakerState::Open;
+                inner.opened_at = Some(Instant::now());
+            }
+            BreakerState::Open => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::CircuitBreaker;
+
+    #[tokio::test]
+    async fn test_breaker_opens_after_consecutive_failures() {
+        let breaker = CircuitBreaker::new("test".to_owned(), 3, Duration::from_secs(60));
+        for _ in 0..3 {
+            assert!(breaker.should_allow().await);
+            breaker.record_failure().await;
+        }
+        assert!(!breaker.should_allow().await);
+    }
+
+    #[tokio::test]
+    async fn test_success_resets_the_failure_streak() {
+        let breaker = CircuitBreaker::new("test".to_owned(), 3, Duration::from_secs(60));
+        breaker.record_failure().await;
+        breaker.record_failure().await;
+        assert!(!breaker.record_success().await);
+        breaker.record_failure().await;
+        assert!(breaker.should_allow().await);
+    }
+
+    #[tokio::test]
+    async fn test_probe_after_cooldown_and_recovery() {
+        let breaker = CircuitBreaker::new("test".to_owned(), 1, Duration::from_millis(0));
+        breaker.record_failure().await;
+        // cooldown of zero means the next check allows a single probe
+        assert!(breaker.should_allow().await);
+        // only one probe is allowed while it is in flight
+        assert!(!breaker.should_allow().await);
+        // the probe succeeding closes the breaker and reports the recovery
+        assert!(breaker.record_success().await);
+        assert!(breaker.should_allow().await);
+    }
+
+    #[tokio::test]
+    async fn test_failed_probe_reopens_the_breaker() {
+        let breaker = CircuitBreaker::new("test".to_owned(), 1, Duration::from_millis(0));
+        breaker.record_failure().await;
+        // the zero cooldown lets a probe through straight away
+        assert!(breaker.should_allow().await);
+        breaker.record_failure().await;
+        // the failed probe re-opened the breaker, the zero cooldown again
+        // allows exactly one new probe and blocks the rest
+        assert!(breaker.should_allow().await);
+        assert!(!breaker.should_allow().await);
+    }
+}
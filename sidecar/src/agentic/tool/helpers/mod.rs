@@ -1,2 +1,3 @@
 pub(crate) mod cancellation_future;
+pub(crate) mod circuit_breaker;
 pub(crate) mod diff_recent_changes;
@@ -1,6 +1,7 @@
 use super::{
     code_edit::{
         code_editor::CodeEditorParameters,
+        compiler_suggestions::ApplyCompilerSuggestionsRequest,
         filter_edit::FilterEditOperationRequest,
         find::FindCodeSelectionInput,
         search_and_replace::SearchAndReplaceEditingRequest,
@@ -22,6 +23,7 @@ use super::{
         initial_request_follow::CodeSymbolFollowInitialRequest,
         new_location::CodeSymbolNewLocationRequest,
         new_sub_symbol::NewSubSymbolRequiredRequest,
+        outline_diff::OutlineDiffRequest,
         planning_before_code_edit::PlanningBeforeCodeEditRequest,
         probe::ProbeEnoughOrDeeperRequest,
         probe_question_for_symbol::ProbeQuestionForSymbolRequest,
@@ -42,10 +44,15 @@ use super::{
     filtering::broker::{
         CodeToEditFilterRequest, CodeToEditSymbolRequest, CodeToProbeSubSymbolRequest,
     },
-    git::{diff_client::GitDiffClientRequest, edited_files::EditedFilesRequest},
+    format::formatter::FormatCodeRequest,
+    git::{
+        bisect::GitBisectRequest, diff_client::GitDiffClientRequest,
+        edited_files::EditedFilesRequest,
+    },
     grep::file::FindInFileRequest,
     kw_search::tool::KeywordSearchQuery,
     lsp::{
+        call_hierarchy::CallHierarchyRequest,
         create_file::CreateFileRequest,
         diagnostics::LSPDiagnosticsInput,
         file_diagnostics::{FileDiagnosticsInput, WorkspaceDiagnosticsPartial},
@@ -60,9 +67,11 @@ use super::{
         list_files::{ListFilesInput, ListFilesInputPartial},
         open_file::{OpenFileRequest, OpenFileRequestPartial},
         quick_fix::{GetQuickFixRequest, LSPQuickFixInvocationRequest},
+        rename::LSPRenameRequest,
         search_file::{SearchFileContentInput, SearchFileContentInputPartial},
         subprocess_spawned_output::SubProcessSpawnedPendingOutputRequest,
         undo_changes::UndoChangesMadeDuringExchangeRequest,
+        workspace_symbol::WorkspaceSymbolRequest,
     },
     mcp::input::{McpToolInput, McpToolPartial},
     plan::{
@@ -86,8 +95,11 @@ use super::{
     swe_bench::test_tool::SWEBenchTestRequest,
     terminal::terminal::{TerminalInput, TerminalInputPartial},
     test_runner::runner::{TestRunnerRequest, TestRunnerRequestPartial},
+    tree_sitter::{import_graph::ImportGraphRequest, query::TreeSitterQueryRequest},
 };
 
+use crate::agentic::symbol::identifier::LLMProperties;
+
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub enum ToolInputPartial {
     CodeEditing(CodeEditingPartialRequest),
@@ -132,6 +144,55 @@ impl ToolInputPartial {
         }
     }
 
+    /// Parses a native tool call (the tool name the model invoked along with the
+    /// json serialized arguments) into the partial input for that tool, this is
+    /// the bridge between function-calling responses and the agent loop
+    pub fn from_native_tool_call(
+        tool_name: &str,
+        tool_input: &str,
+    ) -> Result<ToolInputPartial, ToolError> {
+        let tool_input = match tool_name {
+            "list_files" => ToolInputPartial::ListFiles(
+                serde_json::from_str::<ListFilesInputPartial>(tool_input)
+                    .map_err(|_e| ToolError::SerdeConversionFailed)?,
+            ),
+            "search_files" => ToolInputPartial::SearchFileContentWithRegex(
+                serde_json::from_str::<SearchFileContentInputPartial>(tool_input)
+                    .map_err(|_e| ToolError::SerdeConversionFailed)?,
+            ),
+            "read_file" => ToolInputPartial::OpenFile(
+                serde_json::from_str::<OpenFileRequestPartial>(tool_input)
+                    .map_err(|_e| ToolError::SerdeConversionFailed)?,
+            ),
+            "execute_command" => ToolInputPartial::TerminalCommand(
+                serde_json::from_str::<TerminalInputPartial>(tool_input)
+                    .map_err(|_e| ToolError::SerdeConversionFailed)?
+                    // well gotta do the hard things sometimes right?
+                    // or the dumb things
+                    .sanitise_for_repro_script(),
+            ),
+            "attempt_completion" => ToolInputPartial::AttemptCompletion(
+                serde_json::from_str::<AttemptCompletionClientRequest>(tool_input)
+                    .map_err(|_e| ToolError::SerdeConversionFailed)?,
+            ),
+            "test_runner" => ToolInputPartial::TestRunner(
+                serde_json::from_str::<TestRunnerRequestPartial>(tool_input)
+                    .map_err(|_e| ToolError::SerdeConversionFailed)?,
+            ),
+            "str_replace_editor" => ToolInputPartial::CodeEditorParameters(
+                serde_json::from_str::<CodeEditorParameters>(tool_input).map_err(|e| {
+                    println!("str_replace_editor::error::{:?}", e);
+                    ToolError::SerdeConversionFailed
+                })?,
+            ),
+            _ => {
+                println!("unknow tool found: {}", tool_name);
+                return Err(ToolError::MissingTool);
+            }
+        };
+        Ok(tool_input)
+    }
+
     pub fn to_string(&self) -> String {
         match self {
             Self::CodeEditing(code_editing) => code_editing.to_string(),
@@ -292,6 +353,8 @@ pub enum ToolInput {
     SearchAndReplaceEditing(SearchAndReplaceEditingRequest),
     // git diff request
     GitDiff(GitDiffClientRequest),
+    GitBisect(GitBisectRequest),
+    OutlineDiff(OutlineDiffRequest),
     OutlineNodesUsingEditor(OutlineNodesUsingEditorRequest),
     // filters references based on user query
     ReferencesFilter(ReferenceFilterRequest),
@@ -348,6 +411,20 @@ pub enum ToolInput {
     FindFiles(FindFilesRequest),
     // Request screenshot input
     RequestScreenshot(RequestScreenshotInput),
+    // Tree-sitter query input
+    TreeSitterQuery(TreeSitterQueryRequest),
+    // Format code input
+    FormatCode(FormatCodeRequest),
+    // Apply compiler suggestions input
+    ApplyCompilerSuggestions(ApplyCompilerSuggestionsRequest),
+    // Import graph input
+    ImportGraph(ImportGraphRequest),
+    // Rename symbol input
+    Rename(LSPRenameRequest),
+    // Call hierarchy input
+    CallHierarchy(CallHierarchyRequest),
+    // Workspace symbol search input
+    WorkspaceSymbol(WorkspaceSymbolRequest),
     // Model Context Protocol tool
     McpTool(McpToolInput),
 }
@@ -411,6 +488,8 @@ impl ToolInput {
             ToolInput::ShouldEditCode(_) => ToolType::ShouldEditCode,
             ToolInput::SearchAndReplaceEditing(_) => ToolType::SearchAndReplaceEditing,
             ToolInput::GitDiff(_) => ToolType::GitDiff,
+            ToolInput::GitBisect(_) => ToolType::GitBisect,
+            ToolInput::OutlineDiff(_) => ToolType::OutlineDiff,
             ToolInput::OutlineNodesUsingEditor(_) => ToolType::OutlineNodesUsingEditor,
             ToolInput::ReferencesFilter(_) => ToolType::ReferencesFilter,
             ToolInput::ScratchPadInput(_) => ToolType::ScratchPadAgent,
@@ -441,10 +520,115 @@ impl ToolInput {
             ToolInput::FeedbackGeneration(_) => ToolType::FeedbackGeneration,
             ToolInput::FindFiles(_) => ToolType::FindFiles,
             ToolInput::RequestScreenshot(_) => ToolType::RequestScreenshot,
+            ToolInput::TreeSitterQuery(_) => ToolType::TreeSitterQuery,
+            ToolInput::FormatCode(_) => ToolType::FormatCode,
+            ToolInput::ApplyCompilerSuggestions(_) => ToolType::ApplyCompilerSuggestions,
+            ToolInput::ImportGraph(_) => ToolType::ImportGraph,
+            ToolInput::Rename(_) => ToolType::Rename,
+            ToolInput::CallHierarchy(_) => ToolType::CallHierarchy,
+            ToolInput::WorkspaceSymbol(_) => ToolType::WorkspaceSymbol,
             ToolInput::McpTool(inp) => ToolType::McpTool(inp.partial.full_name.clone()),
         }
     }
 
+    /// Serialized form of the request for the audit log, only the read-only
+    /// lsp tools are covered since their requests are plain data, anything
+    /// carrying channels or editor state comes back None and cannot replay
+    pub fn replay_json(&self) -> Option<serde_json::Value> {
+        match self {
+            ToolInput::OpenFile(request) => serde_json::to_value(request).ok(),
+            ToolInput::GoToDefinition(request) | ToolInput::GoToTypeDefinition(request) => {
+                serde_json::to_value(request).ok()
+            }
+            ToolInput::GoToReference(request) => serde_json::to_value(request).ok(),
+            ToolInput::SymbolImplementations(request) => serde_json::to_value(request).ok(),
+            ToolInput::OutlineNodesUsingEditor(request) => serde_json::to_value(request).ok(),
+            ToolInput::WorkspaceSymbol(request) => serde_json::to_value(request).ok(),
+            ToolInput::CallHierarchy(request) => serde_json::to_value(request).ok(),
+            ToolInput::GrepSymbolInCodebase(request) => serde_json::to_value(request).ok(),
+            _ => None,
+        }
+    }
+
+    /// Rebuilds the input from a serialized request recorded in the audit
+    /// log, the inverse of replay_json for the tools it covers
+    pub fn from_replay_json(tool_type: &ToolType, value: serde_json::Value) -> Option<ToolInput> {
+        match tool_type {
+            ToolType::OpenFile => serde_json::from_value(value).ok().map(ToolInput::OpenFile),
+            ToolType::GoToDefinitions => serde_json::from_value(value)
+                .ok()
+                .map(ToolInput::GoToDefinition),
+            ToolType::GoToTypeDefinition => serde_json::from_value(value)
+                .ok()
+                .map(ToolInput::GoToTypeDefinition),
+            ToolType::GoToReferences => serde_json::from_value(value)
+                .ok()
+                .map(ToolInput::GoToReference),
+            ToolType::GoToImplementations => serde_json::from_value(value)
+                .ok()
+                .map(ToolInput::SymbolImplementations),
+            ToolType::OutlineNodesUsingEditor => serde_json::from_value(value)
+                .ok()
+                .map(ToolInput::OutlineNodesUsingEditor),
+            ToolType::WorkspaceSymbol => serde_json::from_value(value)
+                .ok()
+                .map(ToolInput::WorkspaceSymbol),
+            ToolType::CallHierarchy => serde_json::from_value(value)
+                .ok()
+                .map(ToolInput::CallHierarchy),
+            ToolType::GrepSymbolInCodebase => serde_json::from_value(value)
+                .ok()
+                .map(ToolInput::GrepSymbolInCodebase),
+            _ => None,
+        }
+    }
+
+    /// Rewrites the request to run with the given llm properties, the broker
+    /// goes through here when a per-tool model override is configured. Only
+    /// the tools which carry a uniform llm_properties on their request take
+    /// the override, everything else comes back unchanged
+    pub fn with_llm_properties_override(self, llm_properties: LLMProperties) -> Self {
+        match self {
+            ToolInput::SearchAndReplaceEditing(mut request) => {
+                request.set_llm_properties(llm_properties);
+                ToolInput::SearchAndReplaceEditing(request)
+            }
+            ToolInput::FilterEditOperation(mut request) => {
+                request.set_llm_properties(llm_properties);
+                ToolInput::FilterEditOperation(request)
+            }
+            ToolInput::ReferencesFilter(mut request) => {
+                request.set_llm_properties(llm_properties);
+                ToolInput::ReferencesFilter(request)
+            }
+            ToolInput::ContextDrivenChatReply(mut request) => {
+                request.set_llm_properties(llm_properties);
+                ToolInput::ContextDrivenChatReply(request)
+            }
+            ToolInput::GenerateStep(mut request) => {
+                request.set_llm_properties(llm_properties);
+                ToolInput::GenerateStep(request)
+            }
+            ToolInput::ShouldEditCode(mut request) => {
+                request.set_llm_properties(llm_properties);
+                ToolInput::ShouldEditCode(request)
+            }
+            ToolInput::ReRankingCodeSnippetsForEditing(mut request) => {
+                request.set_llm_properties(llm_properties);
+                ToolInput::ReRankingCodeSnippetsForEditing(request)
+            }
+            ToolInput::PlanningBeforeCodeEdit(mut request) => {
+                request.set_llm_properties(llm_properties);
+                ToolInput::PlanningBeforeCodeEdit(request)
+            }
+            ToolInput::ApplyOutlineEditToRange(mut request) => {
+                request.set_llm_properties(llm_properties);
+                ToolInput::ApplyOutlineEditToRange(request)
+            }
+            _ => self,
+        }
+    }
+
     pub fn is_find_files(self) -> Result<FindFilesRequest, ToolError> {
         if let ToolInput::FindFiles(request) = self {
             Ok(request)
@@ -539,6 +723,64 @@ impl ToolInput {
         }
     }
 
+    pub fn is_tree_sitter_query(self) -> Result<TreeSitterQueryRequest, ToolError> {
+        if let ToolInput::TreeSitterQuery(request) = self {
+            Ok(request)
+        } else {
+            Err(ToolError::WrongToolInput(ToolType::TreeSitterQuery))
+        }
+    }
+
+    pub fn is_format_code(self) -> Result<FormatCodeRequest, ToolError> {
+        if let ToolInput::FormatCode(request) = self {
+            Ok(request)
+        } else {
+            Err(ToolError::WrongToolInput(ToolType::FormatCode))
+        }
+    }
+
+    pub fn is_apply_compiler_suggestions(
+        self,
+    ) -> Result<ApplyCompilerSuggestionsRequest, ToolError> {
+        if let ToolInput::ApplyCompilerSuggestions(request) = self {
+            Ok(request)
+        } else {
+            Err(ToolError::WrongToolInput(ToolType::ApplyCompilerSuggestions))
+        }
+    }
+
+    pub fn is_import_graph(self) -> Result<ImportGraphRequest, ToolError> {
+        if let ToolInput::ImportGraph(request) = self {
+            Ok(request)
+        } else {
+            Err(ToolError::WrongToolInput(ToolType::ImportGraph))
+        }
+    }
+
+    pub fn is_rename(self) -> Result<LSPRenameRequest, ToolError> {
+        if let ToolInput::Rename(request) = self {
+            Ok(request)
+        } else {
+            Err(ToolError::WrongToolInput(ToolType::Rename))
+        }
+    }
+
+    pub fn is_call_hierarchy(self) -> Result<CallHierarchyRequest, ToolError> {
+        if let ToolInput::CallHierarchy(request) = self {
+            Ok(request)
+        } else {
+            Err(ToolError::WrongToolInput(ToolType::CallHierarchy))
+        }
+    }
+
+    pub fn is_workspace_symbol(self) -> Result<WorkspaceSymbolRequest, ToolError> {
+        if let ToolInput::WorkspaceSymbol(request) = self {
+            Ok(request)
+        } else {
+            Err(ToolError::WrongToolInput(ToolType::WorkspaceSymbol))
+        }
+    }
+
     pub fn is_context_driven_hot_streak_reply(self) -> Result<SessionHotStreakRequest, ToolError> {
         if let ToolInput::ContextDriveHotStreakReply(request) = self {
             Ok(request)
@@ -655,6 +897,22 @@ impl ToolInput {
         }
     }
 
+    pub fn should_git_bisect(self) -> Result<GitBisectRequest, ToolError> {
+        if let ToolInput::GitBisect(request) = self {
+            Ok(request)
+        } else {
+            Err(ToolError::WrongToolInput(ToolType::GitBisect))
+        }
+    }
+
+    pub fn should_outline_diff(self) -> Result<OutlineDiffRequest, ToolError> {
+        if let ToolInput::OutlineDiff(request) = self {
+            Ok(request)
+        } else {
+            Err(ToolError::WrongToolInput(ToolType::OutlineDiff))
+        }
+    }
+
     pub fn should_search_and_replace_editing(
         self,
     ) -> Result<SearchAndReplaceEditingRequest, ToolError> {
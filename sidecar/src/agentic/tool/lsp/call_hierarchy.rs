@@ -0,0 +1,163 @@
+//! Call hierarchy through the editor, incoming and outgoing calls of a
+//! symbol. References only show the direct call sites while the hierarchy
+//! lets us walk the transitive callers, which is what followups really
+//! care about when a function changes shape
+
+use async_trait::async_trait;
+
+use crate::{
+    agentic::tool::{
+        errors::ToolError,
+        input::ToolInput,
+        output::ToolOutput,
+        r#type::{Tool, ToolRewardScale},
+    },
+    chunking::text_document::{Position, PositionEncoding, Range},
+};
+use logging::new_client;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CallHierarchyDirection {
+    Incoming,
+    Outgoing,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct CallHierarchyRequest {
+    fs_file_path: String,
+    position: Position,
+    direction: CallHierarchyDirection,
+    editor_url: String,
+}
+
+impl CallHierarchyRequest {
+    pub fn new(
+        fs_file_path: String,
+        position: Position,
+        direction: CallHierarchyDirection,
+        editor_url: String,
+    ) -> Self {
+        Self {
+            fs_file_path,
+            position,
+            direction,
+            editor_url,
+        }
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct CallHierarchyItem {
+    /// name of the calling or called symbol
+    name: String,
+    fs_file_path: String,
+    /// range of the symbol itself
+    range: Range,
+    /// the call sites inside the symbol, for incoming calls these are where
+    /// the queried symbol gets invoked
+    from_ranges: Vec<Range>,
+}
+
+impl CallHierarchyItem {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn fs_file_path(&self) -> &str {
+        &self.fs_file_path
+    }
+
+    pub fn range(&self) -> &Range {
+        &self.range
+    }
+
+    pub fn from_ranges(&self) -> &[Range] {
+        self.from_ranges.as_slice()
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct CallHierarchyResponse {
+    items: Vec<CallHierarchyItem>,
+}
+
+impl CallHierarchyResponse {
+    pub fn items(self) -> Vec<CallHierarchyItem> {
+        self.items
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+}
+
+pub struct CallHierarchyClient {
+    client: reqwest_middleware::ClientWithMiddleware,
+}
+
+impl CallHierarchyClient {
+    pub fn new() -> Self {
+        Self {
+            client: new_client(),
+        }
+    }
+}
+
+#[async_trait]
+impl Tool for CallHierarchyClient {
+    async fn invoke(&self, input: ToolInput) -> Result<ToolOutput, ToolError> {
+        let mut context = input.is_call_hierarchy()?;
+        // the editor talks utf-16 columns while we track code points, so we
+        // re-encode the position on the way out and decode the ranges which
+        // come back, skipping the conversion if the file is not readable
+        if let Ok(file_content) = tokio::fs::read_to_string(&context.fs_file_path).await {
+            context.position = context
+                .position
+                .encoded_for_file(&file_content, PositionEncoding::Utf16CodeUnit);
+        }
+        let editor_endpoint = context.editor_url.to_owned() + "/call_hierarchy";
+        let response = self
+            .client
+            .post(editor_endpoint)
+            .body(serde_json::to_string(&context).map_err(|_e| ToolError::SerdeConversionFailed)?)
+            .send()
+            .await
+            .map_err(|_e| ToolError::ErrorCommunicatingWithEditor)?;
+        let mut response: CallHierarchyResponse = response
+            .json()
+            .await
+            .map_err(|_e| ToolError::SerdeConversionFailed)?;
+        for item in response.items.iter_mut() {
+            if let Ok(file_content) = tokio::fs::read_to_string(&item.fs_file_path).await {
+                item.range = item
+                    .range
+                    .decoded_from_file(&file_content, PositionEncoding::Utf16CodeUnit);
+                item.from_ranges = item
+                    .from_ranges
+                    .iter()
+                    .map(|range| {
+                        range.decoded_from_file(&file_content, PositionEncoding::Utf16CodeUnit)
+                    })
+                    .collect();
+            }
+        }
+        Ok(ToolOutput::call_hierarchy(response))
+    }
+
+    fn tool_description(&self) -> String {
+        "Finds the incoming or outgoing calls of a symbol through the language server".to_owned()
+    }
+
+    fn tool_input_format(&self) -> String {
+        "".to_owned()
+    }
+
+    fn get_evaluation_criteria(&self, _trajectory_length: usize) -> Vec<String> {
+        vec![]
+    }
+
+    fn get_reward_scale(&self, _trajectory_length: usize) -> Vec<ToolRewardScale> {
+        vec![]
+    }
+}
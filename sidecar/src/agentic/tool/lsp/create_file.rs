@@ -23,6 +23,10 @@ impl CreateFileRequest {
             editor_url,
         }
     }
+
+    pub fn fs_file_path(&self) -> &str {
+        &self.fs_file_path
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
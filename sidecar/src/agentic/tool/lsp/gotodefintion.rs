@@ -5,7 +5,7 @@ use crate::{
         output::ToolOutput,
         r#type::{Tool, ToolRewardScale},
     },
-    chunking::text_document::{Position, Range},
+    chunking::text_document::{Position, PositionEncoding, Range},
 };
 use async_trait::async_trait;
 use logging::new_client;
@@ -77,7 +77,15 @@ impl LSPGoToDefinition {
 #[async_trait]
 impl Tool for LSPGoToDefinition {
     async fn invoke(&self, input: ToolInput) -> Result<ToolOutput, ToolError> {
-        let context = input.is_go_to_definition()?;
+        let mut context = input.is_go_to_definition()?;
+        // the editor talks utf-16 columns while we track code points, so we
+        // re-encode the position on the way out and decode the ranges which
+        // come back, skipping the conversion if the file is not readable
+        if let Ok(file_content) = tokio::fs::read_to_string(&context.fs_file_path).await {
+            context.position = context
+                .position
+                .encoded_for_file(&file_content, PositionEncoding::Utf16CodeUnit);
+        }
         let editor_endpoint = context.editor_url.to_owned() + "/go_to_definition";
         let response = self
             .client
@@ -86,10 +94,17 @@ impl Tool for LSPGoToDefinition {
             .send()
             .await
             .map_err(|_e| ToolError::ErrorCommunicatingWithEditor)?;
-        let response: GoToDefinitionResponse = response
+        let mut response: GoToDefinitionResponse = response
             .json()
             .await
             .map_err(|_e| ToolError::SerdeConversionFailed)?;
+        for definition in response.definitions.iter_mut() {
+            if let Ok(file_content) = tokio::fs::read_to_string(&definition.fs_file_path).await {
+                definition.range = definition
+                    .range
+                    .decoded_from_file(&file_content, PositionEncoding::Utf16CodeUnit);
+            }
+        }
 
         Ok(ToolOutput::GoToDefinition(response))
     }
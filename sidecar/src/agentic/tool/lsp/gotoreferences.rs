@@ -13,7 +13,7 @@ use crate::{
         },
     },
     chunking::{
-        text_document::{Position, Range},
+        text_document::{Position, PositionEncoding, Range},
         types::OutlineNode,
     },
 };
@@ -71,6 +71,13 @@ pub struct ReferenceLocation {
 }
 
 impl ReferenceLocation {
+    pub fn new(fs_file_path: String, range: Range) -> Self {
+        Self {
+            fs_file_path,
+            range,
+        }
+    }
+
     pub fn fs_file_path(&self) -> &str {
         &self.fs_file_path
     }
@@ -86,10 +93,20 @@ pub struct GoToReferencesResponse {
 }
 
 impl GoToReferencesResponse {
+    pub fn new(reference_locations: Vec<ReferenceLocation>) -> Self {
+        Self {
+            reference_locations,
+        }
+    }
+
     pub fn locations(self) -> Vec<ReferenceLocation> {
         self.reference_locations
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.reference_locations.is_empty()
+    }
+
     /// filters out the locations which are pointing to the same location where we
     /// are checking for the references
     pub fn filter_out_same_position_location(
@@ -164,7 +181,15 @@ impl LSPGoToReferences {
 #[async_trait]
 impl Tool for LSPGoToReferences {
     async fn invoke(&self, input: ToolInput) -> Result<ToolOutput, ToolError> {
-        let context = input.reference_request()?;
+        let mut context = input.reference_request()?;
+        // the editor talks utf-16 columns while we track code points, so we
+        // re-encode the position on the way out and decode the ranges which
+        // come back, skipping the conversion if the file is not readable
+        if let Ok(file_content) = tokio::fs::read_to_string(&context.fs_file_path).await {
+            context.position = context
+                .position
+                .encoded_for_file(&file_content, PositionEncoding::Utf16CodeUnit);
+        }
         let editor_endpoint = context.editor_url.to_owned() + "/go_to_references";
         let response = self
             .client
@@ -173,10 +198,19 @@ impl Tool for LSPGoToReferences {
             .send()
             .await
             .map_err(|_e| ToolError::ErrorCommunicatingWithEditor)?;
-        let response: GoToReferencesResponse = response
+        let mut response: GoToReferencesResponse = response
             .json()
             .await
             .map_err(|_e| ToolError::SerdeConversionFailed)?;
+        for reference_location in response.reference_locations.iter_mut() {
+            if let Ok(file_content) =
+                tokio::fs::read_to_string(&reference_location.fs_file_path).await
+            {
+                reference_location.range = reference_location
+                    .range
+                    .decoded_from_file(&file_content, PositionEncoding::Utf16CodeUnit);
+            }
+        }
         Ok(ToolOutput::go_to_reference(response))
     }
 
@@ -4,6 +4,7 @@
 use std::{
     collections::{HashSet, VecDeque},
     path::{Path, PathBuf},
+    sync::Arc,
 };
 
 use async_trait::async_trait;
@@ -14,6 +15,7 @@ use crate::agentic::tool::{
     input::ToolInput,
     output::ToolOutput,
     r#type::{Tool, ToolRewardScale},
+    workspace::transport::WorkspaceTransport,
 };
 
 /// Handwaving this number into existence, no promises offered here and this is just
@@ -274,12 +276,16 @@ impl ListFilesOutput {
 
 pub struct ListFilesClient {
     client: reqwest::Client,
+    /// set in remote-workspace mode, listings come from the remote machine
+    /// instead of the local filesystem
+    workspace_transport: Option<Arc<dyn WorkspaceTransport + Send + Sync>>,
 }
 
 impl ListFilesClient {
-    pub fn new() -> Self {
+    pub fn new(workspace_transport: Option<Arc<dyn WorkspaceTransport + Send + Sync>>) -> Self {
         Self {
             client: reqwest::Client::new(),
+            workspace_transport,
         }
     }
 
@@ -315,6 +321,12 @@ impl Tool for ListFilesClient {
         let context = input.is_list_files()?;
         let directory = context.directory_path.to_owned();
         let is_recursive = context.recursive;
+        if let Some(workspace_transport) = self.workspace_transport.as_ref() {
+            let files = workspace_transport
+                .list_directory(&directory, is_recursive)
+                .await?;
+            return Ok(ToolOutput::ListFiles(ListFilesOutput { files }));
+        }
         let output = list_files(Path::new(&directory), is_recursive, FILES_LIMIT);
         if output.0.is_empty() {
             let files_from_editor = self.list_files_from_editor(context).await;
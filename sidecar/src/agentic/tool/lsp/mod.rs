@@ -1,5 +1,6 @@
 //! We want to talk to the LSP and get useful information out of this
 //! This way we can talk to the LSP running in the editor from the sidecar
+pub mod call_hierarchy;
 pub mod create_file;
 pub mod diagnostics;
 pub mod file_diagnostics;
@@ -15,6 +16,8 @@ pub mod inlay_hints;
 pub mod list_files;
 pub mod open_file;
 pub mod quick_fix;
+pub mod rename;
 pub mod search_file;
 pub(crate) mod subprocess_spawned_output;
 pub(crate) mod undo_changes;
+pub mod workspace_symbol;
@@ -4,12 +4,14 @@ use crate::{
         input::ToolInput,
         output::ToolOutput,
         r#type::{Tool, ToolRewardScale},
+        workspace::transport::WorkspaceTransport,
     },
     chunking::text_document::{Position, Range},
 };
 use async_trait::async_trait;
 use gix::bstr::ByteSlice;
 use logging::new_client;
+use std::sync::Arc;
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct OpenFileRequestPartial {
@@ -205,6 +207,17 @@ impl OpenFileResponse {
         self.file_contents
     }
 
+    /// Masks the values when the file is a configuration or secret file,
+    /// the keys and structure stay so downstream prompts and traces can
+    /// still reason about the file without carrying the secrets
+    pub fn with_masked_secret_values(mut self) -> Self {
+        if crate::agentic::tool::sensitive_files::is_sensitive_file(&self.fs_file_path) {
+            self.file_contents =
+                crate::agentic::tool::sensitive_files::mask_config_values(&self.file_contents);
+        }
+        self
+    }
+
     pub fn contents_ref(&self) -> &str {
         &self.file_contents
     }
@@ -282,12 +295,16 @@ impl OpenFileResponse {
 
 pub struct LSPOpenFile {
     client: reqwest_middleware::ClientWithMiddleware,
+    /// set in remote-workspace mode, file contents come from the remote
+    /// machine instead of the editor
+    workspace_transport: Option<Arc<dyn WorkspaceTransport + Send + Sync>>,
 }
 
 impl LSPOpenFile {
-    pub fn new() -> Self {
+    pub fn new(workspace_transport: Option<Arc<dyn WorkspaceTransport + Send + Sync>>) -> Self {
         Self {
             client: new_client(),
+            workspace_transport,
         }
     }
 }
@@ -297,6 +314,37 @@ impl Tool for LSPOpenFile {
     async fn invoke(&self, input: ToolInput) -> Result<ToolOutput, ToolError> {
         let context = input.is_file_open()?;
 
+        if let Some(workspace_transport) = self.workspace_transport.as_ref() {
+            let fs_file_path = context.fs_file_path.to_owned();
+            // the editor's language id is not around in remote mode so the
+            // file extension stands in for it in the code fences
+            let language = std::path::Path::new(&fs_file_path)
+                .extension()
+                .map(|extension| extension.to_string_lossy().to_string())
+                .unwrap_or_default();
+            return match workspace_transport.read_file(&fs_file_path).await {
+                Ok(file_contents) => Ok(ToolOutput::FileOpen(
+                    OpenFileResponse::new(
+                        fs_file_path,
+                        file_contents,
+                        true,
+                        language,
+                        context.start_line,
+                        context.end_line,
+                    )
+                    .with_masked_secret_values(),
+                )),
+                Err(_) => Ok(ToolOutput::FileOpen(OpenFileResponse::new(
+                    fs_file_path,
+                    "".to_owned(),
+                    false,
+                    language,
+                    context.start_line,
+                    context.end_line,
+                ))),
+            };
+        }
+
         // now we send it over to the editor
         let editor_endpoint = context.editor_url.to_owned() + "/file_open";
 
@@ -313,7 +361,7 @@ impl Tool for LSPOpenFile {
             .await
             .map_err(|_e| ToolError::ErrorCommunicatingWithEditor)?;
 
-        Ok(ToolOutput::FileOpen(response))
+        Ok(ToolOutput::FileOpen(response.with_masked_secret_values()))
     }
 
     fn tool_description(&self) -> String {
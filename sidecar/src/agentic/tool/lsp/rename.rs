@@ -0,0 +1,115 @@
+//! Symbol rename through the editor's rename endpoint
+//!
+//! A pure rename touches every reference of the symbol, which the language
+//! server does atomically and correctly. Driving an LLM edit per reference
+//! for that is slower and riskier, so the followup system prefers this tool
+//! whenever the change is only a new name
+
+use async_trait::async_trait;
+
+use crate::{
+    agentic::tool::{
+        errors::ToolError,
+        input::ToolInput,
+        output::ToolOutput,
+        r#type::{Tool, ToolRewardScale},
+    },
+    chunking::text_document::{Position, PositionEncoding},
+};
+use logging::new_client;
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct LSPRenameRequest {
+    fs_file_path: String,
+    /// position of the symbol being renamed
+    position: Position,
+    new_name: String,
+    editor_url: String,
+}
+
+impl LSPRenameRequest {
+    pub fn new(
+        fs_file_path: String,
+        position: Position,
+        new_name: String,
+        editor_url: String,
+    ) -> Self {
+        Self {
+            fs_file_path,
+            position,
+            new_name,
+            editor_url,
+        }
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct LSPRenameResponse {
+    success: bool,
+    /// every file the workspace edit from the rename touched
+    changed_files: Vec<String>,
+}
+
+impl LSPRenameResponse {
+    pub fn success(&self) -> bool {
+        self.success
+    }
+
+    pub fn changed_files(&self) -> &[String] {
+        &self.changed_files
+    }
+}
+
+pub struct LSPRename {
+    client: reqwest_middleware::ClientWithMiddleware,
+}
+
+impl LSPRename {
+    pub fn new() -> Self {
+        Self {
+            client: new_client(),
+        }
+    }
+}
+
+#[async_trait]
+impl Tool for LSPRename {
+    async fn invoke(&self, input: ToolInput) -> Result<ToolOutput, ToolError> {
+        let mut context = input.is_rename()?;
+        // the editor talks utf-16 columns while we track code points
+        if let Ok(file_content) = tokio::fs::read_to_string(&context.fs_file_path).await {
+            context.position = context
+                .position
+                .encoded_for_file(&file_content, PositionEncoding::Utf16CodeUnit);
+        }
+        let editor_endpoint = context.editor_url.to_owned() + "/rename_symbol";
+        let response = self
+            .client
+            .post(editor_endpoint)
+            .body(serde_json::to_string(&context).map_err(|_e| ToolError::SerdeConversionFailed)?)
+            .send()
+            .await
+            .map_err(|_e| ToolError::ErrorCommunicatingWithEditor)?;
+        let response: LSPRenameResponse = response
+            .json()
+            .await
+            .map_err(|_e| ToolError::SerdeConversionFailed)?;
+        Ok(ToolOutput::rename(response))
+    }
+
+    fn tool_description(&self) -> String {
+        "Renames a symbol across the workspace through the language server".to_owned()
+    }
+
+    fn tool_input_format(&self) -> String {
+        "".to_owned()
+    }
+
+    fn get_evaluation_criteria(&self, _trajectory_length: usize) -> Vec<String> {
+        vec![]
+    }
+
+    fn get_reward_scale(&self, _trajectory_length: usize) -> Vec<ToolRewardScale> {
+        vec![]
+    }
+}
@@ -0,0 +1,145 @@
+//! Workspace-wide symbol search through the editor's workspace/symbol
+//! provider. Unlike `GrepSymbolInCodebase` which is a text search, the
+//! results here are typed: we get the symbol kind, its container and the
+//! precise range straight from the language server
+
+use async_trait::async_trait;
+
+use crate::{
+    agentic::tool::{
+        errors::ToolError,
+        input::ToolInput,
+        output::ToolOutput,
+        r#type::{Tool, ToolRewardScale},
+    },
+    chunking::text_document::Range,
+};
+use logging::new_client;
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct WorkspaceSymbolRequest {
+    query: String,
+    editor_url: String,
+}
+
+impl WorkspaceSymbolRequest {
+    pub fn new(query: String, editor_url: String) -> Self {
+        Self { query, editor_url }
+    }
+}
+
+/// the symbol kinds the language server reports, mirroring the LSP
+/// SymbolKind values we care about
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WorkspaceSymbolKind {
+    File,
+    Module,
+    Namespace,
+    Package,
+    Class,
+    Method,
+    Property,
+    Field,
+    Constructor,
+    Enum,
+    Interface,
+    Function,
+    Variable,
+    Constant,
+    Struct,
+    TypeParameter,
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct WorkspaceSymbolItem {
+    name: String,
+    kind: WorkspaceSymbolKind,
+    /// the enclosing symbol, the class of a method or the module of a type
+    container_name: Option<String>,
+    fs_file_path: String,
+    range: Range,
+}
+
+impl WorkspaceSymbolItem {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn kind(&self) -> WorkspaceSymbolKind {
+        self.kind
+    }
+
+    pub fn container_name(&self) -> Option<&str> {
+        self.container_name.as_deref()
+    }
+
+    pub fn fs_file_path(&self) -> &str {
+        &self.fs_file_path
+    }
+
+    pub fn range(&self) -> &Range {
+        &self.range
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct WorkspaceSymbolResponse {
+    symbols: Vec<WorkspaceSymbolItem>,
+}
+
+impl WorkspaceSymbolResponse {
+    pub fn symbols(&self) -> &[WorkspaceSymbolItem] {
+        self.symbols.as_slice()
+    }
+}
+
+pub struct WorkspaceSymbolClient {
+    client: reqwest_middleware::ClientWithMiddleware,
+}
+
+impl WorkspaceSymbolClient {
+    pub fn new() -> Self {
+        Self {
+            client: new_client(),
+        }
+    }
+}
+
+#[async_trait]
+impl Tool for WorkspaceSymbolClient {
+    async fn invoke(&self, input: ToolInput) -> Result<ToolOutput, ToolError> {
+        let context = input.is_workspace_symbol()?;
+        let editor_endpoint = context.editor_url.to_owned() + "/workspace_symbol";
+        let response = self
+            .client
+            .post(editor_endpoint)
+            .body(serde_json::to_string(&context).map_err(|_e| ToolError::SerdeConversionFailed)?)
+            .send()
+            .await
+            .map_err(|_e| ToolError::ErrorCommunicatingWithEditor)?;
+        let response: WorkspaceSymbolResponse = response
+            .json()
+            .await
+            .map_err(|_e| ToolError::SerdeConversionFailed)?;
+        Ok(ToolOutput::workspace_symbol(response))
+    }
+
+    fn tool_description(&self) -> String {
+        "Searches the whole workspace for symbols through the language server".to_owned()
+    }
+
+    fn tool_input_format(&self) -> String {
+        "".to_owned()
+    }
+
+    fn get_evaluation_criteria(&self, _trajectory_length: usize) -> Vec<String> {
+        vec![]
+    }
+
+    fn get_reward_scale(&self, _trajectory_length: usize) -> Vec<ToolRewardScale> {
+        vec![]
+    }
+}
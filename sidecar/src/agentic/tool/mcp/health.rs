@@ -0,0 +1,196 @@
+//! Health checking and automatic reconnection for MCP servers
+//!
+//! A server process crashing mid-session used to leave its tools failing
+//! silently until a manual reload. The broker pings every server
+//! periodically, respawns crashed ones with exponential backoff and
+//! raises a status event on every transition so the editor can tell the
+//! user a server went down or came back
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use mcp_client_rs::client::Client;
+
+/// How long a ping may take before the server counts as down
+const PING_TIMEOUT: Duration = Duration::from_secs(5);
+/// The backoff for respawn attempts starts here and doubles per failure
+const RESPAWN_BACKOFF_BASE: Duration = Duration::from_secs(2);
+/// The backoff never grows past this
+const RESPAWN_BACKOFF_CAP: Duration = Duration::from_secs(120);
+
+/// Whether a server answered its last health check
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum McpServerStatus {
+    Up,
+    Down,
+}
+
+/// A status transition the editor gets notified about
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct McpServerStatusEvent {
+    pub server_name: String,
+    pub status: McpServerStatus,
+    /// how many respawn attempts failed so far, 0 once the server is back
+    pub failed_attempts: u32,
+}
+
+/// Pings a server over the protocol level ping. A protocol error still
+/// counts as alive since the server answered, only transport failures and
+/// timeouts count as down
+pub async fn ping_server(client: &Client) -> bool {
+    match tokio::time::timeout(PING_TIMEOUT, client.request("ping", None)).await {
+        Ok(Ok(_)) => true,
+        Ok(Err(mcp_client_rs::Error::Protocol { .. })) => true,
+        Ok(Err(_)) => false,
+        Err(_) => false,
+    }
+}
+
+/// The respawn delay after this many failed attempts, doubling from the
+/// base up to the cap
+pub(crate) fn respawn_backoff(failed_attempts: u32) -> Duration {
+    let multiplier = 1u32.checked_shl(failed_attempts.min(16)).unwrap_or(u32::MAX);
+    RESPAWN_BACKOFF_BASE
+        .checked_mul(multiplier)
+        .map(|delay| delay.min(RESPAWN_BACKOFF_CAP))
+        .unwrap_or(RESPAWN_BACKOFF_CAP)
+}
+
+/// What the tracker remembers about a server which stopped answering
+struct DownState {
+    failed_attempts: u32,
+    next_retry_at: Instant,
+}
+
+/// Tracks the last observed status per server and turns observations into
+/// transition events, repeated observations of the same status stay quiet
+pub struct McpHealthTracker {
+    statuses: Mutex<HashMap<String, McpServerStatus>>,
+    down: Mutex<HashMap<String, DownState>>,
+}
+
+impl McpHealthTracker {
+    pub fn new() -> Self {
+        Self {
+            statuses: Mutex::new(HashMap::new()),
+            down: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Records one health observation, the returned event is only present
+    /// on a transition
+    pub fn observe(&self, server_name: &str, healthy: bool) -> Option<McpServerStatusEvent> {
+        let status = if healthy {
+            McpServerStatus::Up
+        } else {
+            McpServerStatus::Down
+        };
+        let previous = self
+            .statuses
+            .lock()
+            .expect("statuses lock to not be poisoned")
+            .insert(server_name.to_owned(), status);
+        let mut down = self.down.lock().expect("down lock to not be poisoned");
+        if healthy {
+            down.remove(server_name);
+        } else {
+            down.entry(server_name.to_owned()).or_insert(DownState {
+                failed_attempts: 0,
+                next_retry_at: Instant::now(),
+            });
+        }
+        if previous == Some(status) {
+            return None;
+        }
+        let failed_attempts = down
+            .get(server_name)
+            .map(|state| state.failed_attempts)
+            .unwrap_or(0);
+        Some(McpServerStatusEvent {
+            server_name: server_name.to_owned(),
+            status,
+            failed_attempts,
+        })
+    }
+
+    /// Whether a down server is due for a respawn attempt, the backoff
+    /// window has to have passed
+    pub fn respawn_due(&self, server_name: &str) -> bool {
+        self.down
+            .lock()
+            .expect("down lock to not be poisoned")
+            .get(server_name)
+            .map(|state| Instant::now() >= state.next_retry_at)
+            .unwrap_or(false)
+    }
+
+    /// Records a failed respawn attempt and pushes the next retry out by
+    /// the doubled backoff
+    pub fn respawn_failed(&self, server_name: &str) {
+        let mut down = self.down.lock().expect("down lock to not be poisoned");
+        if let Some(state) = down.get_mut(server_name) {
+            state.failed_attempts += 1;
+            state.next_retry_at = Instant::now() + respawn_backoff(state.failed_attempts);
+        }
+    }
+
+    /// The last observed status of every server
+    pub fn statuses(&self) -> HashMap<String, McpServerStatus> {
+        self.statuses
+            .lock()
+            .expect("statuses lock to not be poisoned")
+            .clone()
+    }
+
+    /// Drops the tracking for servers which left the registry so a removed
+    /// server does not keep getting respawned
+    pub fn retain_servers(&self, server_names: &[String]) {
+        self.statuses
+            .lock()
+            .expect("statuses lock to not be poisoned")
+            .retain(|server_name, _| server_names.contains(server_name));
+        self.down
+            .lock()
+            .expect("down lock to not be poisoned")
+            .retain(|server_name, _| server_names.contains(server_name));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{respawn_backoff, McpHealthTracker, McpServerStatus, RESPAWN_BACKOFF_CAP};
+
+    #[test]
+    fn test_only_transitions_produce_events() {
+        let tracker = McpHealthTracker::new();
+        let first = tracker.observe("notes", true).expect("first observation");
+        assert_eq!(first.status, McpServerStatus::Up);
+        assert!(tracker.observe("notes", true).is_none());
+        let down = tracker.observe("notes", false).expect("transition to down");
+        assert_eq!(down.status, McpServerStatus::Down);
+        assert!(tracker.observe("notes", false).is_none());
+        let up = tracker.observe("notes", true).expect("transition back up");
+        assert_eq!(up.status, McpServerStatus::Up);
+    }
+
+    #[test]
+    fn test_backoff_doubles_and_caps() {
+        assert!(respawn_backoff(1) > respawn_backoff(0));
+        assert_eq!(respawn_backoff(2), respawn_backoff(1) * 2);
+        assert_eq!(respawn_backoff(30), RESPAWN_BACKOFF_CAP);
+    }
+
+    #[test]
+    fn test_failed_respawns_push_the_retry_out() {
+        let tracker = McpHealthTracker::new();
+        tracker.observe("notes", false);
+        assert!(tracker.respawn_due("notes"));
+        tracker.respawn_failed("notes");
+        assert!(!tracker.respawn_due("notes"));
+        // coming back up clears the down state entirely
+        tracker.observe("notes", true);
+        assert!(!tracker.respawn_due("notes"));
+    }
+}
@@ -0,0 +1,147 @@
+//! Streamable-HTTP transport for remote MCP servers
+//!
+//! The stdio transport covers locally spawned servers, hosted MCP services
+//! speak JSON-RPC over HTTP instead: every request goes out as a POST and
+//! the reply comes back either as a plain JSON body or as an SSE stream of
+//! messages. Auth headers from the config ride along on every request and
+//! the session id the server hands out on initialize gets replayed so
+//! stateful servers keep the conversation together
+
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+use eventsource_stream::Eventsource;
+use futures::{Stream, StreamExt};
+use mcp_client_rs::transport::{Message, Transport};
+use mcp_client_rs::Error;
+
+/// the session header from the streamable-http spec, servers which care
+/// about state send it back on initialize and expect it on every request
+const SESSION_ID_HEADER: &str = "Mcp-Session-Id";
+
+pub struct HttpTransport {
+    url: String,
+    headers: HashMap<String, String>,
+    http_client: reqwest::Client,
+    incoming_sender: tokio::sync::mpsc::UnboundedSender<Result<Message, Error>>,
+    /// handed out once through receive(), the client wires it into its own
+    /// response channel right after construction
+    incoming_receiver: Mutex<Option<tokio::sync::mpsc::UnboundedReceiver<Result<Message, Error>>>>,
+    session_id: Mutex<Option<String>>,
+}
+
+impl HttpTransport {
+    pub fn new(url: String, headers: HashMap<String, String>) -> Self {
+        let (incoming_sender, incoming_receiver) = tokio::sync::mpsc::unbounded_channel();
+        Self {
+            url,
+            headers,
+            http_client: reqwest::Client::new(),
+            incoming_sender,
+            incoming_receiver: Mutex::new(Some(incoming_receiver)),
+            session_id: Mutex::new(None),
+        }
+    }
+
+    fn session_id(&self) -> Option<String> {
+        self.session_id
+            .lock()
+            .expect("session_id lock to not be poisoned")
+            .clone()
+    }
+
+    fn remember_session_id(&self, response: &reqwest::Response) {
+        if let Some(session_id) = response
+            .headers()
+            .get(SESSION_ID_HEADER)
+            .and_then(|value| value.to_str().ok())
+        {
+            *self
+                .session_id
+                .lock()
+                .expect("session_id lock to not be poisoned") = Some(session_id.to_owned());
+        }
+    }
+
+    fn forward(&self, message: Message) {
+        let _ = self.incoming_sender.send(Ok(message));
+    }
+}
+
+#[async_trait]
+impl Transport for HttpTransport {
+    async fn send(&self, message: Message) -> Result<(), Error> {
+        let mut request = self
+            .http_client
+            .post(&self.url)
+            .header("Accept", "application/json, text/event-stream")
+            .json(&message);
+        for (header_name, header_value) in self.headers.iter() {
+            request = request.header(header_name, header_value);
+        }
+        if let Some(session_id) = self.session_id() {
+            request = request.header(SESSION_ID_HEADER, session_id);
+        }
+        let response = request
+            .send()
+            .await
+            .map_err(|e| Error::Transport(e.to_string()))?;
+        if !response.status().is_success() {
+            return Err(Error::Transport(format!(
+                "mcp server at {} answered {}",
+                self.url,
+                response.status()
+            )));
+        }
+        self.remember_session_id(&response);
+        let content_type = response
+            .headers()
+            .get("content-type")
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or("")
+            .to_owned();
+        if content_type.starts_with("text/event-stream") {
+            // the reply is a stream of SSE events each carrying one message
+            let mut events = response.bytes_stream().eventsource();
+            while let Some(event) = events.next().await {
+                let event = event.map_err(|e| Error::Transport(e.to_string()))?;
+                if event.data.is_empty() {
+                    continue;
+                }
+                let message: Message = serde_json::from_str(&event.data)?;
+                self.forward(message);
+            }
+        } else if content_type.starts_with("application/json") {
+            let message: Message = response
+                .json()
+                .await
+                .map_err(|e| Error::Transport(e.to_string()))?;
+            self.forward(message);
+        }
+        // notifications commonly come back as 202 with an empty body,
+        // nothing to forward for those
+        Ok(())
+    }
+
+    fn receive(&self) -> Pin<Box<dyn Stream<Item = Result<Message, Error>> + Send>> {
+        match self
+            .incoming_receiver
+            .lock()
+            .expect("incoming_receiver lock to not be poisoned")
+            .take()
+        {
+            Some(receiver) => {
+                Box::pin(tokio_stream::wrappers::UnboundedReceiverStream::new(
+                    receiver,
+                ))
+            }
+            None => Box::pin(futures::stream::empty()),
+        }
+    }
+
+    async fn close(&self) -> Result<(), Error> {
+        Ok(())
+    }
+}
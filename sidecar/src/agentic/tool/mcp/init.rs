@@ -1,18 +1,31 @@
+use super::http_transport::HttpTransport;
 use super::integration_tool::McpTool;
+use crate::agentic::tool::r#type::ToolType;
 use mcp_client_rs::client::Client;
 use mcp_client_rs::client::ClientBuilder;
+use mcp_client_rs::{ClientCapabilities, Implementation};
 use serde::Deserialize;
 use std::{collections::HashMap, sync::Arc};
 use thiserror::Error;
 
 // Minimal code for MCP client spawner
-#[derive(Deserialize)]
-struct ServerConfig {
-    command: String,
+#[derive(Deserialize, Clone, PartialEq, Eq)]
+pub struct ServerConfig {
+    /// command to spawn for a local server speaking stdio
+    #[serde(default)]
+    command: Option<String>,
     #[serde(default)]
     args: Vec<String>,
     #[serde(default)]
     env: HashMap<String, String>,
+    /// url of a hosted server speaking streamable-HTTP/SSE, mutually
+    /// exclusive with command
+    #[serde(default)]
+    url: Option<String>,
+    /// headers sent on every request to a hosted server, this is where
+    /// auth tokens go
+    #[serde(default)]
+    headers: HashMap<String, String>,
 }
 
 #[derive(Deserialize)]
@@ -37,12 +50,26 @@ pub enum McpError {
         server: String,
         source: mcp_client_rs::Error,
     },
+
+    #[error("Failed reading resource from server '{server}': {source}")]
+    ResourceError {
+        server: String,
+        source: mcp_client_rs::Error,
+    },
+
+    #[error("Failed expanding prompt from server '{server}': {source}")]
+    PromptError {
+        server: String,
+        source: mcp_client_rs::Error,
+    },
+
+    #[error("No MCP server named '{0}' is running")]
+    UnknownServer(String),
 }
 
-/// Set up MCP clients by reading ~/.aide/config.json, spawning each server,
-/// and returning a HashMap<server_name -> Arc<Client>>.
-/// spawn a single MCP process per server, share references.
-async fn setup_mcp_clients() -> Result<HashMap<String, Arc<Client>>, McpError> {
+/// Reads ~/.aide/config.json and returns the configured servers, an absent
+/// config file just means no servers
+pub async fn load_mcp_server_configs() -> Result<HashMap<String, ServerConfig>, McpError> {
     let config_path = dirs::home_dir()
         .ok_or(McpError::NoHomeDir)?
         .join(".aide/config.json");
@@ -53,71 +80,387 @@ async fn setup_mcp_clients() -> Result<HashMap<String, Arc<Client>>, McpError> {
 
     let config_str = tokio::fs::read_to_string(&config_path).await?;
     let root_config: RootConfig = serde_json::from_str(&config_str)?;
+    Ok(root_config.mcp_servers)
+}
 
-    let mut mcp_clients_map = HashMap::new();
+/// Connects a single MCP server and initializes the client, spawning the
+/// process for stdio configs and speaking streamable-HTTP for url configs.
+/// Failures get logged and come back as None so one broken server never
+/// takes the rest down
+async fn spawn_mcp_server(server_name: &str, server_conf: &ServerConfig) -> Option<Arc<Client>> {
+    let connected = match (&server_conf.command, &server_conf.url) {
+        (Some(command), _) => {
+            let mut builder = ClientBuilder::new(command);
+            for arg in &server_conf.args {
+                builder = builder.arg(arg);
+            }
+            for (k, v) in &server_conf.env {
+                builder = builder.env(k, v);
+            }
+            builder.spawn_and_initialize().await
+        }
+        (None, Some(url)) => {
+            let transport =
+                HttpTransport::new(url.to_owned(), server_conf.headers.clone());
+            let client = Client::new(Arc::new(transport));
+            client
+                .initialize(
+                    Implementation {
+                        name: "sidecar".to_owned(),
+                        version: env!("CARGO_PKG_VERSION").to_owned(),
+                    },
+                    ClientCapabilities::default(),
+                )
+                .await
+                .map(|_| client)
+        }
+        (None, None) => {
+            eprintln!(
+                "MCP server '{}' has neither a command nor a url, skipping it",
+                server_name
+            );
+            return None;
+        }
+    };
 
-    // For each server in the config, spawn an MCP client
-    for (server_name, server_conf) in &root_config.mcp_servers {
-        let mut builder = ClientBuilder::new(&server_conf.command);
-        for arg in &server_conf.args {
-            builder = builder.arg(arg);
+    match connected {
+        Ok(client) => {
+            eprintln!("Initialized MCP client for '{}'", server_name);
+            Some(Arc::new(client))
         }
-        for (k, v) in &server_conf.env {
-            builder = builder.env(k, v);
+        Err(e) => {
+            eprintln!(
+                "Failed to initialize MCP client for '{}': {}",
+                server_name, e
+            );
+            None
         }
+    }
+}
 
-        match builder.spawn_and_initialize().await {
-            Ok(client) => {
-                let client_arc = Arc::new(client);
-                mcp_clients_map.insert(server_name.clone(), client_arc);
-                eprintln!("Initialized MCP client for '{}'", server_name);
-            }
-            Err(e) => {
-                eprintln!(
-                    "Failed to initialize MCP client for '{}': {}",
-                    server_name, e
-                );
-                // keep trying other clients
+/// Lists the tools one spawned server exposes and wraps each into a dynamic
+/// [`McpTool`] sharing the server's client
+async fn tools_for_server(
+    server_name: &str,
+    client: &Arc<Client>,
+) -> Result<Vec<McpTool>, McpError> {
+    let list_res = client
+        .list_tools()
+        .await
+        .map_err(|e| McpError::ToolListError {
+            server: server_name.to_owned(),
+            source: e,
+        })?;
+
+    Ok(list_res
+        .tools
+        .into_iter()
+        .map(|tool_info| {
+            McpTool::new(
+                server_name.to_owned(),
+                tool_info.name,
+                tool_info.description,
+                tool_info.input_schema,
+                Arc::clone(client),
+            )
+        })
+        .collect())
+}
+
+/// Respawns one server from the config it was registered with, used by the
+/// health monitor after a crash. Returns false when the spawn or the tool
+/// listing fails so the caller can back off and retry later
+pub(crate) async fn respawn_server(
+    registry: &std::sync::RwLock<McpRegistry>,
+    server_name: &str,
+) -> bool {
+    let config = registry
+        .read()
+        .expect("mcp registry lock to not be poisoned")
+        .server_configs()
+        .get(server_name)
+        .cloned();
+    let config = match config {
+        Some(config) => config,
+        None => return false,
+    };
+    let client = match spawn_mcp_server(server_name, &config).await {
+        Some(client) => client,
+        None => return false,
+    };
+    let tools = match tools_for_server(server_name, &client).await {
+        Ok(tools) => tools,
+        Err(e) => {
+            eprintln!("Failed listing tools while respawning '{}': {}", server_name, e);
+            return false;
+        }
+    };
+    let mut guard = registry
+        .write()
+        .expect("mcp registry lock to not be poisoned");
+    guard.remove_server(server_name);
+    guard.register_server(server_name.to_owned(), config, client, tools);
+    true
+}
+
+/// One running MCP server: the config it was spawned from (so a reload can
+/// tell whether it changed), the shared client and the tool types it
+/// registered
+struct McpServerHandle {
+    config: ServerConfig,
+    /// dropping the last Arc tears the child process down, the tools for
+    /// this server hold clones so removal has to drop both
+    client: Arc<Client>,
+    tool_types: Vec<ToolType>,
+}
+
+/// The running MCP servers and the dynamic tools they contributed, lives on
+/// the broker behind an RwLock so a config reload swaps servers in and out
+/// without restarting sidecar
+pub struct McpRegistry {
+    servers: HashMap<String, McpServerHandle>,
+    tools: HashMap<ToolType, Arc<McpTool>>,
+}
+
+impl McpRegistry {
+    pub fn new() -> Self {
+        Self {
+            servers: HashMap::new(),
+            tools: HashMap::new(),
+        }
+    }
+
+    pub fn tool(&self, tool_type: &ToolType) -> Option<Arc<McpTool>> {
+        self.tools.get(tool_type).cloned()
+    }
+
+    pub fn tool_types(&self) -> Vec<ToolType> {
+        self.tools.keys().cloned().collect()
+    }
+
+    pub fn tool_count(&self) -> usize {
+        self.tools.len()
+    }
+
+    pub fn server_count(&self) -> usize {
+        self.servers.len()
+    }
+
+    /// The clients of every running server, cloned out so callers never
+    /// hold the registry lock across resource or tool calls
+    pub(crate) fn clients(&self) -> Vec<(String, Arc<Client>)> {
+        self.servers
+            .iter()
+            .map(|(server_name, handle)| (server_name.clone(), handle.client.clone()))
+            .collect()
+    }
+
+    pub(crate) fn client_for(&self, server_name: &str) -> Option<Arc<Client>> {
+        self.servers
+            .get(server_name)
+            .map(|handle| handle.client.clone())
+    }
+
+    fn server_configs(&self) -> HashMap<String, ServerConfig> {
+        self.servers
+            .iter()
+            .map(|(server_name, handle)| (server_name.clone(), handle.config.clone()))
+            .collect()
+    }
+
+    fn remove_server(&mut self, server_name: &str) {
+        if let Some(handle) = self.servers.remove(server_name) {
+            for tool_type in handle.tool_types.iter() {
+                self.tools.remove(tool_type);
             }
         }
     }
 
-    Ok(mcp_clients_map)
+    fn register_server(
+        &mut self,
+        server_name: String,
+        config: ServerConfig,
+        client: Arc<Client>,
+        tools: Vec<McpTool>,
+    ) {
+        let mut tool_types = vec![];
+        for tool in tools.into_iter() {
+            let tool_type = ToolType::McpTool(tool.full_name.clone());
+            self.tools.insert(tool_type.clone(), Arc::new(tool));
+            tool_types.push(tool_type);
+        }
+        self.servers.insert(
+            server_name,
+            McpServerHandle {
+                config,
+                client,
+                tool_types,
+            },
+        );
+    }
 }
 
-/// discover each MCP server in ~/.aide/config.json
-/// create dynamic tools from each server
-/// used to augument broker initialization w/MCP tools
-pub async fn discover_mcp_tools() -> Result<Vec<McpTool>, McpError> {
-    let clients = setup_mcp_clients().await?;
-    if clients.is_empty() {
-        return Ok(Vec::new());
-    }
-
-    let mut tools: Vec<McpTool> = Vec::new();
-
-    for (server_name, client) in clients {
-        let list_res = client
-            .list_tools()
-            .await
-            .map_err(|e| McpError::ToolListError {
-                server: server_name.clone(),
-                source: e,
-            })?;
-
-        for tool_info in list_res.tools {
-            let name = tool_info.name;
-            let tool: McpTool = McpTool::new(
-                server_name.clone(),
-                name.clone(),
-                tool_info.description,
-                tool_info.input_schema,
-                Arc::clone(&client),
-            );
+/// How the configured servers differ from the running ones, reloads only
+/// touch the servers listed here and leave the unchanged ones running
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct ServerConfigDiff {
+    /// in the config but not running yet
+    pub added: Vec<String>,
+    /// running but gone from the config
+    pub removed: Vec<String>,
+    /// running with a different command, args or env than the config
+    pub changed: Vec<String>,
+}
+
+pub(crate) fn diff_server_configs(
+    running: &HashMap<String, ServerConfig>,
+    configured: &HashMap<String, ServerConfig>,
+) -> ServerConfigDiff {
+    let mut added = vec![];
+    let mut removed = vec![];
+    let mut changed = vec![];
+    for (server_name, config) in configured.iter() {
+        match running.get(server_name) {
+            None => added.push(server_name.clone()),
+            Some(running_config) if running_config != config => changed.push(server_name.clone()),
+            Some(_) => {}
+        }
+    }
+    for server_name in running.keys() {
+        if !configured.contains_key(server_name) {
+            removed.push(server_name.clone());
+        }
+    }
+    added.sort();
+    removed.sort();
+    changed.sort();
+    ServerConfigDiff {
+        added,
+        removed,
+        changed,
+    }
+}
 
-            tools.push(tool);
+/// What a reload did, reported back to the caller of the reload endpoint
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct McpReloadSummary {
+    /// servers spawned because they are new in the config
+    pub added: Vec<String>,
+    /// servers torn down because they left the config
+    pub removed: Vec<String>,
+    /// servers torn down and respawned because their config changed
+    pub restarted: Vec<String>,
+    pub active_servers: usize,
+    pub active_tools: usize,
+}
+
+/// Re-reads ~/.aide/config.json and reconciles the registry against it:
+/// new servers get spawned, removed ones torn down, changed ones restarted
+/// and unchanged ones keep running untouched. Spawning and tool listing
+/// happen outside the lock so in-flight invocations keep dispatching
+/// against the old set until the swap at the end
+pub async fn reload_registry(
+    registry: &std::sync::RwLock<McpRegistry>,
+) -> Result<McpReloadSummary, McpError> {
+    let configured = load_mcp_server_configs().await?;
+    let running = registry
+        .read()
+        .expect("mcp_registry lock to not be poisoned")
+        .server_configs();
+    let diff = diff_server_configs(&running, &configured);
+
+    // spawn everything new or changed before taking the write lock
+    let mut spawned = vec![];
+    for server_name in diff.added.iter().chain(diff.changed.iter()) {
+        let config = configured
+            .get(server_name)
+            .expect("diffed names to come from the configured map")
+            .clone();
+        let client = match spawn_mcp_server(server_name, &config).await {
+            Some(client) => client,
+            None => continue,
+        };
+        match tools_for_server(server_name, &client).await {
+            Ok(tools) => spawned.push((server_name.clone(), config, client, tools)),
+            Err(e) => eprintln!("Failed listing tools from server '{}': {}", server_name, e),
         }
     }
 
-    Ok(tools)
+    let mut registry = registry
+        .write()
+        .expect("mcp_registry lock to not be poisoned");
+    for server_name in diff.removed.iter().chain(diff.changed.iter()) {
+        registry.remove_server(server_name);
+    }
+    for (server_name, config, client, tools) in spawned.into_iter() {
+        registry.register_server(server_name, config, client, tools);
+    }
+    Ok(McpReloadSummary {
+        added: diff.added,
+        removed: diff.removed,
+        restarted: diff.changed,
+        active_servers: registry.server_count(),
+        active_tools: registry.tool_count(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{diff_server_configs, ServerConfig};
+    use std::collections::HashMap;
+
+    fn config(command: &str) -> ServerConfig {
+        serde_json::from_value(serde_json::json!({ "command": command }))
+            .expect("minimal server config to deserialize")
+    }
+
+    #[test]
+    fn test_diff_sees_added_removed_and_changed_servers() {
+        let running = HashMap::from([
+            ("stays".to_owned(), config("npx stays")),
+            ("goes".to_owned(), config("npx goes")),
+            ("moves".to_owned(), config("npx moves")),
+        ]);
+        let configured = HashMap::from([
+            ("stays".to_owned(), config("npx stays")),
+            ("moves".to_owned(), config("npx moves-elsewhere")),
+            ("arrives".to_owned(), config("npx arrives")),
+        ]);
+        let diff = diff_server_configs(&running, &configured);
+        assert_eq!(diff.added, vec!["arrives".to_owned()]);
+        assert_eq!(diff.removed, vec!["goes".to_owned()]);
+        assert_eq!(diff.changed, vec!["moves".to_owned()]);
+    }
+
+    #[test]
+    fn test_remote_server_configs_deserialize_and_diff_on_headers() {
+        let running = HashMap::from([(
+            "hosted".to_owned(),
+            serde_json::from_value::<ServerConfig>(serde_json::json!({
+                "url": "https://mcp.example.com/notes",
+                "headers": { "Authorization": "Bearer old" }
+            }))
+            .expect("remote server config to deserialize"),
+        )]);
+        let configured = HashMap::from([(
+            "hosted".to_owned(),
+            serde_json::from_value::<ServerConfig>(serde_json::json!({
+                "url": "https://mcp.example.com/notes",
+                "headers": { "Authorization": "Bearer new" }
+            }))
+            .expect("remote server config to deserialize"),
+        )]);
+        // a rotated auth token restarts the server connection
+        let diff = diff_server_configs(&running, &configured);
+        assert_eq!(diff.changed, vec!["hosted".to_owned()]);
+    }
+
+    #[test]
+    fn test_identical_configs_leave_everything_running() {
+        let running = HashMap::from([("stays".to_owned(), config("npx stays"))]);
+        let diff = diff_server_configs(&running, &running.clone());
+        assert!(diff.added.is_empty());
+        assert!(diff.removed.is_empty());
+        assert!(diff.changed.is_empty());
+    }
 }
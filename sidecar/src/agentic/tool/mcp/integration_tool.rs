@@ -78,6 +78,79 @@ impl McpTool {
     }
 }
 
+/// Coerces every argument against the tool's json schema. String values
+/// whose schema wants a number, boolean, array or object get parsed into
+/// the right type, nested objects recurse into their sub-schemas. Values
+/// which do not parse stay as they are so the server reports the mismatch
+/// instead of us guessing
+pub(crate) fn coerce_arguments_to_schema(
+    schema: &Value,
+    arguments: serde_json::Map<String, Value>,
+) -> serde_json::Map<String, Value> {
+    let properties = schema.get("properties").and_then(|value| value.as_object());
+    arguments
+        .into_iter()
+        .map(|(name, value)| {
+            let coerced = match properties.and_then(|properties| properties.get(&name)) {
+                Some(field_schema) => coerce_value_to_schema(field_schema, value),
+                None => value,
+            };
+            (name, coerced)
+        })
+        .collect()
+}
+
+fn coerce_value_to_schema(field_schema: &Value, value: Value) -> Value {
+    let expected_type = field_schema.get("type").and_then(|value| value.as_str());
+    match (expected_type, value) {
+        // already the right shape, recurse into objects so nested fields
+        // coerce against their sub-schemas
+        (Some("object"), Value::Object(object)) => {
+            Value::Object(coerce_arguments_to_schema(field_schema, object))
+        }
+        (Some("array"), Value::Array(array)) => {
+            let item_schema = field_schema.get("items");
+            Value::Array(
+                array
+                    .into_iter()
+                    .map(|item| match item_schema {
+                        Some(item_schema) => coerce_value_to_schema(item_schema, item),
+                        None => item,
+                    })
+                    .collect(),
+            )
+        }
+        // strings get parsed into whatever the schema asks for
+        (Some("integer"), Value::String(text)) => text
+            .trim()
+            .parse::<i64>()
+            .map(Value::from)
+            .unwrap_or(Value::String(text)),
+        (Some("number"), Value::String(text)) => text
+            .trim()
+            .parse::<f64>()
+            .ok()
+            .and_then(|number| serde_json::Number::from_f64(number).map(Value::Number))
+            .unwrap_or(Value::String(text)),
+        (Some("boolean"), Value::String(text)) => match text.trim() {
+            "true" => Value::Bool(true),
+            "false" => Value::Bool(false),
+            _ => Value::String(text),
+        },
+        (Some("object"), Value::String(text)) => match serde_json::from_str::<Value>(&text) {
+            Ok(Value::Object(object)) => {
+                Value::Object(coerce_arguments_to_schema(field_schema, object))
+            }
+            _ => Value::String(text),
+        },
+        (Some("array"), Value::String(text)) => match serde_json::from_str::<Value>(&text) {
+            Ok(array @ Value::Array(_)) => coerce_value_to_schema(field_schema, array),
+            _ => Value::String(text),
+        },
+        (_, value) => value,
+    }
+}
+
 #[async_trait]
 impl Tool for McpTool {
     async fn invoke(&self, input: ToolInput) -> Result<ToolOutput, ToolError> {
@@ -98,7 +171,10 @@ impl Tool for McpTool {
             )));
         }
 
-        let arguments = Value::Object(input.partial.json);
+        // llms (and the xml input format in particular) hand every field
+        // over as a string, coerce them against the schema so servers
+        // expecting numbers, booleans, arrays or nested objects still work
+        let arguments = Value::Object(coerce_arguments_to_schema(&self.schema, input.partial.json));
 
         // Perform the call
         let result = self
@@ -187,6 +263,79 @@ mod tests {
         });
     }
 
+    #[test]
+    fn test_string_fields_coerce_against_the_schema() {
+        let schema = serde_json::json!({
+            "type": "object",
+            "properties": {
+                "count": { "type": "integer" },
+                "threshold": { "type": "number" },
+                "dry_run": { "type": "boolean" },
+                "tags": { "type": "array", "items": { "type": "integer" } },
+                "name": { "type": "string" },
+            },
+        });
+        let arguments = serde_json::json!({
+            "count": "5",
+            "threshold": "0.75",
+            "dry_run": "true",
+            "tags": "[\"1\", \"2\"]",
+            "name": "plain string",
+        });
+        let coerced = coerce_arguments_to_schema(
+            &schema,
+            arguments.as_object().expect("to be an object").clone(),
+        );
+        assert_eq!(coerced["count"], serde_json::json!(5));
+        assert_eq!(coerced["threshold"], serde_json::json!(0.75));
+        assert_eq!(coerced["dry_run"], serde_json::json!(true));
+        assert_eq!(coerced["tags"], serde_json::json!([1, 2]));
+        assert_eq!(coerced["name"], serde_json::json!("plain string"));
+    }
+
+    #[test]
+    fn test_nested_objects_coerce_recursively() {
+        let schema = serde_json::json!({
+            "type": "object",
+            "properties": {
+                "filter": {
+                    "type": "object",
+                    "properties": {
+                        "limit": { "type": "integer" },
+                    },
+                },
+            },
+        });
+        let arguments = serde_json::json!({
+            "filter": "{\"limit\": \"10\"}",
+        });
+        let coerced = coerce_arguments_to_schema(
+            &schema,
+            arguments.as_object().expect("to be an object").clone(),
+        );
+        assert_eq!(coerced["filter"], serde_json::json!({ "limit": 10 }));
+    }
+
+    #[test]
+    fn test_unparseable_values_stay_untouched() {
+        let schema = serde_json::json!({
+            "type": "object",
+            "properties": {
+                "count": { "type": "integer" },
+            },
+        });
+        let arguments = serde_json::json!({
+            "count": "not a number",
+            "extra": "no schema entry",
+        });
+        let coerced = coerce_arguments_to_schema(
+            &schema,
+            arguments.as_object().expect("to be an object").clone(),
+        );
+        assert_eq!(coerced["count"], serde_json::json!("not a number"));
+        assert_eq!(coerced["extra"], serde_json::json!("no schema entry"));
+    }
+
     async fn setup_test_client() -> anyhow::Result<Arc<Client>> {
         let builder = ClientBuilder::new("uvx").arg("mcp-server-time");
 
@@ -1,3 +1,7 @@
+pub mod health;
+pub mod http_transport;
 pub mod init;
 pub mod input;
 pub mod integration_tool;
+pub mod prompts;
+pub mod resources;
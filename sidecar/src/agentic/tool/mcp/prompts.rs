@@ -0,0 +1,218 @@
+//! MCP prompt templates surfaced as slash commands
+//!
+//! Servers can expose reusable prompt templates besides tools and
+//! resources. We list them per server so the editor can render them next
+//! to the built-in slash commands, and when one gets invoked we expand it
+//! server side through `prompts/get` and feed the expansion into the
+//! session chat flow like a normal query
+
+use std::collections::HashMap;
+
+use mcp_client_rs::{
+    client::Client, Error, GetPromptResult, ListPromptsResult, MessageContent, Prompt,
+    PromptMessage,
+};
+
+/// One prompt template a server exposes, trimmed down to what the editor
+/// needs to render it as a slash command
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct McpPromptDescriptor {
+    pub server_name: String,
+    pub name: String,
+    pub description: String,
+    /// the slash command the editor inserts, `/mcp::server::prompt` shaped
+    pub slash_command: String,
+    pub arguments: Vec<McpPromptArgumentDescriptor>,
+}
+
+impl McpPromptDescriptor {
+    pub fn from_prompt(server_name: &str, prompt: &Prompt) -> Self {
+        Self {
+            server_name: server_name.to_owned(),
+            name: prompt.name.clone(),
+            description: prompt.description.clone(),
+            slash_command: prompt_command_name(server_name, &prompt.name),
+            arguments: prompt
+                .arguments
+                .iter()
+                .flatten()
+                .map(|argument| McpPromptArgumentDescriptor {
+                    name: argument.name.clone(),
+                    description: argument.description.clone(),
+                    default_value: argument.default_value.clone(),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// An argument a prompt template takes, invocations pass them as
+/// `name=value` pairs after the slash command
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct McpPromptArgumentDescriptor {
+    pub name: String,
+    pub description: String,
+    pub default_value: Option<String>,
+}
+
+/// The prompt templates one server exposes
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct McpServerPrompts {
+    pub server_name: String,
+    pub prompts: Vec<McpPromptDescriptor>,
+}
+
+/// The slash command a prompt template is invoked under, mirrors the
+/// naming the dynamic tools and resources use so mcp commands are
+/// recognisable next to the built-in ones
+pub fn prompt_command_name(server_name: &str, prompt_name: &str) -> String {
+    format!("/mcp::{}::{}", server_name, prompt_name)
+}
+
+/// An MCP prompt invocation parsed out of a chat query
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParsedPromptCommand {
+    pub server_name: String,
+    pub prompt_name: String,
+    /// the `name=value` pairs after the command, passed to `prompts/get`
+    pub arguments: HashMap<String, String>,
+    /// whatever came after the arguments, appended to the expansion
+    pub rest: String,
+}
+
+/// Parses an MCP prompt invocation from the start of a chat query. Leading
+/// `name=value` tokens become prompt arguments, the first token which is
+/// not one ends the argument list and the remainder rides along as extra
+/// instructions. Returns None when the query is not a `/mcp::` command, in
+/// which case it flows through the normal slash command parsing
+pub fn parse_prompt_command(query: &str) -> Option<ParsedPromptCommand> {
+    let trimmed = query.trim_start();
+    let mut parts = trimmed.splitn(2, char::is_whitespace);
+    let command_word = parts.next().expect("splitn to always yield once");
+    let command_body = command_word.strip_prefix("/mcp::")?;
+    let (server_name, prompt_name) = command_body.split_once("::")?;
+    if server_name.is_empty() || prompt_name.is_empty() {
+        return None;
+    }
+    let mut arguments = HashMap::new();
+    let mut remaining = parts.next().unwrap_or("").trim();
+    while let Some((token, rest)) = split_leading_token(remaining) {
+        match token.split_once('=') {
+            Some((name, value)) if !name.is_empty() => {
+                arguments.insert(name.to_owned(), value.to_owned());
+                remaining = rest;
+            }
+            _ => break,
+        }
+    }
+    Some(ParsedPromptCommand {
+        server_name: server_name.to_owned(),
+        prompt_name: prompt_name.to_owned(),
+        arguments,
+        rest: remaining.to_owned(),
+    })
+}
+
+/// Splits the first whitespace delimited token off the front, None when
+/// there is nothing left
+fn split_leading_token(input: &str) -> Option<(&str, &str)> {
+    let trimmed = input.trim_start();
+    if trimmed.is_empty() {
+        return None;
+    }
+    match trimmed.split_once(char::is_whitespace) {
+        Some((token, rest)) => Some((token, rest.trim_start())),
+        None => Some((trimmed, "")),
+    }
+}
+
+/// Flattens an expanded prompt into plain text for the chat query, text
+/// parts go in verbatim and anything else becomes a marker so the LLM
+/// knows something was there
+pub fn flatten_prompt_messages(messages: &[PromptMessage]) -> String {
+    messages
+        .iter()
+        .flat_map(|message| message.content.iter())
+        .map(|content| match content {
+            MessageContent::Text { text } => text.to_owned(),
+            MessageContent::Image { uri, .. } => format!("<image {}>", uri),
+            MessageContent::Resource { resource } => format!("<resource {}>", resource.uri),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Lists the prompt templates a server exposes, the client crate does not
+/// wrap `prompts/list` so we go through the raw request path
+pub(crate) async fn list_server_prompts(client: &Client) -> Result<ListPromptsResult, Error> {
+    let response = client.request("prompts/list", None).await?;
+    serde_json::from_value(response).map_err(Error::from)
+}
+
+/// Expands a prompt template with the given arguments through
+/// `prompts/get`
+pub(crate) async fn get_server_prompt(
+    client: &Client,
+    prompt_name: &str,
+    arguments: &HashMap<String, String>,
+) -> Result<GetPromptResult, Error> {
+    let params = serde_json::json!({
+        "name": prompt_name,
+        "arguments": arguments,
+    });
+    let response = client.request("prompts/get", Some(params)).await?;
+    serde_json::from_value(response).map_err(Error::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{flatten_prompt_messages, parse_prompt_command};
+    use mcp_client_rs::{MessageContent, PromptMessage};
+
+    #[test]
+    fn test_plain_query_is_not_a_prompt_command() {
+        assert!(parse_prompt_command("fix the bug in main.rs").is_none());
+        assert!(parse_prompt_command("/plan add retries").is_none());
+        assert!(parse_prompt_command("/mcp::only_server").is_none());
+    }
+
+    #[test]
+    fn test_parses_arguments_and_rest() {
+        let parsed = parse_prompt_command(
+            "/mcp::notes::summarize topic=auth depth=short and keep it actionable",
+        )
+        .expect("to parse");
+        assert_eq!(parsed.server_name, "notes");
+        assert_eq!(parsed.prompt_name, "summarize");
+        assert_eq!(parsed.arguments.get("topic").map(String::as_str), Some("auth"));
+        assert_eq!(parsed.arguments.get("depth").map(String::as_str), Some("short"));
+        assert_eq!(parsed.rest, "and keep it actionable");
+    }
+
+    #[test]
+    fn test_command_without_arguments() {
+        let parsed = parse_prompt_command("/mcp::notes::standup").expect("to parse");
+        assert!(parsed.arguments.is_empty());
+        assert_eq!(parsed.rest, "");
+    }
+
+    #[test]
+    fn test_flatten_keeps_text_and_marks_the_rest() {
+        let messages = vec![PromptMessage {
+            role: "user".to_owned(),
+            content: vec![
+                MessageContent::Text {
+                    text: "Review the auth changes".to_owned(),
+                },
+                MessageContent::Image {
+                    uri: "notes://diagram".to_owned(),
+                    alt_text: None,
+                },
+            ],
+        }];
+        assert_eq!(
+            flatten_prompt_messages(&messages),
+            "Review the auth changes\n<image notes://diagram>"
+        );
+    }
+}
@@ -0,0 +1,87 @@
+//! MCP resources surfaced as attachable context
+//!
+//! Servers expose resources (files, database rows, documents) besides
+//! tools. We list them per server so the editor can browse what is on
+//! offer and flatten the contents of a read into plain text which gets
+//! pinned into the session context, from there chat and plan generation
+//! pick it up like any other pinned item
+
+use mcp_client_rs::{Resource, ResourceContents};
+
+/// One resource a server exposes, trimmed down to what the editor needs
+/// to show a picker
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct McpResourceDescriptor {
+    pub server_name: String,
+    pub uri: String,
+    pub title: String,
+    pub description: Option<String>,
+}
+
+impl McpResourceDescriptor {
+    pub fn from_resource(server_name: &str, resource: &Resource) -> Self {
+        Self {
+            server_name: server_name.to_owned(),
+            uri: resource.uri.clone(),
+            title: resource.title.clone(),
+            description: resource.description.clone(),
+        }
+    }
+}
+
+/// The resources one server exposes
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct McpServerResources {
+    pub server_name: String,
+    pub resources: Vec<McpResourceDescriptor>,
+}
+
+/// The identity a pinned MCP resource carries in the context, mirrors the
+/// naming the dynamic tools use so mcp items are recognisable in prompts
+pub fn resource_context_path(server_name: &str, uri: &str) -> String {
+    format!("mcp::{}::{}", server_name, uri)
+}
+
+/// Flattens the contents of a resource read into plain text for the
+/// prompt, text parts go in verbatim and binary blobs become a marker so
+/// the LLM knows something was there without getting base64 dumped on it
+pub fn flatten_resource_contents(contents: &[ResourceContents]) -> String {
+    contents
+        .iter()
+        .map(|content| match content {
+            ResourceContents::Text { text, .. } => text.to_owned(),
+            ResourceContents::Blob { uri, mime_type, .. } => format!(
+                "<binary resource {} ({})>",
+                uri,
+                mime_type.as_deref().unwrap_or("unknown mime type")
+            ),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::flatten_resource_contents;
+    use mcp_client_rs::ResourceContents;
+
+    #[test]
+    fn test_flatten_keeps_text_and_marks_blobs() {
+        let contents = vec![
+            ResourceContents::Text {
+                uri: "notes://1".to_owned(),
+                mime_type: Some("text/plain".to_owned()),
+                text: "first note".to_owned(),
+            },
+            ResourceContents::Blob {
+                uri: "notes://2".to_owned(),
+                mime_type: Some("image/png".to_owned()),
+                blob: "aGVsbG8=".to_owned(),
+            },
+        ];
+        assert_eq!(
+            flatten_resource_contents(&contents),
+            "first note\n<binary resource notes://2 (image/png)>"
+        );
+    }
+}
@@ -0,0 +1,69 @@
+//! Middleware which wraps every tool invocation going through the broker
+//!
+//! Cross-cutting concerns like logging, redaction, timing or dry-run want
+//! to see every tool call without each tool knowing about them. The
+//! interceptors registered at broker construction run in order before the
+//! tool and in reverse order after it, each one can modify the input and
+//! the output or short-circuit the invocation entirely
+
+use async_trait::async_trait;
+
+use super::errors::ToolError;
+use super::input::ToolInput;
+use super::output::ToolOutput;
+use super::r#type::ToolType;
+
+/// What an interceptor decided to do with the invocation before the tool
+/// has run
+pub enum ToolMiddlewareDecision {
+    /// keep going with this (possibly modified) input
+    Continue(ToolInput),
+    /// skip the tool and answer with this result directly, the remaining
+    /// interceptors never see the call
+    ShortCircuit(Result<ToolOutput, ToolError>),
+}
+
+/// An interceptor around tool invocations, register implementations on the
+/// broker with with_middleware. Both hooks default to passing everything
+/// through untouched so implementations only override the side they need
+#[async_trait]
+pub trait ToolMiddleware {
+    /// runs before the tool gets invoked, in registration order
+    async fn before_invoke(&self, input: ToolInput) -> ToolMiddlewareDecision {
+        ToolMiddlewareDecision::Continue(input)
+    }
+
+    /// runs after the tool finished, in reverse registration order, with
+    /// how long the invocation took
+    async fn after_invoke(
+        &self,
+        tool_type: &ToolType,
+        elapsed: std::time::Duration,
+        result: Result<ToolOutput, ToolError>,
+    ) -> Result<ToolOutput, ToolError> {
+        let _ = (tool_type, elapsed);
+        result
+    }
+}
+
+/// Prints how long every tool invocation took and whether it failed, the
+/// simplest useful interceptor
+pub struct ToolTimingMiddleware;
+
+#[async_trait]
+impl ToolMiddleware for ToolTimingMiddleware {
+    async fn after_invoke(
+        &self,
+        tool_type: &ToolType,
+        elapsed: std::time::Duration,
+        result: Result<ToolOutput, ToolError>,
+    ) -> Result<ToolOutput, ToolError> {
+        println!(
+            "tool_broker::invoke::tool({})::took({}ms)::errored({})",
+            tool_type,
+            elapsed.as_millis(),
+            result.is_err(),
+        );
+        result
+    }
+}
@@ -14,15 +14,18 @@
 //! Language server: Gets the diagnostics for the current file if required or over the workspace
 //! Terminal: Use the terminal to run operations or something
 
+pub mod audit;
 pub mod broker;
 pub mod code_edit;
 pub mod code_symbol;
+pub mod determinism;
 pub mod devtools;
 pub mod editor;
 pub mod errors;
 pub mod feedback;
 pub mod file;
 pub mod filtering;
+pub mod format;
 pub mod git;
 pub mod grep;
 pub mod helpers;
@@ -32,15 +35,21 @@ pub mod jitter;
 pub mod kw_search;
 pub mod lsp;
 pub mod mcp;
+pub mod middleware;
+pub mod model_overrides;
 pub mod output;
 pub mod plan;
+pub mod policy;
 pub mod ref_filter;
 pub mod repo_map;
 pub mod rerank;
 pub mod reward;
 pub mod search;
+pub mod sensitive_files;
 pub mod session;
 pub mod swe_bench;
 pub mod terminal;
 pub mod test_runner;
+pub mod tree_sitter;
 pub mod r#type;
+pub mod workspace;
@@ -0,0 +1,101 @@
+//! Per-tool model overrides for the broker
+//!
+//! Different tools want different models, a cheap model filters references
+//! just fine while editing deserves the strong one. The overrides map a
+//! tool to the llm properties it should run with, the broker rewrites the
+//! request before dispatch and everything without an override keeps the
+//! model the request came in with. Like the tool policy this loads from a
+//! json file next to the index and can be swapped at runtime through the
+//! webserver
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::agentic::symbol::identifier::LLMProperties;
+
+use super::r#type::ToolType;
+
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct ToolModelOverrides {
+    #[serde(default)]
+    overrides: HashMap<ToolType, LLMProperties>,
+}
+
+impl ToolModelOverrides {
+    /// Reads the overrides from a json file, a missing file means no
+    /// overrides and a malformed one gets ignored with a warning instead
+    /// of taking the broker down
+    pub async fn load_from_file(path: &Path) -> Option<Self> {
+        let content = tokio::fs::read_to_string(path).await.ok()?;
+        match serde_json::from_str::<Self>(&content) {
+            Ok(overrides) => Some(overrides),
+            Err(e) => {
+                println!(
+                    "tool_model_overrides::load_from_file::malformed({})::error({})",
+                    path.display(),
+                    e
+                );
+                None
+            }
+        }
+    }
+
+    /// The llm properties this tool should run with, when there is no
+    /// override the tool keeps whatever model its request carries
+    pub fn override_for(&self, tool_type: &ToolType) -> Option<&LLMProperties> {
+        self.overrides.get(tool_type)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.overrides.is_empty()
+    }
+
+    pub fn overrides(&self) -> &HashMap<ToolType, LLMProperties> {
+        &self.overrides
+    }
+
+    pub fn with_override(mut self, tool_type: ToolType, llm_properties: LLMProperties) -> Self {
+        self.overrides.insert(tool_type, llm_properties);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use llm_client::clients::types::LLMType;
+    use llm_client::provider::{LLMProvider, LLMProviderAPIKeys, OpenAIProvider};
+
+    use super::ToolModelOverrides;
+    use crate::agentic::symbol::identifier::LLMProperties;
+    use crate::agentic::tool::r#type::ToolType;
+
+    fn properties(llm: LLMType) -> LLMProperties {
+        LLMProperties::new(
+            llm,
+            LLMProvider::OpenAI,
+            LLMProviderAPIKeys::OpenAI(OpenAIProvider::new("".to_owned())),
+        )
+    }
+
+    #[test]
+    fn test_empty_overrides_leave_every_tool_alone() {
+        let overrides = ToolModelOverrides::default();
+        assert!(overrides.override_for(&ToolType::ReferencesFilter).is_none());
+        assert!(overrides.is_empty());
+    }
+
+    #[test]
+    fn test_override_only_applies_to_its_tool() {
+        let overrides = ToolModelOverrides::default()
+            .with_override(ToolType::ReferencesFilter, properties(LLMType::Gpt4OMini));
+        assert_eq!(
+            overrides
+                .override_for(&ToolType::ReferencesFilter)
+                .map(|properties| properties.llm().clone()),
+            Some(LLMType::Gpt4OMini)
+        );
+        assert!(overrides
+            .override_for(&ToolType::SearchAndReplaceEditing)
+            .is_none());
+    }
+}
@@ -5,6 +5,7 @@ use crate::agentic::tool::mcp::integration_tool::McpToolResponse;
 
 use super::{
     code_edit::{
+        compiler_suggestions::ApplyCompilerSuggestionsResponse,
         filter_edit::FilterEditOperationResponse,
         search_and_replace::SearchAndReplaceEditingResponse,
     },
@@ -21,6 +22,7 @@ use super::{
         },
         new_location::CodeSymbolNewLocationResponse,
         new_sub_symbol::NewSubSymbolRequiredResponse,
+        outline_diff::OutlineDiffResponse,
         planning_before_code_edit::PlanningBeforeCodeEditResponse,
         probe::ProbeEnoughOrDeeperResponse,
         reranking_symbols_for_editing_context::ReRankingSnippetsForCodeEditingResponse,
@@ -34,9 +36,14 @@ use super::{
         CodeToEditFilterResponse, CodeToEditSymbolResponse, CodeToProbeFilterResponse,
         CodeToProbeSubSymbolList,
     },
-    git::{diff_client::GitDiffClientResponse, edited_files::EditedFilesResponse},
+    format::formatter::FormatCodeResponse,
+    git::{
+        bisect::GitBisectResponse, diff_client::GitDiffClientResponse,
+        edited_files::EditedFilesResponse,
+    },
     grep::file::FindInFileResponse,
     lsp::{
+        call_hierarchy::CallHierarchyResponse,
         create_file::CreateFileResponse,
         diagnostics::LSPDiagnosticsOutput,
         file_diagnostics::FileDiagnosticsOutput,
@@ -51,9 +58,11 @@ use super::{
         list_files::ListFilesOutput,
         open_file::OpenFileResponse,
         quick_fix::{GetQuickFixResponse, LSPQuickFixInvocationResponse},
+        rename::LSPRenameResponse,
         search_file::SearchFileContentWithRegexOutput,
         subprocess_spawned_output::SubProcessSpanwedPendingOutputResponse,
         undo_changes::UndoChangesMadeDuringExchangeRespnose,
+        workspace_symbol::WorkspaceSymbolResponse,
     },
     plan::{generator::StepGeneratorResponse, reasoning::ReasoningResponse},
     repo_map::generator::RepoMapGeneratorResponse,
@@ -67,6 +76,7 @@ use super::{
     swe_bench::test_tool::SWEBenchTestRepsonse,
     terminal::terminal::TerminalOutput,
     test_runner::runner::TestRunnerResponse,
+    tree_sitter::{import_graph::ImportGraphOutput, query::TreeSitterQueryOutput},
 };
 
 #[derive(Debug)]
@@ -179,6 +189,8 @@ pub enum ToolOutput {
     SearchAndReplaceEditing(SearchAndReplaceEditingResponse),
     // git diff response
     GitDiff(GitDiffClientResponse),
+    GitBisect(GitBisectResponse),
+    OutlineDiff(OutlineDiffResponse),
     // outline nodes from the editor
     OutlineNodesUsingEditor(OutlineNodesUsingEditorResponse),
     // filter reference
@@ -235,10 +247,32 @@ pub enum ToolOutput {
     FindFiles(FindFilesResponse),
     // Request screenshot output
     RequestScreenshot(RequestScreenshotOutput),
+    // Tree-sitter query output
+    TreeSitterQuery(TreeSitterQueryOutput),
+    // Format code output
+    FormatCode(FormatCodeResponse),
+    // Apply compiler suggestions output
+    ApplyCompilerSuggestions(ApplyCompilerSuggestionsResponse),
+    // Import graph output
+    ImportGraph(ImportGraphOutput),
+    // Rename symbol output
+    Rename(LSPRenameResponse),
+    // Call hierarchy output
+    CallHierarchy(CallHierarchyResponse),
+    // Workspace symbol search output
+    WorkspaceSymbol(WorkspaceSymbolResponse),
     // dynamically configured MCP servers
     McpTool(McpToolResponse),
 }
 
+/// Implemented by every response type which lives inside a ToolOutput
+/// variant, so call sites can ask for the concrete type through
+/// [`ToolOutput::downcast`] instead of going through an accessor per
+/// variant. New tools get the impl for free through `impl_output!`
+pub trait ToolOutputVariant: Sized {
+    fn from_tool_output(output: ToolOutput) -> Option<Self>;
+}
+
 macro_rules! impl_output {
     ($name:ident, $variant:ident, $type:ty) => {
         pub fn $name(self) -> Option<$type> {
@@ -250,7 +284,63 @@ macro_rules! impl_output {
     };
 }
 
+/// Wires a response type to its ToolOutput variant so it participates in
+/// the typed downcast
+macro_rules! impl_output_variant {
+    ($variant:ident, $type:ty) => {
+        impl ToolOutputVariant for $type {
+            fn from_tool_output(output: ToolOutput) -> Option<Self> {
+                match output {
+                    ToolOutput::$variant(response) => Some(response),
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+impl_output_variant!(TreeSitterQuery, TreeSitterQueryOutput);
+impl_output_variant!(FormatCode, FormatCodeResponse);
+impl_output_variant!(ApplyCompilerSuggestions, ApplyCompilerSuggestionsResponse);
+impl_output_variant!(ImportGraph, ImportGraphOutput);
+impl_output_variant!(Rename, LSPRenameResponse);
+impl_output_variant!(CallHierarchy, CallHierarchyResponse);
+impl_output_variant!(WorkspaceSymbol, WorkspaceSymbolResponse);
+impl_output_variant!(McpTool, McpToolResponse);
+
 impl ToolOutput {
+    /// Pulls the typed response out of this output, None when the output
+    /// holds a different variant. Prefer this over the `get_*` accessors
+    /// for new call sites, the type parameter makes the expectation explicit
+    pub fn downcast<T: ToolOutputVariant>(self) -> Option<T> {
+        T::from_tool_output(self)
+    }
+
+    /// A clone of this output when it comes from one of the read-only lsp
+    /// style tools, these are the only results which are safe and cheap for
+    /// the broker to hand out again from its cache
+    pub fn cheap_clone(&self) -> Option<ToolOutput> {
+        match self {
+            ToolOutput::FileOpen(response) => Some(ToolOutput::FileOpen(response.clone())),
+            ToolOutput::GoToDefinition(response) => {
+                Some(ToolOutput::GoToDefinition(response.clone()))
+            }
+            ToolOutput::GoToTypeDefinition(response) => {
+                Some(ToolOutput::GoToTypeDefinition(response.clone()))
+            }
+            ToolOutput::GoToReference(response) => {
+                Some(ToolOutput::GoToReference(response.clone()))
+            }
+            ToolOutput::GoToImplementation(response) => {
+                Some(ToolOutput::GoToImplementation(response.clone()))
+            }
+            ToolOutput::OutlineNodesUsingEditor(response) => {
+                Some(ToolOutput::OutlineNodesUsingEditor(response.clone()))
+            }
+            _ => None,
+        }
+    }
+
     pub fn sub_process_spawned_pending_output(
         response: SubProcessSpanwedPendingOutputResponse,
     ) -> Self {
@@ -265,6 +355,34 @@ impl ToolOutput {
         ToolOutput::SearchFileContentWithRegex(response)
     }
 
+    pub fn tree_sitter_query(response: TreeSitterQueryOutput) -> Self {
+        ToolOutput::TreeSitterQuery(response)
+    }
+
+    pub fn format_code(response: FormatCodeResponse) -> Self {
+        ToolOutput::FormatCode(response)
+    }
+
+    pub fn apply_compiler_suggestions(response: ApplyCompilerSuggestionsResponse) -> Self {
+        ToolOutput::ApplyCompilerSuggestions(response)
+    }
+
+    pub fn import_graph(response: ImportGraphOutput) -> Self {
+        ToolOutput::ImportGraph(response)
+    }
+
+    pub fn rename(response: LSPRenameResponse) -> Self {
+        ToolOutput::Rename(response)
+    }
+
+    pub fn call_hierarchy(response: CallHierarchyResponse) -> Self {
+        ToolOutput::CallHierarchy(response)
+    }
+
+    pub fn workspace_symbol(response: WorkspaceSymbolResponse) -> Self {
+        ToolOutput::WorkspaceSymbol(response)
+    }
+
     pub fn context_driven_hot_streak_reply(response: SessionHotStreakResponse) -> Self {
         ToolOutput::ContextDriveHotStreakReply(response)
     }
@@ -314,6 +432,14 @@ impl ToolOutput {
         ToolOutput::GitDiff(response)
     }
 
+    pub fn git_bisect_response(response: GitBisectResponse) -> Self {
+        ToolOutput::GitBisect(response)
+    }
+
+    pub fn outline_diff_response(response: OutlineDiffResponse) -> Self {
+        ToolOutput::OutlineDiff(response)
+    }
+
     pub fn search_and_replace_editing(response: SearchAndReplaceEditingResponse) -> Self {
         ToolOutput::SearchAndReplaceEditing(response)
     }
@@ -741,6 +867,20 @@ impl ToolOutput {
         }
     }
 
+    pub fn get_outline_diff_output(self) -> Option<OutlineDiffResponse> {
+        match self {
+            ToolOutput::OutlineDiff(response) => Some(response),
+            _ => None,
+        }
+    }
+
+    pub fn get_git_bisect_output(self) -> Option<GitBisectResponse> {
+        match self {
+            ToolOutput::GitBisect(response) => Some(response),
+            _ => None,
+        }
+    }
+
     pub fn get_git_diff_output(self) -> Option<GitDiffClientResponse> {
         match self {
             ToolOutput::GitDiff(response) => Some(response),
@@ -931,5 +1071,35 @@ impl ToolOutput {
         }
     }
 
+    pub fn get_tree_sitter_query_response(self) -> Option<TreeSitterQueryOutput> {
+        match self {
+            ToolOutput::TreeSitterQuery(response) => Some(response),
+            _ => None,
+        }
+    }
+
+    pub fn get_format_code_response(self) -> Option<FormatCodeResponse> {
+        match self {
+            ToolOutput::FormatCode(response) => Some(response),
+            _ => None,
+        }
+    }
+
+    pub fn get_apply_compiler_suggestions_response(
+        self,
+    ) -> Option<ApplyCompilerSuggestionsResponse> {
+        match self {
+            ToolOutput::ApplyCompilerSuggestions(response) => Some(response),
+            _ => None,
+        }
+    }
+
+    pub fn get_import_graph_response(self) -> Option<ImportGraphOutput> {
+        match self {
+            ToolOutput::ImportGraph(response) => Some(response),
+            _ => None,
+        }
+    }
+
     impl_output!(get_mcp_response, McpTool, McpToolResponse);
 }
@@ -276,7 +276,9 @@ impl Tool for PlanAddStepClient {
             LLMClientCompletionRequest::new(LLMType::O1Preview, messages, 0.2, None)
         } else {
             LLMClientCompletionRequest::new(LLMType::ClaudeSonnet, messages, 0.2, None)
-        };
+        }
+        // everything after the closing </response> tag is wasted cost
+        .set_stop_after_patterns(vec!["</response>".to_owned()]);
 
         let llm_properties = if is_deep_reasoning {
             LLMProperties::new(
@@ -198,6 +198,10 @@ impl StepGeneratorRequest {
         }
     }
 
+    pub fn set_llm_properties(&mut self, llm_properties: LLMProperties) {
+        self.llm_properties = llm_properties;
+    }
+
     pub fn user_query(&self) -> &str {
         &self.user_query
     }
@@ -390,9 +394,19 @@ Separation of Concerns: Keeps execution state separate from other data, making t
             }
             None => format!(""),
         };
+        // bug-fix tasks get the repro-first methodology wired into the plan:
+        // confirm the failure before touching code and confirm the fix after
+        let repro_first_guidance = if is_bug_fix_query(context.user_query()) {
+            r#"
+- This looks like a bug-fix task, so follow the repro-first methodology:
+The FIRST step of the plan must create a minimal reproduction of the issue (a failing test or a small script) and run it to confirm the failure before any code is changed.
+The LAST step of the plan must re-run that reproduction and require it to pass, proving the fix. Do not skip either step."#
+        } else {
+            ""
+        };
         format!(
             r#"You are a senior software engineer, expert planner and system architect working alongside a software engineer.
-{aide_rules}
+{aide_rules}{repro_first_guidance}
 - Given a request and context, you will generate a step by step plan to accomplish it. Use prior art seen in context where applicable.
 - Your job is to be precise and effective, so avoid extraneous steps even if they offer convenience.
 - Do not talk about testing out the changes unless you are instructed to do so.
@@ -573,7 +587,10 @@ impl Tool for StepGeneratorClient {
             LLMClientCompletionRequest::new(LLMType::O1Preview, messages, 0.2, None)
         } else {
             LLMClientCompletionRequest::new(LLMType::ClaudeSonnet, messages, 0.2, None)
-        };
+        }
+        // the parser only reads until </response>, anything generated after
+        // the closing tag is wasted cost
+        .set_stop_after_patterns(vec!["</response>".to_owned()]);
 
         let llm_properties = context.llm_properties.clone();
         let (sender, receiver) = tokio::sync::mpsc::unbounded_channel();
@@ -902,10 +919,40 @@ fn get_last_newline_line_number(s: &str) -> Option<usize> {
         .map(|last_index| s[..=last_index].chars().filter(|&c| c == '\n').count())
 }
 
+/// Detects whether the user query reads like a bug-fix task, these get the
+/// repro-first methodology in the generated plan
+fn is_bug_fix_query(user_query: &str) -> bool {
+    let query = user_query.to_lowercase();
+    [
+        "bug",
+        "fix",
+        "error",
+        "crash",
+        "panic",
+        "broken",
+        "regression",
+        "fails",
+        "failing",
+        "traceback",
+        "exception",
+    ]
+    .iter()
+    .any(|marker| query.contains(marker))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_bug_fix_query_detection() {
+        assert!(is_bug_fix_query("Fix the crash when the config is missing"));
+        assert!(is_bug_fix_query(
+            "the parser fails on empty input with a traceback"
+        ));
+        assert!(!is_bug_fix_query("Add dark mode support to the settings"));
+    }
+
     #[tokio::test]
     async fn test_parse_response_with_cdata() {
         let input = r#"Certainly! I'll create a stepped plan to implement a new Tool called StepGeneratorClient, similar to the ReasoningClient. Here's the plan:
@@ -1,5 +1,6 @@
 pub(crate) mod add_steps;
 pub mod generator;
+pub mod ownership;
 pub mod plan;
 pub mod plan_step;
 pub(crate) mod reasoning;
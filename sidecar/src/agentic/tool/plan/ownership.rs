@@ -0,0 +1,310 @@
+//! CODEOWNERS awareness for planning. We parse the CODEOWNERS file of the
+//! repository the plan is editing and compute which teams own the files the
+//! plan touches. The summary gets attached to the plan (and through
+//! [`super::plan::Plan::format_to_string`] flows into everything generated
+//! from the plan, like PR descriptions) and carries a warning when the plan
+//! crosses many ownership boundaries since those changes need sign-off from
+//! several teams.
+
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+/// A plan touching more distinct owner groups than this gets flagged, since
+/// every extra group is another review the change has to wait on.
+const OWNERSHIP_BOUNDARY_WARNING_THRESHOLD: usize = 2;
+
+/// The locations github checks for a CODEOWNERS file, in lookup order.
+const CODEOWNERS_LOCATIONS: &[&str] = &[
+    ".github/CODEOWNERS",
+    "CODEOWNERS",
+    "docs/CODEOWNERS",
+];
+
+/// A single pattern -> owners line from CODEOWNERS.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CodeownersRule {
+    pattern: String,
+    owners: Vec<String>,
+}
+
+impl CodeownersRule {
+    pub fn owners(&self) -> &[String] {
+        &self.owners
+    }
+}
+
+/// Parses the contents of a CODEOWNERS file, skipping comments and blank
+/// lines. Rules keep their file order since the last matching rule wins.
+pub fn parse_codeowners(content: &str) -> Vec<CodeownersRule> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                return None;
+            }
+            let mut parts = line.split_whitespace();
+            let pattern = parts.next()?.to_owned();
+            let owners = parts.map(|owner| owner.to_owned()).collect::<Vec<_>>();
+            // a pattern with no owners is valid CODEOWNERS syntax and means
+            // the path is explicitly unowned, we keep it so it can shadow
+            // earlier rules
+            Some(CodeownersRule { pattern, owners })
+        })
+        .collect()
+}
+
+/// Matches a single pattern segment against a path segment, `*` matches any
+/// run of characters within the segment.
+fn segment_matches(pattern: &str, segment: &str) -> bool {
+    match pattern.split_once('*') {
+        None => pattern == segment,
+        Some((prefix, rest)) => {
+            if !segment.starts_with(prefix) {
+                return false;
+            }
+            let remaining = &segment[prefix.len()..];
+            if rest.is_empty() {
+                return true;
+            }
+            // try the rest of the pattern at every char boundary after the
+            // prefix, byte offsets would panic on multi-byte path segments
+            remaining
+                .char_indices()
+                .map(|(idx, _)| idx)
+                .chain(std::iter::once(remaining.len()))
+                .any(|idx| segment_matches(rest, &remaining[idx..]))
+        }
+    }
+}
+
+/// Matches pattern segments against path segments, gitignore style: `**`
+/// spans any number of directories and an exhausted pattern matches the whole
+/// subtree (so `/docs` owns everything under docs/).
+fn match_segments(pattern: &[&str], path: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => true,
+        Some((first, rest_pattern)) => {
+            if *first == "**" {
+                (0..=path.len()).any(|idx| match_segments(rest_pattern, &path[idx..]))
+            } else {
+                match path.split_first() {
+                    None => false,
+                    Some((segment, rest_path)) => {
+                        segment_matches(first, segment) && match_segments(rest_pattern, rest_path)
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Checks whether a CODEOWNERS pattern matches a path relative to the repo
+/// root. Patterns containing a slash are anchored to the root, patterns
+/// without one match at any depth (e.g. `*.rs`).
+pub fn pattern_matches(pattern: &str, relative_path: &str) -> bool {
+    let relative_path = relative_path.trim_start_matches('/');
+    let path_segments = relative_path.split('/').collect::<Vec<_>>();
+    let pattern = pattern.trim_end_matches('/');
+    if let Some(anchored) = pattern.strip_prefix('/') {
+        let pattern_segments = anchored.split('/').collect::<Vec<_>>();
+        match_segments(&pattern_segments, &path_segments)
+    } else if pattern.contains('/') {
+        // a slash anywhere in the pattern anchors it to the root as well
+        let pattern_segments = pattern.split('/').collect::<Vec<_>>();
+        match_segments(&pattern_segments, &path_segments)
+    } else {
+        // a bare pattern matches any segment of the path: a directory name
+        // owns its whole subtree and a file glob matches wherever it appears
+        path_segments
+            .iter()
+            .any(|segment| segment_matches(pattern, segment))
+    }
+}
+
+/// Returns the owners for a file, the last matching rule wins just like
+/// github evaluates CODEOWNERS. `None` means no rule matched at all while an
+/// empty vec means the file matched a rule with no owners.
+pub fn owners_for_file(rules: &[CodeownersRule], relative_path: &str) -> Option<Vec<String>> {
+    rules
+        .iter()
+        .filter(|rule| pattern_matches(&rule.pattern, relative_path))
+        .last()
+        .map(|rule| rule.owners.clone())
+}
+
+/// Walks up from a file the plan touches until we find the repository root
+/// (the directory holding `.git`).
+pub fn find_repo_root(fs_file_path: &str) -> Option<PathBuf> {
+    let mut current = Path::new(fs_file_path).parent()?;
+    loop {
+        if current.join(".git").exists() {
+            return Some(current.to_path_buf());
+        }
+        current = current.parent()?;
+    }
+}
+
+/// Ownership information for the set of files a plan touches.
+#[derive(Debug, Clone)]
+pub struct OwnershipSummary {
+    /// file (relative to the repo root) along with its owners
+    file_owners: Vec<(String, Vec<String>)>,
+    /// files no CODEOWNERS rule covers
+    unowned_files: Vec<String>,
+}
+
+impl OwnershipSummary {
+    pub fn for_files(rules: &[CodeownersRule], repo_root: &Path, files: &[String]) -> Self {
+        let mut file_owners = vec![];
+        let mut unowned_files = vec![];
+        for fs_file_path in files.iter() {
+            let relative_path = Path::new(fs_file_path)
+                .strip_prefix(repo_root)
+                .map(|relative| relative.to_string_lossy().to_string())
+                .unwrap_or_else(|_| fs_file_path.to_owned());
+            match owners_for_file(rules, &relative_path) {
+                Some(owners) if !owners.is_empty() => {
+                    file_owners.push((relative_path, owners));
+                }
+                _ => unowned_files.push(relative_path),
+            }
+        }
+        Self {
+            file_owners,
+            unowned_files,
+        }
+    }
+
+    /// The distinct owner groups the plan needs sign-off from.
+    pub fn distinct_owner_groups(&self) -> BTreeSet<String> {
+        self.file_owners
+            .iter()
+            .flat_map(|(_, owners)| owners.iter().cloned())
+            .collect()
+    }
+
+    pub fn crosses_many_boundaries(&self) -> bool {
+        self.distinct_owner_groups().len() > OWNERSHIP_BOUNDARY_WARNING_THRESHOLD
+    }
+
+    /// Renders the summary for inclusion in the plan and anything generated
+    /// from it (PR descriptions in particular list the owning teams).
+    pub fn to_summary_text(&self) -> String {
+        let mut lines = vec!["Ownership (from CODEOWNERS):".to_owned()];
+        for (relative_path, owners) in self.file_owners.iter() {
+            lines.push(format!("- {}: {}", relative_path, owners.join(" ")));
+        }
+        for relative_path in self.unowned_files.iter() {
+            lines.push(format!("- {}: (no owner)", relative_path));
+        }
+        let owner_groups = self.distinct_owner_groups();
+        if self.crosses_many_boundaries() {
+            lines.push(format!(
+                "WARNING: this plan crosses {} ownership boundaries ({}), expect reviews from each of these teams and consider splitting the change.",
+                owner_groups.len(),
+                owner_groups.into_iter().collect::<Vec<_>>().join(", ")
+            ));
+        }
+        lines.join("\n")
+    }
+}
+
+/// Loads the CODEOWNERS rules for the repository containing the files and
+/// computes the ownership summary for them. Returns `None` when the repo has
+/// no CODEOWNERS file, plans over such repos should not grow an empty
+/// ownership section.
+pub async fn ownership_summary_for_files(files: &[String]) -> Option<OwnershipSummary> {
+    let repo_root = files
+        .iter()
+        .find_map(|fs_file_path| find_repo_root(fs_file_path))?;
+    let mut rules = None;
+    for location in CODEOWNERS_LOCATIONS.iter() {
+        if let Ok(content) = tokio::fs::read_to_string(repo_root.join(location)).await {
+            rules = Some(parse_codeowners(&content));
+            break;
+        }
+    }
+    let rules = rules?;
+    Some(OwnershipSummary::for_files(&rules, &repo_root, files))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{owners_for_file, parse_codeowners, pattern_matches, OwnershipSummary};
+    use std::path::Path;
+
+    #[test]
+    fn test_parsing_codeowners_skips_comments_and_blanks() {
+        let content = r#"# global fallback
+*       @org/core
+
+/docs/  @org/docs
+
+src/webserver/ @org/api @org/infra
+"#;
+        let rules = parse_codeowners(content);
+        assert_eq!(rules.len(), 3);
+        assert_eq!(rules[0].owners(), &["@org/core".to_owned()]);
+        assert_eq!(
+            rules[2].owners(),
+            &["@org/api".to_owned(), "@org/infra".to_owned()]
+        );
+    }
+
+    #[test]
+    fn test_pattern_matching() {
+        assert!(pattern_matches("*", "src/lib.rs"));
+        assert!(pattern_matches("*.rs", "src/lib.rs"));
+        assert!(!pattern_matches("*.go", "src/lib.rs"));
+        assert!(pattern_matches("/docs/", "docs/guide/index.md"));
+        assert!(!pattern_matches("/docs/", "src/docs.rs"));
+        assert!(pattern_matches("src/webserver/", "src/webserver/review.rs"));
+        assert!(pattern_matches("**/tests/", "crates/a/tests/smoke.rs"));
+        // multi-byte segments must not panic the wildcard backtracking
+        assert!(pattern_matches("*.rs", "docs/日本語テスト.rs"));
+        assert!(!pattern_matches("a*z", "aé"));
+    }
+
+    #[test]
+    fn test_last_matching_rule_wins() {
+        let rules = parse_codeowners(
+            r#"*       @org/core
+/docs/  @org/docs
+"#,
+        );
+        assert_eq!(
+            owners_for_file(&rules, "docs/guide.md"),
+            Some(vec!["@org/docs".to_owned()])
+        );
+        assert_eq!(
+            owners_for_file(&rules, "src/lib.rs"),
+            Some(vec!["@org/core".to_owned()])
+        );
+    }
+
+    #[test]
+    fn test_summary_warns_on_many_boundaries() {
+        let rules = parse_codeowners(
+            r#"/a/ @org/alpha
+/b/ @org/beta
+/c/ @org/gamma
+"#,
+        );
+        let summary = OwnershipSummary::for_files(
+            &rules,
+            Path::new("/repo"),
+            &[
+                "/repo/a/one.rs".to_owned(),
+                "/repo/b/two.rs".to_owned(),
+                "/repo/c/three.rs".to_owned(),
+                "/repo/d/unowned.rs".to_owned(),
+            ],
+        );
+        assert_eq!(summary.distinct_owner_groups().len(), 3);
+        assert!(summary.crosses_many_boundaries());
+        let text = summary.to_summary_text();
+        assert!(text.contains("WARNING"));
+        assert!(text.contains("d/unowned.rs: (no owner)"));
+    }
+}
@@ -16,6 +16,10 @@ pub struct Plan {
     checkpoint: Option<usize>,
     storage_path: String,
     original_file_content: HashMap<String, OpenFileResponse>,
+    /// which teams own the files this plan touches, computed from the
+    /// CODEOWNERS of the repo when one exists
+    #[serde(default)]
+    ownership_summary: Option<String>,
 }
 
 impl Plan {
@@ -36,9 +40,19 @@ impl Plan {
             checkpoint: None,
             storage_path,
             original_file_content: Default::default(),
+            ownership_summary: None,
         }
     }
 
+    pub fn with_ownership_summary(mut self, ownership_summary: Option<String>) -> Self {
+        self.ownership_summary = ownership_summary;
+        self
+    }
+
+    pub fn ownership_summary(&self) -> Option<&str> {
+        self.ownership_summary.as_deref()
+    }
+
     /// Drops the steps which are present in the plan until a point
     pub fn drop_plan_steps(mut self, drop_from: usize) -> Self {
         if drop_from < self.steps.len() {
@@ -158,10 +172,15 @@ impl Plan {
             .collect::<Vec<_>>()
             .join("\n");
         let user_query = self.user_query();
+        let ownership = self
+            .ownership_summary
+            .as_ref()
+            .map(|ownership_summary| format!("\n{ownership_summary}"))
+            .unwrap_or_default();
         format!(
             r#"Initial user query: {user_query}
 Plan up until now:
-{plan_steps}"#
+{plan_steps}{ownership}"#
         )
     }
 
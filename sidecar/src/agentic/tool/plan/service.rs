@@ -20,7 +20,10 @@ use crate::{
             types::SymbolEventRequest,
         },
         tool::{
-            errors::ToolError, lsp::file_diagnostics::DiagnosticMap,
+            errors::ToolError,
+            input::ToolInput,
+            lsp::{file_diagnostics::DiagnosticMap, open_file::OpenFileRequest},
+            r#type::Tool,
             session::chat::SessionChatMessage,
         },
     },
@@ -30,10 +33,15 @@ use crate::{
 
 use super::{
     generator::StepSenderEvent,
+    ownership::ownership_summary_for_files,
     plan::Plan,
     plan_step::{PlanStep, StepExecutionContext},
 };
 
+/// Pause between prefetched files, the prefetch runs at low priority and
+/// should never starve the editor endpoint of requests which matter now
+const CONTEXT_PREFETCH_PAUSE: std::time::Duration = std::time::Duration::from_millis(250);
+
 /// Operates on Plan
 #[derive(Clone)]
 pub struct PlanService {
@@ -405,7 +413,7 @@ impl PlanService {
                 previous_messages,
                 is_deep_reasoning,
                 step_sender,
-                message_properties,
+                message_properties.clone(),
             )
             .await?;
 
@@ -422,10 +430,86 @@ impl PlanService {
             plan_steps,
             plan_storage_path.to_owned(),
         );
+        // surface which teams own the files the plan touches, downstream
+        // consumers (PR descriptions) pick this up from the formatted plan
+        let ownership_summary = ownership_summary_for_files(&plan.files_in_plan())
+            .await
+            .map(|ownership_summary| {
+                if ownership_summary.crosses_many_boundaries() {
+                    println!(
+                        "plan::service::create_plan::ownership_boundaries({})",
+                        ownership_summary.distinct_owner_groups().len()
+                    );
+                }
+                ownership_summary.to_summary_text()
+            });
+        let plan = plan.with_ownership_summary(ownership_summary);
         self.save_plan(&plan, &plan_storage_path).await?;
+        // warm the caches for the files the steps are going to touch, step
+        // execution then starts without stalling on serial file opens
+        self.spawn_context_prefetch(&plan, message_properties);
         Ok(plan)
     }
 
+    /// Prefetches the files named by the plan steps in the background, the
+    /// open and outline responses land in the broker result cache so step
+    /// execution gets them without a round trip to the editor. Fire and
+    /// forget, a prefetch failure just means the step pays for the lookup
+    /// itself like it always did
+    fn spawn_context_prefetch(
+        &self,
+        plan: &Plan,
+        message_properties: SymbolEventMessageProperties,
+    ) {
+        let files_in_plan = plan.files_in_plan();
+        if files_in_plan.is_empty() {
+            return;
+        }
+        let tool_box = self.tool_box.clone();
+        tokio::spawn(async move {
+            for fs_file_path in files_in_plan {
+                let file_open = tool_box
+                    .tools()
+                    .invoke(ToolInput::OpenFile(OpenFileRequest::new(
+                        fs_file_path.to_owned(),
+                        message_properties.editor_url(),
+                        None,
+                        None,
+                    )))
+                    .await;
+                match file_open.map(|output| output.get_file_open_response()) {
+                    Ok(Some(file_open_response)) => {
+                        // warms the tree-sitter outline cache on the symbol
+                        // tracker as well
+                        let _ = tool_box
+                            .force_add_document(
+                                &fs_file_path,
+                                file_open_response.contents_ref(),
+                                file_open_response.language(),
+                            )
+                            .await;
+                        let _ = tool_box
+                            .get_outline_nodes_from_editor(
+                                &fs_file_path,
+                                message_properties.clone(),
+                            )
+                            .await;
+                    }
+                    Ok(None) => {}
+                    Err(e) => {
+                        println!(
+                            "plan::service::context_prefetch::file({})::error({:?})",
+                            &fs_file_path, e
+                        );
+                    }
+                }
+                // rate limit: one file at a time with a pause in between so
+                // the prefetch never competes with the step being executed
+                tokio::time::sleep(CONTEXT_PREFETCH_PAUSE).await;
+            }
+        });
+    }
+
     /// gets all files_to_edit from PlanSteps up to index
     pub fn get_edited_files(&self, plan: &Plan, index: usize) -> Vec<String> {
         plan.steps()[..index]
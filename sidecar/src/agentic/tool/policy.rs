@@ -0,0 +1,103 @@
+//! Policy which decides which tools are allowed to run
+//!
+//! Deployments want to switch off individual tools (no terminal, no web
+//! search) or pin the whole broker down to an allowlist. The policy loads
+//! from a json file next to the index and can be swapped out at runtime
+//! through the webserver, the broker consults it on every invocation
+
+use std::collections::HashSet;
+use std::path::Path;
+
+use super::r#type::ToolType;
+
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct ToolPolicy {
+    /// these tools never run no matter what, checked before the allowlist
+    #[serde(default)]
+    disabled_tools: HashSet<ToolType>,
+    /// when present only these tools are allowed to run, everything else
+    /// gets rejected
+    #[serde(default)]
+    allowed_tools: Option<HashSet<ToolType>>,
+}
+
+impl ToolPolicy {
+    /// Reads the policy from a json file, a missing file means no policy
+    /// and a malformed one gets ignored with a warning instead of taking
+    /// the broker down
+    pub async fn load_from_file(path: &Path) -> Option<Self> {
+        let content = tokio::fs::read_to_string(path).await.ok()?;
+        match serde_json::from_str::<Self>(&content) {
+            Ok(policy) => Some(policy),
+            Err(e) => {
+                println!(
+                    "tool_policy::load_from_file::malformed({})::error({})",
+                    path.display(),
+                    e
+                );
+                None
+            }
+        }
+    }
+
+    /// Whether this tool is allowed to run under the current policy
+    pub fn is_allowed(&self, tool_type: &ToolType) -> bool {
+        if self.disabled_tools.contains(tool_type) {
+            return false;
+        }
+        match self.allowed_tools.as_ref() {
+            Some(allowed_tools) => allowed_tools.contains(tool_type),
+            None => true,
+        }
+    }
+
+    pub fn disabled_tools(&self) -> &HashSet<ToolType> {
+        &self.disabled_tools
+    }
+
+    pub fn disable_tool(mut self, tool_type: ToolType) -> Self {
+        self.disabled_tools.insert(tool_type);
+        self
+    }
+
+    pub fn with_allowed_tools(mut self, allowed_tools: HashSet<ToolType>) -> Self {
+        self.allowed_tools = Some(allowed_tools);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ToolPolicy;
+    use crate::agentic::tool::r#type::ToolType;
+
+    #[test]
+    fn test_empty_policy_allows_everything() {
+        let policy = ToolPolicy::default();
+        assert!(policy.is_allowed(&ToolType::TerminalCommand));
+        assert!(policy.is_allowed(&ToolType::OpenFile));
+    }
+
+    #[test]
+    fn test_disabled_tools_are_rejected() {
+        let policy = ToolPolicy::default().disable_tool(ToolType::TerminalCommand);
+        assert!(!policy.is_allowed(&ToolType::TerminalCommand));
+        assert!(policy.is_allowed(&ToolType::OpenFile));
+    }
+
+    #[test]
+    fn test_allowlist_rejects_everything_else() {
+        let policy = ToolPolicy::default()
+            .with_allowed_tools([ToolType::OpenFile].into_iter().collect());
+        assert!(policy.is_allowed(&ToolType::OpenFile));
+        assert!(!policy.is_allowed(&ToolType::TerminalCommand));
+    }
+
+    #[test]
+    fn test_disabled_wins_over_allowlist() {
+        let policy = ToolPolicy::default()
+            .with_allowed_tools([ToolType::OpenFile].into_iter().collect())
+            .disable_tool(ToolType::OpenFile);
+        assert!(!policy.is_allowed(&ToolType::OpenFile));
+    }
+}
@@ -64,6 +64,10 @@ impl ReferenceFilterRequest {
         &self.llm_properties
     }
 
+    pub fn set_llm_properties(&mut self, llm_properties: LLMProperties) {
+        self.llm_properties = llm_properties;
+    }
+
     pub fn root_id(&self) -> &str {
         &self.root_id
     }
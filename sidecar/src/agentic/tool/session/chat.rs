@@ -8,9 +8,7 @@ use crate::{
         symbol::{identifier::LLMProperties, ui_event::UIEventWithID},
         tool::{
             errors::ToolError,
-            helpers::{
-                cancellation_future::run_with_cancellation, diff_recent_changes::DiffRecentChanges,
-            },
+            helpers::diff_recent_changes::DiffRecentChanges,
             input::ToolInput,
             output::ToolOutput,
             r#type::{Tool, ToolRewardScale},
@@ -24,8 +22,8 @@ use futures::StreamExt;
 use llm_client::{
     broker::LLMBroker,
     clients::types::{
-        LLMClientCompletionRequest, LLMClientMessage, LLMClientMessageImage, LLMClientRole,
-        LLMClientToolReturn, LLMClientToolUse,
+        GenerationProfile, LLMClientCompletionRequest, LLMClientMessage, LLMClientMessageImage,
+        LLMClientRole, LLMClientToolReturn, LLMClientToolUse,
     },
 };
 use tokio::sync::mpsc::UnboundedSender;
@@ -194,7 +192,7 @@ impl SessionChatMessage {
             .into_iter()
             .map(|llm_image| {
                 SessionChatMessageImage::new(
-                    llm_image.data().to_owned(),
+                    llm_image.r#type().to_owned(),
                     llm_image.media().to_owned(),
                     llm_image.data().to_owned(),
                 )
@@ -275,6 +273,10 @@ impl SessionChatClientRequest {
             llm_properties,
         }
     }
+
+    pub fn set_llm_properties(&mut self, llm_properties: LLMProperties) {
+        self.llm_properties = llm_properties;
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -437,32 +439,32 @@ impl Tool for SessionChatClient {
         let mut messages = vec![system_message];
         messages.extend(user_messages);
 
-        let request =
-            LLMClientCompletionRequest::new(llm_properties.llm().clone(), messages, 0.2, None);
+        let request = LLMClientCompletionRequest::from_messages_with_profile(
+            GenerationProfile::Fast,
+            messages,
+            llm_properties.llm().clone(),
+        );
 
         // now we have to poll both the stream which will send deltas and also the one
         // which will poll the future from the stream
         let (sender, receiver) = tokio::sync::mpsc::unbounded_channel();
         let cloned_llm_client = self.llm_client.clone();
         let cloned_root_id = root_id.to_owned();
-        let llm_response = run_with_cancellation(
+        // race the request against the cancellation token inside the broker,
+        // on cancel the http stream gets dropped and we get back whatever
+        // partial answer streamed until then
+        let llm_response = cloned_llm_client.stream_completion_with_cancellation(
+            llm_properties.api_key().clone(),
+            request,
+            llm_properties.provider().clone(),
+            vec![
+                ("event_type".to_owned(), "session_chat".to_owned()),
+                ("root_id".to_owned(), cloned_root_id),
+            ]
+            .into_iter()
+            .collect(),
+            sender,
             cancellation_token,
-            tokio::spawn(async move {
-                cloned_llm_client
-                    .stream_completion(
-                        llm_properties.api_key().clone(),
-                        request,
-                        llm_properties.provider().clone(),
-                        vec![
-                            ("event_type".to_owned(), "session_chat".to_owned()),
-                            ("root_id".to_owned(), cloned_root_id),
-                        ]
-                        .into_iter()
-                        .collect(),
-                        sender,
-                    )
-                    .await
-            }),
         );
 
         // now poll from the receiver where we are getting deltas
@@ -492,11 +494,12 @@ impl Tool for SessionChatClient {
         let answer_up_until_now = polling_llm_response.await;
 
         match (response, answer_up_until_now) {
-            (Some(Ok(Ok(_))), Ok(response)) => Ok(ToolOutput::context_driven_chat_reply(
+            // a cancelled request also lands here with the partial answer
+            // which streamed until the user hit cancel
+            (Ok(_), Ok(response)) => Ok(ToolOutput::context_driven_chat_reply(
                 SessionChatClientResponse { reply: response },
             )),
-            (Some(Ok(Err(e))), _) => Err(ToolError::LLMClientError(e)),
-            (Some(Err(_)), _) | (None, _) => Err(ToolError::UserCancellation),
+            (Err(e), _) => Err(ToolError::LLMClientError(e)),
             (_, Err(_)) => Err(ToolError::UserCancellation),
         }
     }
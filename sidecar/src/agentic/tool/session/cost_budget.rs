@@ -0,0 +1,174 @@
+//! Cost budgets for agentic sessions. The editor can attach a token and/or
+//! dollar budget to a run, we accumulate the usage reported by the LLM calls
+//! across the session and pause the loop once the budget is crossed so the
+//! user gets asked before we keep burning tokens
+
+use llm_client::clients::types::{LLMClientUsageStatistics, LLMType};
+
+/// Dollars per million input and output tokens for the models we commonly
+/// run, anything unknown gets a conservative estimate so we err towards
+/// pausing early rather than overspending
+fn pricing_for_model(model: &LLMType) -> (f64, f64) {
+    match model {
+        LLMType::ClaudeOpus => (15.0, 75.0),
+        LLMType::ClaudeSonnet => (3.0, 15.0),
+        LLMType::ClaudeHaiku => (0.8, 4.0),
+        LLMType::Gpt4O => (2.5, 10.0),
+        LLMType::Gpt4OMini => (0.15, 0.6),
+        LLMType::O1 | LLMType::O1Preview => (15.0, 60.0),
+        LLMType::O1Mini | LLMType::O3MiniHigh => (1.1, 4.4),
+        LLMType::GeminiPro => (1.25, 5.0),
+        LLMType::GeminiProFlash => (0.075, 0.3),
+        _ => (5.0, 15.0),
+    }
+}
+
+/// Rough dollar cost of the usage when run against the given model, cached
+/// input tokens are billed at a tenth of the input rate which is what the
+/// providers converge on
+pub fn estimated_cost_dollars(model: &LLMType, usage: &LLMClientUsageStatistics) -> f64 {
+    let (input_rate, output_rate) = pricing_for_model(model);
+    let input_tokens = usage.input_tokens().unwrap_or_default() as f64;
+    let cached_input_tokens = usage.cached_input_tokens().unwrap_or_default() as f64;
+    let output_tokens = usage.output_tokens().unwrap_or_default() as f64;
+    (input_tokens * input_rate + cached_input_tokens * input_rate * 0.1
+        + output_tokens * output_rate)
+        / 1_000_000.0
+}
+
+/// Total tokens the usage accounts for across input, cached input and output
+pub fn total_tokens(usage: &LLMClientUsageStatistics) -> u64 {
+    usage.input_tokens().unwrap_or_default() as u64
+        + usage.cached_input_tokens().unwrap_or_default() as u64
+        + usage.output_tokens().unwrap_or_default() as u64
+}
+
+/// The budget the editor attached to the run, both limits are optional and
+/// an absent limit means unbounded
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct CostBudget {
+    #[serde(default)]
+    max_tokens: Option<u64>,
+    #[serde(default)]
+    max_cost_dollars: Option<f64>,
+}
+
+impl CostBudget {
+    pub fn new(max_tokens: Option<u64>, max_cost_dollars: Option<f64>) -> Self {
+        Self {
+            max_tokens,
+            max_cost_dollars,
+        }
+    }
+
+    /// Checks the accumulated usage against the budget and reports how it was
+    /// crossed, None means we are still inside the budget
+    pub fn check(
+        &self,
+        model: &LLMType,
+        usage: &LLMClientUsageStatistics,
+    ) -> Option<CostBudgetExceeded> {
+        let consumed_tokens = total_tokens(usage);
+        let consumed_dollars = estimated_cost_dollars(model, usage);
+        let tokens_exceeded = self
+            .max_tokens
+            .map(|max_tokens| consumed_tokens >= max_tokens)
+            .unwrap_or(false);
+        let dollars_exceeded = self
+            .max_cost_dollars
+            .map(|max_cost_dollars| consumed_dollars >= max_cost_dollars)
+            .unwrap_or(false);
+        if tokens_exceeded || dollars_exceeded {
+            Some(CostBudgetExceeded {
+                consumed_tokens,
+                estimated_cost_dollars: consumed_dollars,
+                max_tokens: self.max_tokens,
+                max_cost_dollars: self.max_cost_dollars,
+            })
+        } else {
+            None
+        }
+    }
+}
+
+/// Everything the editor needs to show the user when asking whether to
+/// continue past the budget
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct CostBudgetExceeded {
+    consumed_tokens: u64,
+    estimated_cost_dollars: f64,
+    max_tokens: Option<u64>,
+    max_cost_dollars: Option<f64>,
+}
+
+impl CostBudgetExceeded {
+    pub fn consumed_tokens(&self) -> u64 {
+        self.consumed_tokens
+    }
+
+    pub fn estimated_cost_dollars(&self) -> f64 {
+        self.estimated_cost_dollars
+    }
+
+    pub fn to_message(&self) -> String {
+        let mut limits = vec![];
+        if let Some(max_tokens) = self.max_tokens {
+            limits.push(format!("{} tokens", max_tokens));
+        }
+        if let Some(max_cost_dollars) = self.max_cost_dollars {
+            limits.push(format!("${:.2}", max_cost_dollars));
+        }
+        format!(
+            "Session used {} tokens (~${:.2}) which crosses the budget of {}",
+            self.consumed_tokens,
+            self.estimated_cost_dollars,
+            limits.join(" / ")
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use llm_client::clients::types::{LLMClientUsageStatistics, LLMType};
+
+    use super::{estimated_cost_dollars, CostBudget};
+
+    #[test]
+    fn test_budget_crossed_on_tokens() {
+        let budget = CostBudget::new(Some(1_000), None);
+        let usage = LLMClientUsageStatistics::new()
+            .set_input_tokens(900)
+            .set_output_tokens(200);
+        let exceeded = budget.check(&LLMType::ClaudeSonnet, &usage);
+        assert!(exceeded.is_some());
+        let exceeded = exceeded.expect("is_some to hold");
+        assert_eq!(exceeded.consumed_tokens(), 1_100);
+        assert!(exceeded.to_message().contains("1000 tokens"));
+    }
+
+    #[test]
+    fn test_budget_crossed_on_dollars() {
+        let budget = CostBudget::new(None, Some(1.0));
+        // a million output tokens on sonnet is 15 dollars
+        let usage = LLMClientUsageStatistics::new().set_output_tokens(1_000_000);
+        assert!(budget.check(&LLMType::ClaudeSonnet, &usage).is_some());
+    }
+
+    #[test]
+    fn test_inside_the_budget() {
+        let budget = CostBudget::new(Some(10_000), Some(5.0));
+        let usage = LLMClientUsageStatistics::new()
+            .set_input_tokens(1_000)
+            .set_output_tokens(100);
+        assert!(budget.check(&LLMType::ClaudeSonnet, &usage).is_none());
+    }
+
+    #[test]
+    fn test_cached_input_tokens_are_discounted() {
+        let fresh = LLMClientUsageStatistics::new().set_input_tokens(1_000_000);
+        let cached = LLMClientUsageStatistics::new().set_cached_input_tokens(1_000_000);
+        let fresh_cost = estimated_cost_dollars(&LLMType::ClaudeSonnet, &fresh);
+        let cached_cost = estimated_cost_dollars(&LLMType::ClaudeSonnet, &cached);
+        assert!(cached_cost < fresh_cost);
+    }
+}
@@ -6,9 +6,11 @@
 
 pub mod ask_followup_question;
 pub mod attempt_completion;
+pub mod cost_budget;
 pub(crate) mod chat;
 pub(crate) mod exchange;
 pub(crate) mod hot_streak;
 pub mod service;
 pub mod session;
 pub mod tool_use_agent;
+pub mod trace_store;
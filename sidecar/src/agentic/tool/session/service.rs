@@ -16,7 +16,10 @@ use crate::{
             ui_event::UIEventWithID,
         },
         tool::{
-            code_edit::code_editor::EditorCommand,
+            code_edit::{
+                code_editor::EditorCommand,
+                fast_path::{detect_fast_path, resolve_fast_path},
+            },
             input::ToolInputPartial,
             plan::service::PlanService,
             r#type::ToolType,
@@ -35,13 +38,18 @@ use crate::{
     user_context::types::UserContext,
 };
 
+use super::cost_budget::CostBudget;
 use super::session::{AideAgentMode, Session};
+use super::trace_store::SessionTraceStore;
 
 /// The session service which takes care of creating the session and manages the storage
 pub struct SessionService {
     tool_box: Arc<ToolBox>,
     symbol_manager: Arc<SymbolManager>,
     running_exchanges: Arc<Mutex<HashMap<String, CancellationToken>>>,
+    /// when set every finished exchange also gets written as a compressed
+    /// chunk so traces can be fetched lazily one exchange at a time
+    trace_store: Option<Arc<SessionTraceStore>>,
 }
 
 impl SessionService {
@@ -50,9 +58,15 @@ impl SessionService {
             tool_box,
             symbol_manager,
             running_exchanges: Arc::new(Mutex::new(HashMap::new())),
+            trace_store: None,
         }
     }
 
+    pub fn with_trace_store(mut self, trace_store: Arc<SessionTraceStore>) -> Self {
+        self.trace_store = Some(trace_store);
+        self
+    }
+
     async fn track_exchange(
         &self,
         session_id: &str,
@@ -146,6 +160,12 @@ impl SessionService {
 
         println!("session_service::session_created");
 
+        // the user can switch models between exchanges, the history carries
+        // over but the token budget gets re-estimated for the new model
+        session = session
+            .switch_model_if_needed(message_properties.llm_properties().llm())
+            .await;
+
         // add human message
         session = session.human_message(
             exchange_id.to_owned(),
@@ -385,6 +405,7 @@ impl SessionService {
         semantic_search: bool,
         mcts_log_directory: Option<String>,
         repo_name: Option<String>,
+        cost_budget: Option<CostBudget>,
         message_properties: SymbolEventMessageProperties,
         is_devtools_context: bool,
     ) -> Result<(), SymbolError> {
@@ -576,6 +597,7 @@ impl SessionService {
                         tool_agent.clone(),
                         root_directory.clone(),
                         exchange_id.clone(),
+                        cost_budget.clone(),
                         message_properties.clone(),
                     )
                     .await;
@@ -598,6 +620,7 @@ impl SessionService {
                     tool_agent,
                     root_directory,
                     exchange_id,
+                    cost_budget,
                     message_properties,
                 )
                 .await;
@@ -622,6 +645,7 @@ impl SessionService {
         tool_agent: ToolUseAgent,
         root_directory: String,
         parent_exchange_id: String,
+        cost_budget: Option<CostBudget>,
         mut message_properties: SymbolEventMessageProperties,
     ) -> Result<(), SymbolError> {
         let mut previous_failure = false;
@@ -630,6 +654,30 @@ impl SessionService {
             let _ = self
                 .save_to_storage(&session, mcts_log_directory.clone())
                 .await;
+
+            // check the accumulated usage against the budget before spending
+            // anything else, pausing here lets the editor ask the user to
+            // confirm continuation
+            if let Some(cost_budget) = cost_budget.as_ref() {
+                let accumulated_usage = session.accumulated_usage_statistics();
+                let model = message_properties.llm_properties().llm().clone();
+                if let Some(exceeded) = cost_budget.check(&model, &accumulated_usage) {
+                    println!(
+                        "session_service::agent_loop::cost_budget_exceeded::({})",
+                        exceeded.to_message()
+                    );
+                    let _ = message_properties.ui_sender().send(
+                        UIEventWithID::cost_budget_exceeded(
+                            session.session_id().to_owned(),
+                            parent_exchange_id.to_owned(),
+                            exceeded.consumed_tokens(),
+                            exceeded.estimated_cost_dollars(),
+                            exceeded.to_message(),
+                        ),
+                    );
+                    return Ok(());
+                }
+            }
             let tool_exchange_id = self
                 .tool_box
                 .create_new_exchange(session.session_id().to_owned(), message_properties.clone())
@@ -979,6 +1027,13 @@ impl SessionService {
             .set_request_id(edit_exchange_id)
             .set_cancellation_token(cancellation_token);
 
+        // trivially mappable instructions (rename x to y, delete this)
+        // skip the planning pipeline entirely, the full flow spends tens
+        // of seconds on what an lsp rename or a direct apply does instantly
+        let fast_path_edit = detect_fast_path(&edit_request).and_then(|fast_path_edit| {
+            resolve_fast_path(fast_path_edit, file_content.contents_ref(), &selection_range)
+        });
+
         // add an exchange that we are going to perform anchored edits
         session = session.anchored_edit(
             exchange_id.to_owned(),
@@ -990,14 +1045,32 @@ impl SessionService {
         );
 
         // Now we can start editing the selection over here
-        session = session
-            .perform_anchored_edit(
-                exchange_id,
-                scratch_pad_agent,
-                aide_rules,
-                message_properties,
-            )
-            .await?;
+        session = match fast_path_edit {
+            Some(resolved_edit) => {
+                println!(
+                    "session_service::code_edit::anchored::fast_path({:?})",
+                    &resolved_edit
+                );
+                session
+                    .perform_fast_path_anchored_edit(
+                        exchange_id,
+                        resolved_edit,
+                        self.tool_box.clone(),
+                        message_properties,
+                    )
+                    .await?
+            }
+            None => {
+                session
+                    .perform_anchored_edit(
+                        exchange_id,
+                        scratch_pad_agent,
+                        aide_rules,
+                        message_properties,
+                    )
+                    .await?
+            }
+        };
 
         // save the session to the disk
         self.save_to_storage(&session, None).await?;
@@ -1020,6 +1093,27 @@ impl SessionService {
         Ok(())
     }
 
+    /// Forks a stored session at an exchange into a new branch: the branch
+    /// file shares the exchange prefix with the parent and records where it
+    /// split off, the original thread stays untouched
+    pub async fn fork_session(
+        &self,
+        storage_path: String,
+        fork_exchange_id: &str,
+        new_session_id: String,
+        new_storage_path: String,
+    ) -> Result<(), SymbolError> {
+        println!(
+            "session_service::fork_session::fork_exchange_id({})::new_session_id({})",
+            fork_exchange_id, &new_session_id
+        );
+        let session = self.load_from_storage(storage_path).await?;
+        let forked_session =
+            session.fork_at_exchange(new_session_id, new_storage_path, fork_exchange_id)?;
+        self.save_to_storage(&forked_session, None).await?;
+        Ok(())
+    }
+
     /// Provied feedback to the exchange
     ///
     /// We can react to this later on and send out either another exchange or something else
@@ -1157,6 +1251,29 @@ impl SessionService {
             .map_err(|e| SymbolError::IOError(e))?;
         // flush forces the OS to flush any in-memory semantics to the disk
         file.flush().await.map_err(|e| SymbolError::IOError(e))?;
+
+        // mirror the exchanges into the compressed trace store, settled
+        // chunks which are already on disk get skipped so we only pay for
+        // the new ones. The last exchange is still streaming so it always
+        // gets rewritten
+        if let Some(trace_store) = self.trace_store.as_ref() {
+            let exchange_count = session.exchange_list().len();
+            for (index, exchange) in session.exchange_list().iter().enumerate() {
+                let is_last = index + 1 == exchange_count;
+                if !is_last
+                    && trace_store
+                        .has_exchange(session.session_id(), exchange.exchange_id())
+                        .await
+                {
+                    continue;
+                }
+                if let Ok(payload) = serde_json::to_string(exchange) {
+                    let _ = trace_store
+                        .store_exchange(session.session_id(), exchange.exchange_id(), payload)
+                        .await;
+                }
+            }
+        }
         Ok(())
     }
 
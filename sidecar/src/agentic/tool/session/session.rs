@@ -7,7 +7,7 @@ use std::{
 };
 
 use futures::StreamExt;
-use llm_client::clients::types::LLMType;
+use llm_client::clients::types::{LLMClientUsageStatistics, LLMType};
 use tokio::io::AsyncWriteExt;
 
 use crate::{
@@ -28,6 +28,7 @@ use crate::{
             ui_event::UIEventWithID,
         },
         tool::{
+            code_edit::fast_path::ResolvedFastPathEdit,
             devtools::screenshot::RequestScreenshotInput,
             file::semantic_search::SemanticSearchRequest,
             helpers::diff_recent_changes::DiffFileContent,
@@ -517,6 +518,10 @@ impl Exchange {
         self.exchange_state = ExchangeState::Cancelled;
     }
 
+    fn set_compressed(&mut self) {
+        self.is_compressed = true;
+    }
+
     /// Convert the exchange to a session chat message so we can send it over
     /// for inference
     ///
@@ -750,6 +755,26 @@ impl Exchange {
     }
 }
 
+/// Context window in tokens for the models we commonly see, anything we do
+/// not know about gets a conservative default
+fn context_window_for_model(model: &LLMType) -> usize {
+    match model {
+        LLMType::ClaudeOpus | LLMType::ClaudeSonnet | LLMType::ClaudeHaiku => 200_000,
+        LLMType::GeminiPro | LLMType::GeminiProFlash => 1_000_000,
+        LLMType::Gpt4O | LLMType::Gpt4OMini | LLMType::Gpt4Turbo => 128_000,
+        LLMType::O1 | LLMType::O1Preview | LLMType::O1Mini => 128_000,
+        _ => 128_000,
+    }
+}
+
+/// The approximation the tokenizer uses for fast counting: each token is
+/// around 3/4th of a word
+pub(crate) fn approximate_token_count(message: &str) -> usize {
+    let words = message.split_whitespace().count();
+    let new_line_count = message.lines().count();
+    ((words + new_line_count) * 4) / 3
+}
+
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct Session {
     session_id: String,
@@ -761,6 +786,17 @@ pub struct Session {
     tools: Vec<ToolType>,
     #[serde(default)]
     action_nodes: Vec<ActionNode>,
+    /// the model which served the last exchange, older sessions on disk do
+    /// not have this recorded
+    #[serde(default)]
+    last_model_used: Option<LLMType>,
+    /// the session this one was branched off of, None for a root session
+    #[serde(default)]
+    parent_session_id: Option<String>,
+    /// the exchange in the parent where the branch starts, everything up to
+    /// and including it is shared history and cannot be undone from here
+    #[serde(default)]
+    forked_at_exchange_id: Option<String>,
 }
 
 impl Session {
@@ -781,9 +817,46 @@ impl Session {
             global_running_user_context,
             tools,
             action_nodes: vec![],
+            last_model_used: None,
+            parent_session_id: None,
+            forked_at_exchange_id: None,
         }
     }
 
+    /// Forks the session at an exchange: the branch keeps every exchange up
+    /// to and including the fork point under a fresh session id and storage
+    /// path, so an alternative instruction can be tried without touching
+    /// the original thread
+    pub fn fork_at_exchange(
+        mut self,
+        new_session_id: String,
+        new_storage_path: String,
+        fork_exchange_id: &str,
+    ) -> Result<Self, SymbolError> {
+        let fork_index = self
+            .exchanges
+            .iter()
+            .position(|exchange| exchange.exchange_id == fork_exchange_id)
+            .ok_or(SymbolError::SymbolError(format!(
+                "no exchange with id {} to fork at",
+                fork_exchange_id
+            )))?;
+        self.exchanges.truncate(fork_index + 1);
+        self.parent_session_id = Some(self.session_id);
+        self.forked_at_exchange_id = Some(fork_exchange_id.to_owned());
+        self.session_id = new_session_id;
+        self.storage_path = new_storage_path;
+        Ok(self)
+    }
+
+    pub fn parent_session_id(&self) -> Option<&str> {
+        self.parent_session_id.as_deref()
+    }
+
+    pub fn forked_at_exchange_id(&self) -> Option<&str> {
+        self.forked_at_exchange_id.as_deref()
+    }
+
     pub fn last_reasoning_node_if_any(&self) -> Option<usize> {
         self.action_nodes
             .iter()
@@ -812,10 +885,73 @@ impl Session {
         self.action_nodes.as_slice()
     }
 
+    /// Sums the token usage reported by every LLM call the session has made,
+    /// this is what the cost budget gets checked against
+    pub fn accumulated_usage_statistics(&self) -> LLMClientUsageStatistics {
+        self.action_nodes
+            .iter()
+            .filter_map(|action_node| action_node.get_llm_usage_statistics())
+            .fold(LLMClientUsageStatistics::new(), |accumulated, usage| {
+                accumulated.add(usage)
+            })
+    }
+
     pub fn reset_exchanges(&mut self) {
         self.exchanges = vec![];
     }
 
+    pub fn last_model_used(&self) -> Option<&LLMType> {
+        self.last_model_used.as_ref()
+    }
+
+    /// Handles the user switching the model for an ongoing session. The
+    /// exchanges are stored in a provider agnostic format so the history
+    /// carries over as is, what changes is the token budget: we re-estimate
+    /// the footprint of the conversation against the new model's context
+    /// window and compress the oldest exchanges which no longer fit
+    pub async fn switch_model_if_needed(mut self, new_model: &LLMType) -> Self {
+        let previous_model = self.last_model_used.replace(new_model.clone());
+        let switched = previous_model
+            .map(|previous_model| &previous_model != new_model)
+            .unwrap_or(false);
+        if !switched {
+            return self;
+        }
+        println!(
+            "session::switch_model_if_needed::session_id({})::new_model({:?})",
+            &self.session_id, new_model
+        );
+        // keep a quarter of the context window free for the reply and the
+        // rest of the prompt scaffolding
+        let token_budget = (context_window_for_model(new_model) * 3) / 4;
+        let mut token_footprints = vec![];
+        for exchange in self.exchanges.iter() {
+            let tokens = match exchange.to_conversation_message(false).await {
+                Some(conversation_message) => {
+                    approximate_token_count(conversation_message.message())
+                }
+                None => 0,
+            };
+            token_footprints.push(tokens);
+        }
+        // walk backwards keeping the most recent exchanges, everything past
+        // the budget gets compressed
+        let mut running_tokens = 0;
+        let mut keep_from_index = 0;
+        for (index, tokens) in token_footprints.iter().enumerate().rev() {
+            running_tokens = running_tokens + tokens;
+            if running_tokens > token_budget {
+                keep_from_index = index + 1;
+                break;
+            }
+        }
+        self.exchanges
+            .iter_mut()
+            .take(keep_from_index)
+            .for_each(|exchange| exchange.set_compressed());
+        self
+    }
+
     /// Updates the tools which are present in the session
     pub fn set_tools(mut self, tools: Vec<ToolType>) -> Self {
         self.tools = tools;
@@ -834,6 +970,23 @@ impl Session {
         self.exchanges.len()
     }
 
+    /// The user queries which drove this session in order, this is what the
+    /// changelog generation summarises the session by
+    pub fn user_queries(&self) -> Vec<String> {
+        self.exchanges
+            .iter()
+            .filter_map(|exchange| match &exchange.exchange_type {
+                ExchangeType::HumanChat(human_chat) => Some(human_chat.query.to_owned()),
+                ExchangeType::Plan(plan) => Some(plan.query.to_owned()),
+                ExchangeType::Edit(edit) => match &edit.information {
+                    ExchangeEditInformation::Agentic(agentic) => Some(agentic.query.to_owned()),
+                    ExchangeEditInformation::Anchored(anchored) => Some(anchored.query.to_owned()),
+                },
+                _ => None,
+            })
+            .collect()
+    }
+
     pub fn exchanges_not_compressed(&self) -> usize {
         self.exchanges
             .iter()
@@ -842,6 +995,10 @@ impl Session {
             .len()
     }
 
+    pub fn exchange_list(&self) -> &[Exchange] {
+        self.exchanges.as_slice()
+    }
+
     fn find_exchange_by_id(&self, exchange_id: &str) -> Option<&Exchange> {
         self.exchanges
             .iter()
@@ -1064,6 +1221,26 @@ impl Session {
         mut self,
         exchange_id: &str,
     ) -> Result<Self, SymbolError> {
+        // exchanges inherited from the parent belong to the original thread,
+        // undoing them from a branch would rewrite the shared prefix
+        if let Some(forked_at_exchange_id) = self.forked_at_exchange_id.as_ref() {
+            let fork_index = self
+                .exchanges
+                .iter()
+                .position(|exchange| &exchange.exchange_id == forked_at_exchange_id);
+            let target_index = self
+                .exchanges
+                .iter()
+                .position(|exchange| exchange.exchange_id == exchange_id);
+            if let (Some(fork_index), Some(target_index)) = (fork_index, target_index) {
+                if target_index <= fork_index {
+                    return Err(SymbolError::SymbolError(format!(
+                        "exchange {} is part of the prefix shared with the parent session and cannot be undone from this branch",
+                        exchange_id
+                    )));
+                }
+            }
+        }
         // keep grabbing the exchanges until we hit the exchange_id we are interested in
         // over  here, that become our new exchange
         let new_exchanges = self
@@ -1297,6 +1474,17 @@ impl Session {
         let output = tool_use_agent.invoke(tool_use_agent_input).await;
         let usage_stats = output.as_ref().map(|output| output.usage_statistics()).ok();
 
+        // surface the per-step token consumption to the editor
+        if let Some(usage_stats) = usage_stats.clone() {
+            let _ = message_properties
+                .ui_sender()
+                .send(UIEventWithID::token_usage(
+                    message_properties.root_request_id().to_owned(),
+                    message_properties.request_id_str().to_owned(),
+                    usage_stats,
+                ));
+        }
+
         // we match on the output type
         match output.map(|output| output.output_type()) {
             Ok(ToolUseAgentOutputType::Success((tool_input_partial, thinking))) => {
@@ -2086,6 +2274,95 @@ impl Session {
         Ok(self)
     }
 
+    /// Executes a trivially mapped anchored edit without going through the
+    /// planning pipeline, a rename runs through the lsp and a delete is a
+    /// direct apply. The exchange bookkeeping matches perform_anchored_edit
+    /// so the editor cannot tell which path produced the edit
+    pub async fn perform_fast_path_anchored_edit(
+        mut self,
+        parent_exchange_id: String,
+        resolved_edit: ResolvedFastPathEdit,
+        tool_box: Arc<ToolBox>,
+        message_properties: SymbolEventMessageProperties,
+    ) -> Result<Self, SymbolError> {
+        let last_exchange = self.last_exchange().cloned();
+        if let Some(Exchange {
+            exchange_id: _,
+            exchange_type:
+                ExchangeType::Edit(ExchangeTypeEdit {
+                    information:
+                        ExchangeEditInformation::Anchored(ExchangeEditInformationAnchored {
+                            query: _,
+                            fs_file_path,
+                            range,
+                            selection_context: _,
+                        }),
+                    ..
+                }),
+            exchange_state: _,
+            is_compressed: _,
+        }) = last_exchange
+        {
+            let _ = message_properties
+                .ui_sender()
+                .send(UIEventWithID::edits_started_in_exchange(
+                    message_properties.root_request_id().to_owned(),
+                    message_properties.request_id_str().to_owned(),
+                    vec![fs_file_path.to_owned()],
+                ));
+            let reply = match resolved_edit {
+                ResolvedFastPathEdit::Rename {
+                    position,
+                    old_name,
+                    new_name,
+                } => {
+                    tool_box
+                        .rename_symbol(
+                            &fs_file_path,
+                            position,
+                            &new_name,
+                            message_properties.clone(),
+                        )
+                        .await?;
+                    format!(
+                        "Renamed `{}` to `{}` through the language server",
+                        old_name, new_name
+                    )
+                }
+                ResolvedFastPathEdit::DeleteSelection => {
+                    tool_box
+                        .apply_edits_to_editor(
+                            &fs_file_path,
+                            &range,
+                            "",
+                            true,
+                            message_properties.clone(),
+                        )
+                        .await?;
+                    "Deleted the selected block".to_owned()
+                }
+            };
+            self.exchanges.push(Exchange::agent_edits_reply(
+                parent_exchange_id,
+                message_properties.request_id_str().to_owned(),
+                reply,
+            ));
+            let _ = message_properties
+                .ui_sender()
+                .send(UIEventWithID::edits_marked_complete(
+                    message_properties.root_request_id().to_owned(),
+                    message_properties.request_id_str().to_owned(),
+                ));
+            let _ = message_properties
+                .ui_sender()
+                .send(UIEventWithID::request_review(
+                    message_properties.root_request_id().to_owned(),
+                    message_properties.request_id_str().to_owned(),
+                ));
+        }
+        Ok(self)
+    }
+
     /// Grab the references over here and
     pub async fn hot_streak_message(
         mut self,
@@ -2338,7 +2615,7 @@ impl Session {
                     ToolInput::RunTests(TestRunnerRequest::new(fs_file_paths.to_vec(), editor_url));
                 let response = tool_box
                     .tools()
-                    .invoke(input)
+                    .invoke_with_cancellation(input, message_properties.cancellation_token())
                     .await
                     .map_err(|e| SymbolError::ToolError(e))?;
 
@@ -2573,7 +2850,7 @@ impl Session {
                 let input = ToolInput::FindFiles(find_files_input);
                 let response = tool_box
                     .tools()
-                    .invoke(input)
+                    .invoke_with_cancellation(input, message_properties.cancellation_token())
                     .await
                     .map_err(|e| SymbolError::ToolError(e))?;
                 let find_files_output = response
@@ -2621,7 +2898,7 @@ impl Session {
                 let input = ToolInput::ListFiles(list_files_input);
                 let response = tool_box
                     .tools()
-                    .invoke(input)
+                    .invoke_with_cancellation(input, message_properties.cancellation_token())
                     .await
                     .map_err(|e| SymbolError::ToolError(e))?;
                 let list_files_output = response
@@ -2672,7 +2949,7 @@ impl Session {
                 let input = ToolInput::OpenFile(request);
                 let response = tool_box
                     .tools()
-                    .invoke(input)
+                    .invoke_with_cancellation(input, message_properties.cancellation_token())
                     .await
                     .map_err(|e| SymbolError::ToolError(e))?
                     .get_file_open_response()
@@ -2717,7 +2994,7 @@ impl Session {
 
                 let mut semantic_search_response = tool_box
                     .tools()
-                    .invoke(input)
+                    .invoke_with_cancellation(input, message_properties.cancellation_token())
                     .await
                     .map_err(|e| SymbolError::ToolError(e))?
                     .get_semantic_search_response()
@@ -2789,7 +3066,7 @@ reason: {}"#,
                 let input = ToolInput::SearchFileContentWithRegex(request);
                 let response = tool_box
                     .tools()
-                    .invoke(input)
+                    .invoke_with_cancellation(input, message_properties.cancellation_token())
                     .await
                     .map_err(|e| SymbolError::ToolError(e))?
                     .get_search_file_content_with_regex()
@@ -2829,50 +3106,72 @@ reason: {}"#,
                 println!("terminal command: {}", terminal_command.command());
                 let command = terminal_command.command().to_owned();
                 let wait_for_exit = terminal_command.wait_for_exit().to_owned();
-                let request =
-                    TerminalInput::new(command, message_properties.editor_url(), wait_for_exit);
-                let input = ToolInput::TerminalCommand(request);
-                let tool_output = tool_box
-                    .tools()
-                    .invoke(input)
-                    .await
-                    .map_err(|e| SymbolError::ToolError(e))?
-                    .terminal_command()
-                    .ok_or(SymbolError::WrongToolOutput)?;
-
-                let output = tool_output.output().to_owned();
-                let mut output_lines: Vec<String> =
-                    output.lines().map(|line| line.to_string()).collect();
+                let output = if !wait_for_exit {
+                    // long running command: spawn it without waiting for exit
+                    // and poll the pending output with check-ins, handing the
+                    // agent an actionable check-in instead of an empty result
+                    tool_box
+                        .use_terminal_command_with_checkins(
+                            &command,
+                            10,
+                            120,
+                            message_properties.clone(),
+                        )
+                        .await?
+                        .to_checkin_message()
+                } else {
+                    let request =
+                        TerminalInput::new(command, message_properties.editor_url(), wait_for_exit);
+                    let input = ToolInput::TerminalCommand(request);
+                    let tool_output = tool_box
+                        .tools()
+                        .invoke_with_cancellation(input, message_properties.cancellation_token())
+                        .await
+                        .map_err(|e| SymbolError::ToolError(e))?
+                        .terminal_command()
+                        .ok_or(SymbolError::WrongToolOutput)?;
+
+                    let output = tool_output.output().to_owned();
+                    let mut output_lines: Vec<String> =
+                        output.lines().map(|line| line.to_string()).collect();
+
+                    // only keep 1k lines, hand waving this into the world 🪄
+                    let max_lines = 1_000;
+                    if output_lines.len() > max_lines {
+                        let start_index = output_lines.len() - max_lines;
+                        output_lines = output_lines.split_off(start_index);
+                        output_lines.insert(0, "[... previous output truncated ...]".to_owned());
+                    }
 
-                // only keep 1k lines, hand waving this into the world 🪄
-                let max_lines = 1_000;
-                if output_lines.len() > max_lines {
-                    let start_index = output_lines.len() - max_lines;
-                    output_lines = output_lines.split_off(start_index);
-                    output_lines.insert(0, "[... previous output truncated ...]".to_owned());
-                }
+                    // Process each line to add truncation indicators
+                    let max_chars = 500;
+                    let truncation_suffix = "... truncated";
+                    let suffix_len = truncation_suffix.chars().count();
 
-                // Process each line to add truncation indicators
-                let max_chars = 500;
-                let truncation_suffix = "... truncated";
-                let suffix_len = truncation_suffix.chars().count();
+                    for line in &mut output_lines {
+                        let char_count = line.chars().count();
+                        if char_count > max_chars {
+                            let take_chars = max_chars.saturating_sub(suffix_len);
+                            let mut truncated = String::with_capacity(max_chars);
 
-                for line in &mut output_lines {
-                    let char_count = line.chars().count();
-                    if char_count > max_chars {
-                        let take_chars = max_chars.saturating_sub(suffix_len);
-                        let mut truncated = String::with_capacity(max_chars);
+                            // Add truncated content
+                            truncated.extend(line.chars().take(take_chars));
 
-                        // Add truncated content
-                        truncated.extend(line.chars().take(take_chars));
+                            // Add suffix
+                            truncated.push_str(&truncation_suffix);
 
-                        // Add suffix
-                        truncated.push_str(&truncation_suffix);
+                            // Replace original line
+                            *line = truncated;
+                        }
+                    }
 
-                        // Replace original line
-                        *line = truncated;
+                    // append the structured exit information so the exit code
+                    // does not get lost on this path
+                    if let Some(exit_info) = tool_output.exit_info_line() {
+                        output_lines.push(exit_info);
                     }
-                }
+                    output_lines.join("\n")
+                };
 
                 // we have the tool output over here
                 if let Some(action_node) = self.action_nodes.last_mut() {
@@ -2959,7 +3258,7 @@ reason: {}"#,
                 let input = ToolInput::RequestScreenshot(request_screenshot_input);
                 let response = tool_box
                     .tools()
-                    .invoke(input)
+                    .invoke_with_cancellation(input, message_properties.cancellation_token())
                     .await
                     .map_err(|e| SymbolError::ToolError(e))?;
                 let request_screenshot_output = response
@@ -23,9 +23,8 @@ use crate::{
             identifier::LLMProperties, ui_event::UIEventWithID,
         },
         tool::{
-            code_edit::{code_editor::CodeEditorParameters, types::CodeEditingPartialRequest},
+            code_edit::types::CodeEditingPartialRequest,
             devtools::screenshot::RequestScreenshotInputPartial,
-            errors::ToolError,
             file::semantic_search::SemanticSearchParametersPartial,
             helpers::cancellation_future::run_with_cancellation,
             input::ToolInputPartial,
@@ -1157,52 +1156,9 @@ You accomplish a given task iteratively, breaking it down into clear steps and w
             for (tool_type, tool_input) in tool_inputs.into_iter() {
                 let tool_use_id = tool_input.0;
                 let tool_input = tool_input.1;
-                let tool_input = match tool_type.as_ref() {
-                    "list_files" => ToolInputPartial::ListFiles(
-                        serde_json::from_str::<ListFilesInputPartial>(&tool_input).map_err(
-                            |_e| SymbolError::ToolError(ToolError::SerdeConversionFailed),
-                        )?,
-                    ),
-                    "search_files" => ToolInputPartial::SearchFileContentWithRegex(
-                        serde_json::from_str::<SearchFileContentInputPartial>(&tool_input)
-                            .map_err(|_e| {
-                                SymbolError::ToolError(ToolError::SerdeConversionFailed)
-                            })?,
-                    ),
-                    "read_file" => ToolInputPartial::OpenFile(
-                        serde_json::from_str::<OpenFileRequestPartial>(&tool_input).map_err(
-                            |_e| SymbolError::ToolError(ToolError::SerdeConversionFailed),
-                        )?,
-                    ),
-                    "execute_command" => ToolInputPartial::TerminalCommand({
-                        serde_json::from_str::<TerminalInputPartial>(&tool_input)
-                            .map_err(|_e| SymbolError::ToolError(ToolError::SerdeConversionFailed))?
-                            // well gotta do the hard things sometimes right?
-                            // or the dumb things
-                            .sanitise_for_repro_script()
-                    }),
-                    "attempt_completion" => ToolInputPartial::AttemptCompletion(
-                        serde_json::from_str::<AttemptCompletionClientRequest>(&tool_input)
-                            .map_err(|_e| {
-                                SymbolError::ToolError(ToolError::SerdeConversionFailed)
-                            })?,
-                    ),
-                    "test_runner" => ToolInputPartial::TestRunner(
-                        serde_json::from_str::<TestRunnerRequestPartial>(&tool_input).map_err(
-                            |_e| SymbolError::ToolError(ToolError::SerdeConversionFailed),
-                        )?,
-                    ),
-                    "str_replace_editor" => ToolInputPartial::CodeEditorParameters(
-                        serde_json::from_str::<CodeEditorParameters>(&tool_input).map_err(|e| {
-                            println!("str_replace_editor::error::{:?}", e);
-                            SymbolError::ToolError(ToolError::SerdeConversionFailed)
-                        })?,
-                    ),
-                    _ => {
-                        println!("unknow tool found: {}", tool_type);
-                        return Err(SymbolError::WrongToolOutput);
-                    }
-                };
+                let tool_input =
+                    ToolInputPartial::from_native_tool_call(tool_type.as_ref(), &tool_input)
+                        .map_err(SymbolError::ToolError)?;
                 tool_inputs_parsed.push((tool_use_id, tool_input));
             }
 
@@ -0,0 +1,135 @@
+//! Compressed chunked storage for session traces. Long sessions on big
+//! repos accumulate hundreds of MB of exchange payloads, so each exchange
+//! gets written as its own zstd-compressed chunk and only the chunks a
+//! caller asks for ever get decompressed, memory stays flat no matter how
+//! long the session runs
+
+use std::path::PathBuf;
+
+/// zstd level 3 is the library default, plenty for json traces which are
+/// mostly repeated keys and code snippets
+const COMPRESSION_LEVEL: i32 = 3;
+
+/// Stores each exchange of a session as its own zstd-compressed chunk on
+/// disk, chunks load lazily and independently of each other
+pub struct SessionTraceStore {
+    root: PathBuf,
+}
+
+impl SessionTraceStore {
+    pub fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+
+    fn session_directory(&self, session_id: &str) -> PathBuf {
+        self.root.join(session_id)
+    }
+
+    fn chunk_path(&self, session_id: &str, exchange_id: &str) -> PathBuf {
+        self.session_directory(session_id)
+            .join(format!("{}.json.zst", exchange_id))
+    }
+
+    /// Writes one exchange as a compressed chunk, overwriting any previous
+    /// version of the same exchange
+    pub async fn store_exchange(
+        &self,
+        session_id: &str,
+        exchange_id: &str,
+        payload: String,
+    ) -> Result<(), std::io::Error> {
+        let session_directory = self.session_directory(session_id);
+        tokio::fs::create_dir_all(&session_directory).await?;
+        let chunk_path = self.chunk_path(session_id, exchange_id);
+        // compression is cpu work, keep it off the async runtime
+        let compressed = tokio::task::spawn_blocking(move || {
+            zstd::encode_all(payload.as_bytes(), COMPRESSION_LEVEL)
+        })
+        .await??;
+        tokio::fs::write(chunk_path, compressed).await
+    }
+
+    /// True when the exchange already has a chunk on disk, used to skip
+    /// re-compressing exchanges which never change after they finish
+    pub async fn has_exchange(&self, session_id: &str, exchange_id: &str) -> bool {
+        tokio::fs::try_exists(self.chunk_path(session_id, exchange_id))
+            .await
+            .unwrap_or(false)
+    }
+
+    /// Loads and decompresses a single exchange, the rest of the session
+    /// stays untouched on disk
+    pub async fn load_exchange(&self, session_id: &str, exchange_id: &str) -> Option<String> {
+        let compressed = tokio::fs::read(self.chunk_path(session_id, exchange_id))
+            .await
+            .ok()?;
+        let decompressed = tokio::task::spawn_blocking(move || {
+            zstd::decode_all(compressed.as_slice())
+        })
+        .await
+        .ok()?
+        .ok()?;
+        String::from_utf8(decompressed).ok()
+    }
+
+    /// Exchange ids which have chunks stored for this session, sorted so
+    /// the output is stable
+    pub async fn list_exchanges(&self, session_id: &str) -> Vec<String> {
+        let mut exchange_ids = vec![];
+        if let Ok(mut read_dir) = tokio::fs::read_dir(self.session_directory(session_id)).await {
+            while let Ok(Some(entry)) = read_dir.next_entry().await {
+                let file_name = entry.file_name().to_string_lossy().to_string();
+                if let Some(exchange_id) = file_name.strip_suffix(".json.zst") {
+                    exchange_ids.push(exchange_id.to_owned());
+                }
+            }
+        }
+        exchange_ids.sort();
+        exchange_ids
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SessionTraceStore;
+
+    #[tokio::test]
+    async fn test_store_and_load_roundtrip() {
+        let temp_dir = tempfile::tempdir().expect("tempdir to work");
+        let store = SessionTraceStore::new(temp_dir.path().to_path_buf());
+        let payload = r#"{"exchange_id": "0", "query": "add a test"}"#.to_owned();
+        store
+            .store_exchange("session-1", "0", payload.clone())
+            .await
+            .expect("storing to work");
+        assert!(store.has_exchange("session-1", "0").await);
+        let loaded = store
+            .load_exchange("session-1", "0")
+            .await
+            .expect("loading to work");
+        assert_eq!(loaded, payload);
+    }
+
+    #[tokio::test]
+    async fn test_missing_exchange_is_none() {
+        let temp_dir = tempfile::tempdir().expect("tempdir to work");
+        let store = SessionTraceStore::new(temp_dir.path().to_path_buf());
+        assert!(!store.has_exchange("session-1", "42").await);
+        assert!(store.load_exchange("session-1", "42").await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_list_exchanges_is_sorted() {
+        let temp_dir = tempfile::tempdir().expect("tempdir to work");
+        let store = SessionTraceStore::new(temp_dir.path().to_path_buf());
+        for exchange_id in ["2", "0", "1"] {
+            store
+                .store_exchange("session-1", exchange_id, "{}".to_owned())
+                .await
+                .expect("storing to work");
+        }
+        assert_eq!(store.list_exchanges("session-1").await, vec!["0", "1", "2"]);
+        // other sessions stay separate
+        assert!(store.list_exchanges("session-2").await.is_empty());
+    }
+}
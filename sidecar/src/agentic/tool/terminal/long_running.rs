@@ -0,0 +1,231 @@
+//! Support for long running terminal commands (builds, full test suites)
+//! where we do not want to block the whole turn until the command exits.
+//! The command is spawned without waiting for exit and we poll the pending
+//! output on a cadence, at every check-in we look at the partial output and
+//! decide if we should keep waiting, act on an early failure or give up on
+//! the command entirely.
+
+/// Upper bound on how much of the partial output we carry into a check-in,
+/// we keep the tail since that is where build and test failures show up
+pub const CHECKIN_OUTPUT_BUDGET: usize = 10_000;
+
+/// Markers in the partial output which indicate the command has already hit
+/// a failure and there is no point waiting for it to run to completion
+const EARLY_FAILURE_MARKERS: &[&str] = &[
+    "error[",
+    "error:",
+    "FAILED",
+    "panicked at",
+    "Traceback (most recent call last)",
+    "Segmentation fault",
+];
+
+/// The decision taken at a check-in while a long running command is still
+/// executing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckinDecision {
+    /// Nothing interesting in the output yet, poll again after the interval
+    KeepWaiting,
+    /// The output is already actionable (a failure or a command which hit
+    /// its steady state), surface it to the agent now
+    ActOnOutput,
+    /// We have waited past the allowed budget, the command should be killed
+    Kill,
+}
+
+impl CheckinDecision {
+    /// Parses the decision out of an llm check-in response, we look for the
+    /// decision tag anywhere in the output so reasoning before it is fine.
+    /// Returns None when the response has no recognisable decision so the
+    /// caller can fall back to the marker heuristic
+    pub fn from_llm_response(response: &str) -> Option<Self> {
+        let response = response.to_lowercase();
+        let decision = response
+            .split("<decision>")
+            .nth(1)
+            .and_then(|rest| rest.split("</decision>").next())
+            .map(|decision| decision.trim().to_owned())?;
+        match decision.as_str() {
+            "wait" => Some(CheckinDecision::KeepWaiting),
+            "act" => Some(CheckinDecision::ActOnOutput),
+            "kill" => Some(CheckinDecision::Kill),
+            _ => None,
+        }
+    }
+
+    /// Decides what to do with a command given its partial output and how
+    /// long it has been running for, this is the fallback when the llm
+    /// check-in fails or comes back with something unparseable
+    pub fn from_partial_output(
+        partial_output: &str,
+        elapsed_seconds: u64,
+        max_wait_seconds: u64,
+    ) -> Self {
+        if EARLY_FAILURE_MARKERS
+            .iter()
+            .any(|marker| partial_output.contains(marker))
+        {
+            return CheckinDecision::ActOnOutput;
+        }
+        if elapsed_seconds >= max_wait_seconds {
+            return CheckinDecision::Kill;
+        }
+        CheckinDecision::KeepWaiting
+    }
+}
+
+/// Builds the prompt for an llm check-in on a long running command, the
+/// model sees the tail of the output and decides between waiting, acting on
+/// what is already there (a failure or a server which reached steady state)
+/// or killing a command which looks stuck
+pub fn checkin_prompt(
+    command: &str,
+    partial_output: &str,
+    elapsed_seconds: u64,
+    max_wait_seconds: u64,
+) -> String {
+    format!(
+        r#"A terminal command is still running and you are checking in on it.
+Command: `{}`
+Running for: {}s (will be killed after {}s)
+Output so far:
+{}
+
+Decide what to do:
+- wait: nothing actionable yet, check again later
+- act: the output is already actionable, either it shows a failure or the command has reached its steady state (e.g. a server is up and listening)
+- kill: the command looks stuck and waiting longer will not help
+
+Reply with your decision as <decision>wait</decision>, <decision>act</decision> or <decision>kill</decision>."#,
+        command,
+        elapsed_seconds,
+        max_wait_seconds,
+        tail_for_checkin(partial_output, CHECKIN_OUTPUT_BUDGET),
+    )
+}
+
+/// Status of a long running command when polling stopped, this is what gets
+/// shown to the agent so it can decide on the next action. Note that killing
+/// the process itself is left to the agent since the editor owns the spawned
+/// process
+#[derive(Debug)]
+pub struct LongRunningCommandStatus {
+    command: String,
+    partial_output: String,
+    elapsed_seconds: u64,
+    decision: CheckinDecision,
+}
+
+impl LongRunningCommandStatus {
+    pub fn new(
+        command: String,
+        partial_output: String,
+        elapsed_seconds: u64,
+        decision: CheckinDecision,
+    ) -> Self {
+        Self {
+            command,
+            partial_output,
+            elapsed_seconds,
+            decision,
+        }
+    }
+
+    pub fn partial_output(&self) -> &str {
+        &self.partial_output
+    }
+
+    pub fn decision(&self) -> CheckinDecision {
+        self.decision
+    }
+
+    /// Formats the check-in so it reads like any other tool output when we
+    /// put it infront of the LLM
+    pub fn to_checkin_message(&self) -> String {
+        let status_line = match self.decision {
+            CheckinDecision::KeepWaiting => "still running".to_owned(),
+            CheckinDecision::ActOnOutput => {
+                "still running but the output is already actionable".to_owned()
+            }
+            CheckinDecision::Kill => format!(
+                "still running after {} seconds, consider killing it",
+                self.elapsed_seconds
+            ),
+        };
+        format!(
+            r#"Command `{}` is {} ({}s elapsed). Partial output so far:
+{}"#,
+            self.command, status_line, self.elapsed_seconds, self.partial_output
+        )
+    }
+}
+
+/// Clamps the partial output to the check-in budget, keeping the tail of the
+/// output since failures are almost always at the end
+pub fn tail_for_checkin(output: &str, budget: usize) -> String {
+    if output.len() <= budget {
+        return output.to_owned();
+    }
+    let mut start = output.len() - budget;
+    while !output.is_char_boundary(start) {
+        start = start + 1;
+    }
+    format!("... (earlier output elided)\n{}", &output[start..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{tail_for_checkin, CheckinDecision};
+
+    #[test]
+    fn test_keeps_waiting_on_clean_output() {
+        let decision = CheckinDecision::from_partial_output("Compiling sidecar v0.1.0", 30, 600);
+        assert_eq!(decision, CheckinDecision::KeepWaiting);
+    }
+
+    #[test]
+    fn test_acts_on_early_failure() {
+        let decision = CheckinDecision::from_partial_output(
+            "test result: FAILED. 1 passed; 2 failed",
+            30,
+            600,
+        );
+        assert_eq!(decision, CheckinDecision::ActOnOutput);
+    }
+
+    #[test]
+    fn test_kills_after_wait_budget() {
+        let decision = CheckinDecision::from_partial_output("Compiling sidecar v0.1.0", 601, 600);
+        assert_eq!(decision, CheckinDecision::Kill);
+    }
+
+    #[test]
+    fn test_parses_llm_decision_with_reasoning_around_it() {
+        let response = "the build is still compiling\n<decision>wait</decision>";
+        assert_eq!(
+            CheckinDecision::from_llm_response(response),
+            Some(CheckinDecision::KeepWaiting)
+        );
+        assert_eq!(
+            CheckinDecision::from_llm_response("<decision>ACT</decision>"),
+            Some(CheckinDecision::ActOnOutput)
+        );
+    }
+
+    #[test]
+    fn test_unparseable_llm_decision_returns_none() {
+        assert_eq!(CheckinDecision::from_llm_response("sure, waiting"), None);
+        assert_eq!(
+            CheckinDecision::from_llm_response("<decision>maybe</decision>"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_tail_keeps_end_of_output() {
+        let output = "a".repeat(50) + "final line";
+        let clamped = tail_for_checkin(&output, 10);
+        assert!(clamped.ends_with("final line"));
+        assert!(clamped.starts_with("... (earlier output elided)"));
+    }
+}
@@ -1 +1,2 @@
+pub mod long_running;
 pub mod terminal;
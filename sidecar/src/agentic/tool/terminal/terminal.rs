@@ -1,15 +1,20 @@
 use async_trait::async_trait;
 use logging::new_client;
+use std::sync::Arc;
 
 use crate::agentic::tool::{
     errors::ToolError,
     input::ToolInput,
     output::ToolOutput,
     r#type::{Tool, ToolRewardScale},
+    workspace::transport::WorkspaceTransport,
 };
 
 pub struct TerminalTool {
     client: reqwest_middleware::ClientWithMiddleware,
+    /// set in remote-workspace mode, commands run on the remote machine over
+    /// the transport instead of in the editor's terminal
+    workspace_transport: Option<Arc<dyn WorkspaceTransport + Send + Sync>>,
 }
 
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
@@ -103,18 +108,127 @@ impl TerminalInput {
 #[derive(Debug, serde::Deserialize, serde::Serialize)]
 pub struct TerminalOutput {
     output: String,
+    /// exit code of the command, older editors do not send this over so
+    /// it stays optional
+    #[serde(default)]
+    exit_code: Option<i32>,
+    /// stderr separated out from stdout when the editor supports it
+    #[serde(default)]
+    stderr: Option<String>,
+    /// wall clock duration of the command in milliseconds
+    #[serde(default)]
+    duration_ms: Option<u64>,
+    /// set when the editor truncated the output before sending it over
+    #[serde(default)]
+    truncated: bool,
 }
 
 impl TerminalOutput {
     pub fn output(&self) -> &str {
         &self.output
     }
+
+    pub fn exit_code(&self) -> Option<i32> {
+        self.exit_code
+    }
+
+    pub fn stderr(&self) -> Option<&str> {
+        self.stderr.as_deref()
+    }
+
+    pub fn duration_ms(&self) -> Option<u64> {
+        self.duration_ms
+    }
+
+    pub fn truncated(&self) -> bool {
+        self.truncated
+    }
+
+    /// Strips the ANSI escape sequences from the captured streams so the
+    /// output going into prompts and UI rendering is clean text
+    fn sanitised(mut self) -> Self {
+        self.output = strip_ansi_escape_sequences(&self.output);
+        self.stderr = self
+            .stderr
+            .map(|stderr| strip_ansi_escape_sequences(&stderr));
+        self
+    }
+
+    /// Structured exit information which gets appended to the output when
+    /// we show it to the LLM, so exit codes are never lost
+    pub fn exit_info_line(&self) -> Option<String> {
+        let mut parts = vec![];
+        if let Some(exit_code) = self.exit_code {
+            parts.push(format!("Exit code: {}", exit_code));
+        }
+        if let Some(duration_ms) = self.duration_ms {
+            parts.push(format!("Duration: {}ms", duration_ms));
+        }
+        if self.truncated {
+            parts.push("Output was truncated".to_owned());
+        }
+        if parts.is_empty() {
+            None
+        } else {
+            Some(parts.join(" | "))
+        }
+    }
+}
+
+/// Removes ANSI escape sequences (colors, cursor movement etc) from terminal
+/// output, we do this by hand instead of pulling in a dependency since the
+/// grammar for the escape sequences we care about is small
+pub fn strip_ansi_escape_sequences(input: &str) -> String {
+    let mut result = String::with_capacity(input.len());
+    let mut chars = input.chars().peekable();
+    while let Some(current_char) = chars.next() {
+        if current_char != '\u{1b}' {
+            result.push(current_char);
+            continue;
+        }
+        match chars.peek() {
+            // CSI sequence: ESC [ parameters final-byte
+            Some('[') => {
+                chars.next();
+                while let Some(&next_char) = chars.peek() {
+                    chars.next();
+                    // the final byte of a CSI sequence is in the 0x40..=0x7e range
+                    if ('\u{40}'..='\u{7e}').contains(&next_char) {
+                        break;
+                    }
+                }
+            }
+            // OSC sequence: ESC ] ... terminated by BEL or ESC \
+            Some(']') => {
+                chars.next();
+                while let Some(&next_char) = chars.peek() {
+                    chars.next();
+                    if next_char == '\u{7}' {
+                        break;
+                    }
+                    if next_char == '\u{1b}' {
+                        if let Some('\\') = chars.peek() {
+                            chars.next();
+                        }
+                        break;
+                    }
+                }
+            }
+            // two character escape sequences
+            Some(_) => {
+                chars.next();
+            }
+            None => {}
+        }
+    }
+    result
 }
 
 impl TerminalTool {
-    pub fn new() -> Self {
+    pub fn new(workspace_transport: Option<Arc<dyn WorkspaceTransport + Send + Sync>>) -> Self {
         Self {
             client: new_client(),
+            workspace_transport,
         }
     }
 }
@@ -123,6 +237,18 @@ impl TerminalTool {
 impl Tool for TerminalTool {
     async fn invoke(&self, input: ToolInput) -> Result<ToolOutput, ToolError> {
         let context = input.is_terminal_command()?;
+        if let Some(workspace_transport) = self.workspace_transport.as_ref() {
+            let start_instant = std::time::Instant::now();
+            let command_output = workspace_transport.exec_command(&context.command).await?;
+            let terminal_output = TerminalOutput {
+                output: command_output.stdout().to_owned(),
+                exit_code: command_output.exit_code(),
+                stderr: Some(command_output.stderr().to_owned()),
+                duration_ms: Some(start_instant.elapsed().as_millis() as u64),
+                truncated: false,
+            };
+            return Ok(ToolOutput::TerminalCommand(terminal_output.sanitised()));
+        }
         let editor_endpoint = context.editor_url.to_owned() + "/execute_terminal_command";
 
         let response = self
@@ -138,7 +264,7 @@ impl Tool for TerminalTool {
             .await
             .map_err(|_e| ToolError::SerdeConversionFailed)?;
 
-        Ok(ToolOutput::TerminalCommand(terminal_response))
+        Ok(ToolOutput::TerminalCommand(terminal_response.sanitised()))
     }
 
     // credit Cline.
@@ -231,3 +357,29 @@ true
         ]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::strip_ansi_escape_sequences;
+
+    #[test]
+    fn test_strips_color_codes() {
+        let input = "\u{1b}[31merror\u{1b}[0m: something failed";
+        assert_eq!(
+            strip_ansi_escape_sequences(input),
+            "error: something failed"
+        );
+    }
+
+    #[test]
+    fn test_strips_osc_sequences() {
+        let input = "\u{1b}]0;window title\u{7}ls output";
+        assert_eq!(strip_ansi_escape_sequences(input), "ls output");
+    }
+
+    #[test]
+    fn test_plain_output_is_unchanged() {
+        let input = "all tests passed\nin 0.5s";
+        assert_eq!(strip_ansi_escape_sequences(input), input);
+    }
+}
@@ -40,7 +40,8 @@ impl TestRunnerRequestPartial {
             "description": r#"Runs the tests in the provided files
 
 # Requirements:
-You should verify where the test files are located, only use test_runner tool after you have this information"#,
+You should verify where the test files are located, only use test_runner tool after you have this information
+When you are fixing a bug, run the reproduction test you wrote BEFORE making the fix to confirm it fails, and run it again AFTER the fix to confirm it passes"#,
             "input_schema": {
                 "type": "object",
                 "properties": {
@@ -0,0 +1,281 @@
+//! Builds the import graph of a repository from tree-sitter parses
+//!
+//! Multi-file edits go better when the plan knows which files import the
+//! module being changed and what the edited file itself depends on. The
+//! import identifier queries on the language configs already extract the
+//! imports of a single file, this tool runs them across the repository and
+//! answers both directions of the question
+
+use async_trait::async_trait;
+use ignore::WalkBuilder;
+use std::sync::Arc;
+
+use crate::agentic::tool::errors::ToolError;
+use crate::agentic::tool::input::ToolInput;
+use crate::agentic::tool::output::ToolOutput;
+use crate::agentic::tool::r#type::{Tool, ToolRewardScale};
+use crate::chunking::languages::TSLanguageParsing;
+
+/// caps how many files the walk parses, repositories beyond this should be
+/// queried per sub-directory
+const MAX_FILES_TO_SCAN: usize = 500;
+
+#[derive(Debug, Clone)]
+pub struct ImportGraphRequest {
+    /// the directory whose files get parsed
+    root_directory: String,
+    /// answers "what does this file depend on" when set
+    fs_file_path: Option<String>,
+    /// answers "which files import this module" when set
+    imported_module: Option<String>,
+}
+
+impl ImportGraphRequest {
+    pub fn new(
+        root_directory: String,
+        fs_file_path: Option<String>,
+        imported_module: Option<String>,
+    ) -> Self {
+        Self {
+            root_directory,
+            fs_file_path,
+            imported_module,
+        }
+    }
+
+    pub fn root_directory(&self) -> &str {
+        &self.root_directory
+    }
+}
+
+/// A file and the modules it imports
+#[derive(Debug, Clone)]
+pub struct FileImports {
+    fs_file_path: String,
+    imports: Vec<String>,
+}
+
+impl FileImports {
+    pub fn fs_file_path(&self) -> &str {
+        &self.fs_file_path
+    }
+
+    pub fn imports(&self) -> &[String] {
+        &self.imports
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ImportGraphOutput {
+    files: Vec<FileImports>,
+    /// true when the file cap cut the walk short
+    truncated: bool,
+}
+
+impl ImportGraphOutput {
+    pub fn files(&self) -> &[FileImports] {
+        &self.files
+    }
+
+    pub fn truncated(&self) -> bool {
+        self.truncated
+    }
+
+    /// renders the graph the way the planning prompts consume it, one file
+    /// per block with its imports indented under it
+    pub fn to_prompt_format(&self) -> String {
+        self.files
+            .iter()
+            .map(|file| {
+                let imports = file
+                    .imports()
+                    .iter()
+                    .map(|import| format!("  {}", import))
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                format!("{}\n{}", file.fs_file_path(), imports)
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// Keeps the files matching the query: a single file's dependencies, the
+/// importers of a module, or everything when neither filter is set
+fn filter_graph(
+    files: Vec<FileImports>,
+    fs_file_path: Option<&str>,
+    imported_module: Option<&str>,
+) -> Vec<FileImports> {
+    files
+        .into_iter()
+        .filter(|file| {
+            if let Some(fs_file_path) = fs_file_path {
+                if file.fs_file_path() != fs_file_path {
+                    return false;
+                }
+            }
+            if let Some(imported_module) = imported_module {
+                if !file
+                    .imports()
+                    .iter()
+                    .any(|import| import == imported_module)
+                {
+                    return false;
+                }
+            }
+            true
+        })
+        .collect()
+}
+
+pub struct ImportGraphClient {
+    language_parsing: Arc<TSLanguageParsing>,
+}
+
+impl ImportGraphClient {
+    pub fn new(language_parsing: Arc<TSLanguageParsing>) -> Self {
+        Self { language_parsing }
+    }
+
+    /// Walks the directory and parses the imports of every file with a
+    /// language config, files in other languages contribute nothing
+    async fn build_graph(&self, root_directory: &str) -> (Vec<FileImports>, bool) {
+        let mut files = vec![];
+        let mut truncated = false;
+        for entry in WalkBuilder::new(root_directory).build().flatten() {
+            if !entry
+                .file_type()
+                .map(|file_type| file_type.is_file())
+                .unwrap_or(false)
+            {
+                continue;
+            }
+            if files.len() >= MAX_FILES_TO_SCAN {
+                truncated = true;
+                break;
+            }
+            let fs_file_path = entry.path().to_string_lossy().to_string();
+            let Some(language_config) = self.language_parsing.for_file_path(&fs_file_path) else {
+                continue;
+            };
+            let Ok(content) = tokio::fs::read_to_string(&fs_file_path).await else {
+                continue;
+            };
+            let mut imports = language_config
+                .generate_import_identifiers_fresh(content.as_bytes())
+                .into_iter()
+                .map(|(import, _range)| import)
+                .collect::<Vec<_>>();
+            imports.dedup();
+            files.push(FileImports {
+                fs_file_path,
+                imports,
+            });
+        }
+        (files, truncated)
+    }
+}
+
+#[async_trait]
+impl Tool for ImportGraphClient {
+    async fn invoke(&self, input: ToolInput) -> Result<ToolOutput, ToolError> {
+        let request = input.is_import_graph()?;
+        let (files, truncated) = self.build_graph(request.root_directory()).await;
+        let files = filter_graph(
+            files,
+            request.fs_file_path.as_deref(),
+            request.imported_module.as_deref(),
+        );
+        Ok(ToolOutput::import_graph(ImportGraphOutput {
+            files,
+            truncated,
+        }))
+    }
+
+    fn tool_description(&self) -> String {
+        "Builds the import graph of a directory and answers which files import a module or what a file depends on".to_owned()
+    }
+
+    fn tool_input_format(&self) -> String {
+        format!(
+            r#"Parameters:
+- root_directory: (required) The ABSOLUTE path of the directory to build the import graph for.
+- fs_file_path: (optional) Restrict the output to the imports of this file.
+- imported_module: (optional) Restrict the output to the files importing this module.
+
+Usage:
+<import_graph>
+<root_directory>
+Directory path here
+</root_directory>
+<imported_module>
+module name here
+</imported_module>
+</import_graph>
+"#
+        )
+    }
+
+    fn get_evaluation_criteria(&self, _trajectory_length: usize) -> Vec<String> {
+        vec![]
+    }
+
+    fn get_reward_scale(&self, _trajectory_length: usize) -> Vec<ToolRewardScale> {
+        vec![]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{filter_graph, FileImports, ImportGraphClient};
+    use crate::chunking::languages::TSLanguageParsing;
+    use std::sync::Arc;
+
+    fn file(fs_file_path: &str, imports: &[&str]) -> FileImports {
+        FileImports {
+            fs_file_path: fs_file_path.to_owned(),
+            imports: imports.iter().map(|import| import.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_filtering_by_imported_module_keeps_only_importers() {
+        let files = vec![
+            file("src/a.rs", &["collections", "fmt"]),
+            file("src/b.rs", &["fmt"]),
+        ];
+        let importers = filter_graph(files, None, Some("collections"));
+        assert_eq!(importers.len(), 1);
+        assert_eq!(importers[0].fs_file_path(), "src/a.rs");
+    }
+
+    #[test]
+    fn test_filtering_by_file_returns_its_dependencies() {
+        let files = vec![
+            file("src/a.rs", &["collections"]),
+            file("src/b.rs", &["fmt"]),
+        ];
+        let dependencies = filter_graph(files, Some("src/b.rs"), None);
+        assert_eq!(dependencies.len(), 1);
+        assert_eq!(dependencies[0].imports(), &["fmt".to_owned()]);
+    }
+
+    #[tokio::test]
+    async fn test_building_the_graph_parses_rust_imports() {
+        let directory = tempfile::tempdir().expect("tempdir creation to work");
+        let fs_file_path = directory.path().join("lib.rs");
+        std::fs::write(
+            &fs_file_path,
+            "use std::collections::HashMap;\n\nfn main() {}\n",
+        )
+        .expect("write to work");
+        let client = ImportGraphClient::new(Arc::new(TSLanguageParsing::init()));
+        let (files, truncated) = client
+            .build_graph(&directory.path().to_string_lossy())
+            .await;
+        assert!(!truncated);
+        assert_eq!(files.len(), 1);
+        assert!(!files[0].imports().is_empty());
+    }
+}
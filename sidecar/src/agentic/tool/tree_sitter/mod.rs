@@ -0,0 +1,4 @@
+//! Tools backed by tree-sitter directly instead of the editor or an LLM
+
+pub mod import_graph;
+pub mod query;
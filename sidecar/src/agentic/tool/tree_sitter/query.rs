@@ -0,0 +1,330 @@
+//! Runs an arbitrary tree-sitter query over a file or a directory
+//!
+//! Structural questions like "all functions calling X" or "all structs
+//! deriving Serialize" are painful to answer with regex searches, a
+//! tree-sitter query expresses them directly. The tool compiles the query
+//! against the grammar of each file it visits and returns the captured
+//! nodes with their ranges
+
+use async_trait::async_trait;
+use ignore::WalkBuilder;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::agentic::tool::errors::ToolError;
+use crate::agentic::tool::input::ToolInput;
+use crate::agentic::tool::output::ToolOutput;
+use crate::agentic::tool::r#type::{Tool, ToolRewardScale};
+use crate::chunking::languages::TSLanguageParsing;
+use crate::chunking::text_document::Range;
+
+/// caps how many files a directory query visits, beyond this the caller
+/// should point the query at a sub-directory
+const MAX_FILES_TO_QUERY: usize = 250;
+
+/// caps the matches we format so the context window survives
+const MAX_MATCHES: usize = 250;
+
+/// captured nodes can be entire classes, we truncate their text and keep
+/// the range as the pointer to the full code
+const MAX_NODE_TEXT_LENGTH: usize = 400;
+
+#[derive(Debug, Clone)]
+pub struct TreeSitterQueryRequest {
+    /// the tree-sitter s-expression query to run
+    query: String,
+    /// file or directory the query runs over
+    path: String,
+}
+
+impl TreeSitterQueryRequest {
+    pub fn new(query: String, path: String) -> Self {
+        Self { query, path }
+    }
+
+    pub fn query(&self) -> &str {
+        &self.query
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+}
+
+/// A single captured node from running the query
+#[derive(Debug, Clone)]
+pub struct TreeSitterQueryMatch {
+    fs_file_path: String,
+    /// the @name of the capture in the query
+    capture_name: String,
+    range: Range,
+    /// text of the captured node, truncated for giant nodes
+    node_text: String,
+}
+
+impl TreeSitterQueryMatch {
+    pub fn fs_file_path(&self) -> &str {
+        &self.fs_file_path
+    }
+
+    pub fn capture_name(&self) -> &str {
+        &self.capture_name
+    }
+
+    pub fn range(&self) -> &Range {
+        &self.range
+    }
+
+    pub fn node_text(&self) -> &str {
+        &self.node_text
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TreeSitterQueryOutput {
+    matches: Vec<TreeSitterQueryMatch>,
+    /// true when the file or match caps cut the results off
+    truncated: bool,
+}
+
+impl TreeSitterQueryOutput {
+    pub fn matches(&self) -> &[TreeSitterQueryMatch] {
+        &self.matches
+    }
+
+    pub fn truncated(&self) -> bool {
+        self.truncated
+    }
+}
+
+pub struct TreeSitterQueryClient {
+    language_parsing: Arc<TSLanguageParsing>,
+}
+
+impl TreeSitterQueryClient {
+    pub fn new(language_parsing: Arc<TSLanguageParsing>) -> Self {
+        Self { language_parsing }
+    }
+
+    /// Compiles the query against the grammar of this file and collects
+    /// every capture, files in languages we cannot parse match nothing
+    fn query_file(
+        &self,
+        fs_file_path: &str,
+        content: &str,
+        query_str: &str,
+    ) -> Result<Vec<TreeSitterQueryMatch>, ToolError> {
+        let Some(language_config) = self.language_parsing.for_file_path(fs_file_path) else {
+            return Ok(vec![]);
+        };
+        let query = tree_sitter::Query::new((language_config.grammar)(), query_str)
+            .map_err(|e| ToolError::InvalidInput(format!("malformed tree-sitter query: {}", e)))?;
+        let mut parser = language_config.pooled_parser();
+        let Some(tree) = parser.parse(content.as_bytes(), None) else {
+            return Ok(vec![]);
+        };
+        let capture_names = query.capture_names().to_vec();
+        let mut cursor = tree_sitter::QueryCursor::new();
+        let query_captures = cursor.captures(&query, tree.root_node(), content.as_bytes());
+        let mut matches = vec![];
+        for (query_match, _) in query_captures {
+            for capture in query_match.captures {
+                let node_range = capture.node.byte_range();
+                let mut node_text = content
+                    .get(node_range)
+                    .unwrap_or_default()
+                    .to_owned();
+                if node_text.len() > MAX_NODE_TEXT_LENGTH {
+                    // walk back to a char boundary so multi-byte text does
+                    // not panic the truncate
+                    let mut cutoff = MAX_NODE_TEXT_LENGTH;
+                    while !node_text.is_char_boundary(cutoff) {
+                        cutoff -= 1;
+                    }
+                    node_text.truncate(cutoff);
+                }
+                matches.push(TreeSitterQueryMatch {
+                    fs_file_path: fs_file_path.to_owned(),
+                    capture_name: capture_names
+                        .get(capture.index as usize)
+                        .cloned()
+                        .unwrap_or_default(),
+                    range: Range::for_tree_node(&capture.node),
+                    node_text,
+                });
+            }
+        }
+        Ok(matches)
+    }
+
+    /// The files a query over this path visits: the path itself when its a
+    /// file, otherwise a gitignore-aware walk of the directory
+    fn files_to_query(&self, path: &str) -> (Vec<String>, bool) {
+        if Path::new(path).is_file() {
+            return (vec![path.to_owned()], false);
+        }
+        let mut files = vec![];
+        let mut truncated = false;
+        for entry in WalkBuilder::new(path).build().flatten() {
+            if !entry
+                .file_type()
+                .map(|file_type| file_type.is_file())
+                .unwrap_or(false)
+            {
+                continue;
+            }
+            if files.len() >= MAX_FILES_TO_QUERY {
+                truncated = true;
+                break;
+            }
+            files.push(entry.path().to_string_lossy().to_string());
+        }
+        (files, truncated)
+    }
+}
+
+#[async_trait]
+impl Tool for TreeSitterQueryClient {
+    async fn invoke(&self, input: ToolInput) -> Result<ToolOutput, ToolError> {
+        let request = input.is_tree_sitter_query()?;
+        let (files, files_truncated) = self.files_to_query(request.path());
+        let mut matches = vec![];
+        let mut truncated = files_truncated;
+        for fs_file_path in files.into_iter() {
+            let Ok(content) = tokio::fs::read_to_string(&fs_file_path).await else {
+                continue;
+            };
+            matches.extend(self.query_file(&fs_file_path, &content, request.query())?);
+            if matches.len() >= MAX_MATCHES {
+                matches.truncate(MAX_MATCHES);
+                truncated = true;
+                break;
+            }
+        }
+        Ok(ToolOutput::tree_sitter_query(TreeSitterQueryOutput {
+            matches,
+            truncated,
+        }))
+    }
+
+    fn tool_description(&self) -> String {
+        format!(
+            r#"### tree_sitter_query
+Runs a tree-sitter query over a file or directory and returns the captured nodes with their ranges.
+Use this for structural searches which regex cannot express reliably: all functions calling a symbol, all structs deriving a trait, every match arm of an enum.
+The query uses the tree-sitter s-expression syntax and runs against the grammar of each file it visits."#
+        )
+    }
+
+    fn tool_input_format(&self) -> String {
+        format!(
+            r#"Parameters:
+- query: (required) The tree-sitter query in s-expression syntax, for example (function_item name: (identifier) @name).
+- path: (required) The absolute path of the file or directory to run the query over. Directories are walked respecting gitignore.
+
+Usage:
+<tree_sitter_query>
+<query>
+(function_item name: (identifier) @name)
+</query>
+<path>
+/absolute/path/to/search
+</path>
+</tree_sitter_query>"#
+        )
+    }
+
+    fn get_evaluation_criteria(&self, _trajectory_length: usize) -> Vec<String> {
+        vec![]
+    }
+
+    fn get_reward_scale(&self, _trajectory_length: usize) -> Vec<ToolRewardScale> {
+        vec![]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TreeSitterQueryClient, TreeSitterQueryRequest};
+    use crate::agentic::tool::input::ToolInput;
+    use crate::agentic::tool::r#type::Tool;
+    use crate::chunking::languages::TSLanguageParsing;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_querying_functions_in_a_rust_file() {
+        let directory = tempfile::tempdir().expect("tempdir creation to work");
+        let fs_file_path = directory.path().join("lib.rs");
+        std::fs::write(
+            &fs_file_path,
+            r#"fn first() {}
+fn second(value: i32) -> i32 {
+    value
+}
+"#,
+        )
+        .expect("write to work");
+        let client = TreeSitterQueryClient::new(Arc::new(TSLanguageParsing::init()));
+        let output = client
+            .invoke(ToolInput::TreeSitterQuery(TreeSitterQueryRequest::new(
+                "(function_item name: (identifier) @name)".to_owned(),
+                fs_file_path.to_string_lossy().to_string(),
+            )))
+            .await
+            .expect("invoke to work")
+            .get_tree_sitter_query_response()
+            .expect("tree sitter output");
+        let names = output
+            .matches()
+            .iter()
+            .map(|query_match| query_match.node_text())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["first", "second"]);
+        assert!(!output.truncated());
+    }
+
+    #[tokio::test]
+    async fn test_multi_byte_node_text_truncates_on_a_char_boundary() {
+        let directory = tempfile::tempdir().expect("tempdir creation to work");
+        let fs_file_path = directory.path().join("lib.rs");
+        // the captured string literal is over the 400 byte cap and every
+        // char past the opening quote is two bytes, so the cap lands in
+        // the middle of one
+        let content = format!("fn main() {{ let value = \"{}\"; }}\n", "é".repeat(250));
+        std::fs::write(&fs_file_path, content).expect("write to work");
+        let client = TreeSitterQueryClient::new(Arc::new(TSLanguageParsing::init()));
+        let output = client
+            .invoke(ToolInput::TreeSitterQuery(TreeSitterQueryRequest::new(
+                "(string_literal) @text".to_owned(),
+                fs_file_path.to_string_lossy().to_string(),
+            )))
+            .await
+            .expect("invoke to work")
+            .get_tree_sitter_query_response()
+            .expect("tree sitter output");
+        let node_text = output
+            .matches()
+            .get(0)
+            .expect("one match")
+            .node_text();
+        assert!(node_text.len() <= 400);
+        // the truncation walked back one byte instead of splitting the char
+        assert_eq!(node_text.len(), 399);
+        assert!(node_text.ends_with('é'));
+    }
+
+    #[tokio::test]
+    async fn test_malformed_query_surfaces_an_error() {
+        let directory = tempfile::tempdir().expect("tempdir creation to work");
+        let fs_file_path = directory.path().join("lib.rs");
+        std::fs::write(&fs_file_path, "fn main() {}\n").expect("write to work");
+        let client = TreeSitterQueryClient::new(Arc::new(TSLanguageParsing::init()));
+        let result = client
+            .invoke(ToolInput::TreeSitterQuery(TreeSitterQueryRequest::new(
+                "(function_item".to_owned(),
+                fs_file_path.to_string_lossy().to_string(),
+            )))
+            .await;
+        assert!(result.is_err());
+    }
+}
@@ -94,6 +94,10 @@ pub enum ToolType {
     SearchAndReplaceEditing,
     // Grabs the git-diff
     GitDiff,
+    // Drives git bisect to find the commit which introduced a regression
+    GitBisect,
+    // Structural diff between two versions of a file over outline nodes
+    OutlineDiff,
     // code editing warmup tool
     CodeEditingWarmupTool,
     // grab outline nodes using the editor
@@ -158,6 +162,20 @@ pub enum ToolType {
     RequestScreenshot,
     // Context crunching
     ContextCrunching,
+    // Runs a tree-sitter query over files
+    TreeSitterQuery,
+    // Runs the language formatter on a file
+    FormatCode,
+    // Applies machine-applicable compiler suggestions
+    ApplyCompilerSuggestions,
+    // Import graph over the repository
+    ImportGraph,
+    // Rename a symbol through the editor
+    Rename,
+    // Incoming/outgoing calls of a symbol through the editor
+    CallHierarchy,
+    // Workspace-wide symbol search through the editor
+    WorkspaceSymbol,
     // dynamically configured MCP servers
     McpTool(String),
 }
@@ -227,6 +245,8 @@ impl std::fmt::Display for ToolType {
                 f,
                 "Gets the git diff output for a certain file, also returns the original version"
             ),
+            ToolType::GitBisect => write!(f, "git_bisect"),
+            ToolType::OutlineDiff => write!(f, "outline_diff"),
             ToolType::CodeEditingWarmupTool => write!(f, "Code editing warmup tool"),
             ToolType::OutlineNodesUsingEditor => write!(f, "Outline nodes using the editor"),
             ToolType::ReferencesFilter => write!(f, "Filters references"),
@@ -264,6 +284,13 @@ impl std::fmt::Display for ToolType {
             ToolType::FindFiles => write!(f, "find_file"),
             ToolType::RequestScreenshot => write!(f, "request_screenshot"),
             ToolType::ContextCrunching => write!(f, "context_crunching"),
+            ToolType::TreeSitterQuery => write!(f, "tree_sitter_query"),
+            ToolType::FormatCode => write!(f, "format_code"),
+            ToolType::ApplyCompilerSuggestions => write!(f, "apply_compiler_suggestions"),
+            ToolType::ImportGraph => write!(f, "import_graph"),
+            ToolType::Rename => write!(f, "rename_symbol"),
+            ToolType::CallHierarchy => write!(f, "call_hierarchy"),
+            ToolType::WorkspaceSymbol => write!(f, "workspace_symbol"),
             ToolType::McpTool(name) => write!(f, "{}", name),
         }
     }
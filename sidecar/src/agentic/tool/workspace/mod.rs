@@ -0,0 +1,6 @@
+//! Remote-workspace mode: sidecar can run on the developer's laptop while
+//! the workspace lives on a remote machine or dev-container, the transport
+//! here sits behind the same tool interfaces used by file_open, list_files
+//! and the terminal
+
+pub mod transport;
@@ -0,0 +1,217 @@
+//! File and exec transport for remote workspaces. The tools which touch the
+//! filesystem or run commands go through [`WorkspaceTransport`] when sidecar
+//! is configured with a remote workspace, the ssh implementation shells out
+//! to the `ssh` binary so the user's existing keys, agent and ssh config all
+//! keep working without us reimplementing the protocol
+
+use async_trait::async_trait;
+use std::path::PathBuf;
+
+use crate::agentic::tool::errors::ToolError;
+
+/// Where the remote workspace lives, parsed from "[user@]host[:port]"
+#[derive(Debug, Clone)]
+pub struct SshWorkspaceConfig {
+    user: Option<String>,
+    host: String,
+    port: Option<u16>,
+    /// wraps every remote command when the workspace sits inside a container
+    /// on the remote host, something like "docker exec -i devcontainer"
+    exec_prefix: Option<String>,
+}
+
+impl SshWorkspaceConfig {
+    pub fn parse(connection: &str, exec_prefix: Option<String>) -> Option<Self> {
+        let connection = connection.trim();
+        if connection.is_empty() {
+            return None;
+        }
+        let (user, host_and_port) = match connection.split_once('@') {
+            Some((user, rest)) => (Some(user.to_owned()), rest),
+            None => (None, connection),
+        };
+        let (host, port) = match host_and_port.split_once(':') {
+            Some((host, port)) => (host, Some(port.parse::<u16>().ok()?)),
+            None => (host_and_port, None),
+        };
+        if host.is_empty() {
+            return None;
+        }
+        Some(Self {
+            user,
+            host: host.to_owned(),
+            port,
+            exec_prefix,
+        })
+    }
+
+    fn target(&self) -> String {
+        match &self.user {
+            Some(user) => format!("{}@{}", user, self.host),
+            None => self.host.to_owned(),
+        }
+    }
+}
+
+/// What came back from running a command on the workspace
+#[derive(Debug, Clone)]
+pub struct WorkspaceCommandOutput {
+    stdout: String,
+    stderr: String,
+    exit_code: Option<i32>,
+}
+
+impl WorkspaceCommandOutput {
+    pub fn stdout(&self) -> &str {
+        &self.stdout
+    }
+
+    pub fn stderr(&self) -> &str {
+        &self.stderr
+    }
+
+    pub fn exit_code(&self) -> Option<i32> {
+        self.exit_code
+    }
+}
+
+/// The file and exec operations the tools need from a workspace, implemented
+/// by the ssh transport today and leaves room for an agent protocol later
+#[async_trait]
+pub trait WorkspaceTransport {
+    /// Reads a file from the workspace as utf-8 text
+    async fn read_file(&self, fs_file_path: &str) -> Result<String, ToolError>;
+
+    /// Lists the files under a directory on the workspace
+    async fn list_directory(
+        &self,
+        directory_path: &str,
+        recursive: bool,
+    ) -> Result<Vec<PathBuf>, ToolError>;
+
+    /// Runs a shell command on the workspace
+    async fn exec_command(&self, command: &str) -> Result<WorkspaceCommandOutput, ToolError>;
+}
+
+/// Remote workspace reached over ssh, every operation is an `ssh` invocation
+/// so file reads and command runs behave exactly like the user typing them
+pub struct SshWorkspace {
+    config: SshWorkspaceConfig,
+}
+
+impl SshWorkspace {
+    pub fn new(config: SshWorkspaceConfig) -> Self {
+        Self { config }
+    }
+
+    /// Runs a raw command string on the remote host, wrapping it with the
+    /// container exec prefix when one is configured
+    async fn run_remote(&self, command: &str) -> Result<WorkspaceCommandOutput, ToolError> {
+        let remote_command = match &self.config.exec_prefix {
+            Some(exec_prefix) => format!("{} sh -c {}", exec_prefix, shell_quote(command)),
+            None => command.to_owned(),
+        };
+        let mut ssh_command = tokio::process::Command::new("ssh");
+        if let Some(port) = self.config.port {
+            ssh_command.arg("-p").arg(port.to_string());
+        }
+        let output = ssh_command
+            .arg("-o")
+            .arg("BatchMode=yes")
+            .arg(self.config.target())
+            .arg(remote_command)
+            .output()
+            .await
+            .map_err(|e| ToolError::InvocationError(format!("ssh failed to spawn: {}", e)))?;
+        Ok(WorkspaceCommandOutput {
+            stdout: String::from_utf8_lossy(&output.stdout).to_string(),
+            stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+            exit_code: output.status.code(),
+        })
+    }
+}
+
+#[async_trait]
+impl WorkspaceTransport for SshWorkspace {
+    async fn read_file(&self, fs_file_path: &str) -> Result<String, ToolError> {
+        let output = self
+            .run_remote(&format!("cat {}", shell_quote(fs_file_path)))
+            .await?;
+        if output.exit_code == Some(0) {
+            Ok(output.stdout)
+        } else {
+            Err(ToolError::InvocationError(format!(
+                "reading {} over ssh failed: {}",
+                fs_file_path,
+                output.stderr.trim()
+            )))
+        }
+    }
+
+    async fn list_directory(
+        &self,
+        directory_path: &str,
+        recursive: bool,
+    ) -> Result<Vec<PathBuf>, ToolError> {
+        let command = if recursive {
+            format!("find {} -not -path '*/.git/*'", shell_quote(directory_path))
+        } else {
+            format!(
+                "find {} -maxdepth 1 -not -path '*/.git/*'",
+                shell_quote(directory_path)
+            )
+        };
+        let output = self.run_remote(&command).await?;
+        if output.exit_code != Some(0) {
+            return Err(ToolError::InvocationError(format!(
+                "listing {} over ssh failed: {}",
+                directory_path,
+                output.stderr.trim()
+            )));
+        }
+        Ok(output
+            .stdout
+            .lines()
+            .filter(|line| !line.is_empty())
+            .map(PathBuf::from)
+            .collect())
+    }
+
+    async fn exec_command(&self, command: &str) -> Result<WorkspaceCommandOutput, ToolError> {
+        self.run_remote(command).await
+    }
+}
+
+/// Single-quotes a string for a posix shell, embedded single quotes become
+/// the '\'' dance
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r#"'\''"#))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{shell_quote, SshWorkspaceConfig};
+
+    #[test]
+    fn test_parsing_connection_strings() {
+        let config = SshWorkspaceConfig::parse("dev@build-box:2222", None).expect("to parse");
+        assert_eq!(config.target(), "dev@build-box");
+        assert_eq!(config.port, Some(2222));
+        let config = SshWorkspaceConfig::parse("build-box", None).expect("to parse");
+        assert_eq!(config.target(), "build-box");
+        assert_eq!(config.port, None);
+    }
+
+    #[test]
+    fn test_invalid_connection_strings_are_rejected() {
+        assert!(SshWorkspaceConfig::parse("", None).is_none());
+        assert!(SshWorkspaceConfig::parse("dev@", None).is_none());
+        assert!(SshWorkspaceConfig::parse("host:not-a-port", None).is_none());
+    }
+
+    #[test]
+    fn test_shell_quoting_survives_single_quotes() {
+        assert_eq!(shell_quote("plain"), "'plain'");
+        assert_eq!(shell_quote("it's"), r#"'it'\''s'"#);
+    }
+}
@@ -4,7 +4,7 @@
 use std::sync::Arc;
 
 use llm_client::{
-    broker::LLMBroker,
+    broker::{LLMBroker, LLMBrokerRetryPolicy},
     clients::types::LLMType,
     provider::{AnthropicAPIKey, LLMProvider, LLMProviderAPIKeys, OpenAIProvider},
     tokenizer::tokenizer::LLMTokenizer,
@@ -20,17 +20,34 @@ use tracing::{debug, warn};
 use crate::repo::state::RepositoryPool;
 use crate::{
     agentic::{
+        experiments::ExperimentRegistry,
+        swe_bench::dataset_recorder::DatasetRecorder,
         symbol::{identifier::LLMProperties, manager::SymbolManager, tool_box::ToolBox},
         tool::{
+            audit::ToolAuditLog,
             broker::{ToolBroker, ToolBrokerConfiguration},
             code_edit::models::broker::CodeEditBroker,
-            session::service::SessionService,
+            model_overrides::ToolModelOverrides,
+            policy::ToolPolicy,
+            r#type::ToolType,
+            sensitive_files::{SensitiveFileEditGuardMiddleware, SensitiveFileGuard},
+            session::{service::SessionService, trace_store::SessionTraceStore},
+            workspace::transport::{SshWorkspace, SshWorkspaceConfig},
         },
     },
     chunking::{editor_parsing::EditorParsing, languages::TSLanguageParsing},
-    inline_completion::{state::FillInMiddleState, symbols_tracker::SymbolTrackerInline},
+    inline_completion::{
+        provider_selection::CompletionProviderSelector, state::FillInMiddleState,
+        symbols_tracker::SymbolTrackerInline,
+    },
     reporting::posthog::client::{posthog_client, PosthogClient},
     webserver::agentic::{AnchoredEditingTracker, ProbeRequestTracker},
+    webserver::feedback::FeedbackStore,
+    webserver::jobs::JobTracker,
+    webserver::doc_index::DocumentationIndex,
+    webserver::edit_proposals::EditProposalRegistry,
+    webserver::pinned_context::PinnedContextTracker,
+    webserver::suggestions::SuggestionChannel,
 };
 
 use super::{config::configuration::Configuration, logging::tracing::tracing_subscribe};
@@ -56,12 +73,39 @@ pub struct Application {
     pub answer_models: Arc<LLMAnswerModelBroker>,
     pub editor_parsing: Arc<EditorParsing>,
     pub fill_in_middle_state: Arc<FillInMiddleState>,
+    /// Learns which completion capable model to route to per language from
+    /// latency and the accept/reject feedback the editor reports
+    pub completion_provider_selector: Arc<CompletionProviderSelector>,
     pub symbol_tracker: Arc<SymbolTrackerInline>,
     pub probe_request_tracker: Arc<ProbeRequestTracker>,
     pub symbol_manager: Arc<SymbolManager>,
     pub tool_box: Arc<ToolBox>,
+    /// Which sensitive files the user has allowed edits on, the broker
+    /// middleware blocks edits to the rest
+    pub sensitive_file_guard: Arc<SensitiveFileGuard>,
     pub anchored_request_tracker: Arc<AnchoredEditingTracker>,
     pub session_service: Arc<SessionService>,
+    /// Tracks long running background jobs which the editor polls on
+    pub job_tracker: Arc<JobTracker>,
+    /// Pinned context items per session which always get merged into the
+    /// prompt construction
+    pub pinned_context_tracker: Arc<PinnedContextTracker>,
+    /// Outstanding proposed edits awaiting accept/reject from the editor
+    pub edit_proposal_registry: Arc<EditProposalRegistry>,
+    /// Doc comments indexed per symbol for hover-grade answers
+    pub documentation_index: Arc<DocumentationIndex>,
+    /// A/B experiments over prompts and model choices along with their
+    /// outcome log
+    pub experiment_registry: Arc<ExperimentRegistry>,
+    /// Opt-in recorder building a local eval dataset out of real sessions
+    pub dataset_recorder: Arc<DatasetRecorder>,
+    /// Persisted user feedback joined with exchange traces
+    pub feedback_store: Arc<FeedbackStore>,
+    /// Compressed per-exchange session traces which load lazily
+    pub session_trace_store: Arc<SessionTraceStore>,
+    /// Low-priority suggestions from background analysis, pulled by the
+    /// editor whenever it wants to surface them
+    pub suggestion_channel: Arc<SuggestionChannel>,
 }
 
 impl Application {
@@ -74,8 +118,40 @@ impl Application {
         let repo_pool = config.state_source.initialize_pool()?;
         let config = Arc::new(config);
         let language_parsing = Arc::new(TSLanguageParsing::init());
+        // warm the tree-sitter parser pools for the languages present in the
+        // indexed repositories, first edits then skip parser initialization
+        let mut workspace_directories = vec![];
+        repo_pool.scan(|_, repo| {
+            workspace_directories.push(repo.disk_path.clone());
+        });
+        {
+            let language_parsing = language_parsing.clone();
+            tokio::task::spawn_blocking(move || {
+                language_parsing.warm_up_parsers(&workspace_directories);
+            });
+        }
         let posthog_client = posthog_client(&config.user_id);
-        let llm_broker = Arc::new(LLMBroker::new().await?);
+        // a zeroed out retry configuration (what Configuration::default gives
+        // the agent binaries) falls back to the broker's default policy
+        let retry_policy = if config.llm_retry_max_attempts > 0 {
+            LLMBrokerRetryPolicy::new(
+                config.llm_retry_max_attempts,
+                config.llm_retry_initial_backoff_ms,
+                config.llm_retry_max_backoff_ms,
+            )
+        } else {
+            LLMBrokerRetryPolicy::default()
+        };
+        let mut llm_broker = LLMBroker::new().await?.with_retry_policy(retry_policy);
+        if config.capture_llm_requests {
+            llm_broker = llm_broker.with_capture_directory(config.llm_capture_dir());
+        }
+        // same deal for the ollama url, Configuration::default leaves it empty
+        if !config.ollama_url.is_empty() {
+            llm_broker = llm_broker
+                .with_ollama_endpoint(config.ollama_url.clone(), config.ollama_keep_alive.clone());
+        }
+        let llm_broker = Arc::new(llm_broker);
         let llm_tokenizer = Arc::new(LLMTokenizer::new()?);
         let chat_broker = Arc::new(LLMChatModelBroker::init());
         let reranker = Arc::new(ReRankBroker::new());
@@ -85,22 +161,76 @@ impl Application {
         let fill_in_middle_state = Arc::new(FillInMiddleState::new());
         let symbol_tracker = Arc::new(SymbolTrackerInline::new(editor_parsing.clone()));
 
+        // do not apply the edits directly
+        let mut tool_broker_config = ToolBrokerConfiguration::new(None, config.apply_directly)
+            // file opens and outlines get cached on the broker, the plan
+            // prefetch warms these so step execution skips the editor round
+            // trip, an edit going through drops the cache wholesale
+            .with_cached_tool(ToolType::OpenFile)
+            .with_cached_tool(ToolType::OutlineNodesUsingEditor);
+        // remote-workspace mode: the file and terminal tools go over ssh to
+        // the machine which really holds the workspace
+        if let Some(connection) = config.remote_workspace_ssh.as_ref() {
+            match SshWorkspaceConfig::parse(
+                connection,
+                config.remote_workspace_exec_prefix.clone(),
+            ) {
+                Some(ssh_config) => {
+                    tool_broker_config = tool_broker_config
+                        .with_remote_workspace(Arc::new(SshWorkspace::new(ssh_config)));
+                }
+                None => {
+                    warn!(?connection, "invalid remote_workspace_ssh, ignoring");
+                }
+            }
+        }
+        // deployments switch off tools (or pin the broker to an allowlist)
+        // through a policy file next to the index
+        let tool_policy_path = config.index_dir.join("tool_policy.json");
+        if let Some(tool_policy) = ToolPolicy::load_from_file(&tool_policy_path).await {
+            debug!(?tool_policy_path, "tool policy loaded");
+            tool_broker_config = tool_broker_config.with_tool_policy(tool_policy);
+        }
+        // per-tool model overrides (cheap model for filtering, strong model
+        // for editing) load the same way
+        let model_overrides_path = config.index_dir.join("tool_model_overrides.json");
+        if let Some(model_overrides) =
+            ToolModelOverrides::load_from_file(&model_overrides_path).await
+        {
+            debug!(?model_overrides_path, "tool model overrides loaded");
+            tool_broker_config = tool_broker_config.with_model_overrides(model_overrides);
+        }
+        // debug flag: every tool invocation appends to a trajectory file
+        // which /tools/replay_trajectory can re-run later
+        if config.audit_tool_invocations {
+            tool_broker_config = tool_broker_config.with_audit_log(Arc::new(ToolAuditLog::new(
+                config.scratch_pad().join("tool_audit.jsonl"),
+            )));
+        }
+        // edits to .env/credential/secret files get blocked until the user
+        // confirms them through the webserver
+        let sensitive_file_guard = Arc::new(SensitiveFileGuard::new());
         let tool_broker = Arc::new(
             ToolBroker::new(
                 llm_broker.clone(),
                 Arc::new(CodeEditBroker::new()),
                 symbol_tracker.clone(),
                 language_parsing.clone(),
-                // do not apply the edits directly
-                ToolBrokerConfiguration::new(None, config.apply_directly),
+                tool_broker_config,
                 LLMProperties::new(
                     LLMType::Gpt4O,
                     LLMProvider::OpenAI,
                     LLMProviderAPIKeys::OpenAI(OpenAIProvider::new("".to_owned())),
                 ),
             )
-            .await,
+            .await
+            .with_middleware(Box::new(SensitiveFileEditGuardMiddleware::new(
+                sensitive_file_guard.clone(),
+            ))),
         );
+        // background ping loop which respawns crashed MCP servers and
+        // raises status events for the editor
+        tool_broker.start_mcp_health_monitor();
         let tool_box = Arc::new(ToolBox::new(
             tool_broker.clone(),
             symbol_tracker.clone(),
@@ -116,16 +246,19 @@ impl Application {
                 LLMProviderAPIKeys::Anthropic(AnthropicAPIKey::new("".to_owned())),
             ),
         ));
-        let session_service = Arc::new(SessionService::new(
-            tool_box.clone(),
-            symbol_manager.clone(),
+        let session_trace_store = Arc::new(SessionTraceStore::new(
+            config.scratch_pad().join("session_traces"),
         ));
+        let session_service = Arc::new(
+            SessionService::new(tool_box.clone(), symbol_manager.clone())
+                .with_trace_store(session_trace_store.clone()),
+        );
 
         let anchored_request_tracker = Arc::new(AnchoredEditingTracker::new());
         Ok(Self {
             config: config.clone(),
             repo_pool: repo_pool.clone(),
-            language_parsing,
+            language_parsing: language_parsing.clone(),
             posthog_client: Arc::new(posthog_client),
             user_id: config.user_id.clone(),
             llm_broker,
@@ -137,12 +270,30 @@ impl Application {
             answer_models,
             editor_parsing,
             fill_in_middle_state,
+            completion_provider_selector: Arc::new(CompletionProviderSelector::new()),
             symbol_tracker,
             probe_request_tracker: Arc::new(ProbeRequestTracker::new()),
             symbol_manager,
             tool_box,
+            sensitive_file_guard,
             anchored_request_tracker,
             session_service,
+            job_tracker: Arc::new(JobTracker::new()),
+            pinned_context_tracker: Arc::new(PinnedContextTracker::new()),
+            edit_proposal_registry: Arc::new(EditProposalRegistry::new()),
+            documentation_index: Arc::new(DocumentationIndex::new(language_parsing.clone())),
+            experiment_registry: Arc::new(ExperimentRegistry::new(
+                config.scratch_pad().join("experiments.jsonl"),
+            )),
+            dataset_recorder: Arc::new(DatasetRecorder::new(
+                config.enable_eval_recording,
+                config.scratch_pad().join("eval_dataset.jsonl"),
+            )),
+            feedback_store: Arc::new(FeedbackStore::new(
+                config.scratch_pad().join("feedback.jsonl"),
+            )),
+            session_trace_store,
+            suggestion_channel: Arc::new(SuggestionChannel::new()),
         })
     }
 
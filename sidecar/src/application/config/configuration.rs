@@ -60,6 +60,73 @@ pub struct Configuration {
     #[clap(long)]
     #[serde(default)]
     pub apply_directly: bool,
+
+    /// Maximum size of an incoming request body in megabytes, users attaching
+    /// very large contexts can bump this up (or use the streaming context
+    /// upload endpoint instead)
+    #[clap(long, default_value_t = default_request_body_limit_mb())]
+    #[serde(default = "default_request_body_limit_mb")]
+    pub request_body_limit_mb: usize,
+
+    /// Opt-in recording of anonymized task/edit/outcome tuples from real
+    /// sessions into a local eval dataset, everything stays on disk locally
+    #[clap(long)]
+    #[serde(default)]
+    pub enable_eval_recording: bool,
+
+    /// Debug flag which captures every LLM request/response pair (scrubbed
+    /// of keys and secrets) to per-session files under the scratch pad
+    #[clap(long)]
+    #[serde(default)]
+    pub capture_llm_requests: bool,
+
+    /// Debug flag which appends every tool invocation going through the
+    /// broker to a jsonl trajectory file under the scratch pad, replayable
+    /// through the webserver
+    #[clap(long)]
+    #[serde(default)]
+    pub audit_tool_invocations: bool,
+
+    /// Total attempts (including the first one) for LLM calls which fail
+    /// with a transient error like a rate limit
+    #[clap(long, default_value_t = default_llm_retry_max_attempts())]
+    #[serde(default = "default_llm_retry_max_attempts")]
+    pub llm_retry_max_attempts: usize,
+
+    /// Backoff in milliseconds before the first LLM retry, doubles on each
+    /// attempt after that
+    #[clap(long, default_value_t = default_llm_retry_initial_backoff_ms())]
+    #[serde(default = "default_llm_retry_initial_backoff_ms")]
+    pub llm_retry_initial_backoff_ms: u64,
+
+    /// Ceiling in milliseconds for the LLM retry backoff
+    #[clap(long, default_value_t = default_llm_retry_max_backoff_ms())]
+    #[serde(default = "default_llm_retry_max_backoff_ms")]
+    pub llm_retry_max_backoff_ms: u64,
+
+    /// Base url of the ollama server we send local model requests to
+    #[clap(long, default_value_t = default_ollama_url())]
+    #[serde(default = "default_ollama_url")]
+    pub ollama_url: String,
+
+    /// How long ollama keeps the model loaded after a request, passed
+    /// through verbatim (values like "5m", or "0" to unload immediately)
+    #[clap(long)]
+    #[serde(default)]
+    pub ollama_keep_alive: Option<String>,
+
+    /// Remote-workspace mode: the workspace lives on another machine reached
+    /// over ssh, formatted as "[user@]host[:port]". File reads, listings and
+    /// terminal commands run against the remote machine
+    #[clap(long)]
+    #[serde(default)]
+    pub remote_workspace_ssh: Option<String>,
+
+    /// Command prefix wrapped around remote commands when the workspace sits
+    /// inside a container on the remote host, for example "docker exec -i dev"
+    #[clap(long)]
+    #[serde(default)]
+    pub remote_workspace_exec_prefix: Option<String>,
 }
 
 impl Configuration {
@@ -79,6 +146,12 @@ impl Configuration {
     pub fn scratch_pad(&self) -> PathBuf {
         self.index_dir.join("scratch_pad")
     }
+
+    /// Directory where the scrubbed LLM request/response captures are written
+    /// when capture_llm_requests is turned on
+    pub fn llm_capture_dir(&self) -> PathBuf {
+        self.scratch_pad().join("llm_capture")
+    }
 }
 
 fn default_index_dir() -> PathBuf {
@@ -104,6 +177,26 @@ fn default_buffer_size() -> usize {
     100_000_000 * default_parallelism()
 }
 
+fn default_llm_retry_max_attempts() -> usize {
+    3
+}
+
+fn default_llm_retry_initial_backoff_ms() -> u64 {
+    500
+}
+
+fn default_llm_retry_max_backoff_ms() -> u64 {
+    10_000
+}
+
+fn default_ollama_url() -> String {
+    "http://localhost:11434".to_owned()
+}
+
+fn default_request_body_limit_mb() -> usize {
+    20
+}
+
 fn default_collection_name() -> String {
     "codestory".to_owned()
 }
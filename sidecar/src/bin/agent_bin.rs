@@ -209,6 +209,7 @@ Your thinking should be thorough and so it's fine if it's very long."#,
             false,
             Some(args.log_directory.clone()),
             Some(args.repo_name.clone()),
+            None,
             message_properties,
             false, // not in devtools context
         )
@@ -202,6 +202,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             false,
             Some(args.log_directory.clone()),
             Some(args.repo_name.clone()),
+            None,
             message_properties,
             false, // not in devtools context
         )
@@ -3,7 +3,7 @@
 
 use anyhow::Result;
 use axum::extract::DefaultBodyLimit;
-use axum::routing::get;
+use axum::routing::{get, post};
 use axum::Extension;
 use clap::Parser;
 use sidecar::application::{application::Application, config::configuration::Configuration};
@@ -142,7 +142,8 @@ pub async fn start(app: Application) -> anyhow::Result<()> {
     // routes through middleware
     let protected_routes = Router::new()
         .nest("/agentic", agentic_router())
-        .nest("/plan", plan_router());
+        .nest("/plan", plan_router())
+        .nest("/context", context_router());
     // .layer(from_fn(auth_middleware)); // routes through middleware
 
     // no middleware check
@@ -153,8 +154,52 @@ pub async fn start(app: Application) -> anyhow::Result<()> {
             get(sidecar::webserver::config::reach_the_devs),
         )
         .route("/version", get(sidecar::webserver::config::version))
+        // the editor declares its protocol version before anything else,
+        // incompatible versions get a clear upgrade message instead of
+        // confusing failures later
+        .route(
+            "/version/handshake",
+            post(sidecar::webserver::version_negotiation::version_handshake),
+        )
+        // checks the release manifest and stages a newer sidecar binary
+        // for the editor to swap in
+        .route(
+            "/version/self_update",
+            post(sidecar::webserver::version_negotiation::check_self_update),
+        )
+        // dry-run check of the azure per-model deployment mapping
+        .route(
+            "/config/validate_azure",
+            post(sidecar::webserver::model_selection::validate_azure_deployments),
+        )
         .nest("/tree_sitter", tree_sitter_router())
-        .nest("/file", file_operations_router());
+        .nest("/file", file_operations_router())
+        .nest("/jobs", jobs_router())
+        .route(
+            "/debug/runtime",
+            get(sidecar::webserver::debug::runtime_stats),
+        )
+        // index over the scrubbed per-session LLM capture files
+        .route(
+            "/debug/llm_capture",
+            get(sidecar::webserver::debug::llm_capture_index),
+        )
+        // queue metrics of the per-provider LLM request limiter
+        .route(
+            "/debug/llm_concurrency",
+            get(sidecar::webserver::debug::llm_concurrency_stats),
+        )
+        // compressed session traces, fetched one exchange at a time
+        .route(
+            "/debug/session_trace/:session_id",
+            get(sidecar::webserver::debug::session_trace_index),
+        )
+        .route(
+            "/debug/session_trace/:session_id/:exchange_id",
+            get(sidecar::webserver::debug::session_trace_exchange),
+        )
+        // time-to-first-token and throughput averages per model
+        .route("/stats/llm", get(sidecar::webserver::stats::llm_stats));
 
     // both protected and public merged into api
     let mut api = Router::new().merge(protected_routes).merge(public_routes);
@@ -167,8 +212,11 @@ pub async fn start(app: Application) -> anyhow::Result<()> {
         .with_state(app.clone())
         .layer(CorsLayer::permissive())
         .layer(CatchPanicLayer::new())
-        // I want to set the bytes limit here to 20 MB
-        .layer(DefaultBodyLimit::max(20 * 1024 * 1024));
+        // the limit defaults to 20MB and is configurable for users who
+        // attach very large contexts
+        .layer(DefaultBodyLimit::max(
+            app.config.request_body_limit_mb * 1024 * 1024,
+        ));
 
     let router = Router::new().nest("/api", api);
 
@@ -196,6 +244,90 @@ fn agentic_router() -> Router {
             "/probe_request_stop",
             post(sidecar::webserver::agentic::probe_request_stop),
         )
+        // structured explanation of a symbol or selection over the probing
+        // stack, the final reply gets cached on disk
+        .route("/explain", post(sidecar::webserver::explain::agent_explain))
+        // active tool set under the current policy and runtime policy swaps
+        .route("/tools", get(sidecar::webserver::tools::list_tools))
+        .route(
+            "/tools/policy",
+            post(sidecar::webserver::tools::update_tool_policy),
+        )
+        // per-tool model overrides on the broker and runtime swaps of the map
+        .route(
+            "/tools/model_overrides",
+            get(sidecar::webserver::tools::list_model_overrides)
+                .post(sidecar::webserver::tools::update_model_overrides),
+        )
+        // re-runs a recorded tool trajectory against the live broker
+        .route(
+            "/tools/replay_trajectory",
+            post(sidecar::webserver::tools::replay_trajectory),
+        )
+        // pending passive suggestions from background analysis and the
+        // per-category enable flags
+        .route(
+            "/suggestions",
+            get(sidecar::webserver::suggestions::list_suggestions),
+        )
+        .route(
+            "/suggestions/settings",
+            post(sidecar::webserver::suggestions::update_suggestion_settings),
+        )
+        // re-reads the MCP config and reconciles the running servers
+        // against it without a restart
+        .route(
+            "/mcp/reload",
+            post(sidecar::webserver::tools::reload_mcp),
+        )
+        // resources the running MCP servers expose and attaching one as
+        // pinned session context
+        .route(
+            "/mcp/resources",
+            get(sidecar::webserver::tools::list_mcp_resources),
+        )
+        .route(
+            "/mcp/resources/attach",
+            post(sidecar::webserver::tools::attach_mcp_resource),
+        )
+        // prompt templates the running MCP servers expose, the editor
+        // renders them as slash commands
+        .route(
+            "/mcp/prompts",
+            get(sidecar::webserver::tools::list_mcp_prompts),
+        )
+        // health of the running MCP servers and a stream of the
+        // transitions the monitor raises
+        .route(
+            "/mcp/health",
+            get(sidecar::webserver::tools::mcp_health),
+        )
+        .route(
+            "/mcp/health/events",
+            get(sidecar::webserver::tools::mcp_health_events),
+        )
+        // reproducible runs: pins the sampling seed and serializes tool
+        // execution so a benchmark or bug report replays bit-for-bit
+        .route(
+            "/tools/determinism",
+            post(sidecar::webserver::tools::set_determinism),
+        )
+        // user decision on whether an agent may edit a .env/credential
+        // file, the broker blocks those edits until confirmed
+        .route(
+            "/sensitive_file_confirm",
+            post(sidecar::webserver::tools::confirm_sensitive_file_edit),
+        )
+        // call-site preview for an edit which changes a signature and the
+        // user's decision on what to do about the callers
+        .route(
+            "/signature_change_preview",
+            post(sidecar::webserver::signature_change::signature_change_preview),
+        )
+        .route(
+            "/signature_change_decision",
+            post(sidecar::webserver::signature_change::signature_change_decision),
+        )
         .route(
             "/code_sculpting_followup",
             post(sidecar::webserver::agentic::code_sculpting),
@@ -248,10 +380,69 @@ fn agentic_router() -> Router {
             "/user_feedback_on_exchange",
             post(sidecar::webserver::agentic::user_feedback_on_exchange),
         )
+        // aggregated view over the persisted feedback records
+        .route(
+            "/feedback_aggregate",
+            get(sidecar::webserver::feedback::feedback_aggregate),
+        )
         .route(
             "/user_handle_session_undo",
             post(sidecar::webserver::agentic::handle_session_undo),
         )
+        // forks a session at an exchange so alternatives can be explored
+        // without losing the original thread
+        .route(
+            "/user_handle_session_fork",
+            post(sidecar::webserver::agentic::handle_session_fork),
+        )
+        // low latency fast path for selection anchored quick edits, skips
+        // planning and followups entirely
+        .route(
+            "/quick_edit",
+            post(sidecar::webserver::quick_edit::quick_edit),
+        )
+        // discovery endpoint for editor side slash command auto-completion
+        .route(
+            "/review_diff",
+            post(sidecar::webserver::review::review_diff),
+        )
+        // categorized changelog entries out of session history and git diffs
+        .route(
+            "/generate_changelog",
+            post(sidecar::webserver::changelog::generate_changelog),
+        )
+        // per-conflict merge resolution proposals with an approve-then-apply loop
+        .route(
+            "/resolve_merge_conflicts",
+            post(sidecar::webserver::merge_conflicts::resolve_merge_conflicts),
+        )
+        // TODO/FIXME/HACK harvesting clustered by area, seeds plan queries
+        .route(
+            "/harvest_todos",
+            post(sidecar::webserver::todos::harvest_todos),
+        )
+        .route(
+            "/slash_commands",
+            get(sidecar::webserver::slash_commands::list_slash_commands),
+        )
+        // doc-comment lookup per symbol, instant answers for "what does X do"
+        .route(
+            "/symbol_documentation",
+            post(sidecar::webserver::doc_index::symbol_documentation),
+        )
+        // proposed changes the editor renders as inline accept/reject widgets
+        .route(
+            "/propose_edits",
+            post(sidecar::webserver::edit_proposals::propose_edits),
+        )
+        .route(
+            "/list_edit_proposals",
+            post(sidecar::webserver::edit_proposals::list_edit_proposals),
+        )
+        .route(
+            "/resolve_edit_proposal",
+            post(sidecar::webserver::edit_proposals::resolve_edit_proposal),
+        )
 }
 
 fn tree_sitter_router() -> Router {
@@ -277,5 +468,41 @@ fn tree_sitter_router() -> Router {
 
 fn file_operations_router() -> Router {
     use axum::routing::*;
-    Router::new().route("/edit_file", post(sidecar::webserver::file_edit::file_edit))
+    Router::new()
+        .route("/edit_file", post(sidecar::webserver::file_edit::file_edit))
+        // streaming upload for oversized user context, the body limit is
+        // disabled here since the payload is streamed to the scratch pad
+        .route(
+            "/upload_context",
+            post(sidecar::webserver::context_upload::upload_context)
+                .layer(DefaultBodyLimit::disable()),
+        )
+}
+
+// Routes for managing pinned context items for a session
+fn context_router() -> Router {
+    use axum::routing::*;
+    Router::new()
+        .route("/pin", post(sidecar::webserver::pinned_context::pin_context))
+        .route(
+            "/unpin",
+            post(sidecar::webserver::pinned_context::unpin_context),
+        )
+        .route(
+            "/pins/:session_id",
+            get(sidecar::webserver::pinned_context::list_pinned_context),
+        )
+}
+
+// Routes for inspecting and cancelling long running background jobs
+fn jobs_router() -> Router {
+    use axum::routing::*;
+    Router::new()
+        // spawns a repo map build tracked as a background job
+        .route(
+            "/repo_map",
+            post(sidecar::webserver::jobs::repo_map_generate),
+        )
+        .route("/:job_id", get(sidecar::webserver::jobs::job_status))
+        .route("/:job_id/cancel", post(sidecar::webserver::jobs::job_cancel))
 }
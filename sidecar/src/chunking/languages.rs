@@ -13,6 +13,7 @@ use crate::{
 use super::{
     go::go_language_config,
     javascript::javascript_language_config,
+    parser_pool::{self, PooledParser},
     python::python_language_config,
     rust::rust_language_config,
     text_document::{Position, Range},
@@ -161,10 +162,14 @@ impl TSLanguageConfig {
         self.language_ids.first().map(|s| s.to_string())
     }
 
+    /// Checks out a parser for this language from the global pool, the
+    /// grammar is already set on the parser which comes back
+    pub fn pooled_parser(&self) -> PooledParser {
+        parser_pool::checkout(&self.language_str, self.grammar)
+    }
+
     pub fn is_valid_code(&self, code: &str) -> bool {
-        let grammar = self.grammar;
-        let mut parser = tree_sitter::Parser::new();
-        parser.set_language(grammar()).unwrap();
+        let mut parser = self.pooled_parser();
         let tree_maybe = parser.parse(code, None);
         tree_maybe
             .map(|tree| !tree.root_node().has_error())
@@ -182,8 +187,7 @@ impl TSLanguageConfig {
     /// operations
     pub fn hoverable_nodes(&self, source_code: &[u8]) -> Vec<Range> {
         let grammar = self.grammar;
-        let mut parser = tree_sitter::Parser::new();
-        parser.set_language(grammar()).unwrap();
+        let mut parser = self.pooled_parser();
         let hoverable_query = self.hoverable_query.to_owned();
         let tree = parser.parse(source_code, None).unwrap();
         let query = tree_sitter::Query::new(grammar(), &hoverable_query).expect("to work");
@@ -211,11 +215,7 @@ impl TSLanguageConfig {
         source_code: &[u8],
         fs_file_path: &str,
     ) -> Vec<OutlineNode> {
-        let grammar = self.grammar;
-        let mut parser = tree_sitter::Parser::new();
-        parser
-            .set_language(grammar())
-            .expect("for lanaguage parser from tree_sitter should not fail");
+        let mut parser = self.pooled_parser();
         let tree = parser.parse(source_code, None).unwrap();
         self.generate_outline(source_code, &tree, fs_file_path.to_owned())
     }
@@ -227,8 +227,6 @@ impl TSLanguageConfig {
         fs_file_path: String,
     ) -> Vec<OutlineNode> {
         let grammar = self.grammar;
-        let mut parser = tree_sitter::Parser::new();
-        parser.set_language(grammar()).unwrap();
         let outline_query = self.outline_query.clone();
         if let None = outline_query {
             return vec![];
@@ -684,10 +682,7 @@ impl TSLanguageConfig {
     /// when the LLM is writing code
     pub fn generate_function_call_paths(&self, source_code: &[u8]) -> Option<Vec<(String, Range)>> {
         let grammar = self.grammar;
-        let mut parser = tree_sitter::Parser::new();
-        parser
-            .set_language(grammar())
-            .expect("for lanaguage parser from tree_sitter should not fail");
+        let mut parser = self.pooled_parser();
         let tree = parser.parse(source_code, None).unwrap();
         let function_call_path = self.function_call_path.to_owned();
         let node = tree.root_node();
@@ -719,10 +714,7 @@ impl TSLanguageConfig {
     /// on to learn more about the types
     pub fn generate_function_insights(&self, source_code: &[u8]) -> Vec<(String, Range)> {
         let grammar = self.grammar;
-        let mut parser = tree_sitter::Parser::new();
-        parser
-            .set_language(grammar())
-            .expect("for lanaguage parser from tree_sitter should not fail");
+        let mut parser = self.pooled_parser();
         let tree = parser.parse(source_code, None).unwrap();
         let required_parameter_types_for_functions =
             self.required_parameter_types_for_functions.to_owned();
@@ -751,11 +743,7 @@ impl TSLanguageConfig {
     /// This function generates the tree by parsing the source code and can be
     /// used when we do not have the tree sitter tree already created
     pub fn generate_import_identifiers_fresh(&self, source_code: &[u8]) -> Vec<(String, Range)> {
-        let grammar = self.grammar;
-        let mut parser = tree_sitter::Parser::new();
-        parser
-            .set_language(grammar())
-            .expect("for lanaguage parser from tree_sitter should not fail");
+        let mut parser = self.pooled_parser();
         let tree = parser.parse(source_code, None).unwrap();
         self.generate_import_identifier_nodes(source_code, &tree)
     }
@@ -766,8 +754,6 @@ impl TSLanguageConfig {
         tree: &Tree,
     ) -> Vec<(String, Range)> {
         let grammar = self.grammar;
-        let mut parser = tree_sitter::Parser::new();
-        parser.set_language(grammar()).unwrap();
         let import_identifier_query = self.import_identifier_queries.to_owned();
         let node = tree.root_node();
         let query = tree_sitter::Query::new(grammar(), &import_identifier_query).expect("to work");
@@ -934,9 +920,8 @@ impl TSLanguageConfig {
 
     pub fn capture_documentation_queries(&self, source_code: &[u8]) -> Vec<(Range, String)> {
         // Now we try to grab the documentation strings so we can add them to the functions as well
-        let mut parser = tree_sitter::Parser::new();
         let grammar = self.grammar;
-        parser.set_language(grammar()).unwrap();
+        let mut parser = self.pooled_parser();
         let parsed_data = parser.parse(source_code, None).unwrap();
         let node = parsed_data.root_node();
         let mut range_set = HashSet::new();
@@ -976,9 +961,7 @@ impl TSLanguageConfig {
     }
 
     pub fn get_tree_sitter_tree(&self, source_code: &[u8]) -> Option<Tree> {
-        let grammar = self.grammar;
-        let mut parser = tree_sitter::Parser::new();
-        parser.set_language(grammar()).unwrap();
+        let mut parser = self.pooled_parser();
         parser.parse(source_code, None)
     }
 
@@ -986,8 +969,7 @@ impl TSLanguageConfig {
         let type_queries = self.type_query.to_vec();
 
         let grammar = self.grammar;
-        let mut parser = tree_sitter::Parser::new();
-        parser.set_language(grammar()).unwrap();
+        let mut parser = self.pooled_parser();
         let parsed_data = parser.parse(source_code, None).unwrap();
         let node = parsed_data.root_node();
 
@@ -1091,8 +1073,7 @@ impl TSLanguageConfig {
         let class_queries = self.class_query.to_vec();
 
         let grammar = self.grammar;
-        let mut parser = tree_sitter::Parser::new();
-        parser.set_language(grammar()).unwrap();
+        let mut parser = self.pooled_parser();
         let parsed_data = parser.parse(source_code, None).unwrap();
         let node = parsed_data.root_node();
 
@@ -1407,9 +1388,7 @@ impl TSLanguageConfig {
     }
 
     pub fn capture_function_data(&self, source_code: &[u8]) -> Vec<FunctionInformation> {
-        let grammar = self.grammar;
-        let mut parser = tree_sitter::Parser::new();
-        parser.set_language(grammar()).unwrap();
+        let mut parser = self.pooled_parser();
         let parsed_data = parser.parse(source_code, None).unwrap();
         self.capture_function_data_with_tree(source_code, &parsed_data, false)
     }
@@ -1422,6 +1401,17 @@ impl TSLanguageConfig {
         fname: &PathBuf,
         rel_fname: &PathBuf,
         file_content: Vec<u8>,
+    ) -> Vec<Tag> {
+        self.get_tags_sync(fname, rel_fname, file_content)
+    }
+
+    /// Synchronous version of [`Self::get_tags`] for the CPU-bound bulk
+    /// parsing paths which run on a rayon pool off the async runtime
+    pub fn get_tags_sync(
+        &self,
+        fname: &PathBuf,
+        rel_fname: &PathBuf,
+        file_content: Vec<u8>,
     ) -> Vec<Tag> {
         let tree = match self.get_tree_sitter_tree(file_content.as_slice()) {
             Some(tree) => tree,
@@ -1480,8 +1470,7 @@ impl TSLanguageConfig {
         // Now we need to run the tree sitter query on this and get back the
         // answer
         let grammar = self.grammar;
-        let mut parser = tree_sitter::Parser::new();
-        parser.set_language(grammar()).unwrap();
+        let mut parser = self.pooled_parser();
         let parsed_data = parser.parse(source_code, None).unwrap();
         let node = parsed_data.root_node();
         let mut function_nodes = vec![];
@@ -1524,8 +1513,7 @@ impl TSLanguageConfig {
 
     pub fn generate_object_qualifier(&self, source_code: &[u8]) -> Option<Range> {
         let grammar = self.grammar;
-        let mut parser = tree_sitter::Parser::new();
-        parser.set_language(grammar()).unwrap();
+        let mut parser = self.pooled_parser();
         let object_qualifier_query = self.object_qualifier.to_owned();
         let tree = parser.parse(source_code, None).unwrap();
 
@@ -1563,6 +1551,40 @@ impl TSLanguageParsing {
         }
     }
 
+    /// Preloads pooled parsers for the languages we detect in the workspace
+    /// directories, the first edit then skips parser initialization entirely.
+    /// Languages which do not show up in the workspace stay lazy
+    pub fn warm_up_parsers(&self, workspace_directories: &[PathBuf]) {
+        let mut detected: HashSet<&str> = HashSet::new();
+        for directory in workspace_directories {
+            let walk = ignore::WalkBuilder::new(directory)
+                .max_depth(Some(2))
+                .build();
+            for entry in walk.flatten() {
+                let extension = entry
+                    .path()
+                    .extension()
+                    .and_then(|extension| extension.to_str());
+                if let Some(extension) = extension {
+                    if let Some(config) = self
+                        .configs
+                        .iter()
+                        .find(|config| config.file_extensions.contains(&extension))
+                    {
+                        detected.insert(config.language_str.as_str());
+                    }
+                }
+            }
+        }
+        for config in self
+            .configs
+            .iter()
+            .filter(|config| detected.contains(config.language_str.as_str()))
+        {
+            parser_pool::preload(&config.language_str, config.grammar, 2);
+        }
+    }
+
     pub fn for_lang(&self, language: &str) -> Option<&TSLanguageConfig> {
         self.configs
             .iter()
@@ -1612,9 +1634,7 @@ impl TSLanguageParsing {
         if let Some(language_config) = language_config_maybe {
             // We use tree-sitter to parse the file and get the chunks
             // for the file
-            let language = language_config.grammar;
-            let mut parser = tree_sitter::Parser::new();
-            parser.set_language(language()).unwrap();
+            let mut parser = language_config.pooled_parser();
             let tree = parser.parse(buffer.as_bytes(), None).unwrap();
             // we allow for 1500 characters and 100 character coalesce
             let chunks = chunk_tree(&tree, language_config, 2500, 100, &buffer);
@@ -1636,8 +1656,7 @@ impl TSLanguageParsing {
         let language_config = language_config_maybe.expect("if let None check above to hold");
         let grammar = language_config.grammar;
         let documentation_queries = language_config.documentation_query.to_vec();
-        let mut parser = tree_sitter::Parser::new();
-        parser.set_language(grammar()).unwrap();
+        let mut parser = language_config.pooled_parser();
         let parsed_data = parser.parse(code, None).unwrap();
         let node = parsed_data.root_node();
         let mut nodes = vec![];
@@ -1692,8 +1711,7 @@ impl TSLanguageParsing {
         // Now we need to run the tree sitter query on this and get back the
         // answer
         let grammar = language_config.grammar;
-        let mut parser = tree_sitter::Parser::new();
-        parser.set_language(grammar()).unwrap();
+        let mut parser = language_config.pooled_parser();
         let parsed_data = parser.parse(source_code.as_bytes(), None).unwrap();
         let node = parsed_data.root_node();
         let mut function_nodes = vec![];
@@ -1746,9 +1764,7 @@ impl TSLanguageParsing {
             return None;
         }
         let language_config = language_config.expect("if let None check above to hold");
-        let grammar = language_config.grammar;
-        let mut parser = tree_sitter::Parser::new();
-        parser.set_language(grammar()).unwrap();
+        let mut parser = language_config.pooled_parser();
         let parsed_data = parser.parse(source_code.as_bytes(), None).unwrap();
         let node = parsed_data.root_node();
         let descendant_node_maybe =
@@ -1790,8 +1806,7 @@ impl TSLanguageParsing {
         }
         let language_config = language_config.expect("if let None check above to hold");
         let grammar = language_config.grammar;
-        let mut parser = tree_sitter::Parser::new();
-        parser.set_language(grammar()).unwrap();
+        let mut parser = language_config.pooled_parser();
         let parsed_data = parser.parse(source_code.as_bytes(), None).unwrap();
         let node = parsed_data.root_node();
         let query = language_config
@@ -4,6 +4,7 @@ mod go;
 mod helpers;
 mod javascript;
 pub mod languages;
+pub mod parser_pool;
 mod python;
 mod rust;
 pub mod text_document;
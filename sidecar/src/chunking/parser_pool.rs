@@ -0,0 +1,146 @@
+//! Global pool of tree-sitter parsers keyed by language. Today we create a
+//! fresh parser and set the grammar on it for every single parse, pooling
+//! them keeps the warm path allocation free and lets us preload parsers at
+//! startup so the first edit does not pay the initialization cost
+
+use std::collections::HashMap;
+use std::ops::{Deref, DerefMut};
+use std::sync::Mutex;
+
+use once_cell::sync::Lazy;
+
+/// How many idle parsers we keep per language, anything beyond this gets
+/// dropped when its checkout guard goes out of scope
+const MAX_IDLE_PARSERS_PER_LANGUAGE: usize = 8;
+
+static PARSER_POOL: Lazy<Mutex<HashMap<String, Vec<tree_sitter::Parser>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Guard over a parser checked out from the pool, derefs to the parser and
+/// hands it back to the pool on drop
+pub struct PooledParser {
+    parser: Option<tree_sitter::Parser>,
+    language_str: String,
+}
+
+impl Deref for PooledParser {
+    type Target = tree_sitter::Parser;
+
+    fn deref(&self) -> &Self::Target {
+        self.parser
+            .as_ref()
+            .expect("parser is only taken out on drop")
+    }
+}
+
+impl DerefMut for PooledParser {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.parser
+            .as_mut()
+            .expect("parser is only taken out on drop")
+    }
+}
+
+impl Drop for PooledParser {
+    fn drop(&mut self) {
+        if let Some(mut parser) = self.parser.take() {
+            // clear any half-finished parse state before the next user
+            parser.reset();
+            if let Ok(mut pool) = PARSER_POOL.lock() {
+                let idle = pool.entry(self.language_str.to_owned()).or_default();
+                if idle.len() < MAX_IDLE_PARSERS_PER_LANGUAGE {
+                    idle.push(parser);
+                }
+            }
+        }
+    }
+}
+
+/// Checks out a parser for the language, reusing an idle one from the pool
+/// when possible. The grammar is already set on the parser which comes back
+pub fn checkout(language_str: &str, grammar: fn() -> tree_sitter::Language) -> PooledParser {
+    let reused = PARSER_POOL
+        .lock()
+        .ok()
+        .and_then(|mut pool| pool.get_mut(language_str).and_then(|idle| idle.pop()));
+    let parser = match reused {
+        Some(parser) => parser,
+        None => {
+            let mut parser = tree_sitter::Parser::new();
+            parser
+                .set_language(grammar())
+                .expect("grammar versions are pinned by the build");
+            parser
+        }
+    };
+    PooledParser {
+        parser: Some(parser),
+        language_str: language_str.to_owned(),
+    }
+}
+
+/// Fills the pool for a language up to `count` idle parsers, used at startup
+/// to warm languages we detected in the workspace
+pub fn preload(language_str: &str, grammar: fn() -> tree_sitter::Language, count: usize) {
+    let Ok(mut pool) = PARSER_POOL.lock() else {
+        return;
+    };
+    let idle = pool.entry(language_str.to_owned()).or_default();
+    while idle.len() < count.min(MAX_IDLE_PARSERS_PER_LANGUAGE) {
+        let mut parser = tree_sitter::Parser::new();
+        parser
+            .set_language(grammar())
+            .expect("grammar versions are pinned by the build");
+        idle.push(parser);
+    }
+}
+
+/// How many idle parsers the pool currently holds for a language
+pub fn idle_count(language_str: &str) -> usize {
+    PARSER_POOL
+        .lock()
+        .map(|pool| {
+            pool.get(language_str)
+                .map(|idle| idle.len())
+                .unwrap_or_default()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checkout_returns_a_working_parser() {
+        let mut parser = checkout("rust_pool_test", tree_sitter_rust::language);
+        let tree = parser.parse("fn main() {}", None).expect("parse to work");
+        assert!(!tree.root_node().has_error());
+    }
+
+    #[test]
+    fn test_parser_goes_back_to_the_pool_on_drop() {
+        let language = "rust_pool_test_drop";
+        assert_eq!(idle_count(language), 0);
+        {
+            let _parser = checkout(language, tree_sitter_rust::language);
+            assert_eq!(idle_count(language), 0);
+        }
+        assert_eq!(idle_count(language), 1);
+        // the reused parser still parses correctly
+        let mut parser = checkout(language, tree_sitter_rust::language);
+        assert_eq!(idle_count(language), 0);
+        let tree = parser.parse("struct A;", None).expect("parse to work");
+        assert!(!tree.root_node().has_error());
+    }
+
+    #[test]
+    fn test_preload_fills_the_pool() {
+        let language = "rust_pool_test_preload";
+        preload(language, tree_sitter_rust::language, 3);
+        assert_eq!(idle_count(language), 3);
+        // preloading again does not go beyond the requested count
+        preload(language, tree_sitter_rust::language, 3);
+        assert_eq!(idle_count(language), 3);
+    }
+}
@@ -168,6 +168,111 @@ impl Position {
         self.byte_offset = 0;
         self
     }
+
+    /// Builds a position from a column measured in the given encoding, the
+    /// stored character is always in code points. Columns landing inside a
+    /// multi-byte character round up to the next boundary
+    pub fn from_encoded_column(
+        line: usize,
+        column: usize,
+        line_content: &str,
+        encoding: PositionEncoding,
+    ) -> Self {
+        match encoding {
+            PositionEncoding::Byte => Self::from_byte_column(line, column, line_content),
+            PositionEncoding::Utf8CodePoint => {
+                Self::new(line, column.min(line_content.chars().count()), 0)
+            }
+            PositionEncoding::Utf16CodeUnit => Self::from_utf16_column(line, column, line_content),
+        }
+    }
+
+    /// The column of this position measured in the given encoding
+    pub fn column_in_encoding(&self, line_content: &str, encoding: PositionEncoding) -> usize {
+        match encoding {
+            PositionEncoding::Byte => self.byte_column(line_content),
+            PositionEncoding::Utf8CodePoint => self.character.min(line_content.chars().count()),
+            PositionEncoding::Utf16CodeUnit => self.utf16_column(line_content),
+        }
+    }
+
+    /// Converts a byte column (what tree-sitter points carry) to a position
+    /// with a code point character
+    pub fn from_byte_column(line: usize, byte_column: usize, line_content: &str) -> Self {
+        let character = line_content
+            .char_indices()
+            .take_while(|(byte_index, _)| *byte_index < byte_column)
+            .count();
+        Self::new(line, character, 0)
+    }
+
+    /// Converts a utf-16 code unit column (what the LSP sends by default) to
+    /// a position with a code point character
+    pub fn from_utf16_column(line: usize, utf16_column: usize, line_content: &str) -> Self {
+        let mut utf16_units_seen = 0;
+        let mut character = 0;
+        for current_char in line_content.chars() {
+            if utf16_units_seen >= utf16_column {
+                break;
+            }
+            utf16_units_seen += current_char.len_utf16();
+            character += 1;
+        }
+        Self::new(line, character, 0)
+    }
+
+    /// The byte offset of this position inside its line, safe to use for
+    /// string slicing since it always lands on a character boundary
+    pub fn byte_column(&self, line_content: &str) -> usize {
+        line_content
+            .char_indices()
+            .nth(self.character)
+            .map(|(byte_index, _)| byte_index)
+            .unwrap_or(line_content.len())
+    }
+
+    /// The column in utf-16 code units, what we have to hand back to the LSP
+    pub fn utf16_column(&self, line_content: &str) -> usize {
+        line_content
+            .chars()
+            .take(self.character)
+            .map(|current_char| current_char.len_utf16())
+            .sum()
+    }
+
+    /// Re-encodes the column for the wire using the file this position points
+    /// into, positions on lines past the end of the file are left unchanged
+    pub fn encoded_for_file(&self, file_content: &str, encoding: PositionEncoding) -> Self {
+        match file_content.lines().nth(self.line) {
+            Some(line_content) => {
+                let mut position = self.clone();
+                position.character = self.column_in_encoding(line_content, encoding);
+                position
+            }
+            None => self.clone(),
+        }
+    }
+
+    /// Decodes a position which came over the wire in the given encoding back
+    /// into the code point columns we track internally
+    pub fn decoded_from_file(&self, file_content: &str, encoding: PositionEncoding) -> Self {
+        match file_content.lines().nth(self.line) {
+            Some(line_content) => {
+                Self::from_encoded_column(self.line, self.character, line_content, encoding)
+            }
+            None => self.clone(),
+        }
+    }
+}
+
+/// The unit a column value is measured in. tree-sitter points carry byte
+/// columns, the LSP talks utf-16 code units by default and our own Position
+/// stores code points, mixing these up corrupts edits in non-ascii files
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PositionEncoding {
+    Byte,
+    Utf8CodePoint,
+    Utf16CodeUnit,
 }
 
 #[derive(
@@ -211,6 +316,24 @@ impl Range {
         self.end_position.clone()
     }
 
+    /// Re-encodes both ends of the range for the wire, see
+    /// [`Position::encoded_for_file`]
+    pub fn encoded_for_file(&self, file_content: &str, encoding: PositionEncoding) -> Self {
+        Range::new(
+            self.start_position.encoded_for_file(file_content, encoding),
+            self.end_position.encoded_for_file(file_content, encoding),
+        )
+    }
+
+    /// Decodes both ends of a range which came over the wire, see
+    /// [`Position::decoded_from_file`]
+    pub fn decoded_from_file(&self, file_content: &str, encoding: PositionEncoding) -> Self {
+        Range::new(
+            self.start_position.decoded_from_file(file_content, encoding),
+            self.end_position.decoded_from_file(file_content, encoding),
+        )
+    }
+
     pub fn start_byte(&self) -> usize {
         self.start_position.byte_offset
     }
@@ -409,7 +532,14 @@ impl Range {
         }
     }
 
+    /// The minimal distance in lines between the two ranges
+    ///
+    /// Overlapping ranges are at distance 0, otherwise this is the gap
+    /// between the closest pair of endpoints
     pub fn minimal_line_distance(&self, other: &Range) -> i64 {
+        if self.intersects_without_byte(other) {
+            return 0;
+        }
         let self_start_line: i64 = self.start_line().try_into().unwrap();
         let self_end_line: i64 = self.end_line().try_into().unwrap();
         let other_start_line: i64 = other.start_line().try_into().unwrap();
@@ -739,3 +869,55 @@ impl OutlineForRange {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Position, PositionEncoding};
+
+    #[test]
+    fn test_utf16_columns_round_trip_through_emoji() {
+        // the crab emoji is one code point but two utf-16 code units and
+        // four bytes
+        let line = "let 🦀 = crab;";
+        let position = Position::from_utf16_column(0, 6, line);
+        assert_eq!(position.column(), 5);
+        assert_eq!(position.utf16_column(line), 6);
+        assert_eq!(position.byte_column(line), 8);
+    }
+
+    #[test]
+    fn test_byte_columns_round_trip_through_cjk() {
+        // each of these characters is three bytes and one utf-16 code unit
+        let line = "你好世界 = greeting";
+        let position = Position::from_byte_column(0, 12, line);
+        assert_eq!(position.column(), 4);
+        assert_eq!(position.byte_column(line), 12);
+        assert_eq!(position.utf16_column(line), 4);
+    }
+
+    #[test]
+    fn test_columns_are_clamped_to_the_line_end() {
+        let line = "短い";
+        let position = Position::from_encoded_column(0, 50, line, PositionEncoding::Utf8CodePoint);
+        assert_eq!(position.column(), 2);
+        assert_eq!(position.byte_column(line), line.len());
+        assert_eq!(
+            Position::from_byte_column(0, 100, line).byte_column(line),
+            line.len()
+        );
+    }
+
+    #[test]
+    fn test_encodings_agree_on_ascii() {
+        let line = "fn main() {}";
+        for encoding in [
+            PositionEncoding::Byte,
+            PositionEncoding::Utf8CodePoint,
+            PositionEncoding::Utf16CodeUnit,
+        ] {
+            let position = Position::from_encoded_column(0, 3, line, encoding);
+            assert_eq!(position.column(), 3);
+            assert_eq!(position.column_in_encoding(line, encoding), 3);
+        }
+    }
+}
@@ -166,6 +166,92 @@ impl OutlineNodeType {
     }
 }
 
+/// How visible a symbol is to code outside its own file, per-language. A
+/// signature change on a private symbol only ripples inside the file while
+/// a public one can break external consumers, so followup propagation
+/// treats them differently
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+pub enum SymbolVisibility {
+    /// visible to everything, external consumers included
+    Public,
+    /// visible inside the crate or package but not outside it
+    CrateLocal,
+    /// visible only inside the defining file or module
+    Private,
+}
+
+impl SymbolVisibility {
+    pub fn is_public(&self) -> bool {
+        matches!(self, SymbolVisibility::Public)
+    }
+
+    pub fn is_private(&self) -> bool {
+        matches!(self, SymbolVisibility::Private)
+    }
+}
+
+/// The visibility of a declaration from its source alone. Rust reads the
+/// `pub` modifier family, typescript and javascript look for the export
+/// keyword, python goes by the leading underscore convention (refine with
+/// [`python_dunder_all_exports`] when `__all__` is present). Languages we
+/// do not understand stay Public, the safe assumption for followups
+pub fn declaration_visibility(language: &str, name: &str, content: &str) -> SymbolVisibility {
+    match language {
+        "rust" => {
+            let declaration_line = content
+                .lines()
+                .map(|line| line.trim_start())
+                .find(|line| {
+                    !line.is_empty() && !line.starts_with("#[") && !line.starts_with("//")
+                })
+                .unwrap_or("");
+            if declaration_line.starts_with("pub(") {
+                SymbolVisibility::CrateLocal
+            } else if declaration_line.starts_with("pub ") {
+                SymbolVisibility::Public
+            } else {
+                SymbolVisibility::Private
+            }
+        }
+        "typescript" | "javascript" => {
+            let exported = content.lines().map(|line| line.trim_start()).any(|line| {
+                line.starts_with("export ") && line.contains(name)
+            });
+            if exported {
+                SymbolVisibility::Public
+            } else {
+                SymbolVisibility::Private
+            }
+        }
+        "python" => {
+            if name.starts_with('_') {
+                SymbolVisibility::Private
+            } else {
+                SymbolVisibility::Public
+            }
+        }
+        _ => SymbolVisibility::Public,
+    }
+}
+
+/// The names a python module exports through `__all__`, None when the file
+/// does not declare one. A present `__all__` narrows the underscore
+/// convention: anything missing from the list is module private
+pub fn python_dunder_all_exports(file_content: &str) -> Option<Vec<String>> {
+    let start = file_content.find("__all__")?;
+    let after_assignment = file_content[start..].split_once('=')?.1;
+    let open_bracket = after_assignment.find(['[', '('])?;
+    let close_bracket = after_assignment[open_bracket..].find([']', ')'])?;
+    let list_body = &after_assignment[open_bracket + 1..open_bracket + close_bracket];
+    Some(
+        list_body
+            .split(',')
+            .map(|entry| entry.trim().trim_matches(['"', '\'']).to_owned())
+            .filter(|entry| !entry.is_empty())
+            .collect(),
+    )
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, std::hash::Hash)]
 pub struct OutlineNodeContent {
     range: Range,
@@ -308,6 +394,27 @@ impl OutlineNodeContent {
         &self.language
     }
 
+    /// Visibility of this node from its declaration alone, see
+    /// [`declaration_visibility`] for the per-language rules
+    pub fn visibility(&self) -> SymbolVisibility {
+        declaration_visibility(&self.language, &self.name, &self.content)
+    }
+
+    /// Visibility refined with the whole file, which is what python needs
+    /// when the module declares an `__all__` list
+    pub fn visibility_in_file(&self, file_content: &str) -> SymbolVisibility {
+        if self.language == "python" {
+            if let Some(exports) = python_dunder_all_exports(file_content) {
+                return if exports.iter().any(|export| export == &self.name) {
+                    SymbolVisibility::Public
+                } else {
+                    SymbolVisibility::Private
+                };
+            }
+        }
+        self.visibility()
+    }
+
     pub fn to_xml(&self) -> String {
         let name = &self.name;
         let file_path = &self.fs_file_path;
@@ -1458,6 +1565,49 @@ mod tests {
     use crate::chunking::text_document::Range;
 
     use super::concat_documentation_string;
+    use super::{declaration_visibility, python_dunder_all_exports, SymbolVisibility};
+
+    #[test]
+    fn test_rust_visibility_reads_the_pub_modifier() {
+        let public = r#"/// docs
+#[derive(Debug)]
+pub struct Request {}"#;
+        assert_eq!(
+            declaration_visibility("rust", "Request", public),
+            SymbolVisibility::Public
+        );
+        assert_eq!(
+            declaration_visibility("rust", "helper", "pub(crate) fn helper() {}"),
+            SymbolVisibility::CrateLocal
+        );
+        assert_eq!(
+            declaration_visibility("rust", "helper", "fn helper() {}"),
+            SymbolVisibility::Private
+        );
+    }
+
+    #[test]
+    fn test_typescript_visibility_needs_the_export_keyword() {
+        assert_eq!(
+            declaration_visibility("typescript", "parse", "export function parse() {}"),
+            SymbolVisibility::Public
+        );
+        assert_eq!(
+            declaration_visibility("typescript", "parse", "function parse() {}"),
+            SymbolVisibility::Private
+        );
+    }
+
+    #[test]
+    fn test_python_dunder_all_narrows_the_exports() {
+        let file_content = r#"__all__ = [
+    "run",
+    'configure',
+]"#;
+        let exports = python_dunder_all_exports(file_content).expect("__all__ to parse");
+        assert_eq!(exports, vec!["run".to_owned(), "configure".to_owned()]);
+        assert!(python_dunder_all_exports("import os").is_none());
+    }
 
     #[test]
     fn test_documentation_string_concatenation() {
@@ -0,0 +1,214 @@
+//! Crate-wide error taxonomy for user-facing failures
+//!
+//! Internal error enums are precise but stringly once they reach the
+//! editor, which leaves the user staring at "Internal server error: ..."
+//! with nothing to act on. Every failure which crosses the API or UI event
+//! boundary gets classified here into a stable category and code with a
+//! remediation hint, editors key their error UI off the code instead of
+//! parsing messages
+
+use crate::agentic::symbol::errors::SymbolError;
+use crate::agentic::tool::errors::ToolError;
+use llm_client::clients::types::LLMClientError;
+
+/// The stable top-level categories, these never change meaning so editors
+/// can branch on them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCategory {
+    /// something about the local setup is wrong: api keys, config files,
+    /// missing tools
+    Configuration,
+    /// the model provider failed: auth, rate limits, unsupported models
+    Provider,
+    /// sidecar could not talk to the editor
+    EditorConnection,
+    /// a model response did not match the expected format
+    Parsing,
+    /// the tool policy blocked the operation
+    Policy,
+    /// everything we cannot classify better
+    Internal,
+}
+
+/// A classified error ready to serialize into API error responses and
+/// UI events
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct StructuredError {
+    category: ErrorCategory,
+    /// stable machine-readable code, `category.detail` shaped
+    code: &'static str,
+    message: String,
+    /// what the user can do about it, missing when there is nothing
+    /// actionable
+    #[serde(skip_serializing_if = "Option::is_none")]
+    remediation: Option<&'static str>,
+}
+
+impl StructuredError {
+    pub fn new(category: ErrorCategory, code: &'static str, message: String) -> Self {
+        Self {
+            category,
+            code,
+            message,
+            remediation: None,
+        }
+    }
+
+    pub fn with_remediation(mut self, remediation: &'static str) -> Self {
+        self.remediation = Some(remediation);
+        self
+    }
+
+    pub fn category(&self) -> ErrorCategory {
+        self.category
+    }
+
+    pub fn code(&self) -> &'static str {
+        self.code
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    pub fn remediation(&self) -> Option<&'static str> {
+        self.remediation
+    }
+}
+
+impl From<&LLMClientError> for StructuredError {
+    fn from(error: &LLMClientError) -> Self {
+        match error {
+            LLMClientError::UnauthorizedAccess => StructuredError::new(
+                ErrorCategory::Configuration,
+                "configuration.invalid_api_key",
+                format!("{}", error),
+            )
+            .with_remediation("Check the API key configured for the provider and try again"),
+            LLMClientError::RateLimitExceeded => StructuredError::new(
+                ErrorCategory::Provider,
+                "provider.rate_limited",
+                format!("{}", error),
+            )
+            .with_remediation("Wait a moment and retry, or switch to a different model"),
+            _ => StructuredError::new(
+                ErrorCategory::Provider,
+                "provider.llm_call_failed",
+                format!("{}", error),
+            )
+            .with_remediation("Retry the request, if it keeps failing check the provider status"),
+        }
+    }
+}
+
+impl From<&ToolError> for StructuredError {
+    fn from(error: &ToolError) -> Self {
+        match error {
+            ToolError::LLMClientError(llm_error) => StructuredError::from(llm_error),
+            ToolError::NotSupportedLLM(_) => StructuredError::new(
+                ErrorCategory::Provider,
+                "provider.model_not_supported",
+                format!("{}", error),
+            )
+            .with_remediation("Pick a different model for this operation"),
+            ToolError::RetriesExhausted => StructuredError::new(
+                ErrorCategory::Provider,
+                "provider.retries_exhausted",
+                format!("{}", error),
+            )
+            .with_remediation("Retry later, the provider kept failing"),
+            ToolError::ErrorCommunicatingWithEditor => StructuredError::new(
+                ErrorCategory::EditorConnection,
+                "editor.communication_failed",
+                format!("{}", error),
+            )
+            .with_remediation("Check the editor is still running and reachable from sidecar"),
+            ToolError::Timeout(_) => StructuredError::new(
+                ErrorCategory::EditorConnection,
+                "editor.request_timed_out",
+                format!("{}", error),
+            )
+            .with_remediation("Retry the request, the editor or tool took too long to answer"),
+            ToolError::ToolDisabled(_) => StructuredError::new(
+                ErrorCategory::Policy,
+                "policy.tool_disabled",
+                format!("{}", error),
+            )
+            .with_remediation("Enable the tool through the tool policy settings"),
+            ToolError::SerdeConversionFailed
+            | ToolError::MissingXMLTags
+            | ToolError::CodeNotFormatted(_) => StructuredError::new(
+                ErrorCategory::Parsing,
+                "parsing.malformed_model_output",
+                format!("{}", error),
+            )
+            .with_remediation("Retry the request, the model response did not match the expected format"),
+            ToolError::MissingTool => StructuredError::new(
+                ErrorCategory::Configuration,
+                "configuration.tool_not_registered",
+                format!("{}", error),
+            )
+            .with_remediation("Check the sidecar configuration registers the tool you are using"),
+            _ => StructuredError::new(
+                ErrorCategory::Internal,
+                "internal.unexpected",
+                format!("{}", error),
+            ),
+        }
+    }
+}
+
+impl From<&SymbolError> for StructuredError {
+    fn from(error: &SymbolError) -> Self {
+        match error {
+            SymbolError::ToolError(tool_error) => StructuredError::from(tool_error),
+            SymbolError::LLMClientError(llm_error) => StructuredError::from(llm_error),
+            SymbolError::WrongToolOutput => StructuredError::new(
+                ErrorCategory::Parsing,
+                "parsing.unexpected_tool_output",
+                format!("{}", error),
+            )
+            .with_remediation("Retry the request, the tool answered in an unexpected shape"),
+            _ => StructuredError::new(
+                ErrorCategory::Internal,
+                "internal.unexpected",
+                format!("{}", error),
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ErrorCategory, StructuredError};
+    use crate::agentic::symbol::errors::SymbolError;
+    use crate::agentic::tool::errors::ToolError;
+    use crate::agentic::tool::r#type::ToolType;
+    use llm_client::clients::types::LLMClientError;
+
+    #[test]
+    fn test_unauthorized_access_classifies_as_configuration() {
+        let error = SymbolError::LLMClientError(LLMClientError::UnauthorizedAccess);
+        let structured = StructuredError::from(&error);
+        assert_eq!(structured.category(), ErrorCategory::Configuration);
+        assert_eq!(structured.code(), "configuration.invalid_api_key");
+        assert!(structured.remediation().is_some());
+    }
+
+    #[test]
+    fn test_disabled_tool_classifies_as_policy_through_the_wrapper() {
+        let error = SymbolError::ToolError(ToolError::ToolDisabled(ToolType::OpenFile));
+        let structured = StructuredError::from(&error);
+        assert_eq!(structured.category(), ErrorCategory::Policy);
+        assert_eq!(structured.code(), "policy.tool_disabled");
+    }
+
+    #[test]
+    fn test_unclassified_errors_fall_back_to_internal_without_remediation() {
+        let structured = StructuredError::from(&SymbolError::SymbolNotFound);
+        assert_eq!(structured.category(), ErrorCategory::Internal);
+        assert_eq!(structured.code(), "internal.unexpected");
+        assert!(structured.remediation().is_none());
+    }
+}
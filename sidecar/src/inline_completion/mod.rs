@@ -2,6 +2,7 @@ pub mod context;
 pub mod document;
 pub mod helpers;
 pub mod multiline;
+pub mod provider_selection;
 pub mod state;
 pub mod symbols_tracker;
 pub mod types;
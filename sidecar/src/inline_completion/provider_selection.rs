@@ -0,0 +1,315 @@
+//! Bandit-style routing across completion capable providers
+//!
+//! When the editor configures more than one model which can serve inline
+//! completions we pick between them per language instead of always using
+//! the configured fast model. Each (language, model) pair is an arm whose
+//! score blends the acceptance rate the user gives its completions with
+//! the time it takes to produce a first response, plus a UCB exploration
+//! bonus so a rarely tried arm still gets traffic
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use llm_client::clients::types::LLMType;
+
+/// how much score an arm loses per second of mean time-to-first-response,
+/// a provider has to be noticeably better accepted to justify being slower
+const LATENCY_PENALTY_PER_SECOND: f64 = 0.2;
+
+/// the acceptance rate we assume for an arm before any feedback arrived,
+/// optimistic enough that new providers are not starved immediately
+const PRIOR_ACCEPTANCE_RATE: f64 = 0.5;
+
+/// requests we routed but have not heard back about, editors which never
+/// report feedback would otherwise grow this without bound
+const IN_FLIGHT_CAP: usize = 512;
+
+/// Observations for one (language, model) arm
+#[derive(Debug, Default, Clone)]
+pub struct ArmStats {
+    /// completion requests we routed to this arm
+    attempts: u64,
+    /// completions the user kept
+    accepts: u64,
+    /// completions the user discarded
+    rejects: u64,
+    /// summed time-to-first-response over the attempts which produced one
+    total_first_response_ms: u128,
+    /// attempts which produced a first response, the latency denominator
+    responses: u64,
+}
+
+impl ArmStats {
+    /// accepted fraction of the completions the user gave a verdict on,
+    /// None until any feedback arrived
+    fn acceptance_rate(&self) -> Option<f64> {
+        let verdicts = self.accepts + self.rejects;
+        if verdicts == 0 {
+            None
+        } else {
+            Some(self.accepts as f64 / verdicts as f64)
+        }
+    }
+
+    /// mean time-to-first-response in milliseconds, None until a response
+    /// was observed
+    fn mean_first_response_ms(&self) -> Option<f64> {
+        if self.responses == 0 {
+            None
+        } else {
+            Some(self.total_first_response_ms as f64 / self.responses as f64)
+        }
+    }
+}
+
+/// Scores an arm for selection, higher wins. Acceptance carries the score,
+/// latency drags it down and the UCB term keeps under-sampled arms alive
+pub(crate) fn arm_score(stats: &ArmStats, total_attempts: u64) -> f64 {
+    let acceptance = stats.acceptance_rate().unwrap_or(PRIOR_ACCEPTANCE_RATE);
+    let latency_penalty = stats
+        .mean_first_response_ms()
+        .map(|mean_ms| (mean_ms / 1000.0) * LATENCY_PENALTY_PER_SECOND)
+        .unwrap_or(0.0);
+    let exploration = if stats.attempts == 0 {
+        // untried arms are handled before scoring, this keeps the math sane
+        f64::MAX
+    } else {
+        (2.0 * (total_attempts.max(1) as f64).ln() / stats.attempts as f64).sqrt()
+    };
+    acceptance - latency_penalty + exploration
+}
+
+/// Routes inline completion requests between the completion capable models
+/// the editor configured, learning per language from latency and whether
+/// the user accepted what came back
+pub struct CompletionProviderSelector {
+    /// observations per (language, model) arm
+    arms: Mutex<HashMap<(String, LLMType), ArmStats>>,
+    /// request id -> the arm which served it, resolved by feedback
+    in_flight: Mutex<HashMap<String, (String, LLMType)>>,
+}
+
+impl CompletionProviderSelector {
+    pub fn new() -> Self {
+        Self {
+            arms: Mutex::new(HashMap::new()),
+            in_flight: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Picks the model to route a completion to for the language, untried
+    /// candidates go first in configuration order, after that the highest
+    /// scoring arm wins
+    pub fn pick(&self, language: &str, candidates: &[LLMType]) -> Option<LLMType> {
+        if candidates.is_empty() {
+            return None;
+        }
+        let arms = self.arms.lock().expect("arms lock to not be poisoned");
+        let total_attempts = candidates
+            .iter()
+            .map(|model| {
+                arms.get(&(language.to_owned(), model.clone()))
+                    .map(|stats| stats.attempts)
+                    .unwrap_or(0)
+            })
+            .sum::<u64>();
+        if let Some(untried) = candidates.iter().find(|model| {
+            arms.get(&(language.to_owned(), (*model).clone()))
+                .map(|stats| stats.attempts == 0)
+                .unwrap_or(true)
+        }) {
+            return Some(untried.clone());
+        }
+        candidates
+            .iter()
+            .max_by(|left, right| {
+                let left_score = arm_score(
+                    arms.get(&(language.to_owned(), (*left).clone()))
+                        .expect("untried arms are handled above"),
+                    total_attempts,
+                );
+                let right_score = arm_score(
+                    arms.get(&(language.to_owned(), (*right).clone()))
+                        .expect("untried arms are handled above"),
+                    total_attempts,
+                );
+                left_score
+                    .partial_cmp(&right_score)
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            })
+            .cloned()
+    }
+
+    /// Records that a request got routed to a model so the later latency
+    /// and acceptance observations land on the right arm
+    pub fn record_request(&self, request_id: &str, language: &str, model: &LLMType) {
+        {
+            let mut arms = self.arms.lock().expect("arms lock to not be poisoned");
+            arms.entry((language.to_owned(), model.clone()))
+                .or_default()
+                .attempts += 1;
+        }
+        let mut in_flight = self
+            .in_flight
+            .lock()
+            .expect("in_flight lock to not be poisoned");
+        if in_flight.len() >= IN_FLIGHT_CAP {
+            in_flight.clear();
+        }
+        in_flight.insert(
+            request_id.to_owned(),
+            (language.to_owned(), model.clone()),
+        );
+    }
+
+    /// Records the time the first streamed response took for a routed
+    /// request, unknown ids are ignored
+    pub fn record_first_response(&self, request_id: &str, elapsed: Duration) {
+        let arm_key = {
+            let in_flight = self
+                .in_flight
+                .lock()
+                .expect("in_flight lock to not be poisoned");
+            in_flight.get(request_id).cloned()
+        };
+        if let Some(arm_key) = arm_key {
+            let mut arms = self.arms.lock().expect("arms lock to not be poisoned");
+            let stats = arms.entry(arm_key).or_default();
+            stats.total_first_response_ms += elapsed.as_millis();
+            stats.responses += 1;
+        }
+    }
+
+    /// Records the user verdict on a routed completion, returns false when
+    /// the request id is not one we routed
+    pub fn record_feedback(&self, request_id: &str, accepted: bool) -> bool {
+        let arm_key = {
+            let mut in_flight = self
+                .in_flight
+                .lock()
+                .expect("in_flight lock to not be poisoned");
+            in_flight.remove(request_id)
+        };
+        match arm_key {
+            Some(arm_key) => {
+                let mut arms = self.arms.lock().expect("arms lock to not be poisoned");
+                let stats = arms.entry(arm_key).or_default();
+                if accepted {
+                    stats.accepts += 1;
+                } else {
+                    stats.rejects += 1;
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Snapshot of the observations per arm, for the stats endpoint
+    pub fn arm_snapshot(&self) -> Vec<CompletionArmSnapshot> {
+        let arms = self.arms.lock().expect("arms lock to not be poisoned");
+        let mut snapshot = arms
+            .iter()
+            .map(|((language, model), stats)| CompletionArmSnapshot {
+                language: language.to_owned(),
+                model: model.to_string(),
+                attempts: stats.attempts,
+                accepts: stats.accepts,
+                rejects: stats.rejects,
+                mean_first_response_ms: stats.mean_first_response_ms(),
+            })
+            .collect::<Vec<_>>();
+        snapshot.sort_by(|left, right| {
+            (&left.language, &left.model).cmp(&(&right.language, &right.model))
+        });
+        snapshot
+    }
+}
+
+/// Serializable view over one arm for the editor facing stats endpoint
+#[derive(Debug, serde::Serialize)]
+pub struct CompletionArmSnapshot {
+    pub language: String,
+    pub model: String,
+    pub attempts: u64,
+    pub accepts: u64,
+    pub rejects: u64,
+    pub mean_first_response_ms: Option<f64>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidates() -> Vec<LLMType> {
+        vec![LLMType::Gpt4O, LLMType::ClaudeHaiku]
+    }
+
+    #[test]
+    fn test_untried_candidates_get_picked_first() {
+        let selector = CompletionProviderSelector::new();
+        selector.record_request("request-1", "rust", &LLMType::Gpt4O);
+        let picked = selector.pick("rust", &candidates());
+        assert_eq!(picked, Some(LLMType::ClaudeHaiku));
+    }
+
+    #[test]
+    fn test_acceptance_rate_drives_the_pick() {
+        let selector = CompletionProviderSelector::new();
+        // both arms get sampled, one keeps getting rejected
+        for index in 0..10 {
+            let accepted_id = format!("accepted-{}", index);
+            selector.record_request(&accepted_id, "rust", &LLMType::Gpt4O);
+            selector.record_feedback(&accepted_id, true);
+            let rejected_id = format!("rejected-{}", index);
+            selector.record_request(&rejected_id, "rust", &LLMType::ClaudeHaiku);
+            selector.record_feedback(&rejected_id, false);
+        }
+        let picked = selector.pick("rust", &candidates());
+        assert_eq!(picked, Some(LLMType::Gpt4O));
+    }
+
+    #[test]
+    fn test_slow_arms_get_penalised() {
+        let selector = CompletionProviderSelector::new();
+        for index in 0..10 {
+            let fast_id = format!("fast-{}", index);
+            selector.record_request(&fast_id, "rust", &LLMType::Gpt4O);
+            selector.record_first_response(&fast_id, Duration::from_millis(200));
+            selector.record_feedback(&fast_id, true);
+            let slow_id = format!("slow-{}", index);
+            selector.record_request(&slow_id, "rust", &LLMType::ClaudeHaiku);
+            selector.record_first_response(&slow_id, Duration::from_secs(5));
+            selector.record_feedback(&slow_id, true);
+        }
+        let picked = selector.pick("rust", &candidates());
+        assert_eq!(picked, Some(LLMType::Gpt4O));
+    }
+
+    #[test]
+    fn test_languages_learn_independently() {
+        let selector = CompletionProviderSelector::new();
+        for index in 0..10 {
+            let rust_id = format!("rust-{}", index);
+            selector.record_request(&rust_id, "rust", &LLMType::Gpt4O);
+            selector.record_feedback(&rust_id, false);
+            let go_id = format!("go-{}", index);
+            selector.record_request(&go_id, "rust", &LLMType::ClaudeHaiku);
+            selector.record_feedback(&go_id, true);
+        }
+        // typescript has no history so its first candidate is untried
+        let picked = selector.pick("typescript", &candidates());
+        assert_eq!(picked, Some(LLMType::Gpt4O));
+    }
+
+    #[test]
+    fn test_feedback_for_unknown_requests_is_reported() {
+        let selector = CompletionProviderSelector::new();
+        assert!(!selector.record_feedback("never-routed", true));
+        selector.record_request("request-1", "rust", &LLMType::Gpt4O);
+        assert!(selector.record_feedback("request-1", true));
+        // feedback resolves the in-flight entry, a second verdict is stale
+        assert!(!selector.record_feedback("request-1", true));
+    }
+}
@@ -109,6 +109,22 @@ impl GetFileEditedLinesRequest {
     }
 }
 
+struct CompareAndGetFileContentRequest {
+    file_path: String,
+    /// version of the document the caller last saw, the read only succeeds
+    /// when the tracked document is still at this version
+    expected_version: u64,
+}
+
+impl CompareAndGetFileContentRequest {
+    pub fn new(file_path: String, expected_version: u64) -> Self {
+        Self {
+            file_path,
+            expected_version,
+        }
+    }
+}
+
 struct GetIdentifierNodesRequest {
     file_path: String,
     cursor_position: Position,
@@ -141,6 +157,8 @@ enum SharedStateRequest {
     GetDocumentOutline(GetDocumentOutlineRequest),
     GetSymbolHistory,
     GetSymbolsInRange(SymbolsInRangeRequest),
+    GetDocumentVersion(String),
+    CompareAndGetFileContent(CompareAndGetFileContentRequest),
 }
 
 enum SharedStateResponse {
@@ -154,6 +172,7 @@ enum SharedStateResponse {
     GetSymbolHistoryResponse(Vec<SymbolInformation>),
     GetDocumentOutlineResponse(Option<Vec<OutlineNode>>),
     SymbolsInRangeResponse(Vec<OutlineNode>),
+    DocumentVersionResponse(Option<u64>),
 }
 
 /// We are keeping track of the symbol node where the user is editing, this can
@@ -200,6 +219,9 @@ impl SymbolInformation {
 pub struct SharedState {
     document_lines: Mutex<HashMap<String, DocumentEditLines>>,
     document_history: Mutex<Vec<String>>,
+    // monotonically increasing per document, bumped every time we observe a
+    // new snapshot or an edit so readers can detect stale content
+    document_versions: Mutex<HashMap<String, u64>>,
     editor_parsing: Arc<EditorParsing>,
     // really here this should not be a vector but it needs to be a graph where
     // the user is jumping around, somehow we wll figure out what to do about that?
@@ -211,6 +233,7 @@ impl SharedState {
     async fn process_request(&self, request: SharedStateRequest) -> SharedStateResponse {
         match request {
             SharedStateRequest::AddDocument(add_document_request) => {
+                let document_path = add_document_request.document_path.to_owned();
                 let _ = self
                     .add_document(
                         add_document_request.document_path,
@@ -219,9 +242,12 @@ impl SharedState {
                         add_document_request.force_update,
                     )
                     .await;
+                self.bump_document_version(&document_path).await;
                 SharedStateResponse::Ok
             }
             SharedStateRequest::FileContentChange(file_content_change_request) => {
+                let document_path = file_content_change_request.document_path.to_owned();
+                let has_edits = !file_content_change_request.edits.is_empty();
                 let _ = self
                     .file_content_change(
                         file_content_change_request.document_path,
@@ -230,6 +256,9 @@ impl SharedState {
                         file_content_change_request.edits,
                     )
                     .await;
+                if has_edits {
+                    self.bump_document_version(&document_path).await;
+                }
                 SharedStateResponse::Ok
             }
             SharedStateRequest::GetDocumentLines(get_document_lines_request) => {
@@ -277,7 +306,49 @@ impl SharedState {
                 let response = self.get_symbols_in_range(symbols_in_range_request).await;
                 SharedStateResponse::SymbolsInRangeResponse(response)
             }
+            SharedStateRequest::GetDocumentVersion(file_path) => {
+                let response = self.get_document_version(&file_path).await;
+                SharedStateResponse::DocumentVersionResponse(response)
+            }
+            SharedStateRequest::CompareAndGetFileContent(request) => {
+                let response = self
+                    .compare_and_get_file_content(&request.file_path, request.expected_version)
+                    .await;
+                SharedStateResponse::FileContentResponse(response)
+            }
+        }
+    }
+
+    async fn bump_document_version(&self, document_path: &str) {
+        let mut document_versions = self.document_versions.lock().await;
+        *document_versions
+            .entry(document_path.to_owned())
+            .or_insert(0) += 1;
+    }
+
+    async fn get_document_version(&self, file_path: &str) -> Option<u64> {
+        let document_versions = self.document_versions.lock().await;
+        document_versions.get(file_path).copied()
+    }
+
+    /// Reads the content of the document only when it is still at the version
+    /// the caller expects, returns None when the document has moved on (or was
+    /// never tracked) so stale reads never go unnoticed.
+    async fn compare_and_get_file_content(
+        &self,
+        file_path: &str,
+        expected_version: u64,
+    ) -> Option<String> {
+        // hold the version lock across the content read so a concurrent edit
+        // cannot slip in between the check and the read
+        let document_versions = self.document_versions.lock().await;
+        if document_versions.get(file_path).copied() != Some(expected_version) {
+            return None;
         }
+        let document_lines = self.document_lines.lock().await;
+        document_lines
+            .get(file_path)
+            .map(|document_lines| document_lines.get_content())
     }
 
     async fn get_symbols_in_range(&self, request: SymbolsInRangeRequest) -> Vec<OutlineNode> {
@@ -650,6 +721,7 @@ impl SymbolTrackerInline {
         let shared_state = Arc::new(SharedState {
             document_lines: Mutex::new(HashMap::new()),
             document_history: Mutex::new(Vec::new()),
+            document_versions: Mutex::new(HashMap::new()),
             editor_parsing,
             symbol_history: Arc::new(Mutex::new(Vec::new())),
         });
@@ -687,6 +759,40 @@ impl SymbolTrackerInline {
         }
     }
 
+    /// Current version of a tracked document, None when we have never seen
+    /// the document.
+    pub async fn get_document_version(&self, file_path: &str) -> Option<u64> {
+        let (sender, receiver) = tokio::sync::oneshot::channel();
+        let request = SharedStateRequest::GetDocumentVersion(file_path.to_owned());
+        let _ = self.sender.send((request, sender));
+        let reply = receiver.await;
+        if let Ok(SharedStateResponse::DocumentVersionResponse(response)) = reply {
+            response
+        } else {
+            None
+        }
+    }
+
+    /// Compare-and-read: returns the content only if the document is still at
+    /// the expected version, None signals the caller is holding stale text.
+    pub async fn get_file_content_at_version(
+        &self,
+        file_path: &str,
+        expected_version: u64,
+    ) -> Option<String> {
+        let (sender, receiver) = tokio::sync::oneshot::channel();
+        let request = SharedStateRequest::CompareAndGetFileContent(
+            CompareAndGetFileContentRequest::new(file_path.to_owned(), expected_version),
+        );
+        let _ = self.sender.send((request, sender));
+        let reply = receiver.await;
+        if let Ok(SharedStateResponse::FileContentResponse(response)) = reply {
+            response
+        } else {
+            None
+        }
+    }
+
     pub async fn get_file_edited_lines(&self, file_path: &str) -> Vec<usize> {
         let (sender, receiver) = tokio::sync::oneshot::channel();
         let request = SharedStateRequest::GetFileEditedLines(GetFileEditedLinesRequest::new(
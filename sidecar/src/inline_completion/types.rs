@@ -28,6 +28,7 @@ use crate::{
 };
 
 use super::context::codebase_context::CodeBaseContext;
+use super::provider_selection::CompletionProviderSelector;
 use super::symbols_tracker::SymbolTrackerInline;
 use super::{
     context::{current_file::CurrentFileContext, types::DocumentLines},
@@ -225,6 +226,7 @@ pub struct FillInMiddleCompletionAgent {
     editor_parsing: Arc<EditorParsing>,
     answer_mode: Arc<LLMAnswerModelBroker>,
     symbol_tracker: Arc<SymbolTrackerInline>,
+    provider_selector: Arc<CompletionProviderSelector>,
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -280,6 +282,7 @@ impl FillInMiddleCompletionAgent {
         fill_in_middle_broker: Arc<FillInMiddleBroker>,
         editor_parsing: Arc<EditorParsing>,
         symbol_tracker: Arc<SymbolTrackerInline>,
+        provider_selector: Arc<CompletionProviderSelector>,
     ) -> Self {
         Self {
             llm_broker,
@@ -288,6 +291,7 @@ impl FillInMiddleCompletionAgent {
             fill_in_middle_broker,
             editor_parsing,
             symbol_tracker,
+            provider_selector,
         }
     }
 
@@ -295,7 +299,7 @@ impl FillInMiddleCompletionAgent {
         &self,
         completion_request: InlineCompletionRequest,
         abort_handle: AbortHandle,
-        _request_start: Instant,
+        request_start: Instant,
     ) -> Result<
         Pin<Box<dyn Stream<Item = Result<InlineCompletionResponse, InLineCompletionError>> + Send>>,
         InLineCompletionError,
@@ -304,20 +308,45 @@ impl FillInMiddleCompletionAgent {
         // Now that we have the position, we want to create the request for the fill
         // in the middle request.
         let model_config = &completion_request.model_config;
-        // If we are using the codestory provider, use the only model compatible with the codestory
-        // provider.
-        let fast_model = match model_config.provider_for_fast_model() {
-            Some(provider) => {
-                fix_model_for_sidecar_provider(provider, model_config.fast_model.clone())
-            }
-            None => model_config.fast_model.clone(),
+        // When the editor configured more than one completion capable model
+        // we let the bandit route per language, otherwise the configured
+        // fast model wins like before
+        let candidate_models = model_config
+            .models
+            .keys()
+            .filter(|model| {
+                model_config.provider_for_model(model).is_some()
+                    && self
+                        .answer_mode
+                        .get_answer_model(model)
+                        .map(|answer_model| answer_model.inline_completion_tokens.is_some())
+                        .unwrap_or(false)
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        let selected_model = if candidate_models.len() > 1 {
+            self.provider_selector
+                .pick(&completion_request.language, &candidate_models)
+                .unwrap_or_else(|| model_config.fast_model.clone())
+        } else {
+            model_config.fast_model.clone()
         };
         let fast_model_api_key = model_config
-            .provider_for_fast_model()
+            .provider_for_model(&selected_model)
             .ok_or(InLineCompletionError::MissingProviderKeys(
-                fast_model.clone(),
+                selected_model.clone(),
             ))?
             .clone();
+        if candidate_models.len() > 1 {
+            self.provider_selector.record_request(
+                &request_id,
+                &completion_request.language,
+                &selected_model,
+            );
+        }
+        // If we are using the codestory provider, use the only model compatible with the codestory
+        // provider.
+        let fast_model = fix_model_for_sidecar_provider(&fast_model_api_key, selected_model);
         let model_config = self.answer_mode.get_answer_model(&fast_model);
         if let None = model_config {
             return Err(InLineCompletionError::LLMNotSupported(fast_model));
@@ -662,6 +691,21 @@ impl FillInMiddleCompletionAgent {
                     }
                     Err(e) => Err(e),
                 })
+                // feed the time-to-first-response back to the bandit so the
+                // routing learns which provider answers quickly
+                .map({
+                    let provider_selector = self.provider_selector.clone();
+                    let request_id = request_id.to_owned();
+                    let mut first_response_recorded = false;
+                    move |item| {
+                        if !first_response_recorded && item.is_ok() {
+                            first_response_recorded = true;
+                            provider_selector
+                                .record_first_response(&request_id, request_start.elapsed());
+                        }
+                        item
+                    }
+                })
         }))
     }
 }
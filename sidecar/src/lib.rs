@@ -3,6 +3,7 @@ pub mod agentic;
 pub mod application;
 pub mod chunking;
 pub mod db;
+pub mod errors;
 pub mod file_analyser;
 pub mod git;
 pub mod in_line_agent;
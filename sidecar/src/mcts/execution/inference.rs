@@ -716,7 +716,11 @@ Always include the <thinking></thinking> section before using the tool."#
                     .map_err(|e| InferenceError::ToolError(e))?
                     .terminal_command()
                     .ok_or(InferenceError::WrongToolOutput)?;
-                let output = tool_output.output().to_owned();
+                let mut output = tool_output.output().to_owned();
+                if let Some(exit_info) = tool_output.exit_info_line() {
+                    output.push_str("\n");
+                    output.push_str(&exit_info);
+                }
                 let message = format!(
                     r#"Here's the output from running the terminal command
 Command: {}
@@ -1,15 +1,27 @@
 use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
 
 use crate::chunking::languages::TSLanguageParsing;
 
-use super::error::RepoMapError;
-
 use super::file::errors::FileError;
 use super::file::git::GitWalker;
-use futures::{stream, StreamExt};
+use once_cell::sync::Lazy;
+use rayon::prelude::*;
+
+/// Bounded rayon pool for the CPU-bound tree-sitter work, kept separate from
+/// the tokio runtime so bulk parsing does not starve the async executor
+static PARSING_POOL: Lazy<rayon::ThreadPool> = Lazy::new(|| {
+    let threads = std::thread::available_parallelism()
+        .map(|parallelism| parallelism.get())
+        .unwrap_or(1)
+        .min(8);
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .thread_name(|index| format!("repomap-parse-{}", index))
+        .build()
+        .expect("building the repomap parsing pool should not fail")
+});
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
 pub struct Tag {
@@ -214,57 +226,39 @@ impl TagIndex {
     }
 
     async fn generate_tag_index(&mut self, files: HashMap<String, Vec<u8>>) {
-        let ts_parsing = Arc::new(TSLanguageParsing::init());
-        let _ = stream::iter(
-            files
-                .into_iter()
-                .map(|(file, _)| (file, ts_parsing.clone())),
-        )
-        .map(|(file, ts_parsing)| async {
-            self.generate_tags_for_file(&file, ts_parsing)
-                .await
-                .map(|tags| (tags, file))
-                .ok()
+        // tree-sitter parsing is pure CPU work, fan it out over the bounded
+        // rayon pool instead of paying per-file async overhead on the runtime
+        let root_path = self.path.to_owned();
+        let parsed_files = tokio::task::spawn_blocking(move || {
+            let ts_parsing = TSLanguageParsing::init();
+            PARSING_POOL.install(|| {
+                files
+                    .into_par_iter()
+                    .filter_map(|(fname, content)| {
+                        let config = ts_parsing.for_file_path(&fname)?;
+                        let file_path = PathBuf::from(&fname);
+                        let rel_path = file_path
+                            .strip_prefix(&root_path)
+                            .unwrap_or(&file_path)
+                            .to_path_buf();
+                        let tags = config.get_tags_sync(&file_path, &rel_path, content);
+                        Some((tags, file_path))
+                    })
+                    .collect::<Vec<_>>()
+            })
         })
-        .buffer_unordered(10000)
-        .collect::<Vec<_>>()
         .await
-        .into_iter()
-        .filter_map(|s| s)
-        .for_each(|(tags, file)| {
-            let file_ref = &file;
+        .unwrap_or_default();
+
+        parsed_files.into_iter().for_each(|(tags, file_path)| {
             tags.into_iter().for_each(|tag| {
-                self.add_tag(tag, &PathBuf::from(file_ref));
+                self.add_tag(tag, &file_path);
             });
         });
 
         self.post_process_tags();
     }
 
-    async fn generate_tags_for_file(
-        &self,
-        fname: &str,
-        ts_parsing: Arc<TSLanguageParsing>,
-    ) -> Result<Vec<Tag>, RepoMapError> {
-        let rel_path = self.get_rel_fname(&PathBuf::from(fname));
-        let config = ts_parsing.for_file_path(fname).ok_or_else(|| {
-            RepoMapError::ParseError(format!("Language configuration not found for: {}", fname,))
-        });
-        let content = tokio::fs::read(fname).await;
-        if let Err(_) = content {
-            return Err(RepoMapError::IoError);
-        }
-        let content = content.expect("if let Err to hold");
-        if let Ok(config) = config {
-            let tags = config
-                .get_tags(&PathBuf::from(fname), &rel_path, content)
-                .await;
-            Ok(tags)
-        } else {
-            Ok(vec![])
-        }
-    }
-
     pub fn get_tags_for_file(&self, file_name: &Path) -> Option<Vec<Tag>> {
         self.file_to_tags.get(file_name).map(|tag_ids| {
             tag_ids
@@ -2,11 +2,13 @@
 
 use super::model_selection::LLMClientConfig;
 use super::plan::check_session_storage_path;
+use super::slash_commands::{parse_slash_command, SlashCommand};
+use super::tenancy::ClientId;
 use super::types::json as json_result;
 use axum::response::{sse, IntoResponse, Sse};
 use axum::{extract::Query as axumQuery, Extension, Json};
 use futures::{stream, StreamExt};
-use llm_client::clients::types::{LLMClientError, LLMType};
+use llm_client::clients::types::LLMType;
 use llm_client::provider::{
     CodeStoryLLMTypes, CodestoryAccessToken, LLMProvider, LLMProviderAPIKeys,
 };
@@ -19,8 +21,9 @@ use tokio::task::JoinHandle;
 use tracing::error;
 
 use super::types::Result;
+use super::feedback::FeedbackRecord;
+use crate::agentic::experiments::{ExperimentMetric, ExperimentOutcome};
 use crate::agentic::symbol::anchored::AnchoredSymbol;
-use crate::agentic::symbol::errors::SymbolError;
 use crate::agentic::symbol::events::environment_event::{EnvironmentEvent, EnvironmentEventType};
 use crate::agentic::symbol::events::input::SymbolEventRequestId;
 use crate::agentic::symbol::events::lsp::LSPDiagnosticError;
@@ -32,9 +35,12 @@ use crate::agentic::symbol::tool_properties::ToolProperties;
 use crate::agentic::symbol::toolbox::helpers::SymbolChangeSet;
 use crate::agentic::symbol::ui_event::{RelevantReference, UIEventWithID};
 use crate::agentic::tool::lsp::open_file::OpenFileResponse;
+use crate::agentic::tool::mcp::prompts::parse_prompt_command;
 use crate::agentic::tool::plan::service::PlanService;
+use crate::agentic::tool::session::cost_budget::CostBudget;
 use crate::agentic::tool::session::session::AideAgentMode;
 use crate::chunking::text_document::Range;
+use crate::errors::StructuredError;
 use crate::repo::types::RepoRef;
 use crate::webserver::plan::{
     check_plan_storage_path, check_scratch_pad_path, plan_storage_directory,
@@ -71,6 +77,12 @@ impl ProbeRequestTracker {
             }
         }
     }
+
+    /// Number of probe requests we are tracking right now, used by the
+    /// debug runtime endpoint
+    pub async fn running_request_count(&self) -> usize {
+        self.running_requests.lock().await.len()
+    }
 }
 
 /// Contains all the data which we will need to trigger the edits
@@ -226,6 +238,12 @@ impl AnchoredEditingTracker {
             }
         }
     }
+
+    /// Number of anchored editing requests we are tracking right now, used
+    /// by the debug runtime endpoint
+    pub async fn running_request_count(&self) -> usize {
+        self.running_requests.lock().await.len()
+    }
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -647,13 +665,18 @@ pub struct AgenticDiagnosticsResponse {
 impl ApiResponse for AgenticDiagnosticsResponse {}
 
 pub async fn push_diagnostics(
-    Extension(_app): Extension<Application>,
+    Extension(app): Extension<Application>,
     Json(AgenticDiagnostics {
         fs_file_path,
         diagnostics,
         source: _source,
     }): Json<AgenticDiagnostics>,
 ) -> Result<impl IntoResponse> {
+    // every push feeds the passive suggestion channel, a file whose
+    // diagnostics keep growing surfaces a low-priority cleanup suggestion
+    app.suggestion_channel
+        .observe_diagnostics(&fs_file_path, diagnostics.len())
+        .await;
     // implement this api endpoint properly and send events over to the right
     // scratch-pad agent
     let _ = diagnostics
@@ -685,6 +708,9 @@ pub struct AgenticEditFeedbackExchange {
     step_index: Option<usize>,
     editor_url: String,
     accepted: bool,
+    /// free-text feedback from the user, older editors do not send this over
+    #[serde(default)]
+    feedback_text: Option<String>,
     access_token: String,
     model_configuration: LLMClientConfig,
 }
@@ -704,11 +730,13 @@ impl ApiResponse for AgenticHandleSessionUndoResponse {}
 
 pub async fn handle_session_undo(
     Extension(app): Extension<Application>,
+    client_id: ClientId,
     Json(AgenticHandleSessionUndo {
         session_id,
         exchange_id,
     }): Json<AgenticHandleSessionUndo>,
 ) -> Result<impl IntoResponse> {
+    let session_id = client_id.scoped_id(&session_id);
     println!("webserver::agent_session::handle_session_undo::hit");
     println!(
         "webserver::agent_session::handle_session_undo::session_id({})",
@@ -725,6 +753,59 @@ pub async fn handle_session_undo(
     Ok(Json(AgenticHandleSessionUndoResponse { done: true }))
 }
 
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct AgenticForkSession {
+    session_id: String,
+    /// the exchange where the branch starts, it is included in the branch
+    exchange_id: String,
+    new_session_id: String,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct AgenticForkSessionResponse {
+    done: bool,
+    new_session_id: String,
+}
+
+impl ApiResponse for AgenticForkSessionResponse {}
+
+pub async fn handle_session_fork(
+    Extension(app): Extension<Application>,
+    client_id: ClientId,
+    Json(AgenticForkSession {
+        session_id,
+        exchange_id,
+        new_session_id,
+    }): Json<AgenticForkSession>,
+) -> Result<impl IntoResponse> {
+    let scoped_session_id = client_id.scoped_id(&session_id);
+    let scoped_new_session_id = client_id.scoped_id(&new_session_id);
+    println!(
+        "webserver::agent_session::handle_session_fork::session_id({})::exchange_id({})",
+        &scoped_session_id, &exchange_id
+    );
+
+    let session_storage_path =
+        check_session_storage_path(app.config.clone(), scoped_session_id.to_string()).await;
+    let new_session_storage_path =
+        check_session_storage_path(app.config.clone(), scoped_new_session_id.to_string()).await;
+
+    let session_service = app.session_service.clone();
+    let done = session_service
+        .fork_session(
+            session_storage_path,
+            &exchange_id,
+            scoped_new_session_id,
+            new_session_storage_path,
+        )
+        .await
+        .is_ok();
+    Ok(Json(AgenticForkSessionResponse {
+        done,
+        new_session_id,
+    }))
+}
+
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct AgenticEditFeedbackExchangeResponse {
     success: bool,
@@ -734,16 +815,19 @@ impl ApiResponse for AgenticEditFeedbackExchangeResponse {}
 
 pub async fn user_feedback_on_exchange(
     Extension(app): Extension<Application>,
+    client_id: ClientId,
     Json(AgenticEditFeedbackExchange {
         exchange_id,
         session_id,
         step_index,
         editor_url,
         accepted,
+        feedback_text,
         access_token,
         model_configuration,
     }): Json<AgenticEditFeedbackExchange>,
 ) -> Result<impl IntoResponse> {
+    let session_id = client_id.scoped_id(&session_id);
     let llm_provider = model_configuration
         .llm_properties_for_slow_model()
         .unwrap_or(LLMProperties::new(
@@ -771,6 +855,63 @@ pub async fn user_feedback_on_exchange(
     let session_storage_path =
         check_session_storage_path(app.config.clone(), session_id.to_string()).await;
 
+    // the feedback itself gets persisted joined with the exchange trace so
+    // we can aggregate it later
+    let feedback_store = app.feedback_store.clone();
+    {
+        let session_storage_path = session_storage_path.to_owned();
+        let session_id = session_id.to_owned();
+        let exchange_id = exchange_id.to_owned();
+        let feedback_text = feedback_text.clone();
+        let _ = tokio::spawn(async move {
+            let record = FeedbackRecord::from_session_file(
+                &session_storage_path,
+                session_id,
+                exchange_id,
+                accepted,
+                feedback_text,
+            )
+            .await;
+            feedback_store.append(record).await;
+        });
+    }
+
+    // when eval recording is opted into, accepted and rejected edits both
+    // become instances in the local dataset
+    let dataset_recorder = app.dataset_recorder.clone();
+    {
+        let session_storage_path = session_storage_path.to_owned();
+        let exchange_id = exchange_id.to_owned();
+        let _ = tokio::spawn(async move {
+            dataset_recorder
+                .record_from_session_file(session_storage_path, exchange_id, accepted)
+                .await;
+        });
+    }
+
+    // edit acceptance is one of the outcome metrics for any running prompt
+    // experiments on this session
+    let experiment_registry = app.experiment_registry.clone();
+    {
+        let session_id = session_id.to_owned();
+        let exchange_id = exchange_id.to_owned();
+        let _ = tokio::spawn(async move {
+            let variant = experiment_registry
+                .variant_for_session("session_edits", &session_id)
+                .await
+                .unwrap_or("control".to_owned());
+            experiment_registry
+                .log_outcome(ExperimentOutcome::new(
+                    "session_edits".to_owned(),
+                    variant,
+                    session_id,
+                    exchange_id,
+                    ExperimentMetric::EditAccepted(accepted),
+                ))
+                .await;
+        });
+    }
+
     let session_service = app.session_service.clone();
     let _ = tokio::spawn(async move {
         let _ = session_service
@@ -848,6 +989,7 @@ impl ApiResponse for AgenticCancelRunningExchangeResponse {}
 /// TODO(skcd): Figure out how to cancel a running request properly over here
 pub async fn cancel_running_exchange(
     Extension(app): Extension<Application>,
+    client_id: ClientId,
     Json(AgenticCancelRunningExchange {
         exchange_id,
         session_id,
@@ -856,6 +998,7 @@ pub async fn cancel_running_exchange(
         model_configuration,
     }): Json<AgenticCancelRunningExchange>,
 ) -> Result<impl IntoResponse> {
+    let session_id = client_id.scoped_id(&session_id);
     let llm_provider = model_configuration
         .llm_properties_for_slow_model()
         .unwrap_or(LLMProperties::new(
@@ -992,6 +1135,10 @@ pub struct AgentSessionChatRequest {
     semantic_search: bool,
     #[serde(default)]
     is_devtools_context: bool,
+    /// token/dollar budget for the run, the agentic loop pauses and asks for
+    /// confirmation once it is crossed
+    #[serde(default)]
+    cost_budget: Option<CostBudget>,
 }
 
 /// Handles the agent session and either creates it or appends to it
@@ -999,6 +1146,7 @@ pub struct AgentSessionChatRequest {
 /// Whenever we try to do an anchored or agentic editing we also go through this flow
 pub async fn agent_session_chat(
     Extension(app): Extension<Application>,
+    client_id: ClientId,
     Json(AgentSessionChatRequest {
         session_id,
         exchange_id,
@@ -1019,8 +1167,72 @@ pub async fn agent_session_chat(
         reasoning: _reasoning,
         semantic_search: _semantic_search,
         is_devtools_context: _is_devtools_context,
+        cost_budget: _,
     }): Json<AgentSessionChatRequest>,
 ) -> Result<impl IntoResponse> {
+    let session_id = client_id.scoped_id(&session_id);
+    // slash commands get parsed out of the query before anything is
+    // dispatched to the LLM, each one maps onto a subsystem we already have
+    let mut query = query;
+    let mut model_configuration = model_configuration;
+    let mut agent_mode = AideAgentMode::Chat;
+    let mut undo_last_exchange = false;
+    // MCP prompt templates invoke as `/mcp::server::prompt` commands, the
+    // template expands server side and the expansion becomes the chat query
+    if let Some(prompt_command) = parse_prompt_command(&query) {
+        let expanded = app
+            .tool_box
+            .tools()
+            .expand_mcp_prompt(
+                &prompt_command.server_name,
+                &prompt_command.prompt_name,
+                &prompt_command.arguments,
+            )
+            .await
+            .map_err(|e| super::types::Error::internal(format!("{}", e)))?;
+        query = format!("{}\n{}", expanded, prompt_command.rest)
+            .trim()
+            .to_owned();
+    }
+    if let Some(parsed_command) = parse_slash_command(&query) {
+        match parsed_command.command {
+            SlashCommand::Plan => {
+                agent_mode = AideAgentMode::Plan;
+                query = parsed_command.rest;
+            }
+            SlashCommand::Model(model) => {
+                model_configuration.slow_model = model;
+                query = parsed_command.rest;
+            }
+            SlashCommand::Test => {
+                query = format!(
+                    "Generate tests for the changes made in this session and run them to make sure they pass. {}",
+                    parsed_command.rest
+                )
+                .trim()
+                .to_owned();
+            }
+            SlashCommand::Diff => {
+                query = format!(
+                    "Show the changes made in this session by running git diff and walk me through them. {}",
+                    parsed_command.rest
+                )
+                .trim()
+                .to_owned();
+            }
+            SlashCommand::Undo => {
+                undo_last_exchange = true;
+            }
+            SlashCommand::Compact => {
+                query = format!(
+                    "Summarize the conversation so far into a compact form keeping only the context required to continue, and carry on from there. {}",
+                    parsed_command.rest
+                )
+                .trim()
+                .to_owned();
+            }
+        }
+    }
     let llm_provider = model_configuration
         .llm_properties_for_slow_model()
         .unwrap_or(LLMProperties::new(
@@ -1029,8 +1241,6 @@ pub async fn agent_session_chat(
             LLMProviderAPIKeys::CodeStory(CodestoryAccessToken::new(access_token.to_owned())),
         ));
     println!("llm_provider::{:?}", &llm_provider);
-    // bring this back later
-    let agent_mode = AideAgentMode::Chat;
     println!("webserver::agent_session::chat::hit");
     println!(
         "webserver::agent_session::chat::session_id({})",
@@ -1049,9 +1259,43 @@ pub async fn agent_session_chat(
     let session_storage_path =
         check_session_storage_path(app.config.clone(), session_id.to_string()).await;
 
+    // pinned context items for this session always ride along with whatever
+    // context the editor sent over for this exchange
+    let pinned_variables = app.pinned_context_tracker.to_variables(&session_id).await;
+    let user_context = user_context.add_variables(pinned_variables);
+
     let session_service = app.session_service.clone();
     let cloned_session_id = session_id.to_string();
 
+    // `/undo` short-circuits the LLM completely, we roll back the last
+    // exchange and confirm over the same stream
+    if undo_last_exchange {
+        let _ = tokio::spawn({
+            let sender = sender.clone();
+            let session_id = session_id.to_string();
+            let exchange_id = exchange_id.to_owned();
+            async move {
+                let _ = session_service
+                    .handle_session_undo(&exchange_id, session_storage_path)
+                    .await;
+                let _ = sender.send(UIEventWithID::chat_event(
+                    session_id.to_owned(),
+                    exchange_id.to_owned(),
+                    "Rolled back the changes from the last exchange.".to_owned(),
+                    None,
+                ));
+                let _ = sender.send(UIEventWithID::finished_exchange(session_id, exchange_id));
+            }
+        });
+        let undo_stream = tokio_stream::wrappers::UnboundedReceiverStream::new(receiver);
+        let answer_stream = undo_stream.map(|ui_event: UIEventWithID| {
+            sse::Event::default()
+                .json_data(ui_event)
+                .map_err(anyhow::Error::new)
+        });
+        return Ok(Sse::new(Box::pin(answer_stream)).into_response());
+    }
+
     let _ = tokio::spawn({
         let sender = sender.clone();
         let session_id = session_id.clone();
@@ -1078,14 +1322,10 @@ pub async fn agent_session_chat(
                 Ok(Ok(_)) => (),
                 Ok(Err(e)) => {
                     error!("Error in agent_tool_use: {:?}", e);
-                    let error_msg = match e {
-                        SymbolError::LLMClientError(LLMClientError::UnauthorizedAccess) => {
-                            "Unauthorized access. Please check your API key and try again."
-                                .to_string()
-                        }
-                        _ => format!("Internal server error: {}", e),
-                    };
-                    let _ = sender.send(UIEventWithID::error(session_id.clone(), error_msg));
+                    let _ = sender.send(UIEventWithID::structured_error(
+                        session_id.clone(),
+                        StructuredError::from(&e),
+                    ));
                 }
                 Err(e) => {
                     error!("Task panicked: {:?}", e);
@@ -1139,11 +1379,12 @@ pub async fn agent_session_chat(
 
     let stream = init_stream.chain(answer_stream).chain(done_stream);
 
-    Ok(Sse::new(Box::pin(stream)))
+    Ok(Sse::new(Box::pin(stream)).into_response())
 }
 
 pub async fn agent_session_edit_anchored(
     Extension(app): Extension<Application>,
+    client_id: ClientId,
     Json(AgentSessionChatRequest {
         session_id,
         exchange_id,
@@ -1164,8 +1405,10 @@ pub async fn agent_session_edit_anchored(
         reasoning: _reasoning,
         semantic_search: _semantic_search,
         is_devtools_context: _is_devtools_context,
+        cost_budget: _,
     }): Json<AgentSessionChatRequest>,
 ) -> Result<impl IntoResponse> {
+    let session_id = client_id.scoped_id(&session_id);
     let llm_provider = model_configuration
         .llm_properties_for_slow_model()
         .unwrap_or(LLMProperties::new(
@@ -1230,9 +1473,9 @@ pub async fn agent_session_edit_anchored(
                 Ok(Ok(_)) => (),
                 Ok(Err(e)) => {
                     error!("Error in agent_session_edit_anchored: {:?}", e);
-                    let _ = sender.send(UIEventWithID::error(
+                    let _ = sender.send(UIEventWithID::structured_error(
                         session_id.clone(),
-                        format!("Internal server error: {}", e),
+                        StructuredError::from(&e),
                     ));
                 }
                 Err(e) => {
@@ -1294,6 +1537,7 @@ pub async fn agent_session_edit_anchored(
 /// for editing
 pub async fn agent_session_edit_agentic(
     Extension(app): Extension<Application>,
+    client_id: ClientId,
     Json(AgentSessionChatRequest {
         session_id,
         exchange_id,
@@ -1314,8 +1558,10 @@ pub async fn agent_session_edit_agentic(
         reasoning: _reasoning,
         semantic_search: _semantic_search,
         is_devtools_context: _is_devtools_context,
+        cost_budget: _,
     }): Json<AgentSessionChatRequest>,
 ) -> Result<impl IntoResponse> {
+    let session_id = client_id.scoped_id(&session_id);
     let llm_provider = model_configuration
         .llm_properties_for_slow_model()
         .unwrap_or(LLMProperties::new(
@@ -1382,9 +1628,9 @@ pub async fn agent_session_edit_agentic(
                 Ok(Ok(_)) => (),
                 Ok(Err(e)) => {
                     error!("Error in agent_session_edit_agentic: {:?}", e);
-                    let _ = sender.send(UIEventWithID::error(
+                    let _ = sender.send(UIEventWithID::structured_error(
                         session_id.clone(),
-                        format!("Internal server error: {}", e),
+                        StructuredError::from(&e),
                     ));
                 }
                 Err(e) => {
@@ -1458,9 +1704,33 @@ impl ApiResponse for AgenticVerifyModelConfigResponse {}
 pub async fn verify_model_config(
     Extension(_app): Extension<Application>,
     Json(AgenticVerifyModelConfig {
-        model_configuration: _,
+        model_configuration,
     }): Json<AgenticVerifyModelConfig>,
 ) -> Result<impl IntoResponse> {
+    // openai-compatible providers (groq, together, fireworks, vllm, ...) carry
+    // a user-provided base url, so we can at least validate that structurally
+    // without pinging the endpoint
+    if let Some(llm_properties) = model_configuration.llm_properties_for_slow_model() {
+        if let LLMProviderAPIKeys::OpenAICompatible(openai_compatible) = llm_properties.api_key() {
+            if openai_compatible.api_base.trim().is_empty() {
+                return Ok(Json(AgenticVerifyModelConfigResponse {
+                    valid: false,
+                    error: Some(
+                        "No base url configured for the openai-compatible provider".to_owned(),
+                    ),
+                }));
+            }
+            if let Err(e) = reqwest::Url::parse(&openai_compatible.api_base) {
+                return Ok(Json(AgenticVerifyModelConfigResponse {
+                    valid: false,
+                    error: Some(format!(
+                        "Invalid base url for the openai-compatible provider: {}",
+                        e
+                    )),
+                }));
+            }
+        }
+    }
     // short-circuiting the reply here
     return Ok(Json(AgenticVerifyModelConfigResponse {
         valid: true,
@@ -1523,6 +1793,7 @@ pub async fn verify_model_config(
 
 pub async fn agent_tool_use(
     Extension(app): Extension<Application>,
+    client_id: ClientId,
     Json(AgentSessionChatRequest {
         session_id,
         exchange_id,
@@ -1544,8 +1815,10 @@ pub async fn agent_tool_use(
         reasoning,
         semantic_search,
         is_devtools_context,
+        cost_budget,
     }): Json<AgentSessionChatRequest>,
 ) -> Result<impl IntoResponse> {
+    let session_id = client_id.scoped_id(&session_id);
     // disable reasoning
     // disable reasoning
     let reasoning = if whoami::username() == "skcd".to_owned()
@@ -1617,6 +1890,7 @@ pub async fn agent_tool_use(
                         semantic_search,
                         mcts_log_directory,
                         Some(repo_name),
+                        cost_budget,
                         message_properties,
                         is_devtools_context,
                     )
@@ -1628,14 +1902,10 @@ pub async fn agent_tool_use(
                 Ok(Ok(_)) => (),
                 Ok(Err(e)) => {
                     error!("Error in agent_tool_use: {:?}", e);
-                    let error_msg = match e {
-                        SymbolError::LLMClientError(LLMClientError::UnauthorizedAccess) => {
-                            "Unauthorized access. Please check your API key and try again."
-                                .to_string()
-                        }
-                        _ => format!("Internal server error: {}", e),
-                    };
-                    let _ = sender.send(UIEventWithID::error(session_id.clone(), error_msg));
+                    let _ = sender.send(UIEventWithID::structured_error(
+                        session_id.clone(),
+                        StructuredError::from(&e),
+                    ));
                 }
                 Err(e) => {
                     error!("Task panicked: {:?}", e);
@@ -1688,6 +1958,7 @@ pub async fn agent_tool_use(
 
 pub async fn agent_session_plan_iterate(
     Extension(app): Extension<Application>,
+    client_id: ClientId,
     Json(AgentSessionChatRequest {
         session_id,
         exchange_id,
@@ -1708,8 +1979,10 @@ pub async fn agent_session_plan_iterate(
         reasoning: _reasoning,
         semantic_search: _semantic_search,
         is_devtools_context: _is_devtools_context,
+        cost_budget: _,
     }): Json<AgentSessionChatRequest>,
 ) -> Result<impl IntoResponse> {
+    let session_id = client_id.scoped_id(&session_id);
     let llm_provider = model_configuration
         .llm_properties_for_slow_model()
         .unwrap_or(LLMProperties::new(
@@ -1781,14 +2054,10 @@ pub async fn agent_session_plan_iterate(
                 Ok(Ok(_)) => (),
                 Ok(Err(e)) => {
                     error!("Error in agent_tool_use: {:?}", e);
-                    let error_msg = match e {
-                        SymbolError::LLMClientError(LLMClientError::UnauthorizedAccess) => {
-                            "Unauthorized access. Please check your API key and try again."
-                                .to_string()
-                        }
-                        _ => format!("Internal server error: {}", e),
-                    };
-                    let _ = sender.send(UIEventWithID::error(session_id.clone(), error_msg));
+                    let _ = sender.send(UIEventWithID::structured_error(
+                        session_id.clone(),
+                        StructuredError::from(&e),
+                    ));
                 }
                 Err(e) => {
                     error!("Task panicked: {:?}", e);
@@ -1842,6 +2111,7 @@ pub async fn agent_session_plan_iterate(
 /// Generates the plan over here
 pub async fn agent_session_plan(
     Extension(app): Extension<Application>,
+    client_id: ClientId,
     Json(AgentSessionChatRequest {
         session_id,
         exchange_id,
@@ -1862,8 +2132,10 @@ pub async fn agent_session_plan(
         reasoning: _reasoning,
         semantic_search: _semantic_search,
         is_devtools_context: _is_devtools_context,
+        cost_budget: _,
     }): Json<AgentSessionChatRequest>,
 ) -> Result<impl IntoResponse> {
+    let session_id = client_id.scoped_id(&session_id);
     let llm_provider = model_configuration
         .llm_properties_for_slow_model()
         .unwrap_or(LLMProperties::new(
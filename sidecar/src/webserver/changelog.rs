@@ -0,0 +1,256 @@
+//! Changelog generation over one or more sessions. We pull the user queries
+//! out of the stored sessions, resolve the diff they produced from git and
+//! ask the model to group the work into categorized changelog entries
+//! (feature, fix, refactor) which slot straight into release notes
+
+use axum::response::IntoResponse;
+use axum::{Extension, Json};
+use llm_client::clients::types::{
+    GenerationProfile, LLMClientCompletionRequest, LLMClientMessage, LLMType,
+};
+use llm_client::provider::{
+    CodeStoryLLMTypes, CodestoryAccessToken, LLMProvider, LLMProviderAPIKeys,
+};
+use std::collections::HashMap;
+
+use super::model_selection::LLMClientConfig;
+use super::plan::check_session_storage_path;
+use super::review::{diff_for_ref_range, tag_value};
+use super::types::{json, ApiResponse, Result};
+use crate::agentic::symbol::identifier::LLMProperties;
+use crate::agentic::tool::session::session::Session;
+use crate::application::application::Application;
+
+/// Diffs past this size stop adding signal for a changelog and only burn
+/// context window, we truncate and tell the model we did
+const MAX_DIFF_BYTES: usize = 50_000;
+
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct ChangelogRequest {
+    pub root_directory: String,
+    /// the stored sessions whose queries describe the work being released
+    pub session_ids: Vec<String>,
+    /// a git revision range understood by `git diff` (e.g. `main...HEAD`),
+    /// defaults to the uncommitted changes against HEAD
+    #[serde(default)]
+    pub ref_range: Option<String>,
+    pub access_token: String,
+    pub model_configuration: LLMClientConfig,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangelogCategory {
+    Feature,
+    Fix,
+    Refactor,
+    /// docs, chores and anything else which does not fit the buckets above
+    Other,
+}
+
+impl ChangelogCategory {
+    fn parse(value: &str) -> Self {
+        match value.trim().to_lowercase().as_str() {
+            "feature" => ChangelogCategory::Feature,
+            "fix" => ChangelogCategory::Fix,
+            "refactor" => ChangelogCategory::Refactor,
+            _ => ChangelogCategory::Other,
+        }
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ChangelogEntry {
+    pub category: ChangelogCategory,
+    /// one release-note style line describing the change
+    pub description: String,
+    /// the files this entry touches, when the model listed them
+    pub fs_file_paths: Vec<String>,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct ChangelogResponse {
+    entries: Vec<ChangelogEntry>,
+    /// the session queries the changelog was generated from, handy for the
+    /// editor to show what went into the summary
+    session_queries: Vec<String>,
+}
+
+impl ApiResponse for ChangelogResponse {}
+
+fn changelog_system_prompt() -> String {
+    r#"You are writing a changelog entry from the work a developer did. You get the requests they made to their coding assistant and the resulting diff. Group the work into changelog entries a user of the project would care about, one entry per logical change, skipping noise like formatting-only churn.
+Reply with one or more entries in the following format and nothing else:
+<entry>
+<category>feature|fix|refactor|other</category>
+<description>one release-note style line describing the change</description>
+<files>comma separated file paths this entry touches, or omit this tag</files>
+</entry>"#
+        .to_owned()
+}
+
+fn changelog_user_message(session_queries: &[String], diff: &str) -> String {
+    let mut message = String::new();
+    if !session_queries.is_empty() {
+        message.push_str("Requests made during the sessions:\n");
+        for query in session_queries.iter() {
+            message.push_str(&format!("- {}\n", query));
+        }
+        message.push('\n');
+    }
+    if diff.len() > MAX_DIFF_BYTES {
+        let mut cutoff = MAX_DIFF_BYTES;
+        while !diff.is_char_boundary(cutoff) {
+            cutoff -= 1;
+        }
+        message.push_str(&format!(
+            "The diff produced (truncated):\n```\n{}\n```",
+            &diff[..cutoff]
+        ));
+    } else {
+        message.push_str(&format!("The diff produced:\n```\n{}\n```", diff));
+    }
+    message
+}
+
+/// Parses the model reply into structured changelog entries, malformed
+/// blocks get skipped instead of failing the whole changelog
+pub(crate) fn parse_changelog_entries(response: &str) -> Vec<ChangelogEntry> {
+    let mut entries = vec![];
+    let mut remaining = response;
+    while let Some(start) = remaining.find("<entry>") {
+        let after_open = &remaining[start + "<entry>".len()..];
+        let Some(end) = after_open.find("</entry>") else {
+            break;
+        };
+        let block = &after_open[..end];
+        remaining = &after_open[end + "</entry>".len()..];
+        let Some(description) = tag_value(block, "description") else {
+            continue;
+        };
+        if description.is_empty() {
+            continue;
+        }
+        entries.push(ChangelogEntry {
+            category: tag_value(block, "category")
+                .map(ChangelogCategory::parse)
+                .unwrap_or(ChangelogCategory::Other),
+            description: description.to_owned(),
+            fs_file_paths: tag_value(block, "files")
+                .map(|files| {
+                    files
+                        .split(',')
+                        .map(|file| file.trim().to_owned())
+                        .filter(|file| !file.is_empty())
+                        .collect()
+                })
+                .unwrap_or_default(),
+        });
+    }
+    entries
+}
+
+pub async fn generate_changelog(
+    Extension(app): Extension<Application>,
+    Json(request): Json<ChangelogRequest>,
+) -> Result<impl IntoResponse> {
+    println!(
+        "webserver::generate_changelog::sessions({})",
+        request.session_ids.join(",")
+    );
+    let mut session_queries = vec![];
+    for session_id in request.session_ids.iter() {
+        let storage_path =
+            check_session_storage_path(app.config.clone(), session_id.to_owned()).await;
+        // sessions which never got stored simply contribute no queries, the
+        // diff still describes the work
+        if let Ok(content) = tokio::fs::read_to_string(&storage_path).await {
+            if let Ok(session) = serde_json::from_str::<Session>(&content) {
+                session_queries.extend(session.user_queries());
+            }
+        }
+    }
+    let ref_range = request.ref_range.as_deref().unwrap_or("HEAD");
+    let diff = diff_for_ref_range(&request.root_directory, ref_range).await?;
+    if diff.trim().is_empty() && session_queries.is_empty() {
+        return Ok(json(ChangelogResponse {
+            entries: vec![],
+            session_queries,
+        }));
+    }
+    let llm_properties = request
+        .model_configuration
+        .llm_properties_for_slow_model()
+        .unwrap_or(LLMProperties::new(
+            LLMType::ClaudeSonnet,
+            LLMProvider::CodeStory(CodeStoryLLMTypes::new()),
+            LLMProviderAPIKeys::CodeStory(CodestoryAccessToken::new(
+                request.access_token.to_owned(),
+            )),
+        ));
+    let completion_request = LLMClientCompletionRequest::from_messages_with_profile(
+        request
+            .model_configuration
+            .generation_profile_or(GenerationProfile::Fast),
+        vec![
+            LLMClientMessage::system(changelog_system_prompt()),
+            LLMClientMessage::user(changelog_user_message(&session_queries, &diff)),
+        ],
+        llm_properties.llm().clone(),
+    );
+    let (sender, _receiver) = tokio::sync::mpsc::unbounded_channel();
+    let response = app
+        .llm_broker
+        .stream_completion(
+            llm_properties.api_key().clone(),
+            completion_request,
+            llm_properties.provider().clone(),
+            vec![("event_type".to_owned(), "generate_changelog".to_owned())]
+                .into_iter()
+                .collect::<HashMap<_, _>>(),
+            sender,
+        )
+        .await
+        .map_err(|e| anyhow::anyhow!(e))?;
+    Ok(json(ChangelogResponse {
+        entries: parse_changelog_entries(response.answer_up_until_now()),
+        session_queries,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_changelog_entries, ChangelogCategory};
+
+    #[test]
+    fn test_parsing_changelog_entries() {
+        let response = r#"<entry>
+<category>feature</category>
+<description>Added per-tool timeouts to the tool broker</description>
+<files>src/agentic/tool/broker.rs, src/agentic/tool/errors.rs</files>
+</entry>
+<entry>
+<description>missing category defaults to other</description>
+</entry>"#;
+        let entries = parse_changelog_entries(response);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].category, ChangelogCategory::Feature);
+        assert_eq!(
+            entries[0].fs_file_paths,
+            vec![
+                "src/agentic/tool/broker.rs".to_owned(),
+                "src/agentic/tool/errors.rs".to_owned()
+            ]
+        );
+        assert_eq!(entries[1].category, ChangelogCategory::Other);
+        assert!(entries[1].fs_file_paths.is_empty());
+    }
+
+    #[test]
+    fn test_entries_without_description_are_skipped() {
+        let response = r#"<entry>
+<category>fix</category>
+</entry>"#;
+        assert!(parse_changelog_entries(response).is_empty());
+    }
+}
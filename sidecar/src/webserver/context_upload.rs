@@ -0,0 +1,56 @@
+//! Streaming upload endpoint for oversized user context
+//!
+//! The webserver enforces a body limit on the json endpoints, so contexts
+//! which are bigger than that get streamed over here chunk by chunk instead.
+//! We store the payload in the scratch pad directory and hand back the path,
+//! which the editor then passes through UserContext as a file reference
+//! instead of inlining the whole content into every request.
+
+use axum::extract::BodyStream;
+use axum::response::IntoResponse;
+use axum::Extension;
+use futures::StreamExt;
+use tokio::io::AsyncWriteExt;
+
+use crate::application::application::Application;
+
+use super::types::json;
+use super::types::ApiResponse;
+use super::types::Result;
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ContextUploadResponse {
+    /// path in the scratch pad where the uploaded context has been stored,
+    /// reference this as a file in UserContext
+    fs_file_path: String,
+    bytes_written: u64,
+}
+
+impl ApiResponse for ContextUploadResponse {}
+
+pub async fn upload_context(
+    Extension(app): Extension<Application>,
+    mut body: BodyStream,
+) -> Result<impl IntoResponse> {
+    let upload_directory = app.config.scratch_pad().join("uploaded_context");
+    tokio::fs::create_dir_all(&upload_directory)
+        .await
+        .map_err(|e| anyhow::anyhow!(e))?;
+    let fs_file_path = upload_directory.join(format!("{}.txt", uuid::Uuid::new_v4()));
+    let mut file = tokio::fs::File::create(&fs_file_path)
+        .await
+        .map_err(|e| anyhow::anyhow!(e))?;
+    let mut bytes_written: u64 = 0;
+    while let Some(chunk) = body.next().await {
+        let chunk = chunk.map_err(|e| anyhow::anyhow!(e))?;
+        file.write_all(&chunk)
+            .await
+            .map_err(|e| anyhow::anyhow!(e))?;
+        bytes_written = bytes_written + chunk.len() as u64;
+    }
+    file.flush().await.map_err(|e| anyhow::anyhow!(e))?;
+    Ok(json(ContextUploadResponse {
+        fs_file_path: fs_file_path.to_string_lossy().to_string(),
+        bytes_written,
+    }))
+}
@@ -0,0 +1,191 @@
+//! Debug endpoint which exposes runtime statistics about the sidecar process
+//!
+//! Users reporting memory growth after long sessions can hit
+//! GET /api/debug/runtime and attach the response to their report, this gives
+//! us task counts per category, cache sizes and the process RSS without having
+//! to ask them to run platform specific tooling.
+
+use axum::extract::Path;
+use axum::response::IntoResponse;
+use axum::Extension;
+use sysinfo::System;
+
+use crate::application::application::Application;
+
+use super::types::json;
+use super::types::ApiResponse;
+use super::types::Result;
+
+/// Exchange ids which have compressed trace chunks stored for a session
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct SessionTraceIndexResponse {
+    session_id: String,
+    exchange_ids: Vec<String>,
+}
+
+impl ApiResponse for SessionTraceIndexResponse {}
+
+pub async fn session_trace_index(
+    Extension(app): Extension<Application>,
+    Path(session_id): Path<String>,
+) -> Result<impl IntoResponse> {
+    let exchange_ids = app.session_trace_store.list_exchanges(&session_id).await;
+    Ok(json(SessionTraceIndexResponse {
+        session_id,
+        exchange_ids,
+    }))
+}
+
+/// A single exchange loaded from the compressed trace store, the rest of
+/// the session stays on disk
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct SessionTraceExchangeResponse {
+    session_id: String,
+    exchange_id: String,
+    /// raw json of the exchange, missing when no chunk exists for it
+    exchange: Option<serde_json::Value>,
+}
+
+impl ApiResponse for SessionTraceExchangeResponse {}
+
+pub async fn session_trace_exchange(
+    Extension(app): Extension<Application>,
+    Path((session_id, exchange_id)): Path<(String, String)>,
+) -> Result<impl IntoResponse> {
+    let exchange = app
+        .session_trace_store
+        .load_exchange(&session_id, &exchange_id)
+        .await
+        .and_then(|payload| serde_json::from_str(&payload).ok());
+    Ok(json(SessionTraceExchangeResponse {
+        session_id,
+        exchange_id,
+        exchange,
+    }))
+}
+
+/// Queue metrics of the per-provider LLM request limiter
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct LLMConcurrencyResponse {
+    providers: Vec<llm_client::broker::ProviderConcurrencyStats>,
+}
+
+impl ApiResponse for LLMConcurrencyResponse {}
+
+pub async fn llm_concurrency_stats(
+    Extension(app): Extension<Application>,
+) -> Result<impl IntoResponse> {
+    Ok(json(LLMConcurrencyResponse {
+        providers: app.llm_broker.provider_concurrency_stats(),
+    }))
+}
+
+/// Counts of the background tasks we are tracking, keyed by the subsystem
+/// which spawned them
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct RuntimeTaskCounts {
+    /// probe requests which are currently in flight
+    probe_requests: usize,
+    /// anchored editing requests which are currently in flight
+    anchored_editing_requests: usize,
+    /// background jobs which are queued or running
+    background_jobs: usize,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct RuntimeStatsResponse {
+    task_counts: RuntimeTaskCounts,
+    /// number of documents the inline completion symbol tracker has cached
+    tracked_documents: usize,
+    /// resident set size of the sidecar process in bytes, if we are able
+    /// to read it from the system
+    process_rss_bytes: Option<u64>,
+}
+
+impl ApiResponse for RuntimeStatsResponse {}
+
+pub async fn runtime_stats(
+    Extension(app): Extension<Application>,
+) -> Result<impl IntoResponse> {
+    let probe_requests = app.probe_request_tracker.running_request_count().await;
+    let anchored_editing_requests = app.anchored_request_tracker.running_request_count().await;
+    let background_jobs = app.job_tracker.active_job_count().await;
+    let tracked_documents = app.symbol_tracker.get_document_history().await.len();
+    let process_rss_bytes = process_rss_bytes();
+    Ok(json(RuntimeStatsResponse {
+        task_counts: RuntimeTaskCounts {
+            probe_requests,
+            anchored_editing_requests,
+            background_jobs,
+        },
+        tracked_documents,
+        process_rss_bytes,
+    }))
+}
+
+/// A single per-session capture file with scrubbed LLM request/response
+/// pairs, the editor reads the file itself for the "show what was sent to
+/// the model" view
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct LLMCaptureIndexEntry {
+    session_id: String,
+    fs_file_path: String,
+    size_bytes: u64,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct LLMCaptureIndexResponse {
+    /// false when the sidecar was started without --capture-llm-requests
+    capture_enabled: bool,
+    sessions: Vec<LLMCaptureIndexEntry>,
+}
+
+impl ApiResponse for LLMCaptureIndexResponse {}
+
+/// Lists the per-session LLM capture files which have been written so far,
+/// sorted by session id so the output is stable across calls
+pub async fn llm_capture_index(
+    Extension(app): Extension<Application>,
+) -> Result<impl IntoResponse> {
+    let capture_enabled = app.config.capture_llm_requests;
+    let mut sessions = vec![];
+    if let Ok(mut read_dir) = tokio::fs::read_dir(app.config.llm_capture_dir()).await {
+        while let Ok(Some(entry)) = read_dir.next_entry().await {
+            let file_path = entry.path();
+            let Some(session_id) = file_path
+                .file_stem()
+                .and_then(|file_stem| file_stem.to_str())
+            else {
+                continue;
+            };
+            if file_path.extension().and_then(|extension| extension.to_str()) != Some("jsonl") {
+                continue;
+            }
+            let size_bytes = entry
+                .metadata()
+                .await
+                .map(|metadata| metadata.len())
+                .unwrap_or_default();
+            sessions.push(LLMCaptureIndexEntry {
+                session_id: session_id.to_owned(),
+                fs_file_path: file_path.to_string_lossy().to_string(),
+                size_bytes,
+            });
+        }
+    }
+    sessions.sort_by(|left, right| left.session_id.cmp(&right.session_id));
+    Ok(json(LLMCaptureIndexResponse {
+        capture_enabled,
+        sessions,
+    }))
+}
+
+/// Grabs the resident set size for our own process, this refreshes just the
+/// single process instead of the whole process table to keep the endpoint
+/// cheap to hit
+fn process_rss_bytes() -> Option<u64> {
+    let pid = sysinfo::Pid::from_u32(std::process::id());
+    let mut system = System::new();
+    system.refresh_process(pid);
+    system.process(pid).map(|process| process.memory())
+}
@@ -0,0 +1,194 @@
+//! Per-repo index of doc comments keyed by symbol name
+//!
+//! "what does X do" questions usually have their answer sitting right above
+//! the symbol as a doc comment. The index extracts those with tree-sitter
+//! and serves them instantly, the editor only falls back to LLM probing
+//! when a symbol has no docs. The document open and content change
+//! endpoints feed the index so it stays fresh as the user edits.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use axum::response::IntoResponse;
+use axum::{Extension, Json};
+use tokio::sync::Mutex;
+
+use crate::application::application::Application;
+use crate::chunking::languages::TSLanguageParsing;
+use crate::chunking::types::concat_documentation_string;
+
+use super::types::json;
+use super::types::ApiResponse;
+use super::types::Result;
+
+/// A doc comment paired with the symbol it sits above
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct SymbolDocumentation {
+    pub symbol_name: String,
+    pub fs_file_path: String,
+    /// the line the symbol starts on, 0-indexed
+    pub start_line: usize,
+    pub docs: String,
+}
+
+/// The index itself, entries are stored per file so a change to one file
+/// only reindexes that file
+pub struct DocumentationIndex {
+    entries: Arc<Mutex<HashMap<String, Vec<SymbolDocumentation>>>>,
+    language_parsing: Arc<TSLanguageParsing>,
+}
+
+impl DocumentationIndex {
+    pub fn new(language_parsing: Arc<TSLanguageParsing>) -> Self {
+        Self {
+            entries: Arc::new(Mutex::new(HashMap::new())),
+            language_parsing,
+        }
+    }
+
+    /// Reindexes a single file, called whenever the editor reports the file
+    /// as opened or changed
+    pub async fn index_file(&self, fs_file_path: &str, content: &str) {
+        let Some(language_config) = self.language_parsing.for_file_path(fs_file_path) else {
+            return;
+        };
+        let documentation_entries = concat_documentation_string(
+            language_config.capture_documentation_queries(content.as_bytes()),
+        )
+        .into_iter()
+        .map(|(range, docs)| (range.end_line(), docs))
+        .collect::<Vec<_>>();
+        let symbols = language_config
+            .generate_outline_fresh(content.as_bytes(), fs_file_path)
+            .into_iter()
+            .map(|outline_node| {
+                (
+                    outline_node.name().to_owned(),
+                    outline_node.range().start_line(),
+                )
+            })
+            .collect::<Vec<_>>();
+        let documented_symbols = pair_docs_with_symbols(&documentation_entries, &symbols)
+            .into_iter()
+            .map(|(symbol_name, start_line, docs)| SymbolDocumentation {
+                symbol_name,
+                fs_file_path: fs_file_path.to_owned(),
+                start_line,
+                docs,
+            })
+            .collect::<Vec<_>>();
+        let mut entries = self.entries.lock().await;
+        entries.insert(fs_file_path.to_owned(), documented_symbols);
+    }
+
+    /// Indexes the file from disk when nothing has been indexed for it yet,
+    /// covers lookups which arrive before the editor opened the file
+    pub async fn ensure_indexed(&self, fs_file_path: &str) {
+        {
+            let entries = self.entries.lock().await;
+            if entries.contains_key(fs_file_path) {
+                return;
+            }
+        }
+        if let Ok(content) = tokio::fs::read_to_string(fs_file_path).await {
+            self.index_file(fs_file_path, &content).await;
+        }
+    }
+
+    /// Every documented symbol matching the name, across all indexed files
+    pub async fn lookup(&self, symbol_name: &str) -> Vec<SymbolDocumentation> {
+        let entries = self.entries.lock().await;
+        entries
+            .values()
+            .flatten()
+            .filter(|documentation| documentation.symbol_name == symbol_name)
+            .cloned()
+            .collect()
+    }
+}
+
+/// Pairs doc comments with the symbol starting right below them. The doc
+/// entries are (end line, docs) and the symbols (name, start line); a doc
+/// belongs to the closest symbol starting on the following line. Symbol
+/// ranges include their doc comment for some grammars so a doc ending
+/// inside the line span of the symbol it annotates also counts
+pub(crate) fn pair_docs_with_symbols(
+    documentation_entries: &[(usize, String)],
+    symbols: &[(String, usize)],
+) -> Vec<(String, usize, String)> {
+    let mut paired = vec![];
+    for (symbol_name, start_line) in symbols.iter() {
+        let docs = documentation_entries
+            .iter()
+            .find(|(doc_end_line, _)| {
+                doc_end_line + 1 == *start_line || doc_end_line == start_line
+            })
+            .map(|(_, docs)| docs.to_owned());
+        if let Some(docs) = docs {
+            paired.push((symbol_name.to_owned(), *start_line, docs));
+        }
+    }
+    paired
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct SymbolDocumentationRequest {
+    symbol_name: String,
+    /// when set the file gets indexed on demand before the lookup
+    fs_file_path: Option<String>,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct SymbolDocumentationResponse {
+    /// true when the index had docs, the editor probes with the LLM when
+    /// this comes back false
+    found: bool,
+    documentation: Vec<SymbolDocumentation>,
+}
+
+impl ApiResponse for SymbolDocumentationResponse {}
+
+pub async fn symbol_documentation(
+    Extension(app): Extension<Application>,
+    Json(SymbolDocumentationRequest {
+        symbol_name,
+        fs_file_path,
+    }): Json<SymbolDocumentationRequest>,
+) -> Result<impl IntoResponse> {
+    println!(
+        "webserver::symbol_documentation::symbol_name({})",
+        &symbol_name
+    );
+    if let Some(fs_file_path) = fs_file_path.as_deref() {
+        app.documentation_index.ensure_indexed(fs_file_path).await;
+    }
+    let documentation = app.documentation_index.lookup(&symbol_name).await;
+    Ok(json(SymbolDocumentationResponse {
+        found: !documentation.is_empty(),
+        documentation,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::pair_docs_with_symbols;
+
+    #[test]
+    fn test_docs_pair_with_the_symbol_below_them() {
+        let documentation_entries = vec![
+            (2, "/// runs the thing".to_owned()),
+            (10, "/// unrelated".to_owned()),
+        ];
+        let symbols = vec![("run".to_owned(), 3), ("stop".to_owned(), 20)];
+        let paired = pair_docs_with_symbols(&documentation_entries, &symbols);
+        assert_eq!(paired.len(), 1);
+        assert_eq!(paired[0].0, "run");
+        assert_eq!(paired[0].2, "/// runs the thing");
+    }
+
+    #[test]
+    fn test_symbols_without_docs_stay_out_of_the_index() {
+        let paired = pair_docs_with_symbols(&[], &[("run".to_owned(), 3)]);
+        assert!(paired.is_empty());
+    }
+}
@@ -0,0 +1,338 @@
+//! Server side registry for proposed edits
+//!
+//! Instead of applying an edit directly, sidecar can hand the editor a
+//! structured proposed change (per range, with the original text) which the
+//! editor renders as an inline accept/reject widget. The registry keeps the
+//! outstanding proposals so accepting or rejecting is a plain API call, and
+//! an accept only applies when the file still matches what was proposed
+//! against.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use axum::response::IntoResponse;
+use axum::{Extension, Json};
+use tokio::sync::Mutex;
+
+use crate::application::application::Application;
+use crate::chunking::text_document::Range;
+
+use super::types::json;
+use super::types::ApiResponse;
+use super::types::Result;
+
+/// which state a proposal is in, pending ones are the only ones which can
+/// still be resolved
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProposalStatus {
+    Pending,
+    Accepted,
+    Rejected,
+}
+
+/// A single proposed change over a range of a file, carrying the original
+/// text so the editor can render a diff widget and so acceptance can detect
+/// when the file moved underneath the proposal
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ProposedChange {
+    pub proposal_id: String,
+    pub fs_file_path: String,
+    pub range: Range,
+    pub original_text: String,
+    pub updated_text: String,
+    pub status: ProposalStatus,
+}
+
+/// Tracks the outstanding edit proposals, shared across the webserver
+/// handlers through the application state
+pub struct EditProposalRegistry {
+    proposals: Arc<Mutex<HashMap<String, ProposedChange>>>,
+}
+
+impl EditProposalRegistry {
+    pub fn new() -> Self {
+        Self {
+            proposals: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    pub async fn register(&self, proposal: ProposedChange) {
+        let mut proposals = self.proposals.lock().await;
+        proposals.insert(proposal.proposal_id.to_owned(), proposal);
+    }
+
+    /// the outstanding proposals, optionally narrowed to a single file,
+    /// ordered by their position so the editor can render them top to bottom
+    pub async fn list(&self, fs_file_path: Option<&str>) -> Vec<ProposedChange> {
+        let proposals = self.proposals.lock().await;
+        let mut listed = proposals
+            .values()
+            .filter(|proposal| {
+                fs_file_path
+                    .map(|fs_file_path| proposal.fs_file_path == fs_file_path)
+                    .unwrap_or(true)
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        listed.sort_by_key(|proposal| {
+            (
+                proposal.fs_file_path.to_owned(),
+                proposal.range.start_line(),
+            )
+        });
+        listed
+    }
+
+    /// Marks a pending proposal as accepted or rejected, None when the id is
+    /// unknown or the proposal was already resolved
+    pub async fn resolve(&self, proposal_id: &str, accepted: bool) -> Option<ProposedChange> {
+        let mut proposals = self.proposals.lock().await;
+        let proposal = proposals.get_mut(proposal_id)?;
+        if proposal.status != ProposalStatus::Pending {
+            return None;
+        }
+        proposal.status = if accepted {
+            ProposalStatus::Accepted
+        } else {
+            ProposalStatus::Rejected
+        };
+        Some(proposal.clone())
+    }
+}
+
+/// Pulls the text a line-column range covers out of the file content, None
+/// when the range points outside the file
+pub(crate) fn text_for_range(content: &str, range: &Range) -> Option<String> {
+    let lines = content.lines().collect::<Vec<_>>();
+    if range.end_line() >= lines.len() || range.start_line() > range.end_line() {
+        return None;
+    }
+    let mut selected = vec![];
+    for (line_index, line) in lines
+        .iter()
+        .enumerate()
+        .take(range.end_line() + 1)
+        .skip(range.start_line())
+    {
+        let characters = line.chars().collect::<Vec<_>>();
+        let start_column = if line_index == range.start_line() {
+            range.start_column()
+        } else {
+            0
+        };
+        let end_column = if line_index == range.end_line() {
+            range.end_column().min(characters.len())
+        } else {
+            characters.len()
+        };
+        if start_column > end_column {
+            return None;
+        }
+        selected.push(characters[start_column..end_column].iter().collect::<String>());
+    }
+    Some(selected.join("\n"))
+}
+
+/// Splices the updated text over the range, the caller has already checked
+/// that the range still holds the original text
+pub(crate) fn apply_change_to_content(content: &str, range: &Range, updated_text: &str) -> String {
+    let lines = content.lines().collect::<Vec<_>>();
+    let prefix_lines = &lines[..range.start_line()];
+    let prefix_in_line = lines
+        .get(range.start_line())
+        .map(|line| line.chars().take(range.start_column()).collect::<String>())
+        .unwrap_or_default();
+    let suffix_in_line = lines
+        .get(range.end_line())
+        .map(|line| line.chars().skip(range.end_column()).collect::<String>())
+        .unwrap_or_default();
+    let suffix_lines = if range.end_line() + 1 < lines.len() {
+        &lines[range.end_line() + 1..]
+    } else {
+        &[]
+    };
+    let mut updated_lines = prefix_lines
+        .iter()
+        .map(|line| line.to_string())
+        .collect::<Vec<_>>();
+    updated_lines.push(format!("{}{}{}", prefix_in_line, updated_text, suffix_in_line));
+    updated_lines.extend(suffix_lines.iter().map(|line| line.to_string()));
+    updated_lines.join("\n")
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct ProposedEditInput {
+    range: Range,
+    updated_text: String,
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct ProposeEditsRequest {
+    fs_file_path: String,
+    edits: Vec<ProposedEditInput>,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ProposeEditsResponse {
+    proposals: Vec<ProposedChange>,
+}
+
+impl ApiResponse for ProposeEditsResponse {}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct ListEditProposalsRequest {
+    fs_file_path: Option<String>,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ListEditProposalsResponse {
+    proposals: Vec<ProposedChange>,
+}
+
+impl ApiResponse for ListEditProposalsResponse {}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct ResolveEditProposalRequest {
+    proposal_id: String,
+    accepted: bool,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ResolveEditProposalResponse {
+    done: bool,
+    /// whether the accepted change made it to disk, false when the file no
+    /// longer matches the proposal
+    applied: bool,
+    message: Option<String>,
+}
+
+impl ApiResponse for ResolveEditProposalResponse {}
+
+pub async fn propose_edits(
+    Extension(app): Extension<Application>,
+    Json(ProposeEditsRequest {
+        fs_file_path,
+        edits,
+    }): Json<ProposeEditsRequest>,
+) -> Result<impl IntoResponse> {
+    println!(
+        "webserver::propose_edits::fs_file_path({})::edits({})",
+        &fs_file_path,
+        edits.len()
+    );
+    let content = tokio::fs::read_to_string(&fs_file_path)
+        .await
+        .unwrap_or_default();
+    let mut proposals = vec![];
+    for edit in edits.into_iter() {
+        let Some(original_text) = text_for_range(&content, &edit.range) else {
+            continue;
+        };
+        let proposal = ProposedChange {
+            proposal_id: uuid::Uuid::new_v4().to_string(),
+            fs_file_path: fs_file_path.to_owned(),
+            range: edit.range,
+            original_text,
+            updated_text: edit.updated_text,
+            status: ProposalStatus::Pending,
+        };
+        app.edit_proposal_registry.register(proposal.clone()).await;
+        proposals.push(proposal);
+    }
+    Ok(json(ProposeEditsResponse { proposals }))
+}
+
+pub async fn list_edit_proposals(
+    Extension(app): Extension<Application>,
+    Json(ListEditProposalsRequest { fs_file_path }): Json<ListEditProposalsRequest>,
+) -> Result<impl IntoResponse> {
+    let proposals = app
+        .edit_proposal_registry
+        .list(fs_file_path.as_deref())
+        .await;
+    Ok(json(ListEditProposalsResponse { proposals }))
+}
+
+pub async fn resolve_edit_proposal(
+    Extension(app): Extension<Application>,
+    Json(ResolveEditProposalRequest {
+        proposal_id,
+        accepted,
+    }): Json<ResolveEditProposalRequest>,
+) -> Result<impl IntoResponse> {
+    println!(
+        "webserver::resolve_edit_proposal::proposal_id({})::accepted({})",
+        &proposal_id, accepted
+    );
+    let Some(proposal) = app.edit_proposal_registry.resolve(&proposal_id, accepted).await else {
+        return Ok(json(ResolveEditProposalResponse {
+            done: false,
+            applied: false,
+            message: Some("no pending proposal with that id".to_owned()),
+        }));
+    };
+    if !accepted {
+        return Ok(json(ResolveEditProposalResponse {
+            done: true,
+            applied: false,
+            message: None,
+        }));
+    }
+    // only apply when the file still holds the text the proposal was made
+    // against, otherwise the splice would corrupt unrelated edits
+    let content = tokio::fs::read_to_string(&proposal.fs_file_path)
+        .await
+        .unwrap_or_default();
+    if text_for_range(&content, &proposal.range).as_deref() != Some(&proposal.original_text) {
+        return Ok(json(ResolveEditProposalResponse {
+            done: true,
+            applied: false,
+            message: Some("the file changed since the proposal was created".to_owned()),
+        }));
+    }
+    let updated_content =
+        apply_change_to_content(&content, &proposal.range, &proposal.updated_text);
+    let applied = tokio::fs::write(&proposal.fs_file_path, updated_content)
+        .await
+        .is_ok();
+    Ok(json(ResolveEditProposalResponse {
+        done: true,
+        applied,
+        message: None,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{apply_change_to_content, text_for_range};
+    use crate::chunking::text_document::{Position, Range};
+
+    fn range(start_line: usize, start_column: usize, end_line: usize, end_column: usize) -> Range {
+        Range::new(
+            Position::new(start_line, start_column, 0),
+            Position::new(end_line, end_column, 0),
+        )
+    }
+
+    #[test]
+    fn test_text_for_range_spans_lines_and_columns() {
+        let content = "fn main() {\n    let value = 42;\n}";
+        assert_eq!(
+            text_for_range(content, &range(1, 4, 1, 19)),
+            Some("let value = 42;".to_owned())
+        );
+        assert_eq!(
+            text_for_range(content, &range(0, 0, 1, 0)),
+            Some("fn main() {\n".to_owned())
+        );
+        assert!(text_for_range(content, &range(5, 0, 6, 0)).is_none());
+    }
+
+    #[test]
+    fn test_apply_change_splices_the_updated_text() {
+        let content = "fn main() {\n    let value = 42;\n}";
+        let updated = apply_change_to_content(content, &range(1, 4, 1, 19), "let value = 7;");
+        assert_eq!(updated, "fn main() {\n    let value = 7;\n}");
+    }
+}
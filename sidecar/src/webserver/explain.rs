@@ -0,0 +1,206 @@
+//! Explains a symbol or a selection using the probing stack
+//!
+//! The editor sends over the active file along with either a range or a
+//! symbol name and we run a probe request over it, asking for a structured
+//! explanation (purpose, inputs and outputs, key collaborators with their
+//! file paths). The probe events stream back as UIEvents and the final
+//! reply gets cached on disk so asking about the same symbol again does
+//! not spend another round of LLM calls
+
+use std::{path::PathBuf, sync::Arc};
+
+use axum::response::{sse, IntoResponse, Sse};
+use axum::{Extension, Json};
+use futures::StreamExt;
+use llm_client::clients::types::LLMType;
+use llm_client::provider::{
+    CodeStoryLLMTypes, CodestoryAccessToken, LLMProvider, LLMProviderAPIKeys,
+};
+
+use crate::agentic::symbol::events::input::SymbolEventRequestId;
+use crate::agentic::symbol::events::message_event::SymbolEventMessageProperties;
+use crate::agentic::symbol::identifier::LLMProperties;
+use crate::agentic::symbol::ui_event::UIEventWithID;
+use crate::application::application::Application;
+use crate::application::config::configuration::Configuration;
+use crate::chunking::text_document::{Position, Range};
+use crate::user_context::types::{UserContext, VariableInformation};
+
+use super::types::Result;
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct AgentExplainRequest {
+    request_id: String,
+    editor_url: String,
+    access_token: String,
+    fs_file_path: String,
+    file_content: String,
+    language: String,
+    /// explain just this part of the file, the whole file is used when missing
+    range: Option<Range>,
+    /// explain this symbol by name instead of a selection
+    symbol_name: Option<String>,
+}
+
+/// Checks for the explanations directory and creates the path for the
+/// cached explanation of this target
+pub async fn check_explanation_storage_path(config: Arc<Configuration>, target: &str) -> String {
+    let mut explanation_path = config.index_dir.clone();
+    explanation_path = explanation_path.join("explanations");
+    // check if the explanation_storage_path exists
+    if tokio::fs::metadata(&explanation_path).await.is_err() {
+        tokio::fs::create_dir(&explanation_path)
+            .await
+            .expect("directory creation to not fail");
+    }
+    // the target carries file paths and ranges, flatten it into something
+    // every filesystem accepts
+    let file_name = target
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect::<String>();
+    explanation_path = explanation_path.join(file_name);
+    explanation_path
+        .to_str()
+        .expect("path conversion to work on all platforms")
+        .to_owned()
+}
+
+pub async fn agent_explain(
+    Extension(app): Extension<Application>,
+    Json(AgentExplainRequest {
+        request_id,
+        editor_url,
+        access_token,
+        fs_file_path,
+        file_content,
+        language,
+        range,
+        symbol_name,
+    }): Json<AgentExplainRequest>,
+) -> Result<impl IntoResponse> {
+    println!("webserver::agent_explain::file({})", &fs_file_path);
+    let target = match (symbol_name.as_ref(), range.as_ref()) {
+        (Some(symbol_name), _) => format!("{} in {}", symbol_name, fs_file_path),
+        (None, Some(range)) => format!(
+            "{}:{}-{}",
+            fs_file_path,
+            range.start_line(),
+            range.end_line()
+        ),
+        (None, None) => fs_file_path.to_owned(),
+    };
+    let explanation_path = check_explanation_storage_path(app.config.clone(), &target).await;
+
+    let (sender, receiver) = tokio::sync::mpsc::unbounded_channel();
+
+    // cached explanations get replayed directly without going through the
+    // probing stack again
+    if let Ok(cached_reply) = tokio::fs::read_to_string(&explanation_path).await {
+        println!("webserver::agent_explain::cache_hit({})", &target);
+        let _ = sender.send(UIEventWithID::probing_started_event(request_id.to_owned()));
+        let _ = sender.send(UIEventWithID::probing_finished_event(
+            request_id.to_owned(),
+            cached_reply,
+        ));
+        drop(sender);
+        let cached_stream = tokio_stream::wrappers::UnboundedReceiverStream::new(receiver);
+        let answer_stream = cached_stream.map(|ui_event: UIEventWithID| {
+            sse::Event::default()
+                .json_data(ui_event)
+                .map_err(anyhow::Error::new)
+        });
+        return Ok(Sse::new(Box::pin(answer_stream)).into_response());
+    }
+
+    let query = format!(
+        r#"Explain {target}.
+Structure the answer into these sections:
+- Purpose: what this code exists for
+- Inputs and outputs: the parameters, return values and side effects
+- Key collaborators: the symbols this code works with, each one with its file path so the editor can link to it"#
+    );
+
+    // the probe runs over either the selection or the whole active file
+    let variable = match range.clone() {
+        Some(range) => {
+            let selection_content = file_content
+                .lines()
+                .skip(range.start_line())
+                .take(range.end_line() - range.start_line() + 1)
+                .collect::<Vec<_>>()
+                .join("\n");
+            VariableInformation::create_selection(
+                range,
+                fs_file_path.to_owned(),
+                symbol_name.clone().unwrap_or("selection".to_owned()),
+                selection_content,
+                language,
+            )
+        }
+        None => {
+            let full_range = Range::new(
+                Position::new(0, 0, 0),
+                Position::new(file_content.lines().count(), 0, file_content.len()),
+            );
+            VariableInformation::create_file(
+                full_range,
+                fs_file_path.to_owned(),
+                PathBuf::from(&fs_file_path)
+                    .file_name()
+                    .map(|name| name.to_string_lossy().to_string())
+                    .unwrap_or(fs_file_path.to_owned()),
+                file_content,
+                language,
+            )
+        }
+    };
+    let user_context = UserContext::new(vec![variable], vec![], None, vec![]);
+
+    let cancellation_token = tokio_util::sync::CancellationToken::new();
+    let message_properties = SymbolEventMessageProperties::new(
+        SymbolEventRequestId::new(request_id.to_owned(), request_id.to_owned()),
+        sender.clone(),
+        editor_url,
+        cancellation_token.clone(),
+        LLMProperties::new(
+            LLMType::ClaudeSonnet,
+            LLMProvider::CodeStory(CodeStoryLLMTypes::new()),
+            LLMProviderAPIKeys::CodeStory(CodestoryAccessToken::new(access_token.to_owned())),
+        ),
+    );
+
+    let symbol_manager = app.symbol_manager.clone();
+    let join_handle = tokio::spawn(async move {
+        let _ = symbol_manager
+            .probe_request_from_user_context(query, user_context, message_properties)
+            .await;
+    });
+    // register with the probe tracker so /probe_request_stop works on the
+    // explanation as well
+    {
+        let mut running_requests = app.probe_request_tracker.running_requests.lock().await;
+        running_requests.insert(
+            request_id.to_owned(),
+            (cancellation_token, Some(join_handle)),
+        );
+    }
+
+    let ui_event_stream = tokio_stream::wrappers::UnboundedReceiverStream::new(receiver);
+    let answer_stream = ui_event_stream.map(move |ui_event: UIEventWithID| {
+        // the final probe reply is what we cache, everything in between is
+        // progress the editor renders and forgets
+        if let Some(reply) = ui_event.probe_finished_reply() {
+            let explanation_path = explanation_path.to_owned();
+            let reply = reply.to_owned();
+            tokio::spawn(async move {
+                let _ = tokio::fs::write(explanation_path, reply).await;
+            });
+        }
+        sse::Event::default()
+            .json_data(ui_event)
+            .map_err(anyhow::Error::new)
+    });
+
+    Ok(Sse::new(Box::pin(answer_stream)).into_response())
+}
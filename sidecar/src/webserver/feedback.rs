@@ -0,0 +1,223 @@
+//! Persistence for user feedback on exchanges. The thumbs-up/down and any
+//! free-text the user gives us gets joined with the exchange trace (which
+//! tools ran, the resulting diff) and appended to a local jsonl log, with an
+//! aggregation endpoint on top so we can see which tools correlate with
+//! negative feedback.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use axum::response::IntoResponse;
+use axum::Extension;
+
+use super::types::{json as json_result, ApiResponse, Result};
+use crate::application::application::Application;
+
+/// A single feedback observation joined with the trace of the exchange it
+/// belongs to
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct FeedbackRecord {
+    session_id: String,
+    exchange_id: String,
+    accepted: bool,
+    /// free-text feedback when the editor sent any over
+    #[serde(default)]
+    feedback_text: Option<String>,
+    /// the tools which ran during the exchange, in order
+    #[serde(default)]
+    tools_used: Vec<String>,
+    /// the diff the exchange produced, when it made edits
+    #[serde(default)]
+    diff: Option<String>,
+    recorded_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl FeedbackRecord {
+    /// Builds the record by joining the feedback with the exchange trace
+    /// from the stored session file
+    pub async fn from_session_file(
+        storage_path: &str,
+        session_id: String,
+        exchange_id: String,
+        accepted: bool,
+        feedback_text: Option<String>,
+    ) -> Self {
+        let mut tools_used = vec![];
+        let mut diff = None;
+        if let Ok(contents) = tokio::fs::read_to_string(storage_path).await {
+            if let Ok(session) = serde_json::from_str::<serde_json::Value>(&contents) {
+                let exchanges = session
+                    .get("exchanges")
+                    .and_then(|exchanges| exchanges.as_array())
+                    .cloned()
+                    .unwrap_or_default();
+                for exchange in exchanges.iter() {
+                    let exchange_type = match exchange.get("exchange_type") {
+                        Some(exchange_type) => exchange_type,
+                        None => continue,
+                    };
+                    if let Some(tool_output) = exchange_type.get("ToolOutput") {
+                        if let Some(tool_type) =
+                            tool_output.get("tool_type").and_then(|tool| tool.as_str())
+                        {
+                            tools_used.push(tool_type.to_owned());
+                        }
+                    }
+                    let is_reply_to_exchange = exchange_type
+                        .get("AgentChat")
+                        .and_then(|agent_chat| agent_chat.get("parent_exchange_id"))
+                        .and_then(|parent| parent.as_str())
+                        == Some(exchange_id.as_str());
+                    if is_reply_to_exchange {
+                        diff = exchange_type
+                            .get("AgentChat")
+                            .and_then(|agent_chat| agent_chat.get("reply"))
+                            .and_then(|reply| reply.get("Edit"))
+                            .and_then(|edit| edit.get("edits_made_diff"))
+                            .and_then(|edit_diff| edit_diff.as_str())
+                            .map(|edit_diff| edit_diff.to_owned());
+                    }
+                }
+            }
+        }
+        Self {
+            session_id,
+            exchange_id,
+            accepted,
+            feedback_text,
+            tools_used,
+            diff,
+            recorded_at: chrono::Utc::now(),
+        }
+    }
+}
+
+/// Append-only store for the feedback records
+pub struct FeedbackStore {
+    file_path: PathBuf,
+}
+
+impl FeedbackStore {
+    pub fn new(file_path: PathBuf) -> Self {
+        Self { file_path }
+    }
+
+    pub async fn append(&self, record: FeedbackRecord) {
+        let serialised = match serde_json::to_string(&record) {
+            Ok(serialised) => serialised,
+            Err(_) => return,
+        };
+        use tokio::io::AsyncWriteExt;
+        let file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.file_path)
+            .await;
+        if let Ok(mut file) = file {
+            let _ = file.write_all(serialised.as_bytes()).await;
+            let _ = file.write_all(b"\n").await;
+        }
+    }
+
+    pub async fn load_all(&self) -> Vec<FeedbackRecord> {
+        let contents = match tokio::fs::read_to_string(&self.file_path).await {
+            Ok(contents) => contents,
+            Err(_) => return vec![],
+        };
+        contents
+            .lines()
+            .filter_map(|line| serde_json::from_str::<FeedbackRecord>(line).ok())
+            .collect()
+    }
+}
+
+/// Feedback counts for a single tool
+#[derive(Debug, Clone, Default, serde::Serialize)]
+pub struct ToolFeedbackCounts {
+    positive: usize,
+    negative: usize,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct FeedbackAggregateResponse {
+    total_records: usize,
+    accepted: usize,
+    rejected: usize,
+    /// per-tool counts, a tool gets credited with the feedback of every
+    /// exchange it ran in
+    per_tool: HashMap<String, ToolFeedbackCounts>,
+}
+
+impl ApiResponse for FeedbackAggregateResponse {}
+
+/// Aggregates the recorded feedback so we can see which tools show up in
+/// negatively rated exchanges
+pub fn aggregate_feedback(records: &[FeedbackRecord]) -> FeedbackAggregateResponse {
+    let mut per_tool: HashMap<String, ToolFeedbackCounts> = HashMap::new();
+    let mut accepted = 0;
+    let mut rejected = 0;
+    for record in records {
+        if record.accepted {
+            accepted = accepted + 1;
+        } else {
+            rejected = rejected + 1;
+        }
+        for tool in record.tools_used.iter() {
+            let counts = per_tool.entry(tool.to_owned()).or_default();
+            if record.accepted {
+                counts.positive = counts.positive + 1;
+            } else {
+                counts.negative = counts.negative + 1;
+            }
+        }
+    }
+    FeedbackAggregateResponse {
+        total_records: records.len(),
+        accepted,
+        rejected,
+        per_tool,
+    }
+}
+
+pub async fn feedback_aggregate(
+    Extension(app): Extension<Application>,
+) -> Result<impl IntoResponse> {
+    let records = app.feedback_store.load_all().await;
+    Ok(json_result(aggregate_feedback(&records)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{aggregate_feedback, FeedbackRecord};
+
+    fn record(accepted: bool, tools: Vec<&str>) -> FeedbackRecord {
+        FeedbackRecord {
+            session_id: "session".to_owned(),
+            exchange_id: "exchange".to_owned(),
+            accepted,
+            feedback_text: None,
+            tools_used: tools.into_iter().map(|tool| tool.to_owned()).collect(),
+            diff: None,
+            recorded_at: chrono::Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_aggregates_per_tool_counts() {
+        let records = vec![
+            record(true, vec!["CodeEditing"]),
+            record(false, vec!["CodeEditing", "TerminalCommand"]),
+            record(false, vec!["TerminalCommand"]),
+        ];
+        let aggregate = aggregate_feedback(&records);
+        assert_eq!(aggregate.total_records, 3);
+        assert_eq!(aggregate.accepted, 1);
+        assert_eq!(aggregate.rejected, 2);
+        assert_eq!(aggregate.per_tool.get("CodeEditing").unwrap().positive, 1);
+        assert_eq!(aggregate.per_tool.get("CodeEditing").unwrap().negative, 1);
+        assert_eq!(
+            aggregate.per_tool.get("TerminalCommand").unwrap().negative,
+            2
+        );
+    }
+}
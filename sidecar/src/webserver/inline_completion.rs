@@ -12,6 +12,7 @@ use crate::{
     chunking::text_document::{Position, Range},
     inline_completion::{
         multiline::detect_multiline::is_multiline_completion,
+        provider_selection::CompletionArmSnapshot,
         types::{FillInMiddleCompletionAgent, TypeIdentifier},
     },
 };
@@ -102,6 +103,7 @@ pub async fn inline_completion(
         app.fill_in_middle_broker.clone(),
         app.editor_parsing.clone(),
         symbol_tracker,
+        app.completion_provider_selector.clone(),
     );
     let completions = fill_in_middle_agent
         .completion(
@@ -137,6 +139,50 @@ pub async fn inline_completion(
     ))))
 }
 
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct InlineCompletionFeedbackRequest {
+    /// the id the completion request was served under
+    id: String,
+    accepted: bool,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct InlineCompletionFeedbackResponse {
+    /// false when the id does not belong to a routed request, feedback for
+    /// single-model configurations lands here and is fine to drop
+    recorded: bool,
+}
+
+impl ApiResponse for InlineCompletionFeedbackResponse {}
+
+/// The editor reports whether the user kept a completion, the bandit uses
+/// it to learn which provider to route the language to
+pub async fn inline_completion_feedback(
+    Extension(app): Extension<Application>,
+    Json(InlineCompletionFeedbackRequest { id, accepted }): Json<InlineCompletionFeedbackRequest>,
+) -> Result<impl IntoResponse> {
+    let recorded = app
+        .completion_provider_selector
+        .record_feedback(&id, accepted);
+    Ok(Json(InlineCompletionFeedbackResponse { recorded }))
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct InlineCompletionProviderStatsResponse {
+    arms: Vec<CompletionArmSnapshot>,
+}
+
+impl ApiResponse for InlineCompletionProviderStatsResponse {}
+
+/// Snapshot of what the routing has learnt per (language, model) arm
+pub async fn inline_completion_provider_stats(
+    Extension(app): Extension<Application>,
+) -> Result<impl IntoResponse> {
+    Ok(Json(InlineCompletionProviderStatsResponse {
+        arms: app.completion_provider_selector.arm_snapshot(),
+    }))
+}
+
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct CancelInlineCompletionRequest {
     id: String,
@@ -177,6 +223,10 @@ pub async fn inline_document_open(
     }): Json<InLineDocumentOpenRequest>,
 ) -> Result<impl IntoResponse> {
     let symbol_tracker = app.symbol_tracker.clone();
+    // opened documents also refresh the doc comment index
+    app.documentation_index
+        .index_file(&file_path, &file_content)
+        .await;
     symbol_tracker
         .add_document(file_path, file_content, language)
         .await;
@@ -233,6 +283,10 @@ pub async fn inline_completion_file_content_change(
             (range, event.text)
         })
         .collect::<Vec<_>>();
+    // edits also refresh the doc comment index for the file
+    app.documentation_index
+        .index_file(&file_path, &file_content)
+        .await;
     symbol_tracker
         .file_content_change(file_path, file_content, language, events)
         .await;
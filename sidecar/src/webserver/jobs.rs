@@ -0,0 +1,291 @@
+//! Background job queue for the long running operations which the webserver
+//! kicks off (indexing, repo-map generation, batch edits etc)
+//!
+//! Instead of running these inline and blocking the http request, the handlers
+//! register a job over here and return the job id to the editor, which can
+//! then poll GET /api/jobs/{id} for progress or cancel the job if the user
+//! changes their mind.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use axum::extract::Path;
+use axum::response::IntoResponse;
+use axum::Extension;
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+
+use crate::application::application::Application;
+use crate::repomap::tag::TagIndex;
+use crate::repomap::types::RepoMap;
+
+use super::types::json;
+use super::types::ApiResponse;
+use super::types::Result;
+
+/// The lifecycle states a background job can be in
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+/// A single background job which is being tracked, the join handle and the
+/// cancellation token are kept around so we can stop the work if the editor
+/// asks us to
+struct JobRecord {
+    /// what kind of work this job is doing, e.g. "indexing" or "repo_map"
+    kind: String,
+    status: JobStatus,
+    /// free form progress message which the job reports as it makes progress
+    progress_message: Option<String>,
+    /// progress in the range 0..=100 if the job can estimate it
+    progress_percentage: Option<u8>,
+    /// the output of the job once it completes, for jobs which produce one
+    /// (the repo map string for example), polled along with the status
+    result: Option<String>,
+    started_at: chrono::DateTime<chrono::Utc>,
+    cancellation_token: tokio_util::sync::CancellationToken,
+    join_handle: Option<JoinHandle<()>>,
+}
+
+/// Tracks background jobs in a concurrent environment, handed out as part
+/// of the application state so any handler can register long running work
+pub struct JobTracker {
+    jobs: Arc<Mutex<HashMap<String, JobRecord>>>,
+}
+
+impl JobTracker {
+    pub fn new() -> Self {
+        Self {
+            jobs: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Registers a new job and returns the id along with the cancellation
+    /// token which the spawned work should periodically check
+    pub async fn register(&self, kind: &str) -> (String, tokio_util::sync::CancellationToken) {
+        let job_id = uuid::Uuid::new_v4().to_string();
+        let cancellation_token = tokio_util::sync::CancellationToken::new();
+        let mut jobs = self.jobs.lock().await;
+        jobs.insert(
+            job_id.to_owned(),
+            JobRecord {
+                kind: kind.to_owned(),
+                status: JobStatus::Queued,
+                progress_message: None,
+                progress_percentage: None,
+                result: None,
+                started_at: chrono::Utc::now(),
+                cancellation_token: cancellation_token.clone(),
+                join_handle: None,
+            },
+        );
+        (job_id, cancellation_token)
+    }
+
+    /// Attaches the join handle for the spawned work and marks the job
+    /// as running
+    pub async fn attach_join_handle(&self, job_id: &str, join_handle: JoinHandle<()>) {
+        let mut jobs = self.jobs.lock().await;
+        if let Some(job) = jobs.get_mut(job_id) {
+            job.status = JobStatus::Running;
+            job.join_handle = Some(join_handle);
+        }
+    }
+
+    /// Records progress against a running job, both the message and the
+    /// percentage are optional since not every job can estimate them
+    pub async fn report_progress(
+        &self,
+        job_id: &str,
+        progress_message: Option<String>,
+        progress_percentage: Option<u8>,
+    ) {
+        let mut jobs = self.jobs.lock().await;
+        if let Some(job) = jobs.get_mut(job_id) {
+            if progress_message.is_some() {
+                job.progress_message = progress_message;
+            }
+            if progress_percentage.is_some() {
+                job.progress_percentage = progress_percentage.map(|value| value.min(100));
+            }
+        }
+    }
+
+    pub async fn mark_completed(&self, job_id: &str) {
+        self.transition(job_id, JobStatus::Completed).await;
+    }
+
+    /// Marks the job completed and stores its output so the editor can pick
+    /// it up from the status endpoint
+    pub async fn mark_completed_with_result(&self, job_id: &str, result: String) {
+        let mut jobs = self.jobs.lock().await;
+        if let Some(job) = jobs.get_mut(job_id) {
+            job.status = JobStatus::Completed;
+            job.result = Some(result);
+        }
+    }
+
+    pub async fn mark_failed(&self, job_id: &str, message: String) {
+        let mut jobs = self.jobs.lock().await;
+        if let Some(job) = jobs.get_mut(job_id) {
+            job.status = JobStatus::Failed;
+            job.progress_message = Some(message);
+        }
+    }
+
+    /// Cancels the job if its still running, both by cancelling the token
+    /// (for co-operative shutdown) and aborting the join handle
+    pub async fn cancel(&self, job_id: &str) -> bool {
+        let mut jobs = self.jobs.lock().await;
+        if let Some(job) = jobs.get_mut(job_id) {
+            if job.status == JobStatus::Queued || job.status == JobStatus::Running {
+                job.cancellation_token.cancel();
+                if let Some(join_handle) = &job.join_handle {
+                    join_handle.abort();
+                }
+                job.status = JobStatus::Cancelled;
+                return true;
+            }
+        }
+        false
+    }
+
+    pub async fn status(&self, job_id: &str) -> Option<JobStatusResponse> {
+        let jobs = self.jobs.lock().await;
+        jobs.get(job_id).map(|job| JobStatusResponse {
+            job_id: job_id.to_owned(),
+            kind: job.kind.to_owned(),
+            status: job.status.clone(),
+            progress_message: job.progress_message.clone(),
+            progress_percentage: job.progress_percentage,
+            result: job.result.clone(),
+            started_at: job.started_at.to_rfc3339(),
+        })
+    }
+
+    /// Number of jobs which are still queued or running, used by the debug
+    /// runtime endpoint
+    pub async fn active_job_count(&self) -> usize {
+        let jobs = self.jobs.lock().await;
+        jobs.values()
+            .filter(|job| job.status == JobStatus::Queued || job.status == JobStatus::Running)
+            .count()
+    }
+
+    async fn transition(&self, job_id: &str, status: JobStatus) {
+        let mut jobs = self.jobs.lock().await;
+        if let Some(job) = jobs.get_mut(job_id) {
+            job.status = status;
+        }
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct JobStatusResponse {
+    job_id: String,
+    kind: String,
+    status: JobStatus,
+    progress_message: Option<String>,
+    progress_percentage: Option<u8>,
+    result: Option<String>,
+    started_at: String,
+}
+
+impl ApiResponse for JobStatusResponse {}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct JobCancelResponse {
+    job_id: String,
+    cancelled: bool,
+}
+
+impl ApiResponse for JobCancelResponse {}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct RepoMapJobRequest {
+    root_directory: String,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct JobStartedResponse {
+    job_id: String,
+}
+
+impl ApiResponse for JobStartedResponse {}
+
+/// Kicks off a repo map build as a background job and hands back the job id,
+/// the editor polls the status endpoint for progress and grabs the generated
+/// map from the result field once the job completes
+pub async fn repo_map_generate(
+    Extension(app): Extension<Application>,
+    axum::Json(RepoMapJobRequest { root_directory }): axum::Json<RepoMapJobRequest>,
+) -> Result<impl IntoResponse> {
+    let (job_id, cancellation_token) = app.job_tracker.register("repo_map").await;
+    let job_tracker = app.job_tracker.clone();
+    let job_id_for_task = job_id.to_owned();
+    let join_handle = tokio::spawn(async move {
+        job_tracker
+            .report_progress(
+                &job_id_for_task,
+                Some("indexing tags".to_owned()),
+                Some(10),
+            )
+            .await;
+        let tag_index =
+            TagIndex::from_path(std::path::Path::new(&root_directory)).await;
+        // indexing is the long part, bail out here if the user cancelled
+        // while it was running
+        if cancellation_token.is_cancelled() {
+            return;
+        }
+        job_tracker
+            .report_progress(
+                &job_id_for_task,
+                Some("ranking symbols".to_owned()),
+                Some(60),
+            )
+            .await;
+        let repo_map = RepoMap::new().with_map_tokens(10_000);
+        match repo_map.get_repo_map(&tag_index).await {
+            Ok(repo_map_string) => {
+                job_tracker
+                    .mark_completed_with_result(&job_id_for_task, repo_map_string)
+                    .await;
+            }
+            Err(e) => {
+                job_tracker
+                    .mark_failed(&job_id_for_task, e.to_string())
+                    .await;
+            }
+        }
+    });
+    app.job_tracker
+        .attach_join_handle(&job_id, join_handle)
+        .await;
+    Ok(json(JobStartedResponse { job_id }))
+}
+
+pub async fn job_status(
+    Extension(app): Extension<Application>,
+    Path(job_id): Path<String>,
+) -> Result<impl IntoResponse> {
+    let status = app.job_tracker.status(&job_id).await;
+    match status {
+        Some(status) => Ok(json(status)),
+        None => Err(anyhow::anyhow!("no job found for id: {}", job_id).into()),
+    }
+}
+
+pub async fn job_cancel(
+    Extension(app): Extension<Application>,
+    Path(job_id): Path<String>,
+) -> Result<impl IntoResponse> {
+    let cancelled = app.job_tracker.cancel(&job_id).await;
+    Ok(json(JobCancelResponse { job_id, cancelled }))
+}
@@ -0,0 +1,436 @@
+//! Merge conflict resolution over the conflicted files of a workspace. We
+//! ask git which files are unmerged, parse the conflict markers into
+//! ours/base/theirs sections and run a focused resolution prompt per
+//! conflict. The editor drives the approval loop: the first call returns
+//! proposals, the follow-up with `apply` set writes the approved ones back
+//! and optionally runs a verification command so a broken resolution is
+//! caught immediately.
+
+use axum::response::IntoResponse;
+use axum::{Extension, Json};
+use futures::{stream, StreamExt};
+use llm_client::clients::types::{
+    GenerationProfile, LLMClientCompletionRequest, LLMClientMessage, LLMType,
+};
+use llm_client::provider::{
+    CodeStoryLLMTypes, CodestoryAccessToken, LLMProvider, LLMProviderAPIKeys,
+};
+use std::collections::HashMap;
+use std::path::Path;
+
+use super::model_selection::LLMClientConfig;
+use super::review::tag_value;
+use super::types::{json, ApiResponse, Result};
+use crate::agentic::symbol::identifier::LLMProperties;
+use crate::application::application::Application;
+
+/// resolving every conflict of a huge merge in one request gets slow, the
+/// editor can call again for the rest
+const MAX_CONFLICTS_TO_RESOLVE: usize = 20;
+
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct ResolveMergeConflictsRequest {
+    pub root_directory: String,
+    /// when false we only propose resolutions, the editor shows them to the
+    /// user and calls again with apply set for the approved ones
+    #[serde(default)]
+    pub apply: bool,
+    /// restrict application to these files, empty means all conflicted files
+    #[serde(default)]
+    pub approved_files: Vec<String>,
+    /// build or test command ran after applying, e.g. `cargo check`
+    #[serde(default)]
+    pub verify_command: Option<String>,
+    pub access_token: String,
+    pub model_configuration: LLMClientConfig,
+}
+
+/// One conflict parsed out of the markers, lines are 0-indexed into the
+/// conflicted file and cover the whole marker block
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct MergeConflict {
+    fs_file_path: String,
+    start_line: usize,
+    end_line: usize,
+    ours: String,
+    /// only present for diff3-style conflicts
+    base: Option<String>,
+    theirs: String,
+}
+
+impl MergeConflict {
+    pub(crate) fn fs_file_path(&self) -> &str {
+        &self.fs_file_path
+    }
+
+    pub(crate) fn start_line(&self) -> usize {
+        self.start_line
+    }
+
+    pub(crate) fn end_line(&self) -> usize {
+        self.end_line
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ConflictResolution {
+    pub fs_file_path: String,
+    /// 0-indexed lines of the marker block the resolution replaces
+    pub start_line: usize,
+    pub end_line: usize,
+    /// the merged code replacing the whole marker block
+    pub resolution: String,
+    /// why the model merged it this way, shown to the user for approval
+    pub thinking: Option<String>,
+    pub applied: bool,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct VerificationResult {
+    pub success: bool,
+    /// tail of the command output, enough to see what broke
+    pub output: String,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct ResolveMergeConflictsResponse {
+    resolutions: Vec<ConflictResolution>,
+    conflicts_found: usize,
+    verification: Option<VerificationResult>,
+}
+
+impl ApiResponse for ResolveMergeConflictsResponse {}
+
+/// Parses the conflict marker blocks out of a file, both plain and
+/// diff3-style (with a `|||||||` base section) markers parse
+pub(crate) fn parse_merge_conflicts(fs_file_path: &str, content: &str) -> Vec<MergeConflict> {
+    let mut conflicts = vec![];
+    let mut lines = content.lines().enumerate();
+    while let Some((start_line, line)) = lines.next() {
+        if !line.starts_with("<<<<<<<") {
+            continue;
+        }
+        let mut ours = vec![];
+        let mut base: Option<Vec<&str>> = None;
+        let mut theirs = vec![];
+        let mut section = Section::Ours;
+        let mut end_line = None;
+        for (line_number, line) in lines.by_ref() {
+            if line.starts_with("|||||||") {
+                base = Some(vec![]);
+                section = Section::Base;
+            } else if line.starts_with("=======") {
+                section = Section::Theirs;
+            } else if line.starts_with(">>>>>>>") {
+                end_line = Some(line_number);
+                break;
+            } else {
+                match section {
+                    Section::Ours => ours.push(line),
+                    Section::Base => base.get_or_insert_with(Vec::new).push(line),
+                    Section::Theirs => theirs.push(line),
+                }
+            }
+        }
+        // an unterminated marker block means the file is not actually in a
+        // conflicted state, skip it instead of guessing
+        let Some(end_line) = end_line else {
+            continue;
+        };
+        conflicts.push(MergeConflict {
+            fs_file_path: fs_file_path.to_owned(),
+            start_line,
+            end_line,
+            ours: ours.join("\n"),
+            base: base.map(|base| base.join("\n")),
+            theirs: theirs.join("\n"),
+        });
+    }
+    conflicts
+}
+
+enum Section {
+    Ours,
+    Base,
+    Theirs,
+}
+
+/// Replaces the marker blocks with their resolutions, bottom-up so the
+/// line numbers of the earlier blocks stay valid
+pub(crate) fn apply_resolutions_to_content(
+    content: &str,
+    resolutions: &[ConflictResolution],
+) -> String {
+    let mut lines = content.lines().map(String::from).collect::<Vec<_>>();
+    let mut ordered = resolutions.iter().collect::<Vec<_>>();
+    ordered.sort_by(|a, b| b.start_line.cmp(&a.start_line));
+    for resolution in ordered.into_iter() {
+        if resolution.end_line >= lines.len() || resolution.start_line > resolution.end_line {
+            continue;
+        }
+        let replacement = resolution
+            .resolution
+            .lines()
+            .map(String::from)
+            .collect::<Vec<_>>();
+        lines.splice(resolution.start_line..=resolution.end_line, replacement);
+    }
+    lines.join("\n")
+}
+
+/// The unmerged files as git sees them, relative to the root directory
+async fn conflicted_files(root_directory: &str) -> Result<Vec<String>> {
+    let output = tokio::process::Command::new("git")
+        .args(["diff", "--name-only", "--diff-filter=U"])
+        .current_dir(root_directory)
+        .output()
+        .await
+        .map_err(|e| anyhow::anyhow!(e))?;
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "git diff --diff-filter=U failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        )
+        .into());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(|line| line.to_owned())
+        .collect())
+}
+
+fn resolution_system_prompt() -> String {
+    r#"You are resolving a single git merge conflict. You get the ours side, the theirs side and when available the common base. Merge the intent of both sides, do not silently drop changes from either side unless they are superseded by the other.
+Reply in the following format and nothing else:
+<thinking>one or two sentences on how you merged the sides</thinking>
+<resolution>
+the merged code replacing the whole conflict block, without any conflict markers
+</resolution>"#
+        .to_owned()
+}
+
+fn resolution_user_message(conflict: &MergeConflict) -> String {
+    let mut message = format!("File: {}\n", conflict.fs_file_path());
+    message.push_str(&format!("<ours>\n{}\n</ours>\n", conflict.ours));
+    if let Some(base) = conflict.base.as_ref() {
+        message.push_str(&format!("<base>\n{}\n</base>\n", base));
+    }
+    message.push_str(&format!("<theirs>\n{}\n</theirs>", conflict.theirs));
+    message
+}
+
+/// Runs the resolution prompt for a single conflict, a malformed reply
+/// leaves the conflict unresolved instead of corrupting the file
+async fn resolve_conflict(
+    app: Application,
+    llm_properties: LLMProperties,
+    generation_profile: GenerationProfile,
+    conflict: MergeConflict,
+) -> Option<ConflictResolution> {
+    let completion_request = LLMClientCompletionRequest::from_messages_with_profile(
+        generation_profile,
+        vec![
+            LLMClientMessage::system(resolution_system_prompt()),
+            LLMClientMessage::user(resolution_user_message(&conflict)),
+        ],
+        llm_properties.llm().clone(),
+    );
+    let (sender, _receiver) = tokio::sync::mpsc::unbounded_channel();
+    let response = app
+        .llm_broker
+        .stream_completion(
+            llm_properties.api_key().clone(),
+            completion_request,
+            llm_properties.provider().clone(),
+            vec![("event_type".to_owned(), "resolve_merge_conflicts".to_owned())]
+                .into_iter()
+                .collect::<HashMap<_, _>>(),
+            sender,
+        )
+        .await
+        .ok()?;
+    let answer = response.answer_up_until_now().to_owned();
+    let resolution = tag_value(&answer, "resolution")?;
+    Some(ConflictResolution {
+        fs_file_path: conflict.fs_file_path().to_owned(),
+        start_line: conflict.start_line(),
+        end_line: conflict.end_line(),
+        resolution: resolution.to_owned(),
+        thinking: tag_value(&answer, "thinking").map(|thinking| thinking.to_owned()),
+        applied: false,
+    })
+}
+
+pub async fn resolve_merge_conflicts(
+    Extension(app): Extension<Application>,
+    Json(request): Json<ResolveMergeConflictsRequest>,
+) -> Result<impl IntoResponse> {
+    println!(
+        "webserver::resolve_merge_conflicts::root_directory({})::apply({})",
+        &request.root_directory, request.apply
+    );
+    let llm_properties = request
+        .model_configuration
+        .llm_properties_for_slow_model()
+        .unwrap_or(LLMProperties::new(
+            LLMType::ClaudeSonnet,
+            LLMProvider::CodeStory(CodeStoryLLMTypes::new()),
+            LLMProviderAPIKeys::CodeStory(CodestoryAccessToken::new(
+                request.access_token.to_owned(),
+            )),
+        ));
+    let generation_profile = request
+        .model_configuration
+        .generation_profile_or(GenerationProfile::Fast);
+
+    let mut conflicts = vec![];
+    for relative_path in conflicted_files(&request.root_directory).await? {
+        if !request.approved_files.is_empty() && !request.approved_files.contains(&relative_path) {
+            continue;
+        }
+        let fs_file_path = Path::new(&request.root_directory)
+            .join(&relative_path)
+            .to_string_lossy()
+            .to_string();
+        let Ok(content) = tokio::fs::read_to_string(&fs_file_path).await else {
+            continue;
+        };
+        conflicts.extend(parse_merge_conflicts(&fs_file_path, &content));
+    }
+    let conflicts_found = conflicts.len();
+    let conflicts = conflicts
+        .into_iter()
+        .take(MAX_CONFLICTS_TO_RESOLVE)
+        .collect::<Vec<_>>();
+
+    let mut resolutions = stream::iter(conflicts.into_iter().map(|conflict| {
+        (
+            app.clone(),
+            llm_properties.clone(),
+            generation_profile.clone(),
+            conflict,
+        )
+    }))
+    .map(|(app, llm_properties, generation_profile, conflict)| {
+        resolve_conflict(app, llm_properties, generation_profile, conflict)
+    })
+    .buffer_unordered(4)
+    .collect::<Vec<_>>()
+    .await
+    .into_iter()
+    .flatten()
+    .collect::<Vec<_>>();
+
+    let mut verification = None;
+    if request.apply {
+        let mut resolutions_by_file: HashMap<String, Vec<usize>> = HashMap::new();
+        for (index, resolution) in resolutions.iter().enumerate() {
+            resolutions_by_file
+                .entry(resolution.fs_file_path.to_owned())
+                .or_default()
+                .push(index);
+        }
+        for (fs_file_path, indices) in resolutions_by_file.into_iter() {
+            let Ok(content) = tokio::fs::read_to_string(&fs_file_path).await else {
+                continue;
+            };
+            let file_resolutions = indices
+                .iter()
+                .map(|index| resolutions[*index].clone())
+                .collect::<Vec<_>>();
+            let updated = apply_resolutions_to_content(&content, &file_resolutions);
+            if tokio::fs::write(&fs_file_path, updated).await.is_ok() {
+                for index in indices.into_iter() {
+                    resolutions[index].applied = true;
+                }
+            }
+        }
+        if let Some(verify_command) = request.verify_command.as_ref() {
+            let output = tokio::process::Command::new("sh")
+                .args(["-c", verify_command])
+                .current_dir(&request.root_directory)
+                .output()
+                .await
+                .map_err(|e| anyhow::anyhow!(e))?;
+            let mut combined = String::from_utf8_lossy(&output.stdout).to_string();
+            combined.push_str(&String::from_utf8_lossy(&output.stderr));
+            let tail_start = combined.len().saturating_sub(2000);
+            let tail_start = (tail_start..combined.len())
+                .find(|index| combined.is_char_boundary(*index))
+                .unwrap_or(combined.len());
+            verification = Some(VerificationResult {
+                success: output.status.success(),
+                output: combined[tail_start..].to_owned(),
+            });
+        }
+    }
+
+    Ok(json(ResolveMergeConflictsResponse {
+        resolutions,
+        conflicts_found,
+        verification,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{apply_resolutions_to_content, parse_merge_conflicts, ConflictResolution};
+
+    #[test]
+    fn test_parsing_plain_conflict_markers() {
+        let content = r#"fn main() {
+<<<<<<< HEAD
+    println!("ours");
+=======
+    println!("theirs");
+>>>>>>> feature
+}
+"#;
+        let conflicts = parse_merge_conflicts("src/main.rs", content);
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].start_line(), 1);
+        assert_eq!(conflicts[0].end_line(), 5);
+        assert_eq!(conflicts[0].ours, "    println!(\"ours\");");
+        assert_eq!(conflicts[0].theirs, "    println!(\"theirs\");");
+        assert!(conflicts[0].base.is_none());
+    }
+
+    #[test]
+    fn test_parsing_diff3_markers_captures_the_base() {
+        let content = r#"<<<<<<< HEAD
+ours
+||||||| merged common ancestors
+base
+=======
+theirs
+>>>>>>> feature
+"#;
+        let conflicts = parse_merge_conflicts("src/lib.rs", content);
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].base.as_deref(), Some("base"));
+    }
+
+    #[test]
+    fn test_unterminated_markers_parse_to_nothing() {
+        let content = "<<<<<<< HEAD\nours\n=======\ntheirs\n";
+        assert!(parse_merge_conflicts("src/lib.rs", content).is_empty());
+    }
+
+    #[test]
+    fn test_applying_resolutions_replaces_the_marker_block() {
+        let content = "fn main() {\n<<<<<<< HEAD\nours\n=======\ntheirs\n>>>>>>> feature\n}";
+        let resolution = ConflictResolution {
+            fs_file_path: "src/main.rs".to_owned(),
+            start_line: 1,
+            end_line: 5,
+            resolution: "merged".to_owned(),
+            thinking: None,
+            applied: false,
+        };
+        assert_eq!(
+            apply_resolutions_to_content(content, &[resolution]),
+            "fn main() {\nmerged\n}"
+        );
+    }
+}
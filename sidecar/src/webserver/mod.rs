@@ -1,14 +1,34 @@
 pub mod agent;
 pub mod agent_stream;
 pub mod agentic;
+pub mod changelog;
 pub mod config;
 pub mod context_trimming;
+pub mod context_upload;
+pub mod debug;
+pub mod doc_index;
+pub mod edit_proposals;
+pub mod explain;
+pub mod feedback;
 pub mod file_edit;
 pub mod health;
 pub mod in_line_agent;
 pub mod in_line_agent_stream;
 pub mod inline_completion;
+pub mod jobs;
+pub mod merge_conflicts;
 pub mod model_selection;
+pub mod pinned_context;
 pub(crate) mod plan;
+pub mod quick_edit;
+pub mod review;
+pub mod signature_change;
+pub mod slash_commands;
+pub mod stats;
+pub mod suggestions;
+pub mod tenancy;
+pub mod todos;
+pub mod tools;
 pub mod tree_sitter;
 pub mod types;
+pub mod version_negotiation;
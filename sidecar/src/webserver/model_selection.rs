@@ -1,12 +1,14 @@
 //! Contains the types for model selection which we want to use
 
+use axum::{response::IntoResponse, Json};
 use llm_client::{
-    clients::types::LLMType,
+    clients::types::{GenerationProfile, LLMType},
     provider::{AzureOpenAIDeploymentId, LLMProvider, LLMProviderAPIKeys},
 };
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+use super::types::{json, ApiResponse};
 use crate::agentic::symbol::identifier::LLMProperties;
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -15,6 +17,10 @@ pub struct LLMClientConfig {
     pub fast_model: LLMType,
     pub models: HashMap<LLMType, Model>,
     pub providers: Vec<LLMProviderAPIKeys>,
+    /// named generation profile which the request wants us to use, older
+    /// editors do not send this over so we also leave it out when its unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub generation_profile: Option<GenerationProfile>,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -26,6 +32,12 @@ pub struct LLMClientLoggingConfig {
 }
 
 impl LLMClientConfig {
+    /// The generation profile for this request, callers pass the profile
+    /// which fits the operation when the request did not pin one
+    pub fn generation_profile_or(&self, fallback: GenerationProfile) -> GenerationProfile {
+        self.generation_profile.unwrap_or(fallback)
+    }
+
     /// Grabs the llm provider which is given by the user input
     pub fn llm_properties_for_slow_model(&self) -> Option<LLMProperties> {
         let model = self.models.get(&self.slow_model);
@@ -71,6 +83,19 @@ impl LLMClientConfig {
         self.providers.iter().find(|p| p.key(provider).is_some())
     }
 
+    /// Same lookup as provider_for_fast_model but for an arbitrary model,
+    /// used when the completion routing picks something other than the
+    /// configured fast model
+    pub fn provider_for_model(&self, model: &LLMType) -> Option<&LLMProviderAPIKeys> {
+        let model = self.models.get(model);
+        if let None = model {
+            return None;
+        }
+        let model = model.expect("is_none above to hold");
+        let provider = &model.provider;
+        self.providers.iter().find(|p| p.key(provider).is_some())
+    }
+
     pub fn fast_model_temperature(&self) -> Option<f32> {
         self.models
             .get(&self.fast_model)
@@ -122,6 +147,86 @@ pub struct Model {
     pub provider: LLMProvider,
 }
 
+/// How a single azure-routed model resolved, which deployment and api
+/// version its requests will hit
+#[derive(Debug, Serialize)]
+pub struct AzureDeploymentResolution {
+    model: LLMType,
+    deployment_id: String,
+    api_version: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AzureDeploymentValidationResponse {
+    valid: bool,
+    resolutions: Vec<AzureDeploymentResolution>,
+    errors: Vec<String>,
+}
+
+impl ApiResponse for AzureDeploymentValidationResponse {}
+
+/// Validates the azure deployment mapping in a model configuration, for every
+/// model routed through azure we report the deployment and api version it
+/// resolves to so enterprises can sanity-check their per-model mapping before
+/// traffic hits it
+pub async fn validate_azure_deployments(
+    Json(config): Json<LLMClientConfig>,
+) -> impl IntoResponse {
+    let azure_config = config.providers.iter().find_map(|provider| {
+        if let LLMProviderAPIKeys::OpenAIAzureConfig(azure_config) = provider {
+            Some(azure_config)
+        } else {
+            None
+        }
+    });
+    let mut resolutions = vec![];
+    let mut errors = vec![];
+    let mut azure_models = config
+        .models
+        .iter()
+        .filter_map(|(model, model_config)| {
+            if let LLMProvider::Azure(deployment_id) = &model_config.provider {
+                Some((model, deployment_id))
+            } else {
+                None
+            }
+        })
+        .collect::<Vec<_>>();
+    // deterministic output no matter how the models hashmap iterates
+    azure_models.sort_by_key(|(model, _)| model.to_string());
+    for (model, provider_deployment) in azure_models {
+        let Some(azure_config) = azure_config else {
+            errors.push(format!(
+                "{} is routed through azure but no OpenAIAzureConfig provider key is configured",
+                model
+            ));
+            continue;
+        };
+        // a deployment pinned on the model's provider wins, then the
+        // per-model mapping, then the top level deployment on the config
+        let deployment_id = if !provider_deployment.deployment_id.is_empty() {
+            provider_deployment.deployment_id.to_owned()
+        } else {
+            azure_config.deployment_id_for(model)
+        };
+        if deployment_id.is_empty() {
+            errors.push(format!("{} has no azure deployment configured", model));
+            continue;
+        }
+        resolutions.push(AzureDeploymentResolution {
+            model: model.clone(),
+            deployment_id,
+            api_version: azure_config.api_version_for(model),
+        });
+    }
+    let valid = errors.is_empty();
+    json(AzureDeploymentValidationResponse {
+        valid,
+        resolutions,
+        errors,
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use llm_client::provider::{
@@ -177,12 +282,14 @@ mod tests {
             )]
             .into_iter()
             .collect(),
+            generation_profile: None,
             providers: vec![
                 LLMProviderAPIKeys::OpenAIAzureConfig(AzureConfig {
                     deployment_id: "gpt35-turbo-access".to_owned(),
                     api_base: "https://codestory-gpt4.openai.azure.com".to_owned(),
                     api_key: "89ca8a49a33344c9b794b3dabcbbc5d0".to_owned(),
                     api_version: "v1".to_owned(),
+                    deployments: Default::default(),
                 }),
                 LLMProviderAPIKeys::Ollama(OllamaProvider {}),
             ],
@@ -0,0 +1,170 @@
+//! Server side storage for pinned context items
+//!
+//! Editors can pin files, symbols or docs against a session instead of
+//! resending the full context on every request. Prompt construction merges
+//! the pinned items into the incoming UserContext, with a per item budget so
+//! a pinned 10k line file does not blow up the prompt.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use axum::extract::Path;
+use axum::response::IntoResponse;
+use axum::{Extension, Json};
+use tokio::sync::Mutex;
+
+use crate::application::application::Application;
+use crate::chunking::text_document::{Position, Range};
+use crate::user_context::types::VariableInformation;
+
+use super::types::json;
+use super::types::ApiResponse;
+use super::types::Result;
+
+/// Upper bound on the content we inline for a single pinned item, anything
+/// bigger gets truncated with a marker so the LLM knows there is more
+const PINNED_ITEM_CONTENT_BUDGET: usize = 20_000;
+
+/// A single pinned context item for a session
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct PinnedContextItem {
+    /// path on the filesystem which this pin points at
+    pub fs_file_path: String,
+    /// display name for the pin, e.g. the symbol name or the file name
+    pub name: String,
+    /// what kind of item this is: "file", "symbol" or "doc"
+    pub item_type: String,
+    /// inline content for the pin, if missing we read the file from disk
+    /// when constructing the prompt
+    pub content: Option<String>,
+}
+
+/// Tracks the pinned context items per session, shared across the webserver
+/// handlers through the application state
+pub struct PinnedContextTracker {
+    pinned_items: Arc<Mutex<HashMap<String, Vec<PinnedContextItem>>>>,
+}
+
+impl PinnedContextTracker {
+    pub fn new() -> Self {
+        Self {
+            pinned_items: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    pub async fn pin(&self, session_id: &str, item: PinnedContextItem) {
+        let mut pinned_items = self.pinned_items.lock().await;
+        let session_items = pinned_items.entry(session_id.to_owned()).or_default();
+        // re-pinning the same path replaces the existing entry instead of
+        // duplicating it
+        session_items.retain(|existing| existing.fs_file_path != item.fs_file_path);
+        session_items.push(item);
+    }
+
+    pub async fn unpin(&self, session_id: &str, fs_file_path: &str) -> bool {
+        let mut pinned_items = self.pinned_items.lock().await;
+        if let Some(session_items) = pinned_items.get_mut(session_id) {
+            let before = session_items.len();
+            session_items.retain(|existing| existing.fs_file_path != fs_file_path);
+            return session_items.len() != before;
+        }
+        false
+    }
+
+    pub async fn list(&self, session_id: &str) -> Vec<PinnedContextItem> {
+        let pinned_items = self.pinned_items.lock().await;
+        pinned_items.get(session_id).cloned().unwrap_or_default()
+    }
+
+    /// Converts the pinned items for a session into user context variables
+    /// so prompt construction can include them, content which was not pinned
+    /// inline gets read from disk and everything is clamped to a budget
+    pub async fn to_variables(&self, session_id: &str) -> Vec<VariableInformation> {
+        let items = self.list(session_id).await;
+        let mut variables = vec![];
+        for item in items.into_iter() {
+            let content = match item.content {
+                Some(content) => content,
+                None => match tokio::fs::read_to_string(&item.fs_file_path).await {
+                    Ok(content) => content,
+                    Err(_) => continue,
+                },
+            };
+            let content = clamp_to_budget(content);
+            variables.push(VariableInformation::create_file(
+                Range::new(Position::new(0, 0, 0), Position::new(0, 0, 0)),
+                item.fs_file_path,
+                item.name,
+                content,
+                "".to_owned(),
+            ));
+        }
+        variables
+    }
+}
+
+fn clamp_to_budget(content: String) -> String {
+    if content.len() <= PINNED_ITEM_CONTENT_BUDGET {
+        return content;
+    }
+    let mut clamped: String = content.chars().take(PINNED_ITEM_CONTENT_BUDGET).collect();
+    clamped.push_str("\n... (pinned content truncated, read the file for the rest)");
+    clamped
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct PinContextRequest {
+    session_id: String,
+    #[serde(flatten)]
+    item: PinnedContextItem,
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct UnpinContextRequest {
+    session_id: String,
+    fs_file_path: String,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct PinContextResponse {
+    done: bool,
+}
+
+impl ApiResponse for PinContextResponse {}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ListPinnedContextResponse {
+    pinned_items: Vec<PinnedContextItem>,
+}
+
+impl ApiResponse for ListPinnedContextResponse {}
+
+pub async fn pin_context(
+    Extension(app): Extension<Application>,
+    Json(PinContextRequest { session_id, item }): Json<PinContextRequest>,
+) -> Result<impl IntoResponse> {
+    app.pinned_context_tracker.pin(&session_id, item).await;
+    Ok(json(PinContextResponse { done: true }))
+}
+
+pub async fn unpin_context(
+    Extension(app): Extension<Application>,
+    Json(UnpinContextRequest {
+        session_id,
+        fs_file_path,
+    }): Json<UnpinContextRequest>,
+) -> Result<impl IntoResponse> {
+    let done = app
+        .pinned_context_tracker
+        .unpin(&session_id, &fs_file_path)
+        .await;
+    Ok(json(PinContextResponse { done }))
+}
+
+pub async fn list_pinned_context(
+    Extension(app): Extension<Application>,
+    Path(session_id): Path<String>,
+) -> Result<impl IntoResponse> {
+    let pinned_items = app.pinned_context_tracker.list(&session_id).await;
+    Ok(json(ListPinnedContextResponse { pinned_items }))
+}
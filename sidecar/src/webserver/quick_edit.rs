@@ -0,0 +1,171 @@
+//! Low latency fast path for anchored edits where the user has a selection
+//! and a short instruction ("fix this line"). We skip planning, probing and
+//! followups completely and do a single code-edit LLM call with minimal
+//! context, streaming the rewritten selection back to the editor as it is
+//! generated.
+
+use axum::response::{sse, IntoResponse, Sse};
+use axum::{Extension, Json};
+use futures::StreamExt;
+use llm_client::clients::types::{
+    GenerationProfile, LLMClientCompletionRequest, LLMClientMessage, LLMType,
+};
+use llm_client::provider::{
+    CodeStoryLLMTypes, CodestoryAccessToken, LLMProvider, LLMProviderAPIKeys,
+};
+use serde_json::json;
+use std::collections::HashMap;
+
+use super::model_selection::LLMClientConfig;
+use super::types::Result;
+use crate::agentic::symbol::identifier::LLMProperties;
+use crate::application::application::Application;
+use crate::chunking::text_document::Range;
+
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct QuickEditRequest {
+    pub session_id: String,
+    pub exchange_id: String,
+    pub fs_file_path: String,
+    pub range: Range,
+    /// content of the selection which is getting edited
+    pub selection: String,
+    /// content of the file around the selection, kept intentionally small so
+    /// the request stays fast
+    #[serde(default)]
+    pub surrounding_context: Option<String>,
+    pub instruction: String,
+    #[serde(default)]
+    pub language: Option<String>,
+    pub access_token: String,
+    pub model_configuration: LLMClientConfig,
+}
+
+fn quick_edit_system_prompt(language: &str) -> String {
+    format!(
+        r#"You are an expert software engineer doing a quick targeted edit in {language}.
+You are given a code selection and an instruction, rewrite ONLY the selection following the instruction.
+- Output the rewritten selection and nothing else, no explanations and no markdown fences.
+- Preserve the indentation of the original selection since it gets swapped in place.
+- Do not make changes outside the scope of the instruction."#
+    )
+}
+
+fn quick_edit_user_message(request: &QuickEditRequest) -> String {
+    let mut message = format!(
+        r#"File: {}
+"#,
+        request.fs_file_path
+    );
+    if let Some(surrounding_context) = request.surrounding_context.as_ref() {
+        message.push_str(&format!(
+            r#"Surrounding code for context:
+```
+{}
+```
+"#,
+            surrounding_context
+        ));
+    }
+    message.push_str(&format!(
+        r#"Selection to edit:
+```
+{}
+```
+Instruction: {}"#,
+        request.selection, request.instruction
+    ));
+    message
+}
+
+pub async fn quick_edit(
+    Extension(app): Extension<Application>,
+    Json(request): Json<QuickEditRequest>,
+) -> Result<impl IntoResponse> {
+    println!(
+        "webserver::quick_edit::session_id({})::file({})",
+        &request.session_id, &request.fs_file_path
+    );
+    let llm_properties = request
+        .model_configuration
+        .llm_properties_for_slow_model()
+        .unwrap_or(LLMProperties::new(
+            LLMType::ClaudeSonnet,
+            LLMProvider::CodeStory(CodeStoryLLMTypes::new()),
+            LLMProviderAPIKeys::CodeStory(CodestoryAccessToken::new(
+                request.access_token.to_owned(),
+            )),
+        ));
+    let language = request.language.clone().unwrap_or("code".to_owned());
+    // edits get the deterministic profile unless the request pins one
+    let generation_profile = request
+        .model_configuration
+        .generation_profile_or(GenerationProfile::Deterministic);
+    let completion_request = LLMClientCompletionRequest::from_messages_with_profile(
+        generation_profile,
+        vec![
+            LLMClientMessage::system(quick_edit_system_prompt(&language)),
+            LLMClientMessage::user(quick_edit_user_message(&request)),
+        ],
+        llm_properties.llm().clone(),
+    );
+
+    let (sender, receiver) = tokio::sync::mpsc::unbounded_channel();
+    let llm_broker = app.llm_broker.clone();
+    let session_id = request.session_id.to_owned();
+    let exchange_id = request.exchange_id.to_owned();
+    let _ = tokio::spawn(async move {
+        let _ = llm_broker
+            .stream_completion(
+                llm_properties.api_key().clone(),
+                completion_request,
+                llm_properties.provider().clone(),
+                vec![
+                    ("event_type".to_owned(), "quick_edit".to_owned()),
+                    ("root_id".to_owned(), session_id),
+                ]
+                .into_iter()
+                .collect::<HashMap<_, _>>(),
+                sender,
+            )
+            .await;
+    });
+
+    let cloned_session_id = request.session_id.to_owned();
+    let init_stream = futures::stream::once(async move {
+        Ok(sse::Event::default()
+            .json_data(json!({
+                "session_id": cloned_session_id,
+                "started": true,
+            }))
+            // This should never happen, so we force an unwrap.
+            .expect("failed to serialize initialization object"))
+    });
+
+    let cloned_session_id = request.session_id.to_owned();
+    let cloned_exchange_id = exchange_id.to_owned();
+    let edit_stream = tokio_stream::wrappers::UnboundedReceiverStream::new(receiver).map(
+        move |completion_response| {
+            sse::Event::default()
+                .json_data(json!({
+                    "session_id": cloned_session_id.to_owned(),
+                    "exchange_id": cloned_exchange_id.to_owned(),
+                    "answer_up_until_now": completion_response.answer_up_until_now(),
+                    "delta": completion_response.delta(),
+                }))
+                .map_err(anyhow::Error::new)
+        },
+    );
+
+    let done_stream = futures::stream::once(async move {
+        Ok(sse::Event::default()
+            .json_data(json!({
+                "done": "[CODESTORY_DONE]".to_owned(),
+                "session_id": request.session_id.to_owned(),
+            }))
+            .expect("failed to send done object"))
+    });
+
+    let stream = init_stream.chain(edit_stream).chain(done_stream);
+    Ok(Sse::new(Box::pin(stream)))
+}
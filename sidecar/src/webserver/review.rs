@@ -0,0 +1,532 @@
+//! Code review mode over an arbitrary diff. The editor sends either a unified
+//! diff directly or a git ref range which we resolve locally, we split it into
+//! per-file hunks, map each hunk to the symbol it lands in using the outline
+//! from tree-sitter and run a focused review prompt per hunk. Diagnostics from
+//! the editor (lint + type errors) are folded in when an editor url is passed
+//! along, so the review covers both the LLM feedback and the hard failures.
+
+use axum::response::IntoResponse;
+use axum::{Extension, Json};
+use futures::{stream, StreamExt};
+use llm_client::clients::types::{
+    GenerationProfile, LLMClientCompletionRequest, LLMClientMessage, LLMType,
+};
+use llm_client::provider::{
+    CodeStoryLLMTypes, CodestoryAccessToken, LLMProvider, LLMProviderAPIKeys,
+};
+use std::collections::HashMap;
+use std::path::Path;
+
+use super::model_selection::LLMClientConfig;
+use super::types::{json, ApiResponse, Result};
+use crate::agentic::symbol::events::input::SymbolEventRequestId;
+use crate::agentic::symbol::events::message_event::SymbolEventMessageProperties;
+use crate::agentic::symbol::identifier::LLMProperties;
+use crate::application::application::Application;
+
+/// Reviewing every hunk of a huge diff is slow and mostly noise, so we cap
+/// how many hunks get their own review prompt.
+const MAX_REVIEW_HUNKS: usize = 25;
+
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct ReviewDiffRequest {
+    pub root_directory: String,
+    /// unified diff to review, if missing we resolve `ref_range` against the
+    /// repository at `root_directory`
+    #[serde(default)]
+    pub diff: Option<String>,
+    /// a git revision range understood by `git diff` (e.g. `main...HEAD`)
+    #[serde(default)]
+    pub ref_range: Option<String>,
+    /// when present we also pull lint and type diagnostics for the changed
+    /// files from the editor
+    #[serde(default)]
+    pub editor_url: Option<String>,
+    pub access_token: String,
+    pub model_configuration: LLMClientConfig,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReviewSeverity {
+    /// must be fixed before merging (bugs, broken builds, type errors)
+    Blocker,
+    /// worth fixing but not blocking
+    Warning,
+    /// style or polish level feedback
+    Nit,
+}
+
+impl ReviewSeverity {
+    fn parse(value: &str) -> Self {
+        match value.trim().to_lowercase().as_str() {
+            "blocker" => ReviewSeverity::Blocker,
+            "warning" => ReviewSeverity::Warning,
+            _ => ReviewSeverity::Nit,
+        }
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ReviewComment {
+    pub fs_file_path: String,
+    /// 1-indexed line in the new version of the file, when the model gave one
+    pub line: Option<usize>,
+    pub severity: ReviewSeverity,
+    pub message: String,
+    /// replacement code for the flagged lines, when the model suggested one
+    pub suggested_patch: Option<String>,
+    /// symbol the hunk belongs to as per the outline, when we found one
+    pub symbol: Option<String>,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct ReviewDiffResponse {
+    comments: Vec<ReviewComment>,
+    reviewed_hunks: usize,
+    skipped_hunks: usize,
+}
+
+impl ApiResponse for ReviewDiffResponse {}
+
+/// A single hunk from a unified diff, tracked against the new version of
+/// the file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct DiffHunk {
+    fs_file_path: String,
+    /// 1-indexed start line in the new version of the file
+    new_start_line: usize,
+    /// the hunk body including the leading ` `/`+`/`-` markers
+    content: String,
+}
+
+impl DiffHunk {
+    pub(crate) fn fs_file_path(&self) -> &str {
+        &self.fs_file_path
+    }
+
+    pub(crate) fn new_start_line(&self) -> usize {
+        self.new_start_line
+    }
+
+    /// number of lines the hunk covers in the new version of the file
+    pub(crate) fn new_line_count(&self) -> usize {
+        self.content
+            .lines()
+            .filter(|line| !line.starts_with('-'))
+            .count()
+    }
+}
+
+/// Splits a unified diff into per-file hunks. Files which are deleted in the
+/// diff have nothing to review on the new side so their hunks are kept but
+/// point at the old path.
+pub(crate) fn parse_unified_diff(diff: &str) -> Vec<DiffHunk> {
+    let mut hunks = vec![];
+    let mut current_file: Option<String> = None;
+    let mut current_hunk: Option<DiffHunk> = None;
+    for line in diff.lines() {
+        if line.starts_with("+++ ") {
+            if let Some(hunk) = current_hunk.take() {
+                hunks.push(hunk);
+            }
+            let path = line["+++ ".len()..].trim();
+            let path = path.strip_prefix("b/").unwrap_or(path);
+            if path == "/dev/null" {
+                current_file = None;
+            } else {
+                current_file = Some(path.to_owned());
+            }
+        } else if line.starts_with("--- ") || line.starts_with("diff --git")
+            || line.starts_with("index ")
+        {
+            if let Some(hunk) = current_hunk.take() {
+                hunks.push(hunk);
+            }
+        } else if line.starts_with("@@") {
+            if let Some(hunk) = current_hunk.take() {
+                hunks.push(hunk);
+            }
+            if let (Some(fs_file_path), Some(new_start_line)) =
+                (current_file.clone(), parse_hunk_new_start_line(line))
+            {
+                current_hunk = Some(DiffHunk {
+                    fs_file_path,
+                    new_start_line,
+                    content: String::new(),
+                });
+            }
+        } else if let Some(hunk) = current_hunk.as_mut() {
+            if !hunk.content.is_empty() {
+                hunk.content.push('\n');
+            }
+            hunk.content.push_str(line);
+        }
+    }
+    if let Some(hunk) = current_hunk.take() {
+        hunks.push(hunk);
+    }
+    hunks
+}
+
+/// Pulls the new-side start line out of a `@@ -a,b +c,d @@` header.
+fn parse_hunk_new_start_line(header: &str) -> Option<usize> {
+    let new_side = header
+        .split_whitespace()
+        .find(|part| part.starts_with('+'))?;
+    new_side[1..]
+        .split(',')
+        .next()
+        .and_then(|start| start.parse::<usize>().ok())
+}
+
+fn review_system_prompt() -> String {
+    r#"You are a senior engineer reviewing a single hunk from a diff. Flag real problems: bugs, broken invariants, missed edge cases, security issues and misleading names. Do not praise the code and do not restate what the diff does.
+Reply with zero or more comments in the following format and nothing else:
+<comment>
+<severity>blocker|warning|nit</severity>
+<line>line number in the new file, or omit this tag</line>
+<message>what is wrong and why it matters</message>
+<suggested_patch>replacement code for the flagged lines, or omit this tag</suggested_patch>
+</comment>
+If the hunk looks fine reply with an empty string."#
+        .to_owned()
+}
+
+fn review_user_message(hunk: &DiffHunk, symbol: Option<&str>) -> String {
+    let mut message = format!("File: {}\n", hunk.fs_file_path());
+    if let Some(symbol) = symbol {
+        message.push_str(&format!("Enclosing symbol: {}\n", symbol));
+    }
+    message.push_str(&format!(
+        "Hunk starting at line {} of the new file:\n```\n{}\n```",
+        hunk.new_start_line(),
+        hunk.content
+    ));
+    message
+}
+
+/// Grabs the contents of the first `<tag>...</tag>` pair inside the block.
+pub(crate) fn tag_value<'a>(block: &'a str, tag: &str) -> Option<&'a str> {
+    let open = format!("<{}>", tag);
+    let close = format!("</{}>", tag);
+    let start = block.find(&open)? + open.len();
+    let end = block[start..].find(&close)? + start;
+    Some(block[start..end].trim())
+}
+
+/// Parses the model reply for a single hunk into structured comments. The
+/// parsing is intentionally forgiving: comments missing a severity default to
+/// a nit and malformed blocks are skipped instead of failing the review.
+pub(crate) fn parse_review_comments(
+    response: &str,
+    fs_file_path: &str,
+    symbol: Option<&str>,
+) -> Vec<ReviewComment> {
+    let mut comments = vec![];
+    let mut remaining = response;
+    while let Some(start) = remaining.find("<comment>") {
+        let after_open = &remaining[start + "<comment>".len()..];
+        let Some(end) = after_open.find("</comment>") else {
+            break;
+        };
+        let block = &after_open[..end];
+        remaining = &after_open[end + "</comment>".len()..];
+        let Some(message) = tag_value(block, "message") else {
+            continue;
+        };
+        if message.is_empty() {
+            continue;
+        }
+        comments.push(ReviewComment {
+            fs_file_path: fs_file_path.to_owned(),
+            line: tag_value(block, "line").and_then(|line| line.parse::<usize>().ok()),
+            severity: tag_value(block, "severity")
+                .map(ReviewSeverity::parse)
+                .unwrap_or(ReviewSeverity::Nit),
+            message: message.to_owned(),
+            suggested_patch: tag_value(block, "suggested_patch")
+                .filter(|patch| !patch.is_empty())
+                .map(|patch| patch.to_owned()),
+            symbol: symbol.map(|symbol| symbol.to_owned()),
+        });
+    }
+    comments
+}
+
+/// Finds the name of the outline node which contains the hunk, reading the
+/// file from disk since the diff only carries partial context.
+async fn symbol_for_hunk(app: &Application, root_directory: &str, hunk: &DiffHunk) -> Option<String> {
+    let fs_file_path = Path::new(root_directory)
+        .join(hunk.fs_file_path())
+        .to_string_lossy()
+        .to_string();
+    let language_config = app.editor_parsing.for_file_path(&fs_file_path)?;
+    let contents = tokio::fs::read_to_string(&fs_file_path).await.ok()?;
+    // diff lines are 1-indexed while the outline ranges are 0-indexed
+    let hunk_start = hunk.new_start_line().saturating_sub(1);
+    let hunk_end = hunk_start + hunk.new_line_count().saturating_sub(1);
+    language_config
+        .generate_outline_fresh(contents.as_bytes(), &fs_file_path)
+        .into_iter()
+        .find(|outline_node| {
+            outline_node.range().start_line() <= hunk_start
+                && hunk_end <= outline_node.range().end_line()
+        })
+        .map(|outline_node| outline_node.name().to_owned())
+}
+
+/// Resolves a git ref range to a unified diff by shelling out to git in the
+/// root directory.
+pub(crate) async fn diff_for_ref_range(root_directory: &str, ref_range: &str) -> Result<String> {
+    let output = tokio::process::Command::new("git")
+        .arg("diff")
+        .arg(ref_range)
+        .current_dir(root_directory)
+        .output()
+        .await
+        .map_err(|e| anyhow::anyhow!(e))?;
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "git diff {} failed: {}",
+            ref_range,
+            String::from_utf8_lossy(&output.stderr)
+        )
+        .into());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Runs the review prompt for a single hunk and parses the reply.
+async fn review_hunk(
+    app: Application,
+    llm_properties: LLMProperties,
+    generation_profile: GenerationProfile,
+    hunk: DiffHunk,
+    symbol: Option<String>,
+) -> Vec<ReviewComment> {
+    let completion_request = LLMClientCompletionRequest::from_messages_with_profile(
+        generation_profile,
+        vec![
+            LLMClientMessage::system(review_system_prompt()),
+            LLMClientMessage::user(review_user_message(&hunk, symbol.as_deref())),
+        ],
+        llm_properties.llm().clone(),
+    );
+    let (sender, _receiver) = tokio::sync::mpsc::unbounded_channel();
+    let response = app
+        .llm_broker
+        .stream_completion(
+            llm_properties.api_key().clone(),
+            completion_request,
+            llm_properties.provider().clone(),
+            vec![("event_type".to_owned(), "review_diff".to_owned())]
+                .into_iter()
+                .collect::<HashMap<_, _>>(),
+            sender,
+        )
+        .await;
+    match response {
+        Ok(response) => parse_review_comments(
+            response.answer_up_until_now(),
+            hunk.fs_file_path(),
+            symbol.as_deref(),
+        ),
+        Err(_) => vec![],
+    }
+}
+
+/// Pulls the editor diagnostics for the changed files and surfaces them as
+/// blocker comments, these are the lint and type check failures which the
+/// review should never miss.
+async fn diagnostics_comments(
+    app: &Application,
+    editor_url: &str,
+    llm_properties: LLMProperties,
+    file_paths: Vec<String>,
+) -> Vec<ReviewComment> {
+    let (sender, _receiver) = tokio::sync::mpsc::unbounded_channel();
+    let message_properties = SymbolEventMessageProperties::new(
+        SymbolEventRequestId::new("review_diff".to_owned(), "review_diff".to_owned()),
+        sender,
+        editor_url.to_owned(),
+        tokio_util::sync::CancellationToken::new(),
+        llm_properties,
+    );
+    let mut comments = vec![];
+    for fs_file_path in file_paths.into_iter() {
+        let diagnostics = app
+            .tool_box
+            .get_file_diagnostics(&fs_file_path, message_properties.clone(), false)
+            .await;
+        if let Ok(diagnostics) = diagnostics {
+            comments.extend(diagnostics.remove_diagnostics().into_iter().map(
+                |diagnostic| ReviewComment {
+                    fs_file_path: fs_file_path.to_owned(),
+                    line: Some(diagnostic.range().start_line() + 1),
+                    severity: ReviewSeverity::Blocker,
+                    message: diagnostic.message().to_owned(),
+                    suggested_patch: None,
+                    symbol: None,
+                },
+            ));
+        }
+    }
+    comments
+}
+
+pub async fn review_diff(
+    Extension(app): Extension<Application>,
+    Json(request): Json<ReviewDiffRequest>,
+) -> Result<impl IntoResponse> {
+    println!(
+        "webserver::review_diff::root_directory({})",
+        &request.root_directory
+    );
+    let diff = match (request.diff.as_ref(), request.ref_range.as_ref()) {
+        (Some(diff), _) => diff.to_owned(),
+        (None, Some(ref_range)) => diff_for_ref_range(&request.root_directory, ref_range).await?,
+        (None, None) => {
+            return Err(anyhow::anyhow!("one of diff or ref_range is required").into());
+        }
+    };
+    let llm_properties = request
+        .model_configuration
+        .llm_properties_for_slow_model()
+        .unwrap_or(LLMProperties::new(
+            LLMType::ClaudeSonnet,
+            LLMProvider::CodeStory(CodeStoryLLMTypes::new()),
+            LLMProviderAPIKeys::CodeStory(CodestoryAccessToken::new(
+                request.access_token.to_owned(),
+            )),
+        ));
+    let generation_profile = request
+        .model_configuration
+        .generation_profile_or(GenerationProfile::Fast);
+
+    let hunks = parse_unified_diff(&diff);
+    let skipped_hunks = hunks.len().saturating_sub(MAX_REVIEW_HUNKS);
+    let hunks = hunks
+        .into_iter()
+        .take(MAX_REVIEW_HUNKS)
+        .collect::<Vec<_>>();
+    let reviewed_hunks = hunks.len();
+
+    let mut hunks_with_symbols = vec![];
+    for hunk in hunks.into_iter() {
+        let symbol = symbol_for_hunk(&app, &request.root_directory, &hunk).await;
+        hunks_with_symbols.push((hunk, symbol));
+    }
+
+    let changed_files = hunks_with_symbols
+        .iter()
+        .map(|(hunk, _)| {
+            Path::new(&request.root_directory)
+                .join(hunk.fs_file_path())
+                .to_string_lossy()
+                .to_string()
+        })
+        .collect::<std::collections::HashSet<String>>()
+        .into_iter()
+        .collect::<Vec<_>>();
+
+    let mut comments = stream::iter(hunks_with_symbols.into_iter().map(|(hunk, symbol)| {
+        (
+            app.clone(),
+            llm_properties.clone(),
+            generation_profile.clone(),
+            hunk,
+            symbol,
+        )
+    }))
+    .map(|(app, llm_properties, generation_profile, hunk, symbol)| {
+        review_hunk(app, llm_properties, generation_profile, hunk, symbol)
+    })
+    .buffer_unordered(4)
+    .collect::<Vec<_>>()
+    .await
+    .into_iter()
+    .flatten()
+    .collect::<Vec<_>>();
+
+    if let Some(editor_url) = request.editor_url.as_ref() {
+        comments.extend(
+            diagnostics_comments(&app, editor_url, llm_properties.clone(), changed_files).await,
+        );
+    }
+
+    Ok(json(ReviewDiffResponse {
+        comments,
+        reviewed_hunks,
+        skipped_hunks,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_review_comments, parse_unified_diff, ReviewSeverity};
+
+    #[test]
+    fn test_parsing_unified_diff_into_hunks() {
+        let diff = r#"diff --git a/src/lib.rs b/src/lib.rs
+index 1111111..2222222 100644
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -1,3 +1,4 @@
+ fn main() {
++    println!("hello");
+ }
+@@ -10,2 +11,3 @@
+ fn helper() {
++    // noop
+ }
+"#;
+        let hunks = parse_unified_diff(diff);
+        assert_eq!(hunks.len(), 2);
+        assert_eq!(hunks[0].fs_file_path(), "src/lib.rs");
+        assert_eq!(hunks[0].new_start_line(), 1);
+        assert_eq!(hunks[0].new_line_count(), 3);
+        assert_eq!(hunks[1].new_start_line(), 11);
+    }
+
+    #[test]
+    fn test_deleted_files_produce_no_hunks() {
+        let diff = r#"--- a/src/old.rs
++++ /dev/null
+@@ -1,3 +0,0 @@
+-fn gone() {
+-}
+-
+"#;
+        assert!(parse_unified_diff(diff).is_empty());
+    }
+
+    #[test]
+    fn test_parsing_review_comments() {
+        let response = r#"<comment>
+<severity>blocker</severity>
+<line>42</line>
+<message>the lock is held across the await point</message>
+<suggested_patch>drop(guard);</suggested_patch>
+</comment>
+<comment>
+<message>missing severity defaults to nit</message>
+</comment>"#;
+        let comments = parse_review_comments(response, "src/lib.rs", Some("main"));
+        assert_eq!(comments.len(), 2);
+        assert_eq!(comments[0].severity, ReviewSeverity::Blocker);
+        assert_eq!(comments[0].line, Some(42));
+        assert_eq!(
+            comments[0].suggested_patch.as_deref(),
+            Some("drop(guard);")
+        );
+        assert_eq!(comments[0].symbol.as_deref(), Some("main"));
+        assert_eq!(comments[1].severity, ReviewSeverity::Nit);
+        assert_eq!(comments[1].line, None);
+    }
+
+    #[test]
+    fn test_empty_reply_produces_no_comments() {
+        assert!(parse_review_comments("", "src/lib.rs", None).is_empty());
+    }
+}
@@ -0,0 +1,122 @@
+//! Preview and decision endpoints for signature changes
+//!
+//! Before the editor applies an edit which changes a function signature it
+//! can ask for the blast radius: how many call sites live in which files,
+//! with sample snippets. The user then picks between updating every caller,
+//! adding an overload/adapter with the old signature, or cancelling the
+//! caller followups, and the decision gets recorded on the toolbox where
+//! the followup machinery consumes it.
+
+use axum::response::IntoResponse;
+use axum::{Extension, Json};
+use llm_client::clients::types::LLMType;
+use llm_client::provider::{
+    CodeStoryLLMTypes, CodestoryAccessToken, LLMProvider, LLMProviderAPIKeys,
+};
+
+use super::types::{json as json_result, ApiResponse, Result};
+use crate::agentic::symbol::events::input::SymbolEventRequestId;
+use crate::agentic::symbol::events::message_event::SymbolEventMessageProperties;
+use crate::agentic::symbol::identifier::LLMProperties;
+use crate::agentic::symbol::signature_change::{SignatureChangeAction, SignatureChangeImpact};
+use crate::application::application::Application;
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct SignatureChangePreviewRequest {
+    request_id: String,
+    editor_url: String,
+    access_token: String,
+    fs_file_path: String,
+    symbol_name: String,
+    original_code: String,
+    edited_code: String,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct SignatureChangePreviewResponse {
+    /// None when the edit does not touch any signature, applying it needs
+    /// no decision from the user
+    impact: Option<SignatureChangeImpact>,
+    /// the actions the editor can offer, echoed so the UI and the decision
+    /// endpoint stay in sync
+    available_actions: Vec<SignatureChangeAction>,
+}
+
+impl ApiResponse for SignatureChangePreviewResponse {}
+
+pub async fn signature_change_preview(
+    Extension(app): Extension<Application>,
+    Json(SignatureChangePreviewRequest {
+        request_id,
+        editor_url,
+        access_token,
+        fs_file_path,
+        symbol_name,
+        original_code,
+        edited_code,
+    }): Json<SignatureChangePreviewRequest>,
+) -> Result<impl IntoResponse> {
+    println!(
+        "webserver::signature_change_preview::file({})::symbol({})",
+        &fs_file_path, &symbol_name
+    );
+    let (sender, _receiver) = tokio::sync::mpsc::unbounded_channel();
+    let message_properties = SymbolEventMessageProperties::new(
+        SymbolEventRequestId::new(request_id.to_owned(), request_id.to_owned()),
+        sender,
+        editor_url,
+        tokio_util::sync::CancellationToken::new(),
+        LLMProperties::new(
+            LLMType::ClaudeSonnet,
+            LLMProvider::CodeStory(CodeStoryLLMTypes::new()),
+            LLMProviderAPIKeys::CodeStory(CodestoryAccessToken::new(access_token)),
+        ),
+    );
+    let impact = app
+        .tool_box
+        .signature_change_impact(
+            &fs_file_path,
+            &symbol_name,
+            &original_code,
+            &edited_code,
+            message_properties,
+        )
+        .await
+        .map_err(|e| super::types::Error::internal(format!("{:?}", e)))?;
+    Ok(json_result(SignatureChangePreviewResponse {
+        impact,
+        available_actions: vec![
+            SignatureChangeAction::UpdateAllCallers,
+            SignatureChangeAction::AddAdapter,
+            SignatureChangeAction::Cancel,
+        ],
+    }))
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct SignatureChangeDecisionRequest {
+    fs_file_path: String,
+    symbol_name: String,
+    action: SignatureChangeAction,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct SignatureChangeDecisionResponse {
+    done: bool,
+}
+
+impl ApiResponse for SignatureChangeDecisionResponse {}
+
+pub async fn signature_change_decision(
+    Extension(app): Extension<Application>,
+    Json(SignatureChangeDecisionRequest {
+        fs_file_path,
+        symbol_name,
+        action,
+    }): Json<SignatureChangeDecisionRequest>,
+) -> Result<impl IntoResponse> {
+    app.tool_box
+        .record_signature_change_decision(&fs_file_path, &symbol_name, action)
+        .await;
+    Ok(json_result(SignatureChangeDecisionResponse { done: true }))
+}
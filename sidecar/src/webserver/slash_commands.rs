@@ -0,0 +1,182 @@
+//! Server side slash-command registry for session chat. Commands are parsed
+//! out of the user query before we dispatch anything to the LLM and map onto
+//! subsystems we already have (planning, undo, model selection ...). The
+//! registry is also exposed over a discovery endpoint so editors can
+//! auto-complete the commands without hardcoding them.
+
+use axum::response::IntoResponse;
+
+use llm_client::clients::types::LLMType;
+
+use super::types::{json as json_result, ApiResponse, Result};
+
+/// The slash commands the server understands, parsed from the start of a
+/// chat query
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SlashCommand {
+    /// `/plan` switches the exchange over to the planning flow
+    Plan,
+    /// `/test` asks the agent to generate and run tests for the changes
+    Test,
+    /// `/diff` asks the agent to show the changes made in the session
+    Diff,
+    /// `/undo` rolls back the changes made in the last exchange
+    Undo,
+    /// `/model <name>` overrides the slow model for this exchange
+    Model(LLMType),
+    /// `/compact` compresses the conversation history for the session
+    Compact,
+}
+
+/// A slash command along with whatever came after it in the query
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParsedSlashCommand {
+    pub command: SlashCommand,
+    /// the rest of the query after the command (and its argument) was
+    /// stripped off
+    pub rest: String,
+}
+
+/// Parses a slash command from the start of a chat query. Returns None when
+/// the query does not start with a known command, in which case it flows to
+/// the LLM untouched
+pub fn parse_slash_command(query: &str) -> Option<ParsedSlashCommand> {
+    let trimmed = query.trim_start();
+    if !trimmed.starts_with('/') {
+        return None;
+    }
+    let mut parts = trimmed.splitn(2, char::is_whitespace);
+    let command_word = parts.next().expect("splitn to always yield once");
+    let rest = parts.next().unwrap_or("").trim();
+    match command_word {
+        "/plan" => Some(ParsedSlashCommand {
+            command: SlashCommand::Plan,
+            rest: rest.to_owned(),
+        }),
+        "/test" => Some(ParsedSlashCommand {
+            command: SlashCommand::Test,
+            rest: rest.to_owned(),
+        }),
+        "/diff" => Some(ParsedSlashCommand {
+            command: SlashCommand::Diff,
+            rest: rest.to_owned(),
+        }),
+        "/undo" => Some(ParsedSlashCommand {
+            command: SlashCommand::Undo,
+            rest: rest.to_owned(),
+        }),
+        "/model" => {
+            // the argument is the model name, anything after it is the query
+            let mut model_parts = rest.splitn(2, char::is_whitespace);
+            let model_name = model_parts.next().unwrap_or("");
+            if model_name.is_empty() {
+                return None;
+            }
+            let query_rest = model_parts.next().unwrap_or("").trim();
+            Some(ParsedSlashCommand {
+                command: SlashCommand::Model(parse_model_name(model_name)),
+                rest: query_rest.to_owned(),
+            })
+        }
+        "/compact" => Some(ParsedSlashCommand {
+            command: SlashCommand::Compact,
+            rest: rest.to_owned(),
+        }),
+        _ => None,
+    }
+}
+
+/// LLMType only deserializes through serde so we round-trip the name through
+/// a json string, unknown names become custom models
+fn parse_model_name(name: &str) -> LLMType {
+    serde_json::from_value(serde_json::Value::String(name.to_owned()))
+        .unwrap_or(LLMType::Custom(name.to_owned()))
+}
+
+/// Description of a slash command for editor side auto-completion
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct SlashCommandDescription {
+    name: String,
+    description: String,
+    takes_argument: bool,
+}
+
+impl SlashCommandDescription {
+    fn new(name: &str, description: &str, takes_argument: bool) -> Self {
+        Self {
+            name: name.to_owned(),
+            description: description.to_owned(),
+            takes_argument,
+        }
+    }
+}
+
+/// The registry of commands which gets served over the discovery endpoint,
+/// keep this in sync with `parse_slash_command`
+pub fn available_slash_commands() -> Vec<SlashCommandDescription> {
+    vec![
+        SlashCommandDescription::new("/plan", "Create a plan for the task before editing", false),
+        SlashCommandDescription::new(
+            "/test",
+            "Generate and run tests for the changes in this session",
+            false,
+        ),
+        SlashCommandDescription::new("/diff", "Show the changes made in this session", false),
+        SlashCommandDescription::new("/undo", "Undo the changes from the last exchange", false),
+        SlashCommandDescription::new(
+            "/model",
+            "Override the model used for this exchange, e.g. /model gpt-4o",
+            true,
+        ),
+        SlashCommandDescription::new(
+            "/compact",
+            "Compress the conversation history for this session",
+            false,
+        ),
+    ]
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct SlashCommandsResponse {
+    commands: Vec<SlashCommandDescription>,
+}
+
+impl ApiResponse for SlashCommandsResponse {}
+
+/// Discovery endpoint so editors can auto-complete the slash commands
+pub async fn list_slash_commands() -> Result<impl IntoResponse> {
+    Ok(json_result(SlashCommandsResponse {
+        commands: available_slash_commands(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_slash_command, SlashCommand};
+    use llm_client::clients::types::LLMType;
+
+    #[test]
+    fn test_plain_query_is_not_a_command() {
+        assert!(parse_slash_command("fix the bug in main.rs").is_none());
+        assert!(parse_slash_command("/unknown command").is_none());
+    }
+
+    #[test]
+    fn test_parses_command_with_rest() {
+        let parsed = parse_slash_command("/plan add retries to the fetch layer").expect("to parse");
+        assert_eq!(parsed.command, SlashCommand::Plan);
+        assert_eq!(parsed.rest, "add retries to the fetch layer");
+    }
+
+    #[test]
+    fn test_parses_model_with_argument() {
+        let parsed = parse_slash_command("/model gpt-4o now fix the bug").expect("to parse");
+        assert_eq!(parsed.command, SlashCommand::Model(LLMType::Gpt4O));
+        assert_eq!(parsed.rest, "now fix the bug");
+    }
+
+    #[test]
+    fn test_model_without_argument_is_not_a_command() {
+        assert!(parse_slash_command("/model").is_none());
+    }
+}
@@ -0,0 +1,29 @@
+//! Stats endpoint for LLM performance numbers
+//!
+//! GET /api/stats/llm returns time-to-first-token and throughput averages
+//! per model and provider, so users can compare how the models they
+//! configured actually perform for their workflows
+
+use axum::response::IntoResponse;
+use axum::Extension;
+
+use crate::application::application::Application;
+
+use super::types::json;
+use super::types::ApiResponse;
+use super::types::Result;
+
+/// Latency aggregates for every model/provider pair the broker has served
+/// requests for since the process started
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct LLMStatsResponse {
+    models: Vec<llm_client::metrics::ModelLatencyStats>,
+}
+
+impl ApiResponse for LLMStatsResponse {}
+
+pub async fn llm_stats(Extension(app): Extension<Application>) -> Result<impl IntoResponse> {
+    Ok(json(LLMStatsResponse {
+        models: app.llm_broker.latency_stats(),
+    }))
+}
@@ -0,0 +1,282 @@
+//! Low-priority suggestion channel for passive background analysis
+//!
+//! Background producers (diagnostics trends, dead code sweeps, missing
+//! tests for edited symbols) emit non-blocking suggestions here instead of
+//! interrupting an active session. The editor pulls the pending batch
+//! whenever it wants to surface them and each category can be switched off
+//! at runtime, a disabled category drops emissions on the floor
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use axum::response::IntoResponse;
+use axum::{Extension, Json};
+use tokio::sync::Mutex;
+
+use crate::application::application::Application;
+
+use super::types::json as json_result;
+use super::types::ApiResponse;
+use super::types::Result;
+
+/// pending suggestions the editor has not pulled yet, oldest entries get
+/// dropped beyond this so an ignoring editor never grows the queue unbounded
+const MAX_PENDING_SUGGESTIONS: usize = 100;
+/// consecutive growing diagnostic counts on a file before we call it a trend
+const DIAGNOSTIC_TREND_WINDOW: usize = 3;
+
+/// The kinds of background analysis which feed the channel
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SuggestionCategory {
+    /// diagnostics on a file keep growing across pushes from the editor
+    DiagnosticsTrend,
+    /// symbols nothing references anymore
+    DeadCode,
+    /// an edited symbol has no test exercising it
+    MissingTests,
+}
+
+/// A single passive suggestion, surfaced whenever the editor asks
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct Suggestion {
+    category: SuggestionCategory,
+    message: String,
+    /// the file the suggestion is about, when it is about one
+    fs_file_path: Option<String>,
+    recorded_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl Suggestion {
+    pub fn new(
+        category: SuggestionCategory,
+        message: String,
+        fs_file_path: Option<String>,
+    ) -> Self {
+        Self {
+            category,
+            message,
+            fs_file_path,
+            recorded_at: chrono::Utc::now(),
+        }
+    }
+
+    pub fn category(&self) -> SuggestionCategory {
+        self.category
+    }
+}
+
+#[derive(Default)]
+struct SuggestionChannelState {
+    pending: Vec<Suggestion>,
+    disabled_categories: HashSet<SuggestionCategory>,
+    /// recent diagnostic counts per file feeding the trend detection
+    diagnostic_counts: HashMap<String, Vec<usize>>,
+}
+
+/// The channel itself, shared across the webserver handlers through the
+/// application state so any background analysis can emit into it
+pub struct SuggestionChannel {
+    state: Arc<Mutex<SuggestionChannelState>>,
+}
+
+impl SuggestionChannel {
+    pub fn new() -> Self {
+        Self {
+            state: Arc::new(Mutex::new(SuggestionChannelState::default())),
+        }
+    }
+
+    /// Queues a suggestion for the editor to pull, emissions into a
+    /// disabled category get dropped and report false
+    pub async fn emit(&self, suggestion: Suggestion) -> bool {
+        let mut state = self.state.lock().await;
+        if state.disabled_categories.contains(&suggestion.category()) {
+            return false;
+        }
+        state.pending.push(suggestion);
+        if state.pending.len() > MAX_PENDING_SUGGESTIONS {
+            let overflow = state.pending.len() - MAX_PENDING_SUGGESTIONS;
+            state.pending.drain(0..overflow);
+        }
+        true
+    }
+
+    /// Hands the pending batch to the editor and clears it
+    pub async fn drain(&self) -> Vec<Suggestion> {
+        std::mem::take(&mut self.state.lock().await.pending)
+    }
+
+    /// Switches a category on or off, disabling also drops whatever that
+    /// category already queued since the user just said they do not care
+    pub async fn set_category_enabled(&self, category: SuggestionCategory, enabled: bool) {
+        let mut state = self.state.lock().await;
+        if enabled {
+            state.disabled_categories.remove(&category);
+        } else {
+            state.disabled_categories.insert(category);
+            state
+                .pending
+                .retain(|suggestion| suggestion.category() != category);
+        }
+    }
+
+    pub async fn disabled_categories(&self) -> Vec<SuggestionCategory> {
+        self.state
+            .lock()
+            .await
+            .disabled_categories
+            .iter()
+            .copied()
+            .collect()
+    }
+
+    /// Feeds one diagnostics push from the editor into the trend detection,
+    /// a file whose count keeps climbing gets a suggestion and its history
+    /// reset so the same trend does not fire on every push
+    pub async fn observe_diagnostics(&self, fs_file_path: &str, diagnostic_count: usize) {
+        let worsening = {
+            let mut state = self.state.lock().await;
+            let counts = state
+                .diagnostic_counts
+                .entry(fs_file_path.to_owned())
+                .or_default();
+            counts.push(diagnostic_count);
+            if counts.len() > DIAGNOSTIC_TREND_WINDOW {
+                counts.remove(0);
+            }
+            let worsening = is_worsening_trend(counts);
+            if worsening {
+                state.diagnostic_counts.remove(fs_file_path);
+            }
+            worsening
+        };
+        if worsening {
+            let _ = self
+                .emit(Suggestion::new(
+                    SuggestionCategory::DiagnosticsTrend,
+                    format!(
+                        "Diagnostics on {} have kept growing over the last {} checks, it might be worth a cleanup pass",
+                        fs_file_path, DIAGNOSTIC_TREND_WINDOW
+                    ),
+                    Some(fs_file_path.to_owned()),
+                ))
+                .await;
+        }
+    }
+}
+
+/// A full window of strictly growing counts is a trend, anything shorter or
+/// flatter is just noise
+fn is_worsening_trend(counts: &[usize]) -> bool {
+    counts.len() >= DIAGNOSTIC_TREND_WINDOW
+        && counts.windows(2).all(|window| window[1] > window[0])
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct SuggestionsListResponse {
+    suggestions: Vec<Suggestion>,
+}
+
+impl ApiResponse for SuggestionsListResponse {}
+
+/// Hands the pending suggestions to the editor, pulling clears the queue
+pub async fn list_suggestions(Extension(app): Extension<Application>) -> Result<impl IntoResponse> {
+    let suggestions = app.suggestion_channel.drain().await;
+    Ok(json_result(SuggestionsListResponse { suggestions }))
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct SuggestionSettingsRequest {
+    category: SuggestionCategory,
+    enabled: bool,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct SuggestionSettingsResponse {
+    disabled_categories: Vec<SuggestionCategory>,
+}
+
+impl ApiResponse for SuggestionSettingsResponse {}
+
+pub async fn update_suggestion_settings(
+    Extension(app): Extension<Application>,
+    Json(SuggestionSettingsRequest { category, enabled }): Json<SuggestionSettingsRequest>,
+) -> Result<impl IntoResponse> {
+    println!(
+        "webserver::update_suggestion_settings::category({:?})::enabled({})",
+        category, enabled
+    );
+    app.suggestion_channel
+        .set_category_enabled(category, enabled)
+        .await;
+    Ok(json_result(SuggestionSettingsResponse {
+        disabled_categories: app.suggestion_channel.disabled_categories().await,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Suggestion, SuggestionCategory, SuggestionChannel};
+
+    #[tokio::test]
+    async fn test_disabled_category_drops_emissions_and_pending() {
+        let channel = SuggestionChannel::new();
+        assert!(
+            channel
+                .emit(Suggestion::new(
+                    SuggestionCategory::DeadCode,
+                    "unused".to_owned(),
+                    None,
+                ))
+                .await
+        );
+        channel
+            .set_category_enabled(SuggestionCategory::DeadCode, false)
+            .await;
+        // disabling drops what the category already queued
+        assert!(channel.drain().await.is_empty());
+        assert!(
+            !channel
+                .emit(Suggestion::new(
+                    SuggestionCategory::DeadCode,
+                    "unused".to_owned(),
+                    None,
+                ))
+                .await
+        );
+        // other categories keep flowing
+        assert!(
+            channel
+                .emit(Suggestion::new(
+                    SuggestionCategory::MissingTests,
+                    "untested".to_owned(),
+                    None,
+                ))
+                .await
+        );
+        assert_eq!(channel.drain().await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_diagnostics_trend_needs_strict_growth() {
+        let channel = SuggestionChannel::new();
+        channel.observe_diagnostics("a.rs", 1).await;
+        channel.observe_diagnostics("a.rs", 1).await;
+        channel.observe_diagnostics("a.rs", 2).await;
+        // flat then growing is not a full window of growth
+        assert!(channel.drain().await.is_empty());
+        channel.observe_diagnostics("b.rs", 1).await;
+        channel.observe_diagnostics("b.rs", 2).await;
+        channel.observe_diagnostics("b.rs", 3).await;
+        let suggestions = channel.drain().await;
+        assert_eq!(suggestions.len(), 1);
+        assert_eq!(
+            suggestions[0].category(),
+            SuggestionCategory::DiagnosticsTrend
+        );
+        // the history resets after firing so the trend needs to build again
+        channel.observe_diagnostics("b.rs", 4).await;
+        assert!(channel.drain().await.is_empty());
+    }
+}
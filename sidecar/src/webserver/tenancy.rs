@@ -0,0 +1,100 @@
+//! Tenancy layer for a shared sidecar instance. One long-running sidecar can
+//! serve several editor windows or projects at once, so every piece of state
+//! which is keyed by a session id (session files on disk, scratch pads,
+//! plans, pinned context, running exchanges) gets namespaced by a client id
+//! taken from the request headers. Editors which do not send the header all
+//! land in the default tenant which keeps the single-window setup and any
+//! state already on disk working unchanged.
+
+use axum::extract::FromRequestParts;
+use axum::http::request::Parts;
+use std::convert::Infallible;
+
+/// The header the editor sends to identify which window/project a request
+/// belongs to
+pub const CLIENT_ID_HEADER: &str = "x-client-id";
+
+/// The tenant requests without a client id header end up in
+const DEFAULT_CLIENT_ID: &str = "default";
+
+/// Identifies the editor window or project a request belongs to, extracted
+/// from the request headers on the session endpoints
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientId(String);
+
+impl ClientId {
+    pub fn new(client_id: String) -> Self {
+        // the id ends up in directory names so keep it to a safe alphabet,
+        // anything else collapses to the default tenant
+        if client_id.is_empty()
+            || !client_id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return Self(DEFAULT_CLIENT_ID.to_owned());
+        }
+        Self(client_id)
+    }
+
+    pub fn default_client() -> Self {
+        Self(DEFAULT_CLIENT_ID.to_owned())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Namespaces an id (session id, plan id etc) with the client id. The
+    /// default tenant keeps the id as it is so existing on-disk sessions and
+    /// single-editor setups are not disturbed
+    pub fn scoped_id(&self, id: &str) -> String {
+        if self.0 == DEFAULT_CLIENT_ID {
+            id.to_owned()
+        } else {
+            format!("{}--{}", self.0, id)
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl<S> FromRequestParts<S> for ClientId
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let client_id = parts
+            .headers
+            .get(CLIENT_ID_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| ClientId::new(value.to_owned()))
+            .unwrap_or_else(ClientId::default_client);
+        Ok(client_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ClientId;
+
+    #[test]
+    fn test_default_tenant_keeps_ids_unchanged() {
+        let client_id = ClientId::default_client();
+        assert_eq!(client_id.scoped_id("session-1"), "session-1");
+    }
+
+    #[test]
+    fn test_named_tenants_namespace_their_ids() {
+        let client_id = ClientId::new("window-2".to_owned());
+        assert_eq!(client_id.scoped_id("session-1"), "window-2--session-1");
+    }
+
+    #[test]
+    fn test_unsafe_ids_collapse_to_the_default_tenant() {
+        let client_id = ClientId::new("../escape".to_owned());
+        assert_eq!(client_id, ClientId::default_client());
+        let client_id = ClientId::new("".to_owned());
+        assert_eq!(client_id, ClientId::default_client());
+    }
+}
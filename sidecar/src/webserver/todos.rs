@@ -0,0 +1,320 @@
+//! Harvests the TODO/FIXME/HACK comments of a workspace into something a
+//! maintainer can act on. The scan walks the repository, pulls the marker
+//! comments with their git blame age and clusters them by area, and when
+//! the editor sends back a selection we format it into a query for the
+//! existing plan generation flow — the "chip away at tech debt" loop
+
+use axum::response::IntoResponse;
+use axum::{Extension, Json};
+use ignore::WalkBuilder;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::types::{json, ApiResponse, Result};
+use crate::application::application::Application;
+
+/// caps the harvested items so a repository drowning in TODOs still gets a
+/// usable response
+const MAX_TODO_ITEMS: usize = 500;
+
+/// blaming every item one by one gets slow, only the first slice gets an age
+const MAX_ITEMS_TO_BLAME: usize = 100;
+
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct HarvestTodosRequest {
+    pub root_directory: String,
+    /// blame is a subprocess per item, editors can skip it for a fast scan
+    #[serde(default = "default_include_blame")]
+    pub include_blame: bool,
+    /// items the user picked to chip away at, when present the response
+    /// carries a query for the plan generation flow
+    #[serde(default)]
+    pub selected_items: Vec<SelectedTodoItem>,
+}
+
+fn default_include_blame() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct SelectedTodoItem {
+    pub fs_file_path: String,
+    pub line: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TodoKind {
+    Todo,
+    Fixme,
+    Hack,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct TodoComment {
+    pub fs_file_path: String,
+    /// 1-indexed line the comment sits on
+    pub line: usize,
+    pub kind: TodoKind,
+    /// the comment text after the marker
+    pub text: String,
+    /// how long the line has been unchanged as per git blame
+    pub age_days: Option<u64>,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct TodoCluster {
+    /// the area of the repository the items sit in, its top directories
+    pub area: String,
+    pub items: Vec<TodoComment>,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct HarvestTodosResponse {
+    clusters: Vec<TodoCluster>,
+    total_found: usize,
+    truncated: bool,
+    /// the seeded plan query when items were selected
+    plan_query: Option<String>,
+}
+
+impl ApiResponse for HarvestTodosResponse {}
+
+/// Pulls the marker and the text out of a line, markers hiding inside
+/// string literals will match too but blame age and review weed those out
+pub(crate) fn parse_todo_comment(line: &str) -> Option<(TodoKind, String)> {
+    for (marker, kind) in [
+        ("TODO", TodoKind::Todo),
+        ("FIXME", TodoKind::Fixme),
+        ("HACK", TodoKind::Hack),
+    ] {
+        let Some(index) = line.find(marker) else {
+            continue;
+        };
+        // require the marker to stand alone so `HACKathon` does not count
+        let after = &line[index + marker.len()..];
+        if after
+            .chars()
+            .next()
+            .map(|c| c.is_ascii_alphanumeric())
+            .unwrap_or(false)
+        {
+            continue;
+        }
+        let text = after.trim_start_matches([':', ' ']).trim().to_owned();
+        return Some((kind, text));
+    }
+    None
+}
+
+/// the cluster key for a file: its first two directory components relative
+/// to the root, enough to group by subsystem without one cluster per file
+pub(crate) fn cluster_area(root_directory: &str, fs_file_path: &str) -> String {
+    let relative = Path::new(fs_file_path)
+        .strip_prefix(root_directory)
+        .unwrap_or(Path::new(fs_file_path));
+    let components = relative
+        .parent()
+        .map(|parent| {
+            parent
+                .components()
+                .take(2)
+                .map(|component| component.as_os_str().to_string_lossy().to_string())
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    if components.is_empty() {
+        ".".to_owned()
+    } else {
+        components.join("/")
+    }
+}
+
+/// Formats the selected items into a query for the plan generation flow,
+/// one numbered entry per item with its location and age
+pub(crate) fn seed_plan_query(items: &[TodoComment]) -> String {
+    let entries = items
+        .iter()
+        .enumerate()
+        .map(|(index, item)| {
+            let age = item
+                .age_days
+                .map(|age_days| format!(" (unchanged for {} days)", age_days))
+                .unwrap_or_default();
+            format!(
+                "{}. {}:{} — {}{}",
+                index + 1,
+                item.fs_file_path,
+                item.line,
+                item.text,
+                age
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    format!(
+        r#"Work through the following tech debt items, fixing each one properly instead of deleting the comment:
+{}"#,
+        entries
+    )
+}
+
+/// the committer time of a single line as per git blame, None when the
+/// file is untracked or blame fails
+async fn blame_line_age_days(root_directory: &str, fs_file_path: &str, line: usize) -> Option<u64> {
+    let output = tokio::process::Command::new("git")
+        .args([
+            "blame",
+            "--line-porcelain",
+            "-L",
+            &format!("{},{}", line, line),
+            fs_file_path,
+        ])
+        .current_dir(root_directory)
+        .output()
+        .await
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    let committer_time = stdout
+        .lines()
+        .find(|line| line.starts_with("committer-time "))?
+        .split_whitespace()
+        .nth(1)?
+        .parse::<u64>()
+        .ok()?;
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_secs();
+    Some(now.saturating_sub(committer_time) / 86_400)
+}
+
+pub async fn harvest_todos(
+    Extension(_app): Extension<Application>,
+    Json(request): Json<HarvestTodosRequest>,
+) -> Result<impl IntoResponse> {
+    println!(
+        "webserver::harvest_todos::root_directory({})",
+        &request.root_directory
+    );
+    let mut items = vec![];
+    let mut truncated = false;
+    for entry in WalkBuilder::new(&request.root_directory).build().flatten() {
+        if !entry
+            .file_type()
+            .map(|file_type| file_type.is_file())
+            .unwrap_or(false)
+        {
+            continue;
+        }
+        let fs_file_path = entry.path().to_string_lossy().to_string();
+        let Ok(content) = tokio::fs::read_to_string(&fs_file_path).await else {
+            continue;
+        };
+        for (line_index, line) in content.lines().enumerate() {
+            if items.len() >= MAX_TODO_ITEMS {
+                truncated = true;
+                break;
+            }
+            if let Some((kind, text)) = parse_todo_comment(line) {
+                items.push(TodoComment {
+                    fs_file_path: fs_file_path.to_owned(),
+                    line: line_index + 1,
+                    kind,
+                    text,
+                    age_days: None,
+                });
+            }
+        }
+        if truncated {
+            break;
+        }
+    }
+    let total_found = items.len();
+
+    if request.include_blame {
+        for item in items.iter_mut().take(MAX_ITEMS_TO_BLAME) {
+            item.age_days =
+                blame_line_age_days(&request.root_directory, &item.fs_file_path, item.line).await;
+        }
+    }
+
+    let plan_query = if request.selected_items.is_empty() {
+        None
+    } else {
+        let selected = items
+            .iter()
+            .filter(|item| {
+                request.selected_items.iter().any(|selected| {
+                    selected.fs_file_path == item.fs_file_path && selected.line == item.line
+                })
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        (!selected.is_empty()).then(|| seed_plan_query(&selected))
+    };
+
+    // oldest first inside each cluster, those are the ones which fester
+    let mut clusters: Vec<TodoCluster> = vec![];
+    for item in items.into_iter() {
+        let area = cluster_area(&request.root_directory, &item.fs_file_path);
+        match clusters.iter_mut().find(|cluster| cluster.area == area) {
+            Some(cluster) => cluster.items.push(item),
+            None => clusters.push(TodoCluster {
+                area,
+                items: vec![item],
+            }),
+        }
+    }
+    for cluster in clusters.iter_mut() {
+        cluster
+            .items
+            .sort_by(|a, b| b.age_days.unwrap_or(0).cmp(&a.age_days.unwrap_or(0)));
+    }
+    clusters.sort_by(|a, b| b.items.len().cmp(&a.items.len()));
+
+    Ok(json(HarvestTodosResponse {
+        clusters,
+        total_found,
+        truncated,
+        plan_query,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{cluster_area, parse_todo_comment, seed_plan_query, TodoComment, TodoKind};
+
+    #[test]
+    fn test_parsing_markers_and_their_text() {
+        let (kind, text) = parse_todo_comment("// TODO(skcd): make this async").expect("todo");
+        assert_eq!(kind, TodoKind::Todo);
+        assert_eq!(text, "(skcd): make this async");
+        let (kind, _) = parse_todo_comment("# FIXME handle unicode").expect("fixme");
+        assert_eq!(kind, TodoKind::Fixme);
+        assert!(parse_todo_comment("// the HACKathon demo").is_none());
+        assert!(parse_todo_comment("let value = 42;").is_none());
+    }
+
+    #[test]
+    fn test_cluster_area_takes_the_top_directories() {
+        assert_eq!(
+            cluster_area("/repo", "/repo/src/agentic/tool/broker.rs"),
+            "src/agentic"
+        );
+        assert_eq!(cluster_area("/repo", "/repo/README.md"), ".");
+    }
+
+    #[test]
+    fn test_seeding_a_plan_query_numbers_the_items() {
+        let items = vec![TodoComment {
+            fs_file_path: "src/lib.rs".to_owned(),
+            line: 12,
+            kind: TodoKind::Fixme,
+            text: "handle the error".to_owned(),
+            age_days: Some(90),
+        }];
+        let query = seed_plan_query(&items);
+        assert!(query.contains("1. src/lib.rs:12 — handle the error (unchanged for 90 days)"));
+    }
+}
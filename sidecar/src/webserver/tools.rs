@@ -0,0 +1,364 @@
+//! Surface for the tool policy on the broker
+//!
+//! Lists which tools are active under the current policy and lets
+//! deployments swap the policy at runtime, a change through here applies
+//! to every invocation from then on without a restart
+
+use axum::response::{sse, IntoResponse, Sse};
+use axum::{Extension, Json};
+use futures::StreamExt;
+
+use super::types::{json, ApiResponse, Result};
+use crate::agentic::symbol::ui_event::UIEventWithID;
+use crate::agentic::tool::audit::{self, ReplayedRecord, ToolAuditLog};
+use crate::agentic::tool::mcp::health::McpServerStatus;
+use crate::agentic::tool::mcp::init::McpReloadSummary;
+use crate::agentic::tool::mcp::prompts::McpServerPrompts;
+use crate::agentic::tool::mcp::resources::{resource_context_path, McpServerResources};
+use crate::agentic::tool::model_overrides::ToolModelOverrides;
+use crate::agentic::tool::policy::ToolPolicy;
+use crate::agentic::tool::sensitive_files::is_sensitive_file;
+use crate::agentic::tool::r#type::ToolType;
+use crate::application::application::Application;
+use crate::webserver::pinned_context::PinnedContextItem;
+
+#[derive(Debug, serde::Serialize)]
+pub struct ToolsListResponse {
+    /// registered tools which the policy lets run
+    active_tools: Vec<ToolType>,
+    /// tools the policy has switched off
+    disabled_tools: Vec<ToolType>,
+}
+
+impl ApiResponse for ToolsListResponse {}
+
+pub async fn list_tools(Extension(app): Extension<Application>) -> Result<impl IntoResponse> {
+    let tool_broker = app.tool_box.tools();
+    let mut disabled_tools = tool_broker
+        .tool_policy()
+        .disabled_tools()
+        .iter()
+        .cloned()
+        .collect::<Vec<_>>();
+    disabled_tools.sort_by_key(|tool_type| tool_type.to_string());
+    Ok(json(ToolsListResponse {
+        active_tools: tool_broker.active_tools(),
+        disabled_tools,
+    }))
+}
+
+pub async fn update_tool_policy(
+    Extension(app): Extension<Application>,
+    Json(tool_policy): Json<ToolPolicy>,
+) -> Result<impl IntoResponse> {
+    println!("webserver::update_tool_policy");
+    let tool_broker = app.tool_box.tools();
+    tool_broker.set_tool_policy(tool_policy);
+    let mut disabled_tools = tool_broker
+        .tool_policy()
+        .disabled_tools()
+        .iter()
+        .cloned()
+        .collect::<Vec<_>>();
+    disabled_tools.sort_by_key(|tool_type| tool_type.to_string());
+    Ok(json(ToolsListResponse {
+        active_tools: tool_broker.active_tools(),
+        disabled_tools,
+    }))
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct ModelOverridesResponse {
+    /// the override map the broker consults on every invocation, tools
+    /// without an entry keep the model their request carries
+    model_overrides: ToolModelOverrides,
+}
+
+impl ApiResponse for ModelOverridesResponse {}
+
+pub async fn list_model_overrides(
+    Extension(app): Extension<Application>,
+) -> Result<impl IntoResponse> {
+    let tool_broker = app.tool_box.tools();
+    Ok(json(ModelOverridesResponse {
+        model_overrides: tool_broker.model_overrides(),
+    }))
+}
+
+pub async fn update_model_overrides(
+    Extension(app): Extension<Application>,
+    Json(model_overrides): Json<ToolModelOverrides>,
+) -> Result<impl IntoResponse> {
+    println!("webserver::update_model_overrides");
+    let tool_broker = app.tool_box.tools();
+    tool_broker.set_model_overrides(model_overrides);
+    Ok(json(ModelOverridesResponse {
+        model_overrides: tool_broker.model_overrides(),
+    }))
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct ReplayTrajectoryRequest {
+    /// the jsonl trajectory file an audited run produced
+    trajectory_path: String,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct ReplayTrajectoryResponse {
+    total_records: usize,
+    replayed: Vec<ReplayedRecord>,
+}
+
+impl ApiResponse for ReplayTrajectoryResponse {}
+
+pub async fn replay_trajectory(
+    Extension(app): Extension<Application>,
+    Json(ReplayTrajectoryRequest { trajectory_path }): Json<ReplayTrajectoryRequest>,
+) -> Result<impl IntoResponse> {
+    println!(
+        "webserver::replay_trajectory::trajectory_path({})",
+        &trajectory_path
+    );
+    let records = ToolAuditLog::load(std::path::Path::new(&trajectory_path)).await;
+    let total_records = records.len();
+    let tool_broker = app.tool_box.tools();
+    let replayed = audit::replay_trajectory(&tool_broker, records).await;
+    Ok(json(ReplayTrajectoryResponse {
+        total_records,
+        replayed,
+    }))
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct McpReloadResponse {
+    summary: McpReloadSummary,
+}
+
+impl ApiResponse for McpReloadResponse {}
+
+/// Re-reads ~/.aide/config.json and reconciles the running MCP servers
+/// against it, so adding or removing a server does not need a restart
+pub async fn reload_mcp(Extension(app): Extension<Application>) -> Result<impl IntoResponse> {
+    println!("webserver::reload_mcp");
+    let tool_broker = app.tool_box.tools();
+    let summary = tool_broker
+        .reload_mcp_servers()
+        .await
+        .map_err(|e| super::types::Error::internal(format!("{}", e)))?;
+    Ok(json(McpReloadResponse { summary }))
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct McpResourcesResponse {
+    servers: Vec<McpServerResources>,
+}
+
+impl ApiResponse for McpResourcesResponse {}
+
+/// Lists the resources every running MCP server exposes so the editor can
+/// offer a picker for attaching them as context
+pub async fn list_mcp_resources(
+    Extension(app): Extension<Application>,
+) -> Result<impl IntoResponse> {
+    let tool_broker = app.tool_box.tools();
+    Ok(json(McpResourcesResponse {
+        servers: tool_broker.list_mcp_resources().await,
+    }))
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct McpPromptsResponse {
+    servers: Vec<McpServerPrompts>,
+}
+
+impl ApiResponse for McpPromptsResponse {}
+
+/// Lists the prompt templates every running MCP server exposes so the
+/// editor can render them as slash commands next to the built-in ones
+pub async fn list_mcp_prompts(
+    Extension(app): Extension<Application>,
+) -> Result<impl IntoResponse> {
+    let tool_broker = app.tool_box.tools();
+    Ok(json(McpPromptsResponse {
+        servers: tool_broker.list_mcp_prompts().await,
+    }))
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct AttachMcpResourceRequest {
+    session_id: String,
+    server_name: String,
+    uri: String,
+    /// display name for the pinned item, the uri is used when missing
+    #[serde(default)]
+    name: Option<String>,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct AttachMcpResourceResponse {
+    done: bool,
+    /// the identity the resource got pinned under, unpinning uses it
+    pinned_as: String,
+}
+
+impl ApiResponse for AttachMcpResourceResponse {}
+
+/// Reads an MCP resource and pins the flattened contents into the session
+/// context, chat and plan generation pick it up like any other pinned item
+pub async fn attach_mcp_resource(
+    Extension(app): Extension<Application>,
+    Json(AttachMcpResourceRequest {
+        session_id,
+        server_name,
+        uri,
+        name,
+    }): Json<AttachMcpResourceRequest>,
+) -> Result<impl IntoResponse> {
+    println!(
+        "webserver::attach_mcp_resource::server({})::uri({})",
+        &server_name, &uri
+    );
+    let tool_broker = app.tool_box.tools();
+    let content = tool_broker
+        .read_mcp_resource(&server_name, &uri)
+        .await
+        .map_err(|e| super::types::Error::internal(format!("{}", e)))?;
+    let pinned_as = resource_context_path(&server_name, &uri);
+    app.pinned_context_tracker
+        .pin(
+            &session_id,
+            PinnedContextItem {
+                fs_file_path: pinned_as.to_owned(),
+                name: name.unwrap_or_else(|| uri.to_owned()),
+                item_type: "mcp_resource".to_owned(),
+                content: Some(content),
+            },
+        )
+        .await;
+    Ok(json(AttachMcpResourceResponse {
+        done: true,
+        pinned_as,
+    }))
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct DeterminismRequest {
+    enabled: bool,
+    /// the sampling seed for the run, only read when enabling
+    #[serde(default)]
+    seed: Option<u64>,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct DeterminismResponse {
+    enabled: bool,
+    seed: Option<u64>,
+}
+
+impl ApiResponse for DeterminismResponse {}
+
+/// Flips the reproducible mode: enabling pins the sampling seed on the
+/// LLM broker, serializes tool fan-out and records the seeded identifiers
+/// into the trajectory trace so the run replays bit-for-bit
+pub async fn set_determinism(
+    Extension(app): Extension<Application>,
+    Json(DeterminismRequest { enabled, seed }): Json<DeterminismRequest>,
+) -> Result<impl IntoResponse> {
+    let tool_broker = app.tool_box.tools();
+    if enabled {
+        tool_broker.enable_deterministic_mode(seed.unwrap_or(0));
+    } else {
+        tool_broker.disable_deterministic_mode();
+    }
+    let determinism = tool_broker.determinism();
+    Ok(json(DeterminismResponse {
+        enabled: determinism.is_enabled(),
+        seed: determinism.seed(),
+    }))
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct SensitiveFileConfirmRequest {
+    fs_file_path: String,
+    /// true allows edits to the file, false withdraws an earlier
+    /// confirmation
+    confirmed: bool,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct SensitiveFileConfirmResponse {
+    fs_file_path: String,
+    confirmed: bool,
+    /// whether the safeguards apply to the path at all, confirming a
+    /// regular file is harmless but points at an editor bug
+    sensitive: bool,
+}
+
+impl ApiResponse for SensitiveFileConfirmResponse {}
+
+/// Records the user decision on editing a configuration or secret file,
+/// the broker middleware blocks those edits until this arrives
+pub async fn confirm_sensitive_file_edit(
+    Extension(app): Extension<Application>,
+    Json(SensitiveFileConfirmRequest {
+        fs_file_path,
+        confirmed,
+    }): Json<SensitiveFileConfirmRequest>,
+) -> Result<impl IntoResponse> {
+    println!(
+        "webserver::confirm_sensitive_file_edit::path({})::confirmed({})",
+        &fs_file_path, confirmed
+    );
+    if confirmed {
+        app.sensitive_file_guard.confirm(&fs_file_path);
+    } else {
+        app.sensitive_file_guard.revoke(&fs_file_path);
+    }
+    Ok(json(SensitiveFileConfirmResponse {
+        sensitive: is_sensitive_file(&fs_file_path),
+        fs_file_path,
+        confirmed,
+    }))
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct McpHealthResponse {
+    servers: std::collections::HashMap<String, McpServerStatus>,
+}
+
+impl ApiResponse for McpHealthResponse {}
+
+/// Snapshot of the last health observation per MCP server
+pub async fn mcp_health(Extension(app): Extension<Application>) -> Result<impl IntoResponse> {
+    let tool_broker = app.tool_box.tools();
+    Ok(json(McpHealthResponse {
+        servers: tool_broker.mcp_server_statuses(),
+    }))
+}
+
+/// Streams MCP server status transitions as UI events so the editor can
+/// tell the user a server went down or came back
+pub async fn mcp_health_events(
+    Extension(app): Extension<Application>,
+) -> Result<impl IntoResponse> {
+    let mut receiver = app.tool_box.tools().subscribe_mcp_status();
+    let (sender, forward_receiver) = tokio::sync::mpsc::unbounded_channel();
+    // bridge the broadcast subscription into a stream the sse layer can
+    // poll, a lagging editor just drops the connection and resubscribes
+    tokio::spawn(async move {
+        while let Ok(event) = receiver.recv().await {
+            if sender.send(event).is_err() {
+                break;
+            }
+        }
+    });
+    let stream = tokio_stream::wrappers::UnboundedReceiverStream::new(forward_receiver).map(
+        |event| {
+            sse::Event::default().json_data(UIEventWithID::mcp_server_status(
+                "mcp_health".to_owned(),
+                event,
+            ))
+        },
+    );
+    Ok(Sse::new(Box::pin(stream)))
+}
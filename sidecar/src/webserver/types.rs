@@ -48,6 +48,31 @@ impl Error {
             body: EndpointError {
                 kind: ErrorKind::Internal,
                 message: message.to_string().into(),
+                code: None,
+                remediation: None,
+            },
+        }
+    }
+
+    /// Builds the error response from a classified error, the category
+    /// picks the status code and the error kind the response reports
+    pub fn structured(structured_error: crate::errors::StructuredError) -> Self {
+        use crate::errors::ErrorCategory;
+        let (status, kind) = match structured_error.category() {
+            ErrorCategory::Configuration => (StatusCode::INTERNAL_SERVER_ERROR, ErrorKind::Configuration),
+            ErrorCategory::Provider => (StatusCode::BAD_GATEWAY, ErrorKind::UpstreamService),
+            ErrorCategory::EditorConnection => (StatusCode::BAD_GATEWAY, ErrorKind::UpstreamService),
+            ErrorCategory::Parsing => (StatusCode::INTERNAL_SERVER_ERROR, ErrorKind::Internal),
+            ErrorCategory::Policy => (StatusCode::FORBIDDEN, ErrorKind::User),
+            ErrorCategory::Internal => (StatusCode::INTERNAL_SERVER_ERROR, ErrorKind::Internal),
+        };
+        Error {
+            status,
+            body: EndpointError {
+                kind,
+                message: structured_error.message().to_owned().into(),
+                code: Some(structured_error.code()),
+                remediation: structured_error.remediation(),
             },
         }
     }
@@ -67,6 +92,15 @@ pub struct EndpointError<'a> {
 
     /// A context aware message describing the error
     message: Cow<'a, str>,
+
+    /// Stable machine-readable code from the error taxonomy, editors key
+    /// their remediation UI off this instead of parsing the message
+    #[serde(skip_serializing_if = "Option::is_none")]
+    code: Option<&'static str>,
+
+    /// What the user can do about the error, when anything
+    #[serde(skip_serializing_if = "Option::is_none")]
+    remediation: Option<&'static str>,
 }
 
 impl<'a> From<EndpointError<'a>> for Response<'a> {
@@ -0,0 +1,296 @@
+//! Protocol version negotiation and self-update staging
+//!
+//! The editor and sidecar ship separately so their protocol versions
+//! drift. The handshake endpoint lets the editor declare what it speaks
+//! before using any other route: compatible versions negotiate down to
+//! the highest both sides understand, incompatible ones get refused with
+//! a message saying which side needs the upgrade. The self-update checker
+//! compares the running binary against a release manifest and stages a
+//! newer binary on disk for the editor to swap in, sidecar never replaces
+//! itself while running
+
+use axum::response::IntoResponse;
+use axum::{Extension, Json};
+
+use super::types::{json as json_result, ApiResponse, Error, Result};
+use crate::application::application::Application;
+use crate::state::BINARY_VERSION_HASH;
+
+/// The newest protocol version this sidecar speaks
+pub const PROTOCOL_VERSION: u32 = 2;
+/// The oldest protocol version this sidecar still serves, editors below
+/// this are refused
+pub const MIN_SUPPORTED_PROTOCOL_VERSION: u32 = 1;
+
+/// Where the self-update checker looks for the latest release when the
+/// request does not override it
+const DEFAULT_RELEASE_MANIFEST_URL: &str = "https://update.codestory.ai/sidecar/latest.json";
+
+/// What the handshake decided for one editor
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NegotiationOutcome {
+    /// both sides can talk, at the contained version
+    Compatible {
+        negotiated_version: u32,
+        /// set when the editor speaks a newer protocol than us, it keeps
+        /// working but should suggest a sidecar upgrade
+        sidecar_upgrade_available: bool,
+    },
+    /// the editor is too old to serve at all
+    Incompatible { message: String },
+}
+
+/// Negotiates the protocol version with an editor. Versions inside our
+/// supported window settle on the editor's version, newer editors settle
+/// on ours, older ones are refused
+pub fn negotiate_protocol_version(editor_protocol_version: u32) -> NegotiationOutcome {
+    if editor_protocol_version < MIN_SUPPORTED_PROTOCOL_VERSION {
+        return NegotiationOutcome::Incompatible {
+            message: format!(
+                "The editor speaks protocol version {} but this sidecar supports {} through {}. Upgrade the editor to keep using sidecar.",
+                editor_protocol_version, MIN_SUPPORTED_PROTOCOL_VERSION, PROTOCOL_VERSION
+            ),
+        };
+    }
+    NegotiationOutcome::Compatible {
+        negotiated_version: editor_protocol_version.min(PROTOCOL_VERSION),
+        sidecar_upgrade_available: editor_protocol_version > PROTOCOL_VERSION,
+    }
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct VersionHandshakeRequest {
+    /// the protocol version the editor speaks
+    protocol_version: u32,
+    /// the editor's own version string, only logged
+    #[serde(default)]
+    editor_version: Option<String>,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct VersionHandshakeResponse {
+    compatible: bool,
+    /// the protocol version both sides talk from here on, missing when
+    /// incompatible
+    #[serde(skip_serializing_if = "Option::is_none")]
+    negotiated_protocol_version: Option<u32>,
+    /// set when the editor is ahead of us and a sidecar upgrade would
+    /// unlock its newer protocol
+    sidecar_upgrade_available: bool,
+    package_version: String,
+    version_hash: String,
+    /// the upgrade message when incompatible
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message: Option<String>,
+}
+
+impl ApiResponse for VersionHandshakeResponse {}
+
+/// The editor declares its protocol version and learns whether this
+/// sidecar can serve it and at which version
+pub async fn version_handshake(
+    Json(VersionHandshakeRequest {
+        protocol_version,
+        editor_version,
+    }): Json<VersionHandshakeRequest>,
+) -> Result<impl IntoResponse> {
+    println!(
+        "webserver::version_handshake::protocol({})::editor({})",
+        protocol_version,
+        editor_version.as_deref().unwrap_or("unknown")
+    );
+    let response = match negotiate_protocol_version(protocol_version) {
+        NegotiationOutcome::Compatible {
+            negotiated_version,
+            sidecar_upgrade_available,
+        } => VersionHandshakeResponse {
+            compatible: true,
+            negotiated_protocol_version: Some(negotiated_version),
+            sidecar_upgrade_available,
+            package_version: env!("CARGO_PKG_VERSION").to_owned(),
+            version_hash: BINARY_VERSION_HASH.to_owned(),
+            message: None,
+        },
+        NegotiationOutcome::Incompatible { message } => VersionHandshakeResponse {
+            compatible: false,
+            negotiated_protocol_version: None,
+            sidecar_upgrade_available: false,
+            package_version: env!("CARGO_PKG_VERSION").to_owned(),
+            version_hash: BINARY_VERSION_HASH.to_owned(),
+            message: Some(message),
+        },
+    };
+    Ok(json_result(response))
+}
+
+/// The release manifest the update endpoint serves, binary urls are keyed
+/// by `os-arch` so one manifest covers every platform
+#[derive(Debug, serde::Deserialize)]
+struct ReleaseManifest {
+    version: String,
+    urls: std::collections::HashMap<String, String>,
+}
+
+/// The `os-arch` key the running binary looks itself up under in the
+/// release manifest
+fn platform_key() -> String {
+    format!("{}-{}", std::env::consts::OS, std::env::consts::ARCH)
+}
+
+/// Compares dotted numeric versions, true when candidate is strictly
+/// newer than current. Non-numeric segments compare as 0 so a malformed
+/// manifest never triggers a spurious update
+pub fn is_newer_version(candidate: &str, current: &str) -> bool {
+    let parse = |version: &str| -> Vec<u64> {
+        version
+            .split('.')
+            .map(|segment| segment.trim().parse::<u64>().unwrap_or(0))
+            .collect()
+    };
+    let candidate_parts = parse(candidate);
+    let current_parts = parse(current);
+    let segments = candidate_parts.len().max(current_parts.len());
+    for index in 0..segments {
+        let candidate_segment = candidate_parts.get(index).copied().unwrap_or(0);
+        let current_segment = current_parts.get(index).copied().unwrap_or(0);
+        if candidate_segment != current_segment {
+            return candidate_segment > current_segment;
+        }
+    }
+    false
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct SelfUpdateCheckRequest {
+    /// override for the release manifest location, tests and self-hosted
+    /// deployments point this at their own endpoint
+    #[serde(default)]
+    release_manifest_url: Option<String>,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct SelfUpdateCheckResponse {
+    update_available: bool,
+    current_version: String,
+    /// the version the manifest advertises, missing when the manifest did
+    /// not resolve
+    #[serde(skip_serializing_if = "Option::is_none")]
+    latest_version: Option<String>,
+    /// where the downloaded binary is staged, the editor swaps it in and
+    /// restarts sidecar
+    #[serde(skip_serializing_if = "Option::is_none")]
+    staged_binary_path: Option<String>,
+}
+
+impl ApiResponse for SelfUpdateCheckResponse {}
+
+/// Checks the release manifest for a newer sidecar and stages the binary
+/// under the index directory, swapping it in is the editor's job
+pub async fn check_self_update(
+    Extension(app): Extension<Application>,
+    Json(SelfUpdateCheckRequest {
+        release_manifest_url,
+    }): Json<SelfUpdateCheckRequest>,
+) -> Result<impl IntoResponse> {
+    let manifest_url =
+        release_manifest_url.unwrap_or_else(|| DEFAULT_RELEASE_MANIFEST_URL.to_owned());
+    println!("webserver::check_self_update::manifest({})", &manifest_url);
+    let current_version = env!("CARGO_PKG_VERSION").to_owned();
+    let manifest: ReleaseManifest = reqwest::Client::new()
+        .get(&manifest_url)
+        .send()
+        .await
+        .map_err(|e| Error::internal(format!("failed fetching release manifest: {}", e)))?
+        .json()
+        .await
+        .map_err(|e| Error::internal(format!("failed parsing release manifest: {}", e)))?;
+    if !is_newer_version(&manifest.version, &current_version) {
+        return Ok(json_result(SelfUpdateCheckResponse {
+            update_available: false,
+            current_version,
+            latest_version: Some(manifest.version),
+            staged_binary_path: None,
+        }));
+    }
+    let platform = platform_key();
+    let binary_url = manifest.urls.get(&platform).ok_or_else(|| {
+        Error::internal(format!(
+            "release {} has no binary for platform {}",
+            manifest.version, platform
+        ))
+    })?;
+    let binary_bytes = reqwest::Client::new()
+        .get(binary_url)
+        .send()
+        .await
+        .map_err(|e| Error::internal(format!("failed downloading sidecar binary: {}", e)))?
+        .bytes()
+        .await
+        .map_err(|e| Error::internal(format!("failed downloading sidecar binary: {}", e)))?;
+    let staging_dir = app.config.index_dir.join("self_update");
+    tokio::fs::create_dir_all(&staging_dir)
+        .await
+        .map_err(|e| Error::internal(format!("failed creating staging directory: {}", e)))?;
+    let staged_path = staging_dir.join(format!("sidecar-{}", manifest.version));
+    tokio::fs::write(&staged_path, &binary_bytes)
+        .await
+        .map_err(|e| Error::internal(format!("failed staging sidecar binary: {}", e)))?;
+    println!(
+        "webserver::check_self_update::staged({})",
+        staged_path.to_string_lossy()
+    );
+    Ok(json_result(SelfUpdateCheckResponse {
+        update_available: true,
+        current_version,
+        latest_version: Some(manifest.version),
+        staged_binary_path: Some(staged_path.to_string_lossy().to_string()),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        is_newer_version, negotiate_protocol_version, NegotiationOutcome,
+        MIN_SUPPORTED_PROTOCOL_VERSION, PROTOCOL_VERSION,
+    };
+
+    #[test]
+    fn test_supported_version_negotiates_to_the_editor_version() {
+        assert_eq!(
+            negotiate_protocol_version(MIN_SUPPORTED_PROTOCOL_VERSION),
+            NegotiationOutcome::Compatible {
+                negotiated_version: MIN_SUPPORTED_PROTOCOL_VERSION,
+                sidecar_upgrade_available: false,
+            }
+        );
+    }
+
+    #[test]
+    fn test_newer_editor_negotiates_down_with_upgrade_hint() {
+        assert_eq!(
+            negotiate_protocol_version(PROTOCOL_VERSION + 5),
+            NegotiationOutcome::Compatible {
+                negotiated_version: PROTOCOL_VERSION,
+                sidecar_upgrade_available: true,
+            }
+        );
+    }
+
+    #[test]
+    fn test_too_old_editor_is_refused_with_upgrade_message() {
+        match negotiate_protocol_version(MIN_SUPPORTED_PROTOCOL_VERSION - 1) {
+            NegotiationOutcome::Incompatible { message } => {
+                assert!(message.contains("Upgrade the editor"));
+            }
+            other => panic!("expected an incompatible outcome, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_version_comparison_handles_length_and_garbage() {
+        assert!(is_newer_version("1.2.10", "1.2.9"));
+        assert!(is_newer_version("1.3", "1.2.9"));
+        assert!(!is_newer_version("1.2.9", "1.2.9"));
+        assert!(!is_newer_version("not-a-version", "1.0.0"));
+    }
+}
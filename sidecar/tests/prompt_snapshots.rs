@@ -0,0 +1,116 @@
+//! Snapshot tests over the prompt formatters
+//!
+//! Prompt construction is spread across a handful of formatters and a
+//! refactor can silently change what we send to the model. These tests
+//! render each prompt against fixture inputs and snapshot the output so
+//! any change to the wire format shows up as a reviewable diff.
+
+use std::sync::Arc;
+
+use llm_client::broker::LLMBroker;
+use llm_client::clients::types::{LLMClientCompletionRequest, LLMType};
+use llm_client::provider::{AnthropicAPIKey, LLMProvider, LLMProviderAPIKeys};
+use sidecar::agentic::symbol::identifier::{LLMProperties, SymbolIdentifier};
+use sidecar::agentic::tool::code_edit::models::anthropic::AnthropicCodeEditFromatter;
+use sidecar::agentic::tool::code_edit::models::broker::CodeEditPromptFormatters;
+use sidecar::agentic::tool::code_edit::types::CodeEdit;
+use sidecar::agentic::tool::code_symbol::models::anthropic::AnthropicCodeSymbolImportant;
+use sidecar::agentic::tool::plan::generator::{StepGeneratorClient, StepGeneratorRequest};
+use sidecar::chunking::text_document::{Position, Range};
+
+fn fixture_llm_properties() -> LLMProperties {
+    LLMProperties::new(
+        LLMType::ClaudeSonnet,
+        LLMProvider::Anthropic,
+        LLMProviderAPIKeys::Anthropic(AnthropicAPIKey::new("".to_owned())),
+    )
+}
+
+/// Renders a completion request to a stable textual form so the whole
+/// message list (roles included) is covered by one snapshot
+fn render_request(request: &LLMClientCompletionRequest) -> String {
+    request
+        .messages()
+        .iter()
+        .map(|message| {
+            format!(
+                "== {} ==\n{}",
+                message.role().to_string(),
+                message.content()
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+#[test]
+fn test_code_edit_prompt() {
+    let (ui_sender, _ui_receiver) = tokio::sync::mpsc::unbounded_channel();
+    let code_edit = CodeEdit::new(
+        Some("fn add(a: i32, b: i32) -> i32 {\n    a + b\n}".to_owned()),
+        Some("fn divide(a: i32, b: i32) -> i32 {\n    a / b\n}".to_owned()),
+        "src/maths.rs".to_owned(),
+        "fn multiply(a: i32, b: i32) -> i32 {\n    a + b\n}".to_owned(),
+        "".to_owned(),
+        "rust".to_owned(),
+        "fix the multiply implementation, it adds instead of multiplying".to_owned(),
+        fixture_llm_properties(),
+        false,
+        Some("multiply".to_owned()),
+        None,
+        "test-root-id".to_owned(),
+        Range::new(Position::new(4, 0, 0), Position::new(6, 1, 0)),
+        false,
+        None,
+        false,
+        SymbolIdentifier::with_file_path("multiply", "src/maths.rs"),
+        ui_sender,
+        false,
+        None,
+        "test-session-id".to_owned(),
+        "test-exchange-id".to_owned(),
+    );
+    let request = AnthropicCodeEditFromatter::new().format_prompt(&code_edit);
+    insta::assert_snapshot!("code_edit_prompt", render_request(&request));
+}
+
+#[test]
+fn test_plan_step_generator_system_message() {
+    let (ui_sender, _ui_receiver) = tokio::sync::mpsc::unbounded_channel();
+    let request = StepGeneratorRequest::new(
+        "add pagination support to the users endpoint".to_owned(),
+        vec![],
+        false,
+        vec![],
+        "test-root-id".to_owned(),
+        "http://localhost:42427".to_owned(),
+        "test-exchange-id".to_owned(),
+        ui_sender,
+        None,
+        None,
+        tokio_util::sync::CancellationToken::new(),
+        fixture_llm_properties(),
+    );
+    insta::assert_snapshot!(
+        "plan_step_generator_system_message",
+        StepGeneratorClient::system_message(&request)
+    );
+}
+
+#[tokio::test]
+async fn test_code_symbol_prompt_builders() {
+    let llm_broker = Arc::new(LLMBroker::new().await.expect("llm broker to construct"));
+    let formatter = AnthropicCodeSymbolImportant::new(llm_broker, fixture_llm_properties());
+    insta::assert_snapshot!(
+        "correctness_check_system_message",
+        formatter.system_message_for_correctness_check()
+    );
+    insta::assert_snapshot!(
+        "probe_next_symbol_system_message",
+        formatter.system_message_for_probe_next_symbol()
+    );
+    insta::assert_snapshot!(
+        "class_symbol_followup_system_message",
+        formatter.system_message_for_class_symbol()
+    );
+}
@@ -0,0 +1,113 @@
+//! Property tests over Range/Position arithmetic
+//!
+//! Range intersection, minimal_line_distance and split_file_content_into_parts
+//! have subtle edge cases (empty files, zero-width ranges, CRLF) which are easy
+//! to regress with hand-picked examples, so we let proptest generate the inputs
+//! and assert the invariants instead.
+
+use proptest::prelude::*;
+use sidecar::agentic::symbol::helpers::split_file_content_into_parts;
+use sidecar::chunking::text_document::{Position, Range};
+
+fn line_range(start_line: usize, end_line: usize) -> Range {
+    Range::new(Position::new(start_line, 0, 0), Position::new(end_line, 0, 0))
+}
+
+/// Generates a well-formed line range (start <= end), zero-width included
+fn arbitrary_range() -> impl Strategy<Value = Range> {
+    (0usize..200, 0usize..50).prop_map(|(start_line, len)| line_range(start_line, start_line + len))
+}
+
+/// Generates file content from plain lines joined with either LF or CRLF
+fn arbitrary_file_content() -> impl Strategy<Value = String> {
+    (
+        prop::collection::vec("[a-z ]{0,12}", 1..20),
+        prop::bool::ANY,
+    )
+        .prop_map(|(lines, crlf)| lines.join(if crlf { "\r\n" } else { "\n" }))
+}
+
+proptest! {
+    #[test]
+    fn intersection_is_symmetric(first in arbitrary_range(), second in arbitrary_range()) {
+        prop_assert_eq!(
+            first.intersects_with_another_range(&second),
+            second.intersects_with_another_range(&first)
+        );
+        prop_assert_eq!(
+            first.intersects_without_byte(&second),
+            second.intersects_without_byte(&first)
+        );
+    }
+
+    #[test]
+    fn intersection_checks_agree_with_each_other(
+        first in arbitrary_range(),
+        second in arbitrary_range(),
+    ) {
+        // both formulations should reduce to the canonical interval overlap
+        let canonical = first.start_line() <= second.end_line()
+            && second.start_line() <= first.end_line();
+        prop_assert_eq!(first.intersects_with_another_range(&second), canonical);
+        prop_assert_eq!(first.intersects_without_byte(&second), canonical);
+    }
+
+    #[test]
+    fn minimal_line_distance_is_zero_exactly_on_overlap(
+        first in arbitrary_range(),
+        second in arbitrary_range(),
+    ) {
+        let distance = first.minimal_line_distance(&second);
+        prop_assert_eq!(distance, second.minimal_line_distance(&first));
+        prop_assert!(distance >= 0);
+        if first.intersects_without_byte(&second) {
+            prop_assert_eq!(distance, 0);
+        } else {
+            // disjoint ranges are separated by the gap between the closest endpoints
+            let gap = if first.end_line() < second.start_line() {
+                second.start_line() - first.end_line()
+            } else {
+                first.start_line() - second.end_line()
+            };
+            prop_assert_eq!(distance, gap as i64);
+        }
+    }
+
+    #[test]
+    fn split_file_content_reassembles_to_the_original_lines(
+        file_content in arbitrary_file_content(),
+        start_offset in 0usize..25,
+        len in 0usize..25,
+    ) {
+        // the selection has to start within the file, ranges come from the
+        // editor or tree-sitter so they always do
+        let start_line = start_offset % file_content.lines().count().max(1);
+        let selection_range = line_range(start_line, start_line + len);
+        let (above, below, in_selection) =
+            split_file_content_into_parts(&file_content, &selection_range);
+        let mut parts = vec![];
+        if let Some(above) = above.clone() {
+            parts.push(above);
+        }
+        parts.push(in_selection);
+        if let Some(below) = below.clone() {
+            parts.push(below);
+        }
+        // lines() normalizes CRLF away, so we compare against the same view
+        let expected = file_content.lines().collect::<Vec<_>>().join("\n");
+        prop_assert_eq!(parts.join("\n"), expected);
+        prop_assert_eq!(above.is_none(), start_line == 0);
+        prop_assert_eq!(
+            below.is_none(),
+            start_line + len + 1 >= file_content.lines().count()
+        );
+    }
+}
+
+#[test]
+fn split_file_content_handles_an_empty_file() {
+    let (above, below, in_selection) = split_file_content_into_parts("", &line_range(0, 0));
+    assert!(above.is_none());
+    assert!(below.is_none());
+    assert_eq!(in_selection, "");
+}